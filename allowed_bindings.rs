@@ -23,9 +23,12 @@ bind! {
     HashTable,
     _Bucket,
     _call_user_function_impl,
+    _ecalloc,
     _efree,
     _emalloc,
+    _erealloc,
     _estrdup,
+    _estrndup,
     _zend_executor_globals,
     _zend_compiler_globals,
     _sapi_globals_struct,
@@ -47,6 +50,8 @@ bind! {
     // ext_php_rs_php_build_id,
     // ext_php_rs_zend_object_alloc,
     // ext_php_rs_zend_object_release,
+    // ext_php_rs_pemalloc,
+    // ext_php_rs_pefree,
     // ext_php_rs_zend_string_init,
     // ext_php_rs_zend_string_release,
     // ext_php_rs_is_known_valid_utf8,
@@ -59,14 +64,25 @@ bind! {
     php_info_print_table_start,
     std_object_handlers,
     zend_array_destroy,
+    zend_autoload,
     zend_array_dup,
     zend_call_known_function,
     zend_fetch_function_str,
     zend_hash_str_find_ptr_lc,
+    zend_memory_usage,
+    zend_memory_peak_usage,
+    zend_memory_reset_peak_usage,
+    zend_mm_heap,
+    zend_mm_get_heap,
+    zend_mm_set_heap,
+    zend_mm_is_custom_heap,
+    zend_mm_get_custom_handlers,
+    zend_mm_set_custom_handlers,
     zend_ce_argument_count_error,
     zend_ce_arithmetic_error,
     zend_ce_compile_error,
     zend_ce_division_by_zero_error,
+    zend_ce_error,
     zend_ce_error_exception,
     zend_ce_exception,
     zend_ce_parse_error,
@@ -81,6 +97,19 @@ bind! {
     zend_ce_serializable,
     zend_ce_countable,
     zend_ce_stringable,
+    spl_ce_LogicException,
+    spl_ce_BadFunctionCallException,
+    spl_ce_BadMethodCallException,
+    spl_ce_DomainException,
+    spl_ce_InvalidArgumentException,
+    spl_ce_LengthException,
+    spl_ce_OutOfRangeException,
+    spl_ce_RuntimeException,
+    spl_ce_OutOfBoundsException,
+    spl_ce_OverflowException,
+    spl_ce_RangeException,
+    spl_ce_UnderflowException,
+    spl_ce_UnexpectedValueException,
     zend_class_entry,
     zend_declare_class_constant,
     zend_declare_property,
@@ -119,9 +148,32 @@ bind! {
     zend_resource,
     zend_string,
     zend_string_init_interned,
+    zend_new_interned_string,
     zend_throw_exception_ex,
+    zend_throw_exception_hook,
+    zend_update_property,
     zend_throw_exception_object,
     zend_observer_error_register,
+    zend_observer_fcall_begin_handler,
+    zend_observer_fcall_end_handler,
+    zend_observer_fcall_handlers,
+    zend_observer_fcall_init,
+    zend_observer_fcall_register,
+    zend_observer_fiber_destroy_register,
+    zend_observer_fiber_init_register,
+    zend_observer_fiber_switch_register,
+    zend_extension,
+    zend_register_extension,
+    zend_gc_status,
+    zend_gc_get_status,
+    zend_gc_collect_cycles,
+    gc_enabled,
+    zend_compile_file,
+    ZEND_INCLUDE,
+    ZEND_INCLUDE_ONCE,
+    ZEND_REQUIRE,
+    ZEND_REQUIRE_ONCE,
+    ZEND_COMPILE_EXTENDED_STMT,
     zend_type,
     zend_value,
     zend_wrong_parameters_count_error,
@@ -208,6 +260,7 @@ bind! {
     ZEND_ACC_PRELOADED,
     ZEND_ACC_PRIVATE,
     ZEND_ACC_PROMOTED,
+    ZEND_ACC_VIRTUAL,
     ZEND_ACC_PROPERTY_TYPES_RESOLVED,
     ZEND_ACC_PROTECTED,
     ZEND_ACC_PUBLIC,
@@ -252,6 +305,9 @@ bind! {
     zend_std_get_properties,
     zend_std_has_property,
     zend_objects_new,
+    zend_lazy_object_init,
+    IS_OBJ_LAZY_UNINITIALIZED,
+    IS_OBJ_LAZY_PROXY,
     zend_standard_class_def,
     zend_class_serialize_deny,
     zend_class_unserialize_deny,
@@ -274,6 +330,7 @@ bind! {
     sapi_globals_struct,
     sapi_globals,
     sapi_module,
+    module_registry,
     php_printf,
     __zend_malloc,
     tsrm_get_ls_cache,
@@ -303,6 +360,66 @@ bind! {
     php_register_url_stream_wrapper_volatile,
     php_stream_wrapper,
     php_stream_stdio_ops,
+    php_stream_ops,
+    php_stream_wrapper_ops,
+    php_stream_statbuf,
+    php_stream_dirent,
+    php_stream_wrapper_log_error,
+    php_clear_stat_cache,
+    REPORT_ERRORS,
+    PHP_STREAM_MKDIR_RECURSIVE,
+    php_stream_filter,
+    php_stream_filter_ops,
+    php_stream_filter_factory,
+    php_stream_filter_register_factory,
+    php_stream_filter_status_t_PSFS_ERR_FATAL,
+    php_stream_filter_status_t_PSFS_FEED_ME,
+    php_stream_filter_status_t_PSFS_PASS_ON,
+    PSFS_FLAG_FLUSH_INC,
+    PSFS_FLAG_FLUSH_CLOSE,
+    php_stream_bucket,
+    php_stream_bucket_brigade,
+    php_stream_bucket_new,
+    php_stream_bucket_unlink,
+    php_stream_bucket_append,
+    php_stream_bucket_delref,
+    _php_stream_read,
+    _php_stream_write,
+    _php_stream_flush,
+    _php_stream_seek,
+    _php_stream_tell,
+    _php_stream_set_option,
+    _php_stream_cast,
+    php_file_le_stream,
+    php_file_le_pstream,
+    PHP_STREAM_OPTION_BLOCKING,
+    PHP_STREAM_OPTION_RETURN_OK,
+    PHP_STREAM_OPTION_RETURN_NOTIMPL,
+    PHP_STREAM_AS_FD_FOR_SELECT,
+    _php_stream_open_wrapper_ex,
+    php_stream_context_alloc,
+    php_stream_context_set_option,
+    zend_list_delete,
+    php_stream_notifier,
+    php_stream_notification_alloc,
+    php_stream_notification_free,
+    PHP_STREAM_NOTIFIER_PROGRESS,
+    PHP_STREAM_NOTIFY_RESOLVE,
+    PHP_STREAM_NOTIFY_CONNECT,
+    PHP_STREAM_NOTIFY_AUTH_REQUIRED,
+    PHP_STREAM_NOTIFY_MIME_TYPE_IS,
+    PHP_STREAM_NOTIFY_FILE_SIZE_IS,
+    PHP_STREAM_NOTIFY_REDIRECTED,
+    PHP_STREAM_NOTIFY_PROGRESS,
+    PHP_STREAM_NOTIFY_COMPLETED,
+    PHP_STREAM_NOTIFY_FAILURE,
+    PHP_STREAM_NOTIFY_AUTH_RESULT,
+    _php_stream_sock_open_from_socket,
+    php_socket_t,
+    ps_module,
+    ps_module_struct,
+    php_session_register_module,
+    php_session_create_id,
     zend_atomic_bool_store,
     zend_interrupt_function,
     zend_eval_string,
@@ -317,6 +434,10 @@ bind! {
     sapi_shutdown,
     sapi_send_headers,
     sapi_get_default_content_type,
+    sapi_add_header_ex,
+    sapi_header_op,
+    sapi_header_op_enum,
+    sapi_header_line,
     php_module_startup,
     php_module_shutdown,
     php_request_startup,
@@ -332,6 +453,7 @@ bind! {
     php_default_treat_data,
     php_default_input_filter,
     php_register_variable,
+    php_register_variable_safe,
     php_import_environment_variables,
     zend_error
 }
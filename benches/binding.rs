@@ -0,0 +1,92 @@
+//! Benchmarks of the binding layer, run with `cargo bench --features bench`.
+
+#![allow(missing_docs)]
+#![cfg_attr(windows, feature(abi_vectorcall))]
+
+use std::collections::HashMap;
+
+use ext_php_rs::{
+    bench::{
+        bench_call, bench_eval,
+        criterion::{black_box, criterion_group, criterion_main, Criterion},
+        in_request,
+    },
+    convert::{FromZval, IntoZval},
+    prelude::*,
+};
+
+fn arrays(c: &mut Criterion) {
+    in_request(get_module, || {
+        let mut group = c.benchmark_group("arrays");
+        let list: Vec<i64> = (0..1000).collect();
+        let map: HashMap<String, String> = (0..1000)
+            .map(|i| (format!("key{i}"), format!("value{i}")))
+            .collect();
+
+        group.bench_function("vec_into_zval", |b| {
+            b.iter(|| black_box(list.clone()).into_zval(false).unwrap())
+        });
+        let zval = list.clone().into_zval(false).unwrap();
+        group.bench_function("vec_from_zval", |b| {
+            b.iter(|| Vec::<i64>::from_zval(black_box(&zval)).unwrap())
+        });
+        group.bench_function("map_into_zval", |b| {
+            b.iter(|| black_box(map.clone()).into_zval(false).unwrap())
+        });
+        let zval = map.clone().into_zval(false).unwrap();
+        group.bench_function("map_from_zval", |b| {
+            b.iter(|| HashMap::<String, String>::from_zval(black_box(&zval)).unwrap())
+        });
+        group.finish();
+    });
+}
+
+fn strings(c: &mut Criterion) {
+    in_request(get_module, || {
+        let mut group = c.benchmark_group("strings");
+        for (name, len) in [("short", 16), ("long", 64 * 1024)] {
+            let string = "a".repeat(len);
+            group.bench_function(format!("{name}_into_zval"), |b| {
+                b.iter(|| black_box(string.as_str()).into_zval(false).unwrap())
+            });
+            let zval = string.as_str().into_zval(false).unwrap();
+            group.bench_function(format!("{name}_from_zval"), |b| {
+                b.iter(|| String::from_zval(black_box(&zval)).unwrap())
+            });
+            group.bench_function(format!("{name}_str"), |b| {
+                b.iter(|| black_box(&zval).str().unwrap().len())
+            });
+        }
+        group.finish();
+    });
+}
+
+fn calls(c: &mut Criterion) {
+    in_request(get_module, || {
+        let mut group = c.benchmark_group("calls");
+        group.bench_function("rust", |b| b.iter(|| add(black_box(1), black_box(2))));
+        bench_eval(&mut group, "php_expression", "1 + 2");
+        bench_eval(&mut group, "php_builtin", "max(1, 2)");
+        bench_eval(&mut group, "php_to_extension", "bench_add(1, 2)");
+        bench_call(&mut group, "rust_to_extension", "bench_add", &[&1, &2]);
+        bench_call(&mut group, "rust_to_builtin", "max", &[&1, &2]);
+        group.finish();
+    });
+}
+
+#[php_function]
+pub fn bench_add(a: i64, b: i64) -> i64 {
+    add(a, b)
+}
+
+fn add(a: i64, b: i64) -> i64 {
+    a + b
+}
+
+#[php_module]
+pub fn module(module: ModuleBuilder) -> ModuleBuilder {
+    module.function(wrap_function!(bench_add))
+}
+
+criterion_group!(benches, arrays, strings, calls);
+criterion_main!(benches);
@@ -45,6 +45,13 @@ pub trait PHPProvider<'a>: Sized {
     fn print_extra_link_args(&self) -> Result<()> {
         Ok(())
     }
+
+    /// Describes where the headers were found, e.g. the raw output of the
+    /// tool reporting them, for the error raised on a mismatch with the PHP
+    /// executable.
+    fn describe_headers(&self) -> String {
+        String::new()
+    }
 }
 
 /// Finds the location of an executable `name`.
@@ -64,8 +71,61 @@ pub fn path_from_env(key: &str) -> Option<PathBuf> {
     std::env::var_os(key).map(PathBuf::from)
 }
 
+/// Returns the value of a boolean environment variable, accepting `1`, `true`,
+/// `yes` and `on` as true, and any other value as false.
+pub fn env_flag(key: &str) -> Option<bool> {
+    let value = std::env::var(key).ok()?;
+    Some(matches!(
+        value.to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    ))
+}
+
+/// Returns the PHP include directory to use instead of querying the PHP
+/// installation of the host, when cross-compiling against a PHP sysroot.
+///
+/// The directory is given by `PHP_INCLUDE_DIR`, or found inside the sysroot
+/// given by `PHP_SYSROOT`.
+pub fn php_include_dir() -> Result<Option<PathBuf>> {
+    if let Some(dir) = path_from_env("PHP_INCLUDE_DIR") {
+        return Ok(Some(dir));
+    }
+    let Some(sysroot) = path_from_env("PHP_SYSROOT") else {
+        return Ok(None);
+    };
+    ["usr/include/php", "usr/local/include/php"]
+        .iter()
+        .map(|dir| sysroot.join(dir))
+        .find(|dir| dir.join("main").join("php.h").exists())
+        .map(Some)
+        .with_context(|| format!("Could not find the PHP headers in sysroot {sysroot:?}"))
+}
+
+/// Returns the Homebrew installation prefix for the target architecture, which
+/// is `/opt/homebrew` on Apple Silicon and `/usr/local` on Intel.
+pub fn homebrew_prefix() -> PathBuf {
+    path_from_env("HOMEBREW_PREFIX").unwrap_or_else(|| {
+        if env::var("CARGO_CFG_TARGET_ARCH").is_ok_and(|arch| arch == "aarch64") {
+            "/opt/homebrew".into()
+        } else {
+            "/usr/local".into()
+        }
+    })
+}
+
+/// Finds the PHP executable of a Homebrew keg, which is not linked into the
+/// `PATH` for versioned formulae such as `php@8.2`. The unversioned `php`
+/// formula is preferred, then the most recent version.
+fn find_homebrew_php() -> Option<PathBuf> {
+    let opt = homebrew_prefix().join("opt");
+    ["php", "php@8.4", "php@8.3", "php@8.2", "php@8.1"]
+        .iter()
+        .map(|formula| opt.join(formula).join("bin").join("php"))
+        .find(|php| php.exists())
+}
+
 /// Finds the location of the PHP executable.
-fn find_php() -> Result<PathBuf> {
+pub fn find_php() -> Result<PathBuf> {
     // If path is given via env, it takes priority.
     if let Some(path) = path_from_env("PHP") {
         if !path.try_exists()? {
@@ -74,10 +134,24 @@ fn find_php() -> Result<PathBuf> {
         }
         return Ok(path);
     }
-    find_executable("php").with_context(|| {
-        "Could not find PHP executable. \
-        Please ensure `php` is in your PATH or the `PHP` environment variable is set."
-    })
+    find_executable("php")
+        .or_else(|| {
+            // Distributions such as Alpine Linux only ship versioned executables,
+            // e.g. `php83`.
+            ["84", "83", "82", "81", "8"]
+                .iter()
+                .find_map(|suffix| find_executable(&format!("php{suffix}")))
+        })
+        .or_else(|| {
+            env::var("CARGO_CFG_TARGET_OS")
+                .is_ok_and(|os| os == "macos")
+                .then(find_homebrew_php)
+                .flatten()
+        })
+        .with_context(|| {
+            "Could not find PHP executable. \
+            Please ensure `php` is in your PATH or the `PHP` environment variable is set."
+        })
 }
 
 /// Output of `php -i`.
@@ -97,6 +171,46 @@ impl PHPInfo {
         Ok(Self(stdout.to_string()))
     }
 
+    /// Builds the PHP info from the headers in the given include directory,
+    /// for PHP installations which can't be executed on the host.
+    pub fn from_headers(include_dir: &Path) -> Result<Self> {
+        let read = |path: &[&str]| {
+            let path = path
+                .iter()
+                .fold(include_dir.to_path_buf(), |p, c| p.join(c));
+            std::fs::read_to_string(&path).with_context(|| format!("Failed to read {path:?}"))
+        };
+        let define = |header: &str, name: &str| {
+            header.lines().find_map(|line| {
+                let mut parts = line.split_whitespace();
+                (parts.next() == Some("#define") && parts.next() == Some(name)).then(|| {
+                    parts
+                        .next()
+                        .unwrap_or_default()
+                        .trim_matches('"')
+                        .to_string()
+                })
+            })
+        };
+
+        let version_h = read(&["main", "php_version.h"])?;
+        let php_h = read(&["main", "php.h"])?;
+        let config = read(&["main", "php_config.h"])?;
+
+        let version = define(&version_h, "PHP_VERSION").context("Failed to get PHP version")?;
+        let api = define(&php_h, "PHP_API_VERSION").context("Failed to get PHP API version")?;
+        let zts = header_defines(&config, "ZTS");
+        let debug = header_defines(&config, "ZEND_DEBUG");
+
+        Ok(Self(format!(
+            "PHP Version => {}\nPHP API => {}\nThread Safety => {}\nDebug Build => {}\n",
+            version,
+            api,
+            if zts { "enabled" } else { "disabled" },
+            if debug { "yes" } else { "no" },
+        )))
+    }
+
     // Only present on Windows.
     #[cfg(windows)]
     pub fn architecture(&self) -> Result<impl_::Arch> {
@@ -136,6 +250,24 @@ impl PHPInfo {
             .and_then(|s| u32::from_str(s).context("Failed to convert Zend version to integer"))
     }
 
+    /// Get the directory extensions are loaded from, if available.
+    pub fn extension_dir(&self) -> Option<&str> {
+        // INI settings are listed with their local and master values.
+        self.get_key("extension_dir")
+            .and_then(|value| value.split(" => ").next())
+    }
+
+    /// Returns the lines of the given keys, as printed by `php -i`.
+    fn raw_lines(&self, keys: &[&str]) -> Vec<&str> {
+        self.0
+            .lines()
+            .filter(|line| {
+                keys.iter()
+                    .any(|key| line.starts_with(&format!("{key} => ")))
+            })
+            .collect()
+    }
+
     fn get_key(&self, key: &str) -> Option<&str> {
         let split = format!("{key} => ");
         for line in self.0.lines() {
@@ -148,12 +280,43 @@ impl PHPInfo {
     }
 }
 
+/// Returns the compiler flags enabling AddressSanitizer when the `asan`
+/// feature is enabled, so the wrapper and the bindings are built with the same
+/// settings as a PHP built with `--enable-address-sanitizer`.
+fn sanitizer_flags() -> &'static [&'static str] {
+    if env::var_os("CARGO_FEATURE_ASAN").is_some() {
+        &["-fsanitize=address", "-fno-omit-frame-pointer"]
+    } else {
+        &[]
+    }
+}
+
+/// Returns the defines enabling the optional parts of the wrapper, e.g. the
+/// bindings to `ext/session` with the `session` feature, as the headers of
+/// the extension are missing when PHP is built without it.
+fn feature_defines() -> Vec<(&'static str, &'static str)> {
+    let mut defines = vec![];
+    if env::var_os("CARGO_FEATURE_SESSION").is_some() {
+        defines.push(("EXT_PHP_RS_SESSION", "1"));
+    }
+    defines
+}
+
 /// Builds the wrapper library.
 fn build_wrapper(defines: &[(&str, &str)], includes: &[PathBuf]) -> Result<()> {
     let mut build = cc::Build::new();
+    for flag in sanitizer_flags() {
+        build.flag(flag);
+    }
     for (var, val) in defines {
         build.define(var, *val);
     }
+    if let Some(sysroot) = path_from_env("PHP_SYSROOT") {
+        build.flag(format!("--sysroot={}", sysroot.display()));
+    }
+    // The static TSRM cache of the wrapper must not interpose with the one of
+    // libphp, which has the same symbol name.
+    build.flag_if_supported("-fvisibility=hidden");
     build
         .file("src/wrapper.c")
         .includes(includes)
@@ -178,6 +341,11 @@ fn generate_bindings(defines: &[(&str, &str)], includes: &[PathBuf]) -> Result<S
         .no_copy("_zend_string")
         .no_copy("_zend_array")
         .no_debug("_zend_function_entry") // On Windows when the handler uses vectorcall, Debug cannot be derived so we do it in code.
+        .clang_args(cross_compile_target().map(|target| format!("--target={target}")))
+        .clang_args(sanitizer_flags())
+        .clang_args(
+            path_from_env("PHP_SYSROOT").map(|sysroot| format!("--sysroot={}", sysroot.display())),
+        )
         .layout_tests(env::var("EXT_PHP_RS_TEST").is_ok())
         .rust_target(RustTarget::Nightly);
 
@@ -206,9 +374,247 @@ fn generate_bindings(defines: &[(&str, &str)], includes: &[PathBuf]) -> Result<S
     Ok(bindings)
 }
 
-/// Checks the PHP Zend API version for compatibility with ext-php-rs, setting
-/// any configuration flags required.
-fn check_php_version(info: &PHPInfo) -> Result<()> {
+/// Returns whether a header defines the given macro to a non-zero value.
+fn header_defines(header: &str, name: &str) -> bool {
+    header.lines().any(|line| {
+        let mut parts = line.split_whitespace();
+        parts.next() == Some("#define")
+            && parts.next() == Some(name)
+            && parts.next().is_some_and(|value| value != "0")
+    })
+}
+
+/// Checks that the PHP headers match the PHP executable, as mismatching
+/// thread safety, debug or API settings otherwise lead to crashes at runtime.
+/// This happens when `php` and `php-config` come from different
+/// installations.
+fn check_headers<'a>(
+    info: &PHPInfo,
+    provider: &impl PHPProvider<'a>,
+    includes: &[PathBuf],
+) -> Result<()> {
+    // Windows development packs don't ship `php_config.h`, the defines come from
+    // `php -i` directly.
+    let Some(config_path) = includes
+        .iter()
+        .map(|include| include.join("main").join("php_config.h"))
+        .find(|path| path.exists())
+    else {
+        return Ok(());
+    };
+    let config = std::fs::read_to_string(&config_path)
+        .with_context(|| format!("Failed to read {config_path:?}"))?;
+    let php_h = config_path.with_file_name("php.h");
+    let api_version = std::fs::read_to_string(&php_h).ok().and_then(|php_h| {
+        php_h.lines().find_map(|line| {
+            line.strip_prefix("#define PHP_API_VERSION")
+                .and_then(|version| version.trim().parse::<u32>().ok())
+        })
+    });
+
+    let mut mismatches = vec![];
+    let header_zts = header_defines(&config, "ZTS");
+    if header_zts != info.thread_safety()? {
+        mismatches.push(format!(
+            "thread safety: headers are {}, `php -i` reports {}",
+            if header_zts { "ZTS" } else { "NTS" },
+            if info.thread_safety()? { "ZTS" } else { "NTS" },
+        ));
+    }
+    let header_debug = header_defines(&config, "ZEND_DEBUG");
+    if header_debug != info.debug()? {
+        mismatches.push(format!(
+            "debug build: headers have ZEND_DEBUG={}, `php -i` reports {}",
+            u8::from(header_debug),
+            if info.debug()? {
+                "a debug build"
+            } else {
+                "a release build"
+            },
+        ));
+    }
+    if let Some(api_version) = api_version {
+        if api_version != info.zend_version()? {
+            mismatches.push(format!(
+                "API version: headers are for {}, `php -i` reports {}",
+                api_version,
+                info.zend_version()?
+            ));
+        }
+    }
+
+    if !mismatches.is_empty() {
+        let php_i = info.raw_lines(&["PHP Version", "PHP API", "Thread Safety", "Debug Build"]);
+        bail!(
+            "The PHP headers in {:?} do not match the PHP executable:\n  - {}\n\
+            This usually means `php` and `php-config` belong to different PHP installations. \
+            Set the `PHP` and `PHP_CONFIG` environment variables to the executables of the same installation.\n\n\
+            `php -i` reported:\n  {}\n{}",
+            config_path.parent().and_then(Path::parent).unwrap_or(&config_path),
+            mismatches.join("\n  - "),
+            php_i.join("\n  "),
+            provider.describe_headers()
+        );
+    }
+
+    Ok(())
+}
+
+/// Exports the detected PHP configuration to dependents, which can read it in
+/// their build script through the `DEP_PHP_*` environment variables.
+fn print_metadata(info: &PHPInfo, includes: &[PathBuf]) -> Result<()> {
+    println!("cargo:version={}", info.version()?);
+    println!("cargo:api={}", info.zend_version()?);
+    println!("cargo:zts={}", u8::from(info.thread_safety()?));
+    println!("cargo:debug={}", u8::from(info.debug()?));
+    let includes = env::join_paths(includes).context("Failed to join include paths")?;
+    println!("cargo:include={}", includes.to_string_lossy());
+    if let Some(extension_dir) = info.extension_dir() {
+        println!("cargo:extension_dir={extension_dir}");
+    }
+    Ok(())
+}
+
+/// Returns the name of the pre-generated bindings file matching the PHP
+/// installation, e.g. `php83-nts.rs` or `php84-zts-debug.rs`.
+fn bindings_file_name(info: &PHPInfo) -> Result<String> {
+    let version: String = info.version()?.split('.').take(2).collect();
+    Ok(format!(
+        "php{}-{}{}.rs",
+        version,
+        if info.thread_safety()? { "zts" } else { "nts" },
+        if info.debug()? { "-debug" } else { "" }
+    ))
+}
+
+/// Reads pre-generated bindings from the path given by the
+/// `EXT_PHP_RS_BINDINGS` environment variable, allowing the crate to be built
+/// without `libclang`. The path is either a bindings file, or a directory
+/// containing a bindings file for each PHP version and build mode.
+fn pregenerated_bindings(info: &PHPInfo) -> Result<Option<String>> {
+    let Some(mut path) = path_from_env("EXT_PHP_RS_BINDINGS") else {
+        return Ok(None);
+    };
+    if path.is_dir() {
+        path = path.join(bindings_file_name(info)?);
+    }
+    println!("cargo:rerun-if-changed={}", path.display());
+
+    std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read pre-generated bindings from {path:?}"))
+        .map(Some)
+}
+
+/// Writes the generated bindings to the directory given by the
+/// `EXT_PHP_RS_BINDINGS_OUT` environment variable, to be used later through
+/// `EXT_PHP_RS_BINDINGS`. A warning is emitted if the bindings differ from
+/// the existing file.
+///
+/// When `EXT_PHP_RS_BINDINGS_CHECK` is set, the file is left untouched and the
+/// build fails with a diff if it is missing or outdated, so the committed
+/// bindings can be checked in CI.
+fn save_bindings(info: &PHPInfo, bindings: &str) -> Result<()> {
+    let Some(dir) = path_from_env("EXT_PHP_RS_BINDINGS_OUT") else {
+        return Ok(());
+    };
+    let path = dir.join(bindings_file_name(info)?);
+    let existing = std::fs::read_to_string(&path).ok();
+    if existing.as_deref() == Some(bindings) {
+        return Ok(());
+    }
+
+    if env_flag("EXT_PHP_RS_BINDINGS_CHECK").unwrap_or(false) {
+        match existing {
+            Some(existing) => bail!(
+                "Pre-generated bindings {path:?} are outdated, regenerate them without `EXT_PHP_RS_BINDINGS_CHECK`:\n{}",
+                bindings_diff(&existing, bindings)
+            ),
+            None => bail!(
+                "Pre-generated bindings {path:?} are missing, generate them without `EXT_PHP_RS_BINDINGS_CHECK`"
+            ),
+        }
+    }
+    if let Some(existing) = existing {
+        println!(
+            "cargo:warning=Pre-generated bindings {:?} are outdated, updating them ({} lines to {} lines)",
+            path,
+            existing.lines().count(),
+            bindings.lines().count()
+        );
+    }
+
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create bindings directory {dir:?}"))?;
+    std::fs::write(&path, bindings).with_context(|| format!("Failed to write bindings to {path:?}"))
+}
+
+/// Returns a unified diff of the lines differing between the old and new
+/// bindings, as a single hunk spanning from the first to the last changed
+/// line. The hunk is truncated after [`MAX_DIFF_LINES`] lines.
+fn bindings_diff(old: &str, new: &str) -> String {
+    const MAX_DIFF_LINES: usize = 200;
+
+    let old: Vec<_> = old.lines().collect();
+    let new: Vec<_> = new.lines().collect();
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let removed = &old[prefix..old.len() - suffix];
+    let added = &new[prefix..new.len() - suffix];
+
+    let mut diff = format!(
+        "@@ -{},{} +{},{} @@\n",
+        prefix + 1,
+        removed.len(),
+        prefix + 1,
+        added.len()
+    );
+    let lines = removed
+        .iter()
+        .map(|line| format!("-{line}"))
+        .chain(added.iter().map(|line| format!("+{line}")));
+    for (i, line) in lines.enumerate() {
+        if i == MAX_DIFF_LINES {
+            diff.push_str(&format!(
+                "... {} more lines\n",
+                removed.len() + added.len() - MAX_DIFF_LINES
+            ));
+            break;
+        }
+        diff.push_str(&line);
+        diff.push('\n');
+    }
+    diff
+}
+
+/// Returns the target triple when cross-compiling, so the bindings are
+/// generated for the layout of the target rather than the host.
+fn cross_compile_target() -> Option<String> {
+    let target = env::var("TARGET").ok()?;
+    (env::var("HOST").ok()? != target).then_some(target)
+}
+
+/// Checks the target configuration for settings incompatible with the build
+/// mode, such as musl targets linking the C runtime statically, which is the
+/// default for musl but unsupported for dynamically loaded extensions.
+fn check_target() {
+    let is_musl = env::var("CARGO_CFG_TARGET_ENV").is_ok_and(|env| env == "musl");
+    let crt_static = env::var("CARGO_CFG_TARGET_FEATURE")
+        .is_ok_and(|features| features.split(',').any(|feature| feature == "crt-static"));
+    let static_embed = env::var_os("CARGO_FEATURE_STATIC_EMBED").is_some();
+
+    if is_musl && crt_static && !static_embed {
+        println!("cargo:warning=The musl target links the C runtime statically, which prevents PHP from loading the extension. Build with `RUSTFLAGS=\"-C target-feature=-crt-static\"`.");
+    }
+}
+
+/// Checks the PHP Zend API version for compatibility with ext-php-rs,
+/// returning any configuration flags required.
+fn check_php_version(info: &PHPInfo) -> Result<Vec<&'static str>> {
     let version = info.zend_version()?;
 
     if !(MIN_PHP_API_VER..=MAX_PHP_API_VER).contains(&version) {
@@ -235,29 +641,39 @@ fn check_php_version(info: &PHPInfo) -> Result<()> {
     println!(
         "cargo::rustc-check-cfg=cfg(php80, php81, php82, php83, php84, php_zts, php_debug, docs)"
     );
-    println!("cargo:rustc-cfg=php80");
+    let mut cfgs = vec!["php80"];
 
     if (MIN_PHP_API_VER..PHP_81_API_VER).contains(&version) {
         println!("cargo:warning=PHP version 8.0 is EOL and will no longer be supported in a future release. Please upgrade to a supported version of PHP. See https://www.php.net/supported-versions.php for information on version support timelines.");
     }
 
     if version >= PHP_81_API_VER {
-        println!("cargo:rustc-cfg=php81");
+        cfgs.push("php81");
     }
 
     if version >= PHP_82_API_VER {
-        println!("cargo:rustc-cfg=php82");
+        cfgs.push("php82");
     }
 
     if version >= PHP_83_API_VER {
-        println!("cargo:rustc-cfg=php83");
+        cfgs.push("php83");
     }
 
     if version >= PHP_84_API_VER {
-        println!("cargo:rustc-cfg=php84");
+        cfgs.push("php84");
     }
 
-    Ok(())
+    Ok(cfgs)
+}
+
+/// Enables the given configuration flags, and exports them to dependents as
+/// the comma-separated `DEP_PHP_CFGS` environment variable so they can enable
+/// the same flags in their build script.
+fn print_cfgs(cfgs: &[&str]) {
+    for cfg in cfgs {
+        println!("cargo:rustc-cfg={cfg}");
+    }
+    println!("cargo:cfgs={}", cfgs.join(","));
 }
 
 fn main() -> Result<()> {
@@ -274,7 +690,27 @@ fn main() -> Result<()> {
     ] {
         println!("cargo:rerun-if-changed={}", path.to_string_lossy());
     }
-    for env_var in ["PHP", "PHP_CONFIG", "PATH", "EXT_PHP_RS_ALLOWED_BINDINGS"] {
+    for env_var in [
+        "PHP",
+        "PHP_CONFIG",
+        "PATH",
+        "EXT_PHP_RS_ALLOWED_BINDINGS",
+        "PHP_LIB",
+        "PHP_DEVEL_PACK",
+        "PHP_VS_VERSION",
+        "PHP_LIB_DIR",
+        "PHP_LIB_NAME",
+        "PHP_EMBED",
+        "PHP_RPATH",
+        "EXT_PHP_RS_BINDINGS",
+        "EXT_PHP_RS_BINDINGS_OUT",
+        "EXT_PHP_RS_BINDINGS_CHECK",
+        "PHP_SYSROOT",
+        "PHP_INCLUDE_DIR",
+        "PHP_PKG_CONFIG",
+        "PKG_CONFIG_PATH",
+        "PKG_CONFIG_SYSROOT_DIR",
+    ] {
         println!("cargo:rerun-if-env-changed={env_var}");
     }
 
@@ -283,25 +719,37 @@ fn main() -> Result<()> {
     // docs.rs runners only have PHP 7.4 - use pre-generated bindings
     if env::var("DOCS_RS").is_ok() {
         println!("cargo:warning=docs.rs detected - using stub bindings");
-        println!("cargo:rustc-cfg=php_debug");
-        println!("cargo:rustc-cfg=php81");
-        println!("cargo:rustc-cfg=php82");
+        print_cfgs(&["php_debug", "php81", "php82"]);
         std::fs::copy("docsrs_bindings.rs", out_path)
             .expect("failed to copy docs.rs stub bindings to out directory");
         return Ok(());
     }
 
-    let php = find_php()?;
-    let info = PHPInfo::get(&php)?;
+    check_target();
+
+    if env::var_os("CARGO_FEATURE_ASAN").is_some() {
+        println!("cargo:warning=The `asan` feature is enabled. Build with `RUSTFLAGS=\"-Zsanitizer=address\"` on a nightly compiler, and run PHP with `USE_ZEND_ALLOC=0` so allocations go through the system allocator (this is done automatically by `Embed::run`). Valgrind also requires `USE_ZEND_ALLOC=0`.");
+    }
+
+    let info = match php_include_dir()? {
+        Some(include_dir) => PHPInfo::from_headers(&include_dir)?,
+        None => PHPInfo::get(&find_php()?)?,
+    };
     let provider = Provider::new(&info)?;
 
     let includes = provider.get_includes()?;
-    let defines = provider.get_defines()?;
+    let mut defines = provider.get_defines()?;
+    defines.extend(feature_defines());
 
-    check_php_version(&info)?;
+    let mut cfgs = check_php_version(&info)?;
+    check_headers(&info, &provider, &includes)?;
     build_wrapper(&defines, &includes)?;
 
-    let bindings = generate_bindings(&defines, &includes)?;
+    let bindings = match pregenerated_bindings(&info)? {
+        Some(bindings) => bindings,
+        None => generate_bindings(&defines, &includes)?,
+    };
+    save_bindings(&info, &bindings)?;
 
     let out_file =
         File::create(&out_path).context("Failed to open output bindings file for writing")?;
@@ -309,11 +757,13 @@ fn main() -> Result<()> {
     provider.write_bindings(bindings, &mut out_writer)?;
 
     if info.debug()? {
-        println!("cargo:rustc-cfg=php_debug");
+        cfgs.push("php_debug");
     }
     if info.thread_safety()? {
-        println!("cargo:rustc-cfg=php_zts");
+        cfgs.push("php_zts");
     }
+    print_cfgs(&cfgs);
+    print_metadata(&info, &includes)?;
     provider.print_extra_link_args()?;
 
     // Generate guide tests
@@ -0,0 +1,71 @@
+//! Generates typed Rust wrappers for existing PHP classes.
+
+use std::path::PathBuf;
+
+use anyhow::{bail, Context};
+use clap::Parser;
+use ext_php_rs::codegen::{generate, Reflector};
+
+use crate::CrateResult;
+
+#[derive(Parser)]
+pub struct Bindings {
+    /// Names of the classes to generate wrappers for, e.g. `DateTime`.
+    classes: Vec<String>,
+    /// Name of an extension to generate wrappers for all the classes of, e.g.
+    /// `redis`. Can be given multiple times.
+    #[arg(long)]
+    extension: Vec<String>,
+    /// Name or path of an extension to load, if it is not enabled in the
+    /// `php.ini` file. Can be given multiple times.
+    #[arg(long)]
+    load: Vec<PathBuf>,
+    /// Path to the `php` executable reflecting the classes. Defaults to the
+    /// `PHP` variable if it is set, or the `php` executable in the `PATH`.
+    #[arg(long)]
+    php: Option<PathBuf>,
+    /// Path used to store the generated wrappers. Defaults to writing to
+    /// `php_bindings.rs` in the current directory.
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+    /// Print the wrappers to stdout rather than write to file. Cannot be used
+    /// with `out`.
+    #[arg(long, conflicts_with = "out")]
+    stdout: bool,
+}
+
+impl Bindings {
+    pub fn handle(self) -> CrateResult {
+        if self.classes.is_empty() && self.extension.is_empty() {
+            bail!("No classes or extensions were given.");
+        }
+
+        let mut reflector = Reflector::new();
+        if let Some(php) = self.php {
+            reflector = reflector.php(php);
+        }
+        reflector = self.load.into_iter().fold(reflector, Reflector::load);
+        reflector = self.classes.iter().fold(reflector, Reflector::class);
+        reflector = self.extension.iter().fold(reflector, Reflector::extension);
+
+        let classes = reflector
+            .reflect()
+            .with_context(|| "Failed to reflect the classes")?;
+        let bindings = generate(&classes);
+
+        if self.stdout {
+            print!("{bindings}");
+        } else {
+            let out_path = self.out.unwrap_or_else(|| PathBuf::from("php_bindings.rs"));
+            std::fs::write(&out_path, &bindings)
+                .with_context(|| "Failed to write bindings to file")?;
+            println!(
+                "Generated wrappers for {} classes in {}.",
+                classes.len(),
+                out_path.display()
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+//! Checks an extension against the installed PHP.
+
+use std::{path::PathBuf, process::Command};
+
+use anyhow::{bail, Context, Result as AResult};
+use clap::Parser;
+use ext_php_rs::zend::BuildInfo;
+
+use crate::{build_ext, ext, find_ext, CrateResult};
+
+#[derive(Parser)]
+pub struct Check {
+    /// Path to the extension to check. Defaults to building the extension of
+    /// the crate.
+    ext: Option<PathBuf>,
+    /// Name of the PHP function returning the self-check report of the
+    /// extension, added with `ext_php_rs::self_check::function`. Defaults to
+    /// `<crate>_self_check`.
+    #[arg(long)]
+    function: Option<String>,
+    /// Whether to check the release version of the extension.
+    #[arg(long, conflicts_with = "ext")]
+    release: bool,
+    /// Path to the Cargo manifest of the extension. Defaults to the manifest in
+    /// the directory the command is called.
+    #[arg(long, conflicts_with = "ext")]
+    manifest: Option<PathBuf>,
+}
+
+impl Check {
+    pub fn handle(self) -> CrateResult {
+        let ext_path = match self.ext {
+            Some(ext_path) => ext_path,
+            None => {
+                let target = find_ext(&self.manifest)?;
+                build_ext(&target, self.release)?.into()
+            }
+        };
+        if !ext_path.is_file() {
+            bail!("Invalid extension path given, not a file.");
+        }
+        let ext_path = ext_path
+            .canonicalize()
+            .with_context(|| "Failed to resolve the extension path")?;
+
+        // An extension built for another PHP cannot be loaded to run its own
+        // checks, so the builds are compared first.
+        let engine = engine_build_info()?;
+        match ext::build_info(&ext_path) {
+            Ok(Some(extension)) if extension != engine => {
+                bail!("Extension built for {extension}, installed PHP is {engine}.")
+            }
+            Ok(Some(extension)) => {
+                println!("Extension built for {extension}, installed PHP is {engine}.")
+            }
+            Ok(None) => println!(
+                "Extension built with an older version of `ext-php-rs`, installed PHP is {engine}."
+            ),
+            Err(e) => println!("Could not read the build of the extension: {e:#}"),
+        }
+
+        let function = match self.function {
+            Some(function) => function,
+            None => {
+                let stem = ext_path
+                    .file_stem()
+                    .with_context(|| "Extension path wasn't a filepath")?
+                    .to_string_lossy();
+                let name = stem.strip_prefix("lib").unwrap_or(&stem);
+                format!("{}_self_check", name.replace('-', "_"))
+            }
+        };
+        let script = format!(
+            "if (!function_exists('{function}')) {{ exit(2); }}\n\
+             $report = {function}();\n\
+             foreach ($report['checks'] as $check) {{\n\
+                 echo $check['status'], \"\\t\", $check['name'], \"\\t\", $check['message'], \"\\n\";\n\
+             }}\n\
+             exit($report['ok'] ? 0 : 1);"
+        );
+
+        let cmd = Command::new("php")
+            .arg("-d")
+            .arg(format!("extension={}", ext_path.display()))
+            .arg("-r")
+            .arg(script)
+            .output()
+            .context("Failed to call PHP")?;
+
+        for line in String::from_utf8_lossy(&cmd.stdout).lines() {
+            match line.splitn(3, '\t').collect::<Vec<_>>()[..] {
+                [status, name, message] => println!("[{status}] {name}: {message}"),
+                _ => println!("{line}"),
+            }
+        }
+        match cmd.status.code() {
+            Some(0) => Ok(()),
+            Some(1) => bail!("The extension failed its self-check."),
+            Some(2) => bail!(
+                "The extension does not define the function `{function}`. Add it to the module \
+                 with `ext_php_rs::self_check::function(\"{function}\")`, or pass its name with \
+                 `--function`."
+            ),
+            _ => bail!(
+                "PHP failed to run the self-check of the extension: {}",
+                String::from_utf8_lossy(&cmd.stderr)
+            ),
+        }
+    }
+}
+
+/// Returns the build of the installed PHP, read from `php -i`.
+fn engine_build_info() -> AResult<BuildInfo> {
+    let cmd = Command::new("php")
+        .arg("-i")
+        .output()
+        .context("Failed to call PHP")?;
+    if !cmd.status.success() {
+        bail!("Failed to call PHP: {:?}", cmd);
+    }
+
+    let info = String::from_utf8_lossy(&cmd.stdout);
+    let value = |key: &str| {
+        info.lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix(" => "))
+            .map(str::trim)
+            .with_context(|| format!("Failed to find `{key}` in `php -i`"))
+    };
+
+    Ok(BuildInfo {
+        api: value("PHP Extension")?
+            .parse()
+            .with_context(|| "Failed to parse the module API number of PHP")?,
+        zts: value("Thread Safety")? == "enabled",
+        debug: value("Debug Build")? == "yes",
+    })
+}
@@ -1,7 +1,7 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use anyhow::{Context, Result};
-use ext_php_rs::describe::Description;
+use ext_php_rs::{describe::Description, zend::BuildInfo};
 use libloading::os::unix::{Library, Symbol};
 
 #[allow(improper_ctypes_definitions)]
@@ -37,3 +37,16 @@ impl Ext {
         (self.describe_fn)()
     }
 }
+
+/// Reads the build of PHP an extension is compiled for, without loading it
+/// into PHP. Returns [`None`] if the extension was compiled with a version of
+/// `ext-php-rs` which does not export it.
+pub fn build_info(ext_path: &Path) -> Result<Option<BuildInfo>> {
+    let ext_lib =
+        unsafe { Library::new(ext_path) }.with_context(|| "Failed to load extension library")?;
+    let build_info = unsafe { ext_lib.get::<*const BuildInfo>(b"ext_php_rs_build_info") };
+    Ok(build_info
+        .ok()
+        .and_then(|build_info| unsafe { (*build_info).as_ref() })
+        .copied())
+}
@@ -1,7 +1,12 @@
 #![doc = include_str!("../README.md")]
 
+mod bindings;
+#[cfg(not(windows))]
+mod check;
 #[cfg(not(windows))]
 mod ext;
+#[cfg(not(windows))]
+mod package;
 
 use anyhow::{bail, Context, Result as AResult};
 use cargo_metadata::{camino::Utf8PathBuf, CrateType, Target};
@@ -84,6 +89,30 @@ enum Args {
     /// extension classes, functions and constants.
     #[cfg(not(windows))]
     Stubs(Stubs),
+    /// Bundles a SAPI host with the PHP library it links against.
+    ///
+    /// The host binary, the shared PHP library, a `php.ini` and the given
+    /// extensions are copied into a directory which can be moved to other
+    /// machines. The host is linked with a relative rpath, so it loads the
+    /// PHP library of the package instead of the one it was built against.
+    #[cfg(not(windows))]
+    Package(package::Package),
+    /// Checks the extension against the installed PHP.
+    ///
+    /// The build of PHP the extension is compiled for is compared with the
+    /// installed PHP, then the extension is loaded into PHP to run the
+    /// self-check function added with `ext_php_rs::self_check::function`,
+    /// which checks the engine functions and the classes and functions
+    /// registered by the extension.
+    #[cfg(not(windows))]
+    Check(check::Check),
+    /// Generates typed Rust wrappers for existing PHP classes.
+    ///
+    /// The classes are reflected with the `php` executable, and a wrapper is
+    /// generated for each of them, with their constants and their public
+    /// methods, whose arguments and return values have the types declared by
+    /// PHP.
+    Bindings(bindings::Bindings),
 }
 
 #[derive(Parser)]
@@ -160,6 +189,11 @@ impl Args {
             Args::Remove(remove) => remove.handle(),
             #[cfg(not(windows))]
             Args::Stubs(stubs) => stubs.handle(),
+            #[cfg(not(windows))]
+            Args::Package(package) => package.handle(),
+            #[cfg(not(windows))]
+            Args::Check(check) => check.handle(),
+            Args::Bindings(bindings) => bindings.handle(),
         }
     }
 }
@@ -0,0 +1,315 @@
+//! Bundles a SAPI host with the PHP library it links against.
+
+use std::{
+    fs,
+    io::BufReader,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context, Result as AResult};
+use cargo_metadata::{camino::Utf8PathBuf, CrateType, Target};
+use clap::Parser;
+use dialoguer::Select;
+
+use crate::CrateResult;
+
+/// The directory of the PHP library, relative to the binary of the host.
+#[cfg(target_os = "macos")]
+const RPATH: &str = "@executable_path/../lib";
+#[cfg(not(target_os = "macos"))]
+const RPATH: &str = "$ORIGIN/../lib";
+
+#[derive(Parser)]
+pub struct Package {
+    /// Name of the binary target of the host. Defaults to the only binary
+    /// target of the crate.
+    #[arg(long)]
+    bin: Option<String>,
+    /// Directory to write the package to. Defaults to
+    /// `target/php-package/<bin>`.
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+    /// Whether to package the release version of the host.
+    #[arg(long)]
+    release: bool,
+    /// Path to the `php.ini` to ship with the host. Defaults to a `php.ini`
+    /// enabling the extensions given with `--ext`.
+    #[arg(long)]
+    ini: Option<PathBuf>,
+    /// Path to an extension to ship in the extension directory of the
+    /// package. Can be given multiple times.
+    #[arg(long)]
+    ext: Vec<PathBuf>,
+    /// Path to the Cargo manifest of the host. Defaults to the manifest in the
+    /// directory the command is called.
+    #[arg(long)]
+    manifest: Option<PathBuf>,
+}
+
+impl Package {
+    pub fn handle(self) -> CrateResult {
+        let (target, target_dir) = find_bin(&self.manifest, self.bin.as_deref())?;
+        let out = self
+            .out
+            .unwrap_or_else(|| target_dir.join("php-package").join(&target.name).into());
+
+        let lib = find_php_lib()?;
+        let lib_name = lib
+            .file_name()
+            .with_context(|| "PHP library path wasn't a filepath")?
+            .to_owned();
+        let bin_path = build_bin(&target, self.release)?;
+
+        let bin_dir = out.join("bin");
+        let lib_dir = out.join("lib");
+        let ext_dir = lib_dir.join("php").join("extensions");
+        let etc_dir = out.join("etc");
+        for dir in [&bin_dir, &ext_dir, &etc_dir] {
+            fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create directory {dir:?}"))?;
+        }
+
+        let bin = bin_dir.join(&target.name);
+        fs::copy(&bin_path, &bin).with_context(|| "Failed to copy the host binary")?;
+        fs::copy(&lib, lib_dir.join(&lib_name))
+            .with_context(|| "Failed to copy the PHP library")?;
+
+        let mut ext_names = vec![];
+        for ext in &self.ext {
+            let name = ext
+                .file_name()
+                .with_context(|| format!("Extension path {ext:?} wasn't a filepath"))?;
+            fs::copy(ext, ext_dir.join(name))
+                .with_context(|| format!("Failed to copy the extension {ext:?}"))?;
+            ext_names.push(name.to_string_lossy().into_owned());
+        }
+
+        let ini = etc_dir.join("php.ini");
+        match &self.ini {
+            Some(path) => {
+                fs::copy(path, &ini).with_context(|| "Failed to copy `php.ini`")?;
+            }
+            None => fs::write(&ini, default_ini(&ext_names))
+                .with_context(|| "Failed to write `php.ini`")?,
+        }
+
+        #[cfg(target_os = "macos")]
+        fix_install_names(&bin, &lib_dir.join(&lib_name))?;
+
+        println!("Packaged {} into {}", target.name, out.display());
+        Ok(())
+    }
+}
+
+/// Returns the `php.ini` enabling the given extensions, found in the
+/// extension directory of the package.
+fn default_ini(extensions: &[String]) -> String {
+    let mut ini = String::from(
+        "; Default settings of the package, generated by `cargo php package`.\n\
+         ;\n\
+         ; `PHP_PACKAGE_DIR` is the root directory of the package, set by the host\n\
+         ; before starting PHP, as relative paths are resolved from the working\n\
+         ; directory.\n\
+         extension_dir = \"${PHP_PACKAGE_DIR}/lib/php/extensions\"\n",
+    );
+    for ext in extensions {
+        ini.push_str(&format!("extension={ext}\n"));
+    }
+    ini
+}
+
+/// Finds the binary target of the host, along with the target directory of
+/// the workspace.
+fn find_bin(manifest: &Option<PathBuf>, name: Option<&str>) -> AResult<(Target, Utf8PathBuf)> {
+    let mut cmd = cargo_metadata::MetadataCommand::new();
+    if let Some(manifest) = manifest {
+        cmd.manifest_path(manifest);
+    }
+
+    let meta = cmd
+        .exec()
+        .with_context(|| "Failed to call `cargo metadata`")?;
+    let package = meta
+        .root_package()
+        .with_context(|| "Failed to retrieve metadata about crate")?;
+
+    let targets: Vec<_> = package
+        .targets
+        .iter()
+        .filter(|target| target.crate_types.contains(&CrateType::Bin))
+        .filter(|target| name.is_none_or(|name| target.name == name))
+        .collect();
+
+    let target = match targets.len() {
+        0 => bail!("No binary targets were found."),
+        1 => targets[0],
+        _ => {
+            let target_names: Vec<_> = targets.iter().map(|target| &target.name).collect();
+            let chosen = Select::new()
+                .with_prompt("There were multiple binary targets detected in the project. Which would you like to package?")
+                .items(&target_names)
+                .interact()?;
+            targets[chosen]
+        }
+    };
+
+    Ok((target.clone(), meta.target_directory.clone()))
+}
+
+/// Compiles the host, with an rpath pointing to the library directory of the
+/// package. Only the binary is linked with the rpath, so its dependencies are
+/// not rebuilt.
+fn build_bin(target: &Target, release: bool) -> AResult<Utf8PathBuf> {
+    let mut cmd = Command::new("cargo");
+    cmd.arg("rustc")
+        .arg("--bin")
+        .arg(&target.name)
+        .arg("--message-format=json-render-diagnostics");
+    if release {
+        cmd.arg("--release");
+    }
+    cmd.arg("--")
+        .arg("-C")
+        .arg(format!("link-arg=-Wl,-rpath,{RPATH}"));
+
+    let mut spawn = cmd
+        .stdout(Stdio::piped())
+        .spawn()
+        .with_context(|| "Failed to spawn `cargo rustc`")?;
+    let reader = BufReader::new(
+        spawn
+            .stdout
+            .take()
+            .with_context(|| "Failed to take `cargo rustc` stdout")?,
+    );
+
+    let mut executable = None;
+    for message in cargo_metadata::Message::parse_stream(reader) {
+        let message = message.with_context(|| "Invalid message received from `cargo rustc`")?;
+        match message {
+            cargo_metadata::Message::CompilerArtifact(a) => {
+                if &a.target == target {
+                    executable = a.executable;
+                }
+            }
+            cargo_metadata::Message::BuildFinished(b) => {
+                if !b.success {
+                    bail!("Compilation failed, cancelling packaging.")
+                } else {
+                    break;
+                }
+            }
+            _ => continue,
+        }
+    }
+
+    executable.with_context(|| "Host binary was not compiled")
+}
+
+/// Returns the path of the shared PHP library, found like the build script of
+/// `ext-php-rs` does, from the `PHP_LIB_DIR` and `PHP_LIB_NAME` environment
+/// variables or from `php-config`.
+fn find_php_lib() -> AResult<PathBuf> {
+    let lib_dir = match std::env::var_os("PHP_LIB_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            let php_config = std::env::var_os("PHP_CONFIG").unwrap_or_else(|| "php-config".into());
+            let cmd = Command::new(&php_config)
+                .arg("--prefix")
+                .output()
+                .with_context(|| format!("Failed to call {php_config:?}"))?;
+            if !cmd.status.success() {
+                bail!("Failed to call {:?}: {:?}", php_config, cmd);
+            }
+            PathBuf::from(String::from_utf8_lossy(&cmd.stdout).trim()).join("lib")
+        }
+    };
+
+    let names = match std::env::var("PHP_LIB_NAME") {
+        Ok(name) => vec![name],
+        Err(_) => php_lib_names(&lib_dir)?,
+    };
+    names
+        .iter()
+        .map(|name| lib_dir.join(format!("lib{name}.{}", std::env::consts::DLL_EXTENSION)))
+        .find(|path| path.is_file())
+        .with_context(|| {
+            format!(
+                "Failed to find the shared PHP library in {lib_dir:?}. PHP must be built with \
+                 `--enable-embed`, or `PHP_LIB_DIR` and `PHP_LIB_NAME` set."
+            )
+        })
+}
+
+/// Returns the names of the PHP libraries in a directory, preferring the
+/// unversioned name, e.g. `php` before `php83`.
+fn php_lib_names(lib_dir: &Path) -> AResult<Vec<String>> {
+    let mut names: Vec<String> = fs::read_dir(lib_dir)
+        .with_context(|| format!("Failed to read the PHP library directory {lib_dir:?}"))?
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            let stem = name.strip_suffix(&format!(".{}", std::env::consts::DLL_EXTENSION))?;
+            Some(stem.strip_prefix("lib")?.to_string())
+        })
+        .filter(|name| {
+            name.strip_prefix("php")
+                .is_some_and(|version| version.chars().all(|c| c.is_ascii_digit()))
+        })
+        .collect();
+    names.sort_by_key(|name| (name != "php", std::cmp::Reverse(name.clone())));
+    Ok(names)
+}
+
+/// Makes the host load the PHP library of the package on macOS, where
+/// binaries refer to libraries by their install name rather than by their
+/// file name.
+#[cfg(target_os = "macos")]
+fn fix_install_names(bin: &Path, lib: &Path) -> CrateResult {
+    let lib_name = lib
+        .file_name()
+        .with_context(|| "PHP library path wasn't a filepath")?
+        .to_string_lossy();
+    let install_name = format!("@rpath/{lib_name}");
+
+    let otool = Command::new("otool")
+        .arg("-L")
+        .arg(bin)
+        .output()
+        .with_context(|| "Failed to call `otool`")?;
+    let linked = String::from_utf8_lossy(&otool.stdout);
+    let old_name = linked
+        .lines()
+        .skip(1)
+        .filter_map(|line| line.split_whitespace().next())
+        .find(|name| name.ends_with(&*lib_name))
+        .with_context(|| "The host binary does not link the PHP library")?;
+
+    run(Command::new("install_name_tool")
+        .arg("-id")
+        .arg(&install_name)
+        .arg(lib))?;
+    run(Command::new("install_name_tool")
+        .arg("-change")
+        .arg(old_name)
+        .arg(&install_name)
+        .arg(bin))?;
+    // Modified binaries must be signed again to be loaded on Apple silicon.
+    for file in [lib, bin] {
+        run(Command::new("codesign")
+            .args(["--force", "--sign", "-"])
+            .arg(file))?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn run(cmd: &mut Command) -> CrateResult {
+    let status = cmd
+        .status()
+        .with_context(|| format!("Failed to call {cmd:?}"))?;
+    if !status.success() {
+        bail!("Failed to call {:?}", cmd);
+    }
+    Ok(())
+}
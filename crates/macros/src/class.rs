@@ -131,6 +131,7 @@ fn parse_fields<'a>(fields: impl Iterator<Item = &'a mut syn::Field>) -> Result<
         if let Some((ident, prop)) = property {
             result.push(Property {
                 ident,
+                ty: &field.ty,
                 attr: prop,
                 docs,
             });
@@ -143,6 +144,7 @@ fn parse_fields<'a>(fields: impl Iterator<Item = &'a mut syn::Field>) -> Result<
 #[derive(Debug)]
 pub struct Property<'a> {
     pub ident: &'a syn::Ident,
+    pub ty: &'a syn::Type,
     pub attr: PropertyAttr,
     pub docs: Vec<String>,
 }
@@ -251,6 +253,7 @@ fn generate_registered_class_impl(
     let fields = fields.iter().map(|prop| {
         let name = prop.name();
         let ident = prop.ident;
+        let ty = prop.ty;
         let flags = prop
             .attr
             .flags
@@ -263,7 +266,9 @@ fn generate_registered_class_impl(
             (#name, ::ext_php_rs::internal::property::PropertyInfo {
                 prop: ::ext_php_rs::props::Property::field(|this: &mut Self| &mut this.#ident),
                 flags: #flags,
-                docs: &[#(#docs,)*]
+                docs: &[#(#docs,)*],
+                ty: ::std::option::Option::Some(<#ty as ::ext_php_rs::convert::IntoZval>::TYPE),
+                nullable: <#ty as ::ext_php_rs::convert::IntoZval>::NULLABLE,
             })
         }
     });
@@ -0,0 +1,141 @@
+use darling::{ast::NestedMeta, FromMeta};
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{Attribute, DeriveInput, Fields, Meta};
+
+use crate::prelude::*;
+
+/// Arguments of the `#[php_error]` attribute, placed either on the enum to
+/// set defaults or on a variant.
+#[derive(Debug, Default, FromMeta)]
+#[darling(default)]
+struct ErrorArgs {
+    /// A function with the signature `fn() -> &'static ClassEntry` returning
+    /// the exception class to throw.
+    ex: Option<syn::Expr>,
+    /// The message format, with the same syntax as `format!`. Fields can be
+    /// referenced by name, or by index for tuple variants.
+    message: Option<String>,
+    /// The exception code.
+    code: Option<i32>,
+}
+
+impl ErrorArgs {
+    fn parse(attrs: &[Attribute]) -> Result<Self> {
+        let mut args = None;
+        for attr in attrs {
+            if !attr.path().is_ident("php_error") {
+                continue;
+            }
+            if args.is_some() {
+                bail!(attr => "Only one `#[php_error]` attribute is valid per item.");
+            }
+            let Meta::List(list) = &attr.meta else {
+                bail!(attr => "Expected `#[php_error(...)]`.");
+            };
+            let meta = NestedMeta::parse_meta_list(list.tokens.clone())?;
+            args = match Self::from_list(&meta) {
+                Ok(args) => Some(args),
+                Err(e) => bail!(attr => "Failed to parse `#[php_error]` arguments: {:?}", e),
+            };
+        }
+        Ok(args.unwrap_or_default())
+    }
+}
+
+pub fn parser(input: DeriveInput) -> Result<TokenStream> {
+    let DeriveInput {
+        ident,
+        generics,
+        attrs,
+        data,
+        ..
+    } = input;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let syn::Data::Enum(data) = data else {
+        bail!(ident => "`#[derive(PhpError)]` can only be used on enums.");
+    };
+
+    let defaults = ErrorArgs::parse(&attrs)?;
+
+    let arms = data
+        .variants
+        .iter()
+        .map(|variant| {
+            let args = ErrorArgs::parse(&variant.attrs)?;
+            let variant_ident = &variant.ident;
+
+            let pattern = match &variant.fields {
+                Fields::Named(fields) => {
+                    let names: Vec<_> = fields
+                        .named
+                        .iter()
+                        .filter_map(|field| field.ident.clone())
+                        .collect();
+                    quote! { { #(#names),* } }
+                }
+                Fields::Unnamed(fields) => {
+                    let names = (0..fields.unnamed.len()).map(|i| format_ident!("_{}", i));
+                    quote! { ( #(#names),* ) }
+                }
+                Fields::Unit => quote! {},
+            };
+
+            let message = match args.message.as_ref().or(defaults.message.as_ref()) {
+                Some(message) => {
+                    let message = positional_to_named(message);
+                    quote! { ::std::format!(#message) }
+                }
+                None => quote! { ::std::string::ToString::to_string(err) },
+            };
+            let code = args.code.or(defaults.code).unwrap_or_default();
+            let ex = match args.ex.as_ref().or(defaults.ex.as_ref()) {
+                Some(ex) => quote! { (#ex)() },
+                None => quote! { ::ext_php_rs::zend::ce::exception() },
+            };
+
+            Ok(quote! {
+                #ident::#variant_ident #pattern => (#message, #code, #ex)
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        impl #impl_generics ::std::convert::From<#ident #ty_generics>
+            for ::ext_php_rs::exception::PhpException #where_clause
+        {
+            #[allow(unused_variables)]
+            fn from(err: #ident #ty_generics) -> Self {
+                let err = &err;
+                let (message, code, ex) = match err {
+                    #(#arms,)*
+                };
+                ::ext_php_rs::exception::PhpException::new(message, code, ex)
+            }
+        }
+    })
+}
+
+/// Rewrites positional arguments in a format string (`{0}`) to reference the
+/// bindings of tuple variant fields (`{_0}`).
+fn positional_to_named(format: &str) -> String {
+    let mut result = String::with_capacity(format.len());
+    let mut chars = format.chars().peekable();
+    while let Some(c) = chars.next() {
+        result.push(c);
+        if c != '{' {
+            continue;
+        }
+        match chars.peek() {
+            // Escaped brace.
+            Some('{') => {
+                result.push('{');
+                chars.next();
+            }
+            Some(c) if c.is_ascii_digit() => result.push('_'),
+            _ => {}
+        }
+    }
+    result
+}
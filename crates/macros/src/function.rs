@@ -65,8 +65,10 @@ pub struct Function<'a> {
     pub name: String,
     /// Function arguments.
     pub args: Args<'a>,
-    /// Function outputs.
+    /// Function outputs. For async functions, the output of the future.
     pub output: Option<&'a Type>,
+    /// Whether the function is `async`, or returns `impl Future`.
+    pub is_async: bool,
     /// The first optional argument of the function.
     pub optional: Option<Ident>,
     /// Doc comments for the function.
@@ -109,14 +111,20 @@ impl<'a> Function<'a> {
         optional: Option<Ident>,
         docs: Vec<String>,
     ) -> Result<Self> {
+        let (output, is_async) = match &sig.output {
+            syn::ReturnType::Default => (None, sig.asyncness.is_some()),
+            syn::ReturnType::Type(_, ty) => match future_output(ty) {
+                Some(output) => (Some(output), true),
+                None => (Some(&**ty), sig.asyncness.is_some()),
+            },
+        };
+
         Ok(Self {
             ident: &sig.ident,
             name: name.unwrap_or_else(|| sig.ident.to_string()),
             args,
-            output: match &sig.output {
-                syn::ReturnType::Default => None,
-                syn::ReturnType::Type(_, ty) => Some(&**ty),
-            },
+            output,
+            is_async,
             optional,
             docs,
         })
@@ -171,19 +179,25 @@ impl<'a> Function<'a> {
         });
 
         let result = match call_type {
-            CallType::Function => quote! {
-                let parse = ex.parser()
-                    #(.arg(&mut #required_arg_names))*
-                    .not_required()
-                    #(.arg(&mut #not_required_arg_names))*
-                    .parse();
-                if parse.is_err() {
-                    return;
-                }
+            CallType::Function => {
+                let call = self.await_call(quote! { #ident(#({#arg_accessors}),*) });
+                quote! {
+                    let parse = ex.parser()
+                        #(.arg(&mut #required_arg_names))*
+                        .not_required()
+                        #(.arg(&mut #not_required_arg_names))*
+                        .parse();
+                    if parse.is_err() {
+                        return;
+                    }
 
-                #ident(#({#arg_accessors}),*)
-            },
+                    #call
+                }
+            }
             CallType::Method { class, receiver } => {
+                if self.is_async && !matches!(receiver, MethodReceiver::Static) {
+                    bail!(self.ident => "Async methods cannot take `self`, as the object could be used by another fiber while the method is suspended.");
+                }
                 let this = match receiver {
                     MethodReceiver::Static => quote! {
                         let parse = ex.parser();
@@ -210,6 +224,7 @@ impl<'a> Function<'a> {
                         quote! { #class::#ident(this, #({#arg_accessors}),*) }
                     }
                 };
+                let call = self.await_call(call);
                 quote! {
                     #this
                     let parse_result = parse
@@ -244,13 +259,19 @@ impl<'a> Function<'a> {
                     ) {
                         use ::ext_php_rs::convert::IntoZval;
 
-                        #(#arg_declarations)*
-                        let result = {
-                            #result
-                        };
+                        let panic = ::ext_php_rs::exception::catch_panic(|| {
+                            #(#arg_declarations)*
+                            let result = {
+                                #result
+                            };
+
+                            if let Err(e) = result.set_zval(retval, false) {
+                                let e: ::ext_php_rs::exception::PhpException = e.into();
+                                e.throw().expect("Failed to throw PHP exception.");
+                            }
+                        });
 
-                        if let Err(e) = result.set_zval(retval, false) {
-                            let e: ::ext_php_rs::exception::PhpException = e.into();
+                        if let Err(e) = panic {
                             e.throw().expect("Failed to throw PHP exception.");
                         }
                     }
@@ -265,6 +286,24 @@ impl<'a> Function<'a> {
         })
     }
 
+    /// Wraps the call of an async function, so the future is awaited before
+    /// its output is returned to PHP.
+    fn await_call(&self, call: TokenStream) -> TokenStream {
+        if !self.is_async {
+            return call;
+        }
+        quote! {
+            match ::ext_php_rs::future::block_on(#call) {
+                Ok(output) => output,
+                Err(e) => {
+                    let e: ::ext_php_rs::exception::PhpException = e.into();
+                    e.throw().expect("Failed to throw PHP exception.");
+                    return;
+                }
+            }
+        }
+    }
+
     /// Generates a struct and impl for the `PhpFunction` trait.
     pub fn php_function_impl(&self) -> Result<TokenStream> {
         let internal_ident = self.internal_ident();
@@ -291,6 +330,9 @@ impl<'a> Function<'a> {
     /// check if the function is a constructor, however.
     pub fn constructor_meta(&self, class: &syn::Path) -> Result<TokenStream> {
         let ident = self.ident;
+        if self.is_async {
+            bail!(ident => "Constructors cannot be async.");
+        }
         let (required, not_required) = self.args.split_args(self.optional.as_ref());
         let required_args = required
             .iter()
@@ -603,6 +645,29 @@ impl TypedArg<'_> {
     }
 }
 
+/// Returns the output of a future returned as `impl Future<Output = T>`.
+fn future_output(ty: &Type) -> Option<&Type> {
+    let Type::ImplTrait(impl_trait) = ty else {
+        return None;
+    };
+    impl_trait.bounds.iter().find_map(|bound| {
+        let syn::TypeParamBound::Trait(bound) = bound else {
+            return None;
+        };
+        let segment = bound.path.segments.last()?;
+        if segment.ident != "Future" {
+            return None;
+        }
+        let PathArguments::AngleBracketed(args) = &segment.arguments else {
+            return None;
+        };
+        args.args.iter().find_map(|arg| match arg {
+            GenericArgument::AssocType(assoc) if assoc.ident == "Output" => Some(&assoc.ty),
+            _ => None,
+        })
+    })
+}
+
 /// Returns true of the given type is nullable in PHP.
 // TODO(david): Eventually move to compile-time constants for this (similar to
 // FromZval::NULLABLE).
@@ -1,6 +1,7 @@
 //! Macros for the `php-ext` crate.
 mod class;
 mod constant;
+mod error;
 mod extern_;
 mod fastcall;
 mod function;
@@ -8,6 +9,7 @@ mod helpers;
 mod impl_;
 mod module;
 mod syn_ext;
+mod test;
 mod zval;
 
 use proc_macro::TokenStream;
@@ -897,6 +899,121 @@ pub fn zval_convert_derive(input: TokenStream) -> TokenStream {
         .into()
 }
 
+/// # `#[derive(PhpError)]`
+///
+/// Implements `From<T> for PhpException` on an error enum, so it can be
+/// returned from functions exported to PHP, or used with the `?` operator in
+/// functions returning a `PhpResult`.
+///
+/// Each variant can be given the exception to throw with the `#[php_error]`
+/// attribute. The attribute can also be placed on the enum to set defaults
+/// for all variants. It takes the following options:
+///
+/// - `ex` - A function with the signature `fn() -> &'static ClassEntry`
+///   returning the class of the exception. Defaults to `Exception`.
+/// - `message` - The exception message, using the same syntax as `format!`.
+///   Fields of the variant can be referenced by name, or by index for tuple
+///   variants. Defaults to the [`Display`] implementation of the enum, e.g.
+///   one derived with `thiserror`.
+/// - `code` - The exception code. Defaults to zero.
+///
+/// ## Example
+///
+/// ```rust,no_run
+/// # #![cfg_attr(windows, feature(abi_vectorcall))]
+/// # extern crate ext_php_rs;
+/// use ext_php_rs::{prelude::*, zend::ce};
+///
+/// #[derive(Debug, PhpError)]
+/// #[php_error(code = 1)]
+/// pub enum ConfigError {
+///     #[php_error(ex = ce::value_error, message = "invalid value for {key}: {value}")]
+///     InvalidValue { key: String, value: String },
+///     #[php_error(message = "missing key {0}", code = 2)]
+///     MissingKey(String),
+///     #[php_error(message = "config is empty")]
+///     Empty,
+/// }
+///
+/// #[php_function]
+/// pub fn get_config_value(key: &str) -> Result<String, ConfigError> {
+///     Err(ConfigError::MissingKey(key.into()))
+/// }
+///
+/// #[php_module]
+/// pub fn get_module(module: ModuleBuilder) -> ModuleBuilder {
+///     module.function(wrap_function!(get_config_value))
+/// }
+/// # fn main() {}
+/// ```
+///
+/// [`Display`]: std::fmt::Display
+#[proc_macro_derive(PhpError, attributes(php_error))]
+pub fn php_error_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    error::parser(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+/// # `#[php_test]` Attribute
+///
+/// Turns a function into a test run by `cargo test` inside a request of an
+/// embedded PHP, which is started with the module of the crate on first use.
+/// Functions, classes and conversions of the module can then be tested from
+/// Rust, calling PHP with `Embed::eval`, without building the extension and
+/// running PHP scripts.
+///
+/// Like `Embed::run`, this requires PHP built with `--enable-embed`.
+///
+/// ## Options
+///
+/// - `module` - The function returning the module entry, generated by the
+///   [`php_module`] attribute as `get_module`. Defaults to
+///   `crate::get_module`, the module defined at the root of the crate.
+///
+/// Each test runs in a new request, so globals and objects of one test are not
+/// seen by the others. Tests run one at a time, as PHP only runs one request at
+/// a time. A test fails if it panics, or if PHP bails out of it, e.g. on a fatal
+/// error. Tests may return a `Result`, and keep attributes such as
+/// `#[should_panic]` and `#[ignore]`.
+///
+/// ## Example
+///
+/// ```rust,ignore
+/// use ext_php_rs::{embed::Embed, prelude::*};
+///
+/// #[php_function]
+/// pub fn hello_world(name: String) -> String {
+///     format!("Hello, {}!", name)
+/// }
+///
+/// #[php_module]
+/// pub fn get_module(module: ModuleBuilder) -> ModuleBuilder {
+///     module.function(wrap_function!(hello_world))
+/// }
+///
+/// #[cfg(test)]
+/// mod tests {
+///     use ext_php_rs::{embed::Embed, php_test};
+///
+///     #[php_test]
+///     fn test_hello_world() {
+///         let greeting = Embed::eval("hello_world('foo');").expect("should call the function");
+///         assert_eq!(greeting.str(), Some("Hello, foo!"));
+///     }
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn php_test(args: TokenStream, input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as ItemFn);
+
+    test::parser(args.into(), input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
 /// Defines an `extern` function with the Zend fastcall convention based on
 /// operating system.
 ///
@@ -51,6 +51,14 @@ pub fn parser(args: TokenStream, input: ItemFn) -> Result<TokenStream> {
                 #(#stmts)*
             }
 
+            // The module entry cannot be read by an engine with another build,
+            // so an entry failing at startup with a clear error is returned
+            // instead.
+            if let Err(e) = ::ext_php_rs::zend::BuildInfo::check_compatibility() {
+                return ::ext_php_rs::zend::ModuleEntry::incompatible(env!("CARGO_PKG_NAME"), &e)
+                    .into_raw();
+            }
+
             let builder = internal(::ext_php_rs::builders::ModuleBuilder::new(
                 env!("CARGO_PKG_NAME"),
                 env!("CARGO_PKG_VERSION")
@@ -66,6 +74,14 @@ pub fn parser(args: TokenStream, input: ItemFn) -> Result<TokenStream> {
             }
         }
 
+        /// The build of PHP the extension is compiled for, which can be read
+        /// without loading the extension into PHP.
+        #[doc(hidden)]
+        #[no_mangle]
+        #[allow(non_upper_case_globals)]
+        pub static ext_php_rs_build_info: ::ext_php_rs::zend::BuildInfo =
+            ::ext_php_rs::zend::BuildInfo::COMPILED;
+
         #[cfg(debug_assertions)]
         #[no_mangle]
         pub extern "C" fn ext_php_rs_describe_module() -> ::ext_php_rs::describe::Description {
@@ -0,0 +1,47 @@
+use darling::{ast::NestedMeta, FromMeta};
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{ItemFn, Path};
+
+use crate::prelude::*;
+
+#[derive(Debug, Default, FromMeta)]
+#[darling(default)]
+pub struct TestArgs {
+    /// The function returning the module entry, `crate::get_module` by
+    /// default.
+    module: Option<Path>,
+}
+
+pub fn parser(args: TokenStream, input: ItemFn) -> Result<TokenStream> {
+    let meta = NestedMeta::parse_meta_list(args)?;
+    let opts = match TestArgs::from_list(&meta) {
+        Ok(opts) => opts,
+        Err(e) => bail!(input => "Failed to parse attribute options: {:?}", e),
+    };
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = input;
+    if sig.asyncness.is_some() {
+        bail!(sig => "`#[php_test]` functions cannot be `async`.");
+    }
+    if !sig.inputs.is_empty() {
+        bail!(sig.inputs => "`#[php_test]` functions cannot take parameters.");
+    }
+    let module = match opts.module {
+        Some(module) => quote! { #module },
+        None => quote! { crate::get_module },
+    };
+    let output = &sig.output;
+
+    Ok(quote! {
+        #[test]
+        #(#attrs)*
+        #vis #sig {
+            ::ext_php_rs::embed::run_test(#module, move || #output #block)
+        }
+    })
+}
@@ -0,0 +1,86 @@
+//! Applies arbitrary operations to a hashtable and checks that it stays
+//! consistent.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use ext_php_rs::{
+    embed::run_test,
+    fuzz::{check_conversions, Value},
+    prelude::*,
+    types::{ZendHashTable, Zval},
+};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Debug, Arbitrary)]
+enum Op {
+    Insert(String, Value),
+    InsertAtIndex(u64, Value),
+    Push(Value),
+    Remove(String),
+    RemoveIndex(u64),
+    Get(String),
+    GetIndex(u64),
+    Clear,
+    Duplicate,
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    run_test(get_module, || {
+        let mut array = ZendHashTable::new();
+        for op in ops {
+            match op {
+                Op::Insert(key, value) => {
+                    if let Ok(value) = value.to_zval() {
+                        array.insert(&key, value).expect("should insert the value");
+                        assert!(array.get(&key).is_some());
+                    }
+                }
+                Op::InsertAtIndex(index, value) => {
+                    if let Ok(value) = value.to_zval() {
+                        array
+                            .insert_at_index(index, value)
+                            .expect("should insert the value");
+                        assert!(array.get_index(index).is_some());
+                    }
+                }
+                Op::Push(value) => {
+                    if let Ok(value) = value.to_zval() {
+                        let len = array.len();
+                        if array.push(value).is_ok() {
+                            assert_eq!(array.len(), len + 1);
+                        }
+                    }
+                }
+                Op::Remove(key) => {
+                    let _ = array.remove(&key);
+                    assert!(array.get(&key).is_none());
+                }
+                Op::RemoveIndex(index) => {
+                    let _ = array.remove_index(index);
+                    assert!(array.get_index(index).is_none());
+                }
+                Op::Get(key) => {
+                    let _ = array.get(&key);
+                }
+                Op::GetIndex(index) => {
+                    let _ = array.get_index(index);
+                }
+                Op::Clear => {
+                    array.clear();
+                    assert!(array.is_empty());
+                }
+                Op::Duplicate => array = array.to_owned(),
+            }
+        }
+
+        let mut zval = Zval::new();
+        zval.set_hashtable(array);
+        check_conversions(&zval);
+    });
+});
+
+#[php_module]
+pub fn module(module: ModuleBuilder) -> ModuleBuilder {
+    module
+}
@@ -0,0 +1,24 @@
+//! Builds arbitrary PHP values and checks their conversions to Rust values.
+
+#![no_main]
+
+use ext_php_rs::{
+    embed::run_test,
+    fuzz::{check_conversions, Value},
+    prelude::*,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|value: Value| {
+    run_test(get_module, || {
+        // PHP may reject a value, e.g. when an array is full.
+        if let Ok(zval) = value.to_zval() {
+            check_conversions(&zval);
+        }
+    });
+});
+
+#[php_module]
+pub fn module(module: ModuleBuilder) -> ModuleBuilder {
+    module
+}
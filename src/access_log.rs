@@ -0,0 +1,294 @@
+//! Structured access log of the requests handled by SAPI hosts.
+//!
+//! An [`AccessLog`] records an [`AccessLogEntry`] for each request handled by
+//! a module built with [`ModuleBuilder`] or run with [`Embed::run`], with the
+//! method, URI and status of the request, the bytes of the response body, the
+//! duration and the peak memory usage, and passes it to a sink, e.g. a
+//! callback or a writer of JSON lines.
+//!
+//! The bytes are counted by wrapping the unbuffered write function of the
+//! SAPI module when the first request starts up, so they include the output
+//! flushed by the output buffers but not the headers.
+//!
+//! [`ModuleBuilder`]: crate::builders::ModuleBuilder
+//! [`Embed::run`]: crate::embed::Embed::run
+
+use std::{
+    cell::Cell,
+    ffi::c_char,
+    fmt::{self, Debug, Write as _},
+    io::Write,
+    sync::Once,
+    time::{Duration, Instant, SystemTime},
+};
+
+use parking_lot::{const_rwlock, Mutex, RwLock};
+
+use crate::zend::{lifecycle::RequestObserver, SapiGlobals, SapiModule};
+
+type Sink = Box<dyn Fn(&AccessLogEntry) + Send + Sync>;
+type UbWrite = unsafe extern "C" fn(*const c_char, usize) -> usize;
+
+static SINKS: RwLock<Vec<Sink>> = const_rwlock(Vec::new());
+/// The unbuffered write function of the SAPI module.
+static UB_WRITE: RwLock<Option<UbWrite>> = const_rwlock(None);
+static WRAP_UB_WRITE: Once = Once::new();
+
+thread_local! {
+    /// When the request of this thread started, and at which system time.
+    static STARTED: Cell<Option<(Instant, SystemTime)>> = const { Cell::new(None) };
+    /// The bytes written by the request of this thread.
+    static BYTES_WRITTEN: Cell<u64> = const { Cell::new(0) };
+}
+
+/// A request recorded by an [`AccessLog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessLogEntry {
+    /// When the request started.
+    pub started: SystemTime,
+    /// The identifier of the request, see
+    /// [`request_id`](crate::zend::lifecycle::request_id).
+    pub request_id: Option<String>,
+    /// The method of the request, e.g. `GET`.
+    pub method: Option<String>,
+    /// The URI of the request.
+    pub uri: Option<String>,
+    /// The HTTP status of the response, or [`None`] if the SAPI does not
+    /// send one, e.g. for the CLI.
+    pub status: Option<u16>,
+    /// The bytes of the response body written to the SAPI.
+    pub bytes_written: u64,
+    /// The time elapsed between the startup and shutdown of the request.
+    pub duration: Duration,
+    /// The peak memory used by allocations during the request, in bytes.
+    pub peak_memory: usize,
+}
+
+impl AccessLogEntry {
+    /// Formats the entry as a JSON object on a single line, e.g.
+    /// `{"time":1700000000.123,"request_id":"a1-1","method":"GET","uri":"/",
+    /// "status":200,"bytes":512,"duration_ms":1.5,"peak_memory":2097152}`.
+    /// Missing values are `null`.
+    pub fn to_json(&self) -> String {
+        let time = self
+            .started
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let mut json = format!("{{\"time\":{}.{:03}", time.as_secs(), time.subsec_millis());
+        json.push_str(",\"request_id\":");
+        push_json_str(&mut json, self.request_id.as_deref());
+        json.push_str(",\"method\":");
+        push_json_str(&mut json, self.method.as_deref());
+        json.push_str(",\"uri\":");
+        push_json_str(&mut json, self.uri.as_deref());
+        match self.status {
+            Some(status) => {
+                let _ = write!(json, ",\"status\":{status}");
+            }
+            None => json.push_str(",\"status\":null"),
+        }
+        let _ = write!(
+            json,
+            ",\"bytes\":{},\"duration_ms\":{:.3},\"peak_memory\":{}}}",
+            self.bytes_written,
+            self.duration.as_secs_f64() * 1000.0,
+            self.peak_memory
+        );
+        json
+    }
+}
+
+/// Appends a string to a JSON document, or `null`.
+fn push_json_str(json: &mut String, value: Option<&str>) {
+    let Some(value) = value else {
+        json.push_str("null");
+        return;
+    };
+
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(json, "\\u{:04x}", c as u32);
+            }
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+}
+
+/// An access log, passing an [`AccessLogEntry`] to its sink at the end of
+/// each request.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::io;
+///
+/// use ext_php_rs::access_log::AccessLog;
+///
+/// // During module startup:
+/// AccessLog::json_lines(io::stderr()).register();
+///
+/// AccessLog::new(|entry| {
+///     if entry.status.is_some_and(|status| status >= 500) {
+///         eprintln!("{:?} failed after {:?}", entry.uri, entry.duration);
+///     }
+/// })
+/// .register();
+/// ```
+pub struct AccessLog {
+    sink: Sink,
+}
+
+impl AccessLog {
+    /// Creates an access log passing each entry to the given sink, on the
+    /// thread of the request.
+    ///
+    /// # Parameters
+    ///
+    /// * `sink` - Receives the entry of each request.
+    pub fn new<F>(sink: F) -> Self
+    where
+        F: Fn(&AccessLogEntry) + Send + Sync + 'static,
+    {
+        Self {
+            sink: Box::new(sink),
+        }
+    }
+
+    /// Creates an access log writing each entry to the given writer as a line
+    /// of JSON, see [`AccessLogEntry::to_json`]. The writer is flushed after
+    /// each line, and write errors are ignored.
+    ///
+    /// # Parameters
+    ///
+    /// * `writer` - The writer to write the entries to, e.g. a file.
+    pub fn json_lines<W>(writer: W) -> Self
+    where
+        W: Write + Send + 'static,
+    {
+        let writer = Mutex::new(writer);
+        Self::new(move |entry| {
+            let mut line = entry.to_json();
+            line.push('\n');
+            let mut writer = writer.lock();
+            let _ = writer.write_all(line.as_bytes());
+            let _ = writer.flush();
+        })
+    }
+
+    /// Starts recording the requests.
+    ///
+    /// This must be called during module startup.
+    pub fn register(self) {
+        let mut sinks = SINKS.write();
+        if sinks.is_empty() {
+            // Started up first and shut down last, so the duration includes
+            // the other observers.
+            RequestObserver::new()
+                .priority(i32::MIN)
+                .on_startup(start_request)
+                .on_shutdown(finish_request)
+                .register();
+        }
+        sinks.push(self.sink);
+    }
+}
+
+impl Debug for AccessLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AccessLog").finish_non_exhaustive()
+    }
+}
+
+/// Starts recording the request of this thread.
+fn start_request() {
+    WRAP_UB_WRITE.call_once(wrap_ub_write);
+    BYTES_WRITTEN.set(0);
+    STARTED.set(Some((Instant::now(), SystemTime::now())));
+}
+
+/// Passes the entry of the request of this thread to the sinks.
+fn finish_request() {
+    let Some((started, time)) = STARTED.take() else {
+        return;
+    };
+
+    let entry = {
+        let globals = SapiGlobals::get();
+        let info = globals.request_info();
+        AccessLogEntry {
+            started: time,
+            request_id: crate::zend::lifecycle::request_id(),
+            method: info.request_method().map(ToString::to_string),
+            uri: info.request_uri().map(ToString::to_string),
+            status: u16::try_from(globals.sapi_headers().http_response_code)
+                .ok()
+                .filter(|status| *status != 0),
+            bytes_written: BYTES_WRITTEN.get(),
+            duration: started.elapsed(),
+            peak_memory: crate::alloc::MemoryStats::capture().peak_usage,
+        }
+    };
+
+    // Sinks may run requests of their own, e.g. through `Embed::run`.
+    let sinks = SINKS.read_recursive();
+    for sink in sinks.iter() {
+        sink(&entry);
+    }
+}
+
+/// Replaces the unbuffered write function of the SAPI module with
+/// [`counting_ub_write`].
+fn wrap_ub_write() {
+    let mut module = SapiModule::get_mut();
+    if let Some(ub_write) = module.ub_write {
+        *UB_WRITE.write() = Some(ub_write);
+        module.ub_write = Some(counting_ub_write);
+    }
+}
+
+/// Counts the bytes written by the request before calling the original
+/// unbuffered write function.
+unsafe extern "C" fn counting_ub_write(str: *const c_char, str_length: usize) -> usize {
+    let Some(ub_write) = *UB_WRITE.read_recursive() else {
+        return 0;
+    };
+
+    let written = ub_write(str, str_length);
+    BYTES_WRITTEN.set(BYTES_WRITTEN.get() + written as u64);
+    written
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_json() {
+        let entry = AccessLogEntry {
+            started: SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            request_id: None,
+            method: Some("GET".into()),
+            uri: Some("/search?q=\"a\\b\"".into()),
+            status: Some(200),
+            bytes_written: 512,
+            duration: Duration::from_micros(1500),
+            peak_memory: 2_097_152,
+        };
+        assert_eq!(
+            entry.to_json(),
+            "{\"time\":1700000000.123,\"request_id\":null,\"method\":\"GET\",\
+             \"uri\":\"/search?q=\\\"a\\\\b\\\"\",\"status\":200,\"bytes\":512,\
+             \"duration_ms\":1.500,\"peak_memory\":2097152}"
+        );
+    }
+}
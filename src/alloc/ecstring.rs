@@ -0,0 +1,139 @@
+use std::{
+    ffi::{c_char, CStr},
+    fmt::{self, Debug},
+    ptr::NonNull,
+    slice,
+    str::Utf8Error,
+};
+
+use super::efree;
+
+/// An owned, NUL-terminated string allocated through the Zend memory manager,
+/// as returned by [`estrdup`] and [`estrndup`]. The string is freed when
+/// dropped, unless ownership is transferred to PHP with
+/// [`ECString::into_raw`].
+///
+/// The length of the string is tracked separately from the NUL terminator, so
+/// binary strings containing NUL bytes are supported.
+///
+/// [`estrdup`]: super::estrdup
+/// [`estrndup`]: super::estrndup
+pub struct ECString {
+    ptr: NonNull<c_char>,
+    len: usize,
+}
+
+impl ECString {
+    /// Creates an owned string from a pointer to a NUL-terminated string
+    /// allocated through the Zend memory manager.
+    ///
+    /// # Parameters
+    ///
+    /// * `ptr` - The pointer to the string.
+    /// * `len` - The length of the string in bytes, excluding the NUL
+    ///   terminator.
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure that `ptr` is non-null, was allocated through the
+    /// Zend memory manager and points to `len` bytes followed by a NUL byte.
+    pub unsafe fn from_raw(ptr: *mut c_char, len: usize) -> Self {
+        Self {
+            ptr: NonNull::new_unchecked(ptr),
+            len,
+        }
+    }
+
+    /// Returns the pointer to the string, transferring ownership to the
+    /// caller, which becomes responsible for freeing it with `efree`, e.g. by
+    /// handing it to PHP.
+    pub fn into_raw(self) -> *mut c_char {
+        let ptr = self.ptr.as_ptr();
+        #[cfg(feature = "alloc_tracking")]
+        super::tracking::untrack(ptr.cast());
+        std::mem::forget(self);
+        ptr
+    }
+
+    /// Returns a pointer to the string, which remains owned by `self`.
+    pub fn as_ptr(&self) -> *const c_char {
+        self.ptr.as_ptr()
+    }
+
+    /// Returns the length of the string in bytes, excluding the NUL
+    /// terminator.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the bytes of the string, excluding the NUL terminator.
+    pub fn as_bytes(&self) -> &[u8] {
+        // SAFETY: The pointer points to `len` initialized bytes.
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr().cast(), self.len) }
+    }
+
+    /// Returns the string as a [`CStr`], which ends at the first NUL byte.
+    pub fn as_c_str(&self) -> &CStr {
+        // SAFETY: The string is NUL-terminated.
+        unsafe { CStr::from_ptr(self.ptr.as_ptr()) }
+    }
+
+    /// Returns the string as a string slice.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the string is not valid UTF-8.
+    pub fn to_str(&self) -> Result<&str, Utf8Error> {
+        std::str::from_utf8(self.as_bytes())
+    }
+}
+
+impl Drop for ECString {
+    fn drop(&mut self) {
+        // SAFETY: The string was allocated through the Zend memory manager and is
+        // owned by `self`.
+        unsafe { efree(self.ptr.as_ptr().cast()) };
+    }
+}
+
+impl AsRef<[u8]> for ECString {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Debug for ECString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&String::from_utf8_lossy(self.as_bytes()), f)
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use crate::{
+        alloc::{estrdup, estrndup},
+        embed::Embed,
+    };
+
+    #[test]
+    fn test_estrdup() {
+        Embed::run(|| {
+            let string = estrdup("foo\0bar");
+            assert_eq!(string.as_bytes(), b"foo");
+            assert_eq!(string.to_str(), Ok("foo"));
+
+            let binary = estrndup("foo\0bar", 100);
+            assert_eq!(binary.as_bytes(), b"foo\0bar");
+            assert_eq!(binary.as_c_str().to_bytes(), b"foo");
+
+            let prefix = estrndup("foobar", 3);
+            assert_eq!(prefix.as_bytes(), b"foo");
+        });
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    fmt::{self, Debug, Display},
+    ops::Deref,
+};
+
+use super::EVec;
+use crate::{convert::IntoZval, error::Result, flags::DataType, types::Zval};
+
+/// A growable UTF-8 string allocated in request-bound memory through the Zend
+/// memory manager, similar to a [`String`].
+///
+/// See [`EVec`] for how the memory is managed. The string implements
+/// [`fmt::Write`], so it can be used as the target of [`write!`] to build
+/// large per-request buffers.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::fmt::Write;
+/// use ext_php_rs::alloc::EString;
+///
+/// let mut html = EString::with_capacity(1024);
+/// html.push_str("<ul>");
+/// for i in 0..3 {
+///     write!(html, "<li>{i}</li>").unwrap();
+/// }
+/// html.push_str("</ul>");
+/// ```
+#[derive(Default, Clone, PartialEq, Eq)]
+pub struct EString {
+    vec: EVec<u8>,
+}
+
+impl EString {
+    /// Creates a new, empty string. No memory is allocated until data is
+    /// pushed onto it.
+    pub const fn new() -> Self {
+        Self { vec: EVec::new() }
+    }
+
+    /// Creates a new, empty string with space for at least `capacity` bytes.
+    ///
+    /// # Parameters
+    ///
+    /// * `capacity` - The number of bytes to allocate space for.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            vec: EVec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the length of the string in bytes.
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    /// Returns whether the string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    /// Returns the number of bytes the string can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    /// Reserves space for at least `additional` more bytes.
+    ///
+    /// # Parameters
+    ///
+    /// * `additional` - The number of additional bytes to reserve space for.
+    pub fn reserve(&mut self, additional: usize) {
+        self.vec.reserve(additional);
+    }
+
+    /// Shrinks the capacity of the string to its length.
+    pub fn shrink_to_fit(&mut self) {
+        self.vec.shrink_to_fit();
+    }
+
+    /// Appends a string slice to the end of the string.
+    ///
+    /// # Parameters
+    ///
+    /// * `string` - The string to append.
+    pub fn push_str(&mut self, string: &str) {
+        self.vec.extend_from_slice(string.as_bytes());
+    }
+
+    /// Appends a character to the end of the string.
+    ///
+    /// # Parameters
+    ///
+    /// * `ch` - The character to append.
+    pub fn push(&mut self, ch: char) {
+        self.push_str(ch.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Removes all contents of the string, keeping its capacity.
+    pub fn clear(&mut self) {
+        self.vec.clear();
+    }
+
+    /// Returns the contents of the string as a string slice.
+    pub fn as_str(&self) -> &str {
+        // SAFETY: Only valid UTF-8 is ever appended to the buffer.
+        unsafe { std::str::from_utf8_unchecked(&self.vec) }
+    }
+}
+
+impl Deref for EString {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl From<&str> for EString {
+    fn from(value: &str) -> Self {
+        let mut string = Self::with_capacity(value.len());
+        string.push_str(value);
+        string
+    }
+}
+
+impl fmt::Write for EString {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+}
+
+impl Display for EString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(self.as_str(), f)
+    }
+}
+
+impl Debug for EString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl IntoZval for EString {
+    const TYPE: DataType = DataType::String;
+    const NULLABLE: bool = false;
+
+    fn set_zval(self, zv: &mut Zval, persistent: bool) -> Result<()> {
+        zv.set_string(&self, persistent)
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+    use std::fmt::Write;
+
+    #[test]
+    fn test_estring() {
+        Embed::run(|| {
+            let mut string = EString::from("foo");
+            string.push(' ');
+            write!(string, "{}", 42).expect("should write to string");
+            assert_eq!(string.as_str(), "foo 42");
+
+            let zval = string.into_zval(false).expect("should convert to zval");
+            assert_eq!(zval.str(), Some("foo 42"));
+        });
+    }
+}
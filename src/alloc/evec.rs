@@ -0,0 +1,395 @@
+use std::{
+    alloc::{handle_alloc_error, GlobalAlloc, Layout},
+    fmt::Debug,
+    mem::{self, MaybeUninit},
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    slice,
+};
+
+use super::EAllocator;
+use crate::{
+    convert::IntoZval,
+    error::Result,
+    flags::DataType,
+    types::{ZendHashTable, Zval},
+};
+
+/// A growable array allocated in request-bound memory through the Zend
+/// memory manager, similar to a [`Vec`].
+///
+/// The buffer grows through `erealloc`, which can often resize it in place,
+/// and is accounted for by `memory_get_usage()` and the `memory_limit` INI
+/// setting. Any memory still allocated at the end of the request is
+/// reclaimed by PHP, so the vector must not outlive the request it was
+/// created in.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ext_php_rs::alloc::EVec;
+///
+/// let mut values = EVec::with_capacity(16);
+/// values.push(1);
+/// values.extend_from_slice(&[2, 3]);
+/// assert_eq!(&*values, &[1, 2, 3]);
+/// ```
+pub struct EVec<T> {
+    ptr: NonNull<T>,
+    len: usize,
+    cap: usize,
+}
+
+impl<T> EVec<T> {
+    /// Creates a new, empty vector. No memory is allocated until elements are
+    /// pushed onto it.
+    pub const fn new() -> Self {
+        Self {
+            ptr: NonNull::dangling(),
+            len: 0,
+            cap: if mem::size_of::<T>() == 0 {
+                usize::MAX
+            } else {
+                0
+            },
+        }
+    }
+
+    /// Creates a new, empty vector with space for at least `capacity`
+    /// elements.
+    ///
+    /// # Parameters
+    ///
+    /// * `capacity` - The number of elements to allocate space for.
+    pub fn with_capacity(capacity: usize) -> Self {
+        let mut vec = Self::new();
+        vec.reserve(capacity);
+        vec
+    }
+
+    /// Returns the number of elements in the vector.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether the vector contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of elements the vector can hold without
+    /// reallocating.
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    /// Reserves space for at least `additional` more elements. The capacity
+    /// is at least doubled when the vector grows, to amortize reallocations.
+    ///
+    /// # Parameters
+    ///
+    /// * `additional` - The number of additional elements to reserve space
+    ///   for.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows.
+    pub fn reserve(&mut self, additional: usize) {
+        let required = self.len.checked_add(additional).expect("capacity overflow");
+        if required > self.cap {
+            self.grow_to(required.max(self.cap * 2).max(4));
+        }
+    }
+
+    /// Reserves space for exactly `additional` more elements.
+    ///
+    /// # Parameters
+    ///
+    /// * `additional` - The number of additional elements to reserve space
+    ///   for.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows.
+    pub fn reserve_exact(&mut self, additional: usize) {
+        let required = self.len.checked_add(additional).expect("capacity overflow");
+        if required > self.cap {
+            self.grow_to(required);
+        }
+    }
+
+    /// Shrinks the capacity of the vector to its length, giving the spare
+    /// memory back to the memory manager.
+    pub fn shrink_to_fit(&mut self) {
+        if mem::size_of::<T>() == 0 || self.cap == self.len {
+            return;
+        }
+
+        if self.len == 0 {
+            // SAFETY: The capacity is non-zero, so the buffer was allocated with
+            // this layout.
+            unsafe { EAllocator.dealloc(self.ptr.as_ptr().cast(), Self::layout(self.cap)) };
+            self.ptr = NonNull::dangling();
+            self.cap = 0;
+        } else {
+            self.grow_to(self.len);
+        }
+    }
+
+    /// Appends an element to the back of the vector.
+    ///
+    /// # Parameters
+    ///
+    /// * `value` - The element to append.
+    pub fn push(&mut self, value: T) {
+        if self.len == self.cap {
+            self.reserve(1);
+        }
+
+        // SAFETY: There is spare capacity for at least one element.
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+    }
+
+    /// Removes the last element of the vector and returns it, or [`None`] if
+    /// the vector is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+
+        self.len -= 1;
+        // SAFETY: The element was initialized and is no longer part of the vector.
+        Some(unsafe { self.ptr.as_ptr().add(self.len).read() })
+    }
+
+    /// Shortens the vector to `len` elements, dropping the rest. Has no effect
+    /// if the vector is already shorter.
+    ///
+    /// # Parameters
+    ///
+    /// * `len` - The new length of the vector.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len {
+            return;
+        }
+
+        let tail = ptr::slice_from_raw_parts_mut(
+            // SAFETY: `len` is within the initialized elements.
+            unsafe { self.ptr.as_ptr().add(len) },
+            self.len - len,
+        );
+        self.len = len;
+        // SAFETY: The elements were initialized and are no longer part of the
+        // vector.
+        unsafe { ptr::drop_in_place(tail) };
+    }
+
+    /// Removes all elements from the vector, keeping its capacity.
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Returns the spare capacity of the vector, which can be filled before
+    /// calling [`EVec::set_len`], e.g. to read data directly into the vector.
+    pub fn spare_capacity_mut(&mut self) -> &mut [MaybeUninit<T>] {
+        // SAFETY: The buffer has space for `cap` elements, of which the first `len`
+        // are initialized.
+        unsafe {
+            slice::from_raw_parts_mut(self.ptr.as_ptr().add(self.len).cast(), self.cap - self.len)
+        }
+    }
+
+    /// Sets the length of the vector.
+    ///
+    /// # Parameters
+    ///
+    /// * `len` - The new length of the vector.
+    ///
+    /// # Safety
+    ///
+    /// `len` must be less than or equal to the capacity, and the elements up
+    /// to `len` must be initialized.
+    pub unsafe fn set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    /// Returns the elements of the vector as a slice.
+    pub fn as_slice(&self) -> &[T] {
+        self
+    }
+
+    /// Returns the elements of the vector as a mutable slice.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        self
+    }
+
+    fn layout(cap: usize) -> Layout {
+        Layout::array::<T>(cap).expect("capacity overflow")
+    }
+
+    /// Reallocates the buffer to hold exactly `cap` elements, which must not
+    /// be less than the length.
+    fn grow_to(&mut self, cap: usize) {
+        if mem::size_of::<T>() == 0 {
+            // Zero-sized types never need memory, and the capacity is already
+            // `usize::MAX`.
+            panic!("capacity overflow");
+        }
+
+        let layout = Self::layout(cap);
+        // SAFETY: The layout has a non-zero size, and the current buffer was
+        // allocated with the layout of the current capacity.
+        let ptr = unsafe {
+            if self.cap == 0 {
+                EAllocator.alloc(layout)
+            } else {
+                EAllocator.realloc(
+                    self.ptr.as_ptr().cast(),
+                    Self::layout(self.cap),
+                    layout.size(),
+                )
+            }
+        };
+
+        self.ptr = NonNull::new(ptr.cast()).unwrap_or_else(|| handle_alloc_error(layout));
+        self.cap = cap;
+    }
+}
+
+impl<T: Clone> EVec<T> {
+    /// Clones and appends all elements of a slice to the vector.
+    ///
+    /// # Parameters
+    ///
+    /// * `values` - The elements to append.
+    pub fn extend_from_slice(&mut self, values: &[T]) {
+        self.reserve(values.len());
+        for value in values {
+            self.push(value.clone());
+        }
+    }
+}
+
+impl<T> Drop for EVec<T> {
+    fn drop(&mut self) {
+        self.clear();
+        if mem::size_of::<T>() != 0 && self.cap != 0 {
+            // SAFETY: The buffer was allocated with the layout of the capacity.
+            unsafe { EAllocator.dealloc(self.ptr.as_ptr().cast(), Self::layout(self.cap)) };
+        }
+    }
+}
+
+impl<T> Default for EVec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Deref for EVec<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: The pointer is aligned and the first `len` elements are
+        // initialized.
+        unsafe { slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T> DerefMut for EVec<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: The pointer is aligned and the first `len` elements are
+        // initialized.
+        unsafe { slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Clone> Clone for EVec<T> {
+    fn clone(&self) -> Self {
+        let mut vec = Self::with_capacity(self.len);
+        vec.extend_from_slice(self);
+        vec
+    }
+}
+
+impl<T: Debug> Debug for EVec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T: PartialEq> PartialEq for EVec<T> {
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<T: Eq> Eq for EVec<T> {}
+
+impl<T> Extend<T> for EVec<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+impl<T> FromIterator<T> for EVec<T> {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+impl<T: IntoZval> IntoZval for EVec<T> {
+    const TYPE: DataType = DataType::Array;
+    const NULLABLE: bool = false;
+
+    fn set_zval(mut self, zv: &mut Zval, _: bool) -> Result<()> {
+        let mut ht = ZendHashTable::with_capacity(self.len.try_into().unwrap_or(u32::MAX));
+        let len = self.len;
+        // SAFETY: The elements are moved out of the vector, which no longer
+        // considers them part of it.
+        unsafe { self.set_len(0) };
+        for i in 0..len {
+            // SAFETY: The element at `i` is initialized and read exactly once.
+            ht.push(unsafe { self.ptr.as_ptr().add(i).read() })?;
+        }
+        zv.set_hashtable(ht);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_evec() {
+        Embed::run(|| {
+            let mut vec = EVec::new();
+            for i in 0..1000 {
+                vec.push(i);
+            }
+            assert_eq!(vec.len(), 1000);
+            assert!(vec.capacity() >= 1000);
+            assert_eq!(vec.pop(), Some(999));
+
+            vec.truncate(10);
+            vec.shrink_to_fit();
+            assert_eq!(vec.capacity(), 10);
+            assert_eq!(&*vec, &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+            let zval = vec.into_zval(false).expect("should convert to zval");
+            assert_eq!(zval.array().map(|arr| arr.len()), Some(10));
+        });
+    }
+}
@@ -0,0 +1,261 @@
+use std::ffi::c_void;
+
+use crate::ffi::{
+    zend_mm_get_custom_handlers, zend_mm_get_heap, zend_mm_heap, zend_mm_is_custom_heap,
+    zend_mm_set_custom_handlers, zend_mm_set_heap,
+};
+
+/// A heap of the Zend memory manager, from which request-bound memory is
+/// allocated.
+pub type ZendMmHeap = zend_mm_heap;
+
+/// A custom allocation function, replacing `emalloc`.
+#[cfg(not(php_debug))]
+pub type MallocHandler = unsafe extern "C" fn(size: usize) -> *mut c_void;
+/// A custom allocation function, replacing `emalloc`. Debug builds of PHP
+/// pass the source location of the allocation.
+#[cfg(php_debug)]
+pub type MallocHandler = unsafe extern "C" fn(
+    size: usize,
+    filename: *const std::ffi::c_char,
+    lineno: u32,
+    orig_filename: *const std::ffi::c_char,
+    orig_lineno: u32,
+) -> *mut c_void;
+
+/// A custom deallocation function, replacing `efree`.
+#[cfg(not(php_debug))]
+pub type FreeHandler = unsafe extern "C" fn(ptr: *mut c_void);
+/// A custom deallocation function, replacing `efree`. Debug builds of PHP
+/// pass the source location of the deallocation.
+#[cfg(php_debug)]
+pub type FreeHandler = unsafe extern "C" fn(
+    ptr: *mut c_void,
+    filename: *const std::ffi::c_char,
+    lineno: u32,
+    orig_filename: *const std::ffi::c_char,
+    orig_lineno: u32,
+);
+
+/// A custom reallocation function, replacing `erealloc`.
+#[cfg(not(php_debug))]
+pub type ReallocHandler = unsafe extern "C" fn(ptr: *mut c_void, size: usize) -> *mut c_void;
+/// A custom reallocation function, replacing `erealloc`. Debug builds of PHP
+/// pass the source location of the reallocation.
+#[cfg(php_debug)]
+pub type ReallocHandler = unsafe extern "C" fn(
+    ptr: *mut c_void,
+    size: usize,
+    filename: *const std::ffi::c_char,
+    lineno: u32,
+    orig_filename: *const std::ffi::c_char,
+    orig_lineno: u32,
+) -> *mut c_void;
+
+/// The custom handlers of a heap, which replace the allocator of the Zend
+/// memory manager, e.g. to allocate through jemalloc or to instrument
+/// allocations.
+///
+/// Either all handlers are set, or none of them are, in which case the heap
+/// uses the allocator of the Zend memory manager.
+#[derive(Debug, Clone, Copy)]
+pub struct CustomHandlers {
+    /// The function used to allocate memory.
+    pub malloc: Option<MallocHandler>,
+    /// The function used to free memory.
+    pub free: Option<FreeHandler>,
+    /// The function used to resize memory.
+    pub realloc: Option<ReallocHandler>,
+}
+
+impl CustomHandlers {
+    /// Creates a new set of custom handlers.
+    ///
+    /// # Parameters
+    ///
+    /// * `malloc` - The function used to allocate memory.
+    /// * `free` - The function used to free memory.
+    /// * `realloc` - The function used to resize memory.
+    pub fn new(malloc: MallocHandler, free: FreeHandler, realloc: ReallocHandler) -> Self {
+        Self {
+            malloc: Some(malloc),
+            free: Some(free),
+            realloc: Some(realloc),
+        }
+    }
+
+    /// Returns the handlers which disable custom allocation, restoring the
+    /// allocator of the Zend memory manager.
+    pub fn none() -> Self {
+        Self {
+            malloc: None,
+            free: None,
+            realloc: None,
+        }
+    }
+}
+
+impl Default for CustomHandlers {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl ZendMmHeap {
+    /// Returns the heap currently used by the Zend memory manager.
+    pub fn current() -> *mut Self {
+        unsafe { zend_mm_get_heap() }
+    }
+
+    /// Returns whether the heap uses custom handlers.
+    pub fn is_custom(&mut self) -> bool {
+        unsafe { zend_mm_is_custom_heap(self) }
+    }
+
+    /// Returns the custom handlers of the heap, which are all [`None`] if the
+    /// heap does not use custom handlers.
+    pub fn custom_handlers(&mut self) -> CustomHandlers {
+        let mut handlers = CustomHandlers::none();
+        unsafe {
+            zend_mm_get_custom_handlers(
+                self,
+                &mut handlers.malloc,
+                &mut handlers.free,
+                &mut handlers.realloc,
+            )
+        };
+        handlers
+    }
+}
+
+/// Makes the given heap the one used by the Zend memory manager, returning a
+/// guard which restores the previous heap when dropped.
+///
+/// Forget the guard with [`std::mem::forget`] to keep the heap installed
+/// permanently.
+///
+/// # Parameters
+///
+/// * `heap` - The heap to install.
+///
+/// # Safety
+///
+/// The heap must remain valid while it is installed. Memory allocated from
+/// one heap must not be freed while another heap is installed, so the heap
+/// should be swapped when no request-bound memory is live, e.g. during module
+/// startup or around a self-contained section of code.
+pub unsafe fn install_heap(heap: *mut ZendMmHeap) -> HeapGuard {
+    HeapGuard {
+        previous: zend_mm_set_heap(heap),
+    }
+}
+
+/// Restores the previously installed heap when dropped. Returned by
+/// [`install_heap`].
+#[must_use = "the previous heap is restored when the guard is dropped"]
+#[derive(Debug)]
+pub struct HeapGuard {
+    previous: *mut ZendMmHeap,
+}
+
+impl HeapGuard {
+    /// Returns the heap which is restored when the guard is dropped.
+    pub fn previous(&self) -> *mut ZendMmHeap {
+        self.previous
+    }
+
+    /// Restores the previous heap.
+    pub fn restore(self) {
+        drop(self);
+    }
+}
+
+impl Drop for HeapGuard {
+    fn drop(&mut self) {
+        unsafe { zend_mm_set_heap(self.previous) };
+    }
+}
+
+/// Sets the custom handlers of the current heap, returning a guard which
+/// restores the previous handlers when dropped.
+///
+/// Forget the guard with [`std::mem::forget`] to keep the handlers installed
+/// permanently.
+///
+/// # Parameters
+///
+/// * `handlers` - The handlers to install.
+///
+/// # Safety
+///
+/// Memory allocated through one set of handlers must not be freed through
+/// another, so the handlers should be swapped when no request-bound memory is
+/// live, e.g. during module startup. The handlers must behave like their
+/// `libc` counterparts and must not allocate through the Zend memory manager.
+pub unsafe fn set_custom_handlers(handlers: CustomHandlers) -> CustomHandlersGuard {
+    let heap = ZendMmHeap::current();
+    let previous = (*heap).custom_handlers();
+    zend_mm_set_custom_handlers(heap, handlers.malloc, handlers.free, handlers.realloc);
+    CustomHandlersGuard { heap, previous }
+}
+
+/// Restores the previous custom handlers of a heap when dropped. Returned by
+/// [`set_custom_handlers`].
+#[must_use = "the previous handlers are restored when the guard is dropped"]
+#[derive(Debug)]
+pub struct CustomHandlersGuard {
+    heap: *mut ZendMmHeap,
+    previous: CustomHandlers,
+}
+
+impl CustomHandlersGuard {
+    /// Returns the handlers which are restored when the guard is dropped.
+    pub fn previous(&self) -> CustomHandlers {
+        self.previous
+    }
+
+    /// Restores the previous handlers.
+    pub fn restore(self) {
+        drop(self);
+    }
+}
+
+impl Drop for CustomHandlersGuard {
+    fn drop(&mut self) {
+        let CustomHandlers {
+            malloc,
+            free,
+            realloc,
+        } = self.previous;
+        unsafe { zend_mm_set_custom_handlers(self.heap, malloc, free, realloc) };
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_heap() {
+        Embed::run(|| {
+            let heap = ZendMmHeap::current();
+            assert!(!heap.is_null());
+
+            let custom = unsafe { (*heap).is_custom() };
+            let handlers = unsafe { (*heap).custom_handlers() };
+            assert_eq!(handlers.malloc.is_some(), custom);
+
+            let guard = unsafe { set_custom_handlers(handlers) };
+            assert_eq!(guard.previous().malloc.is_some(), custom);
+            guard.restore();
+            assert_eq!(unsafe { (*heap).is_custom() }, custom);
+
+            let guard = unsafe { install_heap(heap) };
+            assert_eq!(guard.previous(), heap);
+            guard.restore();
+            assert_eq!(ZendMmHeap::current(), heap);
+        });
+    }
+}
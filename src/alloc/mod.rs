@@ -0,0 +1,551 @@
+//! Functions relating to the Zend Memory Manager, used to allocate
+//! request-bound memory.
+
+mod ecstring;
+mod estring;
+mod evec;
+mod heap;
+mod stats;
+#[cfg(feature = "alloc_tracking")]
+#[cfg_attr(docs, doc(cfg(feature = "alloc_tracking")))]
+pub mod tracking;
+
+pub use ecstring::ECString;
+pub use estring::EString;
+pub use evec::EVec;
+pub use heap::{
+    install_heap, set_custom_handlers, CustomHandlers, CustomHandlersGuard, FreeHandler, HeapGuard,
+    MallocHandler, ReallocHandler, ZendMmHeap,
+};
+#[cfg(php82)]
+pub use stats::reset_memory_peak_usage;
+pub use stats::{memory_peak_usage, memory_usage, MemoryStats};
+
+use crate::ffi::{
+    _ecalloc, _efree, _emalloc, _erealloc, _estrndup, ext_php_rs_pefree, ext_php_rs_pemalloc,
+    ZEND_MM_ALIGNMENT,
+};
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    ffi::c_void,
+    mem, ptr,
+};
+
+/// Uses the PHP memory allocator to allocate request-bound memory.
+///
+/// # Parameters
+///
+/// * `layout` - The layout of the requested memory.
+///
+/// # Returns
+///
+/// A pointer to the memory allocated.
+#[cfg_attr(feature = "alloc_tracking", track_caller)]
+pub fn emalloc(layout: Layout) -> *mut u8 {
+    // TODO account for alignment
+    let size = layout.size();
+
+    let ptr = (unsafe {
+        #[cfg(php_debug)]
+        {
+            _emalloc(size as _, std::ptr::null_mut(), 0, std::ptr::null_mut(), 0)
+        }
+        #[cfg(not(php_debug))]
+        {
+            _emalloc(size as _)
+        }
+    }) as *mut u8;
+
+    #[cfg(feature = "alloc_tracking")]
+    tracking::track(ptr, size, std::panic::Location::caller());
+    ptr
+}
+
+/// The size of the chunks the Zend memory manager reserves from the system.
+const ZEND_MM_CHUNK_SIZE: usize = 2 * 1024 * 1024;
+/// The size of the pages chunks are divided into.
+const ZEND_MM_PAGE_SIZE: usize = 4 * 1024;
+
+/// Uses the PHP memory allocator to allocate request-bound memory, returning
+/// [`None`] instead of raising a fatal error if the allocation would exceed
+/// the `memory_limit` INI setting.
+///
+/// [`emalloc`] bails out of the current request when the memory limit is
+/// reached, unwinding through Rust frames with a `longjmp`. This function
+/// checks the limit beforehand so the error can be handled gracefully, for
+/// example by returning an exception. The check is conservative: allocations
+/// which may require the memory manager to reserve a new chunk from the
+/// system are refused if the chunk would not fit within the limit.
+///
+/// Failures to reserve memory from the system still abort the request.
+///
+/// # Parameters
+///
+/// * `layout` - The layout of the requested memory.
+///
+/// # Returns
+///
+/// A pointer to the memory allocated, or [`None`] if the memory limit would
+/// be exceeded.
+#[cfg_attr(feature = "alloc_tracking", track_caller)]
+pub fn try_emalloc(layout: Layout) -> Option<*mut u8> {
+    if allocation_fits(layout.size()) {
+        Some(emalloc(layout))
+    } else {
+        None
+    }
+}
+
+/// Returns whether allocating `size` bytes keeps the memory reserved by the
+/// memory manager within the memory limit.
+fn allocation_fits(size: usize) -> bool {
+    let Some(limit) = MemoryStats::capture().limit else {
+        return true;
+    };
+    let real_usage = memory_usage(true);
+
+    let reserved = if size > ZEND_MM_CHUNK_SIZE - ZEND_MM_PAGE_SIZE {
+        // Huge allocations are reserved from the system directly.
+        size.div_ceil(ZEND_MM_PAGE_SIZE) * ZEND_MM_PAGE_SIZE
+    } else if memory_usage(false).saturating_add(size) <= real_usage {
+        // The allocation may fit in the chunks already reserved.
+        0
+    } else {
+        ZEND_MM_CHUNK_SIZE
+    };
+
+    real_usage.saturating_add(reserved) <= limit
+}
+
+/// Frees a given memory pointer which was allocated through the PHP memory
+/// manager.
+///
+/// # Parameters
+///
+/// * `ptr` - The pointer to the memory to free.
+///
+/// # Safety
+///
+/// Caller must guarantee that the given pointer is valid (aligned and non-null)
+/// and was originally allocated through the Zend memory manager.
+pub unsafe fn efree(ptr: *mut u8) {
+    #[cfg(feature = "alloc_tracking")]
+    tracking::untrack(ptr);
+
+    #[cfg(php_debug)]
+    {
+        _efree(
+            ptr as *mut c_void,
+            std::ptr::null_mut(),
+            0,
+            std::ptr::null_mut(),
+            0,
+        )
+    }
+    #[cfg(not(php_debug))]
+    {
+        _efree(ptr as *mut c_void)
+    }
+}
+
+/// Uses the PHP memory allocator to allocate zero-initialized request-bound
+/// memory.
+///
+/// # Parameters
+///
+/// * `layout` - The layout of the requested memory.
+///
+/// # Returns
+///
+/// A pointer to the memory allocated.
+#[cfg_attr(feature = "alloc_tracking", track_caller)]
+pub fn ecalloc(layout: Layout) -> *mut u8 {
+    let size = layout.size();
+
+    let ptr = (unsafe {
+        #[cfg(php_debug)]
+        {
+            _ecalloc(
+                1,
+                size as _,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+                0,
+            )
+        }
+        #[cfg(not(php_debug))]
+        {
+            _ecalloc(1, size as _)
+        }
+    }) as *mut u8;
+
+    #[cfg(feature = "alloc_tracking")]
+    tracking::track(ptr, size, std::panic::Location::caller());
+    ptr
+}
+
+/// Resizes memory allocated through the PHP memory manager, preserving its
+/// contents up to the smaller of the old and new sizes. The memory may be
+/// moved, in which case the old pointer becomes invalid.
+///
+/// Like [`emalloc`], the Zend memory manager only guarantees an alignment of 8
+/// bytes. Memory with a greater alignment can only be resized if it was
+/// allocated through [`EAllocator`], in which case it is reallocated and
+/// copied.
+///
+/// # Parameters
+///
+/// * `ptr` - The pointer to the memory to resize.
+/// * `old_layout` - The layout the memory was allocated with.
+/// * `new_layout` - The layout of the resized memory.
+///
+/// # Returns
+///
+/// A pointer to the resized memory.
+///
+/// # Safety
+///
+/// Caller must guarantee that the given pointer is valid and was allocated
+/// through the Zend memory manager with `old_layout`.
+#[cfg_attr(feature = "alloc_tracking", track_caller)]
+pub unsafe fn erealloc(ptr: *mut u8, old_layout: Layout, new_layout: Layout) -> *mut u8 {
+    if old_layout.align() > ZEND_MM_ALIGNMENT as usize
+        || new_layout.align() > ZEND_MM_ALIGNMENT as usize
+    {
+        let new = EAllocator.alloc(new_layout);
+        if !new.is_null() {
+            ptr::copy_nonoverlapping(ptr, new, old_layout.size().min(new_layout.size()));
+            EAllocator.dealloc(ptr, old_layout);
+        }
+        return new;
+    }
+
+    let size = new_layout.size();
+    #[cfg(feature = "alloc_tracking")]
+    tracking::untrack(ptr);
+
+    let ptr = {
+        #[cfg(php_debug)]
+        {
+            _erealloc(
+                ptr as *mut c_void,
+                size as _,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+                0,
+            )
+        }
+        #[cfg(not(php_debug))]
+        {
+            _erealloc(ptr as *mut c_void, size as _)
+        }
+    } as *mut u8;
+
+    #[cfg(feature = "alloc_tracking")]
+    tracking::track(ptr, size, std::panic::Location::caller());
+    ptr
+}
+
+/// Duplicates a string using the PHP memory manager. Like the C function, the
+/// string is only copied up to the first NUL byte. Use [`estrndup`] to copy
+/// binary data.
+///
+/// # Parameters
+///
+/// * `string` - The string to duplicate.
+///
+/// # Returns
+///
+/// The duplicated string, which is freed when dropped unless released with
+/// [`ECString::into_raw`].
+#[cfg_attr(feature = "alloc_tracking", track_caller)]
+pub fn estrdup(string: impl AsRef<[u8]>) -> ECString {
+    let bytes = string.as_ref();
+    let len = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    estrndup(bytes, len)
+}
+
+/// Duplicates the first `len` bytes of a string using the PHP memory manager,
+/// appending a NUL byte. Unlike [`estrdup`], this is binary-safe: NUL bytes
+/// within the first `len` bytes are copied as well.
+///
+/// # Parameters
+///
+/// * `string` - The string to duplicate.
+/// * `len` - The number of bytes to copy, which is clamped to the length of
+///   the string.
+///
+/// # Returns
+///
+/// The duplicated string, which is freed when dropped unless released with
+/// [`ECString::into_raw`].
+#[cfg_attr(feature = "alloc_tracking", track_caller)]
+pub fn estrndup(string: impl AsRef<[u8]>, len: usize) -> ECString {
+    let bytes = string.as_ref();
+    let len = len.min(bytes.len());
+
+    let ptr = unsafe {
+        #[cfg(php_debug)]
+        {
+            _estrndup(
+                bytes.as_ptr().cast(),
+                len,
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+                0,
+            )
+        }
+        #[cfg(not(php_debug))]
+        {
+            _estrndup(bytes.as_ptr().cast(), len)
+        }
+    };
+    #[cfg(feature = "alloc_tracking")]
+    tracking::track(ptr.cast(), len + 1, std::panic::Location::caller());
+
+    // SAFETY: `_estrndup` returns a NUL-terminated copy of `len` bytes, and bails
+    // out instead of returning null if the allocation fails.
+    unsafe { ECString::from_raw(ptr, len) }
+}
+
+/// Uses the system allocator to allocate persistent memory, which is not
+/// released at the end of the request. Equivalent to `pemalloc(size, 1)`.
+///
+/// Memory which must outlive a request, such as data created during module
+/// startup, must be allocated with this function rather than [`emalloc`].
+///
+/// # Parameters
+///
+/// * `layout` - The layout of the requested memory.
+///
+/// # Returns
+///
+/// A pointer to the memory allocated.
+pub fn pmalloc(layout: Layout) -> *mut u8 {
+    unsafe { ext_php_rs_pemalloc(layout.size()) as *mut u8 }
+}
+
+/// Frees a given memory pointer which was allocated through [`pmalloc`].
+/// Equivalent to `pefree(ptr, 1)`.
+///
+/// # Parameters
+///
+/// * `ptr` - The pointer to the memory to free.
+///
+/// # Safety
+///
+/// Caller must guarantee that the given pointer is valid (aligned and non-null)
+/// and was originally allocated through [`pmalloc`].
+pub unsafe fn pfree(ptr: *mut u8) {
+    ext_php_rs_pefree(ptr as *mut c_void)
+}
+
+/// Allocates memory with the given allocation function, which only
+/// guarantees an alignment of [`ZEND_MM_ALIGNMENT`], over-allocating to align
+/// the pointer manually if required.
+unsafe fn alloc_aligned(layout: Layout, alloc: fn(Layout) -> *mut u8) -> *mut u8 {
+    if layout.align() <= ZEND_MM_ALIGNMENT as usize {
+        return alloc(layout);
+    }
+
+    // The pointer returned by the allocation function is stored right before the
+    // aligned pointer. As the allocation is at least 8-byte aligned, there is
+    // always room for it.
+    let Ok(padded) =
+        Layout::from_size_align(layout.size() + layout.align(), ZEND_MM_ALIGNMENT as usize)
+    else {
+        return ptr::null_mut();
+    };
+    let raw = alloc(padded);
+    if raw.is_null() {
+        return raw;
+    }
+    let aligned = raw.add(layout.align() - (raw as usize & (layout.align() - 1)));
+    aligned
+        .sub(mem::size_of::<*mut u8>())
+        .cast::<*mut u8>()
+        .write_unaligned(raw);
+    aligned
+}
+
+/// Frees memory allocated through [`alloc_aligned`] with the free function
+/// matching the allocation function.
+unsafe fn dealloc_aligned(ptr: *mut u8, layout: Layout, free: unsafe fn(*mut u8)) {
+    if layout.align() <= ZEND_MM_ALIGNMENT as usize {
+        free(ptr);
+    } else {
+        free(
+            ptr.sub(mem::size_of::<*mut u8>())
+                .cast::<*mut u8>()
+                .read_unaligned(),
+        );
+    }
+}
+
+/// An allocator backed by the Zend memory manager, allowing collections used
+/// strictly within a request to live in request-bound memory. Memory
+/// allocated through it is accounted for by `memory_get_usage()` and the
+/// `memory_limit` INI setting.
+///
+/// All memory allocated through the allocator is released by PHP at the end
+/// of the request, so values using it must not outlive the request they were
+/// created in. For the same reason, it must not be used as the
+/// `#[global_allocator]`.
+///
+/// With the `allocator_api` feature, which requires a nightly compiler, the
+/// allocator also implements [`std::alloc::Allocator`]:
+///
+/// ```ignore
+/// use ext_php_rs::alloc::EAllocator;
+///
+/// let mut values = Vec::new_in(EAllocator);
+/// values.push(1);
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct EAllocator;
+
+unsafe impl GlobalAlloc for EAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        alloc_aligned(layout, emalloc)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if layout.align() <= ZEND_MM_ALIGNMENT as usize {
+            return ecalloc(layout);
+        }
+
+        let ptr = self.alloc(layout);
+        if !ptr.is_null() {
+            ptr.write_bytes(0, layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        dealloc_aligned(ptr, layout, efree)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        erealloc(
+            ptr,
+            layout,
+            Layout::from_size_align_unchecked(new_size, layout.align()),
+        )
+    }
+}
+
+/// An allocator for persistent memory, which is not released at the end of
+/// the request, allocated through [`pmalloc`]. This is the allocator used by
+/// [`PBox`].
+///
+/// With the `allocator_api` feature, the allocator also implements
+/// [`std::alloc::Allocator`].
+///
+/// [`PBox`]: crate::boxed::PBox
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PAllocator;
+
+unsafe impl GlobalAlloc for PAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        alloc_aligned(layout, pmalloc)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        dealloc_aligned(ptr, layout, pfree)
+    }
+}
+
+/// Implements [`std::alloc::Allocator`] for an allocator implementing
+/// [`GlobalAlloc`].
+#[cfg(feature = "allocator_api")]
+macro_rules! impl_allocator {
+    ($allocator: ty) => {
+        unsafe impl std::alloc::Allocator for $allocator {
+            fn allocate(
+                &self,
+                layout: Layout,
+            ) -> Result<ptr::NonNull<[u8]>, std::alloc::AllocError> {
+                let ptr = if layout.size() == 0 {
+                    // Zero-sized allocations are not backed by memory, but must
+                    // still be aligned.
+                    ptr::without_provenance_mut(layout.align())
+                } else {
+                    unsafe { self.alloc(layout) }
+                };
+
+                ptr::NonNull::new(ptr)
+                    .map(|ptr| ptr::NonNull::slice_from_raw_parts(ptr, layout.size()))
+                    .ok_or(std::alloc::AllocError)
+            }
+
+            unsafe fn deallocate(&self, ptr: ptr::NonNull<u8>, layout: Layout) {
+                if layout.size() != 0 {
+                    self.dealloc(ptr.as_ptr(), layout);
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "allocator_api")]
+impl_allocator!(EAllocator);
+#[cfg(feature = "allocator_api")]
+impl_allocator!(PAllocator);
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_eallocator_alignment() {
+        Embed::run(|| {
+            for align in [1, 8, 16, 64, 4096] {
+                let layout = Layout::from_size_align(24, align).expect("should create layout");
+                unsafe {
+                    let ptr = EAllocator.alloc(layout);
+                    assert!(!ptr.is_null());
+                    assert_eq!(ptr as usize % align, 0);
+                    ptr.write_bytes(0xAB, layout.size());
+                    EAllocator.dealloc(ptr, layout);
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn test_try_emalloc() {
+        Embed::run(|| {
+            let layout = Layout::from_size_align(64, 8).expect("should create layout");
+            let ptr = try_emalloc(layout).expect("should allocate memory");
+            unsafe { efree(ptr) };
+
+            let limit = MemoryStats::capture()
+                .limit
+                .expect("should have a memory limit");
+            let layout = Layout::from_size_align(limit, 8).expect("should create layout");
+            assert!(try_emalloc(layout).is_none());
+        });
+    }
+
+    #[test]
+    fn test_ecalloc_erealloc() {
+        Embed::run(|| {
+            let layout = Layout::array::<u32>(4).expect("should create layout");
+            let ptr = ecalloc(layout).cast::<u32>();
+            unsafe {
+                assert_eq!(std::slice::from_raw_parts(ptr, 4), [0; 4]);
+                ptr.write(42);
+
+                let new_layout = Layout::array::<u32>(1024).expect("should create layout");
+                let ptr = erealloc(ptr.cast(), layout, new_layout).cast::<u32>();
+                assert_eq!(ptr.read(), 42);
+                efree(ptr.cast());
+            }
+        });
+    }
+}
@@ -0,0 +1,84 @@
+use crate::{
+    ffi::{zend_memory_peak_usage, zend_memory_usage},
+    zend::ProcessGlobals,
+};
+
+/// Returns the amount of memory currently allocated through the Zend memory
+/// manager, equivalent to the userland `memory_get_usage()` function.
+///
+/// # Parameters
+///
+/// * `real_usage` - Whether to return the memory reserved from the system,
+///   including unused pages, instead of the memory used by allocations.
+pub fn memory_usage(real_usage: bool) -> usize {
+    unsafe { zend_memory_usage(real_usage) }
+}
+
+/// Returns the peak amount of memory allocated through the Zend memory
+/// manager, equivalent to the userland `memory_get_peak_usage()` function.
+///
+/// # Parameters
+///
+/// * `real_usage` - Whether to return the peak memory reserved from the
+///   system instead of the peak memory used by allocations.
+pub fn memory_peak_usage(real_usage: bool) -> usize {
+    unsafe { zend_memory_peak_usage(real_usage) }
+}
+
+/// Resets the peak memory usage, equivalent to the userland
+/// `memory_reset_peak_usage()` function.
+#[cfg(php82)]
+pub fn reset_memory_peak_usage() {
+    unsafe { crate::ffi::zend_memory_reset_peak_usage() }
+}
+
+/// A snapshot of the memory usage of the Zend memory manager, e.g. to track
+/// the memory used by a request from a profiling hook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// The memory used by allocations.
+    pub usage: usize,
+    /// The memory reserved from the system.
+    pub real_usage: usize,
+    /// The peak memory used by allocations.
+    pub peak_usage: usize,
+    /// The peak memory reserved from the system.
+    pub real_peak_usage: usize,
+    /// The value of the `memory_limit` INI setting, or [`None`] if there is
+    /// no limit.
+    pub limit: Option<usize>,
+}
+
+impl MemoryStats {
+    /// Captures the current memory usage.
+    pub fn capture() -> Self {
+        let limit = ProcessGlobals::get().memory_limit;
+        Self {
+            usage: memory_usage(false),
+            real_usage: memory_usage(true),
+            peak_usage: memory_peak_usage(false),
+            real_peak_usage: memory_peak_usage(true),
+            limit: usize::try_from(limit).ok(),
+        }
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{alloc::EVec, embed::Embed};
+
+    #[test]
+    fn test_memory_stats() {
+        Embed::run(|| {
+            let before = MemoryStats::capture();
+            let buffer = EVec::<u8>::with_capacity(1024 * 1024);
+            let after = MemoryStats::capture();
+
+            assert!(after.usage >= before.usage + buffer.capacity());
+            assert!(after.peak_usage >= after.usage);
+            assert!(after.real_usage >= after.usage);
+        });
+    }
+}
@@ -0,0 +1,169 @@
+//! Tracking of the request-bound allocations performed through the crate, to
+//! find leaks in extension code.
+//!
+//! With the `alloc_tracking` feature, every allocation made through
+//! [`emalloc`], [`ecalloc`], [`erealloc`] and [`estrndup`] is recorded along
+//! with the location of its Rust caller, and removed when freed through
+//! [`efree`]. Allocations which are still live at the end of the request are
+//! reported on standard error when the request shuts down, after PHP has
+//! released all of its values.
+//!
+//! A backtrace is captured for each allocation when backtraces are enabled
+//! through the `RUST_BACKTRACE` or `RUST_LIB_BACKTRACE` environment
+//! variables. Capturing backtraces is slow, so they should only be enabled
+//! while hunting a leak.
+//!
+//! Memory whose ownership is transferred to PHP, which frees it with its own
+//! `efree`, must be released from tracking with [`untrack`] to avoid being
+//! reported.
+//!
+//! [`emalloc`]: super::emalloc
+//! [`ecalloc`]: super::ecalloc
+//! [`erealloc`]: super::erealloc
+//! [`estrndup`]: super::estrndup
+//! [`efree`]: super::efree
+
+use std::{
+    backtrace::{Backtrace, BacktraceStatus},
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{self, Display},
+    panic::Location,
+};
+
+/// A request-bound allocation which has not been freed.
+#[derive(Debug)]
+pub struct Allocation {
+    /// The address of the allocation.
+    pub address: usize,
+    /// The size of the allocation in bytes.
+    pub size: usize,
+    /// The location of the Rust code which performed the allocation.
+    pub location: &'static Location<'static>,
+    /// The backtrace of the allocation, if backtraces are enabled.
+    pub backtrace: Backtrace,
+}
+
+impl Display for Allocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} bytes at {:#x} allocated at {}",
+            self.size, self.address, self.location
+        )?;
+        if self.backtrace.status() == BacktraceStatus::Captured {
+            write!(f, "\n{}", self.backtrace)?;
+        }
+        Ok(())
+    }
+}
+
+thread_local! {
+    static ALLOCATIONS: RefCell<HashMap<usize, Allocation>> = RefCell::new(HashMap::new());
+}
+
+/// Records an allocation performed at the given location.
+pub(crate) fn track(ptr: *mut u8, size: usize, location: &'static Location<'static>) {
+    if ptr.is_null() {
+        return;
+    }
+
+    let allocation = Allocation {
+        address: ptr as usize,
+        size,
+        location,
+        backtrace: Backtrace::capture(),
+    };
+    ALLOCATIONS.with(|allocations| {
+        allocations
+            .borrow_mut()
+            .insert(allocation.address, allocation)
+    });
+}
+
+/// Stops tracking an allocation, typically because its ownership was
+/// transferred to PHP, which will free it. Pointers which are not tracked are
+/// ignored.
+///
+/// # Parameters
+///
+/// * `ptr` - The pointer to the allocation.
+pub fn untrack(ptr: *const u8) {
+    ALLOCATIONS.with(|allocations| allocations.borrow_mut().remove(&(ptr as usize)));
+}
+
+/// Returns the number of allocations currently tracked on this thread.
+pub fn live_allocations() -> usize {
+    ALLOCATIONS.with(|allocations| allocations.borrow().len())
+}
+
+/// Removes and returns all allocations currently tracked on this thread,
+/// ordered by address.
+pub fn take_leaks() -> Vec<Allocation> {
+    let mut leaks: Vec<_> = ALLOCATIONS
+        .with(|allocations| allocations.take())
+        .into_values()
+        .collect();
+    leaks.sort_unstable_by_key(|allocation| allocation.address);
+    leaks
+}
+
+/// Reports all allocations currently tracked on this thread on standard
+/// error and stops tracking them. This is called at the end of each request,
+/// in the post deactivate hook of modules built with [`ModuleBuilder`].
+///
+/// # Returns
+///
+/// The number of leaked allocations.
+///
+/// [`ModuleBuilder`]: crate::builders::ModuleBuilder
+pub fn report_leaks() -> usize {
+    let leaks = take_leaks();
+    if !leaks.is_empty() {
+        let total: usize = leaks.iter().map(|allocation| allocation.size).sum();
+        eprintln!(
+            "[ext-php-rs] {} allocation(s) totalling {} bytes were not freed:",
+            leaks.len(),
+            total
+        );
+        for leak in &leaks {
+            eprintln!("  {leak}");
+        }
+    }
+    leaks.len()
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        alloc::{efree, emalloc, estrdup},
+        embed::Embed,
+    };
+    use std::alloc::Layout;
+
+    #[test]
+    fn test_tracking() {
+        Embed::run(|| {
+            take_leaks();
+
+            let layout = Layout::from_size_align(16, 8).expect("should create layout");
+            let freed = emalloc(layout);
+            let leaked = emalloc(layout);
+            let line = line!() - 1;
+            unsafe { efree(freed) };
+            drop(estrdup("foo"));
+
+            let leaks = take_leaks();
+            assert_eq!(leaks.len(), 1);
+            assert_eq!(leaks[0].address, leaked as usize);
+            assert_eq!(leaks[0].size, 16);
+            assert_eq!(leaks[0].location.file(), file!());
+            assert_eq!(leaks[0].location.line(), line);
+            assert_eq!(live_allocations(), 0);
+
+            unsafe { efree(leaked) };
+        });
+    }
+}
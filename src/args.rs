@@ -192,6 +192,7 @@ impl From<Arg<'_>> for Parameter {
             ty: Some(val._type).into(),
             nullable: val.allow_null,
             default: val.default_value.map(abi::RString::from).into(),
+            variadic: val.variadic,
         }
     }
 }
@@ -0,0 +1,112 @@
+//! Helpers to measure the binding layer with
+//! [`criterion`](https://docs.rs/criterion), from benchmarks running inside a
+//! request of an embedded PHP.
+//!
+//! [`in_request`] starts PHP once with the module of the extension and runs
+//! the benchmarks inside a request, so conversions, function calls and
+//! scripts are measured without the cost of starting PHP. [`bench_eval`] and
+//! [`bench_call`] measure PHP code and function calls, to be compared with
+//! native Rust code measured in the same group.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ext_php_rs::bench::{bench_call, bench_eval, criterion::Criterion, in_request};
+//! # extern "C" fn get_module() -> *mut ext_php_rs::zend::ModuleEntry { unimplemented!() }
+//!
+//! fn calls(c: &mut Criterion) {
+//!     in_request(get_module, || {
+//!         let mut group = c.benchmark_group("add");
+//!         group.bench_function("rust", |b| b.iter(|| 1 + 2));
+//!         bench_eval(&mut group, "php", "1 + 2");
+//!         bench_call(&mut group, "extension", "my_add", &[&1, &2]);
+//!         group.finish();
+//!     });
+//! }
+//! ```
+
+pub use criterion;
+
+use criterion::{measurement::Measurement, BenchmarkGroup};
+
+use crate::{
+    convert::IntoZvalDyn,
+    embed::{run_test, Embed, GetModule},
+    types::ZendCallable,
+};
+
+/// Runs benchmarks inside a request of PHP, started with the given module on
+/// first use and kept started for the following benchmarks.
+///
+/// # Parameters
+///
+/// * `module` - The function returning the module entry of the extension,
+///   generated by `#[php_module]` as `get_module`.
+/// * `benchmarks` - The benchmarks.
+///
+/// # Panics
+///
+/// Panics if PHP fails to start or bails out of the benchmarks, e.g. on a
+/// fatal error.
+pub fn in_request<R, F: FnOnce() -> R>(module: GetModule, benchmarks: F) -> R {
+    run_test(module, benchmarks)
+}
+
+/// Benchmarks a PHP expression, compiled once into a closure which is called
+/// on each iteration. Must be called inside [`in_request`].
+///
+/// # Parameters
+///
+/// * `group` - The group of the benchmark.
+/// * `id` - The name of the benchmark in the group.
+/// * `expression` - The PHP expression, e.g. `strlen('foo')`.
+///
+/// # Panics
+///
+/// Panics if the expression cannot be compiled, or if it throws.
+pub fn bench_eval<M: Measurement>(group: &mut BenchmarkGroup<'_, M>, id: &str, expression: &str) {
+    let closure = Embed::eval(&format!("fn () => {expression};"))
+        .unwrap_or_else(|e| panic!("Failed to compile `{expression}`: {e:?}"));
+    let callable = ZendCallable::new_owned(closure).expect("should return a closure");
+
+    group.bench_function(id, |b| {
+        b.iter(|| {
+            callable
+                .try_call(vec![])
+                .unwrap_or_else(|e| panic!("Failed to evaluate `{expression}`: {e:?}"))
+        })
+    });
+}
+
+/// Benchmarks calling a PHP function with arguments, e.g. a function of the
+/// extension. The arguments are converted into PHP values on each iteration,
+/// as when calling the function from Rust. Must be called inside
+/// [`in_request`].
+///
+/// # Parameters
+///
+/// * `group` - The group of the benchmark.
+/// * `id` - The name of the benchmark in the group.
+/// * `function` - The name of the function.
+/// * `args` - The arguments passed to the function.
+///
+/// # Panics
+///
+/// Panics if the function does not exist, or if it throws.
+pub fn bench_call<M: Measurement>(
+    group: &mut BenchmarkGroup<'_, M>,
+    id: &str,
+    function: &str,
+    args: &[&dyn IntoZvalDyn],
+) {
+    let callable = ZendCallable::try_from_name(function)
+        .unwrap_or_else(|e| panic!("Failed to find the function {function}: {e:?}"));
+
+    group.bench_function(id, |b| {
+        b.iter(|| {
+            callable
+                .try_call(args.to_vec())
+                .unwrap_or_else(|e| panic!("Failed to call {function}: {e:?}"))
+        })
+    });
+}
@@ -17,11 +17,15 @@
 //! on the downstream type. For example, [`ZendStr`] has a function `new` which
 //! returns a [`ZBox<ZendStr>`].
 //!
+//! Values which must outlive the request can be stored in a [`PBox`] instead,
+//! which uses persistent memory.
+//!
 //! [memory arenas]: https://en.wikipedia.org/wiki/Region-based_memory_management
 //! [`ZendStr`]: crate::types::ZendStr
 //! [`emalloc`]: super::alloc::efree
 
 use std::{
+    alloc::{handle_alloc_error, GlobalAlloc, Layout},
     borrow::Borrow,
     fmt::Debug,
     mem::ManuallyDrop,
@@ -29,7 +33,7 @@ use std::{
     ptr::NonNull,
 };
 
-use super::alloc::efree;
+use super::alloc::{efree, PAllocator};
 
 /// A pointer type for heap allocation using the Zend memory manager.
 ///
@@ -136,3 +140,157 @@ pub unsafe trait ZBoxable {
         unsafe { efree(self as *mut _ as *mut u8) };
     }
 }
+
+/// A pointer type for values allocated in persistent memory through
+/// [`pmalloc`], which is not released at the end of the request.
+///
+/// Values created during module startup and kept for the lifetime of the
+/// module, such as caches shared between requests, should be stored in a
+/// [`PBox`] rather than in request-bound memory.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ext_php_rs::boxed::PBox;
+///
+/// let names = PBox::new(vec!["foo", "bar"]);
+/// assert_eq!(names.len(), 2);
+/// ```
+///
+/// [`pmalloc`]: crate::alloc::pmalloc
+pub struct PBox<T>(NonNull<T>);
+
+impl<T> PBox<T> {
+    /// Allocates persistent memory and places `value` into it.
+    ///
+    /// # Parameters
+    ///
+    /// * `value` - The value to store in the box.
+    pub fn new(value: T) -> Self {
+        let layout = Layout::new::<T>();
+        let ptr = if layout.size() == 0 {
+            NonNull::dangling()
+        } else {
+            // SAFETY: The layout has a non-zero size.
+            NonNull::new(unsafe { PAllocator.alloc(layout) }.cast::<T>())
+                .unwrap_or_else(|| handle_alloc_error(layout))
+        };
+
+        // SAFETY: The pointer is valid for writes and properly aligned.
+        unsafe { ptr.as_ptr().write(value) };
+        Self(ptr)
+    }
+
+    /// Creates a box from a pointer previously returned by
+    /// [`PBox::into_raw`].
+    ///
+    /// # Parameters
+    ///
+    /// * `ptr` - The pointer returned by [`PBox::into_raw`].
+    ///
+    /// # Safety
+    ///
+    /// Caller must ensure that `ptr` was returned by [`PBox::into_raw`] and
+    /// that the box has not been recreated already.
+    pub unsafe fn from_raw(ptr: *mut T) -> Self {
+        Self(NonNull::new_unchecked(ptr))
+    }
+
+    /// Returns the pointer contained by the box, dropping the box in the
+    /// process. The value is neither dropped nor freed, so the box should be
+    /// recreated with [`PBox::from_raw`] to release it.
+    pub fn into_raw(self) -> *mut T {
+        ManuallyDrop::new(self).0.as_ptr()
+    }
+
+    /// Consumes the box, returning a reference valid for the rest of the
+    /// lifetime of the process.
+    pub fn leak(self) -> &'static mut T {
+        // SAFETY: All constructors ensure the contained pointer is well-aligned and
+        // dereferenceable, and the memory is never freed.
+        unsafe { &mut *self.into_raw() }
+    }
+}
+
+impl<T> Drop for PBox<T> {
+    fn drop(&mut self) {
+        let layout = Layout::new::<T>();
+        // SAFETY: The box owns the value, which was allocated with `layout`.
+        unsafe {
+            self.0.as_ptr().drop_in_place();
+            if layout.size() != 0 {
+                PAllocator.dealloc(self.0.as_ptr().cast(), layout);
+            }
+        }
+    }
+}
+
+impl<T> Deref for PBox<T> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: All constructors ensure the contained pointer is well-aligned and
+        // dereferenceable.
+        unsafe { self.0.as_ref() }
+    }
+}
+
+impl<T> DerefMut for PBox<T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: All constructors ensure the contained pointer is well-aligned and
+        // dereferenceable.
+        unsafe { self.0.as_mut() }
+    }
+}
+
+impl<T: Debug> Debug for PBox<T> {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        (**self).fmt(f)
+    }
+}
+
+impl<T> Borrow<T> for PBox<T> {
+    #[inline]
+    fn borrow(&self) -> &T {
+        self
+    }
+}
+
+impl<T> AsRef<T> for PBox<T> {
+    #[inline]
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+// SAFETY: The box uniquely owns its value, like `Box<T>`.
+unsafe impl<T: Send> Send for PBox<T> {}
+// SAFETY: The box uniquely owns its value, like `Box<T>`.
+unsafe impl<T: Sync> Sync for PBox<T> {}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_pbox() {
+        Embed::run(|| {
+            let value = Rc::new(());
+            let boxed = PBox::new(Rc::clone(&value));
+            assert_eq!(Rc::strong_count(&value), 2);
+
+            let boxed = unsafe { PBox::from_raw(boxed.into_raw()) };
+            drop(boxed);
+            assert_eq!(Rc::strong_count(&value), 1);
+
+            let aligned = PBox::new(0u128);
+            assert_eq!(aligned.as_ref() as *const u128 as usize % 16, 0);
+        });
+    }
+}
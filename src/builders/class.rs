@@ -1,4 +1,4 @@
-use std::{ffi::CString, mem::MaybeUninit, rc::Rc};
+use std::{ffi::CString, mem::MaybeUninit, ptr, rc::Rc};
 
 use crate::{
     builders::FunctionBuilder,
@@ -6,12 +6,12 @@ use crate::{
     convert::{IntoZval, IntoZvalDyn},
     describe::DocComments,
     error::{Error, Result},
-    exception::PhpException,
+    exception::{catch_panic, PhpException},
     ffi::{
         zend_declare_class_constant, zend_declare_property, zend_do_implement_interface,
         zend_register_internal_class_ex,
     },
-    flags::{ClassFlags, MethodFlags, PropertyFlags},
+    flags::{ClassFlags, DataType, ErrorType, MethodFlags, PropertyFlags},
     types::{ZendClassObject, ZendObject, ZendStr, Zval},
     zend::{ClassEntry, ExecuteData, FunctionEntry},
     zend_fastcall,
@@ -19,15 +19,24 @@ use crate::{
 
 type ConstantEntry = (String, Box<dyn FnOnce() -> Result<Zval>>, DocComments);
 
+/// A property added to a [`ClassBuilder`].
+pub(crate) struct PropertyEntry {
+    pub(crate) name: String,
+    pub(crate) flags: PropertyFlags,
+    pub(crate) ty: Option<DataType>,
+    pub(crate) nullable: bool,
+    pub(crate) docs: DocComments,
+}
+
 /// Builder for registering a class in PHP.
 pub struct ClassBuilder {
     pub(crate) name: String,
-    ce: ClassEntry,
-    extends: Option<&'static ClassEntry>,
-    interfaces: Vec<&'static ClassEntry>,
+    pub(crate) ce: ClassEntry,
+    pub(crate) extends: Option<&'static ClassEntry>,
+    pub(crate) interfaces: Vec<&'static ClassEntry>,
     pub(crate) methods: Vec<(FunctionBuilder<'static>, MethodFlags)>,
     object_override: Option<unsafe extern "C" fn(class_type: *mut ClassEntry) -> *mut ZendObject>,
-    pub(crate) properties: Vec<(String, PropertyFlags, DocComments)>,
+    pub(crate) properties: Vec<PropertyEntry>,
     pub(crate) constants: Vec<ConstantEntry>,
     register: Option<fn(&'static mut ClassEntry)>,
     pub(crate) docs: DocComments,
@@ -117,7 +126,42 @@ impl ClassBuilder {
         flags: PropertyFlags,
         docs: DocComments,
     ) -> Self {
-        self.properties.push((name.into(), flags, docs));
+        self.properties.push(PropertyEntry {
+            name: name.into(),
+            flags,
+            ty: None,
+            nullable: false,
+            docs,
+        });
+        self
+    }
+
+    /// Adds a property to the class, along with the type of its values. The
+    /// type is not enforced by PHP, but is used in the stubs generated by
+    /// `cargo php stubs`.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the property to add to the class.
+    /// * `flags` - Flags relating to the property. See [`PropertyFlags`].
+    /// * `ty` - The type of the values of the property.
+    /// * `nullable` - Whether the property may be `null`.
+    /// * `docs` - Documentation comments for the property.
+    pub fn typed_property<T: Into<String>>(
+        mut self,
+        name: T,
+        flags: PropertyFlags,
+        ty: DataType,
+        nullable: bool,
+        docs: DocComments,
+    ) -> Self {
+        self.properties.push(PropertyEntry {
+            name: name.into(),
+            flags,
+            ty: Some(ty),
+            nullable,
+            docs,
+        });
         self
     }
 
@@ -191,43 +235,60 @@ impl ClassBuilder {
     /// class name specified when creating the builder.
     pub fn object_override<T: RegisteredClass>(mut self) -> Self {
         extern "C" fn create_object<T: RegisteredClass>(ce: *mut ClassEntry) -> *mut ZendObject {
-            // SAFETY: After calling this function, PHP will always call the constructor
-            // defined below, which assumes that the object is uninitialized.
-            let obj = unsafe { ZendClassObject::<T>::new_uninit(ce.as_ref()) };
-            obj.into_raw().get_mut_zend_obj()
+            let obj = catch_panic(|| {
+                // SAFETY: After calling this function, PHP will always call the constructor
+                // defined below, which assumes that the object is uninitialized.
+                let obj = unsafe { ZendClassObject::<T>::new_uninit(ce.as_ref()) };
+                ptr::from_mut(obj.into_raw().get_mut_zend_obj())
+            });
+            match obj {
+                Ok(obj) => obj,
+                Err(e) => {
+                    // No object can be returned to the engine, so the request is
+                    // stopped with a fatal error instead.
+                    let _ = crate::zend::engine_error(ErrorType::Error, e.message());
+                    ptr::null_mut()
+                }
+            }
         }
 
         zend_fastcall! {
             extern fn constructor<T: RegisteredClass>(ex: &mut ExecuteData, _: &mut Zval) {
-                let ConstructorMeta { constructor, .. } = match T::constructor() {
-                    Some(c) => c,
-                    None => {
-                        PhpException::default("You cannot instantiate this class from PHP.".into())
-                            .throw()
-                            .expect("Failed to throw exception when constructing class");
-                        return;
-                    }
-                };
+                let panic = catch_panic(|| {
+                    let ConstructorMeta { constructor, .. } = match T::constructor() {
+                        Some(c) => c,
+                        None => {
+                            PhpException::default("You cannot instantiate this class from PHP.".into())
+                                .throw()
+                                .expect("Failed to throw exception when constructing class");
+                            return;
+                        }
+                    };
+
+                    let this = match constructor(ex) {
+                        ConstructorResult::Ok(this) => this,
+                        ConstructorResult::Exception(e) => {
+                            e.throw()
+                                .expect("Failed to throw exception while constructing class");
+                            return;
+                        }
+                        ConstructorResult::ArgError => return,
+                    };
+                    let this_obj = match ex.get_object::<T>() {
+                        Some(obj) => obj,
+                        None => {
+                            PhpException::default("Failed to retrieve reference to `this` object.".into())
+                                .throw()
+                                .expect("Failed to throw exception while constructing class");
+                            return;
+                        }
+                    };
+                    this_obj.initialize(this);
+                });
 
-                let this = match constructor(ex) {
-                    ConstructorResult::Ok(this) => this,
-                    ConstructorResult::Exception(e) => {
-                        e.throw()
-                            .expect("Failed to throw exception while constructing class");
-                        return;
-                    }
-                    ConstructorResult::ArgError => return,
-                };
-                let this_obj = match ex.get_object::<T>() {
-                    Some(obj) => obj,
-                    None => {
-                        PhpException::default("Failed to retrieve reference to `this` object.".into())
-                            .throw()
-                            .expect("Failed to throw exception while constructing class");
-                        return;
-                    }
-                };
-                this_obj.initialize(this);
+                if let Err(e) = panic {
+                    e.throw().expect("Failed to throw PHP exception.");
+                }
             }
         }
 
@@ -327,7 +388,7 @@ impl ClassBuilder {
             };
         }
 
-        for (name, flags, _) in self.properties {
+        for PropertyEntry { name, flags, .. } in self.properties {
             unsafe {
                 zend_declare_property(
                     class,
@@ -0,0 +1,145 @@
+use super::{ClassBuilder, FunctionBuilder};
+use crate::{
+    convert::IntoZval,
+    describe::DocComments,
+    error::{Error, Result},
+    flags::MethodFlags,
+    zend::{ce, ClassEntry},
+};
+
+/// Builder for registering an exception class in PHP.
+///
+/// Exception classes extend [`Exception`] by default, and inherit the
+/// constructor as well as the `message`, `code`, `file` and `line` properties
+/// from it, so no object override or property declarations are required.
+///
+/// Classes declared with the [`php_exception_class!`] macro are registered
+/// through [`ModuleBuilder::exception_class`]. The builder can also be used
+/// directly from a module startup function:
+///
+/// ```no_run
+/// use ext_php_rs::{builders::exception_class_builder, zend::ce};
+///
+/// exception_class_builder("App\\TimeoutException")
+///     .extends(ce::runtime_exception())
+///     .register()
+///     .expect("failed to register exception class");
+/// ```
+///
+/// [`Exception`]: https://www.php.net/manual/en/class.exception.php
+/// [`php_exception_class!`]: crate::php_exception_class
+/// [`ModuleBuilder::exception_class`]: super::ModuleBuilder::exception_class
+pub struct ExceptionClassBuilder {
+    builder: ClassBuilder,
+    extends: Option<&'static ClassEntry>,
+}
+
+impl ExceptionClassBuilder {
+    /// Creates a new exception class builder.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the class, including its namespace.
+    pub fn new<T: Into<String>>(name: T) -> Self {
+        Self {
+            builder: ClassBuilder::new(name).registration(|_| {}),
+            extends: None,
+        }
+    }
+
+    /// Sets the parent class of the exception. Defaults to `Exception`.
+    ///
+    /// # Parameters
+    ///
+    /// * `parent` - The parent class, which must implement `Throwable`.
+    pub fn extends(mut self, parent: &'static ClassEntry) -> Self {
+        self.extends = Some(parent);
+        self
+    }
+
+    /// Implements an interface on the exception class.
+    ///
+    /// # Parameters
+    ///
+    /// * `interface` - Interface to implement on the class.
+    pub fn implements(mut self, interface: &'static ClassEntry) -> Self {
+        self.builder = self.builder.implements(interface);
+        self
+    }
+
+    /// Adds a method to the exception class.
+    ///
+    /// # Parameters
+    ///
+    /// * `func` - The function entry to add to the class.
+    /// * `flags` - Flags relating to the function. See [`MethodFlags`].
+    pub fn method(mut self, func: FunctionBuilder<'static>, flags: MethodFlags) -> Self {
+        self.builder = self.builder.method(func, flags);
+        self
+    }
+
+    /// Adds a constant to the exception class.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the constant to add to the class.
+    /// * `value` - The value of the constant.
+    /// * `docs` - Documentation comments for the constant.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] if the value could not be converted into a zval.
+    pub fn constant<T: Into<String>>(
+        mut self,
+        name: T,
+        value: impl IntoZval + 'static,
+        docs: DocComments,
+    ) -> Result<Self> {
+        self.builder = self.builder.constant(name, value, docs)?;
+        Ok(self)
+    }
+
+    /// Sets the documentation for the exception class.
+    ///
+    /// # Parameters
+    ///
+    /// * `docs` - The documentation comments for the class.
+    pub fn docs(mut self, docs: DocComments) -> Self {
+        self.builder = self.builder.docs(docs);
+        self
+    }
+
+    /// Returns the [`ClassBuilder`] for the exception class.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidException`] if the parent class does not
+    /// implement `Throwable`.
+    pub fn build(self) -> Result<ClassBuilder> {
+        let parent = self.extends.unwrap_or_else(ce::exception);
+        if !parent.instance_of(ce::throwable()) {
+            return Err(Error::InvalidException(parent.flags()));
+        }
+
+        Ok(self.builder.extends(parent))
+    }
+
+    /// Builds and registers the exception class.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`Error`] variant if the parent class does not implement
+    /// `Throwable` or the class could not be registered.
+    pub fn register(self) -> Result<()> {
+        self.build()?.register()
+    }
+}
+
+/// Creates a new [`ExceptionClassBuilder`] for the given class name.
+///
+/// # Parameters
+///
+/// * `name` - The name of the class, including its namespace.
+pub fn exception_class_builder<T: Into<String>>(name: T) -> ExceptionClassBuilder {
+    ExceptionClassBuilder::new(name)
+}
@@ -1,14 +1,33 @@
-use std::ffi::{c_char, CStr, CString};
-use crate::ffi::{
-    php_ini_builder,
-    php_ini_builder_prepend,
-    php_ini_builder_unquoted,
-    php_ini_builder_quoted,
-    php_ini_builder_define,
-};
-
-/// A builder for creating INI configurations.
-pub type IniBuilder = php_ini_builder;
+use std::ffi::{c_char, CString};
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A builder for creating INI configurations, e.g. the INI entries of a SAPI
+/// set with [`SapiBuilder::ini_entries`](crate::builders::SapiBuilder::ini_entries).
+///
+/// The configuration is built as text, in the format of php.ini files, so
+/// defaults, overrides and fragments of php.ini files can be merged, and the
+/// resulting entries inspected with [`IniBuilder::entries`] and
+/// [`IniBuilder::get`].
+#[derive(Debug, Clone, Default)]
+pub struct IniBuilder {
+    value: String,
+    finished: Option<CString>,
+}
+
+/// An entry of an INI configuration, returned by [`IniBuilder::entries`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IniEntry {
+    /// The section the entry is in, e.g. `PHP` for the entries following
+    /// `[PHP]`, or [`None`] before the first section.
+    pub section: Option<String>,
+    /// The name of the entry, e.g. `memory_limit` or `extension[]`.
+    pub name: String,
+    /// The value of the entry, without quotes or comments.
+    pub value: String,
+}
 
 impl IniBuilder {
     /// Creates a new INI builder.
@@ -20,17 +39,47 @@ impl IniBuilder {
     /// let mut builder = IniBuilder::new();
     /// ```
     pub fn new() -> IniBuilder {
-         IniBuilder {
-            value: std::ptr::null_mut(),
-            length: 0,
-        }
+        IniBuilder::default()
+    }
+
+    /// Creates an INI builder from the text of an INI configuration, e.g. a
+    /// fragment of a php.ini file.
+    ///
+    /// # Arguments
+    ///
+    /// * `ini` - The text of the configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ext_php_rs::builders::IniBuilder;
+    /// let builder = IniBuilder::parse("[PHP]\nmemory_limit = 256M ; per request\n");
+    /// assert_eq!(builder.get("memory_limit").as_deref(), Some("256M"));
+    /// ```
+    pub fn parse<S: AsRef<str>>(ini: S) -> IniBuilder {
+        let mut builder = IniBuilder::new();
+        builder.append(ini);
+        builder
+    }
+
+    /// Creates an INI builder from an INI file, e.g. a php.ini file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file could not be read, or is not valid UTF-8.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> io::Result<IniBuilder> {
+        fs::read_to_string(path).map(IniBuilder::parse)
     }
 
-    /// Appends a value to the INI builder.
+    /// Prepends a value to the INI builder, on its own line.
     ///
     /// # Arguments
     ///
-    /// * `value` - The value to append.
+    /// * `value` - The value to prepend.
     ///
     /// # Examples
     ///
@@ -40,10 +89,34 @@ impl IniBuilder {
     /// builder.prepend("foo=bar");
     /// ```
     pub fn prepend<V: AsRef<str>>(&mut self, value: V) {
-        let value = value.as_ref();
-        let c_value = CString::new(value).unwrap();
-        unsafe {
-            php_ini_builder_prepend(self, c_value.into_raw(), value.len());
+        self.value.insert(0, '\n');
+        self.value.insert_str(0, value.as_ref());
+    }
+
+    /// Appends the text of an INI configuration to the INI builder, e.g. a
+    /// fragment of a php.ini file overriding the entries set before.
+    ///
+    /// # Arguments
+    ///
+    /// * `ini` - The text of the configuration.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ext_php_rs::builders::IniBuilder;
+    /// let mut builder = IniBuilder::new();
+    /// builder.unquoted("memory_limit", "128M");
+    /// builder.append("memory_limit = 256M");
+    /// assert_eq!(builder.get("memory_limit").as_deref(), Some("256M"));
+    /// ```
+    pub fn append<S: AsRef<str>>(&mut self, ini: S) {
+        let ini = ini.as_ref();
+        if ini.is_empty() {
+            return;
+        }
+        self.value.push_str(ini);
+        if !ini.ends_with('\n') {
+            self.value.push('\n');
         }
     }
 
@@ -66,13 +139,7 @@ impl IniBuilder {
         N: AsRef<str>,
         V: AsRef<str>,
     {
-        let name = name.as_ref();
-        let value = value.as_ref();
-        let c_name = CString::new(name).unwrap();
-        let c_value = CString::new(value).unwrap();
-        unsafe {
-            php_ini_builder_unquoted(self, c_name.into_raw(), name.len(), c_value.into_raw(), value.len());
-        }
+        self.append(format!("{}={}", name.as_ref(), value.as_ref()));
     }
 
     /// Appends a quoted name-value pair to the INI builder.
@@ -94,16 +161,12 @@ impl IniBuilder {
         N: AsRef<str>,
         V: AsRef<str>,
     {
-        let name = name.as_ref();
-        let value = value.as_ref();
-        let c_name = CString::new(name).unwrap();
-        let c_value = CString::new(value).unwrap();
-        unsafe {
-            php_ini_builder_quoted(self, c_name.into_raw(), name.len(), c_value.into_raw(), value.len());
-        }
+        self.append(format!("{}=\"{}\"", name.as_ref(), value.as_ref()));
     }
 
-    /// Defines a value in the INI builder.
+    /// Defines a value in the INI builder, like the `-d` option of the CLI:
+    /// `name=value` sets the value, which is quoted unless it starts with an
+    /// alphanumeric character or a quote, and `name` sets the value to `1`.
     ///
     /// # Arguments
     ///
@@ -117,27 +180,180 @@ impl IniBuilder {
     /// builder.define("foo=bar");
     /// ```
     pub fn define<V: AsRef<str>>(&mut self, value: V) {
-        let value = value.as_ref();
-        let c_value = CString::new(value).unwrap();
-        unsafe {
-            php_ini_builder_define(self, c_value.into_raw());
+        match value.as_ref().split_once('=') {
+            Some((name, value)) => match value.chars().next() {
+                Some(c) if !c.is_ascii_alphanumeric() && c != '"' && c != '\'' => {
+                    self.quoted(name, value);
+                }
+                _ => self.unquoted(name, value),
+            },
+            None => self.unquoted(value, "1"),
+        }
+    }
+
+    /// Starts a section of the INI builder, e.g. `PHP` or
+    /// `HOST=example.com`. The entries appended afterwards are in the
+    /// section.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the section.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ext_php_rs::builders::IniBuilder;
+    /// let mut builder = IniBuilder::new();
+    /// builder.section("PATH=/var/www/app");
+    /// builder.unquoted("display_errors", "1");
+    /// assert_eq!(builder.entries()[0].section.as_deref(), Some("PATH=/var/www/app"));
+    /// ```
+    pub fn section<N: AsRef<str>>(&mut self, name: N) {
+        self.append(format!("[{}]", name.as_ref()));
+    }
+
+    /// Returns the entries set in the INI builder, in order. An entry may be
+    /// set several times, the last one being the one PHP uses.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use ext_php_rs::builders::IniBuilder;
+    /// let mut builder = IniBuilder::new();
+    /// builder.quoted("error_log", "/var/log/php.log");
+    /// builder.define("display_errors");
+    ///
+    /// let entries = builder.entries();
+    /// assert_eq!(entries[0].value, "/var/log/php.log");
+    /// assert_eq!(entries[1].name, "display_errors");
+    /// ```
+    pub fn entries(&self) -> Vec<IniEntry> {
+        let mut section = None;
+        let mut entries = vec![];
+        for line in self.value.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[') {
+                section = Some(name.strip_suffix(']').unwrap_or(name).trim().to_string());
+                continue;
+            }
+            let (name, value) = line.split_once('=').unwrap_or((line, ""));
+            entries.push(IniEntry {
+                section: section.clone(),
+                name: name.trim().to_string(),
+                value: parse_value(value.trim()),
+            });
         }
+        entries
+    }
+
+    /// Returns the value of an entry set in the INI builder, i.e. the last
+    /// value it was set to, in any section.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the entry.
+    pub fn get(&self, name: &str) -> Option<String> {
+        self.entries()
+            .into_iter()
+            .rev()
+            .find(|entry| entry.name == name)
+            .map(|entry| entry.value)
     }
 
     /// Finishes building the INI configuration.
     ///
+    /// Returns a null pointer if nothing was set. The pointer is owned by the
+    /// builder, and is valid until it is modified or dropped. Finishing an
+    /// unmodified builder again returns the same pointer.
+    ///
     /// # Examples
     ///
     /// ```
     /// # use ext_php_rs::builders::IniBuilder;
     /// let mut builder = IniBuilder::new();
-    /// let ini = builder.finish();
+    /// builder.unquoted("foo", "bar");
+    /// assert_eq!(builder.finish(), builder.finish());
     /// ```
     pub fn finish(&mut self) -> *mut c_char {
-        if self.value.is_null() {
-          return std::ptr::null_mut();
+        if self.value.is_empty() {
+            self.finished = None;
+            return std::ptr::null_mut();
+        }
+
+        let value = self.c_value();
+        let finished = match self.finished.take() {
+            Some(finished) if finished.as_bytes() == value.as_bytes() => finished,
+            _ => value,
+        };
+        self.finished.insert(finished).as_ptr() as *mut c_char
+    }
+
+    /// Finishes building the INI configuration, and transfers its ownership
+    /// to the caller, e.g. to set the `ini_entries` of a SAPI module.
+    ///
+    /// Returns a null pointer if nothing was set. The configuration must stay
+    /// valid while PHP may read it, i.e. from the startup of the SAPI until
+    /// its shutdown. It can then be freed with [`CString::from_raw`], or
+    /// leaked for the lifetime of the process.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use std::ffi::CString;
+    /// # use ext_php_rs::builders::IniBuilder;
+    /// let ini = IniBuilder::parse("memory_limit = 256M").into_raw_ini();
+    ///
+    /// // After the SAPI was shut down:
+    /// drop(unsafe { CString::from_raw(ini) });
+    /// ```
+    pub fn into_raw_ini(self) -> *mut c_char {
+        if self.value.is_empty() {
+            return std::ptr::null_mut();
         }
+        self.c_value().into_raw()
+    }
 
-        unsafe { CStr::from_ptr(self.value) }.as_ptr() as *mut c_char
+    /// Returns the INI configuration as a C string.
+    fn c_value(&self) -> CString {
+        // INI configurations cannot contain NUL bytes, which end the text.
+        let value = self.value.split('\0').next().unwrap_or_default();
+        CString::new(value).unwrap_or_default()
+    }
+}
+
+/// Formats the INI configuration built so far, in the format of php.ini files.
+///
+/// # Examples
+///
+/// ```
+/// # use ext_php_rs::builders::IniBuilder;
+/// let mut builder = IniBuilder::new();
+/// builder.section("PHP");
+/// builder.unquoted("precision", "10");
+/// assert_eq!(builder.to_string(), "[PHP]\nprecision=10\n");
+/// ```
+impl Display for IniBuilder {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.value)
+    }
+}
+
+/// Parses the value of an INI entry, removing its quotes, or its trailing
+/// comment if it is not quoted.
+fn parse_value(value: &str) -> String {
+    for quote in ['"', '\''] {
+        if let Some(quoted) = value.strip_prefix(quote) {
+            if let Some(end) = quoted.find(quote) {
+                return quoted[..end].to_string();
+            }
+        }
     }
+    value
+        .split_once(';')
+        .map_or(value, |(value, _)| value)
+        .trim_end()
+        .to_string()
 }
@@ -2,13 +2,19 @@
 //! Generally zero-cost abstractions.
 
 mod class;
+mod exception;
 mod function;
 mod ini;
 mod module;
+mod request_info;
 mod sapi;
 
 pub use class::ClassBuilder;
+pub(crate) use class::PropertyEntry;
+pub use exception::{exception_class_builder, ExceptionClassBuilder};
 pub use function::FunctionBuilder;
-pub use ini::IniBuilder;
+pub use ini::{IniBuilder, IniEntry};
 pub use module::{ModuleBuilder, ModuleStartup};
+pub(crate) use request_info::RequestInfo;
+pub use request_info::RequestInfoBuilder;
 pub use sapi::SapiBuilder;
@@ -1,12 +1,16 @@
 use std::{convert::TryFrom, ffi::CString, mem, ptr};
 
+use parking_lot::{const_mutex, const_rwlock, Mutex, RwLock};
+
 use super::{ClassBuilder, FunctionBuilder};
 use crate::{
     class::RegisteredClass,
     constant::IntoConst,
     describe::DocComments,
     error::Result,
-    ffi::{ext_php_rs_php_build_id, ZEND_MODULE_API_NO},
+    exception::ExceptionClass,
+    ffi::{ext_php_rs_php_build_id, module_registry, zend_hash_str_find, ZEND_MODULE_API_NO},
+    self_check,
     zend::{FunctionEntry, ModuleEntry},
     PHP_DEBUG, PHP_ZTS,
 };
@@ -170,7 +174,7 @@ impl ModuleBuilder<'_> {
     /// Adds a class to the extension.
     pub fn class<T: RegisteredClass>(mut self) -> Self {
         self.classes.push(|| {
-            let mut builder = ClassBuilder::new(T::CLASS_NAME);
+            let mut builder = ClassBuilder::new(T::CLASS_NAME).flags(T::FLAGS);
             for (method, flags) in T::method_builders() {
                 builder = builder.method(method, flags);
             }
@@ -186,7 +190,16 @@ impl ModuleBuilder<'_> {
                     .expect("Failed to register constant");
             }
             for (name, prop_info) in T::get_properties() {
-                builder = builder.property(name, prop_info.flags, prop_info.docs);
+                builder = match prop_info.ty {
+                    Some(ty) => builder.typed_property(
+                        name,
+                        prop_info.flags,
+                        ty,
+                        prop_info.nullable,
+                        prop_info.docs,
+                    ),
+                    None => builder.property(name, prop_info.flags, prop_info.docs),
+                };
             }
             if let Some(modifier) = T::BUILDER_MODIFIER {
                 builder = modifier(builder);
@@ -201,6 +214,19 @@ impl ModuleBuilder<'_> {
         });
         self
     }
+
+    /// Adds an exception class to the extension. Exception classes are
+    /// usually declared with the [`php_exception_class!`] macro.
+    ///
+    /// [`php_exception_class!`]: crate::php_exception_class
+    pub fn exception_class<T: ExceptionClass>(mut self) -> Self {
+        self.classes.push(|| {
+            T::builder()
+                .build()
+                .expect("Failed to build exception class")
+        });
+        self
+    }
 }
 
 /// Artifacts from the [`ModuleBuilder`] that should be revisited inside the
@@ -218,7 +244,9 @@ impl ModuleStartup {
             val.register_constant(&name, mod_num)?;
         }
 
-        self.classes.into_iter().map(|c| c()).for_each(|c| {
+        let classes: Vec<_> = self.classes.into_iter().map(|c| c()).collect();
+        self_check::register_classes(classes.iter().map(Into::into).collect());
+        classes.into_iter().for_each(|c| {
             c.register().expect("Failed to build class");
         });
         Ok(())
@@ -231,12 +259,122 @@ pub type StartupShutdownFunc = unsafe extern "C" fn(_type: i32, _module_number:
 /// A function to be called when `phpinfo();` is called.
 pub type InfoFunc = unsafe extern "C" fn(zend_module: *mut ModuleEntry);
 
+/// The functions of a module called between the hooks of the crate.
+#[derive(Clone, Copy, Default)]
+struct ModuleFuncs {
+    startup: Option<StartupShutdownFunc>,
+    request_startup: Option<StartupShutdownFunc>,
+    request_shutdown: Option<StartupShutdownFunc>,
+    #[cfg(feature = "alloc_tracking")]
+    post_deactivate: Option<unsafe extern "C" fn() -> i32>,
+}
+
+/// The functions of the modules which were built but did not start up yet,
+/// by lowercase name, as module numbers are assigned when modules are
+/// registered.
+static PENDING_FUNCS: Mutex<Vec<(String, ModuleFuncs)>> = const_mutex(Vec::new());
+
+/// The functions of the modules which started up, by module number.
+static MODULE_FUNCS: RwLock<Vec<(i32, ModuleFuncs)>> = const_rwlock(Vec::new());
+
+/// Returns the functions of the module with the given number.
+fn module_funcs(module_number: i32) -> ModuleFuncs {
+    MODULE_FUNCS
+        .read()
+        .iter()
+        .find(|(number, _)| *number == module_number)
+        .map(|(_, funcs)| *funcs)
+        .unwrap_or_default()
+}
+
+/// Moves the functions of the module with the given number from the pending
+/// functions, looking up the numbers of the pending modules in the module
+/// registry.
+fn resolve_module_funcs(module_number: i32) -> ModuleFuncs {
+    let mut pending = PENDING_FUNCS.lock();
+    let Some(index) = pending.iter().position(|(name, _)| {
+        let entry = unsafe {
+            zend_hash_str_find(
+                ptr::addr_of!(module_registry),
+                name.as_ptr().cast(),
+                name.len(),
+            )
+        };
+        // SAFETY: The module registry stores pointers to module entries.
+        unsafe { entry.as_ref() }
+            .and_then(|entry| unsafe { entry.value.ptr.cast::<ModuleEntry>().as_ref() })
+            .is_some_and(|entry| entry.module_number == module_number)
+    }) else {
+        return ModuleFuncs::default();
+    };
+    let (_, funcs) = pending.swap_remove(index);
+    MODULE_FUNCS.write().push((module_number, funcs));
+    funcs
+}
+
+/// Associates the functions of the module with its number before calling its
+/// startup function.
+unsafe extern "C" fn module_startup(ty: i32, module_number: i32) -> i32 {
+    match resolve_module_funcs(module_number).startup {
+        Some(func) => func(ty, module_number),
+        None => 0,
+    }
+}
+
+/// Reports the allocations which were not freed once PHP has released all
+/// values of the request.
+///
+/// PHP does not tell which module is deactivated, so the post deactivate
+/// functions of all modules are called by the first module deactivated after
+/// each request.
+#[cfg(feature = "alloc_tracking")]
+unsafe extern "C" fn tracking_post_deactivate() -> i32 {
+    if !crate::internal::take_post_deactivate() {
+        return 0;
+    }
+    let funcs: Vec<_> = MODULE_FUNCS
+        .read()
+        .iter()
+        .filter_map(|(_, funcs)| funcs.post_deactivate)
+        .collect();
+    let mut result = 0;
+    for func in funcs {
+        if func() != 0 {
+            result = -1;
+        }
+    }
+    crate::alloc::tracking::report_leaks();
+    result
+}
+
+/// Runs the request startup hooks of the crate, e.g. opening the spans of the
+/// request, before calling the request startup function of the module.
+unsafe extern "C" fn request_startup(ty: i32, module_number: i32) -> i32 {
+    crate::internal::start_request();
+    match module_funcs(module_number).request_startup {
+        Some(func) => func(ty, module_number),
+        None => 0,
+    }
+}
+
+/// Runs the request shutdown hooks of the crate after calling the request
+/// shutdown function of the module.
+unsafe extern "C" fn request_shutdown(ty: i32, module_number: i32) -> i32 {
+    let result = match module_funcs(module_number).request_shutdown {
+        Some(func) => func(ty, module_number),
+        None => 0,
+    };
+    crate::internal::finish_request();
+    result
+}
+
 /// Builds a [`ModuleEntry`] and [`ModuleStartup`] from a [`ModuleBuilder`].
 /// This is the entry point for the module to be registered with PHP.
 impl TryFrom<ModuleBuilder<'_>> for (ModuleEntry, ModuleStartup) {
     type Error = crate::error::Error;
 
     fn try_from(builder: ModuleBuilder) -> Result<Self, Self::Error> {
+        self_check::register_functions(builder.functions.iter().map(|f| f.name.clone()).collect());
         let mut functions = builder
             .functions
             .into_iter()
@@ -245,6 +383,7 @@ impl TryFrom<ModuleBuilder<'_>> for (ModuleEntry, ModuleStartup) {
         functions.push(FunctionEntry::end());
         let functions = Box::into_raw(functions.into_boxed_slice()) as *const FunctionEntry;
 
+        let lc_name = builder.name.to_lowercase();
         let name = CString::new(builder.name)?.into_raw();
         let version = CString::new(builder.version)?.into_raw();
 
@@ -257,6 +396,17 @@ impl TryFrom<ModuleBuilder<'_>> for (ModuleEntry, ModuleStartup) {
             classes: builder.classes,
         };
 
+        PENDING_FUNCS.lock().push((
+            lc_name,
+            ModuleFuncs {
+                startup: builder.startup_func,
+                request_startup: builder.request_startup_func,
+                request_shutdown: builder.request_shutdown_func,
+                #[cfg(feature = "alloc_tracking")]
+                post_deactivate: builder.post_deactivate_func,
+            },
+        ));
+
         Ok((
             ModuleEntry {
                 size: mem::size_of::<ModuleEntry>() as u16,
@@ -267,10 +417,10 @@ impl TryFrom<ModuleBuilder<'_>> for (ModuleEntry, ModuleStartup) {
                 deps: ptr::null(),
                 name,
                 functions,
-                module_startup_func: builder.startup_func,
+                module_startup_func: Some(module_startup),
                 module_shutdown_func: builder.shutdown_func,
-                request_startup_func: builder.request_startup_func,
-                request_shutdown_func: builder.request_shutdown_func,
+                request_startup_func: Some(request_startup),
+                request_shutdown_func: Some(request_shutdown),
                 info_func: builder.info_func,
                 version,
                 globals_size: 0,
@@ -280,7 +430,10 @@ impl TryFrom<ModuleBuilder<'_>> for (ModuleEntry, ModuleStartup) {
                 globals_id_ptr: ptr::null_mut(),
                 globals_ctor: None,
                 globals_dtor: None,
+                #[cfg(not(feature = "alloc_tracking"))]
                 post_deactivate_func: builder.post_deactivate_func,
+                #[cfg(feature = "alloc_tracking")]
+                post_deactivate_func: Some(tracking_post_deactivate),
                 module_started: 0,
                 type_: 0,
                 handle: ptr::null_mut(),
@@ -0,0 +1,384 @@
+use std::{
+    ffi::{c_char, CString},
+    fmt::{self, Debug},
+    ptr,
+};
+
+use crate::{
+    alloc::{estrdup, ECString},
+    error::{Error, Result},
+    zend::SapiGlobals,
+};
+
+/// A builder for the request info of the SAPI, i.e. `SG(request_info)`, which
+/// a SAPI host must fill in before starting a request.
+///
+/// The request info is passed to [`RequestContext::start_with`], which
+/// installs it before starting the request, and frees its strings once the
+/// request is shut down, as PHP reads them until then.
+///
+/// [`RequestContext::start_with`]: crate::embed::RequestContext::start_with
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::{builders::RequestInfoBuilder, embed::RequestContext};
+///
+/// let info = RequestInfoBuilder::new()
+///     .request_method("POST")
+///     .request_uri("/users?notify=1")
+///     .query_string("notify=1")
+///     .content_type("application/json")
+///     .content_length(16)
+///     .path_translated("/var/www/app/index.php");
+/// let request = RequestContext::start_with(info).expect("should start the request");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RequestInfoBuilder {
+    request_method: Option<String>,
+    request_uri: Option<String>,
+    query_string: Option<String>,
+    content_type: Option<String>,
+    content_length: i64,
+    cookie_data: Option<String>,
+    path_translated: Option<String>,
+    auth: Option<(String, String)>,
+    headers_only: bool,
+    proto_num: Option<i32>,
+}
+
+impl RequestInfoBuilder {
+    /// Creates a builder of an empty request info, with no method, URI or
+    /// body.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the method of the request, e.g. `GET`.
+    ///
+    /// # Parameters
+    ///
+    /// * `method` - The method.
+    pub fn request_method(mut self, method: impl Into<String>) -> Self {
+        self.request_method = Some(method.into());
+        self
+    }
+
+    /// Sets the URI of the request, including the query string, e.g.
+    /// `/users?page=2`.
+    ///
+    /// # Parameters
+    ///
+    /// * `uri` - The URI.
+    pub fn request_uri(mut self, uri: impl Into<String>) -> Self {
+        self.request_uri = Some(uri.into());
+        self
+    }
+
+    /// Sets the query string of the request, without `?`, which PHP parses
+    /// into `$_GET`.
+    ///
+    /// # Parameters
+    ///
+    /// * `query_string` - The query string.
+    pub fn query_string(mut self, query_string: impl Into<String>) -> Self {
+        self.query_string = Some(query_string.into());
+        self
+    }
+
+    /// Sets the content type of the body of the request, which PHP uses to
+    /// parse it into `$_POST`.
+    ///
+    /// # Parameters
+    ///
+    /// * `content_type` - The content type, e.g. `application/json`.
+    pub fn content_type(mut self, content_type: impl Into<String>) -> Self {
+        self.content_type = Some(content_type.into());
+        self
+    }
+
+    /// Sets the length of the body of the request, in bytes.
+    ///
+    /// # Parameters
+    ///
+    /// * `length` - The length.
+    pub fn content_length(mut self, length: i64) -> Self {
+        self.content_length = length;
+        self
+    }
+
+    /// Sets the cookies of the request, as sent in the `Cookie` header, which
+    /// PHP parses into `$_COOKIE` unless the SAPI reads them itself.
+    ///
+    /// # Parameters
+    ///
+    /// * `cookies` - The cookies, e.g. `id=42; theme=dark`.
+    pub fn cookie_data(mut self, cookies: impl Into<String>) -> Self {
+        self.cookie_data = Some(cookies.into());
+        self
+    }
+
+    /// Sets the path of the script handling the request.
+    ///
+    /// # Parameters
+    ///
+    /// * `path` - The path.
+    pub fn path_translated(mut self, path: impl Into<String>) -> Self {
+        self.path_translated = Some(path.into());
+        self
+    }
+
+    /// Sets the credentials of the request, e.g. from a basic `Authorization`
+    /// header, which PHP exposes as `PHP_AUTH_USER` and `PHP_AUTH_PW`.
+    ///
+    /// # Parameters
+    ///
+    /// * `user` - The user.
+    /// * `password` - The password.
+    pub fn auth(mut self, user: impl Into<String>, password: impl Into<String>) -> Self {
+        self.auth = Some((user.into(), password.into()));
+        self
+    }
+
+    /// Sets whether the request only expects headers, e.g. a `HEAD` request,
+    /// so PHP discards the output.
+    ///
+    /// # Parameters
+    ///
+    /// * `headers_only` - Whether the request only expects headers.
+    pub fn headers_only(mut self, headers_only: bool) -> Self {
+        self.headers_only = headers_only;
+        self
+    }
+
+    /// Sets the version of the protocol of the request, which PHP uses in the
+    /// status line of the response.
+    ///
+    /// # Parameters
+    ///
+    /// * `proto_num` - The version, e.g. `1001` for HTTP/1.1.
+    pub fn proto_num(mut self, proto_num: i32) -> Self {
+        self.proto_num = Some(proto_num);
+        self
+    }
+
+    /// Writes the request info into the SAPI globals, replacing the current
+    /// one.
+    ///
+    /// The credentials are allocated through the Zend memory manager, as PHP
+    /// frees them when the request is shut down. They are freed by the
+    /// returned [`RequestInfo`] unless [`RequestInfo::request_started`] is
+    /// called.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidCString`] if a string contains a NUL byte.
+    pub(crate) fn install(self) -> Result<RequestInfo> {
+        let c_string = |string: Option<String>| string.map(CString::new).transpose();
+        let auth = match self.auth {
+            Some((user, password)) => {
+                if user.contains('\0') || password.contains('\0') {
+                    return Err(Error::InvalidCString);
+                }
+                Some((estrdup(user), estrdup(password)))
+            }
+            None => None,
+        };
+        let info = RequestInfo {
+            request_method: c_string(self.request_method)?,
+            request_uri: c_string(self.request_uri)?,
+            query_string: c_string(self.query_string)?,
+            content_type: c_string(self.content_type)?,
+            cookie_data: c_string(self.cookie_data)?,
+            path_translated: c_string(self.path_translated)?,
+            auth,
+        };
+
+        let mut globals = SapiGlobals::get_mut();
+        let request_info = &mut globals.request_info;
+        request_info.request_method = as_ptr(&info.request_method);
+        request_info.request_uri = as_ptr(&info.request_uri).cast_mut();
+        request_info.query_string = as_ptr(&info.query_string).cast_mut();
+        request_info.content_type = as_ptr(&info.content_type);
+        request_info.cookie_data = as_ptr(&info.cookie_data).cast_mut();
+        request_info.path_translated = as_ptr(&info.path_translated).cast_mut();
+        request_info.content_length = self.content_length;
+        request_info.headers_only = self.headers_only;
+        if let Some(proto_num) = self.proto_num {
+            request_info.proto_num = proto_num;
+        }
+        if let Some((user, password)) = &info.auth {
+            request_info.auth_user = user.as_ptr().cast_mut();
+            request_info.auth_password = password.as_ptr().cast_mut();
+        }
+        Ok(info)
+    }
+}
+
+/// The request info installed into the SAPI globals by
+/// [`RequestInfoBuilder::install`], which owns its strings.
+///
+/// Dropping it clears the fields of the request info which still point at its
+/// strings, and frees the strings. It must not be dropped before the request
+/// is shut down.
+pub(crate) struct RequestInfo {
+    request_method: Option<CString>,
+    request_uri: Option<CString>,
+    query_string: Option<CString>,
+    content_type: Option<CString>,
+    cookie_data: Option<CString>,
+    path_translated: Option<CString>,
+    // Owned until a request is started, as PHP frees them when the request
+    // is shut down.
+    auth: Option<(ECString, ECString)>,
+}
+
+impl RequestInfo {
+    /// Hands the credentials over to PHP once a request was started with the
+    /// request info, as PHP frees them when the request is shut down.
+    pub(crate) fn request_started(&mut self) {
+        if let Some((user, password)) = self.auth.take() {
+            user.into_raw();
+            password.into_raw();
+        }
+    }
+}
+
+impl Debug for RequestInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestInfo")
+            .field("request_method", &self.request_method)
+            .field("request_uri", &self.request_uri)
+            .field("query_string", &self.query_string)
+            .field("content_type", &self.content_type)
+            .field("path_translated", &self.path_translated)
+            // The cookies are omitted, as they may hold credentials.
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for RequestInfo {
+    fn drop(&mut self) {
+        let mut globals = SapiGlobals::get_mut();
+        let request_info = &mut globals.request_info;
+        // Another request info may have been installed since, which is left
+        // untouched.
+        let installed = [
+            clear(&mut request_info.request_method, &self.request_method),
+            clear(&mut request_info.content_type, &self.content_type),
+            clear_mut(&mut request_info.request_uri, &self.request_uri),
+            clear_mut(&mut request_info.query_string, &self.query_string),
+            clear_mut(&mut request_info.cookie_data, &self.cookie_data),
+            clear_mut(&mut request_info.path_translated, &self.path_translated),
+        ]
+        .contains(&true);
+        if installed {
+            request_info.content_length = 0;
+            request_info.headers_only = false;
+        }
+
+        // The credentials are only still owned if no request was started, and
+        // are freed when dropped.
+        if let Some((user, password)) = &self.auth {
+            if request_info.auth_user == user.as_ptr().cast_mut() {
+                request_info.auth_user = ptr::null_mut();
+            }
+            if request_info.auth_password == password.as_ptr().cast_mut() {
+                request_info.auth_password = ptr::null_mut();
+            }
+        }
+    }
+}
+
+fn as_ptr(string: &Option<CString>) -> *const c_char {
+    string
+        .as_ref()
+        .map_or(ptr::null(), |string| string.as_ptr())
+}
+
+/// Clears a field of the request info if it points at the given string,
+/// returning whether it did.
+fn clear(field: &mut *const c_char, string: &Option<CString>) -> bool {
+    let installed = string.is_some() && *field == as_ptr(string);
+    if installed {
+        *field = ptr::null();
+    }
+    installed
+}
+
+/// Same as [`clear`], for the fields PHP declares as mutable.
+fn clear_mut(field: &mut *mut c_char, string: &Option<CString>) -> bool {
+    let mut value = field.cast_const();
+    let installed = clear(&mut value, string);
+    *field = value.cast_mut();
+    installed
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_install() {
+        Embed::run(|| {
+            let info = RequestInfoBuilder::new()
+                .request_method("POST")
+                .request_uri("/users?notify=1")
+                .query_string("notify=1")
+                .content_type("application/json")
+                .content_length(16)
+                .cookie_data("id=42")
+                .install()
+                .expect("should install the request info");
+            {
+                let globals = SapiGlobals::get();
+                let request_info = globals.request_info();
+                assert_eq!(request_info.request_method(), Some("POST"));
+                assert_eq!(request_info.request_uri(), Some("/users?notify=1"));
+                assert_eq!(request_info.query_string(), Some("notify=1"));
+                assert_eq!(request_info.content_type(), Some("application/json"));
+                assert_eq!(request_info.content_length(), 16);
+                assert_eq!(request_info.cookie_data(), Some("id=42"));
+                assert_eq!(request_info.path_translated(), None);
+            }
+
+            drop(info);
+            let globals = SapiGlobals::get();
+            assert_eq!(globals.request_info().request_method(), None);
+            assert_eq!(globals.request_info().content_length(), 0);
+        });
+    }
+
+    #[test]
+    fn test_drop_replaced() {
+        Embed::run(|| {
+            let old = RequestInfoBuilder::new()
+                .request_method("GET")
+                .auth("user", "secret")
+                .install()
+                .expect("should install the request info");
+            let new = RequestInfoBuilder::new()
+                .request_method("POST")
+                .install()
+                .expect("should install the request info");
+
+            drop(old);
+            {
+                let globals = SapiGlobals::get();
+                let request_info = globals.request_info();
+                assert_eq!(request_info.request_method(), Some("POST"));
+                assert_eq!(request_info.auth_user(), None);
+            }
+            drop(new);
+            assert_eq!(SapiGlobals::get().request_info().request_method(), None);
+        });
+    }
+
+    #[test]
+    fn test_install_nul() {
+        let info = RequestInfoBuilder::new().request_uri("/\0").install();
+        assert!(matches!(info, Err(Error::InvalidCString)));
+    }
+}
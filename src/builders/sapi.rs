@@ -1,17 +1,23 @@
+use crate::builders::{FunctionBuilder, IniBuilder};
+use crate::exception::catch_panic_or;
 use crate::ffi::{
-    sapi_header_struct,
+    ext_php_rs_php_error, ext_php_rs_set_sapi_error_handler, gid_t, php_default_input_filter,
+    php_default_post_reader, php_default_treat_data, sapi_header_struct, sapi_headers_struct,
     uid_t,
-    gid_t,
-    php_default_post_reader,
-    php_default_treat_data,
-    php_default_input_filter,
-    ext_php_rs_php_error
 };
+use crate::flags::ErrorType;
 use crate::types::Zval;
+use crate::zend::FunctionEntry;
 use crate::{embed::SapiModule, error::Result};
 
-use std::ffi::{c_char, c_int, c_void};
-use std::{ffi::CString, ptr};
+use std::ffi::{c_char, c_int, c_uint, c_void};
+use std::{ffi::CString, ptr, slice};
+
+use parking_lot::{const_rwlock, RwLock};
+
+type SapiErrorHandler = Box<dyn Fn(ErrorType, &str) + Send + Sync>;
+
+static SAPI_ERROR_HANDLER: RwLock<Option<SapiErrorHandler>> = const_rwlock(None);
 
 /// Builds a Sapi module to run PHP.
 ///
@@ -42,7 +48,10 @@ pub struct SapiBuilder {
     name: String,
     pretty_name: String,
     module: SapiModule,
-    executable_location: Option<String>
+    executable_location: Option<String>,
+    functions: Vec<FunctionBuilder<'static>>,
+    ini_entries: Option<IniBuilder>,
+    sapi_error: Option<SapiErrorHandler>,
 }
 
 impl SapiBuilder {
@@ -79,7 +88,6 @@ impl SapiBuilder {
                 php_ini_path_override: ptr::null_mut(),
                 default_post_reader: Some(php_default_post_reader),
                 treat_data: Some(php_default_treat_data),
-                // treat_data: None,
                 executable_location: ptr::null_mut(),
                 php_ini_ignore: 0,
                 php_ini_ignore_cwd: 0,
@@ -88,14 +96,16 @@ impl SapiBuilder {
                 get_target_uid: None,
                 get_target_gid: None,
                 input_filter: Some(php_default_input_filter),
-                // input_filter: None,
                 ini_defaults: None,
                 phpinfo_as_text: 0,
                 ini_entries: ptr::null_mut(),
                 additional_functions: ptr::null(),
                 input_filter_init: None,
             },
-            executable_location: None
+            executable_location: None,
+            functions: vec![],
+            ini_entries: None,
+            sapi_error: None,
         }
     }
 
@@ -169,17 +179,39 @@ impl SapiBuilder {
         self
     }
 
-    /// Sets the sapi error function for this SAPI
+    /// Sets the sapi error function for this SAPI, e.g. to route the errors
+    /// of the SAPI layer, such as invalid headers, into the error pipeline of
+    /// the host. Without one, the errors are raised like `trigger_error()`.
+    ///
+    /// The `sapi_error` hook of PHP is variadic, so it is implemented in C,
+    /// which formats the message before calling the function. There is a
+    /// single function per process, replaced when the SAPI is built.
     ///
     /// # Parameters
     ///
-    /// * `func` - The function to be called when PHP encounters an error.
-    // pub fn sapi_error_function(mut self, func: SapiErrorFunc) -> Self {
-    //     self.module.sapi_error = Some(func);
-    //     self
-    // }
+    /// * `func` - The function to be called with the type and message of the
+    ///   error when PHP encounters an error.
+    pub fn sapi_error_function<F>(mut self, func: F) -> Self
+    where
+        F: Fn(ErrorType, &str) + Send + Sync + 'static,
+    {
+        self.sapi_error = Some(Box::new(func));
+        self
+    }
 
-    // TODO: Implement header_handler and send_headers
+    // TODO: Implement header_handler
+
+    /// Sets the send headers function for this SAPI
+    ///
+    /// # Parameters
+    ///
+    /// * `func` - The function to be called when PHP sends the headers,
+    ///   returning whether PHP should send them one by one to the send
+    ///   header function.
+    pub fn send_headers_function(mut self, func: SapiSendHeadersFunc) -> Self {
+        self.module.send_headers = Some(func);
+        self
+    }
 
     /// Sets the send header function for this SAPI
     ///
@@ -216,7 +248,10 @@ impl SapiBuilder {
     /// # Parameters
     ///
     /// * `func` - The function to be called when PHP registers server variables.
-    pub fn register_server_variables_function(mut self, func: SapiRegisterServerVariablesFunc) -> Self {
+    pub fn register_server_variables_function(
+        mut self,
+        func: SapiRegisterServerVariablesFunc,
+    ) -> Self {
         self.module.register_server_variables = Some(func);
         self
     }
@@ -271,6 +306,159 @@ impl SapiBuilder {
         self
     }
 
+    /// Sets the get fd function for this SAPI, which hands the socket of the
+    /// client connection owned by the host to PHP.
+    ///
+    /// The socket is usually found through the server context of the
+    /// request, set with
+    /// [`SapiGlobals::set_server_context`](crate::zend::SapiGlobals::set_server_context)
+    /// before the request starts up. Hosts keeping their connections in a
+    /// thread local can use
+    /// [`connection_fd`](crate::embed::connection_fd) instead.
+    ///
+    /// # Parameters
+    ///
+    /// * `func` - The function to be called when PHP gets the socket of the
+    ///   client connection, which stores it into its argument and returns 0,
+    ///   or returns -1 if there is none.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::ffi::{c_int, c_void};
+    /// use std::net::TcpStream;
+    /// use std::os::unix::io::AsRawFd;
+    ///
+    /// use ext_php_rs::{builders::SapiBuilder, zend::SapiGlobals};
+    ///
+    /// struct Client {
+    ///     stream: TcpStream,
+    /// }
+    ///
+    /// #[allow(clippy::not_unsafe_ptr_arg_deref)]
+    /// extern "C" fn get_fd(fd: *mut c_int) -> c_int {
+    ///     let client = SapiGlobals::get().server_context().cast::<Client>();
+    ///     match unsafe { (client.as_ref(), fd.as_mut()) } {
+    ///         (Some(client), Some(fd)) => {
+    ///             *fd = client.stream.as_raw_fd();
+    ///             0
+    ///         }
+    ///         _ => -1,
+    ///     }
+    /// }
+    ///
+    /// let sapi = SapiBuilder::new("my-server", "My Server")
+    ///     .get_fd_function(get_fd)
+    ///     .build();
+    ///
+    /// // For each request, before it starts up, and until it is shut down:
+    /// # let stream = TcpStream::connect("127.0.0.1:8080").unwrap();
+    /// let mut client = Client { stream };
+    /// SapiGlobals::get_mut().set_server_context((&mut client as *mut Client).cast::<c_void>());
+    /// ```
+    pub fn get_fd_function(mut self, func: SapiGetFdFunc) -> Self {
+        self.module.get_fd = Some(func);
+        self
+    }
+
+    /// Sets the default post reader function for this SAPI, replacing
+    /// `php_default_post_reader`, which reads the body of requests whose
+    /// content type is not handled by PHP into `php://input`.
+    ///
+    /// # Parameters
+    ///
+    /// * `func` - The function to be called when PHP reads the body of a
+    ///   request with an unknown content type.
+    pub fn default_post_reader_function(mut self, func: SapiDefaultPostReaderFunc) -> Self {
+        self.module.default_post_reader = Some(func);
+        self
+    }
+
+    /// Sets the treat data function for this SAPI, replacing
+    /// `php_default_treat_data`, which parses the query string, the cookies
+    /// and the form data into `$_GET`, `$_COOKIE` and `$_POST`.
+    ///
+    /// The default function can still be called for the data which should be
+    /// parsed, e.g. to skip parsing the cookies:
+    ///
+    /// ```rust,no_run
+    /// use std::ffi::{c_char, c_int};
+    ///
+    /// use ext_php_rs::{builders::SapiBuilder, ffi::php_default_treat_data, types::Zval};
+    ///
+    /// /// `PARSE_COOKIE` in `main/php_variables.h`.
+    /// const PARSE_COOKIE: c_int = 2;
+    ///
+    /// extern "C" fn treat_data(arg: c_int, str: *mut c_char, dest_array: *mut Zval) {
+    ///     if arg != PARSE_COOKIE {
+    ///         unsafe { php_default_treat_data(arg, str, dest_array) };
+    ///     }
+    /// }
+    ///
+    /// let sapi = SapiBuilder::new("my-server", "My Server")
+    ///     .treat_data_function(treat_data)
+    ///     .build();
+    /// ```
+    ///
+    /// # Parameters
+    ///
+    /// * `func` - The function to be called when PHP parses the data of a
+    ///   request into an array.
+    pub fn treat_data_function(mut self, func: SapiTreatDataFunc) -> Self {
+        self.module.treat_data = Some(func);
+        self
+    }
+
+    /// Sets the input filter function for this SAPI, replacing
+    /// `php_default_input_filter`, which leaves the values unchanged.
+    ///
+    /// # Parameters
+    ///
+    /// * `func` - The function to be called when PHP registers a variable
+    ///   from the data of a request. It may replace the value, and returns 0
+    ///   to skip the variable.
+    pub fn input_filter_function(mut self, func: SapiInputFilterFunc) -> Self {
+        self.module.input_filter = Some(func);
+        self
+    }
+
+    /// Sets the input filter init function for this SAPI
+    ///
+    /// # Parameters
+    ///
+    /// * `func` - The function to be called when PHP starts parsing the data
+    ///   of a request, before the input filter is called.
+    pub fn input_filter_init_function(mut self, func: SapiInputFilterInitFunc) -> Self {
+        self.module.input_filter_init = Some(func);
+        self
+    }
+
+    /// Adds a function to this SAPI, e.g. `apache_request_headers`, which is
+    /// only available to the scripts run by the SAPI.
+    ///
+    /// # Parameters
+    ///
+    /// * `func` - The function to be added.
+    pub fn function(mut self, func: FunctionBuilder<'static>) -> Self {
+        self.functions.push(func);
+        self
+    }
+
+    /// Adds functions to this SAPI, which are only available to the scripts
+    /// run by the SAPI. The functions are registered as the additional
+    /// functions of the SAPI when PHP starts up.
+    ///
+    /// # Parameters
+    ///
+    /// * `funcs` - The functions to be added.
+    pub fn functions<I>(mut self, funcs: I) -> Self
+    where
+        I: IntoIterator<Item = FunctionBuilder<'static>>,
+    {
+        self.functions.extend(funcs);
+        self
+    }
+
     /// Sets the php ini path override for this SAPI
     ///
     /// # Parameters
@@ -281,6 +469,34 @@ impl SapiBuilder {
         self
     }
 
+    /// Sets the INI entries of this SAPI, which override the entries of the
+    /// php.ini file, like the hard-coded entries of the CLI and FPM SAPIs.
+    ///
+    /// The entries are copied into a string owned by the built module, which
+    /// must live until PHP is shut down.
+    ///
+    /// # Parameters
+    ///
+    /// * `ini` - The builder of the entries.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ext_php_rs::builders::{IniBuilder, SapiBuilder};
+    ///
+    /// let mut ini = IniBuilder::new();
+    /// ini.unquoted("display_errors", "1");
+    /// ini.quoted("error_log", "/var/log/php.log");
+    ///
+    /// let sapi = SapiBuilder::new("my-server", "My Server")
+    ///     .ini_entries(ini)
+    ///     .build();
+    /// ```
+    pub fn ini_entries(mut self, ini: IniBuilder) -> Self {
+        self.ini_entries = Some(ini);
+        self
+    }
+
     /// Sets the php ini ignore for this SAPI
     ///
     /// # Parameters
@@ -319,23 +535,42 @@ impl SapiBuilder {
         self.module.pretty_name = CString::new(self.pretty_name)?.into_raw();
 
         self.module.executable_location = maybe_cstr(self.executable_location)?;
+        if let Some(ini) = self.ini_entries {
+            self.module.ini_entries = ini.into_raw_ini();
+        }
+
+        if !self.functions.is_empty() {
+            let mut functions = self
+                .functions
+                .into_iter()
+                .map(|f| f.build())
+                .collect::<Result<Vec<_>>>()?;
+            functions.push(FunctionEntry::end());
+            self.module.additional_functions =
+                Box::into_raw(functions.into_boxed_slice()) as *const FunctionEntry;
+        }
 
         if self.module.send_header.is_none() {
             self.module.send_header = Some(dummy_send_header);
         }
 
+        if let Some(handler) = self.sapi_error {
+            *SAPI_ERROR_HANDLER.write() = Some(handler);
+            unsafe { ext_php_rs_set_sapi_error_handler(Some(sapi_error_trampoline)) };
+        }
+
         Ok(self.module)
     }
 }
 
 fn maybe_cstr<T>(m: Option<T>) -> Result<*mut c_char>
 where
-  T: Into<Vec<u8>>
+    T: Into<Vec<u8>>,
 {
-  Ok(match m {
-    None => std::ptr::null_mut(),
-    Some(s) => CString::new(s)?.into_raw()
-  })
+    Ok(match m {
+        None => std::ptr::null_mut(),
+        Some(s) => CString::new(s)?.into_raw(),
+    })
 }
 
 /// A function to be called when PHP starts the SAPI
@@ -359,16 +594,15 @@ pub type SapiFlushFunc = extern "C" fn(*mut c_void);
 /// A function to be called when PHP gets an environment variable
 pub type SapiGetEnvFunc = extern "C" fn(name: *const c_char, name_length: usize) -> *mut c_char;
 
-/// A function to be called when PHP encounters an error
-/// TODO: Figure out variadic functions
-// pub type SapiErrorFunc = extern "C" fn(type: c_int, error_msg: *const c_char, args: va_list);
-
 /// A function to be called when PHP read the POST data
 pub type SapiReadPostFunc = extern "C" fn(buffer: *mut c_char, length: usize) -> usize;
 
 /// A function to be called when PHP read the cookies
 pub type SapiReadCookiesFunc = extern "C" fn() -> *mut c_char;
 
+/// A function to be called when PHP send the headers
+pub type SapiSendHeadersFunc = extern "C" fn(sapi_headers: *mut sapi_headers_struct) -> c_int;
+
 /// A function to be called when PHP send a header
 pub type SapiSendHeaderFunc =
     extern "C" fn(header: *mut sapi_header_struct, server_context: *mut c_void);
@@ -391,4 +625,40 @@ pub type SapiGetUidFunc = extern "C" fn(uid: *mut uid_t) -> c_int;
 /// A function to be called when PHP gets the gid
 pub type SapiGetGidFunc = extern "C" fn(gid: *mut gid_t) -> c_int;
 
+/// A function to be called when PHP gets the socket of the client connection
+pub type SapiGetFdFunc = extern "C" fn(fd: *mut c_int) -> c_int;
+
+/// A function to be called when PHP reads the body of a request with an
+/// unknown content type
+pub type SapiDefaultPostReaderFunc = extern "C" fn();
+
+/// A function to be called when PHP parses the data of a request into an
+/// array, `arg` being one of the `PARSE_*` constants of `main/php_variables.h`
+pub type SapiTreatDataFunc = extern "C" fn(arg: c_int, str: *mut c_char, dest_array: *mut Zval);
+
+/// A function to be called when PHP registers a variable from the data of a
+/// request
+pub type SapiInputFilterFunc = extern "C" fn(
+    arg: c_int,
+    var: *const c_char,
+    val: *mut *mut c_char,
+    val_len: usize,
+    new_val_len: *mut usize,
+) -> c_uint;
+
+/// A function to be called when PHP starts parsing the data of a request
+pub type SapiInputFilterInitFunc = extern "C" fn() -> c_uint;
+
 extern "C" fn dummy_send_header(_header: *mut sapi_header_struct, _server_context: *mut c_void) {}
+
+extern "C" fn sapi_error_trampoline(type_: c_int, message: *const c_char, len: usize) {
+    let message = unsafe { slice::from_raw_parts(message.cast::<u8>(), len) };
+    if let Some(handler) = SAPI_ERROR_HANDLER.read().as_ref() {
+        catch_panic_or((), || {
+            handler(
+                ErrorType::from_bits_truncate(type_ as u32),
+                &String::from_utf8_lossy(message),
+            );
+        });
+    }
+}
@@ -0,0 +1,235 @@
+//! Cooperative cancellation of the work of a request.
+//!
+//! A [`CancellationToken`] is tripped by the host from any thread with
+//! [`CancellationToken::cancel`], e.g. when the client disconnected, and
+//! polled by long running Rust functions with
+//! [`CancellationToken::is_cancelled`], which is a single atomic load, or
+//! with [`CancellationToken::check`].
+//!
+//! Once [bound](CancellationToken::bind) to the request of a thread,
+//! cancelling the token also interrupts the VM of that thread, through the
+//! interrupts of [`crate::zend::interrupt`], and throws an exception at the
+//! next function call or backward jump, so PHP loops stop as well. Functions
+//! called by the request find the token with [`CancellationToken::current`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ext_php_rs::{cancel::CancellationToken, prelude::*};
+//!
+//! #[php_function]
+//! pub fn count_primes(limit: u64) -> PhpResult<u64> {
+//!     let token = CancellationToken::current();
+//!     let mut count = 0;
+//!     for n in 2..limit {
+//!         if let Some(token) = &token {
+//!             token.check()?;
+//!         }
+//!         if (2..n).take_while(|d| d * d <= n).all(|d| n % d != 0) {
+//!             count += 1;
+//!         }
+//!     }
+//!     Ok(count)
+//! }
+//!
+//! // In the host, when a request starts:
+//! let token = CancellationToken::new();
+//! token.bind();
+//! // From another thread, e.g. when the client disconnected:
+//! token.cancel();
+//! ```
+
+use std::{
+    cell::RefCell,
+    fmt::{self, Debug},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Once,
+    },
+    thread::{self, ThreadId},
+};
+
+use parking_lot::Mutex;
+
+use crate::{
+    error::{Error, Result},
+    exception::PhpException,
+    zend::interrupt::{register_interrupt_handler, InterruptHandle},
+};
+
+static INTERRUPT_HANDLER: Once = Once::new();
+
+thread_local! {
+    /// The token bound to the request of this thread, and whether the
+    /// cancellation was already thrown into the request.
+    static BOUND: RefCell<Option<(CancellationToken, bool)>> = const { RefCell::new(None) };
+}
+
+/// A flag cancelling the work of requests, shared by the host and the
+/// requests.
+///
+/// The token can be cloned and sent to other threads. Clones share the same
+/// flag, which stays set once cancelled.
+#[derive(Clone)]
+pub struct CancellationToken {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    cancelled: AtomicBool,
+    /// The threads whose request the token is bound to.
+    threads: Mutex<Vec<(ThreadId, InterruptHandle)>>,
+}
+
+impl CancellationToken {
+    /// Creates a token which is not cancelled.
+    ///
+    /// The first token must be created during module startup, as it registers
+    /// an interrupt handler.
+    pub fn new() -> Self {
+        INTERRUPT_HANDLER.call_once(|| register_interrupt_handler(|_| throw_cancelled()));
+
+        Self {
+            inner: Arc::new(Inner {
+                cancelled: AtomicBool::new(false),
+                threads: Mutex::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Returns the token bound to the request of the current thread, if any.
+    pub fn current() -> Option<Self> {
+        BOUND.with(|bound| bound.borrow().as_ref().map(|(token, _)| token.clone()))
+    }
+
+    /// Binds the token to the request of the current thread, which must
+    /// execute PHP code, until the request ends. Replaces the token previously
+    /// bound to the request.
+    ///
+    /// If the token is cancelled, the VM of the thread is interrupted and
+    /// throws an exception, which PHP code may catch.
+    pub fn bind(&self) {
+        let id = thread::current().id();
+        unbind_request();
+
+        let handle = InterruptHandle::current();
+        self.inner.threads.lock().push((id, handle));
+        BOUND.with(|bound| *bound.borrow_mut() = Some((self.clone(), false)));
+        if self.is_cancelled() {
+            handle.interrupt();
+        }
+    }
+
+    /// Cancels the token, and interrupts the requests it is bound to. Has no
+    /// effect if the token is already cancelled.
+    pub fn cancel(&self) {
+        let threads = self.inner.threads.lock();
+        if !self.inner.cancelled.swap(true, Ordering::AcqRel) {
+            for (_, handle) in threads.iter() {
+                handle.interrupt();
+            }
+        }
+    }
+
+    /// Returns whether the token was cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Returns an error if the token was cancelled, to stop a Rust function
+    /// with the `?` operator.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Cancelled`] if the token was cancelled, which is
+    /// thrown as an exception when returned to PHP.
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for CancellationToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CancellationToken")
+            .field("cancelled", &self.is_cancelled())
+            .field("requests", &self.inner.threads.lock().len())
+            .finish()
+    }
+}
+
+/// Throws the cancellation into the request of this thread, once, if its
+/// token was cancelled.
+fn throw_cancelled() {
+    let cancelled = BOUND.with(|bound| match bound.borrow_mut().as_mut() {
+        Some((token, thrown)) if token.is_cancelled() && !*thrown => {
+            *thrown = true;
+            true
+        }
+        _ => false,
+    });
+    if cancelled {
+        let _ = PhpException::from(Error::Cancelled).throw();
+    }
+}
+
+/// Unbinds the token of the request of this thread, so it no longer
+/// interrupts the thread. This is called when a request ends.
+pub(crate) fn unbind_request() {
+    let id = thread::current().id();
+    if let Some((token, _)) = BOUND.with(|bound| bound.borrow_mut().take()) {
+        token
+            .inner
+            .threads
+            .lock()
+            .retain(|(thread, _)| *thread != id);
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_cancel() {
+        let token = CancellationToken::new();
+        Embed::run(|| {
+            token.bind();
+            assert!(CancellationToken::current().is_some());
+            assert!(token.check().is_ok());
+
+            let canceller = token.clone();
+            thread::spawn(move || canceller.cancel())
+                .join()
+                .expect("should cancel the token");
+            assert!(token.check().is_err());
+
+            // The loop is interrupted by the exception.
+            let message = Embed::eval(
+                "(function () {
+                    try {
+                        while (true) {}
+                    } catch (Exception $e) {
+                        return $e->getMessage();
+                    }
+                })()",
+            );
+            assert_eq!(
+                message.ok().and_then(|zval| zval.string()).as_deref(),
+                Some("The operation was cancelled.")
+            );
+        });
+        assert!(token.inner.threads.lock().is_empty());
+    }
+}
@@ -0,0 +1,462 @@
+//! Generation of the Rust wrappers of reflected PHP classes.
+
+use std::{collections::HashSet, fmt::Write};
+
+use super::reflect::{ConstantValue, ReflectedClass, ReflectedMethod, ReflectedParameter};
+
+/// The names of the wrappers' own items, which methods cannot use.
+const RESERVED: &[&str] = &["new", "from_zval", "as_zval", "into_inner", "CLASS_NAME"];
+
+/// Rust keywords, which are prefixed with `r#` when used as identifiers.
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let",
+    "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return",
+    "static", "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use",
+    "virtual", "where", "while", "yield",
+];
+
+const ZVAL: &str = "::ext_php_rs::types::Zval";
+const RESULT: &str = "::ext_php_rs::error::Result";
+const DYN: &str = "&dyn ::ext_php_rs::convert::IntoZvalDyn";
+
+/// Generates the Rust wrappers of reflected PHP classes, to be written into a
+/// file and included into a module of a crate using ext-php-rs.
+///
+/// Each class is wrapped by a struct named like the class without its
+/// namespace, or with its namespace if it conflicts with another class,
+/// holding an instance of the class. The struct has:
+///
+/// * a `CLASS_NAME` constant, and a constant per constant of the class,
+/// * a `new` function calling the constructor, if the class can be
+///   instantiated,
+/// * `from_zval`, `as_zval` and `into_inner` functions converting from and
+///   into zvals,
+/// * a method per public method of the class, named in snake case, except
+///   the magic methods and the methods conflicting with the functions above.
+///
+/// # Parameters
+///
+/// * `classes` - The classes, reflected with a
+///   [`Reflector`](super::Reflector).
+pub fn generate(classes: &[ReflectedClass]) -> String {
+    let mut out = String::from("// Generated by ext-php-rs from the reflection of PHP classes.\n");
+
+    let short_name = |class: &ReflectedClass| {
+        class
+            .name
+            .rsplit('\\')
+            .next()
+            .unwrap_or_default()
+            .to_string()
+    };
+    let mut short_names = HashSet::new();
+    let duplicates: HashSet<_> = classes
+        .iter()
+        .map(short_name)
+        .filter(|name| !short_names.insert(name.clone()))
+        .collect();
+
+    for class in classes {
+        let name = short_name(class);
+        let name = if duplicates.contains(&name) {
+            class.name.replace('\\', "")
+        } else {
+            name
+        };
+        generate_class(&mut out, class, &name);
+    }
+    out
+}
+
+/// Generates the wrapper of a class.
+fn generate_class(out: &mut String, class: &ReflectedClass, name: &str) {
+    let class_name = &class.name;
+    let _ = write!(
+        out,
+        "
+/// Wrapper of an instance of the PHP class `{class_name}`.
+#[derive(Debug)]
+pub struct {name}({ZVAL});
+
+#[allow(
+    clippy::new_without_default,
+    clippy::should_implement_trait,
+    clippy::too_many_arguments,
+    clippy::wrong_self_convention
+)]
+impl {name} {{
+    /// The name of the PHP class.
+    pub const CLASS_NAME: &'static str = {class_name:?};
+"
+    );
+
+    for constant in &class.constants {
+        if RESERVED.contains(&constant.name.as_str()) {
+            continue;
+        }
+        let (ty, value) = match &constant.value {
+            ConstantValue::Long(i64::MIN) => ("i64", "i64::MIN".to_string()),
+            ConstantValue::Long(value) => ("i64", value.to_string()),
+            ConstantValue::Double(value) if value.is_nan() => ("f64", "f64::NAN".into()),
+            ConstantValue::Double(value) if value.is_infinite() => (
+                "f64",
+                if value.is_sign_positive() {
+                    "f64::INFINITY".into()
+                } else {
+                    "f64::NEG_INFINITY".into()
+                },
+            ),
+            ConstantValue::Double(value) => ("f64", format!("{value:?}")),
+            ConstantValue::String(value) => ("&'static str", format!("{value:?}")),
+            ConstantValue::Bool(value) => ("bool", value.to_string()),
+        };
+        let _ = write!(
+            out,
+            "
+    /// `{class_name}::{constant}`.
+    pub const {constant}: {ty} = {value};
+",
+            constant = constant.name
+        );
+    }
+
+    let constructor = class
+        .methods
+        .iter()
+        .find(|method| method.name.eq_ignore_ascii_case("__construct"));
+    if class.instantiable {
+        let parameters = constructor.map_or(&[][..], |method| &method.parameters);
+        let (params, args) = arguments(parameters);
+        let _ = write!(
+            out,
+            "
+    /// Creates an instance of the class, calling `{signature}`.
+{by_ref}    pub fn new({params}) -> {RESULT}<Self> {{
+        ::ext_php_rs::codegen::instantiate(Self::CLASS_NAME, {args}).map(Self)
+    }}
+",
+            signature = constructor.map_or_else(
+                || format!("{class_name}::__construct()"),
+                |method| signature(class_name, method)
+            ),
+            by_ref = by_ref_note(parameters),
+        );
+    }
+
+    let _ = write!(
+        out,
+        "
+    /// Wraps an instance of the class.
+    pub fn from_zval(zval: &{ZVAL}) -> {RESULT}<Self> {{
+        ::ext_php_rs::codegen::instance_of(zval, Self::CLASS_NAME).map(Self)
+    }}
+
+    /// Returns the zval of the instance.
+    pub fn as_zval(&self) -> &{ZVAL} {{
+        &self.0
+    }}
+
+    /// Returns the zval of the instance.
+    pub fn into_inner(self) -> {ZVAL} {{
+        self.0
+    }}
+"
+    );
+
+    let mut names: HashSet<String> = RESERVED.iter().map(ToString::to_string).collect();
+    for method in &class.methods {
+        let rust_name = snake_case(&method.name);
+        if method.name.starts_with("__") || !names.insert(rust_name.clone()) {
+            continue;
+        }
+        generate_method(out, class_name, method, &rust_name);
+    }
+    out.push_str("}\n");
+}
+
+/// Generates the wrapper of a method.
+fn generate_method(out: &mut String, class_name: &str, method: &ReflectedMethod, name: &str) {
+    let (params, args) = arguments(&method.parameters);
+    let (receiver, call) = if method.is_static {
+        (
+            "",
+            format!(
+                "::ext_php_rs::codegen::call_static(Self::CLASS_NAME, {:?}, {args})",
+                method.name
+            ),
+        )
+    } else {
+        (
+            if params.is_empty() {
+                "&self"
+            } else {
+                "&self, "
+            },
+            format!("self.0.try_call_method({:?}, {args})", method.name),
+        )
+    };
+    let ty = Type::parse(method.return_type.as_deref());
+    let (ret, body) = match ty.kind {
+        Kind::Unit => ("()".to_string(), format!("{call}.map(drop)")),
+        Kind::Zval => (ZVAL.to_string(), call),
+        _ => (
+            ty.option(ty.kind.returned()),
+            format!("::ext_php_rs::codegen::cast({call}?)"),
+        ),
+    };
+    let _ = write!(
+        out,
+        "
+    /// Calls `{signature}`.
+{by_ref}    pub fn {name}({receiver}{params}) -> {RESULT}<{ret}> {{
+        {body}
+    }}
+",
+        signature = signature(class_name, method),
+        by_ref = by_ref_note(&method.parameters),
+        name = ident(name),
+    );
+}
+
+/// Returns the parameters of a wrapper, and the expression collecting the
+/// arguments of the call.
+fn arguments(parameters: &[ReflectedParameter]) -> (String, String) {
+    let mut params = vec![];
+    let mut required = vec![];
+    let mut optional = vec![];
+    let mut variadic = "&[]".to_string();
+    for parameter in parameters {
+        let name = ident(&snake_case(&parameter.name));
+        let ty = Type::parse(parameter.ty.as_deref());
+        if parameter.variadic {
+            params.push(format!("{name}: &[{DYN}]"));
+            variadic = name;
+            continue;
+        }
+        match (ty.kind, parameter.optional) {
+            (Kind::Zval | Kind::Unit, false) => {
+                params.push(format!("{name}: &{ZVAL}"));
+                required.push(name);
+            }
+            (Kind::Zval | Kind::Unit, true) => {
+                params.push(format!("{name}: Option<&{ZVAL}>"));
+                optional.push(format!("{name}.map(|value| value as {DYN})"));
+            }
+            (kind, false) => {
+                params.push(format!("{name}: {}", ty.option(kind.param())));
+                required.push(format!("&{name}"));
+            }
+            (kind, true) => {
+                params.push(format!("{name}: Option<{}>", kind.param()));
+                optional.push(format!("{name}.as_ref().map(|value| value as {DYN})"));
+            }
+        }
+    }
+    let args = format!(
+        "::ext_php_rs::codegen::arguments(vec![{}], vec![{}], {variadic})?",
+        required.join(", "),
+        optional.join(", "),
+    );
+    (params.join(", "), args)
+}
+
+/// Returns the PHP signature of a method, for the documentation.
+fn signature(class_name: &str, method: &ReflectedMethod) -> String {
+    let params: Vec<_> = method
+        .parameters
+        .iter()
+        .map(|parameter| {
+            format!(
+                "{}{}{}${}",
+                parameter
+                    .ty
+                    .as_ref()
+                    .map_or_else(String::new, |ty| format!("{ty} ")),
+                if parameter.by_ref { "&" } else { "" },
+                if parameter.variadic { "..." } else { "" },
+                parameter.name,
+            )
+        })
+        .collect();
+    let ret = method
+        .return_type
+        .as_ref()
+        .map_or_else(String::new, |ty| format!(": {ty}"));
+    format!("{class_name}::{}({}){ret}", method.name, params.join(", "))
+}
+
+/// Returns a note on the parameters passed by reference, which the wrappers
+/// pass by value.
+fn by_ref_note(parameters: &[ReflectedParameter]) -> String {
+    let by_ref: Vec<_> = parameters
+        .iter()
+        .filter(|parameter| parameter.by_ref)
+        .map(|parameter| format!("`${}`", parameter.name))
+        .collect();
+    if by_ref.is_empty() {
+        return String::new();
+    }
+    let verb = if by_ref.len() == 1 { "is" } else { "are" };
+    format!(
+        "    ///\n    /// {} {verb} passed by value, so changes made by PHP are not visible.\n",
+        by_ref.join(", ")
+    )
+}
+
+/// A PHP type, as mapped to Rust.
+struct Type {
+    kind: Kind,
+    nullable: bool,
+}
+
+/// The Rust type a PHP type is mapped to.
+#[derive(Clone, Copy)]
+enum Kind {
+    Long,
+    Double,
+    Str,
+    Bool,
+    Unit,
+    Zval,
+}
+
+impl Type {
+    /// Parses a type as written by PHP, e.g. `?int` or `string|null`.
+    fn parse(ty: Option<&str>) -> Self {
+        let Some(ty) = ty else {
+            return Self {
+                kind: Kind::Zval,
+                nullable: false,
+            };
+        };
+        let (ty, mut nullable) = match ty.strip_prefix('?') {
+            Some(ty) => (ty, true),
+            None => (ty, false),
+        };
+        let types: Vec<_> = ty
+            .split('|')
+            .filter(|ty| {
+                let null = ty.eq_ignore_ascii_case("null");
+                nullable |= null;
+                !null
+            })
+            .collect();
+        let kind = match types.as_slice() {
+            ["int"] => Kind::Long,
+            ["float"] => Kind::Double,
+            ["string"] => Kind::Str,
+            ["bool" | "false" | "true"] => Kind::Bool,
+            ["void" | "never"] => Kind::Unit,
+            _ => Kind::Zval,
+        };
+        Self { kind, nullable }
+    }
+
+    /// Wraps a Rust type in an `Option` if the type is nullable.
+    fn option(&self, ty: &str) -> String {
+        if self.nullable {
+            format!("Option<{ty}>")
+        } else {
+            ty.to_string()
+        }
+    }
+}
+
+impl Kind {
+    /// Returns the Rust type of an argument.
+    fn param(self) -> &'static str {
+        match self {
+            Kind::Long => "i64",
+            Kind::Double => "f64",
+            Kind::Str => "&str",
+            Kind::Bool => "bool",
+            Kind::Unit | Kind::Zval => ZVAL,
+        }
+    }
+
+    /// Returns the Rust type of a return value.
+    fn returned(self) -> &'static str {
+        match self {
+            Kind::Str => "String",
+            kind => kind.param(),
+        }
+    }
+}
+
+/// Converts a PHP name in camel case into snake case, e.g. `setISODate` into
+/// `set_iso_date`.
+fn snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::with_capacity(name.len());
+    for (i, &char) in chars.iter().enumerate() {
+        if char.is_uppercase() {
+            let previous = i.checked_sub(1).map(|i| chars[i]);
+            let next = chars.get(i + 1);
+            let boundary = previous.is_some_and(|previous| {
+                previous.is_lowercase()
+                    || previous.is_ascii_digit()
+                    || (previous.is_uppercase() && next.is_some_and(|next| next.is_lowercase()))
+            });
+            if boundary {
+                snake.push('_');
+            }
+            snake.extend(char.to_lowercase());
+        } else {
+            snake.push(char);
+        }
+    }
+    snake
+}
+
+/// Escapes a name which is not a valid Rust identifier.
+fn ident(name: &str) -> String {
+    match name {
+        "self" | "Self" | "super" | "crate" | "_" => format!("{name}_"),
+        _ if KEYWORDS.contains(&name) => format!("r#{name}"),
+        _ => name.to_string(),
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codegen::reflect::parse;
+
+    #[test]
+    fn test_generate() {
+        let classes = parse(
+            "C\tApp%5CDateTime\t1\n\
+             K\tATOM\tstring\tY-m-d%5CTH%3Ai%3AsP\n\
+             M\t__construct\t0\t\n\
+             P\tdatetime\tstring\t1\t0\t0\n\
+             P\ttimezone\t%3FDateTimeZone\t1\t0\t0\n\
+             M\tformat\t0\tstring\n\
+             P\tformat\tstring\t0\t0\t0\n\
+             M\tsetISODate\t0\tstatic\n\
+             P\tyear\tint\t0\t0\t0\n\
+             P\ttype\t%3Fint\t0\t0\t0\n\
+             M\tgetLastErrors\t1\tarray%7Cfalse\n\
+             M\tsetTime\t0\tvoid\n\
+             P\tvalues\tint\t1\t1\t0\n",
+        )
+        .expect("should parse the reflection");
+        assert_eq!(classes[0].name, "App\\DateTime");
+        assert_eq!(classes[0].methods[1].parameters[0].name, "format");
+
+        let code = generate(&classes);
+        assert!(code.contains("pub struct DateTime(::ext_php_rs::types::Zval);"));
+        assert!(code.contains("pub const ATOM: &'static str = \"Y-m-d\\\\TH:i:sP\";"));
+        assert!(code.contains(
+            "pub fn new(datetime: Option<&str>, timezone: Option<&::ext_php_rs::types::Zval>)"
+        ));
+        assert!(code
+            .contains("pub fn format(&self, format: &str) -> ::ext_php_rs::error::Result<String>"));
+        assert!(code.contains("pub fn set_iso_date(&self, year: i64, r#type: Option<i64>)"));
+        assert!(code.contains(
+            "pub fn get_last_errors() -> ::ext_php_rs::error::Result<::ext_php_rs::types::Zval>"
+        ));
+        assert!(code.contains(".map(drop)"));
+    }
+}
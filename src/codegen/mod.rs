@@ -0,0 +1,172 @@
+//! Generation of typed Rust wrappers for existing PHP classes, e.g. the
+//! classes of extensions such as `DateTime` or `Redis`.
+//!
+//! The classes are reflected with the `php` executable by a [`Reflector`],
+//! from a build script or with `cargo php bindings`, and [`generate`] writes a
+//! wrapper for each of them, with their constants and a method per public
+//! method, whose arguments and return values are converted from and into the
+//! types declared by PHP:
+//!
+//! | PHP type                  | Argument     | Return value      |
+//! | ------------------------- | ------------ | ----------------- |
+//! | `int`                     | `i64`        | `i64`             |
+//! | `float`                   | `f64`        | `f64`             |
+//! | `string`                  | `&str`       | `String`          |
+//! | `bool`                    | `bool`       | `bool`            |
+//! | `?int`, `?string`, ...    | `Option<..>` | `Option<..>`      |
+//! | `void`, `never`           |              | `()`              |
+//! | any other or no type      | `&Zval`      | [`Zval`]          |
+//!
+//! Optional parameters are `Option`s, where [`None`] omits the argument, and
+//! variadic parameters are slices.
+//!
+//! # Example
+//!
+//! In the build script of a crate:
+//!
+//! ```no_run
+//! use std::{env, path::Path};
+//!
+//! use ext_php_rs::codegen::{generate, Reflector};
+//!
+//! let classes = Reflector::new()
+//!     .class("DateTime")
+//!     .class("DateTimeZone")
+//!     .reflect()
+//!     .expect("should reflect the classes");
+//! let out = Path::new(&env::var("OUT_DIR").unwrap()).join("php.rs");
+//! std::fs::write(out, generate(&classes)).expect("should write the bindings");
+//! ```
+//!
+//! The wrappers can then be included in a module, and used during requests:
+//!
+//! ```ignore
+//! mod php {
+//!     include!(concat!(env!("OUT_DIR"), "/php.rs"));
+//! }
+//!
+//! #[php_function]
+//! pub fn tomorrow() -> PhpResult<String> {
+//!     let date = php::DateTime::new(Some("tomorrow"), None)?;
+//!     Ok(date.format("Y-m-d")?)
+//! }
+//! ```
+
+use crate::{
+    convert::{FromZval, IntoZval, IntoZvalDyn},
+    error::{Error, Result},
+    types::{ZendCallable, ZendObject, Zval},
+    zend::ClassEntry,
+};
+
+mod generate;
+mod reflect;
+
+pub use generate::generate;
+pub use reflect::{
+    ConstantValue, ReflectedClass, ReflectedConstant, ReflectedMethod, ReflectedParameter,
+    Reflector,
+};
+
+/// Collects the arguments of a call from a generated wrapper: the required
+/// arguments, the optional arguments until the first omitted one, and the
+/// variadic arguments.
+///
+/// # Parameters
+///
+/// * `required` - The required arguments.
+/// * `optional` - The optional arguments, [`None`] if omitted.
+/// * `variadic` - The variadic arguments.
+///
+/// # Errors
+///
+/// Returns [`Error::IncorrectArguments`] if an optional argument is omitted
+/// while a later one is given, as arguments are passed by position.
+#[doc(hidden)]
+pub fn arguments<'a>(
+    required: Vec<&'a dyn IntoZvalDyn>,
+    optional: Vec<Option<&'a dyn IntoZvalDyn>>,
+    variadic: &'a [&'a dyn IntoZvalDyn],
+) -> Result<Vec<&'a dyn IntoZvalDyn>> {
+    let given = optional.iter().take_while(|arg| arg.is_some()).count();
+    if let Some(last) = optional.iter().rposition(Option::is_some) {
+        if last >= given {
+            return Err(Error::IncorrectArguments(
+                required.len() + given,
+                required.len() + last + 1,
+            ));
+        }
+    }
+    if given < optional.len() && !variadic.is_empty() {
+        return Err(Error::IncorrectArguments(
+            required.len() + given,
+            required.len() + optional.len(),
+        ));
+    }
+
+    let mut args = required;
+    args.extend(optional.into_iter().flatten());
+    args.extend_from_slice(variadic);
+    Ok(args)
+}
+
+/// Creates an instance of a class, calling its constructor with the given
+/// arguments.
+///
+/// # Errors
+///
+/// Returns [`Error::Callable`] if the class does not exist, or the error of
+/// the constructor.
+#[doc(hidden)]
+pub fn instantiate(class: &str, args: Vec<&dyn IntoZvalDyn>) -> Result<Zval> {
+    let ce = ClassEntry::try_find(class).ok_or(Error::Callable)?;
+    let object = ZendObject::new(ce);
+    if !ce.constructor.is_null() {
+        object.try_call_method("__construct", args)?;
+    }
+    object.into_zval(false)
+}
+
+/// Calls a static method of a class.
+///
+/// # Errors
+///
+/// Returns [`Error::Callable`] if the method does not exist, or the error of
+/// the method.
+#[doc(hidden)]
+pub fn call_static(class: &str, method: &str, args: Vec<&dyn IntoZvalDyn>) -> Result<Zval> {
+    ZendCallable::try_from_name(&format!("{class}::{method}"))?.try_call(args)
+}
+
+/// Checks that a zval is an instance of a class, and returns a copy of it.
+///
+/// # Errors
+///
+/// Returns [`Error::ZvalConversion`] if the zval is not an instance of the
+/// class.
+#[doc(hidden)]
+pub fn instance_of(zval: &Zval, class: &str) -> Result<Zval> {
+    let is_instance = zval
+        .object()
+        .zip(ClassEntry::try_find(class))
+        .is_some_and(|(object, ce)| object.instance_of(ce));
+    if is_instance {
+        Ok(zval.shallow_clone())
+    } else {
+        Err(Error::ZvalConversion(zval.get_type()))
+    }
+}
+
+/// Converts the return value of a method.
+///
+/// # Errors
+///
+/// Returns [`Error::ZvalConversion`] if the value does not have the declared
+/// type.
+#[doc(hidden)]
+pub fn cast<T>(zval: Zval) -> Result<T>
+where
+    T: for<'a> FromZval<'a>,
+{
+    T::from_zval(&zval).ok_or_else(|| Error::ZvalConversion(zval.get_type()))
+}
@@ -0,0 +1,303 @@
+//! Reflection of PHP classes with the `php` executable.
+
+use std::{ffi::OsString, io, path::PathBuf, process::Command};
+
+/// The script printing the reflection of the classes given as arguments, as
+/// `class:<name>` or `ext:<name>` for the classes of an extension.
+///
+/// Each line is a record whose fields are separated by tabs and encoded with
+/// `rawurlencode()`: `C` for a class, followed by `K` for its constants and
+/// `M` for its methods, each followed by `P` for its parameters.
+const SCRIPT: &str = r#"
+$e = fn ($value) => rawurlencode((string) $value);
+$type = fn ($type) => $type === null ? '' : (string) $type;
+$line = function (array $fields) { echo implode("\t", $fields), "\n"; };
+foreach (array_slice($argv, 1) as $arg) {
+    [$kind, $name] = explode(':', $arg, 2);
+    $names = $kind === 'ext' ? (new ReflectionExtension($name))->getClassNames() : [$name];
+    foreach ($names as $name) {
+        $class = new ReflectionClass($name);
+        $line(['C', $e($class->getName()), (int) $class->isInstantiable()]);
+        foreach ($class->getReflectionConstants() as $constant) {
+            $value = $constant->getValue();
+            if ($constant->isPublic() && is_scalar($value)) {
+                $value = is_float($value) ? var_export($value, true) : (is_bool($value) ? (int) $value : $value);
+                $line(['K', $e($constant->getName()), gettype($constant->getValue()), $e($value)]);
+            }
+        }
+        foreach ($class->getMethods(ReflectionMethod::IS_PUBLIC) as $method) {
+            $return = $method->getReturnType();
+            if ($return === null && method_exists($method, 'getTentativeReturnType')) {
+                $return = $method->getTentativeReturnType();
+            }
+            $line(['M', $e($method->getName()), (int) $method->isStatic(), $e($type($return))]);
+            foreach ($method->getParameters() as $parameter) {
+                $line([
+                    'P',
+                    $e($parameter->getName()),
+                    $e($type($parameter->getType())),
+                    (int) $parameter->isOptional(),
+                    (int) $parameter->isVariadic(),
+                    (int) $parameter->isPassedByReference(),
+                ]);
+            }
+        }
+    }
+}
+"#;
+
+/// Reflects PHP classes with the `php` executable, to generate their wrappers
+/// with [`generate`](super::generate).
+///
+/// The classes must be available to the `php` executable, e.g. from an
+/// extension enabled in its php.ini file, or loaded with
+/// [`Reflector::load`].
+#[derive(Debug, Clone)]
+pub struct Reflector {
+    php: OsString,
+    loads: Vec<PathBuf>,
+    targets: Vec<String>,
+}
+
+impl Default for Reflector {
+    fn default() -> Self {
+        Self {
+            php: std::env::var_os("PHP").unwrap_or_else(|| "php".into()),
+            loads: vec![],
+            targets: vec![],
+        }
+    }
+}
+
+impl Reflector {
+    /// Creates a reflector using the `php` executable of the `PHP`
+    /// environment variable, or the one in the `PATH`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the `php` executable to use.
+    ///
+    /// # Parameters
+    ///
+    /// * `php` - The path of the executable.
+    pub fn php<P: Into<OsString>>(mut self, php: P) -> Self {
+        self.php = php.into();
+        self
+    }
+
+    /// Loads an extension which is not enabled in the php.ini file.
+    ///
+    /// # Parameters
+    ///
+    /// * `extension` - The name or path of the extension, e.g. `redis`.
+    pub fn load<P: Into<PathBuf>>(mut self, extension: P) -> Self {
+        self.loads.push(extension.into());
+        self
+    }
+
+    /// Adds a class to reflect.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the class, e.g. `DateTime`.
+    pub fn class<S: AsRef<str>>(mut self, name: S) -> Self {
+        self.targets.push(format!("class:{}", name.as_ref()));
+        self
+    }
+
+    /// Adds the classes of an extension to reflect.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the extension, e.g. `redis`.
+    pub fn extension<S: AsRef<str>>(mut self, name: S) -> Self {
+        self.targets.push(format!("ext:{}", name.as_ref()));
+        self
+    }
+
+    /// Reflects the classes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the `php` executable could not be run, or failed,
+    /// e.g. because a class or extension does not exist.
+    pub fn reflect(&self) -> io::Result<Vec<ReflectedClass>> {
+        let mut command = Command::new(&self.php);
+        for extension in &self.loads {
+            let mut define = OsString::from("extension=");
+            define.push(extension);
+            command.arg("-d").arg(define);
+        }
+        let output = command
+            .arg("-r")
+            .arg(SCRIPT)
+            .arg("--")
+            .args(&self.targets)
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "Failed to reflect the classes: {}{}",
+                String::from_utf8_lossy(&output.stderr),
+                String::from_utf8_lossy(&output.stdout),
+            )));
+        }
+        parse(&String::from_utf8_lossy(&output.stdout))
+    }
+}
+
+/// A reflected PHP class.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReflectedClass {
+    /// The name of the class, with its namespace.
+    pub name: String,
+    /// Whether the class can be instantiated, i.e. it is not abstract and its
+    /// constructor is public.
+    pub instantiable: bool,
+    /// The public constants of the class with a scalar value.
+    pub constants: Vec<ReflectedConstant>,
+    /// The public methods of the class, including the inherited ones.
+    pub methods: Vec<ReflectedMethod>,
+}
+
+/// A constant of a reflected class.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReflectedConstant {
+    /// The name of the constant.
+    pub name: String,
+    /// The value of the constant.
+    pub value: ConstantValue,
+}
+
+/// The value of a reflected constant.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConstantValue {
+    /// An integer.
+    Long(i64),
+    /// A float.
+    Double(f64),
+    /// A string.
+    String(String),
+    /// A boolean.
+    Bool(bool),
+}
+
+/// A method of a reflected class.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReflectedMethod {
+    /// The name of the method.
+    pub name: String,
+    /// Whether the method is static.
+    pub is_static: bool,
+    /// The return type of the method, as written in PHP, e.g. `?string`, or
+    /// [`None`] if it is not declared.
+    pub return_type: Option<String>,
+    /// The parameters of the method.
+    pub parameters: Vec<ReflectedParameter>,
+}
+
+/// A parameter of a reflected method.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReflectedParameter {
+    /// The name of the parameter, without `$`.
+    pub name: String,
+    /// The type of the parameter, as written in PHP, or [`None`] if it is
+    /// not declared.
+    pub ty: Option<String>,
+    /// Whether the parameter has a default value.
+    pub optional: bool,
+    /// Whether the parameter is variadic.
+    pub variadic: bool,
+    /// Whether the parameter is passed by reference.
+    pub by_ref: bool,
+}
+
+/// Parses the output of [`SCRIPT`].
+pub(super) fn parse(output: &str) -> io::Result<Vec<ReflectedClass>> {
+    let invalid = |line: &str| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Invalid reflection record: {line}"),
+        )
+    };
+
+    let mut classes: Vec<ReflectedClass> = vec![];
+    for line in output.lines().filter(|line| !line.is_empty()) {
+        let fields: Vec<_> = line.split('\t').map(decode).collect();
+        let flag = |index: usize| fields.get(index).is_some_and(|field| field == "1");
+        let ty = |index: usize| fields.get(index).filter(|ty| !ty.is_empty()).cloned();
+        match fields.as_slice() {
+            [kind, name, ..] if kind == "C" => classes.push(ReflectedClass {
+                name: name.clone(),
+                instantiable: flag(2),
+                constants: vec![],
+                methods: vec![],
+            }),
+            [kind, name, ty, value] if kind == "K" => {
+                let value = match ty.as_str() {
+                    "integer" => value.parse().ok().map(ConstantValue::Long),
+                    "double" => value.parse().ok().map(ConstantValue::Double),
+                    "boolean" => Some(ConstantValue::Bool(value == "1")),
+                    "string" => Some(ConstantValue::String(value.clone())),
+                    _ => None,
+                };
+                let class = classes.last_mut().ok_or_else(|| invalid(line))?;
+                if let Some(value) = value {
+                    class.constants.push(ReflectedConstant {
+                        name: name.clone(),
+                        value,
+                    });
+                }
+            }
+            [kind, name, ..] if kind == "M" => {
+                let class = classes.last_mut().ok_or_else(|| invalid(line))?;
+                class.methods.push(ReflectedMethod {
+                    name: name.clone(),
+                    is_static: flag(2),
+                    return_type: ty(3),
+                    parameters: vec![],
+                });
+            }
+            [kind, name, ..] if kind == "P" => {
+                let method = classes
+                    .last_mut()
+                    .and_then(|class| class.methods.last_mut())
+                    .ok_or_else(|| invalid(line))?;
+                method.parameters.push(ReflectedParameter {
+                    name: name.clone(),
+                    ty: ty(2),
+                    optional: flag(3),
+                    variadic: flag(4),
+                    by_ref: flag(5),
+                });
+            }
+            _ => return Err(invalid(line)),
+        }
+    }
+    Ok(classes)
+}
+
+/// Decodes a field encoded with `rawurlencode()`.
+fn decode(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes.get(i + 1..i + 3).and_then(|hex| {
+            std::str::from_utf8(hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        });
+        match (bytes[i], byte) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
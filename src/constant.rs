@@ -77,6 +77,13 @@ pub trait IntoConst: Debug {
         module_number: i32,
         flags: GlobalConstantFlags,
     ) -> Result<()>;
+
+    /// Returns the value of the constant as PHP code, used in the stubs
+    /// generated by `cargo php stubs`. Returns [`None`] by default, in which
+    /// case the constant is declared as `null`.
+    fn stub_value(&self) -> Option<String> {
+        None
+    }
 }
 
 impl IntoConst for String {
@@ -89,6 +96,10 @@ impl IntoConst for String {
         self.as_str()
             .register_constant_flags(name, module_number, flags)
     }
+
+    fn stub_value(&self) -> Option<String> {
+        self.as_str().stub_value()
+    }
 }
 
 impl IntoConst for &str {
@@ -109,6 +120,13 @@ impl IntoConst for &str {
         };
         Ok(())
     }
+
+    fn stub_value(&self) -> Option<String> {
+        Some(format!(
+            "'{}'",
+            self.replace('\\', "\\\\").replace('\'', "\\'")
+        ))
+    }
 }
 
 impl IntoConst for bool {
@@ -129,12 +147,16 @@ impl IntoConst for bool {
         };
         Ok(())
     }
+
+    fn stub_value(&self) -> Option<String> {
+        Some(self.to_string())
+    }
 }
 
 /// Implements the `IntoConst` trait for a given number type using a given
-/// function.
+/// function, and a given function formatting the value as PHP code.
 macro_rules! into_const_num {
-    ($type: ty, $fn: expr) => {
+    ($type: ty, $fn: expr, $stub: expr) => {
         impl IntoConst for $type {
             fn register_constant_flags(
                 &self,
@@ -152,13 +174,33 @@ macro_rules! into_const_num {
                     )
                 })
             }
+
+            fn stub_value(&self) -> Option<String> {
+                Some($stub(*self))
+            }
         }
     };
 }
 
-into_const_num!(i8, zend_register_long_constant);
-into_const_num!(i16, zend_register_long_constant);
-into_const_num!(i32, zend_register_long_constant);
-into_const_num!(i64, zend_register_long_constant);
-into_const_num!(f32, zend_register_double_constant);
-into_const_num!(f64, zend_register_double_constant);
+/// Formats an integer as PHP code.
+fn int_stub(value: impl ToString) -> String {
+    value.to_string()
+}
+
+/// Formats a floating point number as PHP code, which always contains a
+/// decimal point or an exponent so it is not read as an integer.
+fn float_stub(value: impl Debug) -> String {
+    match format!("{value:?}").as_str() {
+        "NaN" => "NAN".into(),
+        "inf" => "INF".into(),
+        "-inf" => "-INF".into(),
+        value => value.into(),
+    }
+}
+
+into_const_num!(i8, zend_register_long_constant, int_stub);
+into_const_num!(i16, zend_register_long_constant, int_stub);
+into_const_num!(i32, zend_register_long_constant, int_stub);
+into_const_num!(i64, zend_register_long_constant, int_stub);
+into_const_num!(f32, zend_register_double_constant, float_stub);
+into_const_num!(f64, zend_register_double_constant, float_stub);
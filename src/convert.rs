@@ -3,7 +3,7 @@
 use crate::{
     boxed::ZBox,
     error::Result,
-    exception::PhpException,
+    exception::{PhpException, Severity, WarningReturn},
     flags::DataType,
     types::{ZendObject, Zval},
 };
@@ -189,7 +189,17 @@ where
             Ok(val) => val.set_zval(zv, persistent),
             Err(e) => {
                 let ex: PhpException = e.into();
-                ex.throw()
+                match ex.severity() {
+                    Severity::Exception => ex.throw(),
+                    Severity::Warning(value) => {
+                        crate::zend::warning(ex.message())?;
+                        match value {
+                            WarningReturn::False => zv.set_bool(false),
+                            WarningReturn::Null => zv.set_null(),
+                        }
+                        Ok(())
+                    }
+                }
             }
         }
     }
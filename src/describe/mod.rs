@@ -1,19 +1,22 @@
 //! Types used to describe downstream extensions. Used by the `cargo-php`
-//! CLI application to generate PHP stub files used by IDEs.
+//! CLI application to generate PHP stub files used by IDEs, and by
+//! [`assert_snapshot`] to test the API of extensions against golden files.
 use bitflags::bitflags_match;
 use std::vec::Vec as StdVec;
 
 use crate::{
-    builders::{ClassBuilder, FunctionBuilder},
+    builders::{ClassBuilder, FunctionBuilder, PropertyEntry},
     constant::IntoConst,
-    flags::{DataType, MethodFlags, PropertyFlags},
+    flags::{ClassFlags, DataType, MethodFlags, PropertyFlags},
     prelude::ModuleBuilder,
 };
 use abi::*;
 
 pub mod abi;
+mod snapshot;
 mod stub;
 
+pub use snapshot::{assert_snapshot, UPDATE_SNAPSHOTS_ENV};
 pub use stub::ToStub;
 
 /// A slice of strings containing documentation comments.
@@ -151,6 +154,8 @@ pub struct Parameter {
     pub nullable: bool,
     /// Default value of the parameter.
     pub default: Option<RString>,
+    /// Whether the parameter is variadic.
+    pub variadic: bool,
 }
 
 /// Represents an exported class.
@@ -160,9 +165,15 @@ pub struct Class {
     pub name: RString,
     /// Documentation comments for the class.
     pub docs: DocBlock,
-    /// Name of the class the exported class extends. (Not implemented #326)
+    /// Type of the class.
+    pub ty: ClassType,
+    /// Whether the class is abstract.
+    pub abstract_: bool,
+    /// Whether the class is final.
+    pub final_: bool,
+    /// Name of the class the exported class extends.
     pub extends: Option<RString>,
-    /// Names of the interfaces the exported class implements. (Not implemented #326)
+    /// Names of the interfaces the exported class implements.
     pub implements: Vec<RString>,
     /// Properties of the class.
     pub properties: Vec<Property>,
@@ -174,6 +185,7 @@ pub struct Class {
 
 impl From<ClassBuilder> for Class {
     fn from(val: ClassBuilder) -> Self {
+        let flags = ClassFlags::from_bits_truncate(val.ce.ce_flags);
         Self {
             name: val.name.into(),
             docs: DocBlock(
@@ -183,8 +195,21 @@ impl From<ClassBuilder> for Class {
                     .collect::<StdVec<_>>()
                     .into(),
             ),
-            extends: abi::Option::None, // TODO: Implement extends #326
-            implements: vec![].into(),  // TODO: Implement implements #326
+            ty: flags.into(),
+            abstract_: flags.contains(ClassFlags::Abstract),
+            final_: flags.contains(ClassFlags::Final),
+            extends: val
+                .extends
+                .and_then(|ce| ce.name())
+                .map(RString::from)
+                .into(),
+            implements: val
+                .interfaces
+                .iter()
+                .filter_map(|ce| ce.name())
+                .map(RString::from)
+                .collect::<StdVec<_>>()
+                .into(),
             properties: val
                 .properties
                 .into_iter()
@@ -208,6 +233,30 @@ impl From<ClassBuilder> for Class {
     }
 }
 
+/// Enumerator used to differentiate between classes, interfaces and traits.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum ClassType {
+    /// A class.
+    Class,
+    /// An interface.
+    Interface,
+    /// A trait.
+    Trait,
+}
+
+impl From<ClassFlags> for ClassType {
+    fn from(value: ClassFlags) -> Self {
+        if value.contains(ClassFlags::Interface) {
+            Self::Interface
+        } else if value.contains(ClassFlags::Trait) {
+            Self::Trait
+        } else {
+            Self::Class
+        }
+    }
+}
+
 /// Represents a property attached to an exported class.
 #[repr(C)]
 pub struct Property {
@@ -215,38 +264,38 @@ pub struct Property {
     pub name: RString,
     /// Documentation comments for the property.
     pub docs: DocBlock,
-    /// Type of the property (Not implemented #376)
+    /// Type of the property.
     pub ty: Option<DataType>,
     /// Visibility of the property.
     pub vis: Visibility,
     /// Whether the property is static.
     pub static_: bool,
-    /// Whether the property is nullable. (Not implemented #376)
+    /// Whether the property is nullable.
     pub nullable: bool,
     /// Default value of the property. (Not implemented #376)
     pub default: Option<RString>,
 }
 
-impl From<(String, PropertyFlags, DocComments)> for Property {
-    fn from(value: (String, PropertyFlags, DocComments)) -> Self {
-        let (name, flags, docs) = value;
+impl From<PropertyEntry> for Property {
+    fn from(value: PropertyEntry) -> Self {
+        let PropertyEntry {
+            name,
+            flags,
+            ty,
+            nullable,
+            docs,
+        } = value;
         let static_ = flags.contains(PropertyFlags::Static);
         let vis = Visibility::from(flags);
-        // TODO: Implement ty #376
-        let ty = abi::Option::None;
         // TODO: Implement default #376
         let default = abi::Option::<abi::RString>::None;
-        // TODO: Implement nullable #376
-        let nullable = false;
-        let docs = docs.into();
-        println!("Property: {:?}", name);
         Self {
             name: name.into(),
-            docs,
-            ty,
+            docs: docs.into(),
+            ty: ty.into(),
             vis,
             static_,
-            nullable,
+            nullable: nullable && ty != Some(DataType::Mixed),
             default,
         }
     }
@@ -267,6 +316,8 @@ pub struct Method {
     pub retval: Option<Retval>,
     /// Whether the method is static.
     pub _static: bool,
+    /// Whether the method is abstract.
+    pub abstract_: bool,
     /// Visibility of the method.
     pub visibility: Visibility,
 }
@@ -300,6 +351,7 @@ impl From<(FunctionBuilder<'_>, MethodFlags)> for Method {
                 .into(),
             ty: flags.into(),
             _static: flags.contains(MethodFlags::Static),
+            abstract_: flags.contains(MethodFlags::Abstract),
             visibility: flags.into(),
         }
     }
@@ -395,10 +447,10 @@ impl From<(String, DocComments)> for Constant {
 
 impl From<(String, Box<dyn IntoConst + Send>, DocComments)> for Constant {
     fn from(val: (String, Box<dyn IntoConst + Send + 'static>, DocComments)) -> Self {
-        let (name, _, docs) = val;
+        let (name, value, docs) = val;
         Constant {
             name: name.into(),
-            value: abi::Option::None,
+            value: value.stub_value().map(RString::from).into(),
             docs: docs.into(),
         }
     }
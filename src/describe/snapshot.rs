@@ -0,0 +1,385 @@
+//! Stable dumps of the PHP API of an extension, used in golden-file tests to
+//! catch accidental changes to the functions, classes and constants exposed
+//! to PHP.
+
+use std::{
+    env,
+    fmt::{Result as FmtResult, Write},
+    fs, io,
+    path::Path,
+    vec::Vec as StdVec,
+};
+
+use super::{
+    abi::Option, stub::fmt_type, Class, ClassType, Constant, Function, Method, MethodType, Module,
+    Parameter, Property, ToStub,
+};
+
+/// The environment variable which, when set, makes [`assert_snapshot`]
+/// update the snapshot files instead of comparing them.
+pub const UPDATE_SNAPSHOTS_ENV: &str = "EXT_PHP_RS_UPDATE_SNAPSHOTS";
+
+impl Module {
+    /// Returns the functions, classes and constants registered by the module
+    /// in a stable text form, one declaration per line, sorted by name.
+    ///
+    /// Unlike stubs, the snapshot leaves out doc comments, so it only changes
+    /// along with the API seen by PHP code.
+    ///
+    /// # Example
+    ///
+    /// ```text
+    /// constant VERSION = '1.0.0'
+    /// function hello_world(string $name): string
+    /// class Counter
+    ///     property public int $count
+    ///     method public __construct()
+    ///     method public increment(): int
+    /// ```
+    pub fn snapshot(&self) -> String {
+        let mut buf = String::new();
+        // Writing into a string cannot fail.
+        let _ = self.fmt_snapshot(&mut buf);
+        buf
+    }
+
+    fn fmt_snapshot(&self, buf: &mut String) -> FmtResult {
+        for constant in sorted(&self.constants, |c| c.name.as_str()) {
+            fmt_constant(buf, constant)?;
+        }
+        for function in sorted(&self.functions, |f| f.name.as_str()) {
+            fmt_function(buf, function)?;
+        }
+        for class in sorted(&self.classes, |c| c.name.as_str()) {
+            fmt_class(buf, class)?;
+        }
+        Ok(())
+    }
+}
+
+/// Asserts that the snapshot of a module matches the one stored in a file,
+/// printing the lines which differ otherwise.
+///
+/// The file is written when it does not exist, or when the
+/// [`EXT_PHP_RS_UPDATE_SNAPSHOTS`](UPDATE_SNAPSHOTS_ENV) environment variable
+/// is set, after an intended change of the API.
+///
+/// # Parameters
+///
+/// * `module` - The description of the module, e.g. returned by the
+///   `ext_php_rs_describe_module` function generated by `#[php_module]`.
+/// * `path` - The path of the snapshot file.
+///
+/// # Panics
+///
+/// Panics if the snapshots differ, or if the file cannot be read or written.
+///
+/// # Example
+///
+/// ```no_run
+/// # #[no_mangle]
+/// # pub extern "C" fn ext_php_rs_describe_module() -> ext_php_rs::describe::Description {
+/// #     unimplemented!()
+/// # }
+/// use ext_php_rs::describe::assert_snapshot;
+///
+/// #[test]
+/// fn test_api() {
+///     let description = ext_php_rs_describe_module();
+///     assert_snapshot(
+///         &description.module,
+///         concat!(env!("CARGO_MANIFEST_DIR"), "/tests/api.snap"),
+///     );
+/// }
+/// ```
+#[track_caller]
+pub fn assert_snapshot(module: &Module, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let actual = module.snapshot();
+
+    let expected = match fs::read_to_string(path) {
+        Ok(expected) if env::var_os(UPDATE_SNAPSHOTS_ENV).is_none() => expected,
+        Ok(_) => return write_snapshot(path, &actual),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return write_snapshot(path, &actual),
+        Err(e) => panic!("Failed to read the snapshot {}: {e}", path.display()),
+    };
+
+    if actual != expected {
+        panic!(
+            "The API of the module differs from the snapshot {}:\n{}\n\
+             Set {UPDATE_SNAPSHOTS_ENV}=1 to update the snapshot if the change is intended.",
+            path.display(),
+            diff(&expected, &actual)
+        );
+    }
+}
+
+fn write_snapshot(path: &Path, snapshot: &str) {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).expect("should create the directory of the snapshot");
+    }
+    fs::write(path, snapshot)
+        .unwrap_or_else(|e| panic!("Failed to write the snapshot {}: {e}", path.display()));
+}
+
+/// Returns the lines removed from `old`, prefixed with `-`, and added to
+/// `new`, prefixed with `+`, along with the unchanged lines.
+fn diff(old: &str, new: &str) -> String {
+    let old: StdVec<_> = old.lines().collect();
+    let new: StdVec<_> = new.lines().collect();
+
+    // Lengths of the longest common subsequences of the ends of the lines.
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut buf = String::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            buf.push_str(&format!(" {}\n", old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            buf.push_str(&format!("+{}\n", new[j]));
+            j += 1;
+        } else {
+            buf.push_str(&format!("-{}\n", old[i]));
+            i += 1;
+        }
+    }
+    buf
+}
+
+/// Returns the items sorted by a key.
+fn sorted<T>(items: &[T], key: impl Fn(&T) -> &str) -> StdVec<&T> {
+    let mut items: StdVec<_> = items.iter().collect();
+    items.sort_by(|l, r| key(l).cmp(key(r)));
+    items
+}
+
+fn fmt_constant(buf: &mut String, constant: &Constant) -> FmtResult {
+    write!(buf, "constant {} = ", constant.name)?;
+    match &constant.value {
+        Option::Some(value) => writeln!(buf, "{value}"),
+        Option::None => writeln!(buf, "null"),
+    }
+}
+
+fn fmt_function(buf: &mut String, function: &Function) -> FmtResult {
+    write!(buf, "function {}(", function.name)?;
+    fmt_params(buf, &function.params)?;
+    write!(buf, ")")?;
+    if let Option::Some(retval) = &function.ret {
+        write!(buf, ": ")?;
+        fmt_type(buf, retval.ty, retval.nullable)?;
+    }
+    writeln!(buf)
+}
+
+fn fmt_params(buf: &mut String, params: &[Parameter]) -> FmtResult {
+    for (i, param) in params.iter().enumerate() {
+        if i > 0 {
+            write!(buf, ", ")?;
+        }
+        param.fmt_stub(buf)?;
+    }
+    Ok(())
+}
+
+fn fmt_class(buf: &mut String, class: &Class) -> FmtResult {
+    let interface = matches!(class.ty, ClassType::Interface);
+    match class.ty {
+        ClassType::Interface => write!(buf, "interface ")?,
+        ClassType::Trait => write!(buf, "trait ")?,
+        ClassType::Class => {
+            if class.abstract_ {
+                write!(buf, "abstract ")?;
+            }
+            if class.final_ {
+                write!(buf, "final ")?;
+            }
+            write!(buf, "class ")?;
+        }
+    }
+    write!(buf, "{}", class.name)?;
+
+    if let Option::Some(extends) = &class.extends {
+        write!(buf, " extends \\{extends}")?;
+    }
+    let mut implements: StdVec<_> = class.implements.iter().map(|i| i.as_str()).collect();
+    implements.sort_unstable();
+    if !implements.is_empty() {
+        write!(
+            buf,
+            " {} \\{}",
+            if interface { "extends" } else { "implements" },
+            implements.join(", \\")
+        )?;
+    }
+    writeln!(buf)?;
+
+    for constant in sorted(&class.constants, |c| c.name.as_str()) {
+        write!(buf, "    ")?;
+        fmt_constant(buf, constant)?;
+    }
+    for property in sorted(&class.properties, |p| p.name.as_str()) {
+        fmt_property(buf, property)?;
+    }
+    for method in sorted(&class.methods, |m| m.name.as_str()) {
+        fmt_method(buf, method)?;
+    }
+    Ok(())
+}
+
+fn fmt_property(buf: &mut String, property: &Property) -> FmtResult {
+    write!(buf, "    property ")?;
+    property.vis.fmt_stub(buf)?;
+    if property.static_ {
+        write!(buf, " static")?;
+    }
+    write!(buf, " ")?;
+    if let Option::Some(ty) = &property.ty {
+        fmt_type(buf, *ty, property.nullable)?;
+        write!(buf, " ")?;
+    }
+    writeln!(buf, "${}", property.name)
+}
+
+fn fmt_method(buf: &mut String, method: &Method) -> FmtResult {
+    write!(buf, "    method ")?;
+    if method.abstract_ {
+        write!(buf, "abstract ")?;
+    }
+    method.visibility.fmt_stub(buf)?;
+    if method._static || matches!(method.ty, MethodType::Static) {
+        write!(buf, " static")?;
+    }
+    write!(buf, " {}(", method.name)?;
+    fmt_params(buf, &method.params)?;
+    write!(buf, ")")?;
+    if !matches!(method.ty, MethodType::Constructor) {
+        if let Option::Some(retval) = &method.retval {
+            write!(buf, ": ")?;
+            fmt_type(buf, retval.ty, retval.nullable)?;
+        }
+    }
+    writeln!(buf)
+}
+
+#[cfg(test)]
+mod test {
+    use super::diff;
+    use crate::{
+        describe::{
+            Class, ClassType, Constant, DocBlock, Function, Method, MethodType, Module, Parameter,
+            Property, Retval, Visibility,
+        },
+        flags::DataType,
+    };
+
+    #[test]
+    fn test_snapshot() {
+        let docs = || DocBlock(vec![" Ignored.".into()].into());
+        let function = |name: &str| Function {
+            name: name.into(),
+            docs: docs(),
+            ret: Some(Retval {
+                ty: DataType::String,
+                nullable: false,
+            })
+            .into(),
+            params: vec![Parameter {
+                name: "name".into(),
+                ty: Some(DataType::String).into(),
+                nullable: true,
+                default: Some("null".into()).into(),
+                variadic: false,
+            }]
+            .into(),
+        };
+        let method = |name: &str, ty, retval: Option<DataType>| Method {
+            name: name.into(),
+            docs: docs(),
+            ty,
+            params: vec![].into(),
+            retval: retval
+                .map(|ty| Retval {
+                    ty,
+                    nullable: false,
+                })
+                .into(),
+            _static: matches!(ty, MethodType::Static),
+            abstract_: false,
+            visibility: Visibility::Public,
+        };
+        let module = Module {
+            name: "test".into(),
+            functions: vec![function("hello_world"), function("goodbye")].into(),
+            classes: vec![Class {
+                name: "Counter".into(),
+                docs: docs(),
+                ty: ClassType::Class,
+                abstract_: false,
+                final_: true,
+                extends: None.into(),
+                implements: vec!["Stringable".into(), "Countable".into()].into(),
+                properties: vec![Property {
+                    name: "count".into(),
+                    docs: docs(),
+                    ty: Some(DataType::Long).into(),
+                    vis: Visibility::Protected,
+                    static_: false,
+                    nullable: false,
+                    default: None.into(),
+                }]
+                .into(),
+                methods: vec![
+                    method("increment", MethodType::Member, Some(DataType::Long)),
+                    method("__construct", MethodType::Constructor, Some(DataType::Void)),
+                    method("create", MethodType::Static, Some(DataType::Object(None))),
+                ]
+                .into(),
+                constants: vec![Constant {
+                    name: "MAX".into(),
+                    docs: docs(),
+                    value: None.into(),
+                }]
+                .into(),
+            }]
+            .into(),
+            constants: vec![Constant {
+                name: "VERSION".into(),
+                docs: docs(),
+                value: Some("'1.0.0'".into()).into(),
+            }]
+            .into(),
+        };
+
+        assert_eq!(
+            module.snapshot(),
+            "constant VERSION = '1.0.0'\n\
+             function goodbye(?string $name = null): string\n\
+             function hello_world(?string $name = null): string\n\
+             final class Counter implements \\Countable, \\Stringable\n\
+             \x20   constant MAX = null\n\
+             \x20   property protected int $count\n\
+             \x20   method public __construct()\n\
+             \x20   method public static create(): object\n\
+             \x20   method public increment(): int\n"
+        );
+    }
+
+    #[test]
+    fn test_diff() {
+        assert_eq!(diff("a\nb\nc\n", "a\nc\nd\n"), " a\n-b\n c\n+d\n");
+        assert_eq!(diff("a\n", "a\n"), " a\n");
+    }
+}
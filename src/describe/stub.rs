@@ -4,8 +4,8 @@ use crate::flags::DataType;
 use std::{cmp::Ordering, collections::HashMap};
 
 use super::{
-    abi::*, Class, Constant, DocBlock, Function, Method, MethodType, Module, Parameter, Property,
-    Visibility,
+    abi::*, Class, ClassType, Constant, DocBlock, Function, Method, MethodType, Module, Parameter,
+    Property, Visibility,
 };
 use std::fmt::{Error as FmtError, Result as FmtResult, Write};
 use std::{option::Option as StdOption, vec::Vec as StdVec};
@@ -126,10 +126,7 @@ impl ToStub for Function {
 
         if let Option::Some(retval) = &self.ret {
             write!(buf, ": ")?;
-            if retval.nullable {
-                write!(buf, "?")?;
-            }
-            retval.ty.fmt_stub(buf)?;
+            fmt_type(buf, retval.ty, retval.nullable)?;
         }
 
         writeln!(buf, " {{}}")
@@ -139,18 +136,31 @@ impl ToStub for Function {
 impl ToStub for Parameter {
     fn fmt_stub(&self, buf: &mut String) -> FmtResult {
         if let Option::Some(ty) = &self.ty {
-            if self.nullable {
-                write!(buf, "?")?;
-            }
-
-            ty.fmt_stub(buf)?;
+            fmt_type(buf, *ty, self.nullable)?;
             write!(buf, " ")?;
         }
 
-        write!(buf, "${}", self.name)
+        if self.variadic {
+            write!(buf, "...")?;
+        }
+        write!(buf, "${}", self.name)?;
+
+        if let Option::Some(default) = &self.default {
+            write!(buf, " = {default}")?;
+        }
+        Ok(())
     }
 }
 
+/// Writes a type, prefixed with `?` if it is nullable and does not already
+/// include `null`.
+pub(super) fn fmt_type(buf: &mut String, ty: DataType, nullable: bool) -> FmtResult {
+    if nullable && !matches!(ty, DataType::Mixed | DataType::Null) {
+        write!(buf, "?")?;
+    }
+    ty.fmt_stub(buf)
+}
+
 impl ToStub for DataType {
     fn fmt_stub(&self, buf: &mut String) -> FmtResult {
         let mut fqdn = "\\".to_owned();
@@ -173,6 +183,8 @@ impl ToStub for DataType {
                 DataType::Callable => "callable",
                 DataType::Bool => "bool",
                 DataType::Iterable => "iterable",
+                DataType::Null => "null",
+                DataType::Void => "void",
                 _ => "mixed",
             }
         )
@@ -197,19 +209,34 @@ impl ToStub for Class {
         self.docs.fmt_stub(buf)?;
 
         let (_, name) = split_namespace(self.name.as_ref());
-        write!(buf, "class {name} ")?;
+        let interface = matches!(self.ty, ClassType::Interface);
+        match self.ty {
+            ClassType::Interface => write!(buf, "interface {name} ")?,
+            ClassType::Trait => write!(buf, "trait {name} ")?,
+            ClassType::Class => {
+                if self.abstract_ {
+                    write!(buf, "abstract ")?;
+                }
+                if self.final_ {
+                    write!(buf, "final ")?;
+                }
+                write!(buf, "class {name} ")?;
+            }
+        }
 
         if let Option::Some(extends) = &self.extends {
-            write!(buf, "extends {extends} ")?;
+            write!(buf, "extends \\{extends} ")?;
         }
 
         if !self.implements.is_empty() {
             write!(
                 buf,
-                "implements {} ",
+                // Interfaces extend the interfaces they implement.
+                "{} {} ",
+                if interface { "extends" } else { "implements" },
                 self.implements
                     .iter()
-                    .map(|s| s.as_str())
+                    .map(|s| format!("\\{s}"))
                     .collect::<StdVec<_>>()
                     .join(", ")
             )?;
@@ -223,10 +250,16 @@ impl ToStub for Class {
                 .map(|item| item.to_stub().map(|stub| indent(&stub, 4)))
         }
 
+        let methods = self.methods.iter().map(|method| {
+            let mut stub = String::new();
+            method.fmt_method(&mut stub, interface)?;
+            Ok(indent(&stub, 4))
+        });
+
         buf.push_str(
             &stub(&self.constants)
                 .chain(stub(&self.properties))
-                .chain(stub(&self.methods))
+                .chain(methods)
                 .collect::<Result<StdVec<_>, FmtError>>()?
                 .join(NEW_LINE_SEPARATOR),
         );
@@ -246,7 +279,8 @@ impl ToStub for Property {
             write!(buf, "static ")?;
         }
         if let Option::Some(ty) = &self.ty {
-            ty.fmt_stub(buf)?;
+            fmt_type(buf, *ty, self.nullable)?;
+            write!(buf, " ")?;
         }
         write!(buf, "${}", self.name)?;
         if let Option::Some(default) = &self.default {
@@ -272,12 +306,29 @@ impl ToStub for Visibility {
 
 impl ToStub for Method {
     fn fmt_stub(&self, buf: &mut String) -> FmtResult {
+        self.fmt_method(buf, false)
+    }
+}
+
+impl Method {
+    /// Converts the method into PHP code, without a body if it is abstract.
+    ///
+    /// # Parameters
+    ///
+    /// * `buf` - The buffer to write the PHP code into.
+    /// * `interface` - Whether the method belongs to an interface, whose
+    ///   methods are implicitly abstract.
+    fn fmt_method(&self, buf: &mut String, interface: bool) -> FmtResult {
         self.docs.fmt_stub(buf)?;
+
+        if self.abstract_ && !interface {
+            write!(buf, "abstract ")?;
+        }
         self.visibility.fmt_stub(buf)?;
 
         write!(buf, " ")?;
 
-        if matches!(self.ty, MethodType::Static) {
+        if self._static || matches!(self.ty, MethodType::Static) {
             write!(buf, "static ")?;
         }
 
@@ -295,14 +346,15 @@ impl ToStub for Method {
         if !matches!(self.ty, MethodType::Constructor) {
             if let Option::Some(retval) = &self.retval {
                 write!(buf, ": ")?;
-                if retval.nullable {
-                    write!(buf, "?")?;
-                }
-                retval.ty.fmt_stub(buf)?;
+                fmt_type(buf, retval.ty, retval.nullable)?;
             }
         }
 
-        writeln!(buf, " {{}}")
+        if self.abstract_ || interface {
+            writeln!(buf, ";")
+        } else {
+            writeln!(buf, " {{}}")
+        }
     }
 }
 
@@ -380,6 +432,90 @@ mod test {
         assert_eq!(split_namespace("simple\\ns"), (Some("simple"), "ns"));
     }
 
+    #[test]
+    #[cfg(not(windows))]
+    pub fn test_class_stub() {
+        use super::ToStub;
+        use crate::{
+            describe::{
+                Class, ClassType, Constant, DocBlock, Method, MethodType, Parameter, Property,
+                Retval, Visibility,
+            },
+            flags::DataType,
+        };
+
+        let method = |name: &str, abstract_| Method {
+            name: name.into(),
+            docs: DocBlock(vec![].into()),
+            ty: MethodType::Member,
+            params: vec![Parameter {
+                name: "values".into(),
+                ty: Some(DataType::Long).into(),
+                nullable: false,
+                default: None.into(),
+                variadic: true,
+            }]
+            .into(),
+            retval: Some(Retval {
+                ty: DataType::String,
+                nullable: true,
+            })
+            .into(),
+            _static: false,
+            abstract_,
+            visibility: Visibility::Public,
+        };
+        let class = |ty, abstract_| Class {
+            name: "Foo\\Bar".into(),
+            docs: DocBlock(vec![" A bar.".into()].into()),
+            ty,
+            abstract_,
+            final_: false,
+            extends: None.into(),
+            implements: vec!["Countable".into()].into(),
+            properties: vec![Property {
+                name: "count".into(),
+                docs: DocBlock(vec![].into()),
+                ty: Some(DataType::Long).into(),
+                vis: Visibility::Public,
+                static_: false,
+                nullable: true,
+                default: None.into(),
+            }]
+            .into(),
+            methods: vec![method("join", abstract_)].into(),
+            constants: vec![Constant {
+                name: "SIZE".into(),
+                docs: DocBlock(vec![].into()),
+                value: Some("42".into()).into(),
+            }]
+            .into(),
+        };
+
+        assert_eq!(
+            class(ClassType::Class, true)
+                .to_stub()
+                .expect("should generate the stub"),
+            "/**\n * A bar.\n */\n\
+             abstract class Bar implements \\Countable {\n\
+             \x20   const SIZE = 42;\n\n\
+             \x20   public ?int $count;\n\n\
+             \x20   abstract public function join(int ...$values): ?string;\n\
+             }\n"
+        );
+        assert_eq!(
+            class(ClassType::Interface, true)
+                .to_stub()
+                .expect("should generate the stub"),
+            "/**\n * A bar.\n */\n\
+             interface Bar extends \\Countable {\n\
+             \x20   const SIZE = 42;\n\n\
+             \x20   public ?int $count;\n\n\
+             \x20   public function join(int ...$values): ?string;\n\
+             }\n"
+        );
+    }
+
     #[test]
     #[cfg(not(windows))]
     #[allow(clippy::uninlined_format_args)]
@@ -0,0 +1,409 @@
+//! Calling into PHP from other threads.
+//!
+//! The APIs of the engine must be used on the thread executing PHP code. A
+//! [`PhpDispatcher`] is a handle to such a thread, which can be sent to other
+//! threads to queue closures running on it. The closures run at safe points,
+//! between two opcodes, through the interrupts of [`crate::zend::interrupt`],
+//! or when the host calls [`PhpDispatcher::run_pending`], e.g. while the
+//! thread is idle between requests. Their results are sent back through a
+//! [`Dispatched`] handle.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::thread;
+//!
+//! use ext_php_rs::{dispatcher::PhpDispatcher, embed::Embed};
+//!
+//! // On the thread executing PHP code, e.g. during module startup:
+//! let dispatcher = PhpDispatcher::current();
+//!
+//! thread::spawn(move || {
+//!     let version = dispatcher
+//!         .call(|| Embed::eval("PHP_VERSION").ok()?.string())
+//!         .expect("should run on the PHP thread");
+//!     println!("running PHP {version:?}");
+//! });
+//! ```
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::{self, Debug},
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Once,
+    },
+    task::{Context, Poll, Waker},
+    thread::{self, ThreadId},
+};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::{
+    error::{Error, Result},
+    zend::interrupt::{register_interrupt_handler, InterruptHandle},
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+static INTERRUPT_HANDLER: Once = Once::new();
+
+thread_local! {
+    /// The queue of the closures dispatched to this thread, once a dispatcher
+    /// was created on it.
+    static QUEUE: RefCell<Option<ThreadQueue>> = const { RefCell::new(None) };
+}
+
+/// The closures dispatched to a thread.
+struct Queue {
+    jobs: Mutex<VecDeque<Job>>,
+    thread: ThreadId,
+    interrupt: InterruptHandle,
+    closed: AtomicBool,
+}
+
+impl Queue {
+    /// Removes the closures queued so far, and closes the queue if the thread
+    /// is exiting.
+    fn take(&self, close: bool) -> VecDeque<Job> {
+        let mut jobs = self.jobs.lock();
+        if close {
+            self.closed.store(true, Ordering::Release);
+        }
+        std::mem::take(&mut *jobs)
+    }
+}
+
+/// Closes the queue of a thread when the thread shuts PHP down or exits, so
+/// the closures which will never run are dropped, notifying their callers.
+struct ThreadQueue(Arc<Queue>);
+
+impl Drop for ThreadQueue {
+    fn drop(&mut self) {
+        drop(self.0.take(true));
+    }
+}
+
+/// A handle to a thread executing PHP code, which queues closures to run on
+/// that thread from any other thread.
+///
+/// The handle points to the executor globals of the thread it was created on,
+/// like an [`InterruptHandle`], so the thread must shut PHP down with
+/// `ext_php_rs::embed::ext_php_rs_sapi_per_thread_shutdown`, which closes its
+/// queue before freeing the globals.
+#[derive(Clone)]
+pub struct PhpDispatcher {
+    queue: Arc<Queue>,
+}
+
+impl PhpDispatcher {
+    /// Returns the dispatcher of the current thread, which must execute PHP
+    /// code.
+    ///
+    /// The first dispatcher must be created during module startup, as it
+    /// registers an interrupt handler.
+    pub fn current() -> Self {
+        INTERRUPT_HANDLER.call_once(|| {
+            register_interrupt_handler(|_| {
+                Self::run_pending();
+            })
+        });
+
+        let queue = QUEUE.with(|queue| {
+            queue
+                .borrow_mut()
+                .get_or_insert_with(|| {
+                    ThreadQueue(Arc::new(Queue {
+                        jobs: Mutex::new(VecDeque::new()),
+                        thread: thread::current().id(),
+                        interrupt: InterruptHandle::current(),
+                        closed: AtomicBool::new(false),
+                    }))
+                })
+                .0
+                .clone()
+        });
+        Self { queue }
+    }
+
+    /// Queues a closure to run on the thread of the dispatcher, at the next
+    /// safe point. Called on the thread of the dispatcher, the closure runs
+    /// immediately instead.
+    ///
+    /// Returns a handle receiving the result of the closure. The closure is
+    /// dropped without running if the thread exits first.
+    ///
+    /// # Parameters
+    ///
+    /// * `f` - The closure to run.
+    pub fn dispatch<F, R>(&self, f: F) -> Dispatched<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (sender, dispatched) = channel();
+        let job = move || {
+            // Unwinding out of the interrupt handler would abort the process,
+            // so the panic is reported to the caller instead.
+            if let Ok(value) = panic::catch_unwind(AssertUnwindSafe(f)) {
+                sender.send(value);
+            }
+        };
+
+        if thread::current().id() == self.queue.thread {
+            job();
+        } else {
+            // The thread is interrupted while the queue is locked, so it cannot
+            // close the queue and free its executor globals in between.
+            let mut jobs = self.queue.jobs.lock();
+            if !self.queue.closed.load(Ordering::Acquire) {
+                jobs.push_back(Box::new(job));
+                self.queue.interrupt.interrupt();
+            }
+        }
+        dispatched
+    }
+
+    /// Runs a closure on the thread of the dispatcher, and waits for its
+    /// result.
+    ///
+    /// # Parameters
+    ///
+    /// * `f` - The closure to run.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::DispatcherClosed`] if the closure panicked, or if the
+    /// thread exited before running it.
+    pub fn call<F, R>(&self, f: F) -> Result<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        self.dispatch(f).wait()
+    }
+
+    /// Runs the closures dispatched to the current thread, e.g. while it is
+    /// idle between requests, where interrupts are not handled.
+    ///
+    /// Returns the number of closures which ran.
+    pub fn run_pending() -> usize {
+        let Some(queue) = QUEUE.with(|queue| queue.borrow().as_ref().map(|queue| queue.0.clone()))
+        else {
+            return 0;
+        };
+
+        let mut count = 0;
+        // Closures may dispatch more closures, which run in the same pass.
+        loop {
+            let jobs = queue.take(false);
+            if jobs.is_empty() {
+                return count;
+            }
+            count += jobs.len();
+            for job in jobs {
+                job();
+            }
+        }
+    }
+}
+
+impl Debug for PhpDispatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PhpDispatcher")
+            .field("thread", &self.queue.thread)
+            .field("pending", &self.queue.jobs.lock().len())
+            .finish()
+    }
+}
+
+/// Closes the queue of the current thread, dropping the closures which will
+/// never run. This is called before the thread shuts PHP down, as the
+/// interrupt handle of the queue points to the executor globals freed by the
+/// shutdown.
+pub(crate) fn close_queue() {
+    QUEUE.with(|queue| drop(queue.borrow_mut().take()));
+}
+
+/// Requests an interrupt of the current thread if closures were dispatched to
+/// it while it was idle, as the interrupt flag is reset when a request starts.
+/// This is called when a request starts.
+pub(crate) fn resume_pending() {
+    QUEUE.with(|queue| {
+        if let Some(queue) = queue.borrow().as_ref() {
+            if !queue.0.jobs.lock().is_empty() {
+                queue.0.interrupt.interrupt();
+            }
+        }
+    });
+}
+
+/// Creates a oneshot channel, the receiver being closed once the sender is
+/// dropped without sending a value.
+pub(crate) fn channel<R>() -> (Sender<R>, Dispatched<R>) {
+    let slot = Arc::new(Slot::default());
+    (Sender(slot.clone()), Dispatched(slot))
+}
+
+/// The result of a closure dispatched with [`PhpDispatcher::dispatch`].
+///
+/// The result can be waited for by blocking the thread with
+/// [`Dispatched::wait`], or awaited as a future.
+pub struct Dispatched<R>(Arc<Slot<R>>);
+
+impl<R> Dispatched<R> {
+    /// Blocks the thread until the closure ran, and returns its result.
+    ///
+    /// This must not be called on the thread of the dispatcher, which would
+    /// never reach a safe point.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::DispatcherClosed`] if the closure panicked, or if the
+    /// thread exited before running it.
+    pub fn wait(self) -> Result<R> {
+        let mut state = self.0.state.lock();
+        loop {
+            if let Some(value) = state.value.take() {
+                return Ok(value);
+            }
+            if state.closed {
+                return Err(Error::DispatcherClosed);
+            }
+            self.0.cond.wait(&mut state);
+        }
+    }
+
+    /// Returns whether the closure ran, or will never run.
+    pub fn is_ready(&self) -> bool {
+        let state = self.0.state.lock();
+        state.value.is_some() || state.closed
+    }
+
+    /// Returns the result of the closure if it ran, without blocking.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::DispatcherClosed`] if the closure panicked, or if the
+    /// thread exited before running it.
+    pub fn try_wait(&mut self) -> Result<Option<R>> {
+        let mut state = self.0.state.lock();
+        match state.value.take() {
+            Some(value) => Ok(Some(value)),
+            None if state.closed => Err(Error::DispatcherClosed),
+            None => Ok(None),
+        }
+    }
+}
+
+impl<R> Future for Dispatched<R> {
+    type Output = Result<R>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.0.state.lock();
+        if let Some(value) = state.value.take() {
+            return Poll::Ready(Ok(value));
+        }
+        if state.closed {
+            return Poll::Ready(Err(Error::DispatcherClosed));
+        }
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+impl<R> Debug for Dispatched<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.0.state.lock();
+        f.debug_struct("Dispatched")
+            .field("ready", &state.value.is_some())
+            .field("closed", &state.closed)
+            .finish()
+    }
+}
+
+/// A oneshot channel carrying the result of a closure.
+struct Slot<R> {
+    state: Mutex<SlotState<R>>,
+    cond: Condvar,
+}
+
+struct SlotState<R> {
+    value: Option<R>,
+    closed: bool,
+    waker: Option<Waker>,
+}
+
+impl<R> Default for Slot<R> {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(SlotState {
+                value: None,
+                closed: false,
+                waker: None,
+            }),
+            cond: Condvar::new(),
+        }
+    }
+}
+
+/// Sends the result of a closure, or closes the channel when dropped without
+/// a result.
+pub(crate) struct Sender<R>(Arc<Slot<R>>);
+
+impl<R> Sender<R> {
+    pub(crate) fn send(self, value: R) {
+        self.0.state.lock().value = Some(value);
+    }
+}
+
+impl<R> Drop for Sender<R> {
+    fn drop(&mut self) {
+        let waker = {
+            let mut state = self.0.state.lock();
+            state.closed = true;
+            state.waker.take()
+        };
+        self.0.cond.notify_all();
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_dispatch() {
+        Embed::run(|| {
+            let dispatcher = PhpDispatcher::current();
+            assert_eq!(dispatcher.call(|| 1 + 2).ok(), Some(3));
+
+            let caller = thread::spawn(move || {
+                dispatcher.call(|| {
+                    Embed::eval("strtoupper('php')")
+                        .ok()
+                        .and_then(|zval| zval.string())
+                })
+            });
+            while !caller.is_finished() {
+                PhpDispatcher::run_pending();
+                thread::yield_now();
+            }
+            let result = caller.join().expect("caller should not panic");
+            assert_eq!(result.ok().flatten().as_deref(), Some("PHP"));
+
+            let dispatcher = PhpDispatcher::current();
+            let mut panicked = dispatcher.dispatch(|| panic!("dispatched"));
+            assert!(panicked.try_wait().is_err());
+        });
+    }
+}
@@ -0,0 +1,598 @@
+//! Provisioning of the body of requests handled by SAPI hosts.
+
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    ffi::c_char,
+    fmt::{self, Debug},
+    future::Future,
+    io::{ErrorKind, Read},
+    pin::Pin,
+    slice,
+    sync::Arc,
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use parking_lot::{Condvar, Mutex};
+
+use crate::zend::SapiGlobals;
+
+thread_local! {
+    static BODY: RefCell<Option<RequestBody>> = const { RefCell::new(None) };
+}
+
+/// The body of a request, served to PHP by [`read_request_body`].
+pub struct RequestBody {
+    source: Source,
+    length: Option<u64>,
+    keep: usize,
+}
+
+enum Source {
+    /// A buffer, along with the number of bytes read by PHP.
+    Buffer(Vec<u8>, usize),
+    /// A reader, along with a copy of the bytes read by PHP, up to the limit
+    /// set with [`RequestBody::keep_consumed`].
+    Reader(Box<dyn Read>, Vec<u8>),
+    /// A channel fed by a [`BodySender`], along with a copy of the bytes read
+    /// by PHP, up to the limit set with [`RequestBody::keep_consumed`].
+    Channel(Arc<Channel>, Vec<u8>),
+}
+
+impl RequestBody {
+    /// Creates a body streamed from a reader, e.g. a socket. The bytes read by
+    /// PHP are not kept in memory unless [`RequestBody::keep_consumed`] is
+    /// called.
+    ///
+    /// # Parameters
+    ///
+    /// * `reader` - The reader of the body.
+    /// * `length` - The length of the body, if known, which is set as the
+    ///   content length of the request.
+    pub fn from_reader<R: Read + 'static>(reader: R, length: Option<u64>) -> Self {
+        Self {
+            source: Source::Reader(Box::new(reader), vec![]),
+            length,
+            keep: 0,
+        }
+    }
+
+    /// Creates a body fed incrementally by the returned [`BodySender`], e.g.
+    /// from the async task receiving the upload from the client, so the body
+    /// does not need to be buffered before the request starts up.
+    ///
+    /// PHP only waits when it reads bytes which were not sent yet. Inside a
+    /// fiber, the fiber is suspended until they are sent, like an awaited
+    /// future, with the `tokio` or `worker` feature. Otherwise the thread
+    /// blocks, for up to the given timeout, after which the body ends.
+    ///
+    /// # Parameters
+    ///
+    /// * `length` - The length of the body, if known, which is set as the
+    ///   content length of the request.
+    /// * `capacity` - The number of bytes buffered before the sender waits
+    ///   for PHP to read them.
+    /// * `timeout` - How long PHP waits for the next bytes outside of a fiber,
+    ///   or [`None`] to wait until they are sent.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::time::Duration;
+    ///
+    /// use ext_php_rs::embed::{set_request_body, RequestBody};
+    ///
+    /// let (sender, body) = RequestBody::channel(None, 64 * 1024, Some(Duration::from_secs(30)));
+    /// std::thread::spawn(move || {
+    ///     for chunk in [&b"a=1"[..], b"&b=2"] {
+    ///         if !sender.send_blocking(chunk) {
+    ///             break;
+    ///         }
+    ///     }
+    /// });
+    /// set_request_body(body);
+    /// ```
+    pub fn channel(
+        length: Option<u64>,
+        capacity: usize,
+        timeout: Option<Duration>,
+    ) -> (BodySender, Self) {
+        let channel = Arc::new(Channel {
+            state: Mutex::new(ChannelState {
+                buffer: VecDeque::new(),
+                finished: false,
+                dropped: false,
+                reader: None,
+                writer: None,
+            }),
+            cond: Condvar::new(),
+            capacity: capacity.max(1),
+            timeout,
+        });
+        let body = Self {
+            source: Source::Channel(channel.clone(), vec![]),
+            length,
+            keep: 0,
+        };
+        (BodySender { channel }, body)
+    }
+
+    /// Keeps a copy of up to `limit` bytes of the body read by PHP, so they
+    /// can be read back with [`consumed_request_body`], e.g. to log the
+    /// beginning of the body of a failed request.
+    ///
+    /// Streamed bodies don't keep the bytes read by PHP by default, so large
+    /// uploads are never held in memory. Bodies created from a buffer always
+    /// expose the bytes read by PHP, as the whole buffer is kept anyway.
+    ///
+    /// # Parameters
+    ///
+    /// * `limit` - The maximum number of bytes to keep.
+    pub fn keep_consumed(mut self, limit: usize) -> Self {
+        self.keep = limit;
+        self
+    }
+
+    /// Reads the next bytes of the body into the buffer.
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        match &mut self.source {
+            Source::Buffer(body, position) => {
+                let remaining = &body[*position..];
+                let read = remaining.len().min(buf.len());
+                buf[..read].copy_from_slice(&remaining[..read]);
+                *position += read;
+                read
+            }
+            Source::Reader(reader, consumed) => {
+                // An error ends the body, as PHP only expects short reads.
+                let read = loop {
+                    match reader.read(buf) {
+                        Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                        result => break result.unwrap_or(0),
+                    }
+                };
+                retain(consumed, self.keep, &buf[..read]);
+                read
+            }
+            // Channels are read by `read_request_body` without borrowing the
+            // body, as the reading fiber may be suspended.
+            Source::Channel(channel, consumed) => {
+                let read = channel.read(buf);
+                retain(consumed, self.keep, &buf[..read]);
+                read
+            }
+        }
+    }
+
+    /// Returns the bytes read by PHP so far.
+    fn consumed(&self) -> &[u8] {
+        match &self.source {
+            Source::Buffer(body, position) => &body[..*position],
+            Source::Reader(_, consumed) | Source::Channel(_, consumed) => consumed,
+        }
+    }
+}
+
+/// Appends the bytes read by PHP to the copy kept of the body, up to `keep`
+/// bytes.
+fn retain(consumed: &mut Vec<u8>, keep: usize, bytes: &[u8]) {
+    let len = keep.saturating_sub(consumed.len()).min(bytes.len());
+    consumed.extend_from_slice(&bytes[..len]);
+}
+
+impl Drop for RequestBody {
+    fn drop(&mut self) {
+        if let Source::Channel(channel, _) = &self.source {
+            channel.close(|state| state.dropped = true);
+        }
+    }
+}
+
+impl From<Vec<u8>> for RequestBody {
+    fn from(body: Vec<u8>) -> Self {
+        let length = Some(body.len() as u64);
+        Self {
+            source: Source::Buffer(body, 0),
+            length,
+            keep: 0,
+        }
+    }
+}
+
+impl From<&[u8]> for RequestBody {
+    fn from(body: &[u8]) -> Self {
+        body.to_vec().into()
+    }
+}
+
+impl From<String> for RequestBody {
+    fn from(body: String) -> Self {
+        body.into_bytes().into()
+    }
+}
+
+impl From<&str> for RequestBody {
+    fn from(body: &str) -> Self {
+        body.as_bytes().into()
+    }
+}
+
+impl Debug for RequestBody {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestBody")
+            .field("length", &self.length)
+            .field("consumed", &self.consumed().len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Sets the body of the next request handled on this thread, served to
+/// `php://input`, `$_POST` and `$_FILES` by [`read_request_body`].
+///
+/// This must be called before the request starts up, as PHP parses form data
+/// during the startup. If the length of the body is known, it is set as the
+/// content length of the request. The body replaces the previous one, and is
+/// kept until [`clear_request_body`] is called.
+///
+/// There is no `$HTTP_RAW_POST_DATA` on the supported PHP versions, so scripts
+/// read the raw body from `php://input`.
+///
+/// # Parameters
+///
+/// * `body` - The body, e.g. a [`Vec<u8>`] or a [`RequestBody::from_reader`].
+pub fn set_request_body(body: impl Into<RequestBody>) {
+    let body = body.into();
+    if let Some(length) = body.length {
+        SapiGlobals::get_mut().request_info.content_length = length as _;
+    }
+    BODY.with(|current| *current.borrow_mut() = Some(body));
+}
+
+/// Discards the body of the request handled on this thread.
+pub fn clear_request_body() {
+    BODY.with(|current| current.borrow_mut().take());
+}
+
+/// Returns a copy of the bytes of the body of the request handled on this
+/// thread which were read by PHP, e.g. to log the body of a failed request
+/// once the script consumed it.
+///
+/// For streamed bodies, only the bytes kept with
+/// [`RequestBody::keep_consumed`] are returned.
+///
+/// # Returns
+///
+/// The bytes, or [`None`] if no body was set with [`set_request_body`].
+pub fn consumed_request_body() -> Option<Vec<u8>> {
+    BODY.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .map(|body| body.consumed().to_vec())
+    })
+}
+
+/// Reads the body set with [`set_request_body`], to be set as the read post
+/// function of a SAPI with
+/// [`SapiBuilder::read_post_function`](crate::builders::SapiBuilder::read_post_function).
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::{
+///     builders::SapiBuilder,
+///     embed::{read_request_body, set_request_body},
+/// };
+///
+/// let sapi = SapiBuilder::new("my-server", "My Server")
+///     .read_post_function(read_request_body)
+///     .build();
+///
+/// // Before starting up each request:
+/// set_request_body(r#"{"name":"world"}"#);
+/// ```
+pub extern "C" fn read_request_body(buffer: *mut c_char, length: usize) -> usize {
+    if buffer.is_null() || length == 0 {
+        return 0;
+    }
+    // SAFETY: The engine passes a buffer of the given length.
+    let buf = unsafe { slice::from_raw_parts_mut(buffer.cast::<u8>(), length) };
+    let channel = BODY.with(|current| match current.borrow().as_ref() {
+        Some(RequestBody {
+            source: Source::Channel(channel, _),
+            ..
+        }) => Some(channel.clone()),
+        _ => None,
+    });
+    let Some(channel) = channel else {
+        return BODY.with(|current| {
+            current
+                .borrow_mut()
+                .as_mut()
+                .map_or(0, |body| body.read(buf))
+        });
+    };
+
+    let read = channel.read(buf);
+    BODY.with(|current| {
+        if let Some(RequestBody {
+            source: Source::Channel(current, consumed),
+            keep,
+            ..
+        }) = current.borrow_mut().as_mut()
+        {
+            if Arc::ptr_eq(current, &channel) {
+                retain(consumed, *keep, &buf[..read]);
+            }
+        }
+    });
+    read
+}
+
+/// Feeds the body of a request created with [`RequestBody::channel`]. The
+/// body ends when the sender is dropped.
+pub struct BodySender {
+    channel: Arc<Channel>,
+}
+
+impl BodySender {
+    /// Sends the next bytes of the body, waiting while the buffer of the body
+    /// is full.
+    ///
+    /// Resolves to `false` if the body was dropped, e.g. once the request
+    /// ended, so the rest of the body can be discarded.
+    pub fn send(&self, bytes: impl Into<Vec<u8>>) -> SendBody<'_> {
+        SendBody {
+            channel: &self.channel,
+            bytes: Some(bytes.into()),
+        }
+    }
+
+    /// Sends the next bytes of the body, blocking the thread while the buffer
+    /// of the body is full.
+    ///
+    /// Returns `false` if the body was dropped, e.g. once the request ended.
+    pub fn send_blocking(&self, bytes: impl Into<Vec<u8>>) -> bool {
+        let mut state = self.channel.state.lock();
+        while !state.dropped && state.buffer.len() >= self.channel.capacity {
+            self.channel.cond.wait(&mut state);
+        }
+        self.channel.push(state, bytes.into())
+    }
+}
+
+impl Debug for BodySender {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.channel.state.lock();
+        f.debug_struct("BodySender")
+            .field("buffered", &state.buffer.len())
+            .field("dropped", &state.dropped)
+            .finish()
+    }
+}
+
+impl Drop for BodySender {
+    fn drop(&mut self) {
+        self.channel.close(|state| state.finished = true);
+    }
+}
+
+/// The future returned by [`BodySender::send`].
+#[must_use = "futures do nothing unless polled"]
+pub struct SendBody<'a> {
+    channel: &'a Channel,
+    bytes: Option<Vec<u8>>,
+}
+
+impl Future for SendBody<'_> {
+    type Output = bool;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<bool> {
+        let mut state = self.channel.state.lock();
+        if !state.dropped && state.buffer.len() >= self.channel.capacity {
+            state.writer = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+        let bytes = self.bytes.take().unwrap_or_default();
+        Poll::Ready(self.channel.push(state, bytes))
+    }
+}
+
+impl Debug for SendBody<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SendBody")
+            .field("bytes", &self.bytes.as_ref().map(Vec::len))
+            .finish()
+    }
+}
+
+/// The bytes sent to a body by its [`BodySender`], and not read yet.
+struct Channel {
+    state: Mutex<ChannelState>,
+    /// Notified when bytes are sent or read, or either side is dropped.
+    cond: Condvar,
+    capacity: usize,
+    timeout: Option<Duration>,
+}
+
+struct ChannelState {
+    buffer: VecDeque<u8>,
+    /// Whether the sender was dropped.
+    finished: bool,
+    /// Whether the body was dropped.
+    dropped: bool,
+    /// The fiber waiting for bytes.
+    reader: Option<Waker>,
+    /// The task waiting for the buffer to have room.
+    writer: Option<Waker>,
+}
+
+impl Channel {
+    /// Buffers bytes sent to the body, and wakes PHP if it waits for them.
+    fn push(&self, mut state: parking_lot::MutexGuard<'_, ChannelState>, bytes: Vec<u8>) -> bool {
+        if state.dropped {
+            return false;
+        }
+        state.buffer.extend(bytes);
+        let reader = state.reader.take();
+        drop(state);
+        self.cond.notify_all();
+        if let Some(reader) = reader {
+            reader.wake();
+        }
+        true
+    }
+
+    /// Marks a side of the channel as dropped, and wakes the other one.
+    fn close(&self, mark: impl FnOnce(&mut ChannelState)) {
+        let (reader, writer) = {
+            let mut state = self.state.lock();
+            mark(&mut state);
+            (state.reader.take(), state.writer.take())
+        };
+        self.cond.notify_all();
+        for waker in [reader, writer].into_iter().flatten() {
+            waker.wake();
+        }
+    }
+
+    /// Reads the next bytes of the body into the buffer, waiting for them if
+    /// they were not sent yet.
+    fn read(&self, buf: &mut [u8]) -> usize {
+        let deadline = self.timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            let mut state = self.state.lock();
+            if !state.buffer.is_empty() {
+                let read = state.buffer.len().min(buf.len());
+                for (byte, slot) in state.buffer.drain(..read).zip(buf.iter_mut()) {
+                    *slot = byte;
+                }
+                let writer = state.writer.take();
+                drop(state);
+                self.cond.notify_all();
+                if let Some(writer) = writer {
+                    writer.wake();
+                }
+                return read;
+            }
+            if state.finished {
+                return 0;
+            }
+
+            #[cfg(any(feature = "tokio", feature = "worker"))]
+            if crate::future::in_fiber().unwrap_or(false) {
+                drop(state);
+                // The body ends if the fiber is destroyed while suspended.
+                if crate::future::poll_to_completion(Readable(self), |readable, cx| {
+                    readable.poll(cx)
+                })
+                .is_err()
+                {
+                    return 0;
+                }
+                continue;
+            }
+
+            match deadline {
+                Some(deadline) => {
+                    if self.cond.wait_until(&mut state, deadline).timed_out()
+                        && state.buffer.is_empty()
+                    {
+                        return 0;
+                    }
+                }
+                None => self.cond.wait(&mut state),
+            }
+        }
+    }
+}
+
+/// Resolves once bytes were sent to the channel, or the sender was dropped.
+#[cfg(any(feature = "tokio", feature = "worker"))]
+struct Readable<'a>(&'a Channel);
+
+#[cfg(any(feature = "tokio", feature = "worker"))]
+impl Future for Readable<'_> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.0.state.lock();
+        if !state.buffer.is_empty() || state.finished {
+            return Poll::Ready(());
+        }
+        state.reader = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::embed::Embed;
+
+    fn read(chunk: usize) -> Vec<u8> {
+        let mut body = vec![];
+        let mut buf = vec![0; chunk];
+        loop {
+            let read = read_request_body(buf.as_mut_ptr().cast(), buf.len());
+            if read == 0 {
+                return body;
+            }
+            body.extend_from_slice(&buf[..read]);
+        }
+    }
+
+    #[test]
+    fn test_read_request_body() {
+        BODY.with(|current| *current.borrow_mut() = Some("a=1&b=2".into()));
+        assert_eq!(consumed_request_body(), Some(vec![]));
+        assert_eq!(read(3), b"a=1&b=2");
+        assert_eq!(consumed_request_body(), Some(b"a=1&b=2".to_vec()));
+
+        let reader = RequestBody::from_reader(&b"streamed"[..], None);
+        BODY.with(|current| *current.borrow_mut() = Some(reader));
+        assert_eq!(read(4), b"streamed");
+        assert_eq!(consumed_request_body(), Some(vec![]));
+
+        let reader = RequestBody::from_reader(&b"streamed"[..], None).keep_consumed(6);
+        BODY.with(|current| *current.borrow_mut() = Some(reader));
+        assert_eq!(read(4), b"streamed");
+        assert_eq!(consumed_request_body(), Some(b"stream".to_vec()));
+
+        clear_request_body();
+        assert_eq!(consumed_request_body(), None);
+        assert_eq!(read(4), b"");
+    }
+
+    #[test]
+    fn test_channel_request_body() {
+        Embed::run(|| {
+            let (sender, body) = RequestBody::channel(None, 4, Some(Duration::from_secs(5)));
+            let body = body.keep_consumed(usize::MAX);
+            BODY.with(|current| *current.borrow_mut() = Some(body));
+            let feeder = thread::spawn(move || {
+                for chunk in ["a=1&", "b=2&", "c=3"] {
+                    assert!(sender.send_blocking(chunk));
+                }
+            });
+            assert_eq!(read(3), b"a=1&b=2&c=3");
+            feeder.join().expect("should feed the body");
+            assert_eq!(consumed_request_body(), Some(b"a=1&b=2&c=3".to_vec()));
+
+            // The sender stops once the body was dropped.
+            let (sender, body) = RequestBody::channel(None, 4, None);
+            drop(body);
+            assert!(!sender.send_blocking("ignored"));
+
+            // The body ends when no bytes are sent before the timeout.
+            let (_sender, body) = RequestBody::channel(None, 4, Some(Duration::from_millis(10)));
+            BODY.with(|current| *current.borrow_mut() = Some(body));
+            assert_eq!(read(4), b"");
+            clear_request_body();
+        });
+    }
+}
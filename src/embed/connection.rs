@@ -0,0 +1,303 @@
+//! The client connection of the requests handled by SAPI hosts.
+
+use std::{cell::RefCell, ffi::c_int, net::SocketAddr, ptr};
+
+use super::ServerVars;
+use crate::{
+    builders::FunctionBuilder,
+    flags::DataType,
+    types::{ZendHashTable, Zval},
+    zend::ExecuteData,
+};
+
+thread_local! {
+    static CONNECTION: RefCell<Option<Connection>> = const { RefCell::new(None) };
+}
+
+/// The client connection of a request, e.g. an accepted TCP connection,
+/// exposed to PHP by [`connection_fd`], [`register_connection_variables`],
+/// [`connection_stream`] and [`request_headers_function`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Connection {
+    #[cfg(unix)]
+    fd: Option<std::os::unix::io::RawFd>,
+    local_addr: Option<SocketAddr>,
+    peer_addr: Option<SocketAddr>,
+    headers: Vec<(String, String)>,
+}
+
+impl Connection {
+    /// Creates a connection without a socket, addresses or headers.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a connection from an accepted TCP connection, with its socket
+    /// and addresses.
+    ///
+    /// # Parameters
+    ///
+    /// * `stream` - The accepted connection, which must outlive the request.
+    pub fn from_tcp(stream: &std::net::TcpStream) -> Self {
+        let connection = Self {
+            local_addr: stream.local_addr().ok(),
+            peer_addr: stream.peer_addr().ok(),
+            ..Self::default()
+        };
+        #[cfg(unix)]
+        let connection = connection.fd(std::os::unix::io::AsRawFd::as_raw_fd(stream));
+        connection
+    }
+
+    /// Sets the socket of the connection, which must stay open until the
+    /// request ends.
+    #[cfg(unix)]
+    pub fn fd(mut self, fd: std::os::unix::io::RawFd) -> Self {
+        self.fd = Some(fd);
+        self
+    }
+
+    /// Sets the local address of the connection, i.e. the address of the
+    /// server.
+    pub fn local_addr(mut self, addr: SocketAddr) -> Self {
+        self.local_addr = Some(addr);
+        self
+    }
+
+    /// Sets the address of the client.
+    pub fn peer_addr(mut self, addr: SocketAddr) -> Self {
+        self.peer_addr = Some(addr);
+        self
+    }
+
+    /// Adds a header of the request.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Returns the server variables describing the connection, e.g.
+    /// `REMOTE_ADDR` and `HTTP_HOST`.
+    fn variables(&self) -> Vec<(String, String)> {
+        let mut variables = vec![];
+        if let Some(addr) = self.peer_addr {
+            variables.push(("REMOTE_ADDR".into(), addr.ip().to_string()));
+            variables.push(("REMOTE_PORT".into(), addr.port().to_string()));
+        }
+        if let Some(addr) = self.local_addr {
+            variables.push(("SERVER_ADDR".into(), addr.ip().to_string()));
+            variables.push(("SERVER_PORT".into(), addr.port().to_string()));
+        }
+        for (name, value) in &self.headers {
+            variables.push((header_variable(name), value.clone()));
+        }
+        variables
+    }
+}
+
+/// Returns the name of the server variable of a header, e.g. `HTTP_HOST`.
+pub(super) fn header_variable(name: &str) -> String {
+    let name = name.to_ascii_uppercase().replace('-', "_");
+    // Like other servers, the body headers are not prefixed.
+    match name.as_str() {
+        "CONTENT_TYPE" | "CONTENT_LENGTH" => name,
+        _ => format!("HTTP_{name}"),
+    }
+}
+
+/// Sets the connection of the next request handled on this thread. The
+/// connection replaces the previous one, and is kept until
+/// [`clear_connection`] is called.
+///
+/// # Parameters
+///
+/// * `connection` - The connection of the request.
+pub fn set_connection(connection: Connection) {
+    CONNECTION.with(|current| *current.borrow_mut() = Some(connection));
+}
+
+/// Discards the connection of the request handled on this thread.
+pub fn clear_connection() {
+    CONNECTION.with(|current| current.borrow_mut().take());
+}
+
+/// Returns the socket of the connection set with [`set_connection`], to be set
+/// as the get fd function of a SAPI with
+/// [`SapiBuilder::get_fd_function`](crate::builders::SapiBuilder::get_fd_function).
+// The function is called by the engine with a valid pointer, as its type
+// requires a safe function.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn connection_fd(fd: *mut c_int) -> c_int {
+    #[cfg(unix)]
+    {
+        let socket = CONNECTION.with(|current| current.borrow().as_ref().and_then(|c| c.fd));
+        if let (Some(socket), Some(fd)) = (socket, unsafe { fd.as_mut() }) {
+            *fd = socket;
+            return 0;
+        }
+    }
+    let _ = fd;
+    -1
+}
+
+/// Registers the addresses and headers of the connection set with
+/// [`set_connection`] as server variables, e.g. `REMOTE_ADDR`, `SERVER_PORT`
+/// and `HTTP_HOST`.
+///
+/// This can be set as the register server variables function of a SAPI with
+/// [`SapiBuilder::register_server_variables_function`](crate::builders::SapiBuilder::register_server_variables_function),
+/// or called from it.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn register_connection_variables(vars: *mut Zval) {
+    let variables = CONNECTION.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .map(Connection::variables)
+            .unwrap_or_default()
+    });
+    register_variables(variables, vars);
+}
+
+/// Registers server variables into the array given to the register server
+/// variables function of a SAPI.
+pub(super) fn register_variables(variables: Vec<(String, String)>, vars: *mut Zval) {
+    // SAFETY: The engine passes the array of server variables.
+    if let Some(vars) = unsafe { vars.as_mut() } {
+        ServerVars::register(
+            vars,
+            variables
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        );
+    }
+}
+
+/// Returns the socket of the connection set with [`set_connection`] as a
+/// stream resource, e.g. for `stream_socket_get_name()`.
+///
+/// The stream holds a duplicate of the socket, so closing it leaves the
+/// connection open.
+///
+/// # Returns
+///
+/// The stream, or [`None`] if the connection has no socket or it cannot be
+/// duplicated.
+#[cfg(unix)]
+pub fn connection_stream() -> Option<Zval> {
+    use std::os::unix::io::{AsRawFd, BorrowedFd, IntoRawFd};
+
+    let fd = CONNECTION.with(|current| current.borrow().as_ref().and_then(|c| c.fd))?;
+    // SAFETY: The socket is open until the connection is cleared.
+    let socket = unsafe { BorrowedFd::borrow_raw(fd) }
+        .try_clone_to_owned()
+        .ok()?;
+    let fd = socket.as_raw_fd();
+
+    let stream = unsafe {
+        #[cfg(php_debug)]
+        {
+            crate::ffi::_php_stream_sock_open_from_socket(
+                fd,
+                ptr::null(),
+                0,
+                ptr::null(),
+                0,
+                ptr::null(),
+                0,
+            )
+        }
+        #[cfg(not(php_debug))]
+        {
+            crate::ffi::_php_stream_sock_open_from_socket(fd, ptr::null())
+        }
+    };
+    let stream = unsafe { stream.as_mut() }?;
+    // The stream closes the duplicate from now on.
+    let _ = socket.into_raw_fd();
+
+    let mut zval = Zval::new();
+    zval.set_resource(stream.res);
+    stream.set___exposed(1);
+    Some(zval)
+}
+
+/// Returns a function returning the headers of the connection set with
+/// [`set_connection`], to be added to a SAPI with
+/// [`SapiBuilder::function`](crate::builders::SapiBuilder::function) as
+/// `apache_request_headers` and its alias `getallheaders`.
+///
+/// # Parameters
+///
+/// * `name` - The name of the function.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::{
+///     builders::SapiBuilder,
+///     embed::{connection_fd, register_connection_variables, request_headers_function},
+/// };
+///
+/// let sapi = SapiBuilder::new("my-server", "My Server")
+///     .get_fd_function(connection_fd)
+///     .register_server_variables_function(register_connection_variables)
+///     .function(request_headers_function("apache_request_headers"))
+///     .function(request_headers_function("getallheaders"))
+///     .build();
+/// ```
+pub fn request_headers_function(name: &str) -> FunctionBuilder<'static> {
+    FunctionBuilder::new(name, request_headers).returns(DataType::Array, false, false)
+}
+
+/// Sets the headers of the connection as the return value.
+fn return_headers(retval: &mut Zval) {
+    let mut headers = ZendHashTable::new();
+    CONNECTION.with(|current| {
+        if let Some(connection) = current.borrow().as_ref() {
+            for (name, value) in &connection.headers {
+                let _ = headers.insert(name, value.as_str());
+            }
+        }
+    });
+    retval.set_hashtable(headers);
+}
+
+#[cfg(not(windows))]
+extern "C" fn request_headers(_: &mut ExecuteData, retval: &mut Zval) {
+    return_headers(retval);
+}
+
+#[cfg(windows)]
+extern "vectorcall" fn request_headers(_: &mut ExecuteData, retval: &mut Zval) {
+    return_headers(retval);
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variables() {
+        let connection = Connection::new()
+            .peer_addr("192.0.2.1:51234".parse().expect("should parse"))
+            .local_addr("[::1]:8080".parse().expect("should parse"))
+            .header("Host", "example.com")
+            .header("Content-Type", "text/plain")
+            .header("X-Request-Id", "42");
+        assert_eq!(
+            connection.variables(),
+            [
+                ("REMOTE_ADDR", "192.0.2.1"),
+                ("REMOTE_PORT", "51234"),
+                ("SERVER_ADDR", "::1"),
+                ("SERVER_PORT", "8080"),
+                ("HTTP_HOST", "example.com"),
+                ("CONTENT_TYPE", "text/plain"),
+                ("HTTP_X_REQUEST_ID", "42"),
+            ]
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+        );
+    }
+}
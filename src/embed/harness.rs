@@ -0,0 +1,124 @@
+//! The runtime of the tests generated by the `#[php_test]` attribute.
+
+use std::{
+    ffi::c_char,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use parking_lot::{const_mutex, Mutex};
+
+use super::{RequestContext, RUN_FN_LOCK};
+use crate::{
+    builders::SapiBuilder,
+    ffi::{
+        ext_php_rs_sapi_per_thread_init, ext_php_rs_sapi_startup, php_module_startup, sapi_startup,
+        ZEND_RESULT_CODE_SUCCESS,
+    },
+    zend::{try_catch_first, ModuleEntry},
+};
+
+/// The function returning the module entry of an extension, generated by the
+/// `#[php_module]` attribute as `get_module`.
+pub type GetModule = extern "C" fn() -> *mut ModuleEntry;
+
+/// The module PHP was started with, which stays started until the process
+/// exits, as modules cannot be started twice.
+static STARTED: Mutex<Option<GetModule>> = const_mutex(None);
+
+/// The INI settings of the engine, as set by the embed SAPI.
+const INI_ENTRIES: &std::ffi::CStr =
+    c"html_errors=0\ndisplay_errors=1\nimplicit_flush=1\nmax_execution_time=0\n";
+
+/// Runs a test inside a new request of PHP, started with the given module
+/// on first use. This is called by the tests generated by the `#[php_test]`
+/// attribute.
+///
+/// The requests of all tests are run one at a time, along with the ones of
+/// [`Embed::run`](super::Embed::run).
+///
+/// # Parameters
+///
+/// * `module` - The function returning the module entry of the extension.
+/// * `test` - The test, which may use [`Embed::eval`](super::Embed::eval).
+///
+/// # Panics
+///
+/// Panics if the test panics, if PHP fails to start or bails out of the
+/// test, e.g. on a fatal error, or if PHP was started with another module.
+pub fn run_test<R, F: FnOnce() -> R>(module: GetModule, test: F) -> R {
+    let _guard = RUN_FN_LOCK.write();
+    let mut started = STARTED.lock();
+    match *started {
+        Some(started) => assert!(
+            ptr::fn_addr_eq(started, module),
+            "PHP was already started with another module"
+        ),
+        None => {
+            start(module);
+            *started = Some(module);
+        }
+    }
+    drop(started);
+
+    request(test).expect("PHP bailed out of the test")
+}
+
+/// Returns whether PHP was started by [`run_test`].
+pub(super) fn is_started() -> bool {
+    STARTED.lock().is_some()
+}
+
+/// Starts PHP with a module.
+fn start(module: GetModule) {
+    let sapi = SapiBuilder::new("ext-php-rs-test", "ext-php-rs test harness")
+        .ub_write_function(write_output)
+        .build()
+        .expect("should build the SAPI")
+        .into_raw();
+
+    unsafe {
+        (*sapi).ini_entries = INI_ENTRIES.as_ptr();
+        ext_php_rs_sapi_startup();
+        sapi_startup(sapi);
+        assert_eq!(
+            php_module_startup(sapi, module()),
+            ZEND_RESULT_CODE_SUCCESS,
+            "should start PHP with the module"
+        );
+    }
+}
+
+/// Runs a function inside a new request of the started PHP.
+///
+/// # Returns
+///
+/// The result of the function, or [`None`] if PHP bailed out of it.
+pub(super) fn request<R, F: FnOnce() -> R>(func: F) -> Option<R> {
+    unsafe { ext_php_rs_sapi_per_thread_init() };
+    let context = RequestContext::start().expect("should start the request");
+
+    // The request is shut down before a panic of the function is resumed, so
+    // the next test gets a clean one.
+    let mut func = AssertUnwindSafe(Some(func));
+    let result = try_catch_first(|| {
+        let func = func.take().expect("should run the function once");
+        panic::catch_unwind(AssertUnwindSafe(func))
+    });
+
+    drop(context);
+
+    match result {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(panic)) => panic::resume_unwind(panic),
+        Err(_) => None,
+    }
+}
+
+/// Prints the output of scripts, so it is captured along with the output of
+/// the test.
+extern "C" fn write_output(str: *const c_char, str_length: usize) -> usize {
+    let output = unsafe { std::slice::from_raw_parts(str.cast::<u8>(), str_length) };
+    print!("{}", String::from_utf8_lossy(output));
+    str_length
+}
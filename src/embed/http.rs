@@ -0,0 +1,409 @@
+//! Typed HTTP requests and responses, for servers built on a SAPI.
+//!
+//! A SAPI built from a builder given to [`http_sapi`] serves each [`Request`]
+//! handled with [`Request::handle`] to PHP, and collects what the script sends
+//! back into a [`Response`]:
+//!
+//! * the method, URI, query string, content type and cookies of the request
+//!   are set as the request info of the SAPI,
+//! * the body is read by PHP through [`read_request_body`],
+//! * the headers are registered as server variables, e.g. `HTTP_HOST`, along
+//!   with `REQUEST_METHOD`, `REQUEST_URI` and `QUERY_STRING`, and the
+//!   variables of the connection set with [`set_connection`],
+//! * the output, status code and headers sent by PHP are collected.
+//!
+//! [`set_connection`]: super::set_connection
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    ffi::{c_char, c_int, c_void, CString},
+    fmt::{self, Debug},
+    ptr, slice,
+};
+
+use super::{
+    body::{clear_request_body, read_request_body, set_request_body, RequestBody},
+    connection::{header_variable, register_connection_variables, register_variables},
+    EmbedError, RequestContext,
+};
+use crate::{
+    builders::{RequestInfo, RequestInfoBuilder, SapiBuilder},
+    ffi::{sapi_header_struct, sapi_headers_struct},
+    types::Zval,
+    zend::{SapiGlobals, SapiHeader, SapiHeaders},
+};
+
+thread_local! {
+    /// The request handled on this thread.
+    static ACTIVE: RefCell<Option<Box<Active>>> = const { RefCell::new(None) };
+    /// The response of the request handled on this thread.
+    static RESPONSE: RefCell<Response> = const { RefCell::new(Response::new()) };
+}
+
+/// Sets the hooks serving a [`Request`] to PHP and collecting its
+/// [`Response`] on a SAPI builder, replacing the hooks writing the output,
+/// sending the headers, reading the body and cookies, and registering the
+/// server variables.
+///
+/// # Parameters
+///
+/// * `builder` - The builder of the SAPI.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::{
+///     builders::SapiBuilder,
+///     embed::{http_sapi, Embed, Request},
+/// };
+///
+/// let sapi = http_sapi(SapiBuilder::new("my-server", "My Server")).build();
+///
+/// // Once PHP was started with the SAPI, for each request:
+/// let response = Request::new("POST", "/users?notify=1")
+///     .header("Content-Type", "application/json")
+///     .body(r#"{"name":"world"}"#)
+///     .handle(|| {
+///         let _ = Embed::run_script("index.php");
+///     })
+///     .expect("should start the request");
+/// println!("{} {:?}", response.status, response.header("Location"));
+/// ```
+pub fn http_sapi(builder: SapiBuilder) -> SapiBuilder {
+    builder
+        .ub_write_function(write_response)
+        .send_headers_function(send_response_status)
+        .send_header_function(send_response_header)
+        .read_post_function(read_request_body)
+        .read_cookies_function(read_request_cookies)
+        .register_server_variables_function(register_request_variables)
+}
+
+/// An HTTP request, served to PHP by a SAPI built with [`http_sapi`].
+pub struct Request {
+    method: String,
+    uri: String,
+    headers: Vec<(String, String)>,
+    server_variables: HashMap<String, String>,
+    body: Option<RequestBody>,
+}
+
+impl Request {
+    /// Creates a request without headers or body.
+    ///
+    /// # Parameters
+    ///
+    /// * `method` - The method, e.g. `GET`.
+    /// * `uri` - The URI, with the query string, e.g. `/search?q=php`.
+    pub fn new(method: impl Into<String>, uri: impl Into<String>) -> Self {
+        Self {
+            method: method.into(),
+            uri: uri.into(),
+            headers: vec![],
+            server_variables: HashMap::new(),
+            body: None,
+        }
+    }
+
+    /// Adds a header.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Sets additional server variables, e.g. `SCRIPT_FILENAME` and
+    /// `DOCUMENT_ROOT`, registered after the variables describing the request,
+    /// which they replace. Replaces the variables previously set.
+    pub fn server_variables(mut self, variables: HashMap<String, String>) -> Self {
+        self.server_variables = variables;
+        self
+    }
+
+    /// Sets the body, e.g. a [`Vec<u8>`] or a [`RequestBody::from_reader`].
+    pub fn body(mut self, body: impl Into<RequestBody>) -> Self {
+        self.body = Some(body.into());
+        self
+    }
+
+    /// Returns the method of the request.
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    /// Returns the URI of the request, with the query string.
+    pub fn uri(&self) -> &str {
+        &self.uri
+    }
+
+    /// Returns the query string of the URI, if any.
+    pub fn query_string(&self) -> Option<&str> {
+        self.uri.split_once('?').map(|(_, query)| query)
+    }
+
+    /// Returns the value of the first header with the given name, compared
+    /// case-insensitively.
+    pub fn header_value(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Handles the request on the current thread: starts a request of PHP
+    /// with it, runs a function inside it, e.g. running a script, and shuts
+    /// it down.
+    ///
+    /// PHP must be started with a SAPI built with [`http_sapi`]. A bailout of
+    /// the function, e.g. on `exit()` or a fatal error, skips the rest of the
+    /// function, and the response sent until then is returned.
+    ///
+    /// # Parameters
+    ///
+    /// * `func` - The function, which may use
+    ///   [`Embed::run_script`](super::Embed::run_script).
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EmbedError::InitError`] if the method, URI or content type
+    /// contain a NUL byte, or the request could not be started.
+    pub fn handle<F: FnOnce()>(mut self, func: F) -> Result<Response, EmbedError> {
+        RESPONSE.with(|response| *response.borrow_mut() = Response::new());
+        let mut installed = Installed::new(&mut self)?;
+        {
+            // The request is shut down before the request info is cleared,
+            // as PHP reads the method while shutting it down.
+            let context = RequestContext::start()?;
+            installed.info.request_started();
+            let _ = context.run(func);
+        }
+        Ok(RESPONSE.with(|response| response.take()))
+    }
+
+    /// Returns the server variables describing the request, e.g.
+    /// `REQUEST_METHOD` and `HTTP_HOST`.
+    fn variables(&self) -> Vec<(String, String)> {
+        let mut variables = vec![
+            ("REQUEST_METHOD".into(), self.method.clone()),
+            ("REQUEST_URI".into(), self.uri.clone()),
+            (
+                "QUERY_STRING".into(),
+                self.query_string().unwrap_or_default().into(),
+            ),
+        ];
+        for (name, value) in &self.headers {
+            variables.push((header_variable(name), value.clone()));
+        }
+        variables.extend(self.server_variables.clone());
+        variables
+    }
+}
+
+impl Debug for Request {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Request")
+            .field("method", &self.method)
+            .field("uri", &self.uri)
+            .field("headers", &self.headers)
+            .field("server_variables", &self.server_variables)
+            .field("body", &self.body)
+            .finish()
+    }
+}
+
+/// The response to a [`Request`], sent by PHP.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// The status code.
+    pub status: u16,
+    /// The headers, in order, e.g. `("Content-Type", "text/html")`.
+    pub headers: Vec<(String, String)>,
+    /// The output of the request.
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    /// Creates an empty response, with the default status code.
+    const fn new() -> Self {
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Returns the value of the last header with the given name, compared
+    /// case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .rev()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the body as text, replacing invalid UTF-8.
+    pub fn body_str(&self) -> String {
+        String::from_utf8_lossy(&self.body).into_owned()
+    }
+}
+
+impl Default for Response {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The request handled on this thread, along with the cookies read by the
+/// SAPI.
+struct Active {
+    variables: Vec<(String, String)>,
+    cookies: Option<CString>,
+}
+
+/// Installs a request as the one handled on this thread, until dropped.
+struct Installed {
+    info: RequestInfo,
+}
+
+impl Installed {
+    fn new(request: &mut Request) -> Result<Self, EmbedError> {
+        let mut info = RequestInfoBuilder::new()
+            .request_method(request.method.as_str())
+            .request_uri(request.uri.as_str());
+        if let Some(query_string) = request.query_string() {
+            info = info.query_string(query_string);
+        }
+        if let Some(content_type) = request.header_value("Content-Type") {
+            info = info.content_type(content_type);
+        }
+        if let Some(length) = request
+            .header_value("Content-Length")
+            .and_then(|value| value.trim().parse().ok())
+        {
+            info = info.content_length(length);
+        }
+        let info = info.install().map_err(|_| EmbedError::InitError)?;
+
+        let active = Box::new(Active {
+            variables: request.variables(),
+            cookies: request
+                .header_value("Cookie")
+                .and_then(|value| CString::new(value).ok()),
+        });
+        // PHP only reads the body and cookies of requests with a server
+        // context.
+        SapiGlobals::get_mut()
+            .set_server_context(ptr::from_ref::<Active>(&active).cast_mut().cast());
+        ACTIVE.with(|current| *current.borrow_mut() = Some(active));
+
+        // Sets the content length of the request if the length of the body
+        // is known.
+        match request.body.take() {
+            Some(body) => set_request_body(body),
+            None => clear_request_body(),
+        }
+        Ok(Self { info })
+    }
+}
+
+impl Drop for Installed {
+    fn drop(&mut self) {
+        SapiGlobals::get_mut().set_server_context(ptr::null_mut());
+        ACTIVE.with(|current| current.borrow_mut().take());
+        clear_request_body();
+    }
+}
+
+fn as_ptr(string: &Option<CString>) -> *const c_char {
+    string
+        .as_ref()
+        .map_or(ptr::null(), |string| string.as_ptr())
+}
+
+extern "C" fn write_response(str: *const c_char, str_length: usize) -> usize {
+    let output = unsafe { slice::from_raw_parts(str.cast::<u8>(), str_length) };
+    RESPONSE.with(|response| response.borrow_mut().body.extend_from_slice(output));
+    str_length
+}
+
+/// Collects the status code, and lets PHP send the headers one by one to
+/// [`send_response_header`].
+extern "C" fn send_response_status(sapi_headers: *mut sapi_headers_struct) -> c_int {
+    /// `SAPI_HEADER_DO_SEND`, which is a macro.
+    const SAPI_HEADER_DO_SEND: c_int = 2;
+
+    if let Some(status) =
+        unsafe { SapiHeaders::from_ptr(sapi_headers) }.and_then(SapiHeaders::status_code)
+    {
+        RESPONSE.with(|response| response.borrow_mut().status = status);
+    }
+    SAPI_HEADER_DO_SEND
+}
+
+extern "C" fn send_response_header(header: *mut sapi_header_struct, _server_context: *mut c_void) {
+    // The end of the headers is marked by a null header.
+    let Some(header) = (unsafe { SapiHeader::from_ptr(header) }) else {
+        return;
+    };
+    let header = String::from_utf8_lossy(header.as_bytes());
+    if let Some((name, value)) = header.split_once(':') {
+        RESPONSE.with(|response| {
+            response
+                .borrow_mut()
+                .headers
+                .push((name.trim().to_string(), value.trim().to_string()))
+        });
+    }
+}
+
+extern "C" fn read_request_cookies() -> *mut c_char {
+    ACTIVE.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |active| as_ptr(&active.cookies))
+            .cast_mut()
+    })
+}
+
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+extern "C" fn register_request_variables(vars: *mut Zval) {
+    let variables = ACTIVE.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .map(|active| active.variables.clone())
+            .unwrap_or_default()
+    });
+    register_variables(variables, vars);
+    register_connection_variables(vars);
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_variables() {
+        let request = Request::new("GET", "/search?q=php")
+            .header("Host", "example.com")
+            .header("Cookie", "session=1")
+            .server_variables(HashMap::from([("HTTPS".into(), "on".into())]));
+        assert_eq!(request.query_string(), Some("q=php"));
+        assert_eq!(request.header_value("cookie"), Some("session=1"));
+        assert_eq!(
+            request.variables(),
+            [
+                ("REQUEST_METHOD", "GET"),
+                ("REQUEST_URI", "/search?q=php"),
+                ("QUERY_STRING", "q=php"),
+                ("HTTP_HOST", "example.com"),
+                ("HTTP_COOKIE", "session=1"),
+                ("HTTPS", "on"),
+            ]
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+        );
+    }
+}
@@ -5,7 +5,17 @@
 //! version You should only use this crate for test purpose, it's not production
 //! ready
 
+mod body;
+mod connection;
+mod harness;
+mod http;
+#[cfg(any(docs, php_zts))]
+mod pool;
+mod request;
+mod roundtrip;
 mod sapi;
+mod server_vars;
+mod test_sapi;
 
 use crate::boxed::ZBox;
 use crate::ffi::{
@@ -21,12 +31,33 @@ use std::path::Path;
 use std::ptr::null_mut;
 
 pub use crate::ffi::{
-    ext_php_rs_embed_callback,
+    ext_php_rs_embed_callback, ext_php_rs_sapi_per_thread_init, ext_php_rs_sapi_shutdown,
     ext_php_rs_sapi_startup,
-    ext_php_rs_sapi_shutdown,
-    ext_php_rs_sapi_per_thread_init
 };
+pub use body::{
+    clear_request_body, consumed_request_body, read_request_body, set_request_body, BodySender,
+    RequestBody, SendBody,
+};
+#[cfg(unix)]
+pub use connection::connection_stream;
+pub use connection::{
+    clear_connection, connection_fd, register_connection_variables, request_headers_function,
+    set_connection, Connection,
+};
+pub use harness::{run_test, GetModule};
+pub use http::{http_sapi, Request, Response};
+#[cfg(any(docs, php_zts))]
+#[cfg_attr(docs, doc(cfg(php_zts)))]
+pub use pool::{InterpreterPool, PoolBuilder, PoolMetrics, PoolTask};
+pub use request::RequestContext;
+#[doc(hidden)]
+pub use roundtrip::assert_from_php;
+pub use roundtrip::{eval_with, var_export};
 pub use sapi::SapiModule;
+pub use server_vars::{
+    clear_server_variables, register_server_variables, set_server_variables, ServerVars,
+};
+pub use test_sapi::{Recording, TestSapi};
 
 /// Embed SAPI engine
 pub struct Embed;
@@ -46,6 +77,10 @@ pub enum EmbedError {
     InvalidPath,
     /// PHP bailout
     CatchError,
+    /// The request exceeded the timeout of the [`InterpreterPool`] executing it
+    Timeout,
+    /// The [`InterpreterPool`] was dropped before executing the request
+    PoolClosed,
 }
 
 impl EmbedError {
@@ -55,8 +90,34 @@ impl EmbedError {
     }
 }
 
+/// Shuts PHP down on the current thread once it no longer handles requests,
+/// freeing the globals of the thread in thread-safe builds.
+///
+/// The closures dispatched to the thread with a
+/// [`PhpDispatcher`](crate::dispatcher::PhpDispatcher) which did not run yet
+/// are dropped first, and no closure can be dispatched to it afterwards.
+///
+/// # Safety
+///
+/// No request may be running on the thread, and PHP must have been started
+/// up on it with [`ext_php_rs_sapi_per_thread_init`].
+pub unsafe fn ext_php_rs_sapi_per_thread_shutdown() {
+    crate::dispatcher::close_queue();
+    crate::ffi::ext_php_rs_sapi_per_thread_shutdown();
+}
+
 static RUN_FN_LOCK: RwLock<()> = const_rwlock(());
 
+/// Runs the closure of [`Embed::run`] between the request hooks of the crate.
+unsafe extern "C" fn request_wrapper<R, F: FnMut() -> R + RefUnwindSafe>(
+    ctx: *const c_void,
+) -> *const c_void {
+    crate::internal::start_request();
+    let result = panic_wrapper::<R, F>(ctx);
+    crate::internal::finish_request();
+    result
+}
+
 impl Embed {
     /// Run a php script from a file
     ///
@@ -149,11 +210,25 @@ impl Embed {
         // avoid doing that in this case
         let _guard = RUN_FN_LOCK.write();
 
+        // PHP started by `#[php_test]` stays started, so the function runs in a
+        // request of it instead.
+        if harness::is_started() {
+            return harness::request(func).unwrap_or_default();
+        }
+
+        // The Zend memory manager serves allocations from its own chunks, hiding
+        // invalid accesses from AddressSanitizer, so the system allocator is used
+        // instead. It is selected when PHP starts up.
+        #[cfg(feature = "asan")]
+        if std::env::var_os("USE_ZEND_ALLOC").is_none() {
+            std::env::set_var("USE_ZEND_ALLOC", "0");
+        }
+
         let panic = unsafe {
             ext_php_rs_embed_callback(
                 0,
                 null_mut(),
-                panic_wrapper::<R, F>,
+                request_wrapper::<R, F>,
                 &func as *const F as *const c_void,
             )
         };
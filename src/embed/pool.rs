@@ -0,0 +1,526 @@
+//! A pool of threads executing PHP requests, for hosts serving concurrent
+//! requests with a thread-safe (ZTS) build of PHP.
+//!
+//! An [`InterpreterPool`] spawns its threads up front, each with its own
+//! globals, and executes the closures submitted to it inside a new request on
+//! the first idle thread. A request running longer than the timeout of the
+//! pool is aborted with the fatal error of `max_execution_time`. Threads are
+//! recycled, i.e. replaced by a new thread with fresh globals, after a number
+//! of requests, or once the memory retained by their memory manager grows
+//! beyond a limit, so the leaks of extensions do not accumulate.
+//!
+//! PHP must be started before the pool is built, e.g. with
+//! [`ext_php_rs_sapi_startup`](super::ext_php_rs_sapi_startup),
+//! `sapi_startup` and `php_module_startup`, and shut down after the pool is
+//! dropped.
+
+use std::{
+    collections::VecDeque,
+    fmt::{self, Debug},
+    future::Future,
+    io,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    thread,
+    time::{Duration, Instant},
+};
+
+use parking_lot::{Condvar, Mutex};
+
+use super::{ext_php_rs_sapi_per_thread_shutdown, EmbedError, RequestContext};
+use crate::{
+    alloc::memory_usage,
+    dispatcher::{channel, Dispatched},
+    ffi::{
+        ext_php_rs_sapi_per_thread_init, php_request_shutdown, php_request_startup, zend_long,
+        ZEND_RESULT_CODE_SUCCESS,
+    },
+    zend::{interrupt::InterruptHandle, try_catch_first, ExecutorGlobals},
+};
+
+type Job = Box<dyn FnOnce(&Interpreter) + Send>;
+
+/// Builds an [`InterpreterPool`].
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use ext_php_rs::embed::{Embed, PoolBuilder};
+///
+/// // Once PHP was started:
+/// let pool = PoolBuilder::new(4)
+///     .max_requests(1000)
+///     .max_memory(64 * 1024 * 1024)
+///     .timeout(Duration::from_secs(30))
+///     .build()
+///     .expect("should spawn the threads");
+///
+/// let version = pool
+///     .execute(|| Embed::eval("PHP_VERSION").ok().and_then(|zval| zval.string()))
+///     .wait();
+/// println!("running PHP {version:?}: {:?}", pool.metrics());
+/// ```
+#[derive(Debug, Clone)]
+pub struct PoolBuilder {
+    threads: usize,
+    config: Config,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Config {
+    max_requests: Option<u64>,
+    max_memory: Option<usize>,
+    timeout: Option<Duration>,
+}
+
+impl PoolBuilder {
+    /// Creates a builder of a pool running PHP on the given number of
+    /// threads, without timeout, which never recycles its threads.
+    ///
+    /// # Parameters
+    ///
+    /// * `threads` - The number of threads, at least one.
+    pub fn new(threads: usize) -> Self {
+        Self {
+            threads: threads.max(1),
+            config: Config::default(),
+        }
+    }
+
+    /// Recycles a thread after it executed the given number of requests.
+    ///
+    /// # Parameters
+    ///
+    /// * `requests` - The number of requests, at least one.
+    pub fn max_requests(mut self, requests: u64) -> Self {
+        self.config.max_requests = Some(requests.max(1));
+        self
+    }
+
+    /// Recycles a thread once the memory its memory manager reserved from
+    /// the system exceeds the given size after a request.
+    ///
+    /// # Parameters
+    ///
+    /// * `bytes` - The size in bytes.
+    pub fn max_memory(mut self, bytes: usize) -> Self {
+        self.config.max_memory = Some(bytes);
+        self
+    }
+
+    /// Aborts the requests running longer than the given duration, with the
+    /// fatal error of `max_execution_time`. Like `max_execution_time`, a
+    /// request blocked in a long running internal function, e.g. a database
+    /// query, is only aborted once the function returns.
+    ///
+    /// # Parameters
+    ///
+    /// * `timeout` - The maximum duration of a request.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.config.timeout = Some(timeout);
+        self
+    }
+
+    /// Spawns the threads of the pool, and of its watchdog if a timeout was
+    /// set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a thread could not be spawned.
+    pub fn build(self) -> io::Result<InterpreterPool> {
+        let shared = Arc::new(Shared {
+            config: self.config,
+            state: Mutex::new(State {
+                jobs: VecDeque::new(),
+                closed: false,
+                threads: 0,
+            }),
+            queued: Condvar::new(),
+            exited: Condvar::new(),
+            running: Mutex::new(vec![None; self.threads]),
+            completed: AtomicU64::new(0),
+            failed: AtomicU64::new(0),
+            timed_out: AtomicU64::new(0),
+            recycled: AtomicU64::new(0),
+        });
+        // Dropping the pool on error waits for the threads already spawned.
+        let pool = InterpreterPool { shared };
+
+        if let Some(timeout) = self.config.timeout {
+            let shared = pool.shared.clone();
+            thread::Builder::new()
+                .name("php-pool-watchdog".into())
+                .spawn(move || watch(&shared, timeout))?;
+        }
+        for slot in 0..self.threads {
+            pool.shared.state.lock().threads += 1;
+            if let Err(e) = spawn(pool.shared.clone(), slot) {
+                pool.shared.exit_thread();
+                return Err(e);
+            }
+        }
+        Ok(pool)
+    }
+}
+
+/// A pool of threads executing PHP requests, built with [`PoolBuilder`].
+///
+/// Dropping the pool drops the closures it did not start executing yet, and
+/// waits for the running ones and for its threads to exit.
+pub struct InterpreterPool {
+    shared: Arc<Shared>,
+}
+
+impl InterpreterPool {
+    /// Queues a closure to execute inside a new request, on the first idle
+    /// thread of the pool.
+    ///
+    /// Returns a handle receiving the result of the closure.
+    ///
+    /// # Parameters
+    ///
+    /// * `f` - The closure, which may use [`Embed::eval`](super::Embed::eval).
+    pub fn execute<F, R>(&self, f: F) -> PoolTask<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (sender, dispatched) = channel();
+        let job: Job = Box::new(move |interpreter| {
+            let result = interpreter.request(f);
+            let counter = match &result {
+                Outcome::Returned(Ok(_)) => &interpreter.shared.completed,
+                Outcome::TimedOut => &interpreter.shared.timed_out,
+                _ => &interpreter.shared.failed,
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+            sender.send(result);
+        });
+
+        self.shared.state.lock().jobs.push_back(job);
+        self.shared.queued.notify_one();
+        PoolTask(dispatched)
+    }
+
+    /// Returns the current metrics of the pool.
+    pub fn metrics(&self) -> PoolMetrics {
+        let (threads, queued) = {
+            let state = self.shared.state.lock();
+            (state.threads, state.jobs.len())
+        };
+        PoolMetrics {
+            threads,
+            busy: self.shared.running.lock().iter().flatten().count(),
+            queued,
+            completed: self.shared.completed.load(Ordering::Relaxed),
+            failed: self.shared.failed.load(Ordering::Relaxed),
+            timed_out: self.shared.timed_out.load(Ordering::Relaxed),
+            recycled: self.shared.recycled.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Debug for InterpreterPool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InterpreterPool")
+            .field("config", &self.shared.config)
+            .field("metrics", &self.metrics())
+            .finish()
+    }
+}
+
+impl Drop for InterpreterPool {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock();
+        state.closed = true;
+        let jobs = std::mem::take(&mut state.jobs);
+        self.shared.queued.notify_all();
+        while state.threads > 0 {
+            self.shared.exited.wait(&mut state);
+        }
+        drop(state);
+        drop(jobs);
+    }
+}
+
+/// The metrics of an [`InterpreterPool`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolMetrics {
+    /// The number of threads of the pool.
+    pub threads: usize,
+    /// The number of threads executing a request.
+    pub busy: usize,
+    /// The number of closures waiting for an idle thread.
+    pub queued: usize,
+    /// The number of requests which completed.
+    pub completed: u64,
+    /// The number of requests which panicked or bailed out, e.g. on a fatal
+    /// error, besides the ones which timed out.
+    pub failed: u64,
+    /// The number of requests aborted by the timeout of the pool.
+    pub timed_out: u64,
+    /// The number of threads which were recycled.
+    pub recycled: u64,
+}
+
+/// The result of a closure executed by an [`InterpreterPool`].
+///
+/// The result can be waited for by blocking the thread with
+/// [`PoolTask::wait`], or awaited as a future. A panic of the closure is
+/// resumed on the thread receiving the result.
+pub struct PoolTask<R>(Dispatched<Outcome<R>>);
+
+impl<R> PoolTask<R> {
+    /// Blocks the thread until the request ended, and returns the result of
+    /// the closure.
+    ///
+    /// # Errors
+    ///
+    /// * [`EmbedError::Timeout`] - The request exceeded the timeout of the
+    ///   pool.
+    /// * [`EmbedError::CatchError`] - PHP bailed out of the request, e.g. on a
+    ///   fatal error.
+    /// * [`EmbedError::InitError`] - The request could not be started.
+    /// * [`EmbedError::PoolClosed`] - The pool was dropped before executing
+    ///   the closure.
+    pub fn wait(self) -> Result<R, EmbedError> {
+        finish(self.0.wait())
+    }
+
+    /// Returns whether the request ended, or will never run.
+    pub fn is_ready(&self) -> bool {
+        self.0.is_ready()
+    }
+}
+
+impl<R> Future for PoolTask<R> {
+    type Output = Result<R, EmbedError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.0).poll(cx).map(finish)
+    }
+}
+
+impl<R> Debug for PoolTask<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("PoolTask").field(&self.0).finish()
+    }
+}
+
+fn finish<R>(outcome: crate::error::Result<Outcome<R>>) -> Result<R, EmbedError> {
+    match outcome {
+        Ok(Outcome::Returned(Ok(value))) => Ok(value),
+        Ok(Outcome::Returned(Err(panic))) => panic::resume_unwind(panic),
+        Ok(Outcome::StartupFailed) => Err(EmbedError::InitError),
+        Ok(Outcome::BailedOut) => Err(EmbedError::CatchError),
+        Ok(Outcome::TimedOut) => Err(EmbedError::Timeout),
+        Err(_) => Err(EmbedError::PoolClosed),
+    }
+}
+
+/// How a request executed by the pool ended, sent to its [`PoolTask`].
+enum Outcome<R> {
+    /// The closure returned or panicked.
+    Returned(thread::Result<R>),
+    StartupFailed,
+    BailedOut,
+    TimedOut,
+}
+
+/// The state shared by the pool and its threads.
+struct Shared {
+    config: Config,
+    state: Mutex<State>,
+    /// Notified when a closure is queued or the pool is dropped.
+    queued: Condvar,
+    /// Notified when a thread exits.
+    exited: Condvar,
+    /// The request running on each thread of the pool.
+    running: Mutex<Vec<Option<Running>>>,
+    completed: AtomicU64,
+    failed: AtomicU64,
+    timed_out: AtomicU64,
+    recycled: AtomicU64,
+}
+
+struct State {
+    jobs: VecDeque<Job>,
+    closed: bool,
+    threads: usize,
+}
+
+#[derive(Clone, Copy)]
+struct Running {
+    started: Instant,
+    handle: InterruptHandle,
+    timed_out: bool,
+}
+
+impl Shared {
+    /// Waits for the next closure to execute, or returns [`None`] once the
+    /// pool was dropped.
+    fn next_job(&self) -> Option<Job> {
+        let mut state = self.state.lock();
+        loop {
+            if state.closed {
+                return None;
+            }
+            if let Some(job) = state.jobs.pop_front() {
+                return Some(job);
+            }
+            self.queued.wait(&mut state);
+        }
+    }
+
+    /// Records that a thread of the pool exited.
+    fn exit_thread(&self) {
+        self.state.lock().threads -= 1;
+        self.exited.notify_all();
+    }
+}
+
+/// A thread of the pool.
+struct Interpreter {
+    shared: Arc<Shared>,
+    slot: usize,
+}
+
+impl Interpreter {
+    /// Runs a function inside a new request.
+    fn request<R, F: FnOnce() -> R>(&self, func: F) -> Outcome<R> {
+        let Ok(context) = RequestContext::start() else {
+            return Outcome::StartupFailed;
+        };
+
+        if let Some(timeout) = self.shared.config.timeout {
+            // Only used in the message of the fatal error.
+            let seconds = timeout.as_secs() + u64::from(timeout.subsec_nanos() > 0);
+            ExecutorGlobals::get_mut().timeout_seconds =
+                zend_long::try_from(seconds).unwrap_or(zend_long::MAX);
+        }
+        self.shared.running.lock()[self.slot] = Some(Running {
+            started: Instant::now(),
+            handle: InterruptHandle::current(),
+            timed_out: false,
+        });
+
+        let mut func = AssertUnwindSafe(Some(func));
+        let result = try_catch_first(|| {
+            let func = func.take().expect("should run the function once");
+            panic::catch_unwind(AssertUnwindSafe(func))
+        });
+
+        // The request stops being watched before it shuts down, which resets
+        // the timed out flag.
+        let timed_out = self.shared.running.lock()[self.slot]
+            .take()
+            .is_some_and(|running| running.timed_out);
+        drop(context);
+
+        match result {
+            _ if timed_out => Outcome::TimedOut,
+            Ok(result) => Outcome::Returned(result),
+            Err(_) => Outcome::BailedOut,
+        }
+    }
+}
+
+/// Spawns a thread of the pool, already counted in its state.
+fn spawn(shared: Arc<Shared>, slot: usize) -> io::Result<()> {
+    thread::Builder::new()
+        .name("php-pool".into())
+        .spawn(move || run(shared, slot))
+        .map(|_| ())
+}
+
+/// Executes the closures of the pool on the current thread until the pool is
+/// dropped, or the thread must be recycled.
+fn run(shared: Arc<Shared>, slot: usize) {
+    unsafe { ext_php_rs_sapi_per_thread_init() };
+
+    let interpreter = Interpreter { shared, slot };
+    let config = interpreter.shared.config;
+    let mut requests = 0;
+    let recycle = loop {
+        let Some(job) = interpreter.shared.next_job() else {
+            break false;
+        };
+        job(&interpreter);
+        requests += 1;
+        if config.max_requests.is_some_and(|max| requests >= max)
+            || config
+                .max_memory
+                .is_some_and(|max| memory_usage(true) > max)
+        {
+            break true;
+        }
+    };
+
+    unsafe { ext_php_rs_sapi_per_thread_shutdown() };
+
+    let Interpreter { shared, slot } = interpreter;
+    // The new thread takes over the count of this one.
+    if recycle && !shared.state.lock().closed && spawn(shared.clone(), slot).is_ok() {
+        shared.recycled.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    shared.exit_thread();
+}
+
+/// Aborts the requests of the pool exceeding the timeout, until the pool is
+/// dropped.
+fn watch(shared: &Shared, timeout: Duration) {
+    let poll = (timeout / 10).clamp(Duration::from_millis(10), Duration::from_secs(1));
+    loop {
+        thread::sleep(poll);
+        if shared.state.lock().closed {
+            return;
+        }
+        for running in shared.running.lock().iter_mut().flatten() {
+            if !running.timed_out && running.started.elapsed() >= timeout {
+                running.timed_out = true;
+                running.handle.time_out();
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "embed", php_zts))]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_pool() {
+        Embed::run(|| {
+            let pool = PoolBuilder::new(2)
+                .max_requests(1)
+                .timeout(Duration::from_millis(100))
+                .build()
+                .expect("should spawn the threads");
+
+            let sum = pool
+                .execute(|| Embed::eval("1 + 2").ok().and_then(|zval| zval.long()))
+                .wait();
+            assert_eq!(sum.ok().flatten(), Some(3));
+
+            let infinite = pool
+                .execute(|| Embed::eval("while (true) {}").is_ok())
+                .wait();
+            assert!(matches!(infinite, Err(EmbedError::Timeout)));
+
+            let metrics = pool.metrics();
+            assert_eq!(metrics.threads, 2);
+            assert_eq!(metrics.completed, 1);
+            assert_eq!(metrics.timed_out, 1);
+        });
+    }
+}
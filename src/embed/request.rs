@@ -0,0 +1,159 @@
+//! A guard over the lifetime of a request, for SAPI hosts driving the
+//! requests of PHP themselves.
+
+use std::{
+    fmt::{self, Debug},
+    marker::PhantomData,
+    panic::AssertUnwindSafe,
+    path::Path,
+    ptr,
+};
+
+use super::{Embed, EmbedError};
+use crate::{
+    builders::{RequestInfo, RequestInfoBuilder},
+    ffi::{php_request_shutdown, php_request_startup, ZEND_RESULT_CODE_SUCCESS},
+    types::Zval,
+    zend::try_catch,
+};
+
+/// A request of PHP on the current thread, started when the context is
+/// created and shut down when it is dropped, even if the host returns early
+/// or panics.
+///
+/// PHP must be started, and the thread must not be in a request already. In
+/// thread-safe builds, threads other than the one which started PHP must have
+/// called [`ext_php_rs_sapi_per_thread_init`](super::ext_php_rs_sapi_per_thread_init)
+/// first.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::embed::{EmbedError, RequestContext};
+///
+/// fn handle() -> Result<(), EmbedError> {
+///     let request = RequestContext::start()?;
+///     // The request is shut down if the script fails.
+///     request.run_script("index.php")?;
+///     let status = request.eval("http_response_code()")?;
+///     println!("responded with {:?}", status.long());
+///     Ok(())
+/// }
+/// ```
+pub struct RequestContext {
+    // Dropped after the request is shut down, as PHP reads it until then.
+    _info: Option<RequestInfo>,
+    // The request belongs to the thread which started it.
+    _not_send: PhantomData<*mut ()>,
+}
+
+impl RequestContext {
+    /// Starts a request on the current thread.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EmbedError::InitError`] if the request could not be started.
+    pub fn start() -> Result<Self, EmbedError> {
+        Self::start_inner(None)
+    }
+
+    /// Starts a request on the current thread, with the given request info,
+    /// e.g. its method and URI. The request info is freed once the request is
+    /// shut down.
+    ///
+    /// # Parameters
+    ///
+    /// * `info` - The request info.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EmbedError::InitError`] if the request info contains a NUL
+    /// byte, or the request could not be started.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ext_php_rs::{builders::RequestInfoBuilder, embed::RequestContext};
+    ///
+    /// let info = RequestInfoBuilder::new()
+    ///     .request_method("GET")
+    ///     .request_uri("/users?page=2")
+    ///     .query_string("page=2");
+    /// let request = RequestContext::start_with(info).expect("should start the request");
+    /// let page = request.eval("$_GET['page']").expect("should read the query string");
+    /// assert_eq!(page.str(), Some("2"));
+    /// ```
+    pub fn start_with(info: RequestInfoBuilder) -> Result<Self, EmbedError> {
+        let info = info.install().map_err(|_| EmbedError::InitError)?;
+        Self::start_inner(Some(info))
+    }
+
+    fn start_inner(mut info: Option<RequestInfo>) -> Result<Self, EmbedError> {
+        if unsafe { php_request_startup() } != ZEND_RESULT_CODE_SUCCESS {
+            return Err(EmbedError::InitError);
+        }
+        if let Some(info) = &mut info {
+            info.request_started();
+        }
+        Ok(Self {
+            _info: info,
+            _not_send: PhantomData,
+        })
+    }
+
+    /// Runs a PHP script from a file inside the request.
+    ///
+    /// # Parameters
+    ///
+    /// * `path` - The path of the script.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the script could not be executed, or if PHP bailed
+    /// out of it, see [`Embed::run_script`].
+    pub fn run_script<P: AsRef<Path>>(&self, path: P) -> Result<(), EmbedError> {
+        Embed::run_script(path)
+    }
+
+    /// Evaluates PHP code inside the request.
+    ///
+    /// # Parameters
+    ///
+    /// * `code` - The code to evaluate.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the code could not be evaluated, threw, or if PHP
+    /// bailed out of it, see [`Embed::eval`].
+    pub fn eval(&self, code: &str) -> Result<Zval, EmbedError> {
+        Embed::eval(code)
+    }
+
+    /// Runs a function inside the request, catching the bailouts of PHP, e.g.
+    /// on a fatal error.
+    ///
+    /// # Parameters
+    ///
+    /// * `func` - The function.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`EmbedError::CatchError`] if PHP bailed out of the function.
+    pub fn run<R, F: FnOnce() -> R>(&self, func: F) -> Result<R, EmbedError> {
+        let mut func = AssertUnwindSafe(Some(func));
+        try_catch(|| (func.take().expect("should run the function once"))())
+            .map_err(|_| EmbedError::CatchError)
+    }
+}
+
+impl Debug for RequestContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestContext").finish_non_exhaustive()
+    }
+}
+
+impl Drop for RequestContext {
+    fn drop(&mut self) {
+        unsafe { php_request_shutdown(ptr::null_mut()) };
+    }
+}
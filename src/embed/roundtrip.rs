@@ -0,0 +1,80 @@
+//! Helpers to test the conversions between Rust and PHP values against the
+//! `var_export` of the engine, used by the [`assert_var_export!`] and
+//! [`assert_roundtrip!`] macros.
+//!
+//! [`assert_var_export!`]: crate::assert_var_export
+//! [`assert_roundtrip!`]: crate::assert_roundtrip
+
+use std::{any::type_name, fmt::Debug};
+
+use super::Embed;
+use crate::{
+    convert::{FromZval, IntoZval, IntoZvalDyn},
+    types::{ZendCallable, Zval},
+};
+
+/// Returns the `var_export` of a PHP value. Must be called inside a request.
+///
+/// # Panics
+///
+/// Panics if `var_export` throws, e.g. on a recursive array.
+pub fn var_export(zval: &Zval) -> String {
+    let var_export = ZendCallable::try_from_name("var_export").expect("should find var_export");
+    let exported = var_export
+        .try_call(vec![zval as &dyn IntoZvalDyn, &true])
+        .unwrap_or_else(|e| panic!("Failed to export the value: {e:?}"));
+    exported
+        .string()
+        .expect("var_export should return a string")
+}
+
+/// Converts a Rust value into PHP and evaluates a PHP expression over it,
+/// given as `$value`. Must be called inside a request.
+///
+/// # Parameters
+///
+/// * `value` - The Rust value.
+/// * `expression` - The PHP expression, e.g. `array_reverse($value)`.
+///
+/// # Panics
+///
+/// Panics if the value cannot be converted into PHP, if the expression cannot
+/// be compiled, or if it throws.
+pub fn eval_with(value: impl IntoZval, expression: &str) -> Zval {
+    let value = value
+        .into_zval(false)
+        .expect("should convert the value into PHP");
+    let closure = Embed::eval(&format!("fn ($value) => {expression};"))
+        .unwrap_or_else(|e| panic!("Failed to compile `{expression}`: {e:?}"));
+    let closure = ZendCallable::new_owned(closure).expect("should return a closure");
+    closure
+        .try_call(vec![&value])
+        .unwrap_or_else(|e| panic!("Failed to evaluate `{expression}`: {e:?}"))
+}
+
+/// Asserts that a PHP value converts into the expected Rust value. Called by
+/// [`assert_roundtrip!`](crate::assert_roundtrip).
+///
+/// # Panics
+///
+/// Panics if the value cannot be converted, or into another value.
+#[doc(hidden)]
+#[track_caller]
+pub fn assert_from_php<T>(zval: &Zval, expected: &T)
+where
+    T: for<'a> FromZval<'a> + PartialEq + Debug,
+{
+    match T::from_zval(zval) {
+        Some(value) => assert_eq!(
+            &value,
+            expected,
+            "unexpected value converted back from {}",
+            var_export(zval)
+        ),
+        None => panic!(
+            "Failed to convert {} back into {}",
+            var_export(zval),
+            type_name::<T>()
+        ),
+    }
+}
@@ -0,0 +1,133 @@
+//! Registration of the server variables of requests handled by SAPI hosts.
+
+use std::{cell::RefCell, collections::HashMap, ffi::CString};
+
+use crate::{ffi::php_register_variable_safe, types::Zval};
+
+thread_local! {
+    static SERVER_VARIABLES: RefCell<Option<HashMap<String, String>>> = const { RefCell::new(None) };
+}
+
+/// Registers variables into `$_SERVER`, from the register server variables
+/// function of a SAPI.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::{embed::ServerVars, types::Zval};
+///
+/// #[allow(clippy::not_unsafe_ptr_arg_deref)]
+/// extern "C" fn register_server_variables(vars: *mut Zval) {
+///     let Some(vars) = (unsafe { vars.as_mut() }) else {
+///         return;
+///     };
+///     ServerVars::register(vars, [("SERVER_SOFTWARE", "my-server"), ("HTTPS", "on")]);
+/// }
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct ServerVars;
+
+impl ServerVars {
+    /// Registers variables into the array of server variables given to the
+    /// register server variables function of a SAPI. Variables registered
+    /// later replace the ones with the same name.
+    ///
+    /// Names are mangled by PHP like the names of form fields, e.g. `.` is
+    /// replaced with `_`. Variables whose name contains a NUL byte are
+    /// skipped, while values may contain any byte.
+    ///
+    /// # Parameters
+    ///
+    /// * `track_vars` - The array of server variables.
+    /// * `vars` - The names and values of the variables.
+    pub fn register<'a, I>(track_vars: &mut Zval, vars: I)
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        for (name, value) in vars {
+            let Ok(name) = CString::new(name) else {
+                continue;
+            };
+            unsafe {
+                php_register_variable_safe(
+                    name.as_ptr(),
+                    value.as_ptr().cast(),
+                    value.len(),
+                    track_vars,
+                );
+            };
+        }
+    }
+}
+
+/// Sets the server variables of the next request handled on this thread,
+/// registered by [`register_server_variables`]. The variables replace the
+/// previous ones, and are kept until [`clear_server_variables`] is called.
+///
+/// # Parameters
+///
+/// * `variables` - The names and values of the variables, e.g.
+///   `SCRIPT_FILENAME` and `DOCUMENT_ROOT`.
+pub fn set_server_variables(variables: HashMap<String, String>) {
+    SERVER_VARIABLES.with(|current| *current.borrow_mut() = Some(variables));
+}
+
+/// Discards the server variables of the request handled on this thread.
+pub fn clear_server_variables() {
+    SERVER_VARIABLES.with(|current| current.borrow_mut().take());
+}
+
+/// Registers the server variables set with [`set_server_variables`] into
+/// `$_SERVER`.
+///
+/// This can be set as the register server variables function of a SAPI with
+/// [`SapiBuilder::register_server_variables_function`](crate::builders::SapiBuilder::register_server_variables_function),
+/// or called from it.
+#[allow(clippy::not_unsafe_ptr_arg_deref)]
+pub extern "C" fn register_server_variables(vars: *mut Zval) {
+    // SAFETY: The engine passes the array of server variables.
+    let Some(vars) = (unsafe { vars.as_mut() }) else {
+        return;
+    };
+    SERVER_VARIABLES.with(|current| {
+        if let Some(variables) = current.borrow().as_ref() {
+            ServerVars::register(
+                vars,
+                variables
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_str())),
+            );
+        }
+    });
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{embed::Embed, types::ZendHashTable};
+
+    #[test]
+    fn test_register() {
+        Embed::run(|| {
+            let mut vars = Zval::new();
+            vars.set_hashtable(ZendHashTable::new());
+            ServerVars::register(&mut vars, [("DOCUMENT_ROOT", "/srv"), ("A\0B", "skipped")]);
+
+            set_server_variables(HashMap::from([("HTTPS".into(), "on".into())]));
+            register_server_variables(&mut vars);
+            clear_server_variables();
+
+            let array = vars.array().expect("should be an array");
+            assert_eq!(array.len(), 2);
+            assert_eq!(
+                array.get("DOCUMENT_ROOT").and_then(Zval::string).as_deref(),
+                Some("/srv")
+            );
+            assert_eq!(
+                array.get("HTTPS").and_then(Zval::string).as_deref(),
+                Some("on")
+            );
+        });
+    }
+}
@@ -0,0 +1,334 @@
+//! A SAPI recording what PHP sends to the client, for tests.
+
+use std::{
+    ffi::{c_char, c_int, c_void, CStr},
+    mem,
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use parking_lot::{const_mutex, Mutex};
+
+use super::{harness, GetModule, SapiModule, RUN_FN_LOCK};
+use crate::{
+    builders::SapiBuilder,
+    ffi::{
+        ext_php_rs_sapi_shutdown, ext_php_rs_sapi_startup, php_module_shutdown, php_module_startup,
+        sapi_header_struct, sapi_headers_struct, sapi_shutdown, sapi_startup,
+        ZEND_RESULT_CODE_SUCCESS,
+    },
+    zend::SapiHeader,
+};
+
+/// What is recorded during the current request of a [`TestSapi`].
+static RECORDING: Mutex<Recording> = const_mutex(Recording::new());
+
+/// A SAPI recording the output, headers, status code and log messages of
+/// requests, so the logic of SAPI hosts can be tested without sockets.
+///
+/// Each call to [`TestSapi::run`] starts PHP with the SAPI, runs a request and
+/// shuts PHP down, so it cannot be used once PHP is started by the tests of
+/// `#[php_test]`.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::embed::{Embed, TestSapi};
+///
+/// let recording = TestSapi::new().run(|| {
+///     let _ = Embed::eval("http_response_code(201);");
+///     let _ = Embed::eval("header('Content-Type: text/plain');");
+///     let _ = Embed::eval("print 'Created';");
+/// });
+/// recording
+///     .assert_status(201)
+///     .assert_header("Content-Type", "text/plain")
+///     .assert_output("Created");
+/// ```
+pub struct TestSapi {
+    sapi: *mut SapiModule,
+    module: Option<GetModule>,
+}
+
+impl TestSapi {
+    /// Creates a SAPI recording the requests, without other hooks.
+    pub fn new() -> Self {
+        Self::from_builder(SapiBuilder::new("test", "Test SAPI"))
+    }
+
+    /// Creates a SAPI from a builder, e.g. with the hooks of a host reading
+    /// the body of the request. The hooks writing the output, sending the
+    /// headers and logging messages are replaced by the ones recording them.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the SAPI cannot be built, e.g. if its name contains a NUL
+    /// byte.
+    pub fn from_builder(builder: SapiBuilder) -> Self {
+        let mut sapi = builder
+            .ub_write_function(record_output)
+            .send_header_function(record_header)
+            .log_message_function(record_log_message)
+            .build()
+            .expect("should build the SAPI");
+        sapi.send_headers = Some(record_status);
+        Self {
+            sapi: sapi.into_raw(),
+            module: None,
+        }
+    }
+
+    /// Starts PHP with a module, e.g. one registering what must be registered
+    /// during module startup.
+    ///
+    /// # Parameters
+    ///
+    /// * `module` - The function returning the module entry.
+    pub fn module(mut self, module: GetModule) -> Self {
+        self.module = Some(module);
+        self
+    }
+
+    /// Starts PHP with the SAPI, runs a function inside a request and shuts
+    /// PHP down, returning what was recorded during the request.
+    ///
+    /// If PHP bails out of the function, e.g. on a fatal error, the rest of
+    /// the function is skipped and what was recorded until then is returned.
+    ///
+    /// # Parameters
+    ///
+    /// * `func` - The function, which may use [`Embed::eval`](super::Embed::eval).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function panics, if PHP fails to start, or if PHP was
+    /// started by the tests of `#[php_test]`.
+    pub fn run<F: FnOnce()>(&self, func: F) -> Recording {
+        let _guard = RUN_FN_LOCK.write();
+        assert!(
+            !harness::is_started(),
+            "PHP was already started by `#[php_test]`"
+        );
+        *RECORDING.lock() = Recording::new();
+
+        unsafe {
+            ext_php_rs_sapi_startup();
+            sapi_startup(self.sapi);
+            let module = self.module.map_or(ptr::null_mut(), |module| module());
+            assert_eq!(
+                php_module_startup(self.sapi, module),
+                ZEND_RESULT_CODE_SUCCESS,
+                "should start PHP"
+            );
+        }
+
+        // PHP is shut down before a panic of the function is resumed.
+        let result = panic::catch_unwind(AssertUnwindSafe(|| harness::request(func)));
+
+        unsafe {
+            php_module_shutdown();
+            sapi_shutdown();
+            ext_php_rs_sapi_shutdown();
+        }
+
+        if let Err(panic) = result {
+            panic::resume_unwind(panic);
+        }
+        mem::replace(&mut *RECORDING.lock(), Recording::new())
+    }
+}
+
+impl Default for TestSapi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for TestSapi {
+    fn drop(&mut self) {
+        // PHP keeps a copy of the SAPI while started.
+        drop(unsafe { Box::from_raw(self.sapi) });
+    }
+}
+
+/// What PHP sent to the client during a request of a [`TestSapi`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    /// The output of the request.
+    pub output: Vec<u8>,
+    /// The headers sent, in order, e.g. `("Content-Type", "text/html")`.
+    pub headers: Vec<(String, String)>,
+    /// The status code, if the headers were sent.
+    pub status: Option<u16>,
+    /// The messages logged, e.g. by `error_log()`.
+    pub log_messages: Vec<String>,
+}
+
+impl Recording {
+    /// Creates an empty recording.
+    const fn new() -> Self {
+        Self {
+            output: Vec::new(),
+            headers: Vec::new(),
+            status: None,
+            log_messages: Vec::new(),
+        }
+    }
+
+    /// Returns the output as text, replacing invalid UTF-8.
+    pub fn output_str(&self) -> String {
+        String::from_utf8_lossy(&self.output).into_owned()
+    }
+
+    /// Returns the value of the last header sent with the given name, compared
+    /// case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .rev()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Asserts that a header was sent with the given value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the header was not sent, or with another value.
+    #[track_caller]
+    pub fn assert_header(&self, name: &str, value: &str) -> &Self {
+        assert_eq!(
+            self.header(name),
+            Some(value),
+            "unexpected value of the header {name}, in {:?}",
+            self.headers
+        );
+        self
+    }
+
+    /// Asserts the status code sent.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the headers were not sent, or with another status code.
+    #[track_caller]
+    pub fn assert_status(&self, status: u16) -> &Self {
+        assert_eq!(self.status, Some(status), "unexpected status code");
+        self
+    }
+
+    /// Asserts the whole output.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the output differs.
+    #[track_caller]
+    pub fn assert_output(&self, output: &str) -> &Self {
+        assert_eq!(self.output_str(), output, "unexpected output");
+        self
+    }
+
+    /// Asserts that a message containing the given text was logged.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no such message was logged.
+    #[track_caller]
+    pub fn assert_logged(&self, message: &str) -> &Self {
+        assert!(
+            self.log_messages
+                .iter()
+                .any(|logged| logged.contains(message)),
+            "no message containing {message:?} was logged, in {:?}",
+            self.log_messages
+        );
+        self
+    }
+}
+
+extern "C" fn record_output(str: *const c_char, str_length: usize) -> usize {
+    let output = unsafe { std::slice::from_raw_parts(str.cast::<u8>(), str_length) };
+    RECORDING.lock().output.extend_from_slice(output);
+    str_length
+}
+
+/// Records the status code, and lets PHP send the headers one by one to
+/// [`record_header`].
+unsafe extern "C" fn record_status(sapi_headers: *mut sapi_headers_struct) -> c_int {
+    /// `SAPI_HEADER_DO_SEND`, which is a macro.
+    const SAPI_HEADER_DO_SEND: c_int = 2;
+
+    if let Some(sapi_headers) = sapi_headers.as_ref() {
+        RECORDING.lock().status = u16::try_from(sapi_headers.http_response_code).ok();
+    }
+    SAPI_HEADER_DO_SEND
+}
+
+extern "C" fn record_header(header: *mut sapi_header_struct, _server_context: *mut c_void) {
+    // The end of the headers is marked by a null header.
+    let Some(header) = (unsafe { SapiHeader::from_ptr(header) }) else {
+        return;
+    };
+    let header = String::from_utf8_lossy(header.as_bytes());
+    // The status line is recorded as the status code.
+    if let Some((name, value)) = header.split_once(':') {
+        RECORDING
+            .lock()
+            .headers
+            .push((name.trim().to_string(), value.trim().to_string()));
+    }
+}
+
+extern "C" fn record_log_message(message: *const c_char, _syslog_type_int: c_int) {
+    if message.is_null() {
+        return;
+    }
+    let message = unsafe { CStr::from_ptr(message) }
+        .to_string_lossy()
+        .into_owned();
+    RECORDING.lock().log_messages.push(message);
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{embed::Embed, flags::ErrorType};
+
+    #[test]
+    fn test_recording() {
+        let recording = TestSapi::new().run(|| {
+            Embed::eval("http_response_code(404);").expect("should set the status");
+            Embed::eval("header('Content-Type: text/plain');").expect("should set the header");
+            Embed::eval("header('X-Request-Id: 42');").expect("should set the header");
+            Embed::eval("error_log('not found');").expect("should log the message");
+            Embed::eval("print 'Not found';").expect("should write the output");
+        });
+
+        recording
+            .assert_status(404)
+            .assert_header("content-type", "text/plain")
+            .assert_header("X-Request-Id", "42")
+            .assert_output("Not found")
+            .assert_logged("not found");
+    }
+
+    #[test]
+    fn test_sapi_error() {
+        static ERRORS: Mutex<Vec<(ErrorType, String)>> = const_mutex(Vec::new());
+
+        let sapi = TestSapi::from_builder(
+            SapiBuilder::new("test", "Test SAPI")
+                .sapi_error_function(|ty, message| ERRORS.lock().push((ty, message.to_string()))),
+        );
+        sapi.run(|| {
+            Embed::eval("header(\"X-Injected: a\\r\\nX-Other: b\");")
+                .expect("should reject the header");
+        });
+
+        let errors = ERRORS.lock();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, ErrorType::Warning);
+        assert!(errors[0].1.contains("new line detected"));
+    }
+}
@@ -12,6 +12,7 @@ use crate::{
     ffi::php_error_docref,
     flags::{ClassFlags, DataType, ErrorType, ZvalTypeFlags},
     types::ZendObject,
+    zend::BuildInfo,
 };
 
 /// The main result type which is passed by the library.
@@ -55,6 +56,9 @@ pub enum Error {
     InvalidCString,
     /// The string could not be converted into a valid Utf8 string
     InvalidUtf8,
+    /// The string could not be converted from or into another encoding, e.g.
+    /// it contains characters the encoding cannot represent.
+    EncodingConversion,
     /// Could not call the given function.
     Callable,
     /// An object was expected.
@@ -69,6 +73,31 @@ pub enum Error {
     StreamWrapperRegistrationFailure,
     /// A failure occurred while unregistering the stream wrapper
     StreamWrapperUnregistrationFailure,
+    /// A failure occurred while registering the stream filter
+    StreamFilterRegistrationFailure,
+    /// A failure occurred while opening a stream
+    StreamOpenFailure,
+    /// A failure occurred while registering the session handler
+    SessionHandlerRegistrationFailure,
+    /// A failure occurred while modifying the response headers, usually
+    /// because they have already been sent.
+    SapiHeaderFailure,
+    /// The extension was loaded into a PHP engine with another build than the
+    /// one it was compiled for.
+    ///
+    /// The enum carries the build the extension was compiled for, and the
+    /// build of the running engine.
+    IncompatibleBuild(BuildInfo, BuildInfo),
+    /// A future was awaited without a runtime to poll it in the context of.
+    MissingRuntime,
+    /// A closure dispatched to a PHP thread panicked, or the thread exited
+    /// before running it.
+    DispatcherClosed,
+    /// A background worker panicked, or was cancelled before it started.
+    WorkerFailed,
+    /// The operation was cancelled through a
+    /// [`CancellationToken`](crate::cancel::CancellationToken).
+    Cancelled,
 }
 
 impl Display for Error {
@@ -94,6 +123,9 @@ impl Display for Error {
                 "String given contains NUL-bytes which cannot be present in a C string."
             ),
             Error::InvalidUtf8 => write!(f, "Invalid Utf8 byte sequence."),
+            Error::EncodingConversion => {
+                write!(f, "The string could not be converted between encodings.")
+            }
             Error::Callable => write!(f, "Could not call given function."),
             Error::Object => write!(f, "An object was expected."),
             Error::InvalidException(flags) => {
@@ -112,6 +144,28 @@ impl Display for Error {
                     "A failure occurred while unregistering the stream wrapper"
                 )
             }
+            Error::StreamFilterRegistrationFailure => {
+                write!(f, "A failure occurred while registering the stream filter")
+            }
+            Error::StreamOpenFailure => write!(f, "A failure occurred while opening the stream"),
+            Error::SessionHandlerRegistrationFailure => {
+                write!(
+                    f,
+                    "A failure occurred while registering the session handler"
+                )
+            }
+            Error::SapiHeaderFailure => {
+                write!(f, "A failure occurred while modifying the response headers")
+            }
+            Error::IncompatibleBuild(built, running) => {
+                write!(f, "Extension built for {built}, loaded into {running}.")
+            }
+            Error::MissingRuntime => write!(f, "No runtime was set to await the future."),
+            Error::DispatcherClosed => {
+                write!(f, "The dispatched closure did not run to completion.")
+            }
+            Error::WorkerFailed => write!(f, "The worker did not run to completion."),
+            Error::Cancelled => write!(f, "The operation was cancelled."),
         }
     }
 }
@@ -126,7 +180,11 @@ impl From<NulError> for Error {
 
 impl From<Error> for PhpException {
     fn from(err: Error) -> Self {
-        Self::default(err.to_string())
+        match err {
+            // Keep the original object so it is rethrown as-is.
+            Error::Exception(object) => Self::from_object(object),
+            err => Self::default(err.to_string()),
+        }
     }
 }
 
@@ -141,5 +199,12 @@ pub fn php_error(type_: ErrorType, message: &str) {
         }
     };
 
-    unsafe { php_error_docref(std::ptr::null(), type_.bits() as _, c_string.as_ptr()) }
+    unsafe {
+        php_error_docref(
+            std::ptr::null(),
+            type_.bits() as _,
+            c"%s".as_ptr(),
+            c_string.as_ptr(),
+        )
+    }
 }
@@ -0,0 +1,279 @@
+//! Forwarding of the PHP error log into the [`log`] and [`tracing`] facades.
+//!
+//! Once [`forward_error_log`] is called, the errors raised by the engine,
+//! e.g. warnings, deprecations and fatal errors, and the messages passed to
+//! the userland `error_log()` function are forwarded to the facades enabled
+//! with the `log` and `tracing` features, regardless of where the SAPI and the
+//! `error_log` INI setting send them. PHP keeps logging them as configured.
+//!
+//! Records are emitted with the `php` target, along with the file and line
+//! they were raised on and the identifier of the request returned by
+//! [`request_id`]. Engine errors are mapped to levels as follows:
+//!
+//! | PHP error                                         | Level   |
+//! |---------------------------------------------------|---------|
+//! | `E_ERROR`, `E_PARSE`, `E_*_ERROR`                 | `Error` |
+//! | `E_WARNING`, `E_*_WARNING`                        | `Warn`  |
+//! | `E_NOTICE`, `E_USER_NOTICE`                       | `Info`  |
+//! | `E_DEPRECATED`, `E_USER_DEPRECATED`, `E_STRICT`   | `Debug` |
+//!
+//! Messages passed to `error_log()` are logged at the `Info` level, unless
+//! they are sent by email or appended to a file chosen by the caller.
+//!
+//! [`log`]: https://docs.rs/log
+//! [`tracing`]: https://docs.rs/tracing
+//! [`request_id`]: crate::zend::lifecycle::request_id
+
+use std::sync::Once;
+
+use parking_lot::{const_rwlock, RwLock};
+
+use crate::{
+    exception::{register_error_observer, ErrorInfo},
+    ffi::{zend_function, zend_hash_str_find_ptr_lc, zif_handler},
+    flags::ErrorType,
+    types::Zval,
+    zend::{CompilerGlobals, ExecuteData},
+    zend_fastcall,
+};
+
+/// The target of the forwarded records.
+const TARGET: &str = "php";
+
+/// The `error_log()` message type sending the message to the system logger.
+const MESSAGE_TYPE_SYSTEM: i64 = 0;
+/// The `error_log()` message type sending the message to the SAPI logger.
+const MESSAGE_TYPE_SAPI: i64 = 4;
+
+static ERROR_LOG_HANDLER: RwLock<zif_handler> = const_rwlock(None);
+static FORWARD: Once = Once::new();
+
+/// The level of a forwarded record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl From<ErrorType> for Level {
+    fn from(ty: ErrorType) -> Self {
+        if ty.intersects(
+            ErrorType::Error
+                | ErrorType::Parse
+                | ErrorType::CoreError
+                | ErrorType::CompileError
+                | ErrorType::UserError
+                | ErrorType::RecoverableError,
+        ) {
+            Self::Error
+        } else if ty.intersects(
+            ErrorType::Warning
+                | ErrorType::CoreWarning
+                | ErrorType::CompileWarning
+                | ErrorType::UserWarning,
+        ) {
+            Self::Warn
+        } else if ty.intersects(ErrorType::Notice | ErrorType::UserNotice) {
+            Self::Info
+        } else {
+            Self::Debug
+        }
+    }
+}
+
+/// Forwards the errors raised by the engine and the messages passed to
+/// `error_log()` to the `log` and `tracing` facades.
+///
+/// This must be called during module startup, as the handler of `error_log()`
+/// is replaced while no request is running. Calling it again has no effect.
+pub fn forward_error_log() {
+    FORWARD.call_once(|| {
+        // The observer stays registered when its handle is dropped.
+        let _ = register_error_observer(forward_error);
+        wrap_error_log();
+    });
+}
+
+/// Replaces the handler of `error_log()` with [`error_log_handler`].
+fn wrap_error_log() {
+    const NAME: &str = "error_log";
+
+    // The function is registered by the standard module, which is started up
+    // before the other modules.
+    let function = unsafe {
+        zend_hash_str_find_ptr_lc(
+            CompilerGlobals::get().function_table,
+            NAME.as_ptr().cast(),
+            NAME.len(),
+        )
+    };
+    let Some(function) = (unsafe { function.cast::<zend_function>().as_mut() }) else {
+        return;
+    };
+    // SAFETY: `error_log()` is an internal function, whose handler is shared by
+    // all threads. It is replaced during module startup, while no request is
+    // calling it.
+    unsafe {
+        *ERROR_LOG_HANDLER.write() = function.internal_function.handler;
+        function.internal_function.handler = Some(error_log_handler);
+    }
+}
+
+zend_fastcall! {
+    /// Forwards the message passed to `error_log()` before calling its
+    /// original handler.
+    unsafe extern "C" fn error_log_handler(ex: *mut ExecuteData, retval: *mut Zval) {
+        if let Some(ex) = ex.as_ref() {
+            let args = ex.args();
+            let message = args.first().and_then(|message| message.str());
+            let message_type = args
+                .get(1)
+                .and_then(|message_type| message_type.long())
+                .unwrap_or(MESSAGE_TYPE_SYSTEM);
+            if let Some(message) = message {
+                if message_type == MESSAGE_TYPE_SYSTEM || message_type == MESSAGE_TYPE_SAPI {
+                    let (file, line) = call_site(ex).unzip();
+                    emit(Level::Info, message, file, line);
+                }
+            }
+        }
+
+        if let Some(handler) = *ERROR_LOG_HANDLER.read() {
+            handler(ex, retval);
+        }
+    }
+}
+
+/// Returns the file and line executed in the closest caller defined in PHP
+/// code.
+fn call_site(ex: &ExecuteData) -> Option<(&str, u32)> {
+    let mut frame = ex.previous();
+    while let Some(ex) = frame {
+        if let Some(line) = ex.lineno() {
+            return Some((ex.function()?.filename()?, line));
+        }
+        frame = ex.previous();
+    }
+    None
+}
+
+/// Forwards an error raised by the engine.
+fn forward_error(error: &ErrorInfo) {
+    emit(
+        error.level.into(),
+        error.message,
+        Some(error.file),
+        Some(error.line),
+    );
+}
+
+/// Emits a record to the enabled facades.
+fn emit(level: Level, message: &str, file: Option<&str>, line: Option<u32>) {
+    let request_id = crate::zend::lifecycle::request_id();
+
+    #[cfg(feature = "log")]
+    {
+        let level = match level {
+            Level::Error => ::log::Level::Error,
+            Level::Warn => ::log::Level::Warn,
+            Level::Info => ::log::Level::Info,
+            Level::Debug => ::log::Level::Debug,
+        };
+        if ::log::log_enabled!(target: TARGET, level) {
+            let location = match (file, line) {
+                (Some(file), Some(line)) => format!(" in {file} on line {line}"),
+                _ => String::new(),
+            };
+            match &request_id {
+                Some(id) => ::log::log!(target: TARGET, level, "[{id}] {message}{location}"),
+                None => ::log::log!(target: TARGET, level, "{message}{location}"),
+            }
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    {
+        let request_id = request_id.as_deref();
+        match level {
+            Level::Error => ::tracing::error!(target: TARGET, request_id, file, line, "{message}"),
+            Level::Warn => ::tracing::warn!(target: TARGET, request_id, file, line, "{message}"),
+            Level::Info => ::tracing::info!(target: TARGET, request_id, file, line, "{message}"),
+            Level::Debug => ::tracing::debug!(target: TARGET, request_id, file, line, "{message}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_level() {
+        assert_eq!(Level::from(ErrorType::CompileError), Level::Error);
+        assert_eq!(Level::from(ErrorType::UserWarning), Level::Warn);
+        assert_eq!(Level::from(ErrorType::Notice), Level::Info);
+        assert_eq!(Level::from(ErrorType::Deprecated), Level::Debug);
+    }
+
+    #[cfg(all(feature = "embed", feature = "log"))]
+    mod embed {
+        use parking_lot::{const_mutex, Mutex};
+
+        use super::*;
+        use crate::{
+            builders::ModuleBuilder,
+            embed::{Embed, TestSapi},
+            zend::ModuleEntry,
+        };
+
+        static RECORDS: Mutex<Vec<(::log::Level, String)>> = const_mutex(Vec::new());
+
+        struct Recorder;
+
+        impl ::log::Log for Recorder {
+            fn enabled(&self, metadata: &::log::Metadata) -> bool {
+                metadata.target() == TARGET
+            }
+
+            fn log(&self, record: &::log::Record) {
+                if self.enabled(record.metadata()) {
+                    RECORDS
+                        .lock()
+                        .push((record.level(), record.args().to_string()));
+                }
+            }
+
+            fn flush(&self) {}
+        }
+
+        extern "C" fn startup(_ty: i32, _module_number: i32) -> i32 {
+            forward_error_log();
+            0
+        }
+
+        extern "C" fn get_module() -> *mut ModuleEntry {
+            let (entry, _) = ModuleBuilder::new("test-error-log", "0.1.0")
+                .startup_function(startup)
+                .try_into()
+                .expect("should build the module");
+            entry.into_raw()
+        }
+
+        #[test]
+        fn test_forward_error_log() {
+            let _ = ::log::set_logger(&Recorder);
+            ::log::set_max_level(::log::LevelFilter::Info);
+
+            TestSapi::new().module(get_module).run(|| {
+                let _ = Embed::eval("error_log('forwarded from error_log()');");
+            });
+
+            let records = RECORDS.lock();
+            assert!(records.iter().any(|(level, message)| {
+                *level == ::log::Level::Info && message.contains("forwarded from error_log()")
+            }));
+        }
+    }
+}
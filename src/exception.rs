@@ -1,14 +1,27 @@
 //! Types and functions used for throwing exceptions from Rust to PHP.
 
-use std::{ffi::{c_int, c_uint, CString}, fmt::Debug};
+use std::{
+    backtrace::Backtrace,
+    cell::Cell,
+    ffi::{c_int, c_uint, CString},
+    fmt::Debug,
+    panic::AssertUnwindSafe,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Once, RwLock,
+    },
+};
 
 use crate::{
+    boxed::ZBox,
+    builders::ExceptionClassBuilder,
     class::RegisteredClass,
+    convert::IntoZval,
     error::{Error, Result},
-    ffi::{zend_throw_exception_ex, zend_throw_exception_object},
-    flags::ClassFlags,
-    types::{ZendStr, Zval},
-    zend::{ce, ClassEntry},
+    ffi::{zend_throw_exception_ex, zend_throw_exception_object, zend_update_property},
+    flags::{ClassFlags, ErrorType},
+    types::{ZendObject, ZendStr, Zval},
+    zend::{ce, ClassEntry, ExecutorGlobals},
 };
 
 /// Result type with the error variant as a [`PhpException`].
@@ -27,6 +40,83 @@ pub struct PhpException {
     code: i32,
     ex: &'static ClassEntry,
     object: Option<Zval>,
+    previous: Option<Box<PhpException>>,
+    backtrace: Option<Box<Backtrace>>,
+    overrides: Option<Box<Overrides>>,
+    severity: Severity,
+}
+
+/// How a [`PhpException`] returned from a function exported to PHP is
+/// reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Severity {
+    /// The exception is thrown.
+    #[default]
+    Exception,
+    /// An `E_WARNING` is raised with the exception message and the function
+    /// returns the given value, like many functions of the standard library
+    /// do.
+    Warning(WarningReturn),
+}
+
+/// The value returned by a function when a warning-level [`PhpException`] is
+/// returned from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningReturn {
+    /// The function returns `false`.
+    False,
+    /// The function returns `null`.
+    Null,
+}
+
+/// Values set on the exception object once thrown. Boxed to keep
+/// [`PhpException`] small, as it is used as an error type.
+#[derive(Debug, Default)]
+struct Overrides {
+    file: Option<String>,
+    line: Option<u32>,
+    properties: Vec<(String, Result<Zval>)>,
+}
+
+static CAPTURE_BACKTRACES: AtomicBool = AtomicBool::new(false);
+
+/// Sets whether a Rust backtrace is captured for every [`PhpException`]
+/// created from now on, as if [`PhpException::with_backtrace`] was called on
+/// each of them. Disabled by default, as capturing a backtrace is expensive.
+///
+/// Enabling it also installs a panic hook, so the exceptions returned by
+/// [`catch_panic`] carry the backtrace of the panic site rather than the one
+/// of the caller.
+///
+/// # Parameters
+///
+/// * `enabled` - Whether to capture backtraces.
+pub fn capture_backtraces(enabled: bool) {
+    CAPTURE_BACKTRACES.store(enabled, Ordering::Relaxed);
+    if enabled {
+        PANIC_HOOK.call_once(|| {
+            let previous = std::panic::take_hook();
+            std::panic::set_hook(Box::new(move |info| {
+                if CAPTURE_BACKTRACES.load(Ordering::Relaxed) {
+                    PANIC_BACKTRACE
+                        .with(|backtrace| backtrace.set(Some(Backtrace::force_capture())));
+                }
+                previous(info);
+            }));
+        });
+    }
+}
+
+static PANIC_HOOK: Once = Once::new();
+
+thread_local! {
+    /// Backtrace of the last panic on this thread, captured by the panic hook
+    /// at the panic site, as the stack has unwound by the time the panic is
+    /// caught by [`catch_panic`].
+    static PANIC_BACKTRACE: Cell<Option<Backtrace>> = const { Cell::new(None) };
+    /// Whether the error observers are being called on this thread, so the
+    /// warning raised when one of them panics is not observed again.
+    static OBSERVING_ERROR: Cell<bool> = const { Cell::new(false) };
 }
 
 impl PhpException {
@@ -43,6 +133,12 @@ impl PhpException {
             code,
             ex,
             object: None,
+            previous: None,
+            backtrace: CAPTURE_BACKTRACES
+                .load(Ordering::Relaxed)
+                .then(|| Box::new(Backtrace::force_capture())),
+            overrides: None,
+            severity: Severity::Exception,
         }
     }
 
@@ -57,6 +153,32 @@ impl PhpException {
         Self::new(message, 0, ce::exception())
     }
 
+    /// Creates a warning-level exception. When returned from a function
+    /// exported to PHP, an `E_WARNING` is raised with the message and the
+    /// function returns `false` instead of throwing.
+    ///
+    /// The return type of the function must allow `false`, for example by
+    /// returning a [`Zval`], as PHP verifies the return type of internal
+    /// functions in debug builds.
+    ///
+    /// # Parameters
+    ///
+    /// * `message` - Message of the warning.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use ext_php_rs::{prelude::*, types::Zval};
+    ///
+    /// #[php_function]
+    /// pub fn legacy_open(path: &str) -> PhpResult<Zval> {
+    ///     Err(PhpException::warning(format!("failed to open {path}")))
+    /// }
+    /// ```
+    pub fn warning(message: String) -> Self {
+        Self::default(message).with_severity(Severity::Warning(WarningReturn::False))
+    }
+
     /// Creates an instance of an exception from a PHP class type and a message.
     ///
     /// # Parameters
@@ -66,6 +188,54 @@ impl PhpException {
         Self::new(message, 0, T::get_metadata().ce())
     }
 
+    /// Creates an exception from a PHP exception object, such as one thrown
+    /// by a PHP function called from Rust.
+    ///
+    /// The message, code and class are read from the object, which stays
+    /// accessible through [`PhpException::object`]. Throwing the exception
+    /// rethrows the original object, preserving its PHP stack trace.
+    ///
+    /// # Parameters
+    ///
+    /// * `object` - The exception object.
+    pub fn from_object(object: ZBox<ZendObject>) -> Self {
+        let message = object
+            .try_call_method("getMessage", vec![])
+            .ok()
+            .and_then(|message| message.string())
+            .unwrap_or_default();
+        let code = object
+            .try_call_method("getCode", vec![])
+            .ok()
+            .and_then(|code| code.long())
+            .unwrap_or_default();
+        let ex = object.get_class_entry();
+
+        let mut exception = Self::new(message, code as _, ex);
+        exception.object = object.into_zval(false).ok();
+        exception
+    }
+
+    /// Returns the PHP object the exception is based on, if any.
+    pub fn object(&self) -> Option<&Zval> {
+        self.object.as_ref()
+    }
+
+    /// Returns the exception message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Returns the exception code.
+    pub fn code(&self) -> i32 {
+        self.code
+    }
+
+    /// Returns the class of the exception.
+    pub fn class(&self) -> &'static ClassEntry {
+        self.ex
+    }
+
     /// Set the Zval object for the exception.
     ///
     /// Exceptions can be based of instantiated Zval objects when you are
@@ -78,16 +248,216 @@ impl PhpException {
         self.object = object;
     }
 
+    /// Sets the previous exception in the chain, which is accessible from PHP
+    /// through `getPrevious()` once the exception is thrown.
+    ///
+    /// # Parameters
+    ///
+    /// * `previous` - The exception which caused this exception.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use ext_php_rs::exception::PhpException;
+    ///
+    /// let ex = PhpException::default("Failed to load config".into())
+    ///     .with_previous(PhpException::default("File not found".into()));
+    /// ```
+    pub fn with_previous(mut self, previous: PhpException) -> Self {
+        self.previous = Some(Box::new(previous));
+        self
+    }
+
+    /// Returns the previous exception in the chain, if any.
+    pub fn previous(&self) -> Option<&PhpException> {
+        self.previous.as_deref()
+    }
+
+    /// Captures a Rust backtrace at the current location and attaches it to
+    /// the exception. When thrown, the backtrace is appended to the exception
+    /// message so it shows up alongside the PHP stack trace.
+    ///
+    /// The backtrace is not added to exceptions thrown from an object set
+    /// with [`PhpException::set_object`].
+    pub fn with_backtrace(mut self) -> Self {
+        self.backtrace = Some(Box::new(Backtrace::force_capture()));
+        self
+    }
+
+    /// Returns the Rust backtrace attached to the exception, if any.
+    pub fn backtrace(&self) -> Option<&Backtrace> {
+        self.backtrace.as_deref()
+    }
+
+    /// Overrides the file reported by the exception, which defaults to the
+    /// file of the PHP code calling the extension.
+    ///
+    /// # Parameters
+    ///
+    /// * `file` - The file to report.
+    pub fn with_file(mut self, file: impl Into<String>) -> Self {
+        self.overrides.get_or_insert_with(Default::default).file = Some(file.into());
+        self
+    }
+
+    /// Overrides the line reported by the exception, which defaults to the
+    /// line of the PHP code calling the extension.
+    ///
+    /// # Parameters
+    ///
+    /// * `line` - The line to report.
+    pub fn with_line(mut self, line: u32) -> Self {
+        self.overrides.get_or_insert_with(Default::default).line = Some(line);
+        self
+    }
+
+    /// Sets a property on the exception object when it is thrown, e.g. to
+    /// expose the fields of a Rust error on a domain exception.
+    ///
+    /// The property should be declared on the exception class, as creating
+    /// dynamic properties on exceptions is deprecated since PHP 8.2.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the property.
+    /// * `value` - The value of the property.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use ext_php_rs::{exception::PhpException, zend::ClassEntry};
+    ///
+    /// let ex = PhpException::new(
+    ///     "Query failed".into(),
+    ///     0,
+    ///     ClassEntry::try_find("App\\DbException").unwrap(),
+    /// )
+    /// .with_property("query", "SELECT 1")
+    /// .with_property("sqlState", "42000")
+    /// .with_file("db.rs")
+    /// .with_line(42);
+    /// ```
+    pub fn with_property(mut self, name: impl Into<String>, value: impl IntoZval) -> Self {
+        self.overrides
+            .get_or_insert_with(Default::default)
+            .properties
+            .push((name.into(), value.into_zval(false)));
+        self
+    }
+
+    /// Sets how the exception is reported when returned from a function
+    /// exported to PHP. See [`Severity`].
+    ///
+    /// # Parameters
+    ///
+    /// * `severity` - The severity of the exception.
+    pub fn with_severity(mut self, severity: Severity) -> Self {
+        self.severity = severity;
+        self
+    }
+
+    /// Returns how the exception is reported when returned from a function
+    /// exported to PHP.
+    pub fn severity(&self) -> Severity {
+        self.severity
+    }
+
     /// Throws the exception, returning nothing inside a result if successful
     /// and an error otherwise.
     pub fn throw(self) -> Result<()> {
-        match self.object {
-            Some(object) => throw_object(object),
-            None => throw_with_code(self.ex, self.code, &self.message),
+        let Self {
+            message,
+            code,
+            ex,
+            object,
+            previous,
+            backtrace,
+            overrides,
+            ..
+        } = self;
+
+        let Overrides {
+            file,
+            line,
+            properties,
+        } = overrides.map(|overrides| *overrides).unwrap_or_default();
+        let properties = properties
+            .into_iter()
+            .map(|(name, value)| Ok((name, value?)))
+            .collect::<Result<Vec<_>>>()?;
+
+        // When an exception is thrown while another one is pending, PHP attaches
+        // the pending exception as the previous exception of the new one, so the
+        // chain is built by throwing the innermost exception first.
+        if let Some(previous) = previous {
+            previous.throw()?;
+        }
+
+        match object {
+            Some(object) => throw_object(object)?,
+            None => match backtrace {
+                Some(backtrace) => throw_with_code(
+                    ex,
+                    code,
+                    &format!("{message}\n\nRust backtrace:\n{backtrace}"),
+                )?,
+                None => throw_with_code(ex, code, &message)?,
+            },
+        }
+
+        if file.is_none() && line.is_none() && properties.is_empty() {
+            return Ok(());
+        }
+
+        // SAFETY: The exception was just thrown, so it is the pending exception.
+        let Some(thrown) = (unsafe { ExecutorGlobals::get().exception.as_mut() }) else {
+            return Ok(());
+        };
+
+        // `file` and `line` are protected properties declared separately on the
+        // `Exception` and `Error` base classes.
+        let base = if thrown.instance_of(ce::exception()) {
+            ce::exception()
+        } else {
+            ce::error()
+        };
+        if let Some(file) = file {
+            update_property(base, thrown, "file", file.into_zval(false)?)?;
+        }
+        if let Some(line) = line {
+            update_property(base, thrown, "line", line.into_zval(false)?)?;
         }
+
+        let class = thrown.get_class_entry();
+        for (name, value) in properties {
+            update_property(class, thrown, &name, value)?;
+        }
+
+        Ok(())
     }
 }
 
+/// Updates a property of an object, accessing it from the scope of the given
+/// class so protected properties can be updated.
+fn update_property(
+    scope: &ClassEntry,
+    object: &mut ZendObject,
+    name: &str,
+    mut value: Zval,
+) -> Result<()> {
+    let name = CString::new(name)?;
+    unsafe {
+        zend_update_property(
+            (scope as *const ClassEntry).cast_mut(),
+            object,
+            name.as_ptr(),
+            name.as_bytes().len(),
+            &mut value,
+        )
+    };
+    Ok(())
+}
+
 impl From<PhpException> for String {
     fn from(ex: PhpException) -> Self {
         ex.message
@@ -106,6 +476,80 @@ impl From<&str> for PhpException {
     }
 }
 
+impl From<Box<dyn std::error::Error>> for PhpException {
+    fn from(err: Box<dyn std::error::Error>) -> Self {
+        Self::new(err.to_string(), 0, mapped_exception_class(err.as_ref()))
+    }
+}
+
+impl From<Box<dyn std::error::Error + Send + Sync>> for PhpException {
+    fn from(err: Box<dyn std::error::Error + Send + Sync>) -> Self {
+        Self::new(err.to_string(), 0, mapped_exception_class(err.as_ref()))
+    }
+}
+
+type ErrorMapping =
+    Box<dyn Fn(&(dyn std::error::Error + 'static)) -> Option<&'static ClassEntry> + Send + Sync>;
+
+static ERROR_MAPPINGS: RwLock<Vec<ErrorMapping>> = RwLock::new(Vec::new());
+
+/// Registers the PHP exception class thrown for a Rust error type.
+///
+/// Functions returning `Result<T, Box<dyn std::error::Error + Send + Sync>>`
+/// can use the `?` operator on any error type. When such a function returns
+/// an error, the error and then its chain of sources are checked against the
+/// registered mappings, and an exception of the first matching class is
+/// thrown with the error message. If no mapping matches, a plain `Exception`
+/// is thrown.
+///
+/// The class is resolved lazily when an error is converted, as class entries
+/// are only available once the module has started.
+///
+/// # Parameters
+///
+/// * `class` - Function returning the class entry of the exception to throw.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::{exception::register_error_mapping, zend::{ce, ClassEntry}};
+///
+/// register_error_mapping::<std::num::ParseIntError, _>(ce::value_error);
+/// register_error_mapping::<std::io::Error, _>(|| {
+///     ClassEntry::try_find("RuntimeException").unwrap_or_else(ce::exception)
+/// });
+/// ```
+pub fn register_error_mapping<E, F>(class: F)
+where
+    E: std::error::Error + 'static,
+    F: Fn() -> &'static ClassEntry + Send + Sync + 'static,
+{
+    ERROR_MAPPINGS
+        .write()
+        .expect("should acquire write lock for error mappings")
+        .push(Box::new(move |err| {
+            err.downcast_ref::<E>().map(|_| class())
+        }));
+}
+
+/// Returns the exception class registered for the given error or one of its
+/// sources, falling back to `Exception`.
+fn mapped_exception_class(err: &(dyn std::error::Error + 'static)) -> &'static ClassEntry {
+    let mappings = ERROR_MAPPINGS
+        .read()
+        .expect("should acquire read lock for error mappings");
+
+    let mut current = Some(err);
+    while let Some(err) = current {
+        if let Some(ce) = mappings.iter().find_map(|mapping| mapping(err)) {
+            return ce;
+        }
+        current = err.source();
+    }
+
+    ce::exception()
+}
+
 #[cfg(feature = "anyhow")]
 impl From<anyhow::Error> for PhpException {
     fn from(err: anyhow::Error) -> Self {
@@ -113,6 +557,112 @@ impl From<anyhow::Error> for PhpException {
     }
 }
 
+/// An exception class registered by the extension, usually declared with the
+/// [`php_exception_class!`] macro and registered with
+/// [`ModuleBuilder::exception_class`].
+///
+/// [`php_exception_class!`]: crate::php_exception_class
+/// [`ModuleBuilder::exception_class`]: crate::builders::ModuleBuilder::exception_class
+pub trait ExceptionClass {
+    /// The name of the class, including its namespace.
+    const CLASS_NAME: &'static str;
+
+    /// Returns the parent class of the exception.
+    fn parent() -> &'static ClassEntry {
+        ce::exception()
+    }
+
+    /// Returns the builder used to register the class.
+    fn builder() -> ExceptionClassBuilder {
+        ExceptionClassBuilder::new(Self::CLASS_NAME).extends(Self::parent())
+    }
+
+    /// Returns the class entry of the exception.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the class has not been registered.
+    fn ce() -> &'static ClassEntry {
+        ClassEntry::try_find(Self::CLASS_NAME)
+            .unwrap_or_else(|| panic!("Exception class {} is not registered.", Self::CLASS_NAME))
+    }
+
+    /// Creates an exception of this class with the given message and a code
+    /// of 0.
+    ///
+    /// # Parameters
+    ///
+    /// * `message` - Message to contain in the exception.
+    fn exception<T: Into<String>>(message: T) -> PhpException {
+        PhpException::new(message.into(), 0, Self::ce())
+    }
+}
+
+/// Runs the given function, converting a panic into a PHP `Error` exception
+/// containing the panic message instead of unwinding across the FFI boundary,
+/// which would abort the process.
+///
+/// This is used by the handlers generated for `#[php_function]` and
+/// `#[php_impl]`, and should also be used to wrap the bodies of other
+/// callbacks called by PHP, such as SAPI callbacks.
+///
+/// The function is assumed to be unwind safe, as the alternative is aborting
+/// the process.
+///
+/// # Parameters
+///
+/// * `func` - The function to run.
+///
+/// # Returns
+///
+/// The return value of the function, or the exception to throw if it
+/// panicked.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ext_php_rs::exception::catch_panic;
+///
+/// extern "C" fn flush(_server_context: *mut std::ffi::c_void) {
+///     if let Err(e) = catch_panic(|| {
+///         // ...
+///     }) {
+///         let _ = e.throw();
+///     }
+/// }
+/// ```
+pub fn catch_panic<R, F: FnOnce() -> R>(func: F) -> PhpResult<R> {
+    std::panic::catch_unwind(AssertUnwindSafe(func)).map_err(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("Box<dyn Any>");
+        let mut exception = PhpException::new(format!("Rust panic: {message}"), 0, ce::error());
+        if let Some(backtrace) = PANIC_BACKTRACE.with(Cell::take) {
+            exception.backtrace = Some(Box::new(backtrace));
+        }
+        exception
+    })
+}
+
+/// Runs the given function like [`catch_panic`], raising an `E_WARNING` with
+/// the panic message instead of throwing an exception if it panicked, for
+/// callbacks called by PHP which cannot throw, e.g. observers or stream
+/// operations.
+///
+/// # Parameters
+///
+/// * `default` - The value returned if the function panicked, e.g. the
+///   failure value of the callback.
+/// * `func` - The function to run.
+pub(crate) fn catch_panic_or<R, F: FnOnce() -> R>(default: R, func: F) -> R {
+    catch_panic(func).unwrap_or_else(|e| {
+        let _ = crate::zend::engine_error(crate::flags::ErrorType::Warning, e.message());
+        default
+    })
+}
+
 /// Throws an exception with a given message. See [`ClassEntry`] for some
 /// built-in exception types.
 ///
@@ -216,14 +766,100 @@ pub fn throw_object(zval: Zval) -> Result<()> {
     Ok(())
 }
 
-use std::sync::RwLock;
-
 static has_observer: RwLock<bool> = RwLock::new(false);
-static error_observers: RwLock<Vec<Box<
-    dyn Fn(i32, &mut ZendStr, usize, &mut ZendStr) + Send + Sync
->>> = RwLock::new(Vec::new());
+static next_observer_id: AtomicU64 = AtomicU64::new(0);
+static error_observers: RwLock<Vec<(u64, ErrorType, ErrorObserver)>> = RwLock::new(Vec::new());
+
+type ErrorObserver = Arc<dyn Fn(&ErrorInfo) + Send + Sync>;
+
+/// Information about an error raised by PHP, passed to error observers.
+#[derive(Debug, Clone, Copy)]
+pub struct ErrorInfo<'a> {
+    /// The level of the error.
+    pub level: ErrorType,
+    /// The file the error was raised in.
+    pub file: &'a str,
+    /// The line the error was raised on.
+    pub line: u32,
+    /// The error message.
+    pub message: &'a str,
+}
+
+/// Handle to an observer registered with [`register_error_observer`],
+/// [`register_exception_observer`], [`register_uncaught_handler`] or
+/// [`register_fatal_error_hook`], used to unregister it.
+#[derive(Debug, PartialEq, Eq, Hash)]
+#[must_use = "dropping the handle keeps the observer registered forever"]
+pub struct ObserverHandle {
+    id: u64,
+}
+
+impl ObserverHandle {
+    /// Unregisters the observer. It will not be called for errors or
+    /// exceptions raised afterwards.
+    pub fn unregister(self) {
+        {
+            let mut w = error_observers
+                .write()
+                .expect("should acquire write lock for error_observers");
+            w.retain(|(id, _, _)| *id != self.id);
+        }
+        {
+            let mut w = exception_observers
+                .write()
+                .expect("should acquire write lock for exception_observers");
+            w.retain(|(id, _)| *id != self.id);
+        }
+        {
+            let mut w = uncaught_handlers
+                .write()
+                .expect("should acquire write lock for uncaught_handlers");
+            w.retain(|(id, _)| *id != self.id);
+        }
+        {
+            let mut w = fatal_error_hooks
+                .write()
+                .expect("should acquire write lock for fatal_error_hooks");
+            w.retain(|(id, _)| *id != self.id);
+        }
+    }
+
+    /// Converts the handle into a guard which unregisters the observer
+    /// when dropped, e.g. at the end of a request or test.
+    pub fn scoped(self) -> ScopedObserver {
+        ScopedObserver { handle: Some(self) }
+    }
+}
+
+/// Guard which unregisters an observer when dropped. Created with
+/// [`ObserverHandle::scoped`].
+#[derive(Debug)]
+#[must_use = "the observer is unregistered as soon as the guard is dropped"]
+pub struct ScopedObserver {
+    handle: Option<ObserverHandle>,
+}
+
+impl ScopedObserver {
+    /// Keeps the observer registered after the guard is dropped,
+    /// returning its handle.
+    pub fn into_handle(mut self) -> ObserverHandle {
+        self.handle.take().expect("handle is only taken once")
+    }
+}
+
+impl Drop for ScopedObserver {
+    fn drop(&mut self) {
+        if let Some(handle) = self.handle.take() {
+            handle.unregister();
+        }
+    }
+}
 
-/// Register an error observer.
+/// Register an error observer, called for every error raised by PHP.
+///
+/// The observer stays registered until [`ObserverHandle::unregister`] is
+/// called on the returned handle, or until the guard returned by
+/// [`ObserverHandle::scoped`] is dropped.
 ///
 /// # Arguments
 ///
@@ -231,20 +867,59 @@ static error_observers: RwLock<Vec<Box<
 ///
 /// # Example
 ///
-/// ```
+/// ```no_run
 /// use ext_php_rs::exception::register_error_observer;
 ///
-/// register_error_observer(|error_type, filename, line, message| {
-///     // Handle the error
+/// let handle = register_error_observer(|error| {
+///     println!("{}:{}: {}", error.file, error.line, error.message);
 /// });
+///
+/// // Later on, stop observing errors.
+/// handle.unregister();
+///
+/// {
+///     let _guard = register_error_observer(|error| {
+///         // Handle the error
+///     }).scoped();
+///
+///     // The observer is unregistered at the end of the scope.
+/// }
 /// ```
-pub fn register_error_observer<F>(observer: F)
+pub fn register_error_observer<F>(observer: F) -> ObserverHandle
 where
-    F: Fn(i32, &mut ZendStr, usize, &mut ZendStr) + Send + Sync + 'static,
+    F: Fn(&ErrorInfo) + Send + Sync + 'static,
+{
+    register_error_observer_with_mask(ErrorType::all(), observer)
+}
+
+/// Register an error observer, only called for errors matching the given
+/// level mask.
+///
+/// # Arguments
+///
+/// * `mask` - The levels of errors to observe.
+/// * `observer` - The error observer to register.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::{exception::register_error_observer_with_mask, flags::ErrorType};
+///
+/// let handle = register_error_observer_with_mask(
+///     ErrorType::Error | ErrorType::CoreError | ErrorType::CompileError,
+///     |error| {
+///         // Only called for fatal errors.
+///     },
+/// );
+/// ```
+pub fn register_error_observer_with_mask<F>(mask: ErrorType, observer: F) -> ObserverHandle
+where
+    F: Fn(&ErrorInfo) + Send + Sync + 'static,
 {
     {
         if !*has_observer.read().unwrap() {
-            let mut w = has_observer.write()
+            let mut w = has_observer
+                .write()
                 .expect("should acquire write lock for has_observer");
             *w = true;
 
@@ -254,11 +929,16 @@ where
         }
     }
 
+    let id = next_observer_id.fetch_add(1, Ordering::Relaxed);
+
     {
-        let mut w = error_observers.write()
+        let mut w = error_observers
+            .write()
             .expect("should acquire write lock for error_observers");
-        w.push(Box::new(observer));
+        w.push((id, mask, Arc::new(observer)));
     }
+
+    ObserverHandle { id }
 }
 
 #[no_mangle]
@@ -266,21 +946,592 @@ extern "C" fn error_observer_dispatcher(
     error_type: c_int,
     filename: *mut ZendStr,
     line: c_uint,
-    message: *mut ZendStr
+    message: *mut ZendStr,
 ) {
-    let observers = error_observers.read()
-        .expect("should acquire read lock for error_observers");
+    if OBSERVING_ERROR.get() {
+        return;
+    }
+    let level = ErrorType::from_bits_truncate(error_type as u32);
+
+    // Observers are cloned out of the lock so they can unregister themselves,
+    // or register other observers, while being called.
+    let observers: Vec<ErrorObserver> = {
+        let observers = error_observers
+            .read()
+            .expect("should acquire read lock for error_observers");
+        observers
+            .iter()
+            .filter(|(_, mask, _)| mask.intersects(level))
+            .map(|(_, _, observer)| observer.clone())
+            .collect()
+    };
 
     if observers.is_empty() {
         return;
     }
 
-    let file = unsafe { &mut *filename };
-    let message = unsafe { &mut *message };
+    let file = unsafe { filename.as_ref() }
+        .map(|file| String::from_utf8_lossy(file.as_bytes()))
+        .unwrap_or_default();
+    let message = unsafe { message.as_ref() }
+        .map(|message| String::from_utf8_lossy(message.as_bytes()))
+        .unwrap_or_default();
 
-    let line = line as usize;
+    let info = ErrorInfo {
+        level,
+        file: &file,
+        line,
+        message: &message,
+    };
 
     for observer in observers.iter() {
-        observer(error_type, file, line, message);
+        // Same as `catch_panic_or`, without observing the warning it raises,
+        // which would be reported again to an observer panicking on every
+        // warning.
+        if let Err(e) = catch_panic(|| observer(&info)) {
+            OBSERVING_ERROR.set(true);
+            let _ = crate::zend::engine_error(ErrorType::Warning, e.message());
+            OBSERVING_ERROR.set(false);
+        }
+    }
+}
+
+type ExceptionObserver = Arc<dyn Fn(&ZendObject) + Send + Sync>;
+type ThrowExceptionHook = unsafe extern "C" fn(*mut ZendObject);
+
+static exception_observers: RwLock<Vec<(u64, ExceptionObserver)>> = RwLock::new(Vec::new());
+static previous_throw_hook: RwLock<Option<Option<ThrowExceptionHook>>> = RwLock::new(None);
+
+/// Register an exception observer, called with the exception object whenever
+/// an exception is thrown, either from PHP code or from an extension.
+///
+/// The observer is called before the exception is handled, so it is also
+/// called for exceptions which are caught later on. It must not throw
+/// exceptions itself.
+///
+/// The observer stays registered until [`ObserverHandle::unregister`] is
+/// called on the returned handle, or until the guard returned by
+/// [`ObserverHandle::scoped`] is dropped.
+///
+/// # Arguments
+///
+/// * `observer` - The exception observer to register.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::exception::register_exception_observer;
+///
+/// let handle = register_exception_observer(|exception| {
+///     let class = exception.get_class_name().unwrap_or_default();
+///     println!("{class} was thrown");
+/// });
+/// ```
+pub fn register_exception_observer<F>(observer: F) -> ObserverHandle
+where
+    F: Fn(&ZendObject) + Send + Sync + 'static,
+{
+    {
+        let mut previous = previous_throw_hook
+            .write()
+            .expect("should acquire write lock for previous_throw_hook");
+        if previous.is_none() {
+            unsafe {
+                *previous = Some(crate::ffi::zend_throw_exception_hook);
+                crate::ffi::zend_throw_exception_hook = Some(exception_observer_dispatcher);
+            }
+        }
+    }
+
+    let id = next_observer_id.fetch_add(1, Ordering::Relaxed);
+
+    {
+        let mut w = exception_observers
+            .write()
+            .expect("should acquire write lock for exception_observers");
+        w.push((id, Arc::new(observer)));
+    }
+
+    ObserverHandle { id }
+}
+
+unsafe extern "C" fn exception_observer_dispatcher(exception: *mut ZendObject) {
+    // Observers are cloned out of the lock so they can unregister themselves,
+    // or register other observers, while being called.
+    let observers: Vec<ExceptionObserver> = {
+        let observers = exception_observers
+            .read()
+            .expect("should acquire read lock for exception_observers");
+        observers
+            .iter()
+            .map(|(_, observer)| observer.clone())
+            .collect()
+    };
+
+    if let Some(object) = exception.as_ref() {
+        for observer in observers.iter() {
+            catch_panic_or((), || observer(object));
+        }
+    }
+
+    let previous = *previous_throw_hook
+        .read()
+        .expect("should acquire read lock for previous_throw_hook");
+    if let Some(Some(previous)) = previous {
+        previous(exception);
+    }
+}
+
+/// Report of an uncaught exception or fatal error which terminated a request,
+/// passed to handlers registered with [`register_uncaught_handler`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UncaughtReport {
+    /// The class of the uncaught exception, or `None` for a fatal error which
+    /// was not caused by an exception.
+    pub class: Option<String>,
+    /// The exception or error message.
+    pub message: String,
+    /// The exception code, zero for fatal errors.
+    pub code: i64,
+    /// The file the exception was thrown or the error raised in.
+    pub file: String,
+    /// The line the exception was thrown or the error raised on.
+    pub line: u32,
+    /// The PHP stack trace of the exception, as returned by
+    /// `getTraceAsString()`.
+    pub trace: Option<String>,
+    /// The chain of previous exceptions, from the most recent one.
+    pub previous: Vec<UncaughtReport>,
+    /// Whether the response headers were already sent when the request
+    /// terminated, in which case an error page can no longer be sent.
+    pub headers_sent: bool,
+}
+
+impl UncaughtReport {
+    fn from_exception(exception: &ZendObject, headers_sent: bool) -> Self {
+        let mut report = Self::describe(exception, headers_sent);
+
+        let mut current = exception.try_call_method("getPrevious", vec![]).ok();
+        while let Some(previous) = current.as_ref().and_then(Zval::object) {
+            let next = previous.try_call_method("getPrevious", vec![]).ok();
+            report.previous.push(Self::describe(previous, headers_sent));
+            current = next;
+        }
+
+        report
+    }
+
+    fn describe(exception: &ZendObject, headers_sent: bool) -> Self {
+        let call = |method: &str| exception.try_call_method(method, vec![]).ok();
+
+        Self {
+            class: exception.get_class_name().ok(),
+            message: call("getMessage")
+                .and_then(|message| message.string())
+                .unwrap_or_default(),
+            code: call("getCode")
+                .and_then(|code| code.long())
+                .unwrap_or_default(),
+            file: call("getFile")
+                .and_then(|file| file.string())
+                .unwrap_or_default(),
+            line: call("getLine")
+                .and_then(|line| line.long())
+                .unwrap_or_default() as u32,
+            trace: call("getTraceAsString").and_then(|trace| trace.string()),
+            previous: Vec::new(),
+            headers_sent,
+        }
+    }
+}
+
+type UncaughtHandler = Arc<dyn Fn(&UncaughtReport) + Send + Sync>;
+
+static uncaught_handlers: RwLock<Vec<(u64, UncaughtHandler)>> = RwLock::new(Vec::new());
+static uncaught_observers: RwLock<Option<(ObserverHandle, ObserverHandle)>> = RwLock::new(None);
+
+thread_local! {
+    /// Handle and address of the last exception object thrown by the request
+    /// running on this thread.
+    static last_thrown: Cell<Option<(u32, usize)>> = const { Cell::new(None) };
+}
+
+/// Register a handler called when a request terminates with an uncaught
+/// exception or a fatal error, so hosts can render error pages or report
+/// crashes outside of PHP.
+///
+/// The handler is called while PHP reports the error, before the request is
+/// torn down. It must not call into PHP.
+///
+/// The handler stays registered until [`ObserverHandle::unregister`] is
+/// called on the returned handle, or until the guard returned by
+/// [`ObserverHandle::scoped`] is dropped.
+///
+/// # Arguments
+///
+/// * `handler` - The handler to register.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::exception::register_uncaught_handler;
+///
+/// let handle = register_uncaught_handler(|report| {
+///     eprintln!(
+///         "{}: {} in {}:{}",
+///         report.class.as_deref().unwrap_or("Fatal error"),
+///         report.message,
+///         report.file,
+///         report.line,
+///     );
+/// });
+/// ```
+pub fn register_uncaught_handler<F>(handler: F) -> ObserverHandle
+where
+    F: Fn(&UncaughtReport) + Send + Sync + 'static,
+{
+    {
+        let mut observers = uncaught_observers
+            .write()
+            .expect("should acquire write lock for uncaught_observers");
+        if observers.is_none() {
+            let exceptions = register_exception_observer(|exception| {
+                last_thrown.with(|last| {
+                    last.set(Some((
+                        exception.handle,
+                        exception as *const ZendObject as usize,
+                    )))
+                });
+            });
+            let errors = register_error_observer_with_mask(FATAL_ERRORS, uncaught_error_observer);
+            *observers = Some((exceptions, errors));
+        }
+    }
+
+    let id = next_observer_id.fetch_add(1, Ordering::Relaxed);
+
+    {
+        let mut w = uncaught_handlers
+            .write()
+            .expect("should acquire write lock for uncaught_handlers");
+        w.push((id, Arc::new(handler)));
+    }
+
+    ObserverHandle { id }
+}
+
+/// Returns the last thrown exception if it is still alive.
+fn last_thrown_exception() -> Option<&'static ZendObject> {
+    let (handle, address) = last_thrown.with(Cell::take)?;
+
+    // The exception may have been freed since it was thrown, so it is looked up
+    // in the object store rather than dereferenced directly.
+    let globals = ExecutorGlobals::get();
+    let store = &globals.objects_store;
+    if store.object_buckets.is_null() || handle >= store.top {
+        return None;
+    }
+    let object = unsafe { *store.object_buckets.add(handle as usize) };
+    if object as usize != address {
+        return None;
+    }
+    unsafe { object.as_ref() }
+}
+
+fn uncaught_error_observer(error: &ErrorInfo) {
+    let handlers: Vec<UncaughtHandler> = {
+        let handlers = uncaught_handlers
+            .read()
+            .expect("should acquire read lock for uncaught_handlers");
+        handlers
+            .iter()
+            .map(|(_, handler)| handler.clone())
+            .collect()
+    };
+
+    if handlers.is_empty() {
+        return;
+    }
+
+    let headers_sent = crate::zend::SapiGlobals::get().headers_sent != 0;
+
+    // Uncaught exceptions are reported by PHP as a fatal error starting with
+    // "Uncaught", after the exception is cleared from the executor.
+    let exception = error
+        .message
+        .starts_with("Uncaught ")
+        .then(last_thrown_exception)
+        .flatten();
+    let report = match exception {
+        Some(exception) => UncaughtReport::from_exception(exception, headers_sent),
+        None => UncaughtReport {
+            class: None,
+            message: error.message.to_string(),
+            code: 0,
+            file: error.file.to_string(),
+            line: error.line,
+            trace: None,
+            previous: Vec::new(),
+            headers_sent,
+        },
+    };
+
+    for handler in handlers.iter() {
+        catch_panic_or((), || handler(&report));
+    }
+}
+
+/// Error levels which terminate the request.
+const FATAL_ERRORS: ErrorType = ErrorType::Error
+    .union(ErrorType::CoreError)
+    .union(ErrorType::CompileError)
+    .union(ErrorType::UserError)
+    .union(ErrorType::RecoverableError)
+    .union(ErrorType::Parse);
+
+type FatalErrorHook = Arc<dyn Fn(&ErrorInfo) + Send + Sync>;
+
+#[cfg(php81)]
+type ErrorCallback = unsafe extern "C" fn(c_int, *mut ZendStr, u32, *mut ZendStr);
+#[cfg(not(php81))]
+type ErrorCallback = unsafe extern "C" fn(c_int, *const std::ffi::c_char, u32, *mut ZendStr);
+
+static fatal_error_hooks: RwLock<Vec<(u64, FatalErrorHook)>> = RwLock::new(Vec::new());
+static previous_error_cb: RwLock<Option<Option<ErrorCallback>>> = RwLock::new(None);
+
+/// Register a hook called when a fatal error (`E_ERROR`, `E_CORE_ERROR`,
+/// `E_COMPILE_ERROR`, `E_USER_ERROR`, `E_RECOVERABLE_ERROR` or `E_PARSE`) is
+/// raised, before PHP bails out of the request.
+///
+/// This gives hosts a last chance to log the error, capture memory usage or
+/// flush telemetry while the request state is still intact. The hook is
+/// installed by wrapping `zend_error_cb`, and the previous callback is always
+/// called afterwards, so the error is still handled by PHP as usual. Panics
+/// in the hook are caught and ignored.
+///
+/// The hook stays registered until [`ObserverHandle::unregister`] is called
+/// on the returned handle, or until the guard returned by
+/// [`ObserverHandle::scoped`] is dropped.
+///
+/// # Arguments
+///
+/// * `hook` - The hook to register.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::exception::register_fatal_error_hook;
+///
+/// let handle = register_fatal_error_hook(|error| {
+///     eprintln!("fatal error in {}:{}: {}", error.file, error.line, error.message);
+/// });
+/// ```
+pub fn register_fatal_error_hook<F>(hook: F) -> ObserverHandle
+where
+    F: Fn(&ErrorInfo) + Send + Sync + 'static,
+{
+    {
+        let mut previous = previous_error_cb
+            .write()
+            .expect("should acquire write lock for previous_error_cb");
+        if previous.is_none() {
+            unsafe {
+                *previous = Some(crate::ffi::zend_error_cb);
+                crate::ffi::zend_error_cb = Some(fatal_error_dispatcher);
+            }
+        }
+    }
+
+    let id = next_observer_id.fetch_add(1, Ordering::Relaxed);
+
+    {
+        let mut w = fatal_error_hooks
+            .write()
+            .expect("should acquire write lock for fatal_error_hooks");
+        w.push((id, Arc::new(hook)));
+    }
+
+    ObserverHandle { id }
+}
+
+#[cfg(php81)]
+unsafe fn error_filename(filename: *mut ZendStr) -> std::borrow::Cow<'static, str> {
+    filename
+        .as_ref()
+        .map(|file| String::from_utf8_lossy(file.as_bytes()))
+        .unwrap_or_default()
+}
+
+#[cfg(not(php81))]
+unsafe fn error_filename(filename: *const std::ffi::c_char) -> std::borrow::Cow<'static, str> {
+    if filename.is_null() {
+        return Default::default();
+    }
+    std::ffi::CStr::from_ptr(filename).to_string_lossy()
+}
+
+unsafe extern "C" fn fatal_error_dispatcher(
+    error_type: c_int,
+    #[cfg(php81)] filename: *mut ZendStr,
+    #[cfg(not(php81))] filename: *const std::ffi::c_char,
+    line: u32,
+    message: *mut ZendStr,
+) {
+    let level = ErrorType::from_bits_truncate(error_type as u32);
+
+    if level.intersects(FATAL_ERRORS) {
+        let hooks: Vec<FatalErrorHook> = {
+            let hooks = fatal_error_hooks
+                .read()
+                .expect("should acquire read lock for fatal_error_hooks");
+            hooks.iter().map(|(_, hook)| hook.clone()).collect()
+        };
+
+        if !hooks.is_empty() {
+            let file = error_filename(filename);
+            let message = message
+                .as_ref()
+                .map(|message| String::from_utf8_lossy(message.as_bytes()))
+                .unwrap_or_default();
+            let info = ErrorInfo {
+                level,
+                file: &file,
+                line,
+                message: &message,
+            };
+
+            for hook in hooks.iter() {
+                // The previous callback must be called even if a hook panics, as it
+                // is responsible for bailing out of the request.
+                let _ = std::panic::catch_unwind(AssertUnwindSafe(|| hook(&info)));
+            }
+        }
+    }
+
+    let previous = *previous_error_cb
+        .read()
+        .expect("should acquire read lock for previous_error_cb");
+    if let Some(Some(previous)) = previous {
+        previous(error_type, filename, line, message);
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{embed::Embed, zend};
+    use std::sync::atomic::AtomicUsize;
+
+    #[test]
+    fn test_unregister_error_observer() {
+        Embed::run(|| {
+            let calls = Arc::new(AtomicUsize::new(0));
+
+            let counter = calls.clone();
+            let handle = register_error_observer(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+            zend::engine_error(ErrorType::Notice, "first").unwrap();
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+            handle.unregister();
+            zend::engine_error(ErrorType::Notice, "second").unwrap();
+            assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+            {
+                let counter = calls.clone();
+                let _guard = register_error_observer(move |_| {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                })
+                .scoped();
+                zend::engine_error(ErrorType::Notice, "third").unwrap();
+                assert_eq!(calls.load(Ordering::SeqCst), 2);
+            }
+
+            zend::engine_error(ErrorType::Notice, "fourth").unwrap();
+            assert_eq!(calls.load(Ordering::SeqCst), 2);
+        });
+    }
+
+    #[test]
+    fn test_error_observer_mask() {
+        Embed::run(|| {
+            let warnings = Arc::new(RwLock::new(Vec::new()));
+
+            let observed = warnings.clone();
+            let _guard = register_error_observer_with_mask(ErrorType::Warning, move |error| {
+                assert_eq!(error.level, ErrorType::Warning);
+                observed.write().unwrap().push(error.message.to_string());
+            })
+            .scoped();
+
+            zend::engine_error(ErrorType::Notice, "notice").unwrap();
+            zend::engine_error(ErrorType::Warning, "warning").unwrap();
+            assert_eq!(*warnings.read().unwrap(), vec!["warning".to_string()]);
+        });
+    }
+
+    #[test]
+    fn test_exception_observer() {
+        Embed::run(|| {
+            let thrown = Arc::new(RwLock::new(Vec::new()));
+
+            let observed = thrown.clone();
+            let _guard = register_exception_observer(move |exception| {
+                observed
+                    .write()
+                    .unwrap()
+                    .push(exception.get_class_name().unwrap());
+            })
+            .scoped();
+
+            let result = Embed::eval(
+                "try { throw new LogicException('caught'); } catch (Exception $e) {} 'done';",
+            );
+            assert!(result.is_ok());
+            assert_eq!(*thrown.read().unwrap(), vec!["LogicException".to_string()]);
+        });
+    }
+
+    #[test]
+    fn test_uncaught_handler() {
+        Embed::run(|| {
+            let reports = Arc::new(RwLock::new(Vec::new()));
+
+            let observed = reports.clone();
+            let _guard = register_uncaught_handler(move |report| {
+                observed.write().unwrap().push(report.clone());
+            })
+            .scoped();
+
+            let result = Embed::run_script("src/embed/test-script-exception.php");
+            assert!(result.is_err());
+
+            let reports = reports.read().unwrap();
+            assert_eq!(reports.len(), 1);
+            assert_eq!(reports[0].class.as_deref(), Some("RuntimeException"));
+            assert_eq!(reports[0].message, "This is a test exception");
+            assert_eq!(reports[0].line, 3);
+            assert!(reports[0].previous.is_empty());
+        });
+    }
+
+    #[test]
+    fn test_fatal_error_hook() {
+        Embed::run(|| {
+            let errors = Arc::new(RwLock::new(Vec::new()));
+
+            let observed = errors.clone();
+            let _guard = register_fatal_error_hook(move |error| {
+                observed.write().unwrap().push(error.message.to_string());
+            })
+            .scoped();
+
+            zend::engine_error(ErrorType::Warning, "not fatal").unwrap();
+            let result = Embed::eval("trigger_error('fatal', E_USER_ERROR);");
+            assert!(result.is_err());
+            assert_eq!(*errors.read().unwrap(), vec!["fatal".to_string()]);
+        });
     }
 }
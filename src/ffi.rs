@@ -21,16 +21,24 @@ extern "C" {
     pub fn ext_php_rs_zend_string_release(zs: *mut zend_string);
     pub fn ext_php_rs_is_known_valid_utf8(zs: *const zend_string) -> bool;
     pub fn ext_php_rs_set_known_valid_utf8(zs: *mut zend_string);
+    pub fn ext_php_rs_zend_string_init_permanent(
+        str_: *const c_char,
+        len: usize,
+    ) -> *mut zend_string;
 
     pub fn ext_php_rs_php_build_id() -> *const c_char;
     pub fn ext_php_rs_zend_object_alloc(obj_size: usize, ce: *mut zend_class_entry) -> *mut c_void;
     pub fn ext_php_rs_zend_object_release(obj: *mut zend_object);
+    pub fn ext_php_rs_zval_make_ref(zv: *mut zval);
+    pub fn ext_php_rs_pemalloc(size: usize) -> *mut c_void;
+    pub fn ext_php_rs_pefree(ptr: *mut c_void);
     pub fn ext_php_rs_executor_globals() -> *mut zend_executor_globals;
     pub fn ext_php_rs_compiler_globals() -> *mut zend_compiler_globals;
     pub fn ext_php_rs_process_globals() -> *mut php_core_globals;
     pub fn ext_php_rs_sapi_globals() -> *mut sapi_globals_struct;
     pub fn ext_php_rs_file_globals() -> *mut php_file_globals;
     pub fn ext_php_rs_sapi_module() -> *mut sapi_module_struct;
+    pub fn ext_php_rs_tsrm_is_main_thread() -> bool;
     pub fn ext_php_rs_zend_try_catch(
         func: unsafe extern "C" fn(*const c_void) -> *const c_void,
         ctx: *const c_void,
@@ -44,6 +52,16 @@ extern "C" {
     ) -> bool;
 
     pub fn ext_php_rs_zend_bailout() -> !;
+    pub fn ext_php_rs_stream_alloc(
+        ops: *const php_stream_ops,
+        abstract_: *mut c_void,
+        mode: *const c_char,
+    ) -> *mut php_stream;
+    pub fn ext_php_rs_stream_filter_alloc(
+        fops: *const php_stream_filter_ops,
+        abstract_: *mut c_void,
+        persistent: u8,
+    ) -> *mut php_stream_filter;
 }
 
 // TODO: Gate this with the embed feature
@@ -59,6 +77,16 @@ extern "C" {
     pub fn ext_php_rs_sapi_startup();
     pub fn ext_php_rs_sapi_shutdown();
     pub fn ext_php_rs_sapi_per_thread_init();
+    pub fn ext_php_rs_sapi_per_thread_shutdown();
+    pub fn ext_php_rs_set_sapi_error_handler(
+        handler: Option<
+            extern "C" fn(
+                type_: ::std::os::raw::c_int,
+                message: *const ::std::os::raw::c_char,
+                len: usize,
+            ),
+        >,
+    );
     pub fn ext_php_rs_php_error(
         type_: ::std::os::raw::c_int,
         error_msg: *const ::std::os::raw::c_char,
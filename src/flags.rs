@@ -4,8 +4,10 @@ use bitflags::bitflags;
 
 #[cfg(not(php82))]
 use crate::ffi::ZEND_ACC_REUSE_GET_ITERATOR;
+#[cfg(php84)]
+use crate::ffi::ZEND_ACC_VIRTUAL;
 use crate::ffi::{
-    CONST_CS, CONST_DEPRECATED, CONST_NO_FILE_CACHE, CONST_PERSISTENT, E_COMPILE_ERROR,
+    _IS_BOOL, CONST_CS, CONST_DEPRECATED, CONST_NO_FILE_CACHE, CONST_PERSISTENT, E_COMPILE_ERROR,
     E_COMPILE_WARNING, E_CORE_ERROR, E_CORE_WARNING, E_DEPRECATED, E_ERROR, E_NOTICE, E_PARSE,
     E_RECOVERABLE_ERROR, E_STRICT, E_USER_DEPRECATED, E_USER_ERROR, E_USER_NOTICE, E_USER_WARNING,
     E_WARNING, IS_ARRAY, IS_CALLABLE, IS_CONSTANT_AST, IS_DOUBLE, IS_FALSE, IS_INDIRECT,
@@ -23,7 +25,6 @@ use crate::ffi::{
     ZEND_ACC_TOP_LEVEL, ZEND_ACC_TRAIT, ZEND_ACC_TRAIT_CLONE, ZEND_ACC_UNRESOLVED_VARIANCE,
     ZEND_ACC_USES_THIS, ZEND_ACC_USE_GUARDS, ZEND_ACC_VARIADIC, ZEND_EVAL_CODE,
     ZEND_HAS_STATIC_IN_METHODS, ZEND_INTERNAL_FUNCTION, ZEND_USER_FUNCTION, Z_TYPE_FLAGS_SHIFT,
-    _IS_BOOL,
 };
 
 use std::{convert::TryFrom, fmt::Display};
@@ -229,6 +230,9 @@ bitflags! {
         const Static = ZEND_ACC_STATIC;
         /// Promoted property
         const Promoted = ZEND_ACC_PROMOTED;
+        /// Virtual property, which is backed by hooks rather than storage
+        #[cfg(php84)]
+        const Virtual = ZEND_ACC_VIRTUAL;
     }
 }
 
@@ -290,6 +294,7 @@ bitflags! {
 
 bitflags! {
     /// Represents error types when used via php_error_docref for example.
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy)]
     pub struct ErrorType: u32 {
         /// Error
         const Error = E_ERROR;
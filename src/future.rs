@@ -0,0 +1,348 @@
+//! Awaiting Rust futures from PHP, by suspending the current fiber until they
+//! complete.
+//!
+//! With the `tokio` feature, functions declared with `async fn`, or returning
+//! `impl Future`, are awaited with [`block_on`] by the
+//! [`macro@crate::php_function`] attribute. The future is polled on the thread
+//! of the request, inside the context of the runtime set with [`set_runtime`],
+//! so it may use the I/O and timers of tokio. Other futures of the crate, e.g.
+//! the [workers](crate::worker), are awaited the same way without a runtime.
+//! While a future is pending:
+//!
+//! * inside a fiber, the fiber is suspended, leaving it to the scheduler of
+//!   the fibers to resume it once the future may make progress, e.g. after
+//!   [`wait`] returns, or from the hook set with [`set_wake_hook`]. Resuming
+//!   the fiber earlier polls the future again, and suspends the fiber again,
+//! * outside of a fiber, the thread is parked until the future is woken.
+//!
+//! The runtime must run on other threads than the ones of the requests, e.g.
+//! a multi-threaded runtime, as the requests do not drive it.
+//!
+//! # Example
+//!
+//! ```rust,ignore
+//! use ext_php_rs::prelude::*;
+//!
+//! #[php_function]
+//! pub async fn sleep_ms(ms: u64) -> u64 {
+//!     tokio::time::sleep(std::time::Duration::from_millis(ms)).await;
+//!     ms
+//! }
+//!
+//! #[php_module]
+//! pub fn get_module(module: ModuleBuilder) -> ModuleBuilder {
+//!     let runtime = tokio::runtime::Runtime::new().expect("should start tokio");
+//!     ext_php_rs::future::set_runtime(runtime.handle().clone());
+//!     std::mem::forget(runtime);
+//!
+//!     module
+//!         .function(wrap_function!(sleep_ms))
+//!         .function(ext_php_rs::future::wait_function("async_wait"))
+//! }
+//! ```
+//!
+//! ```php
+//! $fibers = [new Fiber(fn () => sleep_ms(20)), new Fiber(fn () => sleep_ms(10))];
+//! foreach ($fibers as $fiber) {
+//!     $fiber->start();
+//! }
+//! // Both sleeps run concurrently.
+//! while (async_wait()) {
+//!     foreach ($fibers as $fiber) {
+//!         if ($fiber->isSuspended()) {
+//!             $fiber->resume();
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::{
+    cell::Cell,
+    future::Future,
+    pin::{pin, Pin},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Wake, Waker},
+    thread::{self, Thread},
+};
+
+#[cfg(feature = "tokio")]
+use std::future::IntoFuture;
+
+#[cfg(feature = "tokio")]
+use ::tokio::runtime::Handle;
+use parking_lot::{const_rwlock, RwLock};
+
+#[cfg(feature = "tokio")]
+use crate::error::Error;
+#[cfg(php81)]
+use crate::types::ZendCallable;
+#[cfg(any(docs, php81))]
+use crate::zend::observer::FiberId;
+use crate::{
+    builders::FunctionBuilder, error::Result, flags::DataType, types::Zval, zend::ExecuteData,
+};
+
+type WakeHook = Box<dyn Fn() + Send + Sync>;
+#[cfg(any(docs, php81))]
+type FiberWakeHook = Box<dyn Fn(FiberId) + Send + Sync>;
+
+#[cfg(feature = "tokio")]
+static RUNTIME: RwLock<Option<Handle>> = const_rwlock(None);
+
+static WAKE_HOOK: RwLock<Option<WakeHook>> = const_rwlock(None);
+#[cfg(any(docs, php81))]
+static FIBER_WAKE_HOOK: RwLock<Option<FiberWakeHook>> = const_rwlock(None);
+
+thread_local! {
+    /// Whether a future or stream waiting on this thread was woken since the
+    /// last call to [`wait`].
+    static WOKEN: Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+
+    /// The number of fibers of this thread suspended until a future or stream
+    /// is woken.
+    static SUSPENDED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Sets the runtime in the context of which futures are polled. Replaces the
+/// previous runtime. Without one, the runtime of the calling thread is used,
+/// if any.
+///
+/// # Parameters
+///
+/// * `handle` - The handle of the runtime, which must run on other threads
+///   than the ones of the requests, e.g. a multi-threaded runtime.
+#[cfg(any(docs, feature = "tokio"))]
+#[cfg_attr(docs, doc(cfg(feature = "tokio")))]
+pub fn set_runtime(handle: Handle) {
+    *RUNTIME.write() = Some(handle);
+}
+
+/// Sets a hook called whenever a future awaited with [`block_on`], or a
+/// stream created by
+/// [`stream_from_async`](crate::stream::tokio::stream_from_async), may make
+/// progress, on the thread of the runtime which woke it, e.g. to schedule the
+/// fiber waiting on it. Replaces the previous hook.
+///
+/// # Parameters
+///
+/// * `hook` - The hook to call.
+pub fn set_wake_hook<F>(hook: F)
+where
+    F: Fn() + Send + Sync + 'static,
+{
+    *WAKE_HOOK.write() = Some(Box::new(hook));
+}
+
+/// Sets a hook called with the fiber waiting on a future awaited with
+/// [`block_on`], or on a stream, whenever it may make progress, on the thread
+/// of the runtime which woke it, so a scheduler can resume that fiber only.
+/// Futures awaited outside of a fiber do not call the hook. Replaces the
+/// previous hook.
+///
+/// The fibers are the ones notified to
+/// [`FiberObserver`](crate::zend::observer::FiberObserver)s.
+///
+/// # Parameters
+///
+/// * `hook` - The hook to call.
+#[cfg(any(docs, php81))]
+#[cfg_attr(docs, doc(cfg(php81)))]
+pub fn set_fiber_wake_hook<F>(hook: F)
+where
+    F: Fn(FiberId) + Send + Sync + 'static,
+{
+    *FIBER_WAKE_HOOK.write() = Some(Box::new(hook));
+}
+
+/// Awaits a future from PHP, suspending the current fiber, or parking the
+/// thread outside of a fiber, until it completes.
+///
+/// # Parameters
+///
+/// * `future` - The future.
+///
+/// # Errors
+///
+/// Returns [`Error::MissingRuntime`] if no runtime was set with
+/// [`set_runtime`] and the thread is not in the context of one, or an error if
+/// the fiber was destroyed while suspended.
+#[cfg(any(docs, feature = "tokio"))]
+#[cfg_attr(docs, doc(cfg(feature = "tokio")))]
+pub fn block_on<F: IntoFuture>(future: F) -> Result<F::Output> {
+    let handle = RUNTIME
+        .read()
+        .clone()
+        .or_else(|| Handle::try_current().ok())
+        .ok_or(Error::MissingRuntime)?;
+
+    poll_to_completion(future.into_future(), |future, cx| {
+        // The context is only entered while polling, as the guards of the
+        // fibers suspended on this thread would otherwise be dropped out of
+        // order.
+        let _guard = handle.enter();
+        future.poll(cx)
+    })
+}
+
+/// Polls a future with the given function until it completes, suspending the
+/// current fiber, or parking the thread outside of a fiber, while it is
+/// pending.
+pub(crate) fn poll_to_completion<F, P>(future: F, mut poll: P) -> Result<F::Output>
+where
+    F: Future,
+    P: FnMut(Pin<&mut F>, &mut Context<'_>) -> Poll<F::Output>,
+{
+    let signal = Signal::new();
+    let waker = Waker::from(signal.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        signal.woken.store(false, Ordering::Release);
+        if let Poll::Ready(output) = poll(future.as_mut(), &mut cx) {
+            return Ok(output);
+        }
+        suspend(&signal)?;
+    }
+}
+
+/// Parks the thread until a future or stream awaited by a fiber of the thread
+/// may make progress, so a scheduler can resume the fibers without spinning.
+///
+/// Returns `false` immediately if no fiber of the thread is suspended on a
+/// future or stream, and `true` otherwise.
+pub fn wait() -> bool {
+    if SUSPENDED.get() == 0 {
+        return false;
+    }
+    WOKEN.with(|woken| {
+        while !woken.swap(false, Ordering::Acquire) {
+            thread::park();
+        }
+    });
+    true
+}
+
+/// Returns a function calling [`wait`], to be added to the module with
+/// [`ModuleBuilder::function`](crate::builders::ModuleBuilder::function).
+///
+/// # Parameters
+///
+/// * `name` - The name of the function.
+pub fn wait_function(name: &str) -> FunctionBuilder<'static> {
+    FunctionBuilder::new(name, wait_handler).returns(DataType::Bool, false, false)
+}
+
+#[cfg(not(windows))]
+extern "C" fn wait_handler(_: &mut ExecuteData, retval: &mut Zval) {
+    retval.set_bool(wait());
+}
+
+#[cfg(windows)]
+extern "vectorcall" fn wait_handler(_: &mut ExecuteData, retval: &mut Zval) {
+    retval.set_bool(wait());
+}
+
+/// Wakes the request waiting on a future or stream.
+pub(crate) struct Signal {
+    pub(crate) woken: AtomicBool,
+    thread: Thread,
+    thread_woken: Arc<AtomicBool>,
+    /// The fiber waiting on the signal, if any.
+    #[cfg(php81)]
+    fiber: Option<FiberId>,
+}
+
+impl Signal {
+    /// Creates a signal waking the current thread.
+    pub(crate) fn new() -> Arc<Self> {
+        Arc::new(Self {
+            woken: AtomicBool::new(false),
+            thread: thread::current(),
+            thread_woken: WOKEN.with(Arc::clone),
+            #[cfg(php81)]
+            fiber: Some(FiberId::current()).filter(|fiber| !fiber.is_main()),
+        })
+    }
+}
+
+impl Wake for Signal {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        self.woken.store(true, Ordering::Release);
+        self.thread_woken.store(true, Ordering::Release);
+        self.thread.unpark();
+        if let Some(hook) = WAKE_HOOK.read_recursive().as_ref() {
+            hook();
+        }
+        #[cfg(php81)]
+        if let (Some(fiber), Some(hook)) = (self.fiber, FIBER_WAKE_HOOK.read_recursive().as_ref()) {
+            hook(fiber);
+        }
+    }
+}
+
+/// Returns whether the current code runs inside a fiber.
+#[cfg(php81)]
+pub(crate) fn in_fiber() -> Result<bool> {
+    let fiber = ZendCallable::try_from_name("Fiber::getCurrent")?.try_call(vec![])?;
+    Ok(fiber.is_object())
+}
+
+/// Returns whether the current code runs inside a fiber, which never happens
+/// before PHP 8.1.
+#[cfg(not(php81))]
+pub(crate) fn in_fiber() -> Result<bool> {
+    Ok(false)
+}
+
+/// Suspends the current fiber, or parks the thread outside of a fiber, until
+/// the signal is woken.
+pub(crate) fn suspend(signal: &Signal) -> Result<()> {
+    #[cfg(php81)]
+    if in_fiber()? {
+        // The scheduler resumes the fiber, or destroys it, which throws.
+        SUSPENDED.set(SUSPENDED.get() + 1);
+        let result = ZendCallable::try_from_name("Fiber::suspend")
+            .and_then(|suspend| suspend.try_call(vec![]));
+        SUSPENDED.set(SUSPENDED.get() - 1);
+        return result.map(|_| ());
+    }
+    while !signal.woken.swap(false, Ordering::Acquire) {
+        thread::park();
+    }
+    Ok(())
+}
+
+#[cfg(all(feature = "embed", feature = "tokio"))]
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_block_on() {
+        let runtime = ::tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_time()
+            .build()
+            .expect("should build the runtime");
+        set_runtime(runtime.handle().clone());
+
+        Embed::run(|| {
+            let value = block_on(async {
+                ::tokio::time::sleep(Duration::from_millis(10)).await;
+                42
+            });
+            assert_eq!(value.ok(), Some(42));
+            assert!(!wait());
+        });
+    }
+}
@@ -0,0 +1,201 @@
+//! Arbitrary PHP values, used to fuzz the conversions between Rust and PHP
+//! values with [`cargo fuzz`](https://github.com/rust-fuzz/cargo-fuzz).
+//!
+//! [`Value`] implements [`Arbitrary`], so fuzz targets receive trees of
+//! nested arrays, objects and references holding strings which are not valid
+//! UTF-8, NUL bytes, extreme numbers and so on. Once built into a [`Zval`]
+//! inside a request, e.g. one started by [`run_test`](crate::embed::run_test),
+//! [`check_conversions`] extracts Rust values from it and checks that they
+//! convert back to the same PHP values.
+//!
+//! The fuzz targets of the crate are in the `fuzz` directory of the
+//! repository, and are run with `cargo fuzz run <target>`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ext_php_rs::fuzz::{check_conversions, Value};
+//!
+//! fn fuzz(value: Value) {
+//!     // Inside a request:
+//!     let zval = value.to_zval().expect("should build the value");
+//!     check_conversions(&zval);
+//! }
+//! ```
+
+use std::collections::HashMap;
+
+use arbitrary::Arbitrary;
+
+use crate::{
+    convert::{FromZval, IntoZval},
+    error::Result,
+    ffi::ext_php_rs_zval_make_ref,
+    types::{ArrayKey, ZendHashTable, ZendObject, Zval},
+};
+
+/// The depth from which nested arrays, objects and references are built as
+/// `null`, so deep trees do not overflow the stack.
+const MAX_DEPTH: usize = 32;
+
+/// An arbitrary PHP value.
+#[derive(Debug, Clone, Arbitrary)]
+pub enum Value {
+    /// `null`.
+    Null,
+    /// A boolean.
+    Bool(bool),
+    /// An integer.
+    Long(i64),
+    /// A floating point number, including `NAN` and `INF`.
+    Double(f64),
+    /// A string of arbitrary bytes.
+    String(Vec<u8>),
+    /// An array, in insertion order.
+    Array(Vec<(Key, Value)>),
+    /// A `stdClass` object with dynamic properties.
+    Object(Vec<(String, Value)>),
+    /// A reference to a value.
+    Reference(Box<Value>),
+}
+
+/// An arbitrary key of a PHP array.
+#[derive(Debug, Clone, Arbitrary)]
+pub enum Key {
+    /// A numerical key.
+    Index(u64),
+    /// A string key, which may look like a number.
+    Name(String),
+    /// The next numerical key, as given by `$array[] = ...`.
+    Next,
+}
+
+impl Value {
+    /// Builds the PHP value. Must be called inside a request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if PHP rejects the value, e.g. when pushing to an
+    /// array whose next numerical key overflows.
+    pub fn to_zval(&self) -> Result<Zval> {
+        self.build(0)
+    }
+
+    fn build(&self, depth: usize) -> Result<Zval> {
+        let mut zval = Zval::new();
+        match self {
+            Value::Null => zval.set_null(),
+            Value::Bool(value) => zval.set_bool(*value),
+            Value::Long(value) => zval.set_long(*value),
+            Value::Double(value) => zval.set_double(*value),
+            Value::String(value) => zval.set_binary(value.clone()),
+            _ if depth >= MAX_DEPTH => zval.set_null(),
+            Value::Array(entries) => {
+                let mut array = ZendHashTable::new();
+                for (key, value) in entries {
+                    let value = value.build(depth + 1)?;
+                    match key {
+                        Key::Index(index) => array.insert_at_index(*index, value)?,
+                        Key::Name(name) => array.insert(name, value)?,
+                        Key::Next => array.push(value)?,
+                    }
+                }
+                zval.set_hashtable(array);
+            }
+            Value::Object(properties) => {
+                let mut object = ZendObject::new_stdclass();
+                for (name, value) in properties {
+                    object.set_property(name, value.build(depth + 1)?)?;
+                }
+                zval = object.into_zval(false)?;
+            }
+            Value::Reference(value) => {
+                zval = value.build(depth + 1)?;
+                unsafe { ext_php_rs_zval_make_ref(&mut zval) };
+            }
+        }
+        Ok(zval)
+    }
+}
+
+/// Extracts Rust values from a PHP value with the conversions of the crate,
+/// recursively, and checks that they convert back to the same PHP values.
+/// Must be called inside a request.
+///
+/// # Panics
+///
+/// Panics if a conversion is not faithful, or if one of the conversions
+/// panics.
+pub fn check_conversions(zval: &Zval) {
+    let _ = format!("{zval:?}");
+    let zval = zval.dereference();
+
+    if let Some(value) = i64::from_zval(zval) {
+        assert_eq!(roundtrip(value).long(), Some(value));
+    }
+    if let Some(value) = f64::from_zval(zval) {
+        let converted = roundtrip(value).double().map(f64::to_bits);
+        assert_eq!(converted, Some(value.to_bits()));
+    }
+    if let Some(value) = bool::from_zval(zval) {
+        assert_eq!(roundtrip(value).bool(), Some(value));
+    }
+    if let Some(value) = String::from_zval(zval) {
+        assert_eq!(roundtrip(value.clone()).str(), Some(value.as_str()));
+    }
+    if let Some(bytes) = zval.binary::<u8>() {
+        let mut converted = Zval::new();
+        converted.set_binary(bytes.clone());
+        assert_eq!(converted.binary::<u8>(), Some(bytes));
+    }
+
+    if let Some(array) = zval.array() {
+        check_array(array);
+    }
+    if let Some(object) = zval.object() {
+        if let Ok(properties) = object.get_properties() {
+            check_array(properties);
+        }
+    }
+}
+
+/// Checks the conversions of an array and of its values.
+fn check_array(array: &ZendHashTable) {
+    let mut len = 0;
+    for (key, value) in array.iter() {
+        len += 1;
+        match key {
+            ArrayKey::Long(index) => {
+                let index = u64::try_from(index).ok();
+                if let Some(index) = index {
+                    assert!(array.get_index(index).is_some());
+                }
+            }
+            ArrayKey::String(name) => {
+                let _ = array.get(&name);
+            }
+        }
+        check_conversions(value);
+    }
+    assert_eq!(array.len(), len);
+    assert_eq!(array.values().count(), len);
+
+    let copy = array.to_owned();
+    assert_eq!(copy.len(), len);
+
+    let mut zval = Zval::new();
+    zval.set_hashtable(copy);
+    if let Some(values) = Vec::<&Zval>::from_zval(&zval) {
+        assert_eq!(values.len(), len);
+    }
+    let _ = HashMap::<String, &Zval>::from_zval(&zval);
+    let _ = array.has_numerical_keys();
+    let _ = array.has_sequential_keys();
+}
+
+/// Converts a Rust value into a PHP value.
+fn roundtrip(value: impl IntoZval) -> Zval {
+    value
+        .into_zval(false)
+        .expect("should convert the value back to PHP")
+}
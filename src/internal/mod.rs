@@ -1,4 +1,6 @@
 //! Internal, public functions that are called from downstream extensions.
+use std::cell::Cell;
+
 use parking_lot::{const_mutex, Mutex};
 
 use crate::builders::ModuleStartup;
@@ -23,4 +25,74 @@ pub const MODULE_STARTUP_INIT: ModuleStartupMutex = const_mutex(None);
 pub fn ext_php_rs_startup() {
     #[cfg(feature = "closure")]
     crate::closure::Closure::build();
+    #[cfg(feature = "worker")]
+    crate::worker::Promise::build();
+}
+
+thread_local! {
+    /// The number of callers of [`start_request`] in the request of this
+    /// thread, which did not call [`finish_request`] yet.
+    static REQUEST_CALLERS: Cell<usize> = const { Cell::new(0) };
+    /// Whether the post deactivate functions of the modules must be called.
+    #[cfg(feature = "alloc_tracking")]
+    static POST_DEACTIVATE: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Called when a request starts, by the request startup function of modules
+/// built with [`ModuleBuilder`] and by [`Embed::run`], before any code of the
+/// request runs.
+///
+/// The hooks of the crate only run for the first caller of the request, as
+/// several modules may be loaded.
+///
+/// [`ModuleBuilder`]: crate::builders::ModuleBuilder
+/// [`Embed::run`]: crate::embed::Embed::run
+pub(crate) fn start_request() {
+    REQUEST_CALLERS.set(REQUEST_CALLERS.get() + 1);
+    if crate::zend::lifecycle::in_request() {
+        return;
+    }
+
+    #[cfg(feature = "tracing")]
+    crate::tracing::start_request();
+    #[cfg(feature = "otel")]
+    crate::otel::start_request();
+    crate::zend::interrupt::track_request();
+    crate::zend::lifecycle::request_startup();
+    crate::dispatcher::resume_pending();
+}
+
+/// Called when a request ends, by the request shutdown function of modules
+/// built with [`ModuleBuilder`] and by [`Embed::run`], in the reverse order of
+/// [`start_request`].
+///
+/// The hooks of the crate only run for the last caller of the request.
+///
+/// [`ModuleBuilder`]: crate::builders::ModuleBuilder
+/// [`Embed::run`]: crate::embed::Embed::run
+pub(crate) fn finish_request() {
+    let callers = REQUEST_CALLERS.get().saturating_sub(1);
+    REQUEST_CALLERS.set(callers);
+    if callers > 0 || !crate::zend::lifecycle::in_request() {
+        return;
+    }
+
+    #[cfg(feature = "worker")]
+    crate::worker::cancel_request();
+    crate::cancel::unbind_request();
+    crate::zend::lifecycle::request_shutdown();
+    crate::zend::interrupt::untrack_request();
+    #[cfg(feature = "otel")]
+    crate::otel::finish_request();
+    #[cfg(feature = "tracing")]
+    crate::tracing::finish_request();
+    #[cfg(feature = "alloc_tracking")]
+    POST_DEACTIVATE.set(true);
+}
+
+/// Returns whether the post deactivate functions of the modules must be
+/// called for the request which just ended, which they are only once.
+#[cfg(feature = "alloc_tracking")]
+pub(crate) fn take_post_deactivate() -> bool {
+    POST_DEACTIVATE.replace(false)
 }
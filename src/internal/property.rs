@@ -1,7 +1,13 @@
-use crate::{describe::DocComments, flags::PropertyFlags, props::Property};
+use crate::{
+    describe::DocComments,
+    flags::{DataType, PropertyFlags},
+    props::Property,
+};
 
 pub struct PropertyInfo<'a, T> {
     pub prop: Property<'a, T>,
     pub flags: PropertyFlags,
     pub docs: DocComments,
+    pub ty: Option<DataType>,
+    pub nullable: bool,
 }
@@ -4,18 +4,30 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 #![cfg_attr(docs, feature(doc_cfg))]
+#![cfg_attr(feature = "allocator_api", feature(allocator_api))]
 #![cfg_attr(windows, feature(abi_vectorcall))]
 
+pub mod access_log;
 pub mod alloc;
 pub mod args;
+#[cfg(any(docs, feature = "bench"))]
+#[cfg_attr(docs, doc(cfg(feature = "bench")))]
+pub mod bench;
 pub mod binary;
 pub mod binary_slice;
 pub mod builders;
+pub mod cancel;
 pub mod convert;
 pub mod error;
 pub mod exception;
 pub mod ffi;
 pub mod flags;
+#[cfg(any(docs, feature = "tokio", feature = "worker"))]
+#[cfg_attr(docs, doc(cfg(any(feature = "tokio", feature = "worker"))))]
+pub mod future;
+#[cfg(any(docs, feature = "arbitrary"))]
+#[cfg_attr(docs, doc(cfg(feature = "arbitrary")))]
+pub mod fuzz;
 #[macro_use]
 pub mod macros;
 pub mod boxed;
@@ -23,14 +35,40 @@ pub mod class;
 #[cfg(any(docs, feature = "closure"))]
 #[cfg_attr(docs, doc(cfg(feature = "closure")))]
 pub mod closure;
+pub mod codegen;
 pub mod constant;
 pub mod describe;
+pub mod dispatcher;
 pub mod embed;
+#[cfg(any(docs, feature = "log", feature = "tracing"))]
+#[cfg_attr(docs, doc(cfg(any(feature = "log", feature = "tracing"))))]
+pub mod error_log;
 #[doc(hidden)]
 pub mod internal;
+pub mod metrics;
+#[cfg(any(docs, feature = "otel"))]
+#[cfg_attr(docs, doc(cfg(feature = "otel")))]
+pub mod otel;
+#[cfg(any(docs, feature = "rayon"))]
+#[cfg_attr(docs, doc(cfg(feature = "rayon")))]
+pub mod parallel;
+pub mod profiler;
 pub mod props;
 pub mod rc;
+pub mod sapi;
+pub mod self_check;
+#[cfg(any(docs, feature = "session"))]
+#[cfg_attr(docs, doc(cfg(feature = "session")))]
+pub mod session;
+pub mod stream;
+#[cfg(any(docs, feature = "tracing"))]
+#[cfg_attr(docs, doc(cfg(feature = "tracing")))]
+pub mod tracing;
 pub mod types;
+pub mod watchdog;
+#[cfg(any(docs, feature = "worker"))]
+#[cfg_attr(docs, doc(cfg(feature = "worker")))]
+pub mod worker;
 pub mod zend;
 
 /// A module typically glob-imported containing the typically required macros
@@ -41,13 +79,14 @@ pub mod prelude {
     #[cfg(any(docs, feature = "closure"))]
     #[cfg_attr(docs, doc(cfg(feature = "closure")))]
     pub use crate::closure::Closure;
-    pub use crate::exception::{PhpException, PhpResult};
+    pub use crate::exception::{ExceptionClass, PhpException, PhpResult};
+    pub use crate::php_exception_class;
     pub use crate::php_print;
     pub use crate::php_println;
     pub use crate::types::ZendCallable;
     pub use crate::{
         php_class, php_const, php_extern, php_function, php_impl, php_module, wrap_constant,
-        wrap_function, zend_fastcall, ZvalConvert,
+        wrap_function, zend_fastcall, PhpError, ZvalConvert,
     };
 }
 
@@ -61,6 +100,6 @@ pub const PHP_DEBUG: bool = cfg!(php_debug);
 pub const PHP_ZTS: bool = cfg!(php_zts);
 
 pub use ext_php_rs_derive::{
-    php_class, php_const, php_extern, php_function, php_impl, php_module, wrap_constant,
-    wrap_function, zend_fastcall, ZvalConvert,
+    php_class, php_const, php_extern, php_function, php_impl, php_module, php_test, wrap_constant,
+    wrap_function, zend_fastcall, PhpError, ZvalConvert,
 };
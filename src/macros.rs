@@ -72,6 +72,34 @@ macro_rules! call_user_func {
     };
 }
 
+/// Returns a permanent interned [`ZendStr`] for a string literal, creating it
+/// on first use and caching the pointer for all later calls.
+///
+/// The string is created with [`ZendStr::new_permanent_interned`], so it
+/// should first be evaluated during module startup for it to be shared with
+/// PHP's interned string table. Subsequent evaluations only perform an atomic
+/// load, avoiding hashing and allocating the string on hot paths such as
+/// array keys and property names.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ext_php_rs::{interned, types::ZendStr};
+///
+/// let key: &'static ZendStr = interned!("key");
+/// assert_eq!(key.as_bytes(), b"key");
+/// ```
+///
+/// [`ZendStr`]: crate::types::ZendStr
+/// [`ZendStr::new_permanent_interned`]: crate::types::ZendStr::new_permanent_interned
+#[macro_export]
+macro_rules! interned {
+    ($str: literal) => {{
+        static INTERNED: $crate::types::InternedStr = $crate::types::InternedStr::new($str);
+        INTERNED.get()
+    }};
+}
+
 /// Parses a given list of arguments using the [`ArgParser`] class.
 ///
 /// # Examples
@@ -380,6 +408,65 @@ macro_rules! try_from_zval {
     };
 }
 
+/// Declares an exception class which can be registered with
+/// [`ModuleBuilder::exception_class`] and thrown from Rust.
+///
+/// The class extends `Exception` unless a parent class is given with
+/// `extends`, in the form of a function returning the parent [`ClassEntry`].
+/// The generated type implements [`ExceptionClass`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use ext_php_rs::{prelude::*, zend::ce};
+///
+/// php_exception_class! {
+///     /// Thrown when an operation times out.
+///     pub struct TimeoutException("App\\TimeoutException") extends ce::runtime_exception;
+/// }
+///
+/// #[php_function]
+/// pub fn wait() -> PhpResult<()> {
+///     Err(TimeoutException::exception("Timed out after 5 seconds"))
+/// }
+///
+/// #[php_module]
+/// pub fn get_module(module: ModuleBuilder) -> ModuleBuilder {
+///     module
+///         .exception_class::<TimeoutException>()
+///         .function(wrap_function!(wait))
+/// }
+/// # fn main() {}
+/// ```
+///
+/// [`ModuleBuilder::exception_class`]: crate::builders::ModuleBuilder::exception_class
+/// [`ClassEntry`]: crate::zend::ClassEntry
+/// [`ExceptionClass`]: crate::exception::ExceptionClass
+#[macro_export]
+macro_rules! php_exception_class {
+    ($(#[$attr: meta])* $vis: vis struct $ident: ident($name: expr);) => {
+        $(#[$attr])*
+        $vis struct $ident;
+
+        impl $crate::exception::ExceptionClass for $ident {
+            const CLASS_NAME: &'static str = $name;
+        }
+    };
+
+    ($(#[$attr: meta])* $vis: vis struct $ident: ident($name: expr) extends $parent: path;) => {
+        $(#[$attr])*
+        $vis struct $ident;
+
+        impl $crate::exception::ExceptionClass for $ident {
+            const CLASS_NAME: &'static str = $name;
+
+            fn parent() -> &'static $crate::zend::ClassEntry {
+                $parent()
+            }
+        }
+    };
+}
+
 /// Prints to the PHP standard output, without a newline.
 ///
 /// Acts exactly the same as the built-in [`print`] macro.
@@ -426,5 +513,91 @@ macro_rules! php_println {
     };
 }
 
+/// Asserts the `var_export` of a Rust value converted into PHP. Must be used
+/// inside a request, e.g. in a test with the [`macro@crate::php_test`]
+/// attribute.
+///
+/// # Panics
+///
+/// Panics if the value cannot be converted into PHP, or exports to another
+/// string.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::assert_var_export;
+///
+/// assert_var_export!(vec![1, 2], "array (\n  0 => 1,\n  1 => 2,\n)");
+/// assert_var_export!(Some("foo"), "'foo'");
+/// ```
+#[macro_export]
+macro_rules! assert_var_export {
+    ($value: expr, $expected: expr $(,)?) => {{
+        let zval = $crate::convert::IntoZval::into_zval($value, false)
+            .expect("should convert the value into PHP");
+        assert_eq!(
+            $crate::embed::var_export(&zval),
+            $expected,
+            "unexpected var_export of {}",
+            stringify!($value)
+        );
+    }};
+}
+
+/// Asserts that a Rust value goes through PHP and back. Must be used inside a
+/// request, e.g. in a test with the [`macro@crate::php_test`] attribute.
+///
+/// With two arguments, the value is converted into PHP, passed through a PHP
+/// function, compared with the expected `var_export` and converted back into
+/// a value equal to the original one.
+///
+/// With four arguments, a PHP expression is evaluated over the value, given as
+/// `$value`. Its result is compared with the expected `var_export`, and
+/// converted back into the type of the expected Rust value.
+///
+/// # Panics
+///
+/// Panics if a conversion fails, if the expression throws, or if the exported
+/// or converted values differ from the expected ones.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::assert_roundtrip;
+///
+/// assert_roundtrip!(String::from("foo"), "'foo'");
+/// assert_roundtrip!(
+///     vec![1, 2, 3],
+///     "array_reverse($value)",
+///     "array (\n  0 => 3,\n  1 => 2,\n  2 => 1,\n)",
+///     vec![3, 2, 1]
+/// );
+/// ```
+#[macro_export]
+macro_rules! assert_roundtrip {
+    ($value: expr, $expected_export: expr $(,)?) => {{
+        let value = $value;
+        let zval = $crate::embed::eval_with(::std::clone::Clone::clone(&value), "$value");
+        assert_eq!(
+            $crate::embed::var_export(&zval),
+            $expected_export,
+            "unexpected var_export of {}",
+            stringify!($value)
+        );
+        $crate::embed::assert_from_php(&zval, &value);
+    }};
+
+    ($value: expr, $expression: expr, $expected_export: expr, $expected: expr $(,)?) => {{
+        let zval = $crate::embed::eval_with($value, $expression);
+        assert_eq!(
+            $crate::embed::var_export(&zval),
+            $expected_export,
+            "unexpected var_export of `{}`",
+            $expression
+        );
+        $crate::embed::assert_from_php(&zval, &$expected);
+    }};
+}
+
 pub(crate) use into_zval;
 pub(crate) use try_from_zval;
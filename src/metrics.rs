@@ -0,0 +1,270 @@
+//! Sampling of the memory usage and of the status of the garbage collector and
+//! opcode cache, e.g. to export Prometheus metrics from SAPI hosts.
+//!
+//! A [`Sampler`] captures a [`Sample`] and passes it to its sink at the end of
+//! each request handled by a module built with [`ModuleBuilder`] or run with
+//! [`Embed::run`], and can also sample periodically while requests run. SAPI
+//! hosts which do not go through either can call [`start_request`] and
+//! [`finish_request`] themselves.
+//!
+//! Periodic samples are captured on the thread running the request, between
+//! two opcodes, through the interrupts of [`crate::zend::interrupt`]. A
+//! request blocked in a long running internal function, e.g. a database
+//! query, is only sampled once the function returns.
+//!
+//! [`ModuleBuilder`]: crate::builders::ModuleBuilder
+//! [`Embed::run`]: crate::embed::Embed::run
+
+use std::{
+    cell::RefCell,
+    thread,
+    time::{Duration, Instant},
+};
+
+use parking_lot::{const_rwlock, RwLock};
+
+use crate::{
+    alloc::MemoryStats,
+    zend::{
+        gc::GcStatus,
+        interrupt::{for_each_running, register_interrupt_handler},
+        lifecycle::RequestObserver,
+        opcache::OpcacheStatus,
+    },
+};
+
+type Sink = Box<dyn Fn(&Sample) + Send + Sync>;
+
+static SAMPLERS: RwLock<Vec<Sampler>> = const_rwlock(Vec::new());
+
+thread_local! {
+    /// The time of the last periodic sample of each sampler on this thread.
+    static LAST_SAMPLES: RefCell<Vec<Instant>> = const { RefCell::new(Vec::new()) };
+}
+
+/// What triggered a sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// The end of a request.
+    RequestEnd,
+    /// The interval of the sampler elapsed while a request was running.
+    Interval,
+}
+
+/// The metrics captured by a [`Sampler`].
+#[derive(Debug, Clone, Copy)]
+pub struct Sample {
+    /// What triggered the sample.
+    pub trigger: Trigger,
+    /// The memory usage of the Zend memory manager.
+    pub memory: MemoryStats,
+    /// The status of the garbage collector.
+    pub gc: GcStatus,
+    /// The status of the opcode cache, if requested with
+    /// [`Sampler::with_opcache`] and the cache is enabled.
+    pub opcache: Option<OpcacheStatus>,
+}
+
+impl Sample {
+    /// Captures the current metrics, including the status of the opcode
+    /// cache.
+    ///
+    /// # Parameters
+    ///
+    /// * `trigger` - What triggered the sample.
+    pub fn capture(trigger: Trigger) -> Self {
+        Self::capture_with(trigger, true)
+    }
+
+    fn capture_with(trigger: Trigger, opcache: bool) -> Self {
+        Self {
+            trigger,
+            memory: MemoryStats::capture(),
+            gc: GcStatus::capture(),
+            opcache: if opcache {
+                OpcacheStatus::capture()
+            } else {
+                None
+            },
+        }
+    }
+}
+
+/// Captures samples of the engine metrics and passes them to a sink.
+///
+/// By default, a sample is captured at the end of each request.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::{
+///     sync::atomic::{AtomicUsize, Ordering},
+///     time::Duration,
+/// };
+///
+/// use ext_php_rs::metrics::{Sampler, Trigger};
+///
+/// static PEAK_MEMORY: AtomicUsize = AtomicUsize::new(0);
+///
+/// Sampler::new(|sample| {
+///     if sample.trigger == Trigger::RequestEnd {
+///         PEAK_MEMORY.fetch_max(sample.memory.peak_usage, Ordering::Relaxed);
+///     }
+/// })
+/// .every(Duration::from_secs(1))
+/// .register();
+/// ```
+pub struct Sampler {
+    sink: Sink,
+    request_end: bool,
+    interval: Option<Duration>,
+    opcache: bool,
+}
+
+impl Sampler {
+    /// Creates a sampler which passes its samples to the given sink.
+    ///
+    /// # Parameters
+    ///
+    /// * `sink` - Receives the samples, on the thread running the request.
+    pub fn new<F>(sink: F) -> Self
+    where
+        F: Fn(&Sample) + Send + Sync + 'static,
+    {
+        Self {
+            sink: Box::new(sink),
+            request_end: true,
+            interval: None,
+            opcache: false,
+        }
+    }
+
+    /// Sets whether a sample is captured at the end of each request, which is
+    /// the default.
+    pub fn at_request_end(mut self, enabled: bool) -> Self {
+        self.request_end = enabled;
+        self
+    }
+
+    /// Captures a sample of each running request whenever the given interval
+    /// elapses.
+    pub fn every(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Sets whether the status of the opcode cache is captured, which is off
+    /// by default as `opcache_get_status()` allocates the whole status array.
+    pub fn with_opcache(mut self, enabled: bool) -> Self {
+        self.opcache = enabled;
+        self
+    }
+
+    /// Registers the sampler.
+    ///
+    /// This must be called during module startup, as periodic sampling
+    /// installs an interrupt handler. A thread is started for each periodic
+    /// sampler, which interrupts the running requests at each interval.
+    pub fn register(self) {
+        if SAMPLERS.read().is_empty() {
+            RequestObserver::new()
+                .on_startup(start_request)
+                .on_shutdown(finish_request)
+                .register();
+        }
+        if let Some(interval) = self.interval {
+            if !SAMPLERS
+                .read()
+                .iter()
+                .any(|sampler| sampler.interval.is_some())
+            {
+                register_interrupt_handler(|_| sample_interval());
+            }
+            thread::Builder::new()
+                .name("ext-php-rs-sampler".into())
+                .spawn(move || loop {
+                    thread::sleep(interval);
+                    for_each_running(|request| request.handle.interrupt());
+                })
+                .expect("should spawn the sampler thread");
+        }
+        SAMPLERS.write().push(self);
+    }
+}
+
+impl std::fmt::Debug for Sampler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sampler")
+            .field("request_end", &self.request_end)
+            .field("interval", &self.interval)
+            .field("opcache", &self.opcache)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Starts the interval of the periodic samplers for the request of this
+/// thread. This is called when a request starts.
+pub fn start_request() {
+    let samplers = SAMPLERS.read();
+    if !samplers.iter().any(|sampler| sampler.interval.is_some()) {
+        return;
+    }
+
+    let now = Instant::now();
+    LAST_SAMPLES.with(|last| *last.borrow_mut() = vec![now; samplers.len()]);
+}
+
+/// Captures the final sample of the request of this thread. This is called
+/// when a request ends.
+pub fn finish_request() {
+    let samplers = SAMPLERS.read_recursive();
+    for sampler in samplers.iter().filter(|sampler| sampler.request_end) {
+        (sampler.sink)(&Sample::capture_with(Trigger::RequestEnd, sampler.opcache));
+    }
+}
+
+/// Captures a sample for each periodic sampler whose interval elapsed on this
+/// thread.
+fn sample_interval() {
+    let now = Instant::now();
+    // Sinks may execute PHP code, which may be interrupted in turn.
+    let samplers = SAMPLERS.read_recursive();
+    for (index, sampler) in samplers.iter().enumerate() {
+        let Some(interval) = sampler.interval else {
+            continue;
+        };
+        let due = LAST_SAMPLES.with(|last| {
+            let mut last = last.borrow_mut();
+            match last.get_mut(index) {
+                Some(last) if now.duration_since(*last) >= interval => {
+                    *last = now;
+                    true
+                }
+                _ => false,
+            }
+        });
+        if due {
+            (sampler.sink)(&Sample::capture_with(Trigger::Interval, sampler.opcache));
+        }
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_sample() {
+        Embed::run(|| {
+            let sample = Sample::capture(Trigger::Interval);
+            assert_eq!(sample.trigger, Trigger::Interval);
+            assert!(sample.memory.usage > 0);
+            assert!(sample.gc.enabled);
+            assert!(sample
+                .opcache
+                .is_none_or(|opcache| opcache.used_memory > 0 || !opcache.enabled));
+        });
+    }
+}
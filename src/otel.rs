@@ -0,0 +1,294 @@
+//! Export of requests and function calls as [OpenTelemetry] spans.
+//!
+//! With the `otel` feature, a server span is started for each request handled
+//! by a module built with [`ModuleBuilder`] or run with [`Embed::run`], through
+//! the tracer provider installed with [`opentelemetry::global`]. The span
+//! continues the trace of the caller when the request carries the W3C
+//! `traceparent` and `tracestate` headers, and records the method, path and
+//! response code of the request, and its peak memory usage. SAPI hosts can
+//! start and end the span themselves with [`start_request`] and
+//! [`finish_request`].
+//!
+//! PHP function calls can also be exported as child spans with
+//! [`trace_calls`], which is built on the function call observers of
+//! [`crate::zend::observer`]. The trace is propagated to outgoing requests
+//! with the header returned by [`traceparent`].
+//!
+//! [OpenTelemetry]: https://opentelemetry.io
+//! [`ModuleBuilder`]: crate::builders::ModuleBuilder
+//! [`Embed::run`]: crate::embed::Embed::run
+
+use std::cell::RefCell;
+
+use opentelemetry::{
+    global,
+    trace::{
+        SpanContext, SpanId, SpanKind, Status, TraceContextExt, TraceFlags, TraceId, TraceState,
+        Tracer,
+    },
+    Context, ContextGuard, KeyValue,
+};
+
+use crate::{
+    alloc::memory_peak_usage,
+    zend::{
+        observer::{CallFrame, FcallObserver},
+        ProcessGlobals, SapiGlobals,
+    },
+};
+
+/// The name of the tracer spans are created with.
+const TRACER_NAME: &str = "ext-php-rs";
+
+thread_local! {
+    static REQUEST: RefCell<Option<ContextGuard>> = const { RefCell::new(None) };
+    static CALLS: RefCell<Vec<ContextGuard>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Parses a W3C `traceparent` header, along with the `tracestate` header if
+/// present, into the span context of the remote caller.
+///
+/// # Parameters
+///
+/// * `traceparent` - The value of the `traceparent` header, e.g.
+///   `00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01`.
+/// * `tracestate` - The value of the `tracestate` header.
+///
+/// # Returns
+///
+/// The span context, or [`None`] if the header is invalid.
+pub fn parse_traceparent(traceparent: &str, tracestate: Option<&str>) -> Option<SpanContext> {
+    let mut parts = traceparent.trim().split('-');
+    let version = parts.next()?;
+    let trace_id = parts.next()?;
+    let span_id = parts.next()?;
+    let flags = parts.next()?;
+    // Later versions may append fields, which must be ignored.
+    if version.len() != 2 || version == "ff" || (version == "00" && parts.next().is_some()) {
+        return None;
+    }
+    if trace_id.len() != 32 || span_id.len() != 16 || flags.len() != 2 {
+        return None;
+    }
+
+    let trace_id = TraceId::from_hex(trace_id).ok()?;
+    let span_id = SpanId::from_hex(span_id).ok()?;
+    let flags = u8::from_str_radix(flags, 16).ok()?;
+    if trace_id == TraceId::INVALID || span_id == SpanId::INVALID {
+        return None;
+    }
+
+    let state = tracestate
+        .and_then(|state| state.parse().ok())
+        .unwrap_or(TraceState::NONE);
+    Some(SpanContext::new(
+        trace_id,
+        span_id,
+        TraceFlags::new(flags) & TraceFlags::SAMPLED,
+        true,
+        state,
+    ))
+}
+
+/// Returns the `traceparent` header propagating the current span to an
+/// outgoing request, or [`None`] if there is no valid current span.
+pub fn traceparent() -> Option<String> {
+    let cx = Context::current();
+    let span = cx.span();
+    let span_context = span.span_context();
+    if !span_context.is_valid() {
+        return None;
+    }
+    Some(format!(
+        "00-{}-{}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags()
+    ))
+}
+
+/// Returns the span context of the caller of the current request, read from
+/// the `traceparent` and `tracestate` headers of the request.
+fn remote_parent() -> Option<SpanContext> {
+    let globals = ProcessGlobals::get();
+    let server = globals.http_server_vars()?;
+    let traceparent = server.get("HTTP_TRACEPARENT")?.str()?;
+    let tracestate = server.get("HTTP_TRACESTATE").and_then(|state| state.str());
+    parse_traceparent(traceparent, tracestate)
+}
+
+/// Starts the server span of the current request and makes it the current
+/// span, unless it is already started. This is called when a request starts,
+/// once the SAPI has filled in the request info.
+pub fn start_request() {
+    if REQUEST.with(|request| request.borrow().is_some()) {
+        return;
+    }
+
+    let parent = match remote_parent() {
+        Some(remote) => Context::current().with_remote_span_context(remote),
+        None => Context::current(),
+    };
+
+    let mut attributes = vec![];
+    {
+        let globals = SapiGlobals::get();
+        let info = globals.request_info();
+        if let Some(method) = info.request_method() {
+            attributes.push(KeyValue::new("http.request.method", method.to_string()));
+        }
+        if let Some(uri) = info.request_uri() {
+            attributes.push(KeyValue::new("url.path", uri.to_string()));
+        }
+        if let Some(query) = info.query_string() {
+            attributes.push(KeyValue::new("url.query", query.to_string()));
+        }
+    }
+
+    let tracer = global::tracer(TRACER_NAME);
+    let span = tracer
+        .span_builder("php.request")
+        .with_kind(SpanKind::Server)
+        .with_attributes(attributes)
+        .start_with_context(&tracer, &parent);
+    let guard = parent.with_span(span).attach();
+    REQUEST.with(|request| *request.borrow_mut() = Some(guard));
+}
+
+/// Records the response code and peak memory usage of the current request,
+/// and ends its span along with the spans of the calls which did not end,
+/// e.g. because of a bailout. This is called when a request ends.
+pub fn finish_request() {
+    while let Some(call) = CALLS.with(|calls| calls.borrow_mut().pop()) {
+        Context::current().span().end();
+        drop(call);
+    }
+
+    let Some(request) = REQUEST.with(|request| request.borrow_mut().take()) else {
+        return;
+    };
+    let status = SapiGlobals::get().sapi_headers().http_response_code;
+    let cx = Context::current();
+    let span = cx.span();
+    span.set_attribute(KeyValue::new(
+        "http.response.status_code",
+        i64::from(status),
+    ));
+    span.set_attribute(KeyValue::new(
+        "php.memory.peak_usage",
+        memory_peak_usage(false) as i64,
+    ));
+    if status >= 500 {
+        span.set_status(Status::error(format!("HTTP {status}")));
+    }
+    span.end();
+    drop(request);
+}
+
+/// Exports PHP function calls as spans, children of the span of the request
+/// or of the calling function.
+///
+/// Exporting every call is expensive, see [`trace_calls_with_filter`] to only
+/// export some functions. This must be called during module startup.
+pub fn trace_calls() {
+    trace_calls_with_filter(|_| true);
+}
+
+/// Exports the calls of the PHP functions accepted by the filter as spans.
+///
+/// This must be called during module startup.
+///
+/// # Parameters
+///
+/// * `filter` - Returns whether the function of the given call is exported.
+///   It must only depend on the function, see [`FcallObserver::filter`].
+pub fn trace_calls_with_filter<F>(filter: F)
+where
+    F: Fn(&CallFrame) -> bool + Send + Sync + 'static,
+{
+    FcallObserver::new()
+        .filter(filter)
+        .on_begin(|frame| {
+            let name = match (frame.class_name(), frame.function_name()) {
+                (Some(class), Some(function)) => format!("{class}::{function}"),
+                (None, Some(function)) => function.to_string(),
+                _ => "{main}".to_string(),
+            };
+            let mut attributes = vec![KeyValue::new("code.function.name", name.clone())];
+            if let Some(file) = frame.file() {
+                attributes.push(KeyValue::new("code.file.path", file.to_string()));
+            }
+            if let Some(line) = frame.line() {
+                attributes.push(KeyValue::new("code.line.number", i64::from(line)));
+            }
+
+            let tracer = global::tracer(TRACER_NAME);
+            let parent = Context::current();
+            let span = tracer
+                .span_builder(name)
+                .with_kind(SpanKind::Internal)
+                .with_attributes(attributes)
+                .start_with_context(&tracer, &parent);
+            let guard = parent.with_span(span).attach();
+            CALLS.with(|calls| calls.borrow_mut().push(guard));
+        })
+        .on_end(|_, _| {
+            if let Some(call) = CALLS.with(|calls| calls.borrow_mut().pop()) {
+                Context::current().span().end();
+                drop(call);
+            }
+        })
+        .register();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_traceparent() {
+        let context = parse_traceparent(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            Some("congo=t61rcWkgMzE"),
+        )
+        .expect("should parse traceparent");
+        assert_eq!(
+            context.trace_id(),
+            TraceId::from_hex("4bf92f3577b34da6a3ce929d0e0e4736").expect("should parse trace id")
+        );
+        assert_eq!(
+            context.span_id(),
+            SpanId::from_hex("00f067aa0ba902b7").expect("should parse span id")
+        );
+        assert!(context.is_sampled());
+        assert!(context.is_remote());
+        assert_eq!(context.trace_state().get("congo"), Some("t61rcWkgMzE"));
+
+        assert!(parse_traceparent(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00",
+            None
+        )
+        .is_some_and(|context| !context.is_sampled()));
+        assert!(parse_traceparent(
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+            None
+        )
+        .is_none());
+        assert!(parse_traceparent(
+            "ff-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            None
+        )
+        .is_none());
+        assert!(parse_traceparent(
+            "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-xx",
+            None
+        )
+        .is_none());
+        assert!(parse_traceparent(
+            "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01-xx",
+            None
+        )
+        .is_some());
+        assert!(parse_traceparent("garbage", None).is_none());
+    }
+}
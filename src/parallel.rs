@@ -0,0 +1,202 @@
+//! Parallel computations over PHP arrays.
+//!
+//! PHP values belong to the request which created them, and must not be
+//! touched by other threads. [`ParallelMap`] enforces the rules for using
+//! other threads from a request:
+//!
+//! 1. the array is copied into [`ZvalSnapshot`]s on the thread of the request,
+//! 2. the chunks of the copy are processed on the threads of a Rayon pool,
+//!    which only see the owned snapshots, and return values which are
+//!    [`Send`],
+//! 3. the results are converted back into a PHP array on the thread of the
+//!    request, once all the threads completed.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ext_php_rs::{parallel::ParallelMap, prelude::*, types::{ZendHashTable, ZvalSnapshot}};
+//! use ext_php_rs::boxed::ZBox;
+//!
+//! #[php_function]
+//! pub fn word_counts(texts: &ZendHashTable) -> PhpResult<ZBox<ZendHashTable>> {
+//!     Ok(ParallelMap::new().map(texts, |_, text| match text {
+//!         ZvalSnapshot::String(text) => text.split(u8::is_ascii_whitespace).count() as i64,
+//!         _ => 0,
+//!     })?)
+//! }
+//! ```
+
+use std::{
+    num::NonZeroUsize,
+    panic::{self, AssertUnwindSafe},
+    sync::Arc,
+};
+
+use rayon::{prelude::*, ThreadPool};
+
+use crate::{
+    boxed::ZBox,
+    convert::IntoZval,
+    error::{Error, Result},
+    types::{ArrayKey, ZendHashTable, ZvalSnapshot},
+};
+
+/// Maps the elements of PHP arrays on the threads of a Rayon pool.
+///
+/// The global Rayon pool is used unless a pool is set, and the array is split
+/// into chunks balanced by Rayon unless a chunk size is set.
+#[derive(Debug, Clone, Default)]
+pub struct ParallelMap {
+    pool: Option<Arc<ThreadPool>>,
+    chunk_size: Option<NonZeroUsize>,
+}
+
+impl ParallelMap {
+    /// Creates a map running on the global Rayon pool.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the pool whose threads process the chunks, e.g. a pool with a
+    /// limited number of threads shared by the functions of the extension.
+    ///
+    /// # Parameters
+    ///
+    /// * `pool` - The pool.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::sync::Arc;
+    ///
+    /// use ext_php_rs::parallel::ParallelMap;
+    ///
+    /// let pool = rayon::ThreadPoolBuilder::new()
+    ///     .num_threads(4)
+    ///     .build()
+    ///     .expect("should build the pool");
+    /// let map = ParallelMap::new().pool(Arc::new(pool));
+    /// ```
+    pub fn pool(mut self, pool: Arc<ThreadPool>) -> Self {
+        self.pool = Some(pool);
+        self
+    }
+
+    /// Sets the minimum number of elements processed by a thread at once.
+    /// Small chunks balance uneven work better.
+    ///
+    /// # Parameters
+    ///
+    /// * `size` - The number of elements, at least 1.
+    pub fn chunk_size(mut self, size: usize) -> Self {
+        self.chunk_size = NonZeroUsize::new(size);
+        self
+    }
+
+    /// Maps the elements of an array on several threads, and returns an array
+    /// of the results, with the keys of the elements.
+    ///
+    /// This must be called on the thread of a request, which is blocked until
+    /// every element was mapped.
+    ///
+    /// # Parameters
+    ///
+    /// * `array` - The array.
+    /// * `func` - The function, called on another thread with the key and a
+    ///   snapshot of each element.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an element could not be captured into a snapshot,
+    /// see [`ZvalSnapshot::capture`], [`Error::WorkerFailed`] if the function
+    /// panicked, or an error if a result could not be converted into a zval.
+    pub fn map<F, R>(&self, array: &ZendHashTable, func: F) -> Result<ZBox<ZendHashTable>>
+    where
+        F: Fn(&ArrayKey, ZvalSnapshot) -> R + Sync,
+        R: IntoZval + Send,
+    {
+        let elements = array
+            .iter()
+            .map(|(key, value)| Ok((key, ZvalSnapshot::capture(value)?)))
+            .collect::<Result<Vec<_>>>()?;
+        let results = self.run(elements, &func)?;
+
+        let mut mapped = ZendHashTable::with_capacity(results.len().try_into().unwrap_or(u32::MAX));
+        for (key, value) in results {
+            match key {
+                ArrayKey::Long(index) => mapped.insert_at_index(index as u64, value)?,
+                ArrayKey::String(key) => mapped.insert(&key, value)?,
+            }
+        }
+        Ok(mapped)
+    }
+
+    /// Maps the snapshots of the elements on the pool, keeping their order.
+    fn run<F, R>(
+        &self,
+        elements: Vec<(ArrayKey, ZvalSnapshot)>,
+        func: &F,
+    ) -> Result<Vec<(ArrayKey, R)>>
+    where
+        F: Fn(&ArrayKey, ZvalSnapshot) -> R + Sync,
+        R: Send,
+    {
+        let min_len = self.chunk_size.map_or(1, NonZeroUsize::get);
+        let map = || {
+            elements
+                .into_par_iter()
+                .with_min_len(min_len)
+                .map(|(key, value)| {
+                    let result = func(&key, value);
+                    (key, result)
+                })
+                .collect()
+        };
+
+        // Rayon resumes the panic of a thread on the calling thread, where it
+        // must not unwind into the engine.
+        panic::catch_unwind(AssertUnwindSafe(|| match &self.pool {
+            Some(pool) => pool.install(map),
+            None => map(),
+        }))
+        .map_err(|_| Error::WorkerFailed)
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_map() {
+        Embed::run(|| {
+            let value = Embed::eval("array_merge(range(1, 100), ['name' => 'php'])")
+                .expect("should evaluate the array");
+            let array = value.array().expect("should be an array");
+
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(4)
+                .build()
+                .expect("should build the pool");
+            let mapped = ParallelMap::new()
+                .pool(Arc::new(pool))
+                .chunk_size(7)
+                .map(array, |_, value| match value {
+                    ZvalSnapshot::Long(n) => n * 2,
+                    _ => -1,
+                })
+                .expect("should map the array");
+            assert_eq!(mapped.len(), 101);
+            let values: Vec<_> = mapped.iter().filter_map(|(_, v)| v.long()).collect();
+            assert_eq!(values[..3], [2, 4, 6]);
+            assert_eq!(mapped.get("name").and_then(|v| v.long()), Some(-1));
+
+            let failed = ParallelMap::new().map(array, |key, _| {
+                assert!(key != &ArrayKey::Long(50), "should fail");
+            });
+            assert!(matches!(failed, Err(Error::WorkerFailed)));
+        });
+    }
+}
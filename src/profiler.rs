@@ -0,0 +1,252 @@
+//! A sampling profiler of PHP code, producing folded stacks for flame graphs.
+//!
+//! While a [`Profiler`] is running, a background thread interrupts the
+//! running requests at each interval, and the PHP backtrace of each request
+//! is captured on its own thread, between two opcodes, through the interrupts
+//! of [`crate::zend::interrupt`], so the stack is walked while it cannot
+//! change. Identical stacks are aggregated into [folded stacks], which
+//! `flamegraph.pl`, `inferno` or speedscope turn into flame graphs.
+//!
+//! Requests are sampled when they are handled by a module built with
+//! [`ModuleBuilder`] or run with [`Embed::run`], so the profiler can be used
+//! by extensions and SAPI hosts alike. Time spent in a long running internal
+//! function, e.g. a database query, is attributed to the code running once
+//! the function returns.
+//!
+//! [folded stacks]: https://github.com/brendangregg/FlameGraph#2-fold-stacks
+//! [`ModuleBuilder`]: crate::builders::ModuleBuilder
+//! [`Embed::run`]: crate::embed::Embed::run
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write as _,
+    io::{self, Write},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, ThreadId},
+    time::Duration,
+};
+
+use parking_lot::{const_rwlock, Mutex, RwLock};
+
+use crate::zend::{
+    backtrace,
+    interrupt::{for_each_running, register_interrupt_handler},
+    ExecuteData,
+};
+
+static PROFILERS: RwLock<Vec<Arc<State>>> = const_rwlock(Vec::new());
+
+#[derive(Debug, Default)]
+struct State {
+    interval: Duration,
+    running: AtomicBool,
+    /// Incremented on each start, so threads of previous runs stop.
+    generation: AtomicU64,
+    /// The threads interrupted by the profiler which were not sampled yet.
+    pending: Mutex<HashSet<ThreadId>>,
+    /// The number of samples of each folded stack.
+    stacks: Mutex<HashMap<String, u64>>,
+    samples: AtomicU64,
+}
+
+/// A sampling profiler, capturing the PHP backtrace of the running requests
+/// at a fixed interval.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::{fs::File, time::Duration};
+///
+/// use ext_php_rs::profiler::Profiler;
+///
+/// // During module startup:
+/// let profiler = Profiler::new(Duration::from_millis(10));
+///
+/// // Later on, e.g. from a management endpoint:
+/// profiler.start();
+/// // ...
+/// profiler.stop();
+/// let mut file = File::create("php.folded").expect("should create file");
+/// profiler.write_folded(&mut file).expect("should write stacks");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    state: Arc<State>,
+}
+
+impl Profiler {
+    /// Creates a stopped profiler sampling the running requests at the given
+    /// interval.
+    ///
+    /// This must be called during module startup, as the profiler installs an
+    /// interrupt handler.
+    ///
+    /// # Parameters
+    ///
+    /// * `interval` - The interval between two samples of a request.
+    pub fn new(interval: Duration) -> Self {
+        let state = Arc::new(State {
+            interval,
+            ..Default::default()
+        });
+
+        let mut profilers = PROFILERS.write();
+        if profilers.is_empty() {
+            register_interrupt_handler(sample);
+        }
+        profilers.push(state.clone());
+        Self { state }
+    }
+
+    /// Starts sampling, unless the profiler is already running. A thread is
+    /// started, which interrupts the running requests until the profiler is
+    /// stopped.
+    pub fn start(&self) {
+        if self.state.running.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        let state = self.state.clone();
+        let generation = state.generation.fetch_add(1, Ordering::AcqRel) + 1;
+        thread::Builder::new()
+            .name("ext-php-rs-profiler".into())
+            .spawn(move || {
+                while state.running.load(Ordering::Acquire)
+                    && state.generation.load(Ordering::Acquire) == generation
+                {
+                    thread::sleep(state.interval);
+                    let mut pending = state.pending.lock();
+                    for_each_running(|request| {
+                        pending.insert(request.thread);
+                        request.handle.interrupt();
+                    });
+                }
+            })
+            .expect("should spawn the profiler thread");
+    }
+
+    /// Stops sampling. The samples captured so far are kept.
+    pub fn stop(&self) {
+        self.state.running.store(false, Ordering::Release);
+    }
+
+    /// Returns whether the profiler is running.
+    pub fn is_running(&self) -> bool {
+        self.state.running.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of samples captured.
+    pub fn samples(&self) -> u64 {
+        self.state.samples.load(Ordering::Relaxed)
+    }
+
+    /// Discards the samples captured so far.
+    pub fn reset(&self) {
+        self.state.stacks.lock().clear();
+        self.state.samples.store(0, Ordering::Relaxed);
+    }
+
+    /// Returns the samples as folded stacks, one stack per line, with the
+    /// frames from the outermost to the innermost separated by semicolons,
+    /// followed by the number of samples, e.g. `{main};App\Foo::bar;strlen 3`.
+    /// Stacks are sorted, so the output of two profiles can be compared.
+    pub fn folded(&self) -> String {
+        let mut stacks: Vec<_> = self
+            .state
+            .stacks
+            .lock()
+            .iter()
+            .map(|(stack, count)| (stack.clone(), *count))
+            .collect();
+        stacks.sort_unstable();
+
+        let mut folded = String::new();
+        for (stack, count) in stacks {
+            let _ = writeln!(folded, "{stack} {count}");
+        }
+        folded
+    }
+
+    /// Writes the samples as folded stacks, see [`Profiler::folded`].
+    ///
+    /// # Parameters
+    ///
+    /// * `writer` - The writer to write the stacks to.
+    pub fn write_folded<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(self.folded().as_bytes())
+    }
+}
+
+/// Folds a backtrace, innermost frame first, into a single line.
+fn fold(frames: &[backtrace::StackFrame]) -> String {
+    frames
+        .iter()
+        .rev()
+        .map(|frame| frame.name().replace(';', ":"))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Captures a sample for each profiler which interrupted this thread.
+fn sample(frame: &ExecuteData) {
+    let id = thread::current().id();
+    let mut stack = None;
+    let profilers = PROFILERS.read_recursive();
+    for profiler in profilers.iter() {
+        if !profiler.pending.lock().remove(&id) {
+            continue;
+        }
+
+        let stack = stack.get_or_insert_with(|| fold(&backtrace::capture_from(frame)));
+        if stack.is_empty() {
+            continue;
+        }
+        *profiler.stacks.lock().entry(stack.clone()).or_default() += 1;
+        profiler.samples.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zend::backtrace::StackFrame;
+
+    fn frame(class: Option<&str>, function: Option<&str>) -> StackFrame {
+        StackFrame {
+            function: function.map(ToString::to_string),
+            class: class.map(ToString::to_string),
+            file: None,
+            line: None,
+        }
+    }
+
+    #[test]
+    fn test_fold() {
+        let frames = [
+            frame(None, Some("strlen")),
+            frame(Some("App\\Foo"), Some("bar")),
+            frame(None, None),
+        ];
+        assert_eq!(fold(&frames), "{main};App\\Foo::bar;strlen");
+    }
+
+    #[test]
+    fn test_folded() {
+        let profiler = Profiler::new(Duration::from_millis(10));
+        assert!(!profiler.is_running());
+        {
+            let mut stacks = profiler.state.stacks.lock();
+            stacks.insert("{main};foo".into(), 2);
+            stacks.insert("{main};bar".into(), 1);
+        }
+        assert_eq!(profiler.folded(), "{main};bar 1\n{main};foo 2\n");
+
+        profiler.reset();
+        assert_eq!(profiler.folded(), "");
+        assert_eq!(profiler.samples(), 0);
+    }
+}
@@ -0,0 +1,146 @@
+//! Control of the response of the current request from Rust, for the hosts
+//! of an embedded SAPI and for extension functions, without going through
+//! the userland `header()` and `http_response_code()` functions.
+//!
+//! The functions can be called while a request is active and until its
+//! headers are sent. The lower level operations, e.g. [`header_op`], are in
+//! [`crate::zend::headers`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ext_php_rs::sapi;
+//!
+//! sapi::set_status(201).unwrap();
+//! sapi::set_header("Location", "/items/42").unwrap();
+//! sapi::append_header("Set-Cookie", "session=abc").unwrap();
+//! ```
+
+pub use crate::zend::headers::{header_op, headers, headers_sent, remove_header, HeaderOp};
+use crate::{
+    error::{Error, Result},
+    zend::SapiGlobals,
+};
+
+/// Sets a header of the response, replacing any existing header with the same
+/// name, like `header("$name: $value")`.
+///
+/// Special headers are handled like PHP does, e.g. `Location` sets the status
+/// code to 302, and `Content-Type` sets the MIME type of the response.
+///
+/// # Parameters
+///
+/// * `name` - The name of the header, e.g. `Cache-Control`.
+/// * `value` - The value of the header.
+///
+/// # Returns
+///
+/// Nothing on success, [`Error::SapiHeaderFailure`] if the name is not a
+/// valid header name, the value contains a new line, or the headers have
+/// already been sent.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::sapi;
+///
+/// sapi::set_header("Cache-Control", "no-store").unwrap();
+/// sapi::set_status(503).unwrap();
+/// ```
+pub fn set_header(name: &str, value: &str) -> Result<()> {
+    header_op(HeaderOp::Replace, &header_line(name, value)?, 0)
+}
+
+/// Adds a header to the response, keeping any existing header with the same
+/// name, like `header("$name: $value", false)`, e.g. for `Set-Cookie`.
+///
+/// # Parameters
+///
+/// * `name` - The name of the header.
+/// * `value` - The value of the header.
+///
+/// # Returns
+///
+/// Nothing on success, [`Error::SapiHeaderFailure`] if the name is not a
+/// valid header name, the value contains a new line, or the headers have
+/// already been sent.
+pub fn append_header(name: &str, value: &str) -> Result<()> {
+    header_op(HeaderOp::Add, &header_line(name, value)?, 0)
+}
+
+/// Removes all headers from the response, equivalent to calling the userland
+/// `header_remove()` function without arguments.
+pub fn remove_all_headers() -> Result<()> {
+    header_op(HeaderOp::DeleteAll, "", 0)
+}
+
+/// Sets the HTTP status code of the response, equivalent to the userland
+/// `http_response_code()` function.
+///
+/// # Parameters
+///
+/// * `code` - The status code, from 100 to 999.
+///
+/// # Returns
+///
+/// Nothing on success, [`Error::SapiHeaderFailure`] if the status code is not
+/// valid, or the headers have already been sent.
+pub fn set_status(code: u16) -> Result<()> {
+    if !(100..1000).contains(&code) {
+        return Err(Error::SapiHeaderFailure);
+    }
+    header_op(HeaderOp::SetStatus, "", code.into())
+}
+
+/// Returns the HTTP status code of the response, or [`None`] if it was not
+/// set yet.
+pub fn status() -> Option<u16> {
+    SapiGlobals::get().sapi_headers().status_code()
+}
+
+/// Formats a header line, checking the name of the header, as PHP only
+/// checks the line for new lines.
+fn header_line(name: &str, value: &str) -> Result<String> {
+    let valid = !name.is_empty()
+        && name
+            .bytes()
+            .all(|byte| byte.is_ascii_graphic() && byte != b':');
+    if !valid {
+        return Err(Error::SapiHeaderFailure);
+    }
+    Ok(format!("{name}: {value}"))
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_set_header() {
+        Embed::run(|| {
+            assert!(set_header("X-Foo", "bar").is_ok());
+            assert!(set_header("X-Foo", "baz").is_ok());
+            assert!(append_header("Set-Cookie", "a=1").is_ok());
+            assert!(append_header("Set-Cookie", "b=2").is_ok());
+            assert!(set_header("X Foo", "bar").is_err());
+            assert!(set_header("X-Foo", "bar\r\nX-Injected: 1").is_err());
+
+            let list = headers();
+            assert!(list.contains(&"X-Foo: baz".to_string()));
+            assert!(!list.contains(&"X-Foo: bar".to_string()));
+            assert_eq!(
+                list.iter().filter(|h| h.starts_with("Set-Cookie")).count(),
+                2
+            );
+
+            assert!(set_status(418).is_ok());
+            assert_eq!(status(), Some(418));
+            assert!(set_status(42).is_err());
+
+            assert!(remove_all_headers().is_ok());
+            assert!(!headers().iter().any(|h| h.starts_with("X-Foo")));
+        });
+    }
+}
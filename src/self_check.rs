@@ -0,0 +1,508 @@
+//! Runtime self-check of an extension against the PHP it is loaded into.
+//!
+//! Loading an extension into another build of PHP, or into a binary which
+//! does not export the engine, usually ends with a crash far from the cause.
+//! A [`Report`] checks the causes seen in practice once the extension is
+//! loaded:
+//!
+//! * the module API number, thread-safety and debug mode of the engine,
+//! * the engine functions called by the crate, which are resolved lazily
+//!   and would only be found missing when first called,
+//! * the functions and classes registered by the module, with the parents and
+//!   interfaces of the classes.
+//!
+//! The report is exposed to PHP by adding the function returned by
+//! [`function`] to the module, which `cargo php check` calls to check an
+//! extension against the installed PHP.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ext_php_rs::{prelude::*, self_check};
+//!
+//! #[php_module]
+//! pub fn get_module(module: ModuleBuilder) -> ModuleBuilder {
+//!     module.function(self_check::function("my_extension_self_check"))
+//! }
+//! ```
+
+use std::{
+    ffi::CStr,
+    fmt::{self, Display},
+};
+
+use parking_lot::{const_mutex, Mutex};
+
+use crate::{
+    boxed::ZBox,
+    builders::{ClassBuilder, FunctionBuilder},
+    flags::{DataType, FunctionType},
+    types::{ZendHashTable, Zval},
+    zend::{BuildInfo, ClassEntry, ExecuteData, ExecutorGlobals, Function},
+};
+
+/// The functions and classes registered by the module.
+static REGISTERED: Mutex<Registered> = const_mutex(Registered::new());
+
+/// Engine functions called by the crate, which exist in every supported
+/// version of PHP.
+const ENGINE_SYMBOLS: &[&CStr] = &[
+    c"_emalloc",
+    c"_efree",
+    c"php_printf",
+    c"php_error_docref",
+    c"zend_register_internal_class_ex",
+    c"zend_register_bool_constant",
+    c"zend_register_string_constant",
+    c"zend_register_ini_entries",
+    c"zend_lookup_class_ex",
+    c"zend_fetch_function_str",
+    c"zend_call_known_function",
+    c"zend_hash_str_find",
+    c"zend_hash_index_find",
+    c"zend_hash_get_current_data_ex",
+    c"zend_object_std_init",
+    c"zend_object_std_dtor",
+    c"zend_objects_clone_members",
+    c"zend_std_read_property",
+    c"zend_std_write_property",
+    c"zend_std_get_properties",
+    c"zend_update_property",
+    c"zend_throw_exception_ex",
+    c"zend_throw_exception_object",
+    c"zend_wrong_parameters_count_error",
+    c"zend_is_true",
+];
+
+#[derive(Debug)]
+struct Registered {
+    functions: Vec<String>,
+    classes: Vec<RegisteredClass>,
+}
+
+impl Registered {
+    const fn new() -> Self {
+        Self {
+            functions: Vec::new(),
+            classes: Vec::new(),
+        }
+    }
+}
+
+/// A class registered by the module, as declared by its builder.
+#[derive(Debug)]
+pub(crate) struct RegisteredClass {
+    name: String,
+    extends: Option<String>,
+    interfaces: Vec<String>,
+    interface: bool,
+}
+
+impl From<&ClassBuilder> for RegisteredClass {
+    fn from(builder: &ClassBuilder) -> Self {
+        let name = |ce: &ClassEntry| ce.name().unwrap_or_default().to_string();
+        Self {
+            name: builder.name.clone(),
+            extends: builder.extends.map(name),
+            interfaces: builder.interfaces.iter().copied().map(name).collect(),
+            interface: builder.ce.is_interface(),
+        }
+    }
+}
+
+/// Records the functions registered by the module, once built.
+pub(crate) fn register_functions(functions: Vec<String>) {
+    REGISTERED.lock().functions = functions;
+}
+
+/// Records the classes registered by the module, on startup.
+pub(crate) fn register_classes(classes: Vec<RegisteredClass>) {
+    REGISTERED.lock().classes = classes;
+}
+
+/// The outcome of a check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    /// Nothing wrong was found.
+    Passed,
+    /// The check could not be done.
+    Warning,
+    /// The extension does not match the engine.
+    Failed,
+}
+
+impl Status {
+    /// Returns the name of the status, e.g. `passed`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Status::Passed => "passed",
+            Status::Warning => "warning",
+            Status::Failed => "failed",
+        }
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A check of a [`Report`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Check {
+    /// The name of the check, e.g. `api` or `classes`.
+    pub name: &'static str,
+    /// The outcome of the check.
+    pub status: Status,
+    /// What was checked, or what is wrong.
+    pub message: String,
+}
+
+impl Check {
+    fn new(name: &'static str, status: Status, message: impl Into<String>) -> Self {
+        Self {
+            name,
+            status,
+            message: message.into(),
+        }
+    }
+
+    /// Creates a check failing with the given problems, or passing with the
+    /// given message if there are none.
+    fn from_problems(name: &'static str, problems: Vec<String>, passed: String) -> Self {
+        if problems.is_empty() {
+            Self::new(name, Status::Passed, passed)
+        } else {
+            Self::new(name, Status::Failed, problems.join("; "))
+        }
+    }
+}
+
+impl Display for Check {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}: {}", self.status, self.name, self.message)
+    }
+}
+
+/// The diagnosis of the extension against the running engine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// The build of PHP the extension is compiled for.
+    pub extension: BuildInfo,
+    /// The build of the running engine, or [`None`] if it is not started.
+    pub engine: Option<BuildInfo>,
+    /// The checks, in the order they were run.
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Checks the extension against the running engine. Functions and classes
+    /// are checked once the module is started.
+    pub fn run() -> Self {
+        let extension = BuildInfo::COMPILED;
+        let engine = BuildInfo::running();
+        let mut checks = vec![];
+
+        match engine {
+            Some(engine) => {
+                checks.push(compare("api", extension.api, engine.api, |api| {
+                    format!("module API {api}")
+                }));
+                checks.push(compare("zts", extension.zts, engine.zts, |zts| {
+                    format!("thread-safety {}", on_off(zts))
+                }));
+                checks.push(compare("debug", extension.debug, engine.debug, |debug| {
+                    format!("debug mode {}", on_off(debug))
+                }));
+            }
+            None => checks.push(Check::new("engine", Status::Warning, "PHP is not started")),
+        }
+        checks.push(check_symbols());
+
+        if ExecutorGlobals::get().class_table().is_some() {
+            let registered = REGISTERED.lock();
+            checks.push(check_functions(&registered.functions));
+            checks.push(check_classes(&registered.classes));
+        }
+
+        Self {
+            extension,
+            engine,
+            checks,
+        }
+    }
+
+    /// Returns whether none of the checks failed.
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(|c| c.status != Status::Failed)
+    }
+
+    /// Returns the failed checks.
+    pub fn failures(&self) -> impl Iterator<Item = &Check> {
+        self.checks.iter().filter(|c| c.status == Status::Failed)
+    }
+
+    /// Returns the report as a PHP array, with the keys `ok`, `extension`,
+    /// `engine` and `checks`, a list of arrays with the keys `name`, `status`
+    /// and `message`.
+    pub fn to_array(&self) -> crate::error::Result<ZBox<ZendHashTable>> {
+        let mut checks = ZendHashTable::new();
+        for check in &self.checks {
+            let mut entry = ZendHashTable::new();
+            entry.insert("name", check.name)?;
+            entry.insert("status", check.status.as_str())?;
+            entry.insert("message", check.message.as_str())?;
+            checks.push(entry)?;
+        }
+
+        let mut report = ZendHashTable::new();
+        report.insert("ok", self.is_ok())?;
+        report.insert("extension", self.extension.to_string())?;
+        report.insert("engine", self.engine.map(|engine| engine.to_string()))?;
+        report.insert("checks", checks)?;
+        Ok(report)
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Extension built for {}", self.extension)?;
+        if let Some(engine) = &self.engine {
+            write!(f, ", loaded into {engine}")?;
+        }
+        writeln!(f, ".")?;
+        for check in &self.checks {
+            writeln!(f, "{check}")?;
+        }
+        Ok(())
+    }
+}
+
+fn on_off(enabled: bool) -> &'static str {
+    if enabled {
+        "enabled"
+    } else {
+        "disabled"
+    }
+}
+
+/// Compares a setting of the extension with the one of the engine.
+fn compare<T: PartialEq + Copy>(
+    name: &'static str,
+    extension: T,
+    engine: T,
+    describe: impl Fn(T) -> String,
+) -> Check {
+    if extension == engine {
+        Check::new(name, Status::Passed, describe(engine))
+    } else {
+        Check::new(
+            name,
+            Status::Failed,
+            format!(
+                "extension built with {}, engine built with {}",
+                describe(extension),
+                describe(engine)
+            ),
+        )
+    }
+}
+
+/// Checks that the engine functions called by the crate can be resolved.
+fn check_symbols() -> Check {
+    if cfg!(feature = "static-embed") {
+        return Check::new("symbols", Status::Passed, "PHP is linked statically");
+    }
+    match missing_symbols() {
+        Some(missing) => Check::from_problems(
+            "symbols",
+            missing
+                .into_iter()
+                .map(|symbol| format!("{symbol} is not exported by the engine"))
+                .collect(),
+            format!("{} engine functions found", ENGINE_SYMBOLS.len()),
+        ),
+        None => Check::new(
+            "symbols",
+            Status::Warning,
+            "symbols cannot be checked on this platform",
+        ),
+    }
+}
+
+/// Returns the engine functions which cannot be resolved from the global
+/// symbols of the process.
+#[cfg(unix)]
+fn missing_symbols() -> Option<Vec<&'static str>> {
+    use std::ffi::{c_char, c_void};
+
+    extern "C" {
+        fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    }
+
+    /// `RTLD_DEFAULT`, which is a macro.
+    #[cfg(target_os = "macos")]
+    const RTLD_DEFAULT: *mut c_void = -2isize as *mut c_void;
+    #[cfg(not(target_os = "macos"))]
+    const RTLD_DEFAULT: *mut c_void = std::ptr::null_mut();
+
+    Some(
+        ENGINE_SYMBOLS
+            .iter()
+            .filter(|symbol| unsafe { dlsym(RTLD_DEFAULT, symbol.as_ptr()) }.is_null())
+            .map(|symbol| symbol.to_str().unwrap_or_default())
+            .collect(),
+    )
+}
+
+#[cfg(not(unix))]
+fn missing_symbols() -> Option<Vec<&'static str>> {
+    None
+}
+
+/// Checks that the functions of the module are registered as internal
+/// functions.
+fn check_functions(functions: &[String]) -> Check {
+    let problems = functions
+        .iter()
+        .filter_map(|name| match Function::try_from_function(name) {
+            None => Some(format!("function {name} is not registered")),
+            Some(function) if function.function_type() != FunctionType::Internal => {
+                Some(format!("function {name} is declared by a script"))
+            }
+            Some(_) => None,
+        })
+        .collect();
+    Check::from_problems(
+        "functions",
+        problems,
+        format!("{} functions registered", functions.len()),
+    )
+}
+
+/// Checks that the classes of the module are registered with their parents
+/// and interfaces.
+fn check_classes(classes: &[RegisteredClass]) -> Check {
+    let mut problems = vec![];
+    for class in classes {
+        let Some(ce) = ClassEntry::try_find(&class.name) else {
+            problems.push(format!("class {} is not registered", class.name));
+            continue;
+        };
+        if ce.is_interface() != class.interface {
+            problems.push(format!(
+                "class {} is {}an interface",
+                class.name,
+                if ce.is_interface() { "" } else { "not " }
+            ));
+        }
+
+        let parent = ce.parent().and_then(|parent| parent.name());
+        let same_parent = match (&class.extends, parent) {
+            (Some(expected), Some(parent)) => expected.eq_ignore_ascii_case(parent),
+            (None, None) => true,
+            _ => false,
+        };
+        if !same_parent {
+            problems.push(format!(
+                "class {} extends {}, expected {}",
+                class.name,
+                parent.unwrap_or("nothing"),
+                class.extends.as_deref().unwrap_or("nothing")
+            ));
+        }
+
+        for interface in &class.interfaces {
+            let implemented =
+                ClassEntry::try_find(interface).is_some_and(|interface| ce.instance_of(interface));
+            if !implemented {
+                problems.push(format!(
+                    "class {} does not implement {interface}",
+                    class.name
+                ));
+            }
+        }
+    }
+    Check::from_problems(
+        "classes",
+        problems,
+        format!("{} classes registered", classes.len()),
+    )
+}
+
+/// Returns a function returning the [`Report`] of the extension as an array,
+/// to be added to the module with
+/// [`ModuleBuilder::function`](crate::builders::ModuleBuilder::function).
+///
+/// `cargo php check` calls the function named `<crate>_self_check` by
+/// default, e.g. `my_extension_self_check` for the crate `my-extension`.
+///
+/// # Parameters
+///
+/// * `name` - The name of the function.
+pub fn function(name: &str) -> FunctionBuilder<'static> {
+    FunctionBuilder::new(name, self_check).returns(DataType::Array, false, false)
+}
+
+/// Sets the report as the return value.
+fn return_report(retval: &mut Zval) {
+    match Report::run().to_array() {
+        Ok(report) => retval.set_hashtable(report),
+        Err(_) => retval.set_null(),
+    }
+}
+
+#[cfg(not(windows))]
+extern "C" fn self_check(_: &mut ExecuteData, retval: &mut Zval) {
+    return_report(retval);
+}
+
+#[cfg(windows)]
+extern "vectorcall" fn self_check(_: &mut ExecuteData, retval: &mut Zval) {
+    return_report(retval);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_display() {
+        let built = BuildInfo {
+            api: 20230831,
+            zts: false,
+            debug: false,
+        };
+        let report = Report {
+            extension: built,
+            engine: Some(BuildInfo { zts: true, ..built }),
+            checks: vec![
+                Check::new("api", Status::Passed, "module API 20230831"),
+                compare("zts", false, true, |zts| {
+                    format!("thread-safety {}", on_off(zts))
+                }),
+            ],
+        };
+
+        assert!(!report.is_ok());
+        assert_eq!(report.failures().count(), 1);
+        assert_eq!(
+            report.to_string(),
+            "Extension built for PHP 8.3 NTS, loaded into PHP 8.3 ZTS.\n\
+             [passed] api: module API 20230831\n\
+             [failed] zts: extension built with thread-safety disabled, engine built \
+             with thread-safety enabled\n"
+        );
+    }
+
+    #[cfg(feature = "embed")]
+    #[test]
+    fn test_report_running() {
+        crate::embed::Embed::run(|| {
+            let report = Report::run();
+            assert_eq!(report.engine, Some(BuildInfo::COMPILED));
+            assert!(report.is_ok(), "{report}");
+        });
+    }
+}
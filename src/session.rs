@@ -0,0 +1,423 @@
+//! Session save handlers implemented in Rust.
+//!
+//! A [`SessionHandler`] stores the sessions of `ext/session`, e.g. in Redis or
+//! a SQL database. It is registered as a save handler module of the session
+//! extension, like the built-in `files` handler, and selected by scripts or
+//! the configuration with the `session.save_handler` setting.
+
+use std::{
+    any::{Any, TypeId},
+    ffi::{c_char, c_int, c_void, CStr, CString},
+    io, ptr,
+    sync::Arc,
+    time::Duration,
+};
+
+use parking_lot::{const_rwlock, RwLock};
+
+use crate::{
+    error::{Error, Result},
+    exception::catch_panic_or,
+    ffi::{
+        module_registry, php_session_create_id, php_session_register_module, ps_module,
+        zend_hash_str_find, zend_long, zend_string,
+    },
+    types::ZendStr,
+};
+
+const SUCCESS: c_int = 0;
+const FAILURE: c_int = -1;
+
+/// The registered handlers, by type.
+static HANDLERS: RwLock<Vec<(TypeId, Arc<dyn Any + Send + Sync>)>> = const_rwlock(Vec::new());
+
+/// A session save handler, storing the data of the sessions by their ID.
+///
+/// The handler is shared by all the requests, which may run concurrently on a
+/// thread-safe build. A request opens the handler when it starts its session,
+/// and closes it once the session is written.
+///
+/// The data of a session is opaque, as serialized by PHP. A handler which
+/// panics fails the operation, raising a warning.
+pub trait SessionHandler: Send + Sync + 'static {
+    /// Opens the handler for a request, e.g. to connect to a server.
+    ///
+    /// # Parameters
+    ///
+    /// * `save_path` - The `session.save_path` setting, e.g. the address of
+    ///   the server.
+    /// * `session_name` - The name of the session, e.g. `PHPSESSID`.
+    fn open(&self, _save_path: &str, _session_name: &str) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Closes the handler once the request wrote its session.
+    fn close(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Reads the data of a session, or returns [`None`] if it does not exist.
+    ///
+    /// # Parameters
+    ///
+    /// * `id` - The ID of the session.
+    /// * `max_lifetime` - The `session.gc_maxlifetime` setting.
+    fn read(&self, id: &str, max_lifetime: Duration) -> io::Result<Option<Vec<u8>>>;
+
+    /// Writes the data of a session.
+    ///
+    /// # Parameters
+    ///
+    /// * `id` - The ID of the session.
+    /// * `data` - The data of the session.
+    /// * `max_lifetime` - The `session.gc_maxlifetime` setting, e.g. to
+    ///   expire the session.
+    fn write(&self, id: &str, data: &[u8], max_lifetime: Duration) -> io::Result<()>;
+
+    /// Destroys a session, for `session_destroy()` and
+    /// `session_regenerate_id(true)`.
+    fn destroy(&self, id: &str) -> io::Result<()>;
+
+    /// Removes the sessions unused for longer than the given lifetime,
+    /// returning how many were removed. Stores expiring sessions on their own
+    /// can leave this unimplemented.
+    fn gc(&self, _max_lifetime: Duration) -> io::Result<u64> {
+        Ok(0)
+    }
+
+    /// Creates the ID of a new session, or returns [`None`] to use the IDs
+    /// generated by PHP.
+    fn create_id(&self) -> Option<String> {
+        None
+    }
+
+    /// Returns whether a session exists, which is checked before using an ID
+    /// sent by the client when `session.use_strict_mode` is enabled.
+    fn validate_id(&self, id: &str) -> io::Result<bool> {
+        Ok(self.read(id, Duration::ZERO)?.is_some())
+    }
+
+    /// Refreshes a session which was not modified by the request, when
+    /// `session.lazy_write` is enabled. Writes the session unless
+    /// implemented.
+    fn update_timestamp(&self, id: &str, data: &[u8], max_lifetime: Duration) -> io::Result<()> {
+        self.write(id, data, max_lifetime)
+    }
+}
+
+/// Registers a save handler, which is used by the requests whose
+/// `session.save_handler` setting is the given name.
+///
+/// This must be called during module startup, and the session extension must
+/// be loaded before the module. Each type of handler can be registered once.
+///
+/// # Parameters
+///
+/// * `name` - The name of the handler, e.g. `redis`.
+/// * `handler` - The handler.
+///
+/// # Errors
+///
+/// Returns an error if the name contains a NUL byte, if the session extension
+/// is not loaded, or if the handler cannot be registered, e.g. because the
+/// type was already registered or too many handlers are.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::{collections::HashMap, io, time::Duration};
+///
+/// use ext_php_rs::session::{register_session_handler, SessionHandler};
+/// use parking_lot::Mutex;
+///
+/// #[derive(Default)]
+/// struct MemoryHandler(Mutex<HashMap<String, Vec<u8>>>);
+///
+/// impl SessionHandler for MemoryHandler {
+///     fn read(&self, id: &str, _max_lifetime: Duration) -> io::Result<Option<Vec<u8>>> {
+///         Ok(self.0.lock().get(id).cloned())
+///     }
+///
+///     fn write(&self, id: &str, data: &[u8], _max_lifetime: Duration) -> io::Result<()> {
+///         self.0.lock().insert(id.to_string(), data.to_vec());
+///         Ok(())
+///     }
+///
+///     fn destroy(&self, id: &str) -> io::Result<()> {
+///         self.0.lock().remove(id);
+///         Ok(())
+///     }
+/// }
+///
+/// // During module startup:
+/// register_session_handler("memory", MemoryHandler::default())
+///     .expect("should register the handler");
+/// ```
+pub fn register_session_handler<H: SessionHandler>(name: &str, handler: H) -> Result<()> {
+    let name = CString::new(name)?;
+    let loaded = unsafe {
+        !zend_hash_str_find(ptr::addr_of!(module_registry), c"session".as_ptr(), 7).is_null()
+    };
+    if !loaded {
+        return Err(Error::SessionHandlerRegistrationFailure);
+    }
+
+    let mut handlers = HANDLERS.write();
+    if handlers.iter().any(|(ty, _)| *ty == TypeId::of::<H>()) {
+        return Err(Error::SessionHandlerRegistrationFailure);
+    }
+
+    let module: &'static ps_module = Box::leak(Box::new(ps_module {
+        s_name: name.into_raw(),
+        s_open: Some(open::<H>),
+        s_close: Some(close::<H>),
+        s_read: Some(read::<H>),
+        s_write: Some(write::<H>),
+        s_destroy: Some(destroy::<H>),
+        s_gc: Some(gc::<H>),
+        s_create_sid: Some(create_sid::<H>),
+        s_validate_sid: Some(validate_sid::<H>),
+        s_update_timestamp: Some(update_timestamp::<H>),
+    }));
+    if unsafe { php_session_register_module(module) } != SUCCESS {
+        return Err(Error::SessionHandlerRegistrationFailure);
+    }
+    handlers.push((TypeId::of::<H>(), Arc::new(handler)));
+    Ok(())
+}
+
+/// Returns the handler opened by the request.
+unsafe fn handler<'a, H: SessionHandler>(mod_data: *mut *mut c_void) -> Option<&'a H> {
+    mod_data
+        .as_ref()
+        .and_then(|data| data.cast::<Arc<H>>().as_ref())
+        .map(|handler| &**handler)
+}
+
+/// Converts a string passed by the engine.
+unsafe fn str<'a>(ptr: *const c_char) -> &'a str {
+    if ptr.is_null() {
+        return "";
+    }
+    CStr::from_ptr(ptr).to_str().unwrap_or_default()
+}
+
+/// Converts a session ID passed by the engine.
+unsafe fn id<'a>(key: *mut zend_string) -> Option<&'a str> {
+    key.as_ref().and_then(|key| key.as_str().ok())
+}
+
+/// Converts the maximum lifetime of the sessions.
+fn lifetime(max_lifetime: zend_long) -> Duration {
+    Duration::from_secs(max_lifetime.try_into().unwrap_or(0))
+}
+
+/// Converts the result of an operation to the status returned to the engine.
+fn status(result: io::Result<()>) -> c_int {
+    if result.is_ok() {
+        SUCCESS
+    } else {
+        FAILURE
+    }
+}
+
+unsafe extern "C" fn open<H: SessionHandler>(
+    mod_data: *mut *mut c_void,
+    save_path: *const c_char,
+    session_name: *const c_char,
+) -> c_int {
+    catch_panic_or(FAILURE, || {
+        let Some(mod_data) = mod_data.as_mut() else {
+            return FAILURE;
+        };
+        let handler = HANDLERS
+            .read()
+            .iter()
+            .find(|(ty, _)| *ty == TypeId::of::<H>())
+            .and_then(|(_, handler)| handler.clone().downcast::<H>().ok());
+        let Some(handler) = handler else {
+            return FAILURE;
+        };
+
+        if handler.open(str(save_path), str(session_name)).is_err() {
+            return FAILURE;
+        }
+        *mod_data = Box::into_raw(Box::new(handler)).cast();
+        SUCCESS
+    })
+}
+
+unsafe extern "C" fn close<H: SessionHandler>(mod_data: *mut *mut c_void) -> c_int {
+    catch_panic_or(FAILURE, || {
+        let Some(mod_data) = mod_data.as_mut() else {
+            return FAILURE;
+        };
+        let handler = std::mem::replace(mod_data, ptr::null_mut());
+        if handler.is_null() {
+            return FAILURE;
+        }
+        let handler = Box::from_raw(handler.cast::<Arc<H>>());
+        status(handler.close())
+    })
+}
+
+unsafe extern "C" fn read<H: SessionHandler>(
+    mod_data: *mut *mut c_void,
+    key: *mut zend_string,
+    val: *mut *mut zend_string,
+    maxlifetime: zend_long,
+) -> c_int {
+    catch_panic_or(FAILURE, || {
+        let (Some(handler), Some(id), Some(val)) = (handler::<H>(mod_data), id(key), val.as_mut())
+        else {
+            return FAILURE;
+        };
+        match handler.read(id, lifetime(maxlifetime)) {
+            Ok(data) => {
+                *val = ptr::from_mut(ZendStr::new(data.unwrap_or_default(), false).into_raw());
+                SUCCESS
+            }
+            Err(_) => FAILURE,
+        }
+    })
+}
+
+unsafe extern "C" fn write<H: SessionHandler>(
+    mod_data: *mut *mut c_void,
+    key: *mut zend_string,
+    val: *mut zend_string,
+    maxlifetime: zend_long,
+) -> c_int {
+    catch_panic_or(FAILURE, || {
+        let (Some(handler), Some(id), Some(val)) = (handler::<H>(mod_data), id(key), val.as_ref())
+        else {
+            return FAILURE;
+        };
+        status(handler.write(id, val.as_bytes(), lifetime(maxlifetime)))
+    })
+}
+
+unsafe extern "C" fn destroy<H: SessionHandler>(
+    mod_data: *mut *mut c_void,
+    key: *mut zend_string,
+) -> c_int {
+    catch_panic_or(FAILURE, || {
+        let (Some(handler), Some(id)) = (handler::<H>(mod_data), id(key)) else {
+            return FAILURE;
+        };
+        status(handler.destroy(id))
+    })
+}
+
+unsafe extern "C" fn gc<H: SessionHandler>(
+    mod_data: *mut *mut c_void,
+    maxlifetime: zend_long,
+    nrdels: *mut zend_long,
+) -> zend_long {
+    catch_panic_or(-1, || {
+        let Some(handler) = handler::<H>(mod_data) else {
+            return -1;
+        };
+        match handler.gc(lifetime(maxlifetime)) {
+            Ok(deleted) => {
+                let deleted = zend_long::try_from(deleted).unwrap_or(zend_long::MAX);
+                if let Some(nrdels) = nrdels.as_mut() {
+                    *nrdels = deleted;
+                }
+                deleted
+            }
+            Err(_) => -1,
+        }
+    })
+}
+
+unsafe extern "C" fn create_sid<H: SessionHandler>(mod_data: *mut *mut c_void) -> *mut zend_string {
+    catch_panic_or(ptr::null_mut(), || {
+        match handler::<H>(mod_data).and_then(SessionHandler::create_id) {
+            Some(id) => ptr::from_mut(ZendStr::new(id, false).into_raw()),
+            None => php_session_create_id(mod_data),
+        }
+    })
+}
+
+unsafe extern "C" fn validate_sid<H: SessionHandler>(
+    mod_data: *mut *mut c_void,
+    key: *mut zend_string,
+) -> c_int {
+    catch_panic_or(FAILURE, || {
+        let (Some(handler), Some(id)) = (handler::<H>(mod_data), id(key)) else {
+            return FAILURE;
+        };
+        match handler.validate_id(id) {
+            Ok(true) => SUCCESS,
+            _ => FAILURE,
+        }
+    })
+}
+
+unsafe extern "C" fn update_timestamp<H: SessionHandler>(
+    mod_data: *mut *mut c_void,
+    key: *mut zend_string,
+    val: *mut zend_string,
+    maxlifetime: zend_long,
+) -> c_int {
+    catch_panic_or(FAILURE, || {
+        let (Some(handler), Some(id), Some(val)) = (handler::<H>(mod_data), id(key), val.as_ref())
+        else {
+            return FAILURE;
+        };
+        status(handler.update_timestamp(id, val.as_bytes(), lifetime(maxlifetime)))
+    })
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::embed::Embed;
+
+    #[derive(Default)]
+    struct MemoryHandler(Mutex<HashMap<String, Vec<u8>>>);
+
+    impl SessionHandler for MemoryHandler {
+        fn read(&self, id: &str, _max_lifetime: Duration) -> io::Result<Option<Vec<u8>>> {
+            Ok(self.0.lock().get(id).cloned())
+        }
+
+        fn write(&self, id: &str, data: &[u8], _max_lifetime: Duration) -> io::Result<()> {
+            self.0.lock().insert(id.to_string(), data.to_vec());
+            Ok(())
+        }
+
+        fn destroy(&self, id: &str) -> io::Result<()> {
+            self.0.lock().remove(id);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_session_handler() {
+        Embed::run(|| {
+            register_session_handler("test_memory", MemoryHandler::default())
+                .expect("should register the handler");
+
+            let result = Embed::eval(
+                "ini_set('session.save_handler', 'test_memory');
+                ini_set('session.use_cookies', '0');
+                session_id('abc');
+                session_start();
+                $_SESSION['count'] = 1;
+                session_write_close();
+                $_SESSION = [];
+                session_start();
+                $_SESSION['count'];",
+            )
+            .expect("should use the handler");
+            assert_eq!(result.long(), Some(1));
+        });
+    }
+}
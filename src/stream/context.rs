@@ -0,0 +1,252 @@
+use std::{ffi::CString, mem, time::Duration};
+
+use super::notify::{set_notifier, StreamNotification};
+use crate::{
+    binary::Binary,
+    convert::IntoZval,
+    error::Result,
+    ffi::{
+        php_stream_context, php_stream_context_alloc, php_stream_context_set_option,
+        zend_list_delete,
+    },
+    types::Zval,
+};
+
+/// The options of the `ssl://` and `tls://` transports, also used by the
+/// `https://` and `ftps://` wrappers. Options left to [`None`] keep the
+/// defaults of PHP.
+///
+/// See the [PHP documentation](https://www.php.net/manual/en/context.ssl.php)
+/// for the meaning of each option.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SslOptions {
+    /// Whether to verify the certificate of the peer.
+    pub verify_peer: Option<bool>,
+    /// Whether to verify the name of the peer against its certificate.
+    pub verify_peer_name: Option<bool>,
+    /// Whether to accept self-signed certificates.
+    pub allow_self_signed: Option<bool>,
+    /// The name of the peer to verify, if it differs from the host.
+    pub peer_name: Option<String>,
+    /// The path to a file of certificate authorities.
+    pub cafile: Option<String>,
+    /// The path to a directory of certificate authorities.
+    pub capath: Option<String>,
+    /// The path to the local certificate, for client authentication.
+    pub local_cert: Option<String>,
+    /// The path to the private key of the local certificate.
+    pub local_pk: Option<String>,
+    /// The passphrase of the local certificate.
+    pub passphrase: Option<String>,
+    /// The maximum depth of the chain of certificates.
+    pub verify_depth: Option<u32>,
+    /// The list of ciphers, in the format of OpenSSL.
+    pub ciphers: Option<String>,
+    /// Whether to send the name of the server, for SNI.
+    pub sni_enabled: Option<bool>,
+}
+
+/// The options of the `http://` and `https://` wrappers. Options left to
+/// [`None`] keep the defaults of PHP.
+///
+/// See the [PHP documentation](https://www.php.net/manual/en/context.http.php)
+/// for the meaning of each option.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HttpOptions {
+    /// The method of the request, e.g. `POST`.
+    pub method: Option<String>,
+    /// The headers of the request, e.g. `Accept: application/json`.
+    pub headers: Vec<String>,
+    /// The user agent, unless set in the headers.
+    pub user_agent: Option<String>,
+    /// The body of the request.
+    pub content: Option<Vec<u8>>,
+    /// The URI of the proxy server, e.g. `tcp://proxy.example.com:5100`.
+    pub proxy: Option<String>,
+    /// Whether to request the full URI instead of the path, for proxies.
+    pub request_fulluri: Option<bool>,
+    /// Whether to follow redirects.
+    pub follow_location: Option<bool>,
+    /// The maximum number of redirects to follow.
+    pub max_redirects: Option<u32>,
+    /// The version of the protocol, e.g. `1.1`.
+    pub protocol_version: Option<f64>,
+    /// The timeout of reads.
+    pub timeout: Option<Duration>,
+    /// Whether to read the body of failed responses instead of failing.
+    pub ignore_errors: Option<bool>,
+}
+
+/// A stream context, passing options to the wrappers opening streams, e.g. the
+/// headers of a HTTP request.
+///
+/// The context is allocated by the engine, so it must be used within a
+/// request.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::stream::{open_stream, HttpOptions, StreamContext};
+///
+/// let mut context = StreamContext::new();
+/// context.set_http(&HttpOptions {
+///     method: Some("POST".into()),
+///     headers: vec!["Content-Type: application/json".into()],
+///     content: Some(br#"{"name":"world"}"#.to_vec()),
+///     ..Default::default()
+/// })?;
+/// let stream = open_stream("https://example.com/hello", "rb", Some(&context))?;
+/// # Ok::<(), ext_php_rs::error::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct StreamContext {
+    ptr: *mut php_stream_context,
+}
+
+impl StreamContext {
+    /// Creates an empty context.
+    pub fn new() -> Self {
+        Self {
+            ptr: unsafe { php_stream_context_alloc() },
+        }
+    }
+
+    /// Sets an option of a wrapper, e.g. the `level` of the `zlib` wrapper.
+    ///
+    /// # Parameters
+    ///
+    /// * `wrapper` - The name of the wrapper, e.g. `http`.
+    /// * `option` - The name of the option.
+    /// * `value` - The value of the option.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the names contain a NUL byte, or if the value
+    /// cannot be converted into a zval.
+    pub fn set_option<T: IntoZval>(&mut self, wrapper: &str, option: &str, value: T) -> Result<()> {
+        let wrapper = CString::new(wrapper)?;
+        let option = CString::new(option)?;
+        let mut value = value.into_zval(false)?;
+        // The engine copies the value, so ours is released when dropped.
+        unsafe {
+            php_stream_context_set_option(self.ptr, wrapper.as_ptr(), option.as_ptr(), &mut value);
+        }
+        Ok(())
+    }
+
+    /// Sets an option of a wrapper, unless it is [`None`].
+    fn set_some<T: IntoZval>(
+        &mut self,
+        wrapper: &str,
+        option: &str,
+        value: Option<T>,
+    ) -> Result<()> {
+        match value {
+            Some(value) => self.set_option(wrapper, option, value),
+            None => Ok(()),
+        }
+    }
+
+    /// Sets the options of the `ssl` transport.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a value contains a NUL byte.
+    pub fn set_ssl(&mut self, options: &SslOptions) -> Result<()> {
+        self.set_some("ssl", "verify_peer", options.verify_peer)?;
+        self.set_some("ssl", "verify_peer_name", options.verify_peer_name)?;
+        self.set_some("ssl", "allow_self_signed", options.allow_self_signed)?;
+        self.set_some("ssl", "peer_name", options.peer_name.as_deref())?;
+        self.set_some("ssl", "cafile", options.cafile.as_deref())?;
+        self.set_some("ssl", "capath", options.capath.as_deref())?;
+        self.set_some("ssl", "local_cert", options.local_cert.as_deref())?;
+        self.set_some("ssl", "local_pk", options.local_pk.as_deref())?;
+        self.set_some("ssl", "passphrase", options.passphrase.as_deref())?;
+        self.set_some("ssl", "verify_depth", options.verify_depth.map(i64::from))?;
+        self.set_some("ssl", "ciphers", options.ciphers.as_deref())?;
+        self.set_some("ssl", "SNI_enabled", options.sni_enabled)
+    }
+
+    /// Sets the options of the `http` wrapper.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a value contains a NUL byte.
+    pub fn set_http(&mut self, options: &HttpOptions) -> Result<()> {
+        self.set_some("http", "method", options.method.as_deref())?;
+        if !options.headers.is_empty() {
+            self.set_option("http", "header", options.headers.clone())?;
+        }
+        self.set_some("http", "user_agent", options.user_agent.as_deref())?;
+        let content = options.content.clone().map(Binary::from);
+        self.set_some("http", "content", content)?;
+        self.set_some("http", "proxy", options.proxy.as_deref())?;
+        self.set_some("http", "request_fulluri", options.request_fulluri)?;
+        self.set_some("http", "follow_location", options.follow_location)?;
+        self.set_some(
+            "http",
+            "max_redirects",
+            options.max_redirects.map(i64::from),
+        )?;
+        self.set_some("http", "protocol_version", options.protocol_version)?;
+        let timeout = options.timeout.map(|timeout| timeout.as_secs_f64());
+        self.set_some("http", "timeout", timeout)?;
+        self.set_some("http", "ignore_errors", options.ignore_errors)
+    }
+
+    /// Sets a callback receiving the notifications of the wrappers opening
+    /// streams with the context, e.g. the progress of a download. Replaces the
+    /// previous callback, or the notifier set by `stream_context_set_params()`.
+    ///
+    /// # Parameters
+    ///
+    /// * `callback` - The callback, called on the thread of the request.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ext_php_rs::stream::{open_stream, StreamContext, StreamNotification};
+    ///
+    /// let mut context = StreamContext::new();
+    /// context.on_notification(|notification| {
+    ///     if let StreamNotification::Progress { transferred, total } = notification {
+    ///         println!("downloaded {transferred} of {total:?} bytes");
+    ///     }
+    /// });
+    /// let stream = open_stream("https://example.com/large.zip", "rb", Some(&context))?;
+    /// # Ok::<(), ext_php_rs::error::Error>(())
+    /// ```
+    pub fn on_notification<F>(&mut self, callback: F)
+    where
+        F: FnMut(StreamNotification<'_>) + 'static,
+    {
+        unsafe { set_notifier(&mut *self.ptr, callback) };
+    }
+
+    /// Returns a pointer to the context of the engine.
+    pub fn as_ptr(&self) -> *mut php_stream_context {
+        self.ptr
+    }
+
+    /// Converts the context into a resource, e.g. to pass it to
+    /// `file_get_contents()`.
+    pub fn into_zval(self) -> Zval {
+        let mut zval = Zval::new();
+        // The zval takes over the reference to the resource.
+        zval.set_resource(unsafe { (*self.ptr).res });
+        mem::forget(self);
+        zval
+    }
+}
+
+impl Default for StreamContext {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for StreamContext {
+    fn drop(&mut self) {
+        unsafe { zend_list_delete((*self.ptr).res) };
+    }
+}
@@ -0,0 +1,351 @@
+use std::{
+    alloc::Layout,
+    ffi::{c_char, c_int, CStr, CString},
+    io, ptr, slice,
+};
+
+use parking_lot::{const_rwlock, RwLock};
+
+use crate::{
+    alloc::{efree, emalloc, pfree, pmalloc},
+    error::{php_error, Error, Result},
+    exception::{catch_panic, catch_panic_or},
+    ffi::{
+        ext_php_rs_stream_filter_alloc, php_stream, php_stream_bucket_append,
+        php_stream_bucket_brigade, php_stream_bucket_delref, php_stream_bucket_new,
+        php_stream_bucket_unlink, php_stream_filter, php_stream_filter_factory,
+        php_stream_filter_ops, php_stream_filter_register_factory, php_stream_filter_status_t,
+        php_stream_filter_status_t_PSFS_ERR_FATAL, php_stream_filter_status_t_PSFS_FEED_ME,
+        php_stream_filter_status_t_PSFS_PASS_ON, PSFS_FLAG_FLUSH_CLOSE, PSFS_FLAG_FLUSH_INC,
+    },
+    flags::ErrorType,
+    types::Zval,
+};
+
+type Factory = Box<dyn Fn(&str, Option<&Zval>) -> Option<Box<dyn StreamFilter>> + Send + Sync>;
+
+/// The factories of the registered filters, by name or pattern.
+static FACTORIES: RwLock<Vec<(String, Factory)>> = const_rwlock(Vec::new());
+
+static FACTORY: php_stream_filter_factory = php_stream_filter_factory {
+    create_filter: Some(create_filter),
+};
+
+static FILTER_OPS: FilterOps = FilterOps(php_stream_filter_ops {
+    filter: Some(filter),
+    dtor: Some(filter_dtor),
+    label: c"rust".as_ptr(),
+});
+
+/// The operations of the filters.
+struct FilterOps(php_stream_filter_ops);
+
+// SAFETY: The operations are immutable, and their label is a static string.
+unsafe impl Sync for FilterOps {}
+
+/// A filter attached to a stream.
+struct State {
+    name: String,
+    filter: Box<dyn StreamFilter>,
+}
+
+/// A stream filter implemented in Rust, transforming the data read from or
+/// written to a stream, e.g. to compress or encrypt it.
+///
+/// The engine passes the data through the filter in chunks, as it is read or
+/// written. The filter appends its output to the given buffer, and may keep
+/// some of its input until it has enough to produce output, e.g. a block of a
+/// cipher.
+pub trait StreamFilter: 'static {
+    /// Transforms a chunk of data.
+    ///
+    /// # Parameters
+    ///
+    /// * `input` - The chunk to transform.
+    /// * `output` - The buffer to append the transformed data to.
+    fn filter(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()>;
+
+    /// Writes out the data kept by the filter, for `fflush()`.
+    fn flush(&mut self, _output: &mut Vec<u8>) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Writes out the remaining data once the stream is closed or the filter
+    /// is removed, e.g. the trailer of a compressed stream. Flushes the filter
+    /// unless implemented.
+    fn finish(&mut self, output: &mut Vec<u8>) -> io::Result<()> {
+        self.flush(output)
+    }
+}
+
+/// Registers a filter which can be attached to streams with
+/// `stream_filter_append()` and `stream_filter_prepend()`, or used in
+/// `php://filter` URLs.
+///
+/// This should be called during module startup.
+///
+/// # Parameters
+///
+/// * `name` - The name of the filter, e.g. `rust.zstd`, or a pattern matching
+///   all the filters with a prefix, e.g. `rust.*`.
+/// * `create` - Creates a filter for a stream from the name it was requested
+///   with and the parameters passed to `stream_filter_append()`, or returns
+///   [`None`] if the parameters are invalid, which fails the call.
+///
+/// # Errors
+///
+/// Returns an error if the name contains a NUL byte or is already registered.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::io;
+///
+/// use ext_php_rs::stream::{register_stream_filter, StreamFilter};
+///
+/// struct Uppercase;
+///
+/// impl StreamFilter for Uppercase {
+///     fn filter(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+///         output.extend(input.iter().map(u8::to_ascii_uppercase));
+///         Ok(())
+///     }
+/// }
+///
+/// // During module startup:
+/// register_stream_filter("rust.uppercase", |_name, _params| Some(Uppercase))
+///     .expect("should register the filter");
+/// ```
+pub fn register_stream_filter<F, C>(name: &str, create: C) -> Result<()>
+where
+    F: StreamFilter,
+    C: Fn(&str, Option<&Zval>) -> Option<F> + Send + Sync + 'static,
+{
+    let pattern = CString::new(name)?;
+    let mut factories = FACTORIES.write();
+    if factories.iter().any(|(registered, _)| registered == name) {
+        return Err(Error::StreamFilterRegistrationFailure);
+    }
+    if unsafe { php_stream_filter_register_factory(pattern.as_ptr(), &FACTORY) } != 0 {
+        return Err(Error::StreamFilterRegistrationFailure);
+    }
+
+    let factory: Factory = Box::new(move |name, params| {
+        create(name, params).map(|filter| Box::new(filter) as Box<dyn StreamFilter>)
+    });
+    factories.push((name.to_string(), factory));
+    Ok(())
+}
+
+/// Returns the patterns the engine looks up a filter by, from the most to the
+/// least specific, e.g. `a.b.c`, `a.b.*` and `a.*`.
+fn patterns(name: &str) -> impl Iterator<Item = String> + '_ {
+    let wildcards = name
+        .match_indices('.')
+        .rev()
+        .map(|(index, _)| format!("{}.*", &name[..index]));
+    std::iter::once(name.to_string()).chain(wildcards)
+}
+
+unsafe extern "C" fn create_filter(
+    filtername: *const c_char,
+    filterparams: *mut Zval,
+    persistent: u8,
+) -> *mut php_stream_filter {
+    let Some(name) = filtername
+        .as_ref()
+        .and_then(|name| CStr::from_ptr(name).to_str().ok())
+    else {
+        return ptr::null_mut();
+    };
+
+    let filter = {
+        let factories = FACTORIES.read();
+        let Some(factory) = patterns(name).find_map(|pattern| {
+            factories
+                .iter()
+                .find(|(registered, _)| *registered == pattern)
+                .map(|(_, factory)| factory)
+        }) else {
+            return ptr::null_mut();
+        };
+        catch_panic_or(None, || factory(name, filterparams.as_ref()))
+    };
+    let Some(filter) = filter else {
+        return ptr::null_mut();
+    };
+
+    let state = Box::into_raw(Box::new(State {
+        name: name.to_string(),
+        filter,
+    }));
+    let filter = ext_php_rs_stream_filter_alloc(&FILTER_OPS.0, state.cast(), persistent);
+    if filter.is_null() {
+        drop(Box::from_raw(state));
+    }
+    filter
+}
+
+/// Returns the state of a filter.
+unsafe fn state<'a>(filter: *mut php_stream_filter) -> Option<&'a mut State> {
+    filter
+        .as_ref()
+        .and_then(|filter| filter.abstract_.value.ptr.cast::<State>().as_mut())
+}
+
+/// Calls the filter, turning a panic into an error so the stream fails
+/// instead of the process aborting.
+fn guarded(func: impl FnOnce() -> io::Result<()>) -> io::Result<()> {
+    catch_panic(func).unwrap_or_else(|e| Err(io::Error::other(e.message().to_string())))
+}
+
+unsafe extern "C" fn filter(
+    stream: *mut php_stream,
+    thisfilter: *mut php_stream_filter,
+    buckets_in: *mut php_stream_bucket_brigade,
+    buckets_out: *mut php_stream_bucket_brigade,
+    bytes_consumed: *mut usize,
+    flags: c_int,
+) -> php_stream_filter_status_t {
+    let Some(state) = state(thisfilter) else {
+        return php_stream_filter_status_t_PSFS_ERR_FATAL;
+    };
+
+    let mut output = vec![];
+    let mut consumed = 0;
+    let mut result = Ok(());
+    // Every incoming bucket is consumed, even after an error, so none leaks.
+    while let Some(bucket) = buckets_in
+        .as_ref()
+        .and_then(|brigade| brigade.head.as_mut())
+    {
+        php_stream_bucket_unlink(bucket);
+        if result.is_ok() && !bucket.buf.is_null() {
+            let input = slice::from_raw_parts(bucket.buf.cast::<u8>(), bucket.buflen);
+            consumed += input.len();
+            result = guarded(|| state.filter.filter(input, &mut output));
+        }
+        php_stream_bucket_delref(bucket);
+    }
+
+    let flags = flags as u32;
+    if result.is_ok() {
+        if flags & PSFS_FLAG_FLUSH_CLOSE != 0 {
+            result = guarded(|| state.filter.finish(&mut output));
+        } else if flags & PSFS_FLAG_FLUSH_INC != 0 {
+            result = guarded(|| state.filter.flush(&mut output));
+        }
+    }
+    if let Err(error) = result {
+        php_error(
+            ErrorType::Warning,
+            &format!("Filter {} failed: {error}", state.name),
+        );
+        return php_stream_filter_status_t_PSFS_ERR_FATAL;
+    }
+
+    if let Some(bytes_consumed) = bytes_consumed.as_mut() {
+        *bytes_consumed = consumed;
+    }
+    if output.is_empty() {
+        return php_stream_filter_status_t_PSFS_FEED_ME;
+    }
+    if append(stream, buckets_out, &output) {
+        php_stream_filter_status_t_PSFS_PASS_ON
+    } else {
+        php_stream_filter_status_t_PSFS_ERR_FATAL
+    }
+}
+
+/// Appends a bucket holding a copy of the data to a brigade.
+unsafe fn append(
+    stream: *mut php_stream,
+    brigade: *mut php_stream_bucket_brigade,
+    data: &[u8],
+) -> bool {
+    let Ok(layout) = Layout::array::<u8>(data.len()) else {
+        return false;
+    };
+    // The buffers of the buckets of persistent streams must be persistent.
+    let persistent = stream
+        .as_ref()
+        .is_some_and(|stream| stream.is_persistent() != 0);
+    let buf = if persistent {
+        pmalloc(layout)
+    } else {
+        emalloc(layout)
+    };
+    if buf.is_null() {
+        return false;
+    }
+    ptr::copy_nonoverlapping(data.as_ptr(), buf, data.len());
+
+    let bucket = php_stream_bucket_new(stream, buf.cast(), data.len(), 1, u8::from(persistent));
+    if bucket.is_null() {
+        if persistent {
+            pfree(buf);
+        } else {
+            efree(buf);
+        }
+        return false;
+    }
+    // The bucket owns the buffer, which PHP frees with the bucket.
+    #[cfg(feature = "alloc_tracking")]
+    if !persistent {
+        crate::alloc::tracking::untrack(buf);
+    }
+    php_stream_bucket_append(brigade, bucket);
+    true
+}
+
+unsafe extern "C" fn filter_dtor(thisfilter: *mut php_stream_filter) {
+    if let Some(filter) = thisfilter.as_mut() {
+        let state = std::mem::replace(&mut filter.abstract_.value.ptr, ptr::null_mut());
+        if !state.is_null() {
+            drop(Box::from_raw(state.cast::<State>()));
+        }
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    struct Uppercase;
+
+    impl StreamFilter for Uppercase {
+        fn filter(&mut self, input: &[u8], output: &mut Vec<u8>) -> io::Result<()> {
+            output.extend(input.iter().map(u8::to_ascii_uppercase));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_patterns() {
+        assert_eq!(
+            patterns("rust.zstd.fast").collect::<Vec<_>>(),
+            ["rust.zstd.fast", "rust.zstd.*", "rust.*"]
+        );
+        assert_eq!(patterns("rust").collect::<Vec<_>>(), ["rust"]);
+    }
+
+    #[test]
+    fn test_stream_filter() {
+        Embed::run(|| {
+            register_stream_filter("test.uppercase", |_, _| Some(Uppercase))
+                .expect("should register the filter");
+
+            let result = Embed::eval(
+                "$fp = fopen('php://memory', 'w+');
+                stream_filter_append($fp, 'test.uppercase', STREAM_FILTER_WRITE);
+                fwrite($fp, 'hello');
+                rewind($fp);
+                stream_get_contents($fp);",
+            )
+            .expect("should filter the stream");
+            assert_eq!(result.str(), Some("HELLO"));
+        });
+    }
+}
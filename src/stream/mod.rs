@@ -0,0 +1,36 @@
+//! PHP streams implemented in Rust.
+//!
+//! A [`StreamWrapper`] serves the URLs of a scheme, e.g. `bundle://`, to the
+//! file functions of PHP and to `include`, so scripts and assets can be
+//! served from storage managed by Rust, e.g. a bundle embedded in the binary
+//! or an object store. [`VirtualFs`] is a wrapper serving a tree of files
+//! held in memory. A [`StreamFilter`] transforms the data read from or
+//! written to streams, e.g. to compress or encrypt it.
+//!
+//! The other way around, [`open_stream`] opens streams with the wrappers of
+//! PHP, passing them options through a [`StreamContext`], and
+//! [`OwnedStream::from_bytes`] feeds Rust data to functions reading from
+//! streams.
+//!
+//! With the `tokio` feature, the [`tokio`](self::tokio) module bridges PHP
+//! streams and asynchronous I/O.
+//!
+//! This module implements wrappers in Rust. The raw wrappers of the engine
+//! are available as [`crate::zend::StreamWrapper`].
+
+mod context;
+mod filter;
+mod notify;
+mod open;
+#[cfg(feature = "tokio")]
+#[cfg_attr(docs, doc(cfg(feature = "tokio")))]
+pub mod tokio;
+mod vfs;
+mod wrapper;
+
+pub use context::{HttpOptions, SslOptions, StreamContext};
+pub use filter::{register_stream_filter, StreamFilter};
+pub use notify::StreamNotification;
+pub use open::{open_stream, OwnedStream};
+pub use vfs::VirtualFs;
+pub use wrapper::{register_stream_wrapper, FileType, Stat, StreamHandle, StreamWrapper};
@@ -0,0 +1,182 @@
+use std::ffi::{c_char, c_int, c_void, CStr};
+
+use crate::{
+    exception::catch_panic_or,
+    ffi::{
+        php_stream_context, php_stream_notification_alloc, php_stream_notification_free,
+        php_stream_notifier, PHP_STREAM_NOTIFIER_PROGRESS, PHP_STREAM_NOTIFY_AUTH_REQUIRED,
+        PHP_STREAM_NOTIFY_AUTH_RESULT, PHP_STREAM_NOTIFY_COMPLETED, PHP_STREAM_NOTIFY_CONNECT,
+        PHP_STREAM_NOTIFY_FAILURE, PHP_STREAM_NOTIFY_FILE_SIZE_IS, PHP_STREAM_NOTIFY_MIME_TYPE_IS,
+        PHP_STREAM_NOTIFY_PROGRESS, PHP_STREAM_NOTIFY_REDIRECTED, PHP_STREAM_NOTIFY_RESOLVE,
+    },
+};
+
+type Callback = Box<dyn FnMut(StreamNotification<'_>)>;
+
+/// A notification sent by a wrapper while it opens or transfers a stream,
+/// e.g. the progress of a download over `http://`.
+///
+/// Which notifications are sent depends on the wrapper. The `http://` and
+/// `ftp://` wrappers send most of them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamNotification<'a> {
+    /// The address of the host was resolved.
+    Resolve,
+    /// The connection to the host was established.
+    Connect,
+    /// The server requires authentication, with the message of the server.
+    AuthRequired(Option<&'a str>),
+    /// The MIME type of the resource is known.
+    MimeType(&'a str),
+    /// The size of the resource is known, in bytes.
+    FileSize(u64),
+    /// The server redirected to another URL.
+    Redirected(&'a str),
+    /// Bytes were transferred.
+    Progress {
+        /// The bytes transferred so far.
+        transferred: u64,
+        /// The size of the resource, if known.
+        total: Option<u64>,
+    },
+    /// The transfer completed.
+    Completed,
+    /// The request failed, e.g. with a HTTP status of 404.
+    Failure {
+        /// The message of the server.
+        message: Option<&'a str>,
+        /// The code of the failure, e.g. the HTTP status.
+        code: i32,
+    },
+    /// The server accepted or rejected the authentication.
+    AuthResult {
+        /// The message of the server.
+        message: Option<&'a str>,
+        /// The code of the result, e.g. the HTTP status.
+        code: i32,
+    },
+}
+
+/// Replaces the notifier of a context with one calling the given callback.
+pub(super) unsafe fn set_notifier(
+    context: &mut php_stream_context,
+    callback: impl FnMut(StreamNotification<'_>) + 'static,
+) {
+    if !context.notifier.is_null() {
+        php_stream_notification_free(context.notifier);
+    }
+
+    let notifier = &mut *php_stream_notification_alloc();
+    let callback: Callback = Box::new(callback);
+    notifier.func = Some(notify);
+    notifier.dtor = Some(notifier_dtor);
+    notifier.ptr.value.ptr = Box::into_raw(Box::new(callback)).cast();
+    notifier.mask = PHP_STREAM_NOTIFIER_PROGRESS as _;
+    context.notifier = notifier;
+}
+
+unsafe extern "C" fn notify(
+    context: *mut php_stream_context,
+    notifycode: c_int,
+    _severity: c_int,
+    xmsg: *mut c_char,
+    xcode: c_int,
+    bytes_sofar: usize,
+    bytes_max: usize,
+    _ptr: *mut c_void,
+) {
+    let Some(callback) = context
+        .as_ref()
+        .and_then(|context| context.notifier.as_ref())
+        .and_then(|notifier| notifier.ptr.value.ptr.cast::<Callback>().as_mut())
+    else {
+        return;
+    };
+
+    let message = (!xmsg.is_null())
+        .then(|| CStr::from_ptr(xmsg).to_str().ok())
+        .flatten();
+    let notification = match notifycode as u32 {
+        PHP_STREAM_NOTIFY_RESOLVE => StreamNotification::Resolve,
+        PHP_STREAM_NOTIFY_CONNECT => StreamNotification::Connect,
+        PHP_STREAM_NOTIFY_AUTH_REQUIRED => StreamNotification::AuthRequired(message),
+        PHP_STREAM_NOTIFY_MIME_TYPE_IS => StreamNotification::MimeType(message.unwrap_or_default()),
+        PHP_STREAM_NOTIFY_FILE_SIZE_IS => StreamNotification::FileSize(bytes_max as u64),
+        PHP_STREAM_NOTIFY_REDIRECTED => StreamNotification::Redirected(message.unwrap_or_default()),
+        PHP_STREAM_NOTIFY_PROGRESS => StreamNotification::Progress {
+            transferred: bytes_sofar as u64,
+            total: (bytes_max > 0).then_some(bytes_max as u64),
+        },
+        PHP_STREAM_NOTIFY_COMPLETED => StreamNotification::Completed,
+        PHP_STREAM_NOTIFY_FAILURE => StreamNotification::Failure {
+            message,
+            code: xcode,
+        },
+        PHP_STREAM_NOTIFY_AUTH_RESULT => StreamNotification::AuthResult {
+            message,
+            code: xcode,
+        },
+        _ => return,
+    };
+    catch_panic_or((), || callback(notification));
+}
+
+unsafe extern "C" fn notifier_dtor(notifier: *mut php_stream_notifier) {
+    if let Some(notifier) = notifier.as_mut() {
+        let callback = std::mem::replace(&mut notifier.ptr.value.ptr, std::ptr::null_mut());
+        if !callback.is_null() {
+            drop(Box::from_raw(callback.cast::<Callback>()));
+        }
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, ptr, rc::Rc};
+
+    use super::*;
+    use crate::{embed::Embed, stream::StreamContext};
+
+    #[test]
+    fn test_notify() {
+        Embed::run(|| {
+            let progress = Rc::new(RefCell::new(vec![]));
+            let mut context = StreamContext::new();
+            let received = progress.clone();
+            context.on_notification(move |notification| {
+                if let StreamNotification::Progress { transferred, total } = notification {
+                    received.borrow_mut().push((transferred, total));
+                }
+            });
+
+            let code = PHP_STREAM_NOTIFY_PROGRESS as c_int;
+            unsafe {
+                notify(
+                    context.as_ptr(),
+                    code,
+                    0,
+                    ptr::null_mut(),
+                    0,
+                    512,
+                    0,
+                    ptr::null_mut(),
+                );
+                notify(
+                    context.as_ptr(),
+                    code,
+                    0,
+                    ptr::null_mut(),
+                    0,
+                    1024,
+                    2048,
+                    ptr::null_mut(),
+                );
+            }
+            assert_eq!(*progress.borrow(), [(512, None), (1024, Some(2048))]);
+
+            drop(context);
+            assert_eq!(Rc::strong_count(&progress), 1);
+        });
+    }
+}
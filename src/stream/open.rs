@@ -0,0 +1,273 @@
+use std::{
+    ffi::CString,
+    io::{self, Read, Seek, SeekFrom, Write},
+    mem,
+    ptr::{self, NonNull},
+};
+
+use super::StreamContext;
+use crate::{
+    error::{Error, Result},
+    ffi::{_php_stream_open_wrapper_ex, zend_list_delete, zend_resource, REPORT_ERRORS},
+    types::Zval,
+    zend::Stream,
+};
+
+/// A stream opened by [`open_stream`], which is closed once dropped and no
+/// longer used by scripts.
+///
+/// The stream fails to be read from or written to once a script closes it,
+/// e.g. with `fclose()`.
+#[derive(Debug)]
+pub struct OwnedStream(NonNull<zend_resource>);
+
+impl OwnedStream {
+    /// Opens an empty stream held in memory, i.e. `php://memory`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stream failed to open.
+    pub fn memory() -> Result<Self> {
+        open_stream("php://memory", "w+b", None)
+    }
+
+    /// Opens an empty stream held in memory which moves to a temporary file
+    /// once it grows larger than the given size, 2 MiB by default, i.e.
+    /// `php://temp`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stream failed to open.
+    pub fn temp(max_memory: Option<usize>) -> Result<Self> {
+        match max_memory {
+            Some(max_memory) => {
+                open_stream(&format!("php://temp/maxmemory:{max_memory}"), "w+b", None)
+            }
+            None => open_stream("php://temp", "w+b", None),
+        }
+    }
+
+    /// Opens a stream held in memory holding a copy of the given bytes,
+    /// positioned at the start, e.g. to pass data to functions reading from
+    /// streams such as `fgetcsv()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stream failed to open or to be written to.
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut stream = Self::memory()?;
+        stream
+            .write_all(data)
+            .and_then(|()| stream.rewind())
+            .map_err(|_| Error::StreamOpenFailure)?;
+        Ok(stream)
+    }
+
+    /// Returns the stream, or [`None`] if it was closed by a script.
+    pub fn as_stream(&mut self) -> Option<&mut Stream> {
+        Stream::from_resource(unsafe { self.0.as_mut() })
+    }
+
+    /// Returns the whole content of the stream, e.g. the data written to a
+    /// stream held in memory by `fputcsv()`, and leaves the stream positioned
+    /// at its end.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the stream cannot be rewound or read from.
+    pub fn contents(&mut self) -> io::Result<Vec<u8>> {
+        let mut contents = vec![];
+        self.rewind()?;
+        self.read_to_end(&mut contents)?;
+        Ok(contents)
+    }
+
+    /// Returns the stream as a resource, e.g. to pass it to a function, while
+    /// keeping it open.
+    pub fn to_zval(&self) -> Zval {
+        let resource = unsafe { &mut *self.0.as_ptr() };
+        resource.gc.refcount += 1;
+        expose(resource)
+    }
+
+    /// Converts the stream into a resource, e.g. to return it to a script,
+    /// which then owns the stream.
+    pub fn into_zval(self) -> Zval {
+        let resource = unsafe { &mut *self.0.as_ptr() };
+        mem::forget(self);
+        expose(resource)
+    }
+
+    /// Returns the stream, or an error if it was closed by a script.
+    fn stream(&mut self) -> io::Result<&mut Stream> {
+        self.as_stream()
+            .ok_or_else(|| io::Error::other("the stream was closed"))
+    }
+}
+
+/// Returns a zval taking over a reference to the resource of a stream.
+fn expose(resource: &mut zend_resource) -> Zval {
+    if let Some(stream) = Stream::from_resource(resource) {
+        stream.set___exposed(1);
+    }
+    let mut zval = Zval::new();
+    zval.set_resource(resource);
+    zval
+}
+
+impl Read for OwnedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stream()?.read(buf)
+    }
+}
+
+impl Write for OwnedStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream()?.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream()?.flush()
+    }
+}
+
+impl Seek for OwnedStream {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.stream()?.seek(pos)
+    }
+}
+
+impl Drop for OwnedStream {
+    fn drop(&mut self) {
+        // Closes the stream unless a script still uses it.
+        unsafe { zend_list_delete(self.0.as_ptr()) };
+    }
+}
+
+/// Opens a stream with the wrappers of PHP, like `fopen()`, e.g. a local file,
+/// a `http://` or `compress.zlib://` URL, or a URL served by a wrapper
+/// registered by a script with `stream_wrapper_register()`.
+///
+/// The stream is allocated by the engine, so it must be used within a
+/// request. The engine reports why the stream failed to open as a warning.
+///
+/// # Parameters
+///
+/// * `url` - The path or URL to open.
+/// * `mode` - The mode to open the stream with, as passed to `fopen()`, e.g.
+///   `rb`.
+/// * `context` - The context passing options to the wrapper, if any.
+///
+/// # Errors
+///
+/// Returns an error if the URL or mode contain a NUL byte, or if the stream
+/// failed to open.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::io::Read;
+///
+/// use ext_php_rs::stream::open_stream;
+///
+/// let mut stream = open_stream("compress.zlib:///var/log/app.log.gz", "rb", None)?;
+/// let mut log = String::new();
+/// stream.read_to_string(&mut log)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn open_stream(url: &str, mode: &str, context: Option<&StreamContext>) -> Result<OwnedStream> {
+    let url = CString::new(url)?;
+    let mode = CString::new(mode)?;
+    let context = context.map_or(ptr::null_mut(), StreamContext::as_ptr);
+
+    let stream = unsafe {
+        #[cfg(php_debug)]
+        {
+            _php_stream_open_wrapper_ex(
+                url.as_ptr(),
+                mode.as_ptr(),
+                REPORT_ERRORS as _,
+                ptr::null_mut(),
+                context,
+                0,
+                ptr::null(),
+                0,
+                ptr::null(),
+                0,
+            )
+        }
+        #[cfg(not(php_debug))]
+        {
+            _php_stream_open_wrapper_ex(
+                url.as_ptr(),
+                mode.as_ptr(),
+                REPORT_ERRORS as _,
+                ptr::null_mut(),
+                context,
+            )
+        }
+    };
+    let resource = unsafe { stream.as_ref() }.and_then(|stream| NonNull::new(stream.res));
+    resource.map(OwnedStream).ok_or(Error::StreamOpenFailure)
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{embed::Embed, stream::HttpOptions, types::ZendCallable};
+
+    #[test]
+    fn test_open_stream() {
+        Embed::run(|| {
+            let mut stream =
+                open_stream("data://text/plain,hello", "rb", None).expect("should open the stream");
+            let mut contents = String::new();
+            stream
+                .read_to_string(&mut contents)
+                .expect("should read the stream");
+            assert_eq!(contents, "hello");
+
+            assert!(open_stream("/does/not/exist", "rb", None).is_err());
+        });
+    }
+
+    #[test]
+    fn test_stream_context() {
+        Embed::run(|| {
+            let mut context = StreamContext::new();
+            context
+                .set_http(&HttpOptions {
+                    method: Some("POST".into()),
+                    headers: vec!["Accept: text/plain".into()],
+                    content: Some(b"hello".to_vec()),
+                    ..Default::default()
+                })
+                .expect("should set the options");
+            let stream = open_stream("data://text/plain,hello", "rb", Some(&context))
+                .expect("should open the stream");
+            drop(stream);
+        });
+    }
+
+    #[test]
+    fn test_memory_stream() {
+        Embed::run(|| {
+            let mut input = OwnedStream::from_bytes(b"a,b\n1,2\n").expect("should open the stream");
+            let row = ZendCallable::try_from_name("fgetcsv")
+                .and_then(|fgetcsv| fgetcsv.try_call(vec![&input.to_zval()]))
+                .expect("should read a row");
+            assert_eq!(row.array().map(|row| row.len()), Some(2));
+            assert_eq!(
+                input.contents().expect("should read the stream"),
+                b"a,b\n1,2\n"
+            );
+
+            let mut output = OwnedStream::temp(None).expect("should open the stream");
+            ZendCallable::try_from_name("fputcsv")
+                .and_then(|fputcsv| fputcsv.try_call(vec![&output.to_zval(), &vec!["x", "y"]]))
+                .expect("should write a row");
+            assert_eq!(output.contents().expect("should read the stream"), b"x,y\n");
+        });
+    }
+}
@@ -0,0 +1,359 @@
+//! Bridges between PHP streams and the asynchronous I/O of tokio.
+
+use std::{
+    ffi::{c_char, c_int, c_void},
+    io,
+    pin::Pin,
+    sync::{atomic::Ordering, Arc},
+    task::{Context, Poll, Waker},
+};
+
+use ::tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::wrapper::{alloc, state};
+pub use crate::future::set_wake_hook;
+use crate::{
+    error::{Error, Result},
+    exception::catch_panic_or,
+    ffi::{
+        php_stream, php_stream_ops, PHP_STREAM_OPTION_BLOCKING, PHP_STREAM_OPTION_RETURN_NOTIMPL,
+    },
+    future::{suspend, Signal},
+    types::Zval,
+    zend::Stream,
+};
+
+static ASYNC_OPS: AsyncOps = AsyncOps(php_stream_ops {
+    write: Some(async_write),
+    read: Some(async_read),
+    close: Some(async_close),
+    flush: Some(async_flush),
+    label: c"tokio".as_ptr(),
+    seek: None,
+    cast: None,
+    stat: None,
+    set_option: Some(async_set_option),
+});
+
+/// The operations of the streams backed by asynchronous I/O.
+struct AsyncOps(php_stream_ops);
+
+// SAFETY: The operations are immutable, and their label is a static string.
+unsafe impl Sync for AsyncOps {}
+
+/// A PHP stream exposed as [`AsyncRead`] and [`AsyncWrite`], e.g. a socket
+/// opened by a script with `stream_socket_client()`.
+///
+/// The stream is switched to non-blocking mode for the lifetime of the
+/// adapter, and its previous mode is restored on drop. On Unix, the readiness
+/// of the file descriptor of the stream is watched by the reactor of tokio, so
+/// the adapter must be created within a runtime. Streams without a file
+/// descriptor, e.g. `php://memory`, are polled again on the next turn of the
+/// executor when they are not ready.
+///
+/// # Example
+///
+/// ```rust,ignore
+/// use ext_php_rs::{stream::tokio::PhpStreamIo, zend::Stream};
+/// use tokio::io::AsyncWriteExt;
+///
+/// let stream = Stream::from_zval(&mut socket).expect("should be a stream");
+/// let mut io = PhpStreamIo::new(stream)?;
+/// io.write_all(b"PING\r\n").await?;
+/// ```
+pub struct PhpStreamIo<'a> {
+    stream: &'a mut Stream,
+    blocking: Option<bool>,
+    #[cfg(unix)]
+    fd: Option<::tokio::io::unix::AsyncFd<Fd>>,
+}
+
+/// A file descriptor owned by a PHP stream, which is not closed on drop.
+#[cfg(unix)]
+struct Fd(std::os::unix::io::RawFd);
+
+#[cfg(unix)]
+impl std::os::unix::io::AsRawFd for Fd {
+    fn as_raw_fd(&self) -> std::os::unix::io::RawFd {
+        self.0
+    }
+}
+
+impl<'a> PhpStreamIo<'a> {
+    /// Wraps a PHP stream.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file descriptor of the stream cannot be
+    /// registered with the reactor of tokio.
+    ///
+    /// # Panics
+    ///
+    /// Panics on Unix if called outside of a tokio runtime.
+    pub fn new(stream: &'a mut Stream) -> io::Result<Self> {
+        let blocking = stream.set_blocking(false);
+        #[cfg(unix)]
+        let fd = match stream.as_raw_fd() {
+            Some(fd) => Some(::tokio::io::unix::AsyncFd::new(Fd(fd))?),
+            None => None,
+        };
+        Ok(Self {
+            stream,
+            blocking,
+            #[cfg(unix)]
+            fd,
+        })
+    }
+
+    /// Waits until the stream may be read from or written to again, after an
+    /// operation returned `0` bytes. Returns once the operation should be
+    /// tried again.
+    fn poll_ready(&self, cx: &mut Context<'_>, write: bool) -> Poll<io::Result<()>> {
+        #[cfg(unix)]
+        if let Some(fd) = &self.fd {
+            let mut guard = if write {
+                std::task::ready!(fd.poll_write_ready(cx))?
+            } else {
+                std::task::ready!(fd.poll_read_ready(cx))?
+            };
+            // The readiness was consumed by the operation which returned `0`.
+            guard.clear_ready();
+            return Poll::Ready(Ok(()));
+        }
+        let _ = write;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+impl Drop for PhpStreamIo<'_> {
+    fn drop(&mut self) {
+        if self.blocking == Some(true) {
+            self.stream.set_blocking(true);
+        }
+    }
+}
+
+impl AsyncRead for PhpStreamIo<'_> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            let read = this.stream.read(buf.initialize_unfilled())?;
+            if read > 0 || buf.remaining() == 0 || this.stream.eof() != 0 {
+                buf.advance(read);
+                return Poll::Ready(Ok(()));
+            }
+            std::task::ready!(this.poll_ready(cx, false))?;
+        }
+    }
+}
+
+impl AsyncWrite for PhpStreamIo<'_> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        loop {
+            let written = this.stream.write(buf)?;
+            if written > 0 || buf.is_empty() {
+                return Poll::Ready(Ok(written));
+            }
+            std::task::ready!(this.poll_ready(cx, true))?;
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(self.get_mut().stream.flush())
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.poll_flush(cx)
+    }
+}
+
+/// Exposes asynchronous I/O as a PHP stream resource, which scripts can read
+/// from and write to with `fread()`, `fwrite()` and friends.
+///
+/// When the I/O is not ready, the stream returns no data if it was set to
+/// non-blocking mode with `stream_set_blocking()`. Otherwise, it suspends the
+/// current fiber, if any, until the I/O may make progress, leaving it to the
+/// scheduler of the fibers to resume it, e.g. from the hook set with
+/// [`set_wake_hook`] or once [`wait`](crate::future::wait) returns. Outside
+/// of a fiber, the thread is parked until the I/O is ready.
+///
+/// The I/O is polled on the thread of the request, so it must be driven by a
+/// runtime running on other threads, e.g. a multi-threaded runtime.
+///
+/// # Parameters
+///
+/// * `io` - The I/O backing the stream, e.g. a [`tokio::net::TcpStream`].
+///
+/// # Errors
+///
+/// Returns an error if the engine fails to allocate the stream.
+///
+/// [`tokio::net::TcpStream`]: ::tokio::net::TcpStream
+pub fn stream_from_async<T>(io: T) -> Result<Zval>
+where
+    T: AsyncRead + AsyncWrite + Unpin + 'static,
+{
+    let state = AsyncState {
+        io: Box::new(io),
+        blocking: true,
+        signal: Signal::new(),
+    };
+    let stream = unsafe { alloc(&ASYNC_OPS.0, state, c"r+b".as_ptr()) };
+    let stream = unsafe { stream.as_mut() }.ok_or(Error::InvalidPointer)?;
+
+    let mut zval = Zval::new();
+    zval.set_resource(stream.res);
+    stream.set___exposed(1);
+    Ok(zval)
+}
+
+/// Asynchronous I/O which can be read from and written to.
+trait AsyncIo: AsyncRead + AsyncWrite + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> AsyncIo for T {}
+
+/// The state of a stream created by [`stream_from_async`].
+struct AsyncState {
+    io: Box<dyn AsyncIo>,
+    blocking: bool,
+    signal: Arc<Signal>,
+}
+
+impl AsyncState {
+    /// Polls an operation until it completes, or until it would block if the
+    /// stream is non-blocking.
+    fn wait<R>(
+        &mut self,
+        mut poll: impl FnMut(Pin<&mut dyn AsyncIo>, &mut Context<'_>) -> Poll<io::Result<R>>,
+    ) -> Option<io::Result<R>> {
+        let waker = Waker::from(self.signal.clone());
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            self.signal.woken.store(false, Ordering::Release);
+            if let Poll::Ready(result) = poll(Pin::new(&mut *self.io), &mut cx) {
+                return Some(result);
+            }
+            if !self.blocking {
+                return None;
+            }
+            if let Err(error) = suspend(&self.signal) {
+                return Some(Err(io::Error::other(error.to_string())));
+            }
+        }
+    }
+}
+
+unsafe extern "C" fn async_read(stream: *mut php_stream, buf: *mut c_char, count: usize) -> isize {
+    catch_panic_or(-1, || {
+        let Some(state) = state::<AsyncState>(stream) else {
+            return -1;
+        };
+        let buf = std::slice::from_raw_parts_mut(buf.cast::<u8>(), count);
+        let result = state.wait(|io, cx| {
+            let mut buf = ReadBuf::new(buf);
+            io.poll_read(cx, &mut buf).map_ok(|()| buf.filled().len())
+        });
+        match result {
+            Some(Ok(0)) if count > 0 => {
+                (*stream).set_eof(1);
+                0
+            }
+            Some(Ok(read)) => read as isize,
+            None => 0,
+            Some(Err(_)) => -1,
+        }
+    })
+}
+
+unsafe extern "C" fn async_write(
+    stream: *mut php_stream,
+    buf: *const c_char,
+    count: usize,
+) -> isize {
+    catch_panic_or(-1, || {
+        let Some(state) = state::<AsyncState>(stream) else {
+            return -1;
+        };
+        let buf = std::slice::from_raw_parts(buf.cast::<u8>(), count);
+        match state.wait(|io, cx| io.poll_write(cx, buf)) {
+            Some(Ok(written)) => written as isize,
+            None => 0,
+            Some(Err(_)) => -1,
+        }
+    })
+}
+
+unsafe extern "C" fn async_flush(stream: *mut php_stream) -> c_int {
+    catch_panic_or(-1, || {
+        let Some(state) = state::<AsyncState>(stream) else {
+            return -1;
+        };
+        match state.wait(|io, cx| io.poll_flush(cx)) {
+            Some(Ok(())) | None => 0,
+            Some(Err(_)) => -1,
+        }
+    })
+}
+
+unsafe extern "C" fn async_close(stream: *mut php_stream, _close_handle: c_int) -> c_int {
+    catch_panic_or(-1, || {
+        if let Some(stream) = stream.as_mut() {
+            let state = std::mem::replace(&mut stream.abstract_, std::ptr::null_mut());
+            if !state.is_null() {
+                let mut state = Box::from_raw(state.cast::<AsyncState>());
+                // Shuts the I/O down without waiting, as the engine is closing it.
+                state.blocking = false;
+                let _ = state.wait(|io, cx| io.poll_shutdown(cx));
+            }
+        }
+        0
+    })
+}
+
+unsafe extern "C" fn async_set_option(
+    stream: *mut php_stream,
+    option: c_int,
+    value: c_int,
+    _ptrparam: *mut c_void,
+) -> c_int {
+    let Some(state) = state::<AsyncState>(stream) else {
+        return -1;
+    };
+    if option != PHP_STREAM_OPTION_BLOCKING as c_int {
+        return PHP_STREAM_OPTION_RETURN_NOTIMPL;
+    }
+    let previous = state.blocking;
+    state.blocking = value != 0;
+    c_int::from(previous)
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{embed::Embed, types::ZendCallable};
+
+    #[test]
+    fn test_stream_from_async() {
+        Embed::run(|| {
+            let stream = stream_from_async(Cursor::new(b"hello".to_vec()))
+                .expect("should create the stream");
+            let contents = ZendCallable::try_from_name("stream_get_contents")
+                .and_then(|read| read.try_call(vec![&stream]))
+                .expect("should read the stream");
+            assert_eq!(contents.str(), Some("hello"));
+        });
+    }
+}
@@ -0,0 +1,239 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Cursor},
+    sync::Arc,
+};
+
+use super::{Stat, StreamWrapper};
+
+/// A node of a [`VirtualFs`].
+#[derive(Debug, Clone)]
+enum Node {
+    File(Arc<[u8]>),
+    Dir,
+}
+
+/// A read-only tree of files held in memory, served to PHP once registered
+/// with [`register_stream_wrapper`](super::register_stream_wrapper), e.g. the
+/// scripts and templates of an application embedded in the binary.
+///
+/// Paths are relative to the root of the scheme, e.g. `app/index.php` for
+/// `bundle://app/index.php`. The parent directories of files are created
+/// along with them, so `is_dir()`, `scandir()` and the directory iterators of
+/// the SPL see the whole tree. `.` and `..` segments and repeated slashes in
+/// URLs are resolved like local paths.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::stream::{register_stream_wrapper, VirtualFs};
+///
+/// // During module startup:
+/// let fs = VirtualFs::new()
+///     .file("app/index.php", b"<?php require __DIR__ . '/lib/hello.php';".as_slice())
+///     .file("app/lib/hello.php", b"<?php echo 'Hello';".as_slice())
+///     .dir("app/cache");
+/// register_stream_wrapper("bundle", fs).expect("should register the wrapper");
+/// ```
+#[derive(Debug, Clone)]
+pub struct VirtualFs {
+    nodes: BTreeMap<String, Node>,
+}
+
+impl VirtualFs {
+    /// Creates a filesystem holding only its root directory.
+    pub fn new() -> Self {
+        Self {
+            nodes: BTreeMap::from([(String::new(), Node::Dir)]),
+        }
+    }
+
+    /// Adds a file, along with its missing parent directories. Replaces the
+    /// file if it exists.
+    ///
+    /// # Parameters
+    ///
+    /// * `path` - The path of the file.
+    /// * `contents` - The contents of the file.
+    pub fn file(mut self, path: &str, contents: impl Into<Arc<[u8]>>) -> Self {
+        let path = normalize(path);
+        self.add_parents(&path);
+        self.nodes.insert(path, Node::File(contents.into()));
+        self
+    }
+
+    /// Adds an empty directory, along with its missing parent directories.
+    ///
+    /// # Parameters
+    ///
+    /// * `path` - The path of the directory.
+    pub fn dir(mut self, path: &str) -> Self {
+        let path = normalize(path);
+        self.add_parents(&path);
+        self.nodes.entry(path).or_insert(Node::Dir);
+        self
+    }
+
+    /// Adds the missing parent directories of a path.
+    fn add_parents(&mut self, path: &str) {
+        let mut end = 0;
+        while let Some(slash) = path[end..].find('/') {
+            end += slash;
+            self.nodes
+                .entry(path[..end].to_string())
+                .or_insert(Node::Dir);
+            end += 1;
+        }
+        self.nodes.entry(String::new()).or_insert(Node::Dir);
+    }
+
+    /// Returns the node at the path of a URL.
+    fn node(&self, url: &str) -> io::Result<&Node> {
+        self.nodes
+            .get(&normalize(url))
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+}
+
+impl Default for VirtualFs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StreamWrapper for VirtualFs {
+    type Stream = Cursor<Arc<[u8]>>;
+
+    fn open(&self, url: &str, mode: &str) -> io::Result<Self::Stream> {
+        if mode.contains(['w', 'a', 'x', 'c', '+']) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "the filesystem is read-only",
+            ));
+        }
+        match self.node(url)? {
+            Node::File(contents) => Ok(Cursor::new(contents.clone())),
+            Node::Dir => Err(io::ErrorKind::IsADirectory.into()),
+        }
+    }
+
+    fn stat(&self, url: &str) -> io::Result<Stat> {
+        match self.node(url)? {
+            Node::File(contents) => Ok(Stat::file(contents.len() as u64)),
+            Node::Dir => Ok(Stat::dir()),
+        }
+    }
+
+    fn read_dir(&self, url: &str) -> io::Result<Box<dyn Iterator<Item = String> + Send>> {
+        let path = normalize(url);
+        match self.nodes.get(&path) {
+            Some(Node::Dir) => {}
+            Some(Node::File(_)) => return Err(io::ErrorKind::NotADirectory.into()),
+            None => return Err(io::ErrorKind::NotFound.into()),
+        }
+
+        let prefix = if path.is_empty() {
+            path
+        } else {
+            format!("{path}/")
+        };
+        // The descendants of a directory follow it in the order of the map.
+        let entries: Vec<String> = self
+            .nodes
+            .range(prefix.clone()..)
+            .map(|(path, _)| path)
+            .take_while(|path| path.starts_with(&prefix))
+            .map(|path| &path[prefix.len()..])
+            .filter(|name| !name.is_empty() && !name.contains('/'))
+            .map(str::to_string)
+            .collect();
+        Ok(Box::new(entries.into_iter()))
+    }
+}
+
+/// Returns the path of a URL relative to the root, without the scheme, `.`
+/// and `..` segments and empty segments, e.g. `app/index.php` for
+/// `bundle://app/./lib/../index.php`.
+fn normalize(url: &str) -> String {
+    let path = url.split_once("://").map_or(url, |(_, path)| path);
+    let mut segments = vec![];
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop();
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{embed::Embed, stream::register_stream_wrapper, types::Zval};
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("vfs://app/./lib/../index.php"), "app/index.php");
+        assert_eq!(normalize("vfs:///app//lib/"), "app/lib");
+        assert_eq!(normalize("vfs://"), "");
+    }
+
+    #[test]
+    fn test_virtual_fs() {
+        Embed::run(|| {
+            let fs = VirtualFs::new()
+                .file("app/index.php", b"<?php return 42;".as_slice())
+                .file("app/lib/a.php", b"<?php".as_slice())
+                .file("app/lib/b.php", b"<?php".as_slice())
+                .file("app-readme.txt", b"Hello".as_slice())
+                .dir("app/cache");
+            register_stream_wrapper("test-vfs", fs).expect("should register the wrapper");
+
+            let types = Embed::eval(
+                "[is_dir('test-vfs://app'), is_file('test-vfs://app'), \
+                 is_file('test-vfs://app/index.php'), is_dir('test-vfs://app/index.php'), \
+                 file_exists('test-vfs://app/missing.php'), is_dir('test-vfs://')];",
+            )
+            .expect("should stat the files");
+            let types: Vec<bool> = types
+                .array()
+                .expect("should return an array")
+                .values()
+                .filter_map(Zval::bool)
+                .collect();
+            assert_eq!(types, [true, false, true, false, false, true]);
+
+            let entries = Embed::eval("implode(',', scandir('test-vfs://app'));")
+                .expect("should scan the directory");
+            assert_eq!(entries.str(), Some(".,..,cache,index.php,lib"));
+
+            let files = Embed::eval(
+                "(function () { \
+                     $files = []; \
+                     $iterator = new RecursiveIteratorIterator(new RecursiveDirectoryIterator( \
+                         'test-vfs://app', FilesystemIterator::SKIP_DOTS)); \
+                     foreach ($iterator as $file) { $files[] = $file->getPathname(); } \
+                     sort($files); \
+                     return implode(',', $files); \
+                 })();",
+            )
+            .expect("should iterate over the tree");
+            assert_eq!(
+                files.str(),
+                Some("test-vfs://app/index.php,test-vfs://app/lib/a.php,test-vfs://app/lib/b.php")
+            );
+
+            let answer = Embed::eval("include 'test-vfs://app/lib/../index.php';")
+                .expect("should include the file");
+            assert_eq!(answer.long(), Some(42));
+
+            let written = Embed::eval("@file_put_contents('test-vfs://app/new.php', '');")
+                .expect("should try to write the file");
+            assert_eq!(written.bool(), Some(false));
+        });
+    }
+}
@@ -0,0 +1,829 @@
+use std::{
+    ffi::{c_char, c_int, CStr, CString},
+    io::{self, Cursor, Read, Seek, SeekFrom},
+    mem, ptr,
+    time::SystemTime,
+};
+
+use crate::{
+    error::Result,
+    exception::catch_panic_or,
+    ffi::{
+        ext_php_rs_stream_alloc, php_clear_stat_cache, php_stream, php_stream_context,
+        php_stream_dirent, php_stream_ops, php_stream_statbuf, php_stream_wrapper,
+        php_stream_wrapper_log_error, php_stream_wrapper_ops, zend_off_t, zend_stat_t, zend_string,
+        PHP_STREAM_MKDIR_RECURSIVE, REPORT_ERRORS,
+    },
+};
+
+/// The type of a file served by a [`StreamWrapper`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileType {
+    /// A regular file.
+    File,
+    /// A directory.
+    Dir,
+}
+
+/// The status of a file served by a [`StreamWrapper`], returned to `stat()`,
+/// `filesize()`, `is_dir()` and friends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stat {
+    /// The type of the file.
+    pub file_type: FileType,
+    /// The size of the file, in bytes.
+    pub size: u64,
+    /// The permissions of the file, e.g. `0o644`.
+    pub permissions: u32,
+    /// When the file was last modified.
+    pub modified: Option<SystemTime>,
+    /// When the file was last accessed.
+    pub accessed: Option<SystemTime>,
+    /// When the status of the file last changed.
+    pub changed: Option<SystemTime>,
+}
+
+impl Stat {
+    /// Returns the status of a regular file of the given size, readable by
+    /// everyone.
+    pub fn file(size: u64) -> Self {
+        Self {
+            file_type: FileType::File,
+            size,
+            permissions: 0o644,
+            modified: None,
+            accessed: None,
+            changed: None,
+        }
+    }
+
+    /// Returns the status of a directory, readable by everyone.
+    pub fn dir() -> Self {
+        Self {
+            file_type: FileType::Dir,
+            size: 0,
+            permissions: 0o755,
+            modified: None,
+            accessed: None,
+            changed: None,
+        }
+    }
+
+    /// Writes the status to the stat buffer of a stream.
+    fn write_to(&self, ssb: &mut php_stream_statbuf) {
+        /// The file type bits of `st_mode`, which are the same on all
+        /// platforms PHP supports.
+        const S_IFREG: u32 = 0o100_000;
+        const S_IFDIR: u32 = 0o040_000;
+
+        fn secs(time: Option<SystemTime>) -> i64 {
+            time.and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map_or(0, |time| time.as_secs() as i64)
+        }
+
+        // SAFETY: The stat buffer is plain old data.
+        let mut sb: zend_stat_t = unsafe { mem::zeroed() };
+        let file_type = match self.file_type {
+            FileType::File => S_IFREG,
+            FileType::Dir => S_IFDIR,
+        };
+        sb.st_mode = (file_type | (self.permissions & 0o7777)) as _;
+        sb.st_size = self.size as _;
+        sb.st_nlink = 1;
+
+        #[cfg(windows)]
+        {
+            sb.st_mtime = secs(self.modified) as _;
+            sb.st_atime = secs(self.accessed) as _;
+            sb.st_ctime = secs(self.changed) as _;
+        }
+        #[cfg(any(target_os = "macos", target_os = "ios"))]
+        {
+            sb.st_mtimespec.tv_sec = secs(self.modified) as _;
+            sb.st_atimespec.tv_sec = secs(self.accessed) as _;
+            sb.st_ctimespec.tv_sec = secs(self.changed) as _;
+        }
+        #[cfg(not(any(windows, target_os = "macos", target_os = "ios")))]
+        {
+            sb.st_mtim.tv_sec = secs(self.modified) as _;
+            sb.st_atim.tv_sec = secs(self.accessed) as _;
+            sb.st_ctim.tv_sec = secs(self.changed) as _;
+        }
+
+        ssb.sb = sb;
+    }
+}
+
+/// A stream opened by a [`StreamWrapper`].
+///
+/// Only [`StreamHandle::read`] is required. The other operations fail with
+/// [`io::ErrorKind::Unsupported`] unless implemented, which PHP reports as a
+/// failure of the corresponding function, e.g. `fwrite()` returning `false`.
+pub trait StreamHandle: Send + 'static {
+    /// Reads bytes into the buffer, returning how many were read. Returning
+    /// `0` for a non-empty buffer marks the end of the stream.
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Writes bytes from the buffer, returning how many were written.
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Moves the position of the stream, returning the new position from the
+    /// start of the stream.
+    fn seek(&mut self, _pos: SeekFrom) -> io::Result<u64> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Flushes the bytes written so far.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Returns the status of the opened file, used by `fstat()` and to size
+    /// included files.
+    fn stat(&mut self) -> io::Result<Stat> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+impl<T: AsRef<[u8]> + Send + 'static> StreamHandle for Cursor<T> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        Read::read(self, buf)
+    }
+
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        Seek::seek(self, pos)
+    }
+
+    fn stat(&mut self) -> io::Result<Stat> {
+        Ok(Stat::file(self.get_ref().as_ref().len() as u64))
+    }
+}
+
+/// A wrapper serving the URLs of a scheme to PHP, registered with
+/// [`register_stream_wrapper`].
+///
+/// Every operation receives the full URL, including the scheme, e.g.
+/// `bundle://app/index.php`. Only [`StreamWrapper::open`] is required. The
+/// other operations fail with [`io::ErrorKind::Unsupported`] unless
+/// implemented. The errors of `open`, `unlink`, `rename`, `mkdir` and `rmdir`
+/// are reported as PHP warnings, unless silenced by the caller.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::{collections::HashMap, io::{self, Cursor}};
+///
+/// use ext_php_rs::stream::{register_stream_wrapper, Stat, StreamWrapper};
+///
+/// struct Bundle(HashMap<&'static str, &'static [u8]>);
+///
+/// impl StreamWrapper for Bundle {
+///     type Stream = Cursor<&'static [u8]>;
+///
+///     fn open(&self, url: &str, _mode: &str) -> io::Result<Self::Stream> {
+///         self.0
+///             .get(url)
+///             .map(|contents| Cursor::new(*contents))
+///             .ok_or_else(|| io::ErrorKind::NotFound.into())
+///     }
+///
+///     fn stat(&self, url: &str) -> io::Result<Stat> {
+///         self.0
+///             .get(url)
+///             .map(|contents| Stat::file(contents.len() as u64))
+///             .ok_or_else(|| io::ErrorKind::NotFound.into())
+///     }
+/// }
+///
+/// // During module startup:
+/// let bundle = Bundle(HashMap::from([(
+///     "bundle://app/index.php",
+///     b"<?php echo 'Hello from the bundle';".as_slice(),
+/// )]));
+/// register_stream_wrapper("bundle", bundle).expect("should register the wrapper");
+/// ```
+pub trait StreamWrapper: Send + Sync + 'static {
+    /// The type of the opened streams.
+    type Stream: StreamHandle;
+
+    /// Opens a stream, for `fopen()`, `file_get_contents()`, `include` and
+    /// friends.
+    ///
+    /// # Parameters
+    ///
+    /// * `url` - The URL to open.
+    /// * `mode` - The mode passed to `fopen()`, e.g. `rb` or `w+`.
+    fn open(&self, url: &str, mode: &str) -> io::Result<Self::Stream>;
+
+    /// Returns the status of a file or directory, for `stat()`,
+    /// `file_exists()`, `is_file()`, `is_dir()` and friends.
+    ///
+    /// Directories listed by [`StreamWrapper::read_dir`] should have a status
+    /// of [`FileType::Dir`], so the iterators of the SPL descend into them.
+    fn stat(&self, _url: &str) -> io::Result<Stat> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Deletes a file, for `unlink()`.
+    fn unlink(&self, _url: &str) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Renames a file, for `rename()`. Both URLs have the scheme of the
+    /// wrapper.
+    fn rename(&self, _from: &str, _to: &str) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Creates a directory, for `mkdir()`.
+    ///
+    /// # Parameters
+    ///
+    /// * `url` - The URL of the directory.
+    /// * `permissions` - The permissions of the directory, e.g. `0o755`.
+    /// * `recursive` - Whether the missing parent directories are created.
+    fn mkdir(&self, _url: &str, _permissions: u32, _recursive: bool) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Deletes a directory, for `rmdir()`.
+    fn rmdir(&self, _url: &str) -> io::Result<()> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+
+    /// Lists the names of the entries of a directory, for `opendir()`,
+    /// `scandir()` and friends. `rewinddir()` lists the directory again.
+    ///
+    /// The entries `.` and `..` are listed before the returned names, like the
+    /// entries of local directories, so they must not be returned.
+    fn read_dir(&self, _url: &str) -> io::Result<Box<dyn Iterator<Item = String> + Send>> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// Registers a wrapper serving the URLs of a scheme, e.g. `bundle` for
+/// `bundle://` URLs.
+///
+/// The wrapper is registered as a local wrapper, so its files can be included
+/// without `allow_url_include`. This should be called during module startup.
+///
+/// # Parameters
+///
+/// * `scheme` - The scheme of the URLs, without `://`.
+/// * `wrapper` - The wrapper serving the URLs.
+///
+/// # Errors
+///
+/// Returns an error if the scheme is invalid or already registered.
+pub fn register_stream_wrapper<W: StreamWrapper>(scheme: &str, wrapper: W) -> Result<()> {
+    let registration: &'static mut Registration<W> = Box::leak(Box::new(Registration {
+        wrapper,
+        wops: php_stream_wrapper_ops {
+            stream_opener: Some(stream_opener::<W>),
+            stream_closer: None,
+            stream_stat: None,
+            url_stat: Some(url_stat::<W>),
+            dir_opener: Some(dir_opener::<W>),
+            label: c"rust".as_ptr(),
+            unlink: Some(unlink::<W>),
+            rename: Some(rename::<W>),
+            stream_mkdir: Some(mkdir::<W>),
+            stream_rmdir: Some(rmdir::<W>),
+            stream_metadata: None,
+        },
+        stream_ops: php_stream_ops {
+            write: Some(stream_write::<W>),
+            read: Some(stream_read::<W>),
+            close: Some(stream_close::<W>),
+            flush: Some(stream_flush::<W>),
+            label: c"rust".as_ptr(),
+            seek: Some(stream_seek::<W>),
+            cast: None,
+            stat: Some(stream_stat::<W>),
+            set_option: None,
+        },
+        dir_ops: php_stream_ops {
+            write: None,
+            read: Some(dir_read),
+            close: Some(dir_close),
+            flush: None,
+            label: c"rust dir".as_ptr(),
+            seek: Some(dir_rewind::<W>),
+            cast: None,
+            stat: None,
+            set_option: None,
+        },
+    }));
+
+    let raw = php_stream_wrapper {
+        wops: &registration.wops,
+        abstract_: (registration as *mut Registration<W>).cast(),
+        is_url: 0,
+    };
+    raw.register(scheme)?;
+    Ok(())
+}
+
+/// A registered wrapper, along with the operations of the engine calling it.
+struct Registration<W> {
+    wrapper: W,
+    wops: php_stream_wrapper_ops,
+    stream_ops: php_stream_ops,
+    dir_ops: php_stream_ops,
+}
+
+/// An opened directory.
+struct Dir {
+    url: String,
+    entries: Box<dyn Iterator<Item = String> + Send>,
+}
+
+/// Returns the registration of a wrapper.
+unsafe fn registration<'a, W>(wrapper: *mut php_stream_wrapper) -> Option<&'a Registration<W>> {
+    wrapper
+        .as_ref()
+        .and_then(|wrapper| wrapper.abstract_.cast::<Registration<W>>().as_ref())
+}
+
+/// Converts a string passed by the engine.
+unsafe fn str<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+    CStr::from_ptr(ptr).to_str().ok()
+}
+
+/// Reports an error of the wrapper as a warning, if requested by the caller.
+unsafe fn report(wrapper: *mut php_stream_wrapper, options: c_int, error: &io::Error) {
+    if options & REPORT_ERRORS as c_int == 0 {
+        return;
+    }
+    if let Ok(message) = CString::new(error.to_string()) {
+        php_stream_wrapper_log_error(wrapper, options, c"%s".as_ptr(), message.as_ptr());
+    }
+}
+
+/// Converts the result of an operation changing files to the status returned
+/// to the engine.
+unsafe fn status(
+    wrapper: *mut php_stream_wrapper,
+    options: c_int,
+    result: io::Result<()>,
+) -> c_int {
+    match result {
+        Ok(()) => {
+            // Like the wrapper of local files, so `is_file()` and friends see
+            // the change.
+            php_clear_stat_cache(false, ptr::null(), 0);
+            1
+        }
+        Err(error) => {
+            report(wrapper, options, &error);
+            0
+        }
+    }
+}
+
+/// Returns the entries of a directory, after `.` and `..`.
+fn dir_entries(
+    entries: Box<dyn Iterator<Item = String> + Send>,
+) -> Box<dyn Iterator<Item = String> + Send> {
+    Box::new(
+        [".".to_string(), "..".to_string()]
+            .into_iter()
+            .chain(entries),
+    )
+}
+
+/// Allocates a stream of the engine, taking ownership of its state.
+pub(super) unsafe fn alloc<T>(
+    ops: &php_stream_ops,
+    state: T,
+    mode: *const c_char,
+) -> *mut php_stream {
+    let state = Box::into_raw(Box::new(state));
+    let stream = ext_php_rs_stream_alloc(ops, state.cast(), mode);
+    if stream.is_null() {
+        drop(Box::from_raw(state));
+    }
+    stream
+}
+
+/// Returns the state of a stream.
+pub(super) unsafe fn state<'a, T>(stream: *mut php_stream) -> Option<&'a mut T> {
+    stream
+        .as_ref()
+        .and_then(|stream| stream.abstract_.cast::<T>().as_mut())
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn stream_opener<W: StreamWrapper>(
+    wrapper: *mut php_stream_wrapper,
+    filename: *const c_char,
+    mode: *const c_char,
+    options: c_int,
+    _opened_path: *mut *mut zend_string,
+    _context: *mut php_stream_context,
+    #[cfg(php_debug)] _call_depth: c_int,
+    #[cfg(php_debug)] _zend_filename: *const c_char,
+    #[cfg(php_debug)] _zend_lineno: u32,
+    #[cfg(php_debug)] _zend_orig_filename: *const c_char,
+    #[cfg(php_debug)] _zend_orig_lineno: u32,
+) -> *mut php_stream {
+    catch_panic_or(ptr::null_mut(), || {
+        let (Some(registration), Some(url)) = (registration::<W>(wrapper), str(filename)) else {
+            return ptr::null_mut();
+        };
+
+        match registration.wrapper.open(url, str(mode).unwrap_or("rb")) {
+            Ok(handle) => alloc(&registration.stream_ops, handle, mode),
+            Err(error) => {
+                report(wrapper, options, &error);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+unsafe extern "C" fn url_stat<W: StreamWrapper>(
+    wrapper: *mut php_stream_wrapper,
+    url: *const c_char,
+    _flags: c_int,
+    ssb: *mut php_stream_statbuf,
+    _context: *mut php_stream_context,
+) -> c_int {
+    catch_panic_or(-1, || {
+        let (Some(registration), Some(url), Some(ssb)) =
+            (registration::<W>(wrapper), str(url), ssb.as_mut())
+        else {
+            return -1;
+        };
+
+        // Missing files are not errors, e.g. for `file_exists()`.
+        match registration.wrapper.stat(url) {
+            Ok(stat) => {
+                stat.write_to(ssb);
+                0
+            }
+            Err(_) => -1,
+        }
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe extern "C" fn dir_opener<W: StreamWrapper>(
+    wrapper: *mut php_stream_wrapper,
+    filename: *const c_char,
+    mode: *const c_char,
+    options: c_int,
+    _opened_path: *mut *mut zend_string,
+    _context: *mut php_stream_context,
+    #[cfg(php_debug)] _call_depth: c_int,
+    #[cfg(php_debug)] _zend_filename: *const c_char,
+    #[cfg(php_debug)] _zend_lineno: u32,
+    #[cfg(php_debug)] _zend_orig_filename: *const c_char,
+    #[cfg(php_debug)] _zend_orig_lineno: u32,
+) -> *mut php_stream {
+    catch_panic_or(ptr::null_mut(), || {
+        let (Some(registration), Some(url)) = (registration::<W>(wrapper), str(filename)) else {
+            return ptr::null_mut();
+        };
+
+        match registration.wrapper.read_dir(url) {
+            Ok(entries) => {
+                let dir = Dir {
+                    url: url.to_string(),
+                    entries: dir_entries(entries),
+                };
+                alloc(&registration.dir_ops, dir, mode)
+            }
+            Err(error) => {
+                report(wrapper, options, &error);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+unsafe extern "C" fn unlink<W: StreamWrapper>(
+    wrapper: *mut php_stream_wrapper,
+    url: *const c_char,
+    options: c_int,
+    _context: *mut php_stream_context,
+) -> c_int {
+    catch_panic_or(0, || {
+        let (Some(registration), Some(url)) = (registration::<W>(wrapper), str(url)) else {
+            return 0;
+        };
+        status(wrapper, options, registration.wrapper.unlink(url))
+    })
+}
+
+unsafe extern "C" fn rename<W: StreamWrapper>(
+    wrapper: *mut php_stream_wrapper,
+    url_from: *const c_char,
+    url_to: *const c_char,
+    options: c_int,
+    _context: *mut php_stream_context,
+) -> c_int {
+    catch_panic_or(0, || {
+        let (Some(registration), Some(from), Some(to)) =
+            (registration::<W>(wrapper), str(url_from), str(url_to))
+        else {
+            return 0;
+        };
+        status(wrapper, options, registration.wrapper.rename(from, to))
+    })
+}
+
+unsafe extern "C" fn mkdir<W: StreamWrapper>(
+    wrapper: *mut php_stream_wrapper,
+    url: *const c_char,
+    mode: c_int,
+    options: c_int,
+    _context: *mut php_stream_context,
+) -> c_int {
+    catch_panic_or(0, || {
+        let (Some(registration), Some(url)) = (registration::<W>(wrapper), str(url)) else {
+            return 0;
+        };
+        let recursive = options & PHP_STREAM_MKDIR_RECURSIVE as c_int != 0;
+        let result = registration
+            .wrapper
+            .mkdir(url, mode as u32 & 0o7777, recursive);
+        status(wrapper, options, result)
+    })
+}
+
+unsafe extern "C" fn rmdir<W: StreamWrapper>(
+    wrapper: *mut php_stream_wrapper,
+    url: *const c_char,
+    options: c_int,
+    _context: *mut php_stream_context,
+) -> c_int {
+    catch_panic_or(0, || {
+        let (Some(registration), Some(url)) = (registration::<W>(wrapper), str(url)) else {
+            return 0;
+        };
+        status(wrapper, options, registration.wrapper.rmdir(url))
+    })
+}
+
+unsafe extern "C" fn stream_read<W: StreamWrapper>(
+    stream: *mut php_stream,
+    buf: *mut c_char,
+    count: usize,
+) -> isize {
+    catch_panic_or(-1, || {
+        let Some(handle) = state::<W::Stream>(stream) else {
+            return -1;
+        };
+        // The buffer may be null if nothing is read.
+        if count == 0 {
+            return 0;
+        }
+        let buf = std::slice::from_raw_parts_mut(buf.cast::<u8>(), count);
+        match handle.read(buf) {
+            Ok(0) => {
+                (*stream).set_eof(1);
+                0
+            }
+            Ok(read) => read as isize,
+            Err(_) => -1,
+        }
+    })
+}
+
+unsafe extern "C" fn stream_write<W: StreamWrapper>(
+    stream: *mut php_stream,
+    buf: *const c_char,
+    count: usize,
+) -> isize {
+    catch_panic_or(-1, || {
+        let Some(handle) = state::<W::Stream>(stream) else {
+            return -1;
+        };
+        if count == 0 {
+            return 0;
+        }
+        let buf = std::slice::from_raw_parts(buf.cast::<u8>(), count);
+        match handle.write(buf) {
+            Ok(written) => written as isize,
+            Err(_) => -1,
+        }
+    })
+}
+
+unsafe extern "C" fn stream_close<W: StreamWrapper>(
+    stream: *mut php_stream,
+    _close_handle: c_int,
+) -> c_int {
+    catch_panic_or(-1, || {
+        if let Some(stream) = stream.as_mut() {
+            let handle = mem::replace(&mut stream.abstract_, ptr::null_mut());
+            if !handle.is_null() {
+                drop(Box::from_raw(handle.cast::<W::Stream>()));
+            }
+        }
+        0
+    })
+}
+
+unsafe extern "C" fn stream_flush<W: StreamWrapper>(stream: *mut php_stream) -> c_int {
+    catch_panic_or(-1, || {
+        match state::<W::Stream>(stream).map(|handle| handle.flush()) {
+            Some(Ok(())) => 0,
+            _ => -1,
+        }
+    })
+}
+
+unsafe extern "C" fn stream_seek<W: StreamWrapper>(
+    stream: *mut php_stream,
+    offset: zend_off_t,
+    whence: c_int,
+    newoffset: *mut zend_off_t,
+) -> c_int {
+    catch_panic_or(-1, || {
+        let Some(handle) = state::<W::Stream>(stream) else {
+            return -1;
+        };
+        let pos = match whence {
+            0 => SeekFrom::Start(offset as u64),
+            1 => SeekFrom::Current(offset),
+            2 => SeekFrom::End(offset),
+            _ => return -1,
+        };
+        match handle.seek(pos) {
+            Ok(position) => {
+                if let Some(newoffset) = newoffset.as_mut() {
+                    *newoffset = position as zend_off_t;
+                }
+                (*stream).set_eof(0);
+                0
+            }
+            Err(_) => -1,
+        }
+    })
+}
+
+unsafe extern "C" fn stream_stat<W: StreamWrapper>(
+    stream: *mut php_stream,
+    ssb: *mut php_stream_statbuf,
+) -> c_int {
+    catch_panic_or(-1, || {
+        let (Some(handle), Some(ssb)) = (state::<W::Stream>(stream), ssb.as_mut()) else {
+            return -1;
+        };
+        match handle.stat() {
+            Ok(stat) => {
+                stat.write_to(ssb);
+                0
+            }
+            Err(_) => -1,
+        }
+    })
+}
+
+/// Reads the next entry of a directory, as a `php_stream_dirent`.
+unsafe extern "C" fn dir_read(stream: *mut php_stream, buf: *mut c_char, count: usize) -> isize {
+    catch_panic_or(-1, || {
+        let Some(dir) = state::<Dir>(stream) else {
+            return -1;
+        };
+        if count < mem::size_of::<php_stream_dirent>() {
+            return -1;
+        }
+
+        let Some(name) = dir.entries.next() else {
+            (*stream).set_eof(1);
+            return 0;
+        };
+        let mut entry: php_stream_dirent = mem::zeroed();
+        let len = name.len().min(entry.d_name.len() - 1);
+        ptr::copy_nonoverlapping(name.as_ptr().cast(), entry.d_name.as_mut_ptr(), len);
+        ptr::write_unaligned(buf.cast::<php_stream_dirent>(), entry);
+        mem::size_of::<php_stream_dirent>() as isize
+    })
+}
+
+unsafe extern "C" fn dir_close(stream: *mut php_stream, _close_handle: c_int) -> c_int {
+    catch_panic_or(-1, || {
+        if let Some(stream) = stream.as_mut() {
+            let dir = mem::replace(&mut stream.abstract_, ptr::null_mut());
+            if !dir.is_null() {
+                drop(Box::from_raw(dir.cast::<Dir>()));
+            }
+        }
+        0
+    })
+}
+
+/// Lists a directory again, for `rewinddir()`.
+unsafe extern "C" fn dir_rewind<W: StreamWrapper>(
+    stream: *mut php_stream,
+    _offset: zend_off_t,
+    _whence: c_int,
+    _newoffset: *mut zend_off_t,
+) -> c_int {
+    catch_panic_or(-1, || {
+        let (Some(dir), Some(registration)) = (
+            state::<Dir>(stream),
+            stream
+                .as_ref()
+                .and_then(|stream| registration::<W>(stream.wrapper)),
+        ) else {
+            return -1;
+        };
+        match registration.wrapper.read_dir(&dir.url) {
+            Ok(entries) => {
+                dir.entries = dir_entries(entries);
+                (*stream).set_eof(0);
+                0
+            }
+            Err(_) => -1,
+        }
+    })
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::{
+        builders::ModuleBuilder,
+        embed::{Embed, TestSapi},
+        zend::ModuleEntry,
+    };
+
+    struct Bundle(HashMap<&'static str, &'static [u8]>);
+
+    impl StreamWrapper for Bundle {
+        type Stream = Cursor<&'static [u8]>;
+
+        fn open(&self, url: &str, _mode: &str) -> io::Result<Self::Stream> {
+            self.0
+                .get(url)
+                .map(|contents| Cursor::new(*contents))
+                .ok_or_else(|| io::ErrorKind::NotFound.into())
+        }
+
+        fn stat(&self, url: &str) -> io::Result<Stat> {
+            self.0
+                .get(url)
+                .map(|contents| Stat::file(contents.len() as u64))
+                .ok_or_else(|| io::ErrorKind::NotFound.into())
+        }
+    }
+
+    extern "C" fn startup(_ty: i32, _module_number: i32) -> i32 {
+        let bundle = Bundle(HashMap::from([
+            ("test-bundle://hello.txt", b"Hello".as_slice()),
+            ("test-bundle://answer.php", b"<?php return 42;".as_slice()),
+        ]));
+        register_stream_wrapper("test-bundle", bundle).expect("should register the wrapper");
+        0
+    }
+
+    extern "C" fn get_module() -> *mut ModuleEntry {
+        let (entry, _) = ModuleBuilder::new("test-bundle", "0.1.0")
+            .startup_function(startup)
+            .try_into()
+            .expect("should build the module");
+        entry.into_raw()
+    }
+
+    #[test]
+    fn test_stream_wrapper() {
+        TestSapi::new().module(get_module).run(|| {
+            let contents = Embed::eval("file_get_contents('test-bundle://hello.txt');")
+                .expect("should read the file");
+            assert_eq!(contents.str(), Some("Hello"));
+
+            let exists = Embed::eval("file_exists('test-bundle://missing.txt');")
+                .expect("should stat the file");
+            assert_eq!(exists.bool(), Some(false));
+
+            let answer = Embed::eval("include 'test-bundle://answer.php';")
+                .expect("should include the file");
+            assert_eq!(answer.long(), Some(42));
+        });
+    }
+
+    #[test]
+    fn test_register_nul() {
+        let wrapper = php_stream_wrapper {
+            wops: ptr::null(),
+            abstract_: ptr::null_mut(),
+            is_url: 0,
+        };
+        assert!(matches!(
+            wrapper.register("test\0bundle"),
+            Err(crate::error::Error::InvalidCString)
+        ));
+    }
+}
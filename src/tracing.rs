@@ -0,0 +1,129 @@
+//! Integration with the [`tracing`] crate.
+//!
+//! With the `tracing` feature, a `php.request` span is opened for each request
+//! handled by a module built with [`ModuleBuilder`] or run with
+//! [`Embed::run`], recording the method and URI of the request, and its
+//! response code and peak memory usage once it ends. SAPI hosts can open the
+//! span themselves with [`start_request`] and [`finish_request`].
+//!
+//! PHP function calls can also be traced as `php.call` spans nested in the
+//! request span with [`trace_calls`], which is built on the function call
+//! observers of [`crate::zend::observer`].
+//!
+//! [`ModuleBuilder`]: crate::builders::ModuleBuilder
+//! [`Embed::run`]: crate::embed::Embed::run
+
+use std::cell::RefCell;
+
+use ::tracing::{debug_span, field::Empty, info_span, span::EnteredSpan};
+
+use crate::{
+    alloc::memory_peak_usage,
+    zend::{
+        observer::{CallFrame, FcallObserver},
+        SapiGlobals,
+    },
+};
+
+thread_local! {
+    static REQUEST: RefCell<Option<EnteredSpan>> = const { RefCell::new(None) };
+    static CALLS: RefCell<Vec<EnteredSpan>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Opens and enters the span of the current request, unless it is already
+/// open. This is called when a request starts, once the SAPI has filled in the
+/// request info.
+pub fn start_request() {
+    if REQUEST.with(|request| request.borrow().is_some()) {
+        return;
+    }
+
+    let span = {
+        let globals = SapiGlobals::get();
+        let info = globals.request_info();
+        info_span!(
+            "php.request",
+            method = info.request_method(),
+            uri = info.request_uri(),
+            status = Empty,
+            memory_peak = Empty,
+        )
+    };
+    REQUEST.with(|request| *request.borrow_mut() = Some(span.entered()));
+}
+
+/// Records the response code and peak memory usage of the current request,
+/// and closes its span along with the spans of the calls which did not end,
+/// e.g. because of a bailout. This is called when a request ends.
+pub fn finish_request() {
+    close_calls();
+
+    let Some(span) = REQUEST.with(|request| request.borrow_mut().take()) else {
+        return;
+    };
+    span.record(
+        "status",
+        SapiGlobals::get().sapi_headers().http_response_code,
+    );
+    span.record("memory_peak", memory_peak_usage(false));
+}
+
+/// Traces PHP function calls as `php.call` spans, recording the name and
+/// class of the function, and the file and line it is defined on.
+///
+/// Tracing every call is expensive, see [`trace_calls_with_filter`] to only
+/// trace some functions. This must be called during module startup.
+pub fn trace_calls() {
+    trace_calls_with_filter(|_| true);
+}
+
+/// Traces the calls of the PHP functions accepted by the filter as `php.call`
+/// spans.
+///
+/// This must be called during module startup.
+///
+/// # Parameters
+///
+/// * `filter` - Returns whether the function of the given call is traced. It
+///   must only depend on the function, see [`FcallObserver::filter`].
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::tracing::trace_calls_with_filter;
+///
+/// // Only trace the methods of the application.
+/// trace_calls_with_filter(|frame| {
+///     frame.class_name().is_some_and(|class| class.starts_with("App\\"))
+/// });
+/// ```
+pub fn trace_calls_with_filter<F>(filter: F)
+where
+    F: Fn(&CallFrame) -> bool + Send + Sync + 'static,
+{
+    FcallObserver::new()
+        .filter(filter)
+        .on_begin(|frame| {
+            let span = debug_span!(
+                "php.call",
+                function = frame.function_name(),
+                class = frame.class_name(),
+                file = frame.file(),
+                line = frame.line(),
+            );
+            CALLS.with(|calls| calls.borrow_mut().push(span.entered()));
+        })
+        .on_end(|_, _| {
+            CALLS.with(|calls| calls.borrow_mut().pop());
+        })
+        .register();
+}
+
+/// Closes the spans of the calls in reverse order, as spans must be exited in
+/// the order they were entered.
+fn close_calls() {
+    let calls = CALLS.with(|calls| calls.take());
+    for span in calls.into_iter().rev() {
+        drop(span);
+    }
+}
@@ -0,0 +1,196 @@
+//! An arena for short-lived zvals.
+
+use std::{
+    cell::{RefCell, UnsafeCell},
+    collections::HashMap,
+};
+
+use crate::{
+    convert::IntoZval,
+    error::Result,
+    types::{ZendStr, Zval},
+};
+
+/// The number of zvals stored in each chunk of the arena.
+const CHUNK_SIZE: usize = 64;
+
+/// An arena for short-lived [`Zval`]s, such as the intermediate values built
+/// while converting nested structures.
+///
+/// Values allocated in the arena live until the arena is reset, which drops
+/// all of them at once while keeping the memory of the arena for reuse. The
+/// arena also deduplicates strings, so repeated strings such as array keys
+/// share a single refcounted `zend_string` when inserted with
+/// [`Zval::shallow_clone`].
+///
+/// The arena must be reset before the end of the request, as the values
+/// point to request-bound memory. [`ZvalArena::with`] provides a reusable
+/// arena which is reset automatically at the end of the call.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ext_php_rs::types::{ZendHashTable, ZvalArena};
+///
+/// let rows = ZvalArena::with(|arena| {
+///     let mut rows = ZendHashTable::new();
+///     for id in 0..100 {
+///         let mut row = ZendHashTable::new();
+///         row.insert("id", id)?;
+///         row.insert("status", arena.str("active").shallow_clone())?;
+///         rows.push(row)?;
+///     }
+///     Ok::<_, ext_php_rs::error::Error>(rows)
+/// });
+/// ```
+#[derive(Default)]
+pub struct ZvalArena {
+    chunks: UnsafeCell<Vec<Vec<Zval>>>,
+    strings: UnsafeCell<HashMap<Box<str>, *const Zval>>,
+}
+
+thread_local! {
+    static ARENA: RefCell<ZvalArena> = RefCell::new(ZvalArena::new());
+}
+
+/// Resets the arena when dropped, including when unwinding.
+struct ResetGuard<'a>(&'a mut ZvalArena);
+
+impl Drop for ResetGuard<'_> {
+    fn drop(&mut self) {
+        self.0.reset();
+    }
+}
+
+impl ZvalArena {
+    /// Creates a new, empty arena.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls the given function with an arena which is reused between calls
+    /// on the same thread and reset when the function returns. Nested calls
+    /// receive a fresh arena.
+    ///
+    /// # Parameters
+    ///
+    /// * `func` - The function to call with the arena.
+    pub fn with<R>(func: impl FnOnce(&ZvalArena) -> R) -> R {
+        ARENA.with(|arena| match arena.try_borrow_mut() {
+            Ok(mut arena) => {
+                let guard = ResetGuard(&mut arena);
+                func(guard.0)
+            }
+            Err(_) => func(&ZvalArena::new()),
+        })
+    }
+
+    /// Moves a value into the arena, returning a reference to it which is
+    /// valid until the arena is reset.
+    ///
+    /// # Parameters
+    ///
+    /// * `value` - The value to store in the arena.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the value could not be converted into a zval.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc(&self, value: impl IntoZval) -> Result<&mut Zval> {
+        Ok(self.push(value.into_zval(false)?))
+    }
+
+    /// Returns a string zval from the arena, creating it on first use. The
+    /// same zval is returned for equal strings until the arena is reset.
+    ///
+    /// # Parameters
+    ///
+    /// * `value` - The content of the string.
+    pub fn str(&self, value: &str) -> &Zval {
+        // SAFETY: The map is only accessed through `&self` within this function,
+        // and the arena is not `Sync`.
+        let strings = unsafe { &mut *self.strings.get() };
+        if let Some(zval) = strings.get(value) {
+            // SAFETY: The zvals of the arena do not move and are only dropped on
+            // reset, which also clears the map.
+            return unsafe { &**zval };
+        }
+
+        let mut zval = Zval::new();
+        zval.set_zend_string(ZendStr::new(value, false));
+        let zval = self.push(zval);
+        strings.insert(value.into(), zval);
+        zval
+    }
+
+    /// Returns the number of values stored in the arena.
+    pub fn len(&self) -> usize {
+        // SAFETY: The arena is not `Sync`, and no reference to the chunk list
+        // outlives the functions of the arena.
+        unsafe { &*self.chunks.get() }.iter().map(Vec::len).sum()
+    }
+
+    /// Returns whether the arena is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drops all values stored in the arena, keeping its memory for reuse.
+    pub fn reset(&mut self) {
+        self.strings.get_mut().clear();
+
+        let chunks = self.chunks.get_mut();
+        // Keep the first chunk to avoid reallocating it on the next use.
+        chunks.truncate(1);
+        if let Some(chunk) = chunks.first_mut() {
+            chunk.clear();
+        }
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    fn push(&self, zval: Zval) -> &mut Zval {
+        // SAFETY: The arena is not `Sync`, and no reference to the chunk list
+        // outlives this function.
+        let chunks = unsafe { &mut *self.chunks.get() };
+        if chunks
+            .last()
+            .is_none_or(|chunk| chunk.len() == chunk.capacity())
+        {
+            chunks.push(Vec::with_capacity(CHUNK_SIZE));
+        }
+
+        let chunk = chunks.last_mut().expect("arena should have a chunk");
+        chunk.push(zval);
+        let zval: *mut Zval = chunk.last_mut().expect("chunk should have a value");
+        // SAFETY: Chunks are never grown beyond their capacity, so the zval does not
+        // move until the arena is reset, which requires `&mut self`.
+        unsafe { &mut *zval }
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_arena() {
+        Embed::run(|| {
+            ZvalArena::with(|arena| {
+                for i in 0..(CHUNK_SIZE * 2) {
+                    let zval = arena.alloc(i as i64).expect("should allocate zval");
+                    assert_eq!(zval.long(), Some(i as i64));
+                }
+
+                let first = arena.str("key");
+                let second = arena.str("key");
+                assert!(std::ptr::eq(first, second));
+                assert_eq!(first.str(), Some("key"));
+                assert_eq!(arena.len(), CHUNK_SIZE * 2 + 1);
+            });
+
+            ZvalArena::with(|arena| assert!(arena.is_empty()));
+        });
+    }
+}
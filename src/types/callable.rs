@@ -90,6 +90,12 @@ impl<'a> ZendCallable<'a> {
     /// Returns the result wrapped in [`Ok`] upon success. If calling the
     /// callable fails, or an exception is thrown, an [`Err`] is returned.
     ///
+    /// A thrown exception is cleared from the executor and returned as
+    /// [`Error::Exception`], so the engine can keep being used. Converting the
+    /// error into a [`PhpException`](crate::exception::PhpException), e.g.
+    /// with `?` in a `#[php_function]`, rethrows the original exception
+    /// object.
+    ///
     /// # Example
     ///
     /// ```no_run
@@ -3,6 +3,7 @@
 //! Generally, it is easier to work directly with Rust types, converting into
 //! these PHP types when required.
 
+mod arena;
 mod array;
 mod callable;
 mod class_object;
@@ -10,9 +11,11 @@ mod iterable;
 mod iterator;
 mod long;
 mod object;
+mod snapshot;
 mod string;
 mod zval;
 
+pub use arena::ZvalArena;
 pub use array::{ArrayKey, ZendHashTable};
 pub use callable::ZendCallable;
 pub use class_object::ZendClassObject;
@@ -20,7 +23,8 @@ pub use iterable::Iterable;
 pub use iterator::ZendIterator;
 pub use long::ZendLong;
 pub use object::{PropertyQuery, ZendObject};
-pub use string::ZendStr;
+pub use snapshot::ZvalSnapshot;
+pub use string::{InternedStr, ZendStr};
 pub use zval::Zval;
 
 use crate::{convert::FromZval, flags::DataType, macros::into_zval};
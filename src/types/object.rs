@@ -3,6 +3,8 @@
 
 use std::{convert::TryInto, fmt::Debug, ops::DerefMut, os::raw::c_char};
 
+#[cfg(php84)]
+use crate::ffi::{zend_lazy_object_init, IS_OBJ_LAZY_PROXY, IS_OBJ_LAZY_UNINITIALIZED};
 use crate::{
     boxed::{ZBox, ZBoxable},
     class::RegisteredClass,
@@ -146,7 +148,8 @@ impl ZendObject {
     ///
     /// # Returns
     /// Returns the return value of the method, or an error if the method
-    /// could not be found or called.
+    /// could not be found or called. If the method throws an exception, the
+    /// exception is cleared and returned as [`Error::Exception`].
     #[inline(always)]
     pub fn try_call_method(&self, name: &str, params: Vec<&dyn IntoZvalDyn>) -> Result<Zval> {
         let mut retval = Zval::new();
@@ -177,7 +180,10 @@ impl ZendObject {
             )
         };
 
-        Ok(retval)
+        match ExecutorGlobals::take_exception() {
+            Some(e) => Err(Error::Exception(e)),
+            None => Ok(retval),
+        }
     }
 
     /// Attempts to read a property from the Object. Returns a result containing
@@ -302,6 +308,50 @@ impl ZendObject {
         format!("{:016x}0000000000000000", self.handle)
     }
 
+    /// Returns whether the object is a lazy object, either a ghost or a proxy,
+    /// which has not been initialized yet or is a proxy to its instance.
+    #[cfg(php84)]
+    pub fn is_lazy(&self) -> bool {
+        self.extra_flags & (IS_OBJ_LAZY_UNINITIALIZED | IS_OBJ_LAZY_PROXY) != 0
+    }
+
+    /// Returns whether the object is a lazy proxy, created with
+    /// `ReflectionClass::newLazyProxy()`.
+    #[cfg(php84)]
+    pub fn is_lazy_proxy(&self) -> bool {
+        self.extra_flags & IS_OBJ_LAZY_PROXY != 0
+    }
+
+    /// Returns whether the object is a lazy object which has not been
+    /// initialized yet.
+    #[cfg(php84)]
+    pub fn is_lazy_uninitialized(&self) -> bool {
+        self.extra_flags & IS_OBJ_LAZY_UNINITIALIZED != 0
+    }
+
+    /// Initializes a lazy object by calling its initializer, equivalent to
+    /// `ReflectionClass::initializeLazyObject()`. Objects which are not lazy
+    /// or are already initialized are returned as-is.
+    ///
+    /// # Returns
+    ///
+    /// The initialized object, which is the real instance for lazy proxies
+    /// and the object itself for lazy ghosts. If the initializer throws an
+    /// exception, the exception is cleared and returned as
+    /// [`Error::Exception`].
+    #[cfg(php84)]
+    pub fn initialize_lazy(&mut self) -> Result<&mut ZendObject> {
+        if !self.is_lazy() {
+            return Ok(self);
+        }
+
+        let obj = unsafe { zend_lazy_object_init(self) };
+        if let Some(e) = ExecutorGlobals::take_exception() {
+            return Err(Error::Exception(e));
+        }
+        unsafe { obj.as_mut() }.ok_or(Error::InvalidPointer)
+    }
+
     /// Attempts to retrieve a reference to the object handlers.
     #[inline]
     unsafe fn handlers(&self) -> Result<&ZendObjectHandlers> {
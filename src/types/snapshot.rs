@@ -0,0 +1,193 @@
+//! Owned snapshots of PHP values, which can be sent to other threads.
+
+use crate::{
+    convert::IntoZval,
+    error::{Error, Result},
+    flags::DataType,
+    types::{ArrayKey, ZendCallable, ZendHashTable, ZendStr, Zval},
+};
+
+/// A deep copy of a PHP value which does not point to memory of the engine,
+/// so it can be sent to other threads, e.g. background workers, and
+/// materialized back into a zval of any request or interpreter.
+///
+/// Scalars, strings and arrays are copied as is, and references are replaced
+/// by the value they point to. Objects are copied through `serialize()`, and
+/// recreated with `unserialize()`, so they must be serializable, e.g. not
+/// closures, and their class must exist where they are materialized.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::types::{Zval, ZvalSnapshot};
+///
+/// fn round_trip(value: &Zval) -> ext_php_rs::error::Result<Zval> {
+///     let snapshot = ZvalSnapshot::capture(value)?;
+///     let snapshot = std::thread::spawn(move || snapshot)
+///         .join()
+///         .expect("should send the snapshot");
+///     snapshot.materialize()
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZvalSnapshot {
+    /// A null value.
+    Null,
+    /// A boolean.
+    Bool(bool),
+    /// An integer.
+    Long(i64),
+    /// A float.
+    Double(f64),
+    /// A string, which may hold any byte.
+    String(Vec<u8>),
+    /// An array, with its keys in order.
+    Array(Vec<(ArrayKey, ZvalSnapshot)>),
+    /// An object, serialized with `serialize()`.
+    Object(Vec<u8>),
+}
+
+impl ZvalSnapshot {
+    /// Captures a deep copy of a value.
+    ///
+    /// # Parameters
+    ///
+    /// * `zval` - The value.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::ZvalConversion`] if the value, or a value it contains,
+    /// is a resource, or an array containing itself through a reference, or an
+    /// error if an object could not be serialized.
+    pub fn capture(zval: &Zval) -> Result<Self> {
+        Self::capture_nested(zval, &mut vec![])
+    }
+
+    /// Captures a value, given the arrays containing it, to detect recursion.
+    fn capture_nested(zval: &Zval, parents: &mut Vec<*const ZendHashTable>) -> Result<Self> {
+        let zval = zval.dereference();
+        if zval.is_null() {
+            return Ok(Self::Null);
+        }
+        if let Some(value) = zval.bool() {
+            return Ok(Self::Bool(value));
+        }
+        if let Some(value) = zval.long() {
+            return Ok(Self::Long(value));
+        }
+        if let Some(value) = zval.double() {
+            return Ok(Self::Double(value));
+        }
+        if let Some(value) = zval.zend_str() {
+            return Ok(Self::String(value.as_bytes().to_vec()));
+        }
+        if let Some(array) = zval.array() {
+            let ptr: *const ZendHashTable = array;
+            if parents.contains(&ptr) {
+                return Err(Error::ZvalConversion(DataType::Array));
+            }
+            parents.push(ptr);
+            let elements = array
+                .iter()
+                .map(|(key, value)| Ok((key, Self::capture_nested(value, parents)?)))
+                .collect::<Result<_>>();
+            parents.pop();
+            return elements.map(Self::Array);
+        }
+        if zval.is_object() {
+            let serialized = ZendCallable::try_from_name("serialize")?.try_call(vec![zval])?;
+            return serialized
+                .zend_str()
+                .map(|value| Self::Object(value.as_bytes().to_vec()))
+                .ok_or(Error::ZvalConversion(zval.get_type()));
+        }
+        Err(Error::ZvalConversion(zval.get_type()))
+    }
+
+    /// Creates a zval holding a copy of the value, in the current request.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an object could not be unserialized.
+    pub fn materialize(&self) -> Result<Zval> {
+        let mut zval = Zval::new();
+        match self {
+            Self::Null => zval.set_null(),
+            Self::Bool(value) => zval.set_bool(*value),
+            Self::Long(value) => zval.set_long(*value),
+            Self::Double(value) => zval.set_double(*value),
+            Self::String(value) => zval.set_zend_string(ZendStr::new(value, false)),
+            Self::Array(elements) => {
+                let mut array =
+                    ZendHashTable::with_capacity(elements.len().try_into().unwrap_or(u32::MAX));
+                for (key, value) in elements {
+                    let value = value.materialize()?;
+                    match key {
+                        ArrayKey::Long(index) => array.insert_at_index(*index as u64, value)?,
+                        ArrayKey::String(key) => array.insert(key, value)?,
+                    }
+                }
+                zval.set_hashtable(array);
+            }
+            Self::Object(serialized) => {
+                let mut data = Zval::new();
+                data.set_zend_string(ZendStr::new(serialized, false));
+                zval = ZendCallable::try_from_name("unserialize")?.try_call(vec![&data])?;
+            }
+        }
+        Ok(zval)
+    }
+}
+
+impl IntoZval for ZvalSnapshot {
+    const TYPE: DataType = DataType::Mixed;
+    const NULLABLE: bool = true;
+
+    fn set_zval(self, zv: &mut Zval, _: bool) -> Result<()> {
+        *zv = self.materialize()?;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_snapshot() {
+        Embed::run(|| {
+            let value = Embed::eval(
+                "[1, 'a' => \"b\\0c\", 'list' => [1.5, true, null], 'obj' => new ArrayObject([1])]",
+            )
+            .expect("should evaluate the value");
+            let snapshot = ZvalSnapshot::capture(&value).expect("should capture the value");
+
+            let snapshot = thread::spawn(move || snapshot)
+                .join()
+                .expect("should send the snapshot");
+            let copy = snapshot
+                .materialize()
+                .expect("should materialize the value");
+            let array = copy.array().expect("should be an array");
+            assert_eq!(array.len(), 4);
+            assert_eq!(
+                array.get("a").and_then(|value| value.binary::<u8>()),
+                Some(b"b\0c".to_vec())
+            );
+            assert!(array
+                .get("obj")
+                .and_then(Zval::object)
+                .is_some_and(
+                    |object| object.get_class_name().ok().as_deref() == Some("ArrayObject")
+                ));
+
+            let recursive = Embed::eval("(function () { $a = []; $a[] = &$a; return $a; })()")
+                .expect("should evaluate the value");
+            assert!(ZvalSnapshot::capture(&recursive).is_err());
+        });
+    }
+}
@@ -6,7 +6,8 @@ use std::{
     convert::TryFrom,
     ffi::{CStr, CString},
     fmt::Debug,
-    slice,
+    ptr, slice,
+    sync::atomic::{AtomicPtr, Ordering},
 };
 
 use parking_lot::{const_mutex, Mutex};
@@ -17,12 +18,13 @@ use crate::{
     error::{Error, Result},
     ffi::{
         ext_php_rs_is_known_valid_utf8, ext_php_rs_set_known_valid_utf8,
-        ext_php_rs_zend_string_init, ext_php_rs_zend_string_release, zend_string,
+        ext_php_rs_zend_string_init, ext_php_rs_zend_string_init_permanent,
+        ext_php_rs_zend_string_release, zend_new_interned_string, zend_string,
         zend_string_init_interned,
     },
     flags::DataType,
     macros::try_from_zval,
-    types::Zval,
+    types::{ZendCallable, Zval},
 };
 
 /// A borrowed Zend string.
@@ -240,6 +242,83 @@ impl ZendStr {
         }
     }
 
+    /// Interns an existing Zend string through `zend_new_interned_string`.
+    ///
+    /// The given string is consumed. If an equal string has already been
+    /// interned, the previously interned string is returned instead.
+    ///
+    /// When called during module startup the string is added to the permanent
+    /// interned string table. During a request, the string is added to the
+    /// request interned string table and is released when the request ends.
+    ///
+    /// # Parameters
+    ///
+    /// * `str` - The string to intern.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function used to intern strings has not been set.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ext_php_rs::types::ZendStr;
+    ///
+    /// let s = ZendStr::intern(ZendStr::new("PHP", true));
+    /// ```
+    pub fn intern(str: ZBox<Self>) -> ZBox<Self> {
+        let _lock = INTERNED_LOCK.lock();
+
+        unsafe {
+            let intern = zend_new_interned_string.expect("`zend_new_interned_string` not ready");
+            let ptr = intern(str.into_raw());
+
+            ZBox::from_raw(ptr.as_mut().expect("Failed to intern Zend string"))
+        }
+    }
+
+    /// Creates a permanent interned Zend string from a slice of bytes.
+    ///
+    /// Permanent interned strings live until the engine shuts down and are
+    /// never freed, so a `'static` reference is returned. The string's hash is
+    /// computed once on creation, which makes the string cheap to use as an
+    /// array key or property name.
+    ///
+    /// This should be called during module startup (`MINIT`), where the string
+    /// is added to PHP's permanent interned string table. When called later, a
+    /// persistent string flagged as permanent is created instead, as the
+    /// interned string table is request-bound at that point.
+    ///
+    /// Also see the [`interned`] macro, which caches the result.
+    ///
+    /// # Parameters
+    ///
+    /// * `str` - String content.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function could not allocate enough memory for the Zend
+    /// string.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ext_php_rs::types::ZendStr;
+    ///
+    /// let s: &'static ZendStr = ZendStr::new_permanent_interned("key");
+    /// ```
+    ///
+    /// [`interned`]: crate::interned
+    pub fn new_permanent_interned(str: impl AsRef<[u8]>) -> &'static Self {
+        let _lock = INTERNED_LOCK.lock();
+        let s = str.as_ref();
+        unsafe {
+            ext_php_rs_zend_string_init_permanent(s.as_ptr().cast(), s.len())
+                .as_ref()
+                .expect("Failed to allocate memory for new Zend string")
+        }
+    }
+
     /// Returns the length of the string.
     ///
     /// # Example
@@ -308,6 +387,123 @@ impl ZendStr {
         unsafe { slice::from_raw_parts(self.val.as_ptr().cast(), self.len()) }
     }
 
+    /// Creates a new Zend string from UTF-16 code units, e.g. a string of a
+    /// Windows API or a JavaScript runtime, encoded as UTF-8.
+    ///
+    /// Returns an [`Error::EncodingConversion`] variant if the code units
+    /// contain an unpaired surrogate.
+    ///
+    /// # Parameters
+    ///
+    /// * `units` - The UTF-16 code units.
+    /// * `persistent` - Whether the string should persist through the request
+    ///   boundary, see [`ZendStr::new`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ext_php_rs::types::ZendStr;
+    ///
+    /// let units: Vec<u16> = "héllo".encode_utf16().collect();
+    /// let s = ZendStr::from_utf16(&units, false).unwrap();
+    /// assert_eq!(s.as_str().unwrap(), "héllo");
+    /// ```
+    pub fn from_utf16(units: &[u16], persistent: bool) -> Result<ZBox<Self>> {
+        let str = String::from_utf16(units).map_err(|_| Error::EncodingConversion)?;
+        Ok(Self::new(str, persistent))
+    }
+
+    /// Creates a new Zend string from UTF-16 code units, encoded as UTF-8,
+    /// replacing unpaired surrogates with the replacement character.
+    ///
+    /// # Parameters
+    ///
+    /// * `units` - The UTF-16 code units.
+    /// * `persistent` - Whether the string should persist through the request
+    ///   boundary, see [`ZendStr::new`].
+    pub fn from_utf16_lossy(units: &[u16], persistent: bool) -> ZBox<Self> {
+        Self::new(String::from_utf16_lossy(units), persistent)
+    }
+
+    /// Returns the UTF-16 code units of the string.
+    ///
+    /// Returns an [Error::InvalidUtf8] variant if the string contains
+    /// non-UTF-8 characters.
+    pub fn to_utf16(&self) -> Result<Vec<u16>> {
+        Ok(self.as_str()?.encode_utf16().collect())
+    }
+
+    /// Creates a new Zend string from Latin-1 (ISO-8859-1) bytes, encoded as
+    /// UTF-8.
+    ///
+    /// # Parameters
+    ///
+    /// * `bytes` - The Latin-1 bytes.
+    /// * `persistent` - Whether the string should persist through the request
+    ///   boundary, see [`ZendStr::new`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ext_php_rs::types::ZendStr;
+    ///
+    /// let s = ZendStr::from_latin1(b"caf\xe9", false);
+    /// assert_eq!(s.as_str().unwrap(), "café");
+    /// assert_eq!(s.to_latin1().unwrap(), b"caf\xe9");
+    /// ```
+    pub fn from_latin1(bytes: &[u8], persistent: bool) -> ZBox<Self> {
+        let str: String = bytes.iter().copied().map(char::from).collect();
+        Self::new(str, persistent)
+    }
+
+    /// Returns the string encoded as Latin-1 (ISO-8859-1).
+    ///
+    /// Returns an [Error::InvalidUtf8] variant if the string contains
+    /// non-UTF-8 characters, or an [`Error::EncodingConversion`] variant if it
+    /// contains characters which are not in Latin-1.
+    pub fn to_latin1(&self) -> Result<Vec<u8>> {
+        self.as_str()?
+            .chars()
+            .map(|char| u8::try_from(char).map_err(|_| Error::EncodingConversion))
+            .collect()
+    }
+
+    /// Converts the string between two encodings supported by PHP, e.g.
+    /// `Windows-1252` or `Shift_JIS`, with `mb_convert_encoding()` if the
+    /// mbstring extension is loaded, or `iconv()` otherwise.
+    ///
+    /// Returns an [`Error::Callable`] variant if neither extension is loaded,
+    /// an [`Error::Exception`] variant if an encoding is not supported, or an
+    /// [`Error::EncodingConversion`] variant if the conversion failed.
+    ///
+    /// # Parameters
+    ///
+    /// * `to` - The encoding to convert the string into.
+    /// * `from` - The encoding of the string.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ext_php_rs::types::ZendStr;
+    ///
+    /// let s = ZendStr::new("café", false);
+    /// let converted = s.convert_encoding("Windows-1252", "UTF-8").unwrap();
+    /// assert_eq!(converted.as_bytes(), b"caf\xe9");
+    /// ```
+    pub fn convert_encoding(&self, to: &str, from: &str) -> Result<ZBox<Self>> {
+        let mut str = Zval::new();
+        str.set_zend_string(self.to_owned());
+
+        let converted = match ZendCallable::try_from_name("mb_convert_encoding") {
+            Ok(convert) => convert.try_call(vec![&str, &to, &from])?,
+            Err(_) => ZendCallable::try_from_name("iconv")?.try_call(vec![&from, &to, &str])?,
+        };
+        converted
+            .zend_str()
+            .map(ToOwned::to_owned)
+            .ok_or(Error::EncodingConversion)
+    }
+
     /// Returns a raw pointer to this object
     pub fn as_ptr(&self) -> *const ZendStr {
         self as *const _
@@ -319,6 +515,77 @@ impl ZendStr {
     }
 }
 
+/// A permanent interned string which is created on first use and cached for
+/// the lifetime of the process.
+///
+/// Usually declared through the [`interned`] macro. The cached string can be
+/// created ahead of time by calling [`InternedStr::get`] during module startup,
+/// so hot paths only perform an atomic load.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::types::InternedStr;
+///
+/// static NAME: InternedStr = InternedStr::new("name");
+///
+/// let name = NAME.get();
+/// assert_eq!(name.as_bytes(), b"name");
+/// ```
+///
+/// [`interned`]: crate::interned
+pub struct InternedStr {
+    value: &'static str,
+    ptr: AtomicPtr<ZendStr>,
+}
+
+impl InternedStr {
+    /// Creates a new, not yet interned, string.
+    ///
+    /// # Parameters
+    ///
+    /// * `value` - String content.
+    pub const fn new(value: &'static str) -> Self {
+        Self {
+            value,
+            ptr: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Returns the permanent interned string, creating it on first use.
+    ///
+    /// See [`ZendStr::new_permanent_interned`] for details on when the string
+    /// should first be created.
+    pub fn get(&self) -> &'static ZendStr {
+        if let Some(str) = unsafe { self.ptr.load(Ordering::Acquire).as_ref() } {
+            return str;
+        }
+
+        let str = ZendStr::new_permanent_interned(self.value);
+        match self.ptr.compare_exchange(
+            ptr::null_mut(),
+            str.as_ptr() as *mut _,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            Ok(_) => str,
+            // SAFETY: Only pointers to permanent strings are ever stored.
+            Err(existing) => unsafe { &*existing },
+        }
+    }
+
+    /// Returns the string content.
+    pub fn value(&self) -> &'static str {
+        self.value
+    }
+}
+
+impl Debug for InternedStr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("InternedStr").field(&self.value).finish()
+    }
+}
+
 unsafe impl ZBoxable for ZendStr {
     fn free(&mut self) {
         unsafe { ext_php_rs_zend_string_release(self) };
@@ -457,6 +724,7 @@ impl<'a> FromZval<'a> for &'a str {
 
 #[cfg(test)]
 mod tests {
+    use super::{InternedStr, ZendStr};
     use crate::embed::Embed;
 
     #[test]
@@ -472,4 +740,40 @@ mod tests {
             assert_eq!(zval.string().unwrap(), "foo");
         });
     }
+
+    #[test]
+    fn test_interned() {
+        Embed::run(|| {
+            let strings: Vec<_> = (0..2).map(|_| crate::interned!("foo")).collect();
+
+            assert_eq!(strings[0].as_str().unwrap(), "foo");
+            assert!(std::ptr::eq(strings[0], strings[1]));
+
+            let key = InternedStr::new("bar");
+            assert_eq!(key.get().as_str().unwrap(), "bar");
+            assert!(std::ptr::eq(key.get(), key.get()));
+        });
+    }
+
+    #[test]
+    fn test_encodings() {
+        Embed::run(|| {
+            let units: Vec<u16> = "h\u{e9}llo \u{1f418}".encode_utf16().collect();
+            let s = ZendStr::from_utf16(&units, false).unwrap();
+            assert_eq!(s.as_str().unwrap(), "h\u{e9}llo \u{1f418}");
+            assert_eq!(s.to_utf16().unwrap(), units);
+            assert!(ZendStr::from_utf16(&[0xd800], false).is_err());
+            assert_eq!(
+                ZendStr::from_utf16_lossy(&[0x61, 0xd800], false)
+                    .as_str()
+                    .unwrap(),
+                "a\u{fffd}"
+            );
+
+            let s = ZendStr::from_latin1(b"caf\xe9", false);
+            assert_eq!(s.as_str().unwrap(), "caf\u{e9}");
+            assert_eq!(s.to_latin1().unwrap(), b"caf\xe9");
+            assert!(ZendStr::new("\u{20ac}", false).to_latin1().is_err());
+        });
+    }
 }
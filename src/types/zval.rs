@@ -20,6 +20,7 @@ use crate::{
     flags::ZvalTypeFlags,
     rc::PhpRc,
     types::{ZendCallable, ZendHashTable, ZendLong, ZendObject, ZendStr},
+    zend::ClassEntry,
 };
 
 /// A zend value. This is the primary storage container used throughout the Zend
@@ -227,6 +228,68 @@ impl Zval {
             .try_call_method(name, params)
     }
 
+    /// Calls a method on the object contained in the zval, converting the
+    /// return value into `R`.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the method.
+    /// * `params` - A list of parameters to call the method with.
+    ///
+    /// # Returns
+    ///
+    /// Returns the converted return value, or an error if the zval is not an
+    /// object, the method could not be called, the method threw an exception
+    /// or the return value could not be converted into `R`.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ext_php_rs::types::Zval;
+    ///
+    /// # fn example(date: &Zval) -> ext_php_rs::error::Result<()> {
+    /// let formatted: String = date.call_method("format", vec![&"Y-m-d"])?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn call_method<R>(&self, name: &str, params: Vec<&dyn IntoZvalDyn>) -> Result<R>
+    where
+        R: for<'a> FromZval<'a>,
+    {
+        let retval = self.try_call_method(name, params)?;
+        R::from_zval(&retval).ok_or_else(|| Error::ZvalConversion(retval.get_type()))
+    }
+
+    /// Calls a static method, converting the return value into `R`.
+    ///
+    /// The class is taken from the object contained in the zval, or looked up
+    /// by name if the zval contains a string.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the static method.
+    /// * `params` - A list of parameters to call the method with.
+    ///
+    /// # Returns
+    ///
+    /// Returns the converted return value, or an error if the class could not
+    /// be found, the method could not be called, the method threw an exception
+    /// or the return value could not be converted into `R`.
+    pub fn call_static<R>(&self, name: &str, params: Vec<&dyn IntoZvalDyn>) -> Result<R>
+    where
+        R: for<'a> FromZval<'a>,
+    {
+        let ce = match self.object() {
+            Some(obj) => obj.get_class_entry(),
+            None => self
+                .str()
+                .and_then(ClassEntry::try_find)
+                .ok_or(Error::Object)?,
+        };
+        let retval = ce.try_call_static_method(name, params)?;
+        R::from_zval(&retval).ok_or_else(|| Error::ZvalConversion(retval.get_type()))
+    }
+
     /// Returns the value of the zval if it is an internal indirect reference.
     pub fn indirect(&self) -> Option<&Zval> {
         if self.is_indirect() {
@@ -0,0 +1,221 @@
+//! Detection of slow requests, to find out what production requests are
+//! stuck on without terminating them.
+//!
+//! The handlers registered with [`on_slow_request`] are called once for each
+//! request running longer than their threshold, with a [`SlowRequest`]
+//! snapshot of the PHP backtrace and memory usage of the request. Requests
+//! are tracked when they are handled by a module built with [`ModuleBuilder`]
+//! or run with [`Embed::run`].
+//!
+//! A background thread watches the running requests and interrupts those
+//! which exceed a threshold, so the snapshot is captured on the thread of the
+//! request, between two opcodes, through the interrupts of
+//! [`crate::zend::interrupt`]. A request blocked in a long running internal
+//! function, e.g. a database query, is only reported once the function
+//! returns.
+//!
+//! [`ModuleBuilder`]: crate::builders::ModuleBuilder
+//! [`Embed::run`]: crate::embed::Embed::run
+
+use std::{
+    cell::{Cell, RefCell},
+    io, thread,
+    time::{Duration, Instant},
+};
+
+use parking_lot::{const_rwlock, RwLock};
+
+use crate::{
+    alloc::MemoryStats,
+    exception::catch_panic_or,
+    zend::{
+        backtrace::{self, StackFrame},
+        interrupt::{for_each_running, register_interrupt_handler},
+        lifecycle::RequestObserver,
+        ExecuteData, SapiGlobals,
+    },
+};
+
+type SlowRequestHandler = Box<dyn Fn(&SlowRequest) + Send + Sync>;
+
+static WATCHDOGS: RwLock<Vec<(Duration, SlowRequestHandler)>> = const_rwlock(Vec::new());
+
+thread_local! {
+    /// When the request of this thread started.
+    static STARTED: Cell<Option<Instant>> = const { Cell::new(None) };
+    /// Whether each watchdog reported the request of this thread.
+    static REPORTED: RefCell<Vec<bool>> = const { RefCell::new(Vec::new()) };
+}
+
+/// A snapshot of a request which exceeded the threshold of a watchdog.
+#[derive(Debug, Clone)]
+pub struct SlowRequest {
+    /// The threshold which was exceeded.
+    pub threshold: Duration,
+    /// The time elapsed since the request started.
+    pub elapsed: Duration,
+    /// The method of the request, e.g. `GET`.
+    pub method: Option<String>,
+    /// The URI of the request.
+    pub uri: Option<String>,
+    /// The memory usage of the request.
+    pub memory: MemoryStats,
+    /// The PHP backtrace of the request, innermost frame first.
+    pub backtrace: Vec<StackFrame>,
+}
+
+/// Registers a handler called with a snapshot of each request running longer
+/// than the given threshold. The request keeps running afterwards.
+///
+/// The handler is called at most once per request, on the thread of the
+/// request. This must be called during module startup. The first call starts
+/// a thread watching the running requests for all the thresholds.
+///
+/// # Parameters
+///
+/// * `threshold` - The duration after which a request is considered slow.
+/// * `handler` - Receives the snapshot of the slow request.
+///
+/// # Errors
+///
+/// Returns an error if the thread watching the requests could not be
+/// started, in which case the handler is not registered.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use ext_php_rs::watchdog::on_slow_request;
+///
+/// on_slow_request(Duration::from_secs(5), |request| {
+///     eprintln!(
+///         "{} is still running after {:?}, using {} bytes:",
+///         request.uri.as_deref().unwrap_or("-"),
+///         request.elapsed,
+///         request.memory.usage,
+///     );
+///     for (i, frame) in request.backtrace.iter().enumerate() {
+///         eprintln!("  #{i} {frame}");
+///     }
+/// })
+/// .expect("should start the watchdog");
+/// ```
+pub fn on_slow_request<F>(threshold: Duration, handler: F) -> io::Result<()>
+where
+    F: Fn(&SlowRequest) + Send + Sync + 'static,
+{
+    let mut watchdogs = WATCHDOGS.write();
+    if watchdogs.is_empty() {
+        thread::Builder::new()
+            .name("ext-php-rs-watchdog".into())
+            .spawn(watch)?;
+        RequestObserver::new()
+            .on_startup(start_request)
+            .on_shutdown(finish_request)
+            .register();
+        register_interrupt_handler(check_request);
+    }
+    watchdogs.push((threshold, Box::new(handler)));
+    Ok(())
+}
+
+/// Interrupts the running requests each time they exceed a threshold, so they
+/// are reported by [`check_request`] on their own thread.
+fn watch() {
+    // The number of thresholds each running request had exceeded when it was
+    // last checked.
+    let mut exceeded: Vec<(u64, usize)> = vec![];
+    loop {
+        let thresholds: Vec<Duration> = WATCHDOGS
+            .read()
+            .iter()
+            .map(|(threshold, _)| *threshold)
+            .collect();
+        let poll = thresholds
+            .iter()
+            .min()
+            .map_or(Duration::from_secs(1), |threshold| *threshold / 10)
+            .clamp(Duration::from_millis(10), Duration::from_secs(1));
+        thread::sleep(poll);
+
+        let mut running = vec![];
+        for_each_running(|request| {
+            let elapsed = request.started.elapsed();
+            let count = thresholds
+                .iter()
+                .filter(|threshold| elapsed >= **threshold)
+                .count();
+            let previous = exceeded
+                .iter()
+                .find(|(id, _)| *id == request.id)
+                .map_or(0, |(_, count)| *count);
+            if count > previous {
+                request.handle.interrupt();
+            }
+            running.push((request.id, count));
+        });
+        exceeded = running;
+    }
+}
+
+/// Starts the clock of the request of this thread. This is called when a
+/// request starts.
+pub(crate) fn start_request() {
+    let watchdogs = WATCHDOGS.read().len();
+    if watchdogs == 0 {
+        return;
+    }
+
+    STARTED.with(|started| started.set(Some(Instant::now())));
+    REPORTED.with(|reported| *reported.borrow_mut() = vec![false; watchdogs]);
+}
+
+/// Stops watching the request of this thread. This is called when a request
+/// ends.
+pub(crate) fn finish_request() {
+    STARTED.with(|started| started.set(None));
+}
+
+/// Reports the request of this thread to the watchdogs whose threshold it
+/// exceeded.
+fn check_request(frame: &ExecuteData) {
+    let Some(started) = STARTED.with(Cell::get) else {
+        return;
+    };
+    let elapsed = started.elapsed();
+
+    let mut snapshot: Option<SlowRequest> = None;
+    // Handlers may execute PHP code, which may be interrupted in turn.
+    let watchdogs = WATCHDOGS.read_recursive();
+    for (index, (threshold, handler)) in watchdogs.iter().enumerate() {
+        if elapsed < *threshold {
+            continue;
+        }
+        let reported = REPORTED.with(|reported| {
+            reported
+                .borrow_mut()
+                .get_mut(index)
+                .map(|reported| std::mem::replace(reported, true))
+                .unwrap_or(true)
+        });
+        if reported {
+            continue;
+        }
+
+        let snapshot = snapshot.get_or_insert_with(|| {
+            let globals = SapiGlobals::get();
+            let info = globals.request_info();
+            SlowRequest {
+                threshold: *threshold,
+                elapsed,
+                method: info.request_method().map(ToString::to_string),
+                uri: info.request_uri().map(ToString::to_string),
+                memory: MemoryStats::capture(),
+                backtrace: backtrace::capture_from(frame),
+            }
+        });
+        snapshot.threshold = *threshold;
+        catch_panic_or((), || handler(snapshot));
+    }
+}
@@ -0,0 +1,550 @@
+//! Background workers spawned from a request, running Rust code on other
+//! threads while the request goes on.
+//!
+//! A worker is spawned with [`spawn`], and returns a [`Worker`] handle to its
+//! result, which is delivered back into the request:
+//!
+//! * by joining the worker with [`Worker::join`], which suspends the current
+//!   fiber until the worker completes, like the [futures](crate::future) of
+//!   the crate, or blocks the request outside of a fiber,
+//! * by returning a [`Promise`] to PHP, whose `wait()` method joins the
+//!   worker the same way,
+//! * or by calling a callback registered with [`Worker::on_complete`] at the
+//!   next yield point, i.e. the next call to [`yield_point`], e.g. from the
+//!   PHP function returned by [`yield_function`].
+//!
+//! Workers spawned during a request are cancelled when the request shuts
+//! down: workers which did not start yet never run, running workers can stop
+//! early by checking [`WorkerContext::is_cancelled`], and the callbacks which
+//! were not called yet are dropped.
+//!
+//! # Example
+//!
+//! ```rust,no_run
+//! # #![cfg_attr(windows, feature(abi_vectorcall))]
+//! # extern crate ext_php_rs;
+//! use ext_php_rs::{prelude::*, worker::{self, Promise}};
+//!
+//! #[php_function]
+//! pub fn checksum(data: String) -> Promise {
+//!     worker::spawn(move |_| data.bytes().map(u64::from).sum::<u64>()).into_promise()
+//! }
+//!
+//! #[php_module]
+//! pub fn get_module(module: ModuleBuilder) -> ModuleBuilder {
+//!     module.function(wrap_function!(checksum))
+//! }
+//! # fn main() {}
+//! ```
+//!
+//! ```php
+//! $promise = checksum(file_get_contents('large.bin'));
+//! // ...
+//! echo $promise->wait();
+//! ```
+
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fmt::{self, Debug},
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
+    task::{Context, Poll},
+    thread,
+};
+
+use crate::{
+    builders::{ClassBuilder, FunctionBuilder},
+    class::{ClassMetadata, RegisteredClass},
+    convert::IntoZval,
+    describe::DocComments,
+    dispatcher::{self, Dispatched},
+    error::{Error, Result},
+    exception::{catch_panic_or, PhpException},
+    flags::{DataType, MethodFlags},
+    future::poll_to_completion,
+    internal::property::PropertyInfo,
+    types::Zval,
+    zend::{lifecycle, ClassEntry, ExecuteData},
+    zend_fastcall,
+};
+
+/// Class entry and handlers for promises.
+static PROMISE_META: ClassMetadata<Promise> = ClassMetadata::new();
+
+thread_local! {
+    /// The workers spawned by the request of this thread, cancelled when it
+    /// shuts down.
+    static REQUEST_WORKERS: RefCell<Vec<Weak<AtomicBool>>> = const { RefCell::new(Vec::new()) };
+    /// The callbacks waiting for a worker to complete, called at yield points.
+    static COMPLETIONS: RefCell<Vec<Box<dyn Completion>>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Spawns a worker running a closure on a background thread.
+///
+/// The closure receives the [`WorkerContext`] of the worker. When spawned
+/// during a request, the worker is cancelled when the request shuts down.
+/// Dropping the returned handle detaches the worker, which keeps running.
+///
+/// # Parameters
+///
+/// * `f` - The closure to run.
+pub fn spawn<F, R>(f: F) -> Worker<R>
+where
+    F: FnOnce(&WorkerContext) -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let (sender, result) = dispatcher::channel();
+    let context = WorkerContext::default();
+    if lifecycle::in_request() {
+        REQUEST_WORKERS.with(|workers| {
+            let mut workers = workers.borrow_mut();
+            workers.retain(|worker| worker.strong_count() > 0);
+            workers.push(Arc::downgrade(&context.cancelled));
+        });
+    }
+
+    let worker_context = context.clone();
+    // A worker which fails to spawn drops its sender, and fails to join.
+    let _ = thread::Builder::new()
+        .name("php-worker".into())
+        .spawn(move || {
+            if worker_context.is_cancelled() {
+                return;
+            }
+            if let Ok(value) = panic::catch_unwind(AssertUnwindSafe(|| f(&worker_context))) {
+                sender.send(value);
+            }
+        });
+
+    Worker { result, context }
+}
+
+/// Calls the callbacks registered with [`Worker::on_complete`] of the workers
+/// which completed since the last yield point, on the current thread.
+///
+/// Returns the number of callbacks called.
+pub fn yield_point() -> usize {
+    // Callbacks may register callbacks of their own, which are kept for the
+    // next yield point.
+    let mut completions = COMPLETIONS.with(|completions| completions.take());
+    let before = completions.len();
+    completions.retain_mut(|completion| !completion.try_complete());
+    let called = before - completions.len();
+
+    COMPLETIONS.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        completions.append(&mut pending);
+        *pending = completions;
+    });
+    called
+}
+
+/// Returns a function calling [`yield_point`], to be added to the module with
+/// [`ModuleBuilder::function`](crate::builders::ModuleBuilder::function). The
+/// function returns the number of callbacks called.
+///
+/// # Parameters
+///
+/// * `name` - The name of the function.
+pub fn yield_function(name: &str) -> FunctionBuilder<'static> {
+    FunctionBuilder::new(name, yield_handler).returns(DataType::Long, false, false)
+}
+
+#[cfg(not(windows))]
+extern "C" fn yield_handler(_: &mut ExecuteData, retval: &mut Zval) {
+    retval.set_long(yield_point() as i64);
+}
+
+#[cfg(windows)]
+extern "vectorcall" fn yield_handler(_: &mut ExecuteData, retval: &mut Zval) {
+    retval.set_long(yield_point() as i64);
+}
+
+/// Cancels the workers spawned by the request of this thread, and drops the
+/// callbacks which were not called. This is called when a request ends.
+pub(crate) fn cancel_request() {
+    for worker in REQUEST_WORKERS.with(|workers| workers.take()) {
+        if let Some(cancelled) = worker.upgrade() {
+            cancelled.store(true, Ordering::Release);
+        }
+    }
+    drop(COMPLETIONS.with(|completions| completions.take()));
+}
+
+/// The context of a worker, given to the closure it runs.
+#[derive(Debug, Clone, Default)]
+pub struct WorkerContext {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl WorkerContext {
+    /// Returns whether the worker was cancelled, either with
+    /// [`Worker::cancel`] or because the request which spawned it shut down.
+    /// The result of a cancelled worker is still delivered if it completes.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+}
+
+/// A handle to the result of a worker spawned with [`spawn`].
+///
+/// The handle is also a future, resolving to the result of the worker.
+pub struct Worker<R> {
+    result: Dispatched<R>,
+    context: WorkerContext,
+}
+
+impl<R> Worker<R> {
+    /// Returns whether the worker completed, or will never complete.
+    pub fn is_ready(&self) -> bool {
+        self.result.is_ready()
+    }
+
+    /// Cancels the worker. A worker which did not start yet never runs, and a
+    /// running worker stops early if it checks
+    /// [`WorkerContext::is_cancelled`].
+    pub fn cancel(&self) {
+        self.context.cancelled.store(true, Ordering::Release);
+    }
+
+    /// Returns the result of the worker if it completed, without blocking.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::WorkerFailed`] if the worker panicked, or was cancelled
+    /// before it started.
+    pub fn try_join(&mut self) -> Result<Option<R>> {
+        self.result.try_wait().map_err(|_| Error::WorkerFailed)
+    }
+
+    /// Waits for the worker to complete, suspending the current fiber, or
+    /// blocking the thread outside of a fiber, and returns its result.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::WorkerFailed`] if the worker panicked, or was cancelled
+    /// before it started, or an error if the fiber was destroyed while
+    /// suspended.
+    pub fn join(self) -> Result<R> {
+        poll_to_completion(self, |worker, cx| worker.poll(cx))?
+    }
+
+    /// Registers a callback called with the result of the worker at the first
+    /// yield point after it completes, on the current thread. The callback is
+    /// dropped without being called if the request shuts down first. A panic
+    /// of the callback is reported as a warning.
+    ///
+    /// # Parameters
+    ///
+    /// * `callback` - The callback.
+    pub fn on_complete<F>(self, callback: F)
+    where
+        F: FnOnce(Result<R>) + 'static,
+        R: 'static,
+    {
+        COMPLETIONS.with(|completions| {
+            completions.borrow_mut().push(Box::new(OnComplete {
+                worker: self,
+                callback: Some(callback),
+            }))
+        });
+    }
+
+    /// Converts the worker into a promise, which can be returned to PHP.
+    pub fn into_promise(self) -> Promise
+    where
+        R: IntoZval + 'static,
+    {
+        Promise {
+            state: PromiseState::Pending(Box::new(self)),
+        }
+    }
+}
+
+impl<R> Future for Worker<R> {
+    type Output = Result<R>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.result)
+            .poll(cx)
+            .map_err(|_| Error::WorkerFailed)
+    }
+}
+
+impl<R> Debug for Worker<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Worker")
+            .field("ready", &self.is_ready())
+            .field("cancelled", &self.context.is_cancelled())
+            .finish()
+    }
+}
+
+/// A callback waiting for a worker to complete.
+trait Completion {
+    /// Calls the callback if the worker completed, and returns whether it
+    /// did.
+    fn try_complete(&mut self) -> bool;
+}
+
+struct OnComplete<R, F> {
+    worker: Worker<R>,
+    callback: Option<F>,
+}
+
+impl<R, F> Completion for OnComplete<R, F>
+where
+    F: FnOnce(Result<R>),
+{
+    fn try_complete(&mut self) -> bool {
+        let result = match self.worker.try_join() {
+            Ok(None) => return false,
+            Ok(Some(value)) => Ok(value),
+            Err(e) => Err(e),
+        };
+        if let Some(callback) = self.callback.take() {
+            // The callback runs inside a PHP function, which cannot unwind.
+            catch_panic_or((), || callback(result));
+        }
+        true
+    }
+}
+
+/// A worker whose result is converted into a PHP value.
+trait PendingValue {
+    fn is_ready(&self) -> bool;
+    fn cancel(&self);
+    fn join(self: Box<Self>) -> Result<Zval>;
+}
+
+impl<R: IntoZval> PendingValue for Worker<R> {
+    fn is_ready(&self) -> bool {
+        Worker::is_ready(self)
+    }
+
+    fn cancel(&self) {
+        Worker::cancel(self);
+    }
+
+    fn join(self: Box<Self>) -> Result<Zval> {
+        Worker::join(*self)?.into_zval(false)
+    }
+}
+
+enum PromiseState {
+    Pending(Box<dyn PendingValue>),
+    Waiting,
+    Fulfilled(Zval),
+    Rejected(String),
+}
+
+/// The result of a worker, returned to PHP.
+///
+/// Internally, promises are implemented as a PHP class. A class `RustPromise`
+/// is registered with the following methods:
+///
+/// ```php
+/// <?php
+///
+/// class RustPromise {
+///     // Waits for the worker, suspending the current fiber, and returns its
+///     // result, or throws if it failed.
+///     public function wait(): mixed {}
+///     // Returns whether the worker completed.
+///     public function isReady(): bool {}
+///     // Cancels the worker.
+///     public function cancel(): void {}
+/// }
+/// ```
+pub struct Promise {
+    state: PromiseState,
+}
+
+// SAFETY: Promises are only sent to PHP, on the thread of the request, like
+// closures.
+unsafe impl Send for Promise {}
+unsafe impl Sync for Promise {}
+
+impl Promise {
+    /// Builds the class entry for [`Promise`], registering it with PHP. This
+    /// function should only be called once inside your module startup
+    /// function.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the function is called more than once.
+    pub fn build() {
+        if PROMISE_META.has_ce() {
+            panic!("Promise has already been built.");
+        }
+
+        ClassBuilder::new("RustPromise")
+            .method(
+                FunctionBuilder::new("wait", Self::wait).returns(DataType::Mixed, false, true),
+                MethodFlags::Public,
+            )
+            .method(
+                FunctionBuilder::new("isReady", Self::is_ready).returns(
+                    DataType::Bool,
+                    false,
+                    false,
+                ),
+                MethodFlags::Public,
+            )
+            .method(
+                FunctionBuilder::new("cancel", Self::cancel).returns(DataType::Void, false, false),
+                MethodFlags::Public,
+            )
+            .object_override::<Self>()
+            .registration(|ce| PROMISE_META.set_ce(ce))
+            .register()
+            .expect("Failed to build `RustPromise` PHP class.");
+    }
+
+    /// Waits for the worker and records its result, unless it was already
+    /// recorded.
+    fn settle(&mut self) {
+        if let PromiseState::Pending(_) = self.state {
+            let PromiseState::Pending(worker) =
+                std::mem::replace(&mut self.state, PromiseState::Waiting)
+            else {
+                unreachable!();
+            };
+            self.state = match worker.join() {
+                Ok(value) => PromiseState::Fulfilled(value),
+                Err(e) => PromiseState::Rejected(e.to_string()),
+            };
+        }
+    }
+
+    zend_fastcall! {
+        extern "C" fn wait(ex: &mut ExecuteData, ret: &mut Zval) {
+            let (_, this) = ex.parser_method::<Self>();
+            let this = this.expect("Promise method called on non-promise class");
+            // The fiber waiting on the worker may be resumed by another one,
+            // which must not wait on it as well.
+            this.settle();
+            let exception = match &this.state {
+                PromiseState::Fulfilled(value) => {
+                    *ret = value.shallow_clone();
+                    return;
+                }
+                PromiseState::Rejected(message) => PhpException::default(message.clone()),
+                _ => PhpException::default("The promise is already awaited.".into()),
+            };
+            let _ = exception.throw();
+        }
+    }
+
+    zend_fastcall! {
+        extern "C" fn is_ready(ex: &mut ExecuteData, ret: &mut Zval) {
+            let (_, this) = ex.parser_method::<Self>();
+            let this = this.expect("Promise method called on non-promise class");
+            ret.set_bool(match &this.state {
+                PromiseState::Pending(worker) => worker.is_ready(),
+                PromiseState::Waiting => false,
+                PromiseState::Fulfilled(_) | PromiseState::Rejected(_) => true,
+            });
+        }
+    }
+
+    zend_fastcall! {
+        extern "C" fn cancel(ex: &mut ExecuteData, _: &mut Zval) {
+            let (_, this) = ex.parser_method::<Self>();
+            let this = this.expect("Promise method called on non-promise class");
+            if let PromiseState::Pending(worker) = &this.state {
+                worker.cancel();
+            }
+        }
+    }
+}
+
+impl Debug for Promise {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = match &self.state {
+            PromiseState::Pending(_) => "pending",
+            PromiseState::Waiting => "waiting",
+            PromiseState::Fulfilled(_) => "fulfilled",
+            PromiseState::Rejected(_) => "rejected",
+        };
+        f.debug_struct("Promise").field("state", &state).finish()
+    }
+}
+
+impl RegisteredClass for Promise {
+    const CLASS_NAME: &'static str = "RustPromise";
+
+    const BUILDER_MODIFIER: Option<fn(ClassBuilder) -> ClassBuilder> = None;
+    const EXTENDS: Option<fn() -> &'static ClassEntry> = None;
+    const IMPLEMENTS: &'static [fn() -> &'static ClassEntry] = &[];
+
+    fn get_metadata() -> &'static ClassMetadata<Self> {
+        &PROMISE_META
+    }
+
+    fn get_properties<'a>() -> HashMap<&'static str, PropertyInfo<'a, Self>> {
+        HashMap::new()
+    }
+
+    fn method_builders() -> Vec<(FunctionBuilder<'static>, MethodFlags)> {
+        Vec::new()
+    }
+
+    fn constructor() -> Option<crate::class::ConstructorMeta<Self>> {
+        None
+    }
+
+    fn constants() -> &'static [(
+        &'static str,
+        &'static dyn crate::convert::IntoZvalDyn,
+        DocComments,
+    )] {
+        &[]
+    }
+}
+
+class_derives!(Promise);
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc, time::Duration};
+
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_worker() {
+        Embed::run(|| {
+            assert_eq!(spawn(|_| 1 + 2).join().ok(), Some(3));
+            assert!(spawn(|_| panic!("worker")).join().is_err());
+
+            let called = Rc::new(Cell::new(false));
+            let worker = spawn(|_| "done");
+            while !worker.is_ready() {
+                thread::yield_now();
+            }
+            let on_complete = called.clone();
+            worker.on_complete(move |result| {
+                assert_eq!(result.ok(), Some("done"));
+                on_complete.set(true);
+            });
+            assert_eq!(yield_point(), 1);
+            assert!(called.get());
+
+            let slow = spawn(|context| {
+                while !context.is_cancelled() {
+                    thread::sleep(Duration::from_millis(1));
+                }
+            });
+            slow.cancel();
+            assert!(slow.join().is_ok());
+        });
+    }
+}
@@ -1,5 +1,21 @@
 #include "wrapper.h"
 
+#if defined(ZTS) && defined(ZEND_ENABLE_STATIC_TSRMLS_CACHE)
+ZEND_TSRMLS_CACHE_DEFINE()
+
+// The cache is a thread local, filled on the first access of each thread to
+// its globals, as threads of the SAPI may be spawned without notifying the
+// extension.
+#define EXT_PHP_RS_TSRMLS_CACHE_ENSURE()                                       \
+  do {                                                                         \
+    if (!TSRMLS_CACHE) {                                                       \
+      ZEND_TSRMLS_CACHE_UPDATE();                                              \
+    }                                                                          \
+  } while (0)
+#else
+#define EXT_PHP_RS_TSRMLS_CACHE_ENSURE()
+#endif
+
 zend_string *ext_php_rs_zend_string_init(const char *str, size_t len, bool persistent) {
   return zend_string_init(str, len, persistent);
 }
@@ -18,6 +34,21 @@ void ext_php_rs_set_known_valid_utf8(zend_string *zs) {
   }
 }
 
+zend_string *ext_php_rs_zend_string_init_permanent(const char *str, size_t len) {
+  zend_string *zs = zend_string_init_interned(str, len, 1);
+  if (ZSTR_IS_INTERNED(zs) && (GC_FLAGS(zs) & IS_STR_PERMANENT)) {
+    return zs;
+  }
+
+  // Outside of module startup the interned string table is request-bound, so
+  // build a persistent string that behaves like a permanent interned one.
+  zs = zend_string_init(str, len, 1);
+  zend_string_hash_val(zs);
+  GC_SET_REFCOUNT(zs, 1);
+  GC_ADD_FLAGS(zs, IS_STR_INTERNED | IS_STR_PERMANENT);
+  return zs;
+}
+
 const char *ext_php_rs_php_build_id() { return ZEND_MODULE_BUILD_ID; }
 
 void *ext_php_rs_zend_object_alloc(size_t obj_size, zend_class_entry *ce) {
@@ -28,9 +59,16 @@ void ext_php_rs_zend_object_release(zend_object *obj) {
   zend_object_release(obj);
 }
 
+void ext_php_rs_zval_make_ref(zval *zv) { ZVAL_MAKE_REF(zv); }
+
+void *ext_php_rs_pemalloc(size_t size) { return pemalloc(size, 1); }
+
+void ext_php_rs_pefree(void *ptr) { pefree(ptr, 1); }
+
 zend_executor_globals *ext_php_rs_executor_globals() {
 #ifdef ZTS
 #ifdef ZEND_ENABLE_STATIC_TSRMLS_CACHE
+  EXT_PHP_RS_TSRMLS_CACHE_ENSURE();
   return TSRMG_FAST_BULK_STATIC(executor_globals_offset, zend_executor_globals);
 #else
   return TSRMG_FAST_BULK(executor_globals_offset, zend_executor_globals *);
@@ -43,6 +81,7 @@ zend_executor_globals *ext_php_rs_executor_globals() {
 zend_compiler_globals *ext_php_rs_compiler_globals() {
 #ifdef ZTS
 #ifdef ZEND_ENABLE_STATIC_TSRMLS_CACHE
+  EXT_PHP_RS_TSRMLS_CACHE_ENSURE();
   return TSRMG_FAST_BULK_STATIC(compiler_globals_offset, zend_compiler_globals);
 #else
   return TSRMG_FAST_BULK(compiler_globals_offset, zend_compiler_globals *);
@@ -55,6 +94,7 @@ zend_compiler_globals *ext_php_rs_compiler_globals() {
 php_core_globals *ext_php_rs_process_globals() {
 #ifdef ZTS
 #ifdef ZEND_ENABLE_STATIC_TSRMLS_CACHE
+  EXT_PHP_RS_TSRMLS_CACHE_ENSURE();
   return TSRMG_FAST_BULK_STATIC(core_globals_offset, php_core_globals);
 #else
   return TSRMG_FAST_BULK(core_globals_offset, php_core_globals *);
@@ -67,6 +107,7 @@ php_core_globals *ext_php_rs_process_globals() {
 sapi_globals_struct *ext_php_rs_sapi_globals() {
 #ifdef ZTS
 #ifdef ZEND_ENABLE_STATIC_TSRMLS_CACHE
+  EXT_PHP_RS_TSRMLS_CACHE_ENSURE();
   return TSRMG_FAST_BULK_STATIC(sapi_globals_offset, sapi_globals_struct);
 #else
   return TSRMG_FAST_BULK(sapi_globals_offset, sapi_globals_struct *);
@@ -78,7 +119,12 @@ sapi_globals_struct *ext_php_rs_sapi_globals() {
 
 php_file_globals *ext_php_rs_file_globals() {
 #ifdef ZTS
-  return TSRMG_FAST_BULK(file_globals_id, php_file_globals *);
+#ifdef ZEND_ENABLE_STATIC_TSRMLS_CACHE
+  EXT_PHP_RS_TSRMLS_CACHE_ENSURE();
+  return TSRMG_BULK_STATIC(file_globals_id, php_file_globals *);
+#else
+  return TSRMG_BULK(file_globals_id, php_file_globals *);
+#endif
 #else
   return &file_globals;
 #endif
@@ -89,6 +135,9 @@ sapi_module_struct *ext_php_rs_sapi_module() {
 }
 
 bool ext_php_rs_zend_try_catch(void* (*callback)(void *), void *ctx, void **result) {
+  // `zend_try` reads `EG(bailout)` through the cache.
+  EXT_PHP_RS_TSRMLS_CACHE_ENSURE();
+
   zend_try {
     *result = callback(ctx);
   } zend_catch {
@@ -99,6 +148,8 @@ bool ext_php_rs_zend_try_catch(void* (*callback)(void *), void *ctx, void **resu
 }
 
 bool ext_php_rs_zend_first_try_catch(void* (*callback)(void *), void *ctx, void **result) {
+  EXT_PHP_RS_TSRMLS_CACHE_ENSURE();
+
   zend_first_try {
     *result = callback(ctx);
   } zend_catch {
@@ -112,6 +163,14 @@ void ext_php_rs_zend_bailout() {
   zend_bailout();
 }
 
+php_stream *ext_php_rs_stream_alloc(const php_stream_ops *ops, void *abstract, const char *mode) {
+  return php_stream_alloc(ops, abstract, NULL, mode);
+}
+
+php_stream_filter *ext_php_rs_stream_filter_alloc(const php_stream_filter_ops *fops, void *abstract, uint8_t persistent) {
+  return php_stream_filter_alloc(fops, abstract, persistent);
+}
+
 #include <sapi/embed/php_embed.h>
 
 // We actually use the PHP embed API to run PHP code in test
@@ -119,11 +178,21 @@ void ext_php_rs_zend_bailout() {
 SAPI_API void* ext_php_rs_embed_callback(int argc, char** argv, void* (*callback)(void *), void *ctx) {
   void *result = NULL;
 
-  PHP_EMBED_START_BLOCK(argc, argv)
+  // Expanded from `PHP_EMBED_START_BLOCK` and `PHP_EMBED_END_BLOCK`, to
+  // refresh the cache once the embed SAPI started PHP up on this thread, and
+  // reset it once PHP was shut down.
+  php_embed_init(argc, argv);
+  EXT_PHP_RS_TSRMLS_CACHE_ENSURE();
 
-  result = callback(ctx);
+  zend_first_try {
+    result = callback(ctx);
+  } zend_catch {
+  } zend_end_try();
 
-  PHP_EMBED_END_BLOCK()
+  php_embed_shutdown();
+  #if defined(ZTS) && defined(ZEND_ENABLE_STATIC_TSRMLS_CACHE)
+    TSRMLS_CACHE = NULL;
+  #endif
 
   return result;
 }
@@ -136,7 +205,7 @@ SAPI_API void ext_php_rs_sapi_startup() {
   #ifdef ZTS
     php_tsrm_startup();
     // php_tsrm_startup_ex(4);
-    #ifdef PHP_WIN32
+    #ifdef ZEND_ENABLE_STATIC_TSRMLS_CACHE
       ZEND_TSRMLS_CACHE_UPDATE();
     #endif
   #endif
@@ -147,22 +216,58 @@ SAPI_API void ext_php_rs_sapi_startup() {
 SAPI_API void ext_php_rs_sapi_shutdown() {
   #ifdef ZTS
    	tsrm_shutdown();
+    #ifdef ZEND_ENABLE_STATIC_TSRMLS_CACHE
+      TSRMLS_CACHE = NULL;
+    #endif
   #endif
 }
 
 SAPI_API void ext_php_rs_sapi_per_thread_init() {
   #ifdef ZTS
     (void)ts_resource(0);
-    #ifdef PHP_WIN32
+    #ifdef ZEND_ENABLE_STATIC_TSRMLS_CACHE
       ZEND_TSRMLS_CACHE_UPDATE();
     #endif
   #endif
 }
 
+SAPI_API void ext_php_rs_sapi_per_thread_shutdown() {
+  #ifdef ZTS
+    ts_free_thread();
+    #ifdef ZEND_ENABLE_STATIC_TSRMLS_CACHE
+      TSRMLS_CACHE = NULL;
+    #endif
+  #endif
+}
+
+bool ext_php_rs_tsrm_is_main_thread() {
+  #ifdef ZTS
+    return tsrm_is_main_thread();
+  #else
+    return true;
+  #endif
+}
+
+static ext_php_rs_sapi_error_handler sapi_error_handler = NULL;
+
+SAPI_API void ext_php_rs_set_sapi_error_handler(ext_php_rs_sapi_error_handler handler) {
+  sapi_error_handler = handler;
+}
+
 SAPI_API void ext_php_rs_php_error(int type, const char *format, ...) {
   va_list args;
+  char *message = NULL;
+  size_t len;
+
+  // Rust cannot receive a `va_list`, so the message is formatted here.
   va_start(args, format);
-  php_error(type, format, args);
-  vprintf(format, args);
+  len = vspprintf(&message, 0, format, args);
   va_end(args);
+
+  if (sapi_error_handler) {
+    sapi_error_handler(type, message, len);
+  } else {
+    php_error(type, "%s", message);
+  }
+  efree(message);
 }
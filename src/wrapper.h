@@ -20,6 +20,11 @@
 #include "ext/standard/info.h"
 #include "ext/standard/php_var.h"
 #include "ext/standard/file.h"
+#include "ext/standard/php_filestat.h"
+#ifdef EXT_PHP_RS_SESSION
+#include "ext/session/php_session.h"
+#endif
+#include "ext/spl/spl_exceptions.h"
 #include "zend_exceptions.h"
 #include "zend_inheritance.h"
 #include "zend_interfaces.h"
@@ -36,10 +41,14 @@ zend_string *ext_php_rs_zend_string_init(const char *str, size_t len, bool persi
 void ext_php_rs_zend_string_release(zend_string *zs);
 bool ext_php_rs_is_known_valid_utf8(const zend_string *zs);
 void ext_php_rs_set_known_valid_utf8(zend_string *zs);
+zend_string *ext_php_rs_zend_string_init_permanent(const char *str, size_t len);
 
 const char *ext_php_rs_php_build_id();
 void *ext_php_rs_zend_object_alloc(size_t obj_size, zend_class_entry *ce);
 void ext_php_rs_zend_object_release(zend_object *obj);
+void ext_php_rs_zval_make_ref(zval *zv);
+void *ext_php_rs_pemalloc(size_t size);
+void ext_php_rs_pefree(void *ptr);
 zend_executor_globals *ext_php_rs_executor_globals();
 zend_compiler_globals *ext_php_rs_compiler_globals();
 php_core_globals *ext_php_rs_process_globals();
@@ -49,17 +58,19 @@ sapi_module_struct *ext_php_rs_sapi_module();
 bool ext_php_rs_zend_try_catch(void* (*callback)(void *), void *ctx, void **result);
 bool ext_php_rs_zend_first_try_catch(void* (*callback)(void *), void *ctx, void **result);
 void ext_php_rs_zend_bailout();
+php_stream *ext_php_rs_stream_alloc(const php_stream_ops *ops, void *abstract, const char *mode);
+php_stream_filter *ext_php_rs_stream_filter_alloc(const php_stream_filter_ops *fops, void *abstract, uint8_t persistent);
 
 #include "zend.h"
 #include "sapi/embed/php_embed.h"
 
-#if defined(ZTS) && defined(PHP_WIN32)
-ZEND_TSRMLS_CACHE_DEFINE()
-#endif
-
 SAPI_API void* ext_php_rs_embed_callback(int argc, char** argv, void* (*callback)(void *), void *ctx);
 SAPI_API void ext_php_rs_sapi_startup();
 SAPI_API void ext_php_rs_sapi_shutdown();
 SAPI_API void ext_php_rs_sapi_per_thread_init();
+SAPI_API void ext_php_rs_sapi_per_thread_shutdown();
+bool ext_php_rs_tsrm_is_main_thread();
 
+typedef void (*ext_php_rs_sapi_error_handler)(int type, const char *message, size_t len);
+SAPI_API void ext_php_rs_set_sapi_error_handler(ext_php_rs_sapi_error_handler handler);
 SAPI_API void ext_php_rs_php_error(int type, const char* format, ...);
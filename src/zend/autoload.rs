@@ -0,0 +1,134 @@
+//! Class autoloading implemented in Rust.
+//!
+//! PHP resolves unknown classes through the `zend_autoload` hook, which is
+//! normally installed by SPL and dispatches to the callbacks registered with
+//! `spl_autoload_register()`. The functions in this module chain in front of
+//! that hook, giving Rust autoloaders the first chance to define a class
+//! before falling back to the userland autoloaders.
+
+use std::{ptr, sync::Arc};
+
+use parking_lot::{const_rwlock, RwLock};
+
+use crate::{
+    error::{Error, Result},
+    exception::catch_panic_or,
+    ffi::{zend_autoload, zend_class_entry, zend_hash_str_find_ptr_lc, zend_string},
+    types::ZendStr,
+    zend::{ClassEntry, ExecutorGlobals},
+};
+
+type Autoloader = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+type AutoloadHook =
+    unsafe extern "C" fn(*mut zend_string, *mut zend_string) -> *mut zend_class_entry;
+
+static AUTOLOADERS: RwLock<Vec<Autoloader>> = const_rwlock(Vec::new());
+static PREVIOUS_HOOK: RwLock<Option<Option<AutoloadHook>>> = const_rwlock(None);
+
+/// Registers an autoloader implemented in Rust.
+///
+/// The autoloader receives the name of the class being loaded, as written in
+/// the code which triggered the autoload, and returns whether it defined the
+/// class, for example by evaluating a generated proxy class. Autoloaders are
+/// called in registration order and before any autoloader registered with
+/// `spl_autoload_register()`.
+///
+/// This must be called during module startup, after SPL has installed its
+/// own hook, as the hook is shared by all threads.
+///
+/// # Parameters
+///
+/// * `autoloader` - The autoloader to register.
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidScope`] if called while PHP code is executing, e.g.
+/// from a function called by a script.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::zend::autoload::register_autoloader;
+///
+/// pub fn startup(_ty: i32, _module_number: i32) -> i32 {
+///     let registered = register_autoloader(|class| {
+///         if let Some(name) = class.strip_prefix("Proxy\\") {
+///             // Define the proxy class for `name`...
+///             return true;
+///         }
+///         false
+///     });
+///     if registered.is_ok() { 0 } else { 1 }
+/// }
+/// ```
+pub fn register_autoloader<F>(autoloader: F) -> Result<()>
+where
+    F: Fn(&str) -> bool + Send + Sync + 'static,
+{
+    if !ExecutorGlobals::get().current_execute_data.is_null() {
+        return Err(Error::InvalidScope);
+    }
+
+    {
+        let mut previous = PREVIOUS_HOOK.write();
+        if previous.is_none() {
+            // SAFETY: The hook is only replaced during module startup, which is
+            // single threaded, as no PHP code is executing.
+            unsafe {
+                *previous = Some(zend_autoload);
+                zend_autoload = Some(autoload_dispatcher);
+            }
+        }
+    }
+
+    AUTOLOADERS.write().push(Arc::new(autoloader));
+    Ok(())
+}
+
+/// Looks up an already defined class by its lowercase name without
+/// triggering the autoloader.
+fn find_loaded(lc_name: &ZendStr) -> Option<&'static ClassEntry> {
+    let class_table = ExecutorGlobals::get().class_table;
+    if class_table.is_null() {
+        return None;
+    }
+
+    unsafe {
+        (zend_hash_str_find_ptr_lc(
+            class_table,
+            lc_name.as_bytes().as_ptr().cast(),
+            lc_name.len(),
+        ) as *const ClassEntry)
+            .as_ref()
+    }
+}
+
+unsafe extern "C" fn autoload_dispatcher(
+    name: *mut zend_string,
+    lc_name: *mut zend_string,
+) -> *mut zend_class_entry {
+    if let (Some(class), Some(lc_name)) = (name.as_ref(), lc_name.as_ref()) {
+        if let Ok(class) = class.as_str() {
+            // Autoloaders are cloned out of the lock so they can register other
+            // autoloaders, or trigger further autoloads, while being called.
+            let autoloaders: Vec<Autoloader> = AUTOLOADERS.read().iter().cloned().collect();
+            for autoloader in autoloaders.iter() {
+                let loaded = catch_panic_or(false, || autoloader(class));
+                if ExecutorGlobals::has_exception() {
+                    return ptr::null_mut();
+                }
+                if loaded {
+                    if let Some(ce) = find_loaded(lc_name) {
+                        return (ce as *const ClassEntry).cast_mut();
+                    }
+                }
+            }
+        }
+    }
+
+    let previous = *PREVIOUS_HOOK.read();
+    match previous {
+        Some(Some(previous)) => previous(name, lc_name),
+        _ => ptr::null_mut(),
+    }
+}
@@ -0,0 +1,125 @@
+//! Backtraces of the PHP code being executed.
+
+use std::fmt::{self, Display};
+
+use crate::zend::{ExecuteData, ExecutorGlobals};
+
+/// A frame of a PHP backtrace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackFrame {
+    /// The name of the function, or [`None`] for the main script of a file
+    /// and for evaluated code.
+    pub function: Option<String>,
+    /// The name of the class the function is a method of.
+    pub class: Option<String>,
+    /// The file being executed, or [`None`] for internal functions.
+    pub file: Option<String>,
+    /// The line being executed, or [`None`] for internal functions.
+    pub line: Option<u32>,
+}
+
+impl StackFrame {
+    /// Creates a frame from the execute data of a call, or returns [`None`]
+    /// for the frames the engine pushes without a function.
+    pub fn from_execute_data(ex: &ExecuteData) -> Option<Self> {
+        let function = ex.function()?;
+        Some(Self {
+            function: function.name().map(ToString::to_string),
+            class: function
+                .class()
+                .and_then(|class| class.name())
+                .map(ToString::to_string),
+            file: ex
+                .lineno()
+                .and_then(|_| function.filename())
+                .map(ToString::to_string),
+            line: ex.lineno(),
+        })
+    }
+
+    /// Returns the qualified name of the function, e.g. `App\Foo::bar`, or
+    /// `{main}` for the main script of a file and for evaluated code.
+    pub fn name(&self) -> String {
+        match (&self.class, &self.function) {
+            (Some(class), Some(function)) => format!("{class}::{function}"),
+            (None, Some(function)) => function.clone(),
+            _ => "{main}".to_string(),
+        }
+    }
+}
+
+impl Display for StackFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.function {
+            Some(_) => write!(f, "{}()", self.name())?,
+            None => write!(f, "{}", self.name())?,
+        }
+        if let (Some(file), Some(line)) = (&self.file, self.line) {
+            write!(f, " at {file}:{line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Captures the backtrace of the PHP code executed on this thread, innermost
+/// frame first.
+///
+/// # Returns
+///
+/// The frames, which are empty when no PHP code is executing.
+pub fn capture() -> Vec<StackFrame> {
+    let current = ExecutorGlobals::get().current_execute_data;
+    match unsafe { current.as_ref() } {
+        Some(ex) => capture_from(ex),
+        None => vec![],
+    }
+}
+
+/// Captures the backtrace starting at the given frame, innermost frame first.
+///
+/// # Parameters
+///
+/// * `ex` - The innermost frame, e.g. the one passed to an observer.
+pub fn capture_from(ex: &ExecuteData) -> Vec<StackFrame> {
+    let mut frames = vec![];
+    let mut frame = Some(ex);
+    while let Some(ex) = frame {
+        frames.extend(StackFrame::from_execute_data(ex));
+        frame = ex.previous();
+    }
+    frames
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_stack_frame_display() {
+        let frame = StackFrame {
+            function: Some("bar".into()),
+            class: Some("App\\Foo".into()),
+            file: Some("/app/Foo.php".into()),
+            line: Some(12),
+        };
+        assert_eq!(frame.name(), "App\\Foo::bar");
+        assert_eq!(frame.to_string(), "App\\Foo::bar() at /app/Foo.php:12");
+
+        let frame = StackFrame {
+            function: Some("strlen".into()),
+            class: None,
+            file: None,
+            line: None,
+        };
+        assert_eq!(frame.to_string(), "strlen()");
+    }
+
+    #[test]
+    fn test_capture() {
+        Embed::run(|| {
+            assert!(capture().is_empty());
+        });
+    }
+}
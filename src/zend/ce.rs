@@ -3,12 +3,16 @@
 #![allow(clippy::unwrap_used)]
 
 use crate::ffi::{
-    zend_ce_aggregate, zend_ce_argument_count_error, zend_ce_arithmetic_error, zend_ce_arrayaccess,
-    zend_ce_compile_error, zend_ce_countable, zend_ce_division_by_zero_error,
-    zend_ce_error_exception, zend_ce_exception, zend_ce_iterator, zend_ce_parse_error,
-    zend_ce_serializable, zend_ce_stringable, zend_ce_throwable, zend_ce_traversable,
-    zend_ce_type_error, zend_ce_unhandled_match_error, zend_ce_value_error,
-    zend_standard_class_def,
+    spl_ce_BadFunctionCallException, spl_ce_BadMethodCallException, spl_ce_DomainException,
+    spl_ce_InvalidArgumentException, spl_ce_LengthException, spl_ce_LogicException,
+    spl_ce_OutOfBoundsException, spl_ce_OutOfRangeException, spl_ce_OverflowException,
+    spl_ce_RangeException, spl_ce_RuntimeException, spl_ce_UnderflowException,
+    spl_ce_UnexpectedValueException, zend_ce_aggregate, zend_ce_argument_count_error,
+    zend_ce_arithmetic_error, zend_ce_arrayaccess, zend_ce_compile_error, zend_ce_countable,
+    zend_ce_division_by_zero_error, zend_ce_error, zend_ce_error_exception, zend_ce_exception,
+    zend_ce_iterator, zend_ce_parse_error, zend_ce_serializable, zend_ce_stringable,
+    zend_ce_throwable, zend_ce_traversable, zend_ce_type_error, zend_ce_unhandled_match_error,
+    zend_ce_value_error, zend_standard_class_def,
 };
 
 use super::ClassEntry;
@@ -28,6 +32,11 @@ pub fn exception() -> &'static ClassEntry {
     unsafe { zend_ce_exception.as_ref() }.unwrap()
 }
 
+/// Returns the base [`Error`](https://www.php.net/manual/en/class.error.php) class.
+pub fn error() -> &'static ClassEntry {
+    unsafe { zend_ce_error.as_ref() }.unwrap()
+}
+
 /// Returns the base [`ErrorException`](https://www.php.net/manual/en/class.errorexception.php) class.
 pub fn error_exception() -> &'static ClassEntry {
     unsafe { zend_ce_error_exception.as_ref() }.unwrap()
@@ -107,3 +116,68 @@ pub fn countable() -> &'static ClassEntry {
 pub fn stringable() -> &'static ClassEntry {
     unsafe { zend_ce_stringable.as_ref() }.unwrap()
 }
+
+/// Returns the [`LogicException`](https://www.php.net/manual/en/class.logicexception.php) class.
+pub fn logic_exception() -> &'static ClassEntry {
+    unsafe { spl_ce_LogicException.as_ref() }.unwrap()
+}
+
+/// Returns the [`BadFunctionCallException`](https://www.php.net/manual/en/class.badfunctioncallexception.php) class.
+pub fn bad_function_call_exception() -> &'static ClassEntry {
+    unsafe { spl_ce_BadFunctionCallException.as_ref() }.unwrap()
+}
+
+/// Returns the [`BadMethodCallException`](https://www.php.net/manual/en/class.badmethodcallexception.php) class.
+pub fn bad_method_call_exception() -> &'static ClassEntry {
+    unsafe { spl_ce_BadMethodCallException.as_ref() }.unwrap()
+}
+
+/// Returns the [`DomainException`](https://www.php.net/manual/en/class.domainexception.php) class.
+pub fn domain_exception() -> &'static ClassEntry {
+    unsafe { spl_ce_DomainException.as_ref() }.unwrap()
+}
+
+/// Returns the [`InvalidArgumentException`](https://www.php.net/manual/en/class.invalidargumentexception.php) class.
+pub fn invalid_argument_exception() -> &'static ClassEntry {
+    unsafe { spl_ce_InvalidArgumentException.as_ref() }.unwrap()
+}
+
+/// Returns the [`LengthException`](https://www.php.net/manual/en/class.lengthexception.php) class.
+pub fn length_exception() -> &'static ClassEntry {
+    unsafe { spl_ce_LengthException.as_ref() }.unwrap()
+}
+
+/// Returns the [`OutOfRangeException`](https://www.php.net/manual/en/class.outofrangeexception.php) class.
+pub fn out_of_range_exception() -> &'static ClassEntry {
+    unsafe { spl_ce_OutOfRangeException.as_ref() }.unwrap()
+}
+
+/// Returns the [`RuntimeException`](https://www.php.net/manual/en/class.runtimeexception.php) class.
+pub fn runtime_exception() -> &'static ClassEntry {
+    unsafe { spl_ce_RuntimeException.as_ref() }.unwrap()
+}
+
+/// Returns the [`OutOfBoundsException`](https://www.php.net/manual/en/class.outofboundsexception.php) class.
+pub fn out_of_bounds_exception() -> &'static ClassEntry {
+    unsafe { spl_ce_OutOfBoundsException.as_ref() }.unwrap()
+}
+
+/// Returns the [`OverflowException`](https://www.php.net/manual/en/class.overflowexception.php) class.
+pub fn overflow_exception() -> &'static ClassEntry {
+    unsafe { spl_ce_OverflowException.as_ref() }.unwrap()
+}
+
+/// Returns the [`RangeException`](https://www.php.net/manual/en/class.rangeexception.php) class.
+pub fn range_exception() -> &'static ClassEntry {
+    unsafe { spl_ce_RangeException.as_ref() }.unwrap()
+}
+
+/// Returns the [`UnderflowException`](https://www.php.net/manual/en/class.underflowexception.php) class.
+pub fn underflow_exception() -> &'static ClassEntry {
+    unsafe { spl_ce_UnderflowException.as_ref() }.unwrap()
+}
+
+/// Returns the [`UnexpectedValueException`](https://www.php.net/manual/en/class.unexpectedvalueexception.php) class.
+pub fn unexpected_value_exception() -> &'static ClassEntry {
+    unsafe { spl_ce_UnexpectedValueException.as_ref() }.unwrap()
+}
@@ -4,12 +4,14 @@ use crate::ffi::instanceof_function_slow;
 use crate::types::{ZendIterator, Zval};
 use crate::{
     boxed::ZBox,
-    ffi::zend_class_entry,
-    flags::ClassFlags,
+    convert::IntoZvalDyn,
+    error::{Error, Result},
+    ffi::{zend_call_known_function, zend_class_entry, zend_function, zend_hash_str_find_ptr_lc},
+    flags::{ClassFlags, MethodFlags},
     types::{ZendObject, ZendStr},
     zend::ExecutorGlobals,
 };
-use std::{convert::TryInto, fmt::Debug, ops::DerefMut};
+use std::{convert::TryInto, fmt::Debug, ops::DerefMut, os::raw::c_char};
 
 /// A PHP class entry.
 ///
@@ -111,6 +113,77 @@ impl ClassEntry {
         unsafe { iterator.as_mut() }
     }
 
+    /// Attempts to call a static method on the class.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the static method.
+    /// * `params` - A list of parameters to call the method with.
+    ///
+    /// # Returns
+    ///
+    /// Returns the return value of the method, or an error if the method could
+    /// not be found, is not a public concrete static method, or an exception
+    /// was thrown while calling it. A thrown exception is cleared and returned as [`Error::Exception`].
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use ext_php_rs::zend::ClassEntry;
+    ///
+    /// let ce = ClassEntry::try_find("DateTime").unwrap();
+    /// let date = ce
+    ///     .try_call_static_method("createFromFormat", vec![&"Y-m-d", &"2024-01-01"])
+    ///     .unwrap();
+    /// assert!(date.is_object());
+    /// ```
+    pub fn try_call_static_method(
+        &self,
+        name: &str,
+        params: Vec<&dyn IntoZvalDyn>,
+    ) -> Result<Zval> {
+        let mut retval = Zval::new();
+        let len = params.len();
+        let params = params
+            .into_iter()
+            .map(|val| val.as_zval(false))
+            .collect::<Result<Vec<_>>>()?;
+        let packed = params.into_boxed_slice();
+
+        unsafe {
+            let func = zend_hash_str_find_ptr_lc(
+                &self.function_table,
+                name.as_ptr() as *const c_char,
+                name.len(),
+            ) as *mut zend_function;
+            if func.is_null() {
+                return Err(Error::Callable);
+            }
+            let flags = MethodFlags::from_bits_truncate((*func).common.fn_flags);
+            if !flags.contains(MethodFlags::Static)
+                || flags.intersects(
+                    MethodFlags::Private | MethodFlags::Protected | MethodFlags::Abstract,
+                )
+            {
+                return Err(Error::Callable);
+            }
+            zend_call_known_function(
+                func,
+                std::ptr::null_mut(),
+                self as *const _ as *mut _,
+                &mut retval,
+                len as _,
+                packed.as_ptr() as *mut _,
+                std::ptr::null_mut(),
+            )
+        };
+
+        match ExecutorGlobals::take_exception() {
+            Some(e) => Err(Error::Exception(e)),
+            None => Ok(retval),
+        }
+    }
+
     /// Gets the name of the class.
     pub fn name(&self) -> Option<&str> {
         unsafe { self.name.as_ref().and_then(|s| s.as_str().ok()) }
@@ -0,0 +1,292 @@
+//! Observers of the files compiled or included by PHP code.
+//!
+//! The observers registered with [`register_compile_observer`] are notified
+//! of every file compiled for a request, i.e. the main script and the files
+//! it includes or requires, along with the time it took to compile them and
+//! whether they were served from the opcode cache. Hosts can build dependency
+//! maps or preload lists for cache warming from them.
+//!
+//! Files are compiled through the `zend_compile_file` hook, which the opcode
+//! cache replaces with its own once all modules have started up. The
+//! observers chain in front of the original compiler during module startup,
+//! which the opcode cache only calls on cache misses. As the opcode cache is
+//! not started yet at that point, they chain in front of it, which is called
+//! for every file, when the first request starts up. Other requests wait for
+//! the hook to be installed before starting up, so no file is compiled while
+//! it is replaced.
+
+use std::{
+    cell::Cell,
+    ffi::c_int,
+    sync::Once,
+    time::{Duration, Instant},
+};
+
+use parking_lot::{const_rwlock, RwLock};
+
+use crate::{
+    exception::catch_panic_or,
+    ffi::{
+        zend_compile_file, zend_file_handle, zend_op_array, ZEND_INCLUDE, ZEND_INCLUDE_ONCE,
+        ZEND_REQUIRE, ZEND_REQUIRE_ONCE,
+    },
+    zend::lifecycle::RequestObserver,
+};
+
+type CompileObserver = Box<dyn Fn(&CompiledFile) + Send + Sync>;
+type CompileHook = unsafe extern "C" fn(*mut zend_file_handle, c_int) -> *mut zend_op_array;
+
+static OBSERVERS: RwLock<Vec<CompileObserver>> = const_rwlock(Vec::new());
+/// The hook called by [`compile_dispatcher`], i.e. the original compiler.
+static COMPILER: RwLock<Option<Option<CompileHook>>> = const_rwlock(None);
+/// The hook called by [`cache_dispatcher`], i.e. the opcode cache.
+static CACHE: RwLock<Option<CompileHook>> = const_rwlock(None);
+static INSTALL_CACHE_DISPATCHER: Once = Once::new();
+
+thread_local! {
+    /// Whether a file is being loaded through the opcode cache on this thread.
+    static IN_CACHE: Cell<bool> = const { Cell::new(false) };
+    /// Whether the file being loaded through the opcode cache was compiled.
+    static COMPILED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// How a file was loaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncludeKind {
+    /// An `include` expression.
+    Include,
+    /// An `include_once` expression.
+    IncludeOnce,
+    /// A `require` expression, also used for the main script.
+    Require,
+    /// A `require_once` expression.
+    RequireOnce,
+}
+
+impl IncludeKind {
+    fn from_raw(ty: c_int) -> Option<Self> {
+        Some(match u32::try_from(ty).ok()? {
+            ZEND_INCLUDE => Self::Include,
+            ZEND_INCLUDE_ONCE => Self::IncludeOnce,
+            ZEND_REQUIRE => Self::Require,
+            ZEND_REQUIRE_ONCE => Self::RequireOnce,
+            _ => return None,
+        })
+    }
+}
+
+/// A file compiled for a request, passed to compile observers.
+#[derive(Debug, Clone, Copy)]
+pub struct CompiledFile<'a> {
+    /// The resolved path of the file.
+    pub path: &'a str,
+    /// How the file was loaded.
+    pub kind: IncludeKind,
+    /// The time it took to compile the file, or to load it from the opcode
+    /// cache.
+    pub duration: Duration,
+    /// Whether the file was loaded from the opcode cache without being
+    /// compiled.
+    pub cached: bool,
+}
+
+/// Registers an observer notified of every file successfully compiled for a
+/// request, on the thread of the request.
+///
+/// This must be called during module startup.
+///
+/// # Parameters
+///
+/// * `observer` - The observer to register.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::zend::compile::register_compile_observer;
+///
+/// register_compile_observer(|file| {
+///     if !file.cached {
+///         eprintln!("compiled {} in {:?}", file.path, file.duration);
+///     }
+/// });
+/// ```
+pub fn register_compile_observer<F>(observer: F)
+where
+    F: Fn(&CompiledFile) + Send + Sync + 'static,
+{
+    {
+        let mut compiler = COMPILER.write();
+        if compiler.is_none() {
+            // SAFETY: The hook is only replaced during module startup, which is
+            // single threaded.
+            unsafe {
+                *compiler = Some(zend_compile_file);
+                zend_compile_file = Some(compile_dispatcher);
+            }
+            RequestObserver::new()
+                .on_startup(|| INSTALL_CACHE_DISPATCHER.call_once(install_cache_dispatcher))
+                .on_shutdown(|| {
+                    // A bailout out of the opcode cache skips the reset of the
+                    // flags.
+                    IN_CACHE.set(false);
+                    COMPILED.set(false);
+                })
+                .register();
+        }
+    }
+
+    OBSERVERS.write().push(Box::new(observer));
+}
+
+/// Chains in front of the opcode cache, if it replaced the hook installed
+/// during module startup.
+///
+/// This is called once, when the first request starts up. Requests starting
+/// up on other threads wait for it to return, so no file is compiled through
+/// the hook while it is replaced.
+fn install_cache_dispatcher() {
+    // SAFETY: The opcode cache has started up, and no request compiling files
+    // through the hook is running, see above.
+    unsafe {
+        if let Some(cache) = zend_compile_file {
+            if cache as usize != compile_dispatcher as CompileHook as usize {
+                *CACHE.write() = Some(cache);
+                zend_compile_file = Some(cache_dispatcher);
+            }
+        }
+    }
+}
+
+/// Notifies the observers of a compiled file.
+fn notify(op_array: *mut zend_op_array, ty: c_int, duration: Duration, cached: bool) {
+    let Some(kind) = IncludeKind::from_raw(ty) else {
+        return;
+    };
+    let Some(path) = (unsafe { op_array.as_ref() })
+        .and_then(|op_array| unsafe { op_array.filename.as_ref() })
+        .and_then(|filename| filename.as_str().ok())
+    else {
+        return;
+    };
+
+    let file = CompiledFile {
+        path,
+        kind,
+        duration,
+        cached,
+    };
+    // Observers may include files in turn.
+    let observers = OBSERVERS.read_recursive();
+    for observer in observers.iter() {
+        catch_panic_or((), || observer(&file));
+    }
+}
+
+unsafe extern "C" fn compile_dispatcher(
+    file_handle: *mut zend_file_handle,
+    ty: c_int,
+) -> *mut zend_op_array {
+    let Some(Some(compiler)) = *COMPILER.read_recursive() else {
+        return std::ptr::null_mut();
+    };
+
+    // Called by the opcode cache on a cache miss, which is reported once the
+    // cache returns.
+    if IN_CACHE.get() {
+        COMPILED.set(true);
+        return compiler(file_handle, ty);
+    }
+
+    let start = Instant::now();
+    let op_array = compiler(file_handle, ty);
+    notify(op_array, ty, start.elapsed(), false);
+    op_array
+}
+
+unsafe extern "C" fn cache_dispatcher(
+    file_handle: *mut zend_file_handle,
+    ty: c_int,
+) -> *mut zend_op_array {
+    let Some(cache) = *CACHE.read_recursive() else {
+        return std::ptr::null_mut();
+    };
+
+    let in_cache = IN_CACHE.replace(true);
+    let compiled = COMPILED.replace(false);
+    let start = Instant::now();
+    let op_array = cache(file_handle, ty);
+    let duration = start.elapsed();
+    let cached = !COMPILED.replace(compiled);
+    IN_CACHE.set(in_cache);
+
+    notify(op_array, ty, duration, cached);
+    op_array
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_include_kind() {
+        assert_eq!(
+            IncludeKind::from_raw(ZEND_REQUIRE as c_int),
+            Some(IncludeKind::Require)
+        );
+        assert_eq!(
+            IncludeKind::from_raw(ZEND_INCLUDE_ONCE as c_int),
+            Some(IncludeKind::IncludeOnce)
+        );
+        assert_eq!(IncludeKind::from_raw(0), None);
+    }
+
+    #[cfg(feature = "embed")]
+    mod embed {
+        use std::fs;
+
+        use parking_lot::{const_mutex, Mutex};
+
+        use super::*;
+        use crate::{
+            builders::ModuleBuilder,
+            embed::{Embed, TestSapi},
+            zend::ModuleEntry,
+        };
+
+        static COMPILED_FILES: Mutex<Vec<(String, IncludeKind, bool)>> = const_mutex(Vec::new());
+
+        extern "C" fn startup(_ty: i32, _module_number: i32) -> i32 {
+            register_compile_observer(|file| {
+                COMPILED_FILES
+                    .lock()
+                    .push((file.path.to_owned(), file.kind, file.cached));
+            });
+            0
+        }
+
+        extern "C" fn get_module() -> *mut ModuleEntry {
+            let (entry, _) = ModuleBuilder::new("test-compile", "0.1.0")
+                .startup_function(startup)
+                .try_into()
+                .expect("should build the module");
+            entry.into_raw()
+        }
+
+        #[test]
+        fn test_compile_observer() {
+            let path = std::env::temp_dir().join("ext-php-rs-compile-observer.php");
+            fs::write(&path, "<?php return 42;").expect("should write the file");
+            let path = path.to_str().expect("should be UTF-8").to_owned();
+
+            TestSapi::new().module(get_module).run(|| {
+                let answer = Embed::eval(&format!("include_once '{path}';"))
+                    .expect("should include the file");
+                assert_eq!(answer.long(), Some(42));
+            });
+            let _ = fs::remove_file(&path);
+
+            let compiled = COMPILED_FILES.lock();
+            assert!(compiled.contains(&(path, IncludeKind::IncludeOnce, false)));
+        }
+    }
+}
@@ -206,6 +206,50 @@ impl ExecuteData {
         unsafe { self.prev_execute_data.as_ref() }
     }
 
+    /// Returns the number of arguments passed to the function.
+    pub fn num_args(&self) -> usize {
+        // SAFETY: All fields of the `u2` union are the same type.
+        unsafe { self.This.u2.num_args as usize }
+    }
+
+    /// Returns the arguments passed to the function.
+    ///
+    /// Once a function defined in PHP code has started executing, the
+    /// arguments it does not declare are moved after its local variables,
+    /// where they are also looked up.
+    pub fn args(&self) -> Vec<&Zval> {
+        let num_args = self.num_args();
+        let (first_extra, extra_offset) = match self.function() {
+            Some(func) if func.is_user() => unsafe {
+                (
+                    func.op_array.num_args as usize,
+                    (func.op_array.last_var as u32 + func.op_array.T) as isize,
+                )
+            },
+            _ => (num_args, 0),
+        };
+
+        (0..num_args)
+            .filter_map(|i| unsafe {
+                if i < first_extra {
+                    self.zend_call_arg(i).map(|arg| &*arg)
+                } else {
+                    self.zend_call_var_num(extra_offset + (i - first_extra) as isize)
+                        .as_ref()
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the line currently executed in the frame, or [`None`] if the
+    /// frame is not executing PHP code.
+    pub fn lineno(&self) -> Option<u32> {
+        if !self.function()?.is_user() {
+            return None;
+        }
+        unsafe { self.opline.as_ref() }.map(|opline| opline.lineno)
+    }
+
     /// Translation of macro `ZEND_CALL_ARG(call, n)`
     /// zend_compile.h:578
     ///
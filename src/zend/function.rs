@@ -4,16 +4,16 @@ use std::{fmt::Debug, os::raw::c_char, ptr};
 
 use crate::{
     convert::IntoZvalDyn,
-    error::Result,
+    error::{Error, Result},
     ffi::{
         zend_call_known_function, zend_fetch_function_str, zend_function, zend_function_entry,
         zend_hash_str_find_ptr_lc,
     },
     flags::FunctionType,
-    types::Zval,
+    types::{ZendStr, Zval},
 };
 
-use super::ClassEntry;
+use super::{ClassEntry, ExecutorGlobals};
 
 /// A Zend function entry.
 pub type FunctionEntry = zend_function_entry;
@@ -61,6 +61,44 @@ impl Function {
         FunctionType::from(unsafe { self.type_ })
     }
 
+    /// Returns whether the function is defined in PHP code, as opposed to an
+    /// internal function defined by an extension.
+    pub fn is_user(&self) -> bool {
+        matches!(
+            self.function_type(),
+            FunctionType::User | FunctionType::Eval
+        )
+    }
+
+    /// Returns the name of the function, or [`None`] for the main script of a
+    /// file and for evaluated code.
+    pub fn name(&self) -> Option<&str> {
+        unsafe { self.common.function_name.as_ref() }.and_then(|name| name.as_str().ok())
+    }
+
+    /// Returns the class the function is a method of.
+    pub fn class(&self) -> Option<&ClassEntry> {
+        unsafe { self.common.scope.as_ref() }
+    }
+
+    /// Returns the file the function is defined in, or [`None`] for internal
+    /// functions.
+    pub fn filename(&self) -> Option<&str> {
+        if !self.is_user() {
+            return None;
+        }
+        unsafe { self.op_array.filename.as_ref() }.and_then(|file| ZendStr::as_str(file).ok())
+    }
+
+    /// Returns the line the function is defined on, or [`None`] for internal
+    /// functions.
+    pub fn line_start(&self) -> Option<u32> {
+        if !self.is_user() {
+            return None;
+        }
+        Some(unsafe { self.op_array.line_start })
+    }
+
     /// Attempts to fetch a [`Function`] from the function name.
     pub fn try_from_function(name: &str) -> Option<Self> {
         unsafe {
@@ -136,6 +174,9 @@ impl Function {
             )
         };
 
-        Ok(retval)
+        match ExecutorGlobals::take_exception() {
+            Some(e) => Err(Error::Exception(e)),
+            None => Ok(retval),
+        }
     }
 }
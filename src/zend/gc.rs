@@ -0,0 +1,116 @@
+//! Status of the garbage collector of reference cycles.
+
+#[cfg(php83)]
+use std::time::Duration;
+
+use crate::ffi::{gc_enabled, zend_gc_collect_cycles, zend_gc_get_status, zend_gc_status};
+
+/// A snapshot of the status of the garbage collector, equivalent to the
+/// userland `gc_status()` function.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GcStatus {
+    /// Whether the garbage collector is enabled.
+    pub enabled: bool,
+    /// The number of times the garbage collector ran.
+    pub runs: u32,
+    /// The number of values collected.
+    pub collected: u32,
+    /// The number of possible roots which triggers a collection.
+    pub threshold: u32,
+    /// The number of possible roots currently buffered.
+    pub roots: u32,
+    /// Whether a collection is running.
+    #[cfg(php83)]
+    pub running: bool,
+    /// Whether the garbage collector is protected from running, e.g. while a
+    /// collection is running.
+    #[cfg(php83)]
+    pub protected: bool,
+    /// Whether the buffer of possible roots is full.
+    #[cfg(php83)]
+    pub full: bool,
+    /// The size of the buffer of possible roots.
+    #[cfg(php83)]
+    pub buffer_size: u32,
+    /// The time spent outside of collections.
+    #[cfg(php83)]
+    pub application_time: Duration,
+    /// The time spent collecting cycles.
+    #[cfg(php83)]
+    pub collector_time: Duration,
+    /// The time spent calling destructors while collecting cycles.
+    #[cfg(php83)]
+    pub destructor_time: Duration,
+    /// The time spent freeing values while collecting cycles.
+    #[cfg(php83)]
+    pub free_time: Duration,
+}
+
+impl GcStatus {
+    /// Captures the current status of the garbage collector.
+    pub fn capture() -> Self {
+        let mut status = std::mem::MaybeUninit::<zend_gc_status>::zeroed();
+        let status = unsafe {
+            zend_gc_get_status(status.as_mut_ptr());
+            status.assume_init()
+        };
+        Self {
+            enabled: unsafe { gc_enabled() },
+            runs: status.runs,
+            collected: status.collected,
+            threshold: status.threshold,
+            roots: status.num_roots,
+            #[cfg(php83)]
+            running: status.active,
+            #[cfg(php83)]
+            protected: status.gc_protected,
+            #[cfg(php83)]
+            full: status.full,
+            #[cfg(php83)]
+            buffer_size: status.buf_size,
+            #[cfg(php83)]
+            application_time: Duration::from_nanos(status.application_time),
+            #[cfg(php83)]
+            collector_time: Duration::from_nanos(status.collector_time),
+            #[cfg(php83)]
+            destructor_time: Duration::from_nanos(status.dtor_time),
+            #[cfg(php83)]
+            free_time: Duration::from_nanos(status.free_time),
+        }
+    }
+}
+
+/// Collects the reference cycles, equivalent to the userland
+/// `gc_collect_cycles()` function.
+///
+/// # Returns
+///
+/// The number of values collected.
+pub fn collect_cycles() -> usize {
+    usize::try_from(unsafe { zend_gc_collect_cycles() }).unwrap_or(0)
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_gc_status() {
+        Embed::run(|| {
+            let before = GcStatus::capture();
+            let result = Embed::eval(
+                "for ($i = 0; $i < 100; $i++) { $a = new stdClass; $a->self = $a; } \
+                 unset($a); gc_collect_cycles();",
+            );
+            assert!(result.is_ok());
+
+            let after = GcStatus::capture();
+            assert!(after.enabled);
+            assert!(after.runs > before.runs);
+            assert!(after.collected >= before.collected + 99);
+            assert_eq!(collect_cycles(), 0);
+        });
+    }
+}
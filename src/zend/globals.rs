@@ -1,25 +1,29 @@
 //! Types related to the PHP executor, sapi and process globals.
 
 use std::collections::HashMap;
-use std::ffi::CStr;
+use std::ffi::{c_void, CStr};
 use std::ops::{Deref, DerefMut};
 use std::slice;
 use std::str;
+use std::sync::Arc;
 
-use parking_lot::{const_rwlock, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use once_cell::sync::Lazy;
+use parking_lot::{
+    lock_api::{ArcRwLockReadGuard, ArcRwLockWriteGuard},
+    RawRwLock, RwLock,
+};
 
 use crate::boxed::ZBox;
 use crate::exception::PhpResult;
 #[cfg(php82)]
 use crate::ffi::zend_atomic_bool_store;
 use crate::ffi::{
-    _sapi_module_struct, _zend_executor_globals, _zend_compiler_globals,
-    ext_php_rs_executor_globals, ext_php_rs_compiler_globals,
-    ext_php_rs_file_globals, ext_php_rs_process_globals, ext_php_rs_sapi_globals,
-    ext_php_rs_sapi_module, php_core_globals, php_file_globals, sapi_globals_struct,
-    sapi_header_struct, sapi_headers_struct, sapi_request_info, zend_ini_entry,
-    zend_is_auto_global, TRACK_VARS_COOKIE, TRACK_VARS_ENV, TRACK_VARS_FILES, TRACK_VARS_GET,
-    TRACK_VARS_POST, TRACK_VARS_SERVER,
+    _sapi_module_struct, _zend_compiler_globals, _zend_executor_globals,
+    ext_php_rs_compiler_globals, ext_php_rs_executor_globals, ext_php_rs_file_globals,
+    ext_php_rs_process_globals, ext_php_rs_sapi_globals, ext_php_rs_sapi_module, php_core_globals,
+    php_file_globals, sapi_globals_struct, sapi_header_struct, sapi_headers_struct,
+    sapi_request_info, zend_ini_entry, zend_is_auto_global, TRACK_VARS_COOKIE, TRACK_VARS_ENV,
+    TRACK_VARS_FILES, TRACK_VARS_GET, TRACK_VARS_POST, TRACK_VARS_SERVER,
 };
 #[cfg(not(php81))]
 use crate::ffi::{_zend_hash_find_known_hash, _zend_string};
@@ -32,6 +36,7 @@ use crate::ffi::{
 use crate::types::{ZendHashTable, ZendObject, ZendStr};
 
 use super::linked_list::ZendLinkedListIterator;
+use super::FromIniValue;
 
 /// Stores global variables used in the PHP executor.
 pub type ExecutorGlobals = _zend_executor_globals;
@@ -49,7 +54,7 @@ impl ExecutorGlobals {
         // return an invalid pointer.
         let globals = unsafe { ext_php_rs_executor_globals().as_ref() }
             .expect("Static executor globals were invalid");
-        let guard = GLOBALS_LOCK.read();
+        let guard = locks(|locks| locks.executor.read_arc());
         GlobalReadGuard { globals, guard }
     }
 
@@ -65,7 +70,7 @@ impl ExecutorGlobals {
         // return an invalid pointer.
         let globals = unsafe { ext_php_rs_executor_globals().as_mut() }
             .expect("Static executor globals were invalid");
-        let guard = GLOBALS_LOCK.write();
+        let guard = locks(|locks| locks.executor.write_arc());
         GlobalWriteGuard { globals, guard }
     }
 
@@ -107,6 +112,22 @@ impl ExecutorGlobals {
         ini_hash_map
     }
 
+    /// Retrieves the value of an ini directive in the current executor
+    /// context, converted with [`FromIniValue`].
+    ///
+    /// Returns [`None`] if the directive does not exist, has no value, or its
+    /// value cannot be converted.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the directive, e.g. `memory_limit`.
+    pub fn ini_value<T: FromIniValue>(&self, name: &str) -> Option<T> {
+        let hash_table = unsafe { self.ini_directives.as_ref() }?;
+        let ini_entry = unsafe { hash_table.get(name)?.ptr::<zend_ini_entry>()?.as_ref() }?;
+        let value = unsafe { ini_entry.value.as_ref() }?;
+        T::from_ini_value(value.as_str().ok()?)
+    }
+
     /// Attempts to retrieve the global constants table.
     pub fn constants(&self) -> Option<&ZendHashTable> {
         unsafe { self.zend_constants.as_ref() }
@@ -186,36 +207,36 @@ impl ExecutorGlobals {
 pub type CompilerGlobals = _zend_compiler_globals;
 
 impl CompilerGlobals {
-  /// Returns a reference to the PHP compiler globals.
-  ///
-  /// The compiler globals are guarded by a RwLock. There can be multiple
-  /// immutable references at one time but only ever one mutable reference.
-  /// Attempting to retrieve the globals while already holding the global
-  /// guard will lead to a deadlock. Dropping the globals guard will release
-  /// the lock.
-  pub fn get() -> GlobalReadGuard<Self> {
-      // SAFETY: PHP compiler globals are statically declared therefore should never
-      // return an invalid pointer.
-      let globals = unsafe { ext_php_rs_compiler_globals().as_ref() }
-          .expect("Static compiler globals were invalid");
-      let guard = GLOBALS_LOCK.read();
-      GlobalReadGuard { globals, guard }
-  }
-
-  /// Returns a mutable reference to the PHP compiler globals.
-  ///
-  /// The compiler globals are guarded by a RwLock. There can be multiple
-  /// immutable references at one time but only ever one mutable reference.
-  /// Attempting to retrieve the globals while already holding the global
-  /// guard will lead to a deadlock. Dropping the globals guard will release
-  /// the lock.
-  pub fn get_mut() -> GlobalWriteGuard<Self> {
-      // SAFETY: PHP compiler globals are statically declared therefore should never
-      // return an invalid pointer.
-      let globals = unsafe { &mut *ext_php_rs_compiler_globals() };
-      let guard = SAPI_GLOBALS_LOCK.write();
-      GlobalWriteGuard { globals, guard }
-  }
+    /// Returns a reference to the PHP compiler globals.
+    ///
+    /// The compiler globals are guarded by a RwLock. There can be multiple
+    /// immutable references at one time but only ever one mutable reference.
+    /// Attempting to retrieve the globals while already holding the global
+    /// guard will lead to a deadlock. Dropping the globals guard will release
+    /// the lock.
+    pub fn get() -> GlobalReadGuard<Self> {
+        // SAFETY: PHP compiler globals are statically declared therefore should never
+        // return an invalid pointer.
+        let globals = unsafe { ext_php_rs_compiler_globals().as_ref() }
+            .expect("Static compiler globals were invalid");
+        let guard = locks(|locks| locks.compiler.read_arc());
+        GlobalReadGuard { globals, guard }
+    }
+
+    /// Returns a mutable reference to the PHP compiler globals.
+    ///
+    /// The compiler globals are guarded by a RwLock. There can be multiple
+    /// immutable references at one time but only ever one mutable reference.
+    /// Attempting to retrieve the globals while already holding the global
+    /// guard will lead to a deadlock. Dropping the globals guard will release
+    /// the lock.
+    pub fn get_mut() -> GlobalWriteGuard<Self> {
+        // SAFETY: PHP compiler globals are statically declared therefore should never
+        // return an invalid pointer.
+        let globals = unsafe { &mut *ext_php_rs_compiler_globals() };
+        let guard = locks(|locks| locks.compiler.write_arc());
+        GlobalWriteGuard { globals, guard }
+    }
 }
 
 /// Stores the SAPI module used in the PHP executor.
@@ -234,7 +255,7 @@ impl SapiModule {
         // return an invalid pointer.
         let globals = unsafe { ext_php_rs_sapi_module().as_ref() }
             .expect("Static executor globals were invalid");
-        let guard = SAPI_MODULE_LOCK.read();
+        let guard = SAPI_MODULE_LOCK.read_arc();
         GlobalReadGuard { globals, guard }
     }
 
@@ -250,7 +271,7 @@ impl SapiModule {
         // return an invalid pointer.
         let globals = unsafe { ext_php_rs_sapi_module().as_mut() }
             .expect("Static executor globals were invalid");
-        let guard = SAPI_MODULE_LOCK.write();
+        let guard = SAPI_MODULE_LOCK.write_arc();
         GlobalWriteGuard { globals, guard }
     }
 }
@@ -270,7 +291,7 @@ impl ProcessGlobals {
         // SAFETY: PHP executor globals are statically declared therefore should never
         // return an invalid pointer.
         let globals = unsafe { &*ext_php_rs_process_globals() };
-        let guard = PROCESS_GLOBALS_LOCK.read();
+        let guard = locks(|locks| locks.process.read_arc());
         GlobalReadGuard { globals, guard }
     }
 
@@ -285,7 +306,7 @@ impl ProcessGlobals {
         // SAFETY: PHP executor globals are statically declared therefore should never
         // return an invalid pointer.
         let globals = unsafe { &mut *ext_php_rs_process_globals() };
-        let guard = PROCESS_GLOBALS_LOCK.write();
+        let guard = locks(|locks| locks.process.write_arc());
         GlobalWriteGuard { globals, guard }
     }
 
@@ -393,7 +414,7 @@ impl SapiGlobals {
         // SAFETY: PHP executor globals are statically declared therefore should never
         // return an invalid pointer.
         let globals = unsafe { &*ext_php_rs_sapi_globals() };
-        let guard = SAPI_GLOBALS_LOCK.read();
+        let guard = locks(|locks| locks.sapi.read_arc());
         GlobalReadGuard { globals, guard }
     }
 
@@ -408,7 +429,7 @@ impl SapiGlobals {
         // SAFETY: PHP executor globals are statically declared therefore should never
         // return an invalid pointer.
         let globals = unsafe { &mut *ext_php_rs_sapi_globals() };
-        let guard = SAPI_GLOBALS_LOCK.write();
+        let guard = locks(|locks| locks.sapi.write_arc());
         GlobalWriteGuard { globals, guard }
     }
 
@@ -421,39 +442,133 @@ impl SapiGlobals {
     pub fn sapi_headers(&self) -> &SapiHeaders {
         &self.sapi_headers
     }
+
+    /// Returns the server context of the current request, set by the SAPI
+    /// with [`SapiGlobals::set_server_context`], or a null pointer.
+    pub fn server_context(&self) -> *mut c_void {
+        self.server_context
+    }
+
+    /// Sets the server context of the next request, e.g. a pointer to the
+    /// client connection of the host, which the hooks of the SAPI read back
+    /// with [`SapiGlobals::server_context`].
+    ///
+    /// PHP only reads the body and cookies of requests which have a server
+    /// context, so it must be set before the request starts up, and stay
+    /// valid until the request is shut down.
+    ///
+    /// # Parameters
+    ///
+    /// * `context` - The server context, or a null pointer once the request
+    ///   was shut down.
+    pub fn set_server_context(&mut self, context: *mut c_void) {
+        self.server_context = context;
+    }
 }
 
 /// Stores the SAPI headers.
 pub type SapiHeaders = sapi_headers_struct;
 
 impl<'a> SapiHeaders {
+    /// Returns the headers given to the send headers function of a SAPI.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be null, or point to the headers PHP passes to the
+    /// function, which are valid until it returns.
+    pub unsafe fn from_ptr(headers: *mut sapi_headers_struct) -> Option<&'a Self> {
+        headers.as_ref()
+    }
+
     /// Create an iterator over the headers.
-    pub fn headers(&'a mut self) -> ZendLinkedListIterator<'a, SapiHeader> {
+    pub fn headers(&'a self) -> ZendLinkedListIterator<'a, SapiHeader> {
         self.headers.iter()
     }
+
+    /// Returns the HTTP status code of the response, or [`None`] if it was
+    /// not set to a valid status code.
+    pub fn status_code(&self) -> Option<u16> {
+        u16::try_from(self.http_response_code)
+            .ok()
+            .filter(|code| (100..1000).contains(code))
+    }
+
+    /// Returns the status line of the response, e.g. `HTTP/1.1 404 Not
+    /// Found`, if it was set with `header()`.
+    pub fn status_line(&self) -> Option<&str> {
+        if self.http_status_line.is_null() {
+            return None;
+        }
+        unsafe { CStr::from_ptr(self.http_status_line).to_str().ok() }
+    }
+
+    /// Returns the MIME type of the response, if it was set with a
+    /// `Content-Type` header.
+    pub fn mimetype(&self) -> Option<&str> {
+        if self.mimetype.is_null() {
+            return None;
+        }
+        unsafe { CStr::from_ptr(self.mimetype).to_str().ok() }
+    }
 }
 
 /// Stores a single header in the SAPI headers.
 pub type SapiHeader = sapi_header_struct;
 
 impl<'a> SapiHeader {
+    /// Returns the header given to the send header function of a SAPI, or
+    /// [`None`] for the null header marking the end of the headers.
+    ///
+    /// # Safety
+    ///
+    /// The pointer must be null, or point to the header PHP passes to the
+    /// function, which is valid until it returns.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::ffi::c_void;
+    ///
+    /// use ext_php_rs::{ffi::sapi_header_struct, zend::SapiHeader};
+    ///
+    /// extern "C" fn send_header(header: *mut sapi_header_struct, _: *mut c_void) {
+    ///     if let Some(header) = unsafe { SapiHeader::from_ptr(header) } {
+    ///         println!("{}: {:?}", header.name(), header.value());
+    ///     }
+    /// }
+    /// ```
+    pub unsafe fn from_ptr(header: *mut sapi_header_struct) -> Option<&'a Self> {
+        header.as_ref().filter(|header| !header.header.is_null())
+    }
+
+    /// Returns the header line, which may not be valid UTF-8.
+    pub fn as_bytes(&'a self) -> &'a [u8] {
+        if self.header.is_null() {
+            return &[];
+        }
+        unsafe { slice::from_raw_parts(self.header as *const u8, self.header_len) }
+    }
+
     /// Get the header as a string.
     ///
     /// # Panics
     /// - If the header is not a valid UTF-8 string.
     pub fn as_str(&'a self) -> &'a str {
-        unsafe {
-            let slice = slice::from_raw_parts(self.header as *const u8, self.header_len);
-            str::from_utf8(slice).expect("Invalid header string")
-        }
+        str::from_utf8(self.as_bytes()).expect("Invalid header string")
     }
 
     /// Returns the header name (key).
+    ///
+    /// # Panics
+    /// - If the header is not a valid UTF-8 string.
     pub fn name(&'a self) -> &'a str {
         self.as_str().split(':').next().unwrap_or("").trim()
     }
 
     /// Returns the header value.
+    ///
+    /// # Panics
+    /// - If the header is not a valid UTF-8 string.
     pub fn value(&'a self) -> Option<&'a str> {
         self.as_str().splitn(2, ':').nth(1).map(|s| s.trim())
     }
@@ -615,7 +730,7 @@ impl FileGlobals {
         // return an invalid pointer.
         let globals = unsafe { ext_php_rs_file_globals().as_ref() }
             .expect("Static file globals were invalid");
-        let guard = FILE_GLOBALS_LOCK.read();
+        let guard = locks(|locks| locks.file.read_arc());
         GlobalReadGuard { globals, guard }
     }
 
@@ -630,7 +745,7 @@ impl FileGlobals {
         // SAFETY: PHP executor globals are statically declared therefore should never
         // return an invalid pointer.
         let globals = unsafe { &mut *ext_php_rs_file_globals() };
-        let guard = SAPI_GLOBALS_LOCK.write();
+        let guard = locks(|locks| locks.file.write_arc());
         GlobalWriteGuard { globals, guard }
     }
 
@@ -640,27 +755,55 @@ impl FileGlobals {
     }
 }
 
-/// Executor globals rwlock.
+/// The rwlocks of the executor, compiler, process, SAPI and file globals.
+///
+/// PHP provides no indication if the globals are being accessed so this is
+/// only effective on the Rust side.
+#[derive(Default)]
+struct GlobalLocks {
+    executor: Arc<RwLock<()>>,
+    compiler: Arc<RwLock<()>>,
+    process: Arc<RwLock<()>>,
+    sapi: Arc<RwLock<()>>,
+    file: Arc<RwLock<()>>,
+}
+
+#[cfg(not(php_zts))]
+static GLOBAL_LOCKS: Lazy<GlobalLocks> = Lazy::new(GlobalLocks::default);
+
+/// Calls the given function with the locks of the globals of the current
+/// thread.
 ///
-/// PHP provides no indication if the executor globals are being accessed so
-/// this is only effective on the Rust side.
-static GLOBALS_LOCK: RwLock<()> = const_rwlock(());
-static PROCESS_GLOBALS_LOCK: RwLock<()> = const_rwlock(());
-static SAPI_GLOBALS_LOCK: RwLock<()> = const_rwlock(());
-static FILE_GLOBALS_LOCK: RwLock<()> = const_rwlock(());
-
-/// SAPI globals rwlock.
+/// In thread-safe builds, each thread running PHP has its own globals, so it
+/// has its own locks as well, and threads do not contend on them. The guards
+/// share the ownership of the locks, which are freed once the thread exited
+/// and its guards were dropped.
+fn locks<R>(func: impl FnOnce(&GlobalLocks) -> R) -> R {
+    cfg_if::cfg_if! {
+        if #[cfg(php_zts)] {
+            thread_local! {
+                static LOCKS: GlobalLocks = GlobalLocks::default();
+            }
+            LOCKS.with(func)
+        } else {
+            func(&GLOBAL_LOCKS)
+        }
+    }
+}
+
+/// SAPI module rwlock. The SAPI module is shared by all threads, even in
+/// thread-safe builds.
 ///
-/// PHP provides no indication if the executor globals are being accessed so
-/// this is only effective on the Rust side.
-static SAPI_MODULE_LOCK: RwLock<()> = const_rwlock(());
+/// PHP provides no indication if the SAPI module is being accessed so this is
+/// only effective on the Rust side.
+static SAPI_MODULE_LOCK: Lazy<Arc<RwLock<()>>> = Lazy::new(Arc::default);
 
 /// Wrapper guard that contains a reference to a given type `T`. Dropping a
 /// guard releases the lock on the relevant rwlock.
 pub struct GlobalReadGuard<T: 'static> {
     globals: &'static T,
     #[allow(dead_code)]
-    guard: RwLockReadGuard<'static, ()>,
+    guard: ArcRwLockReadGuard<RawRwLock, ()>,
 }
 
 impl<T> Deref for GlobalReadGuard<T> {
@@ -676,7 +819,7 @@ impl<T> Deref for GlobalReadGuard<T> {
 pub struct GlobalWriteGuard<T: 'static> {
     globals: &'static mut T,
     #[allow(dead_code)]
-    guard: RwLockWriteGuard<'static, ()>,
+    guard: ArcRwLockWriteGuard<RawRwLock, ()>,
 }
 
 impl<T> Deref for GlobalWriteGuard<T> {
@@ -2,7 +2,7 @@ use std::{ffi::c_void, mem::MaybeUninit, os::raw::c_int, ptr};
 
 use crate::{
     class::RegisteredClass,
-    exception::PhpResult,
+    exception::{catch_panic, PhpResult},
     ffi::{
         std_object_handlers, zend_is_true, zend_object_handlers, zend_object_std_dtor,
         zend_std_get_properties, zend_std_has_property, zend_std_read_property,
@@ -102,7 +102,8 @@ impl ZendObjectHandlers {
             })
         }
 
-        match internal::<T>(object, member, type_, cache_slot, rv) {
+        match catch_panic(|| internal::<T>(object, member, type_, cache_slot, rv)).and_then(|rv| rv)
+        {
             Ok(rv) => rv,
             Err(e) => {
                 let _ = e.throw();
@@ -146,7 +147,7 @@ impl ZendObjectHandlers {
             })
         }
 
-        match internal::<T>(object, member, value, cache_slot) {
+        match catch_panic(|| internal::<T>(object, member, value, cache_slot)).and_then(|rv| rv) {
             Ok(rv) => rv,
             Err(e) => {
                 let _ = e.throw();
@@ -188,7 +189,7 @@ impl ZendObjectHandlers {
             .or_else(|| Some(ZendHashTable::new().into_raw()))
             .expect("Failed to get property hashtable");
 
-        if let Err(e) = internal::<T>(object, props) {
+        if let Err(e) = catch_panic(|| internal::<T>(object, props)).and_then(|rv| rv) {
             let _ = e.throw();
         }
 
@@ -274,7 +275,9 @@ impl ZendObjectHandlers {
             ))
         }
 
-        match internal::<T>(object, member, has_set_exists, cache_slot) {
+        match catch_panic(|| internal::<T>(object, member, has_set_exists, cache_slot))
+            .and_then(|rv| rv)
+        {
             Ok(rv) => rv,
             Err(e) => {
                 let _ = e.throw();
@@ -0,0 +1,186 @@
+//! Functions for interacting with the response headers of the current
+//! request, equivalent to the userland `header()`, `header_remove()`,
+//! `headers_list()`, `headers_sent()` and `http_response_code()` functions.
+//!
+//! They can be called by extension functions, or by the host of an embedded
+//! SAPI, while a request is active and until its headers are sent.
+//!
+//! Headers set by name and value, and the status code, are handled by
+//! [`crate::sapi`].
+
+use std::ptr;
+
+use crate::{
+    error::{Error, Result},
+    ffi::{
+        sapi_add_header_ex, sapi_header_line, sapi_header_op, sapi_header_op_enum,
+        sapi_header_op_enum_SAPI_HEADER_ADD, sapi_header_op_enum_SAPI_HEADER_DELETE,
+        sapi_header_op_enum_SAPI_HEADER_DELETE_ALL, sapi_header_op_enum_SAPI_HEADER_REPLACE,
+        sapi_header_op_enum_SAPI_HEADER_SET_STATUS,
+    },
+    zend::SapiGlobals,
+};
+
+/// An operation to perform on the response headers through
+/// [`header_op`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderOp {
+    /// Add the header, replacing any existing header with the same name.
+    Replace,
+    /// Add the header, keeping any existing header with the same name.
+    Add,
+    /// Remove all headers with the given name.
+    Delete,
+    /// Remove all headers.
+    DeleteAll,
+    /// Set the HTTP response status code.
+    SetStatus,
+}
+
+impl From<HeaderOp> for sapi_header_op_enum {
+    fn from(value: HeaderOp) -> Self {
+        match value {
+            HeaderOp::Replace => sapi_header_op_enum_SAPI_HEADER_REPLACE,
+            HeaderOp::Add => sapi_header_op_enum_SAPI_HEADER_ADD,
+            HeaderOp::Delete => sapi_header_op_enum_SAPI_HEADER_DELETE,
+            HeaderOp::DeleteAll => sapi_header_op_enum_SAPI_HEADER_DELETE_ALL,
+            HeaderOp::SetStatus => sapi_header_op_enum_SAPI_HEADER_SET_STATUS,
+        }
+    }
+}
+
+/// Performs an operation on the response headers, equivalent to the C
+/// `sapi_header_op` function.
+///
+/// For [`HeaderOp::Replace`] and [`HeaderOp::Add`], `line` is the full header
+/// line (e.g. `X-Foo: bar`) and `response_code` is an optional status code to
+/// set alongside the header. For [`HeaderOp::Delete`], `line` is the name of
+/// the header to remove. For [`HeaderOp::SetStatus`], `response_code` is the
+/// status code to set and `line` is ignored.
+///
+/// # Parameters
+///
+/// * `op` - The operation to perform.
+/// * `line` - The header line or name.
+/// * `response_code` - The HTTP response code, or 0 to leave it unchanged.
+///
+/// # Returns
+///
+/// Nothing on success, [`Error::SapiHeaderFailure`] if PHP refused the
+/// operation, for example because the headers have already been sent.
+pub fn header_op(op: HeaderOp, line: &str, response_code: i64) -> Result<()> {
+    let result = unsafe {
+        if op == HeaderOp::SetStatus {
+            sapi_header_op(op.into(), response_code as usize as *mut _)
+        } else {
+            let mut header_line = sapi_header_line {
+                line: line.as_ptr().cast(),
+                line_len: line.len(),
+                response_code: response_code as _,
+            };
+            sapi_header_op(op.into(), ptr::addr_of_mut!(header_line).cast())
+        }
+    };
+
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(Error::SapiHeaderFailure)
+    }
+}
+
+/// Adds a header to the response, equivalent to the C `sapi_add_header`
+/// macro.
+///
+/// # Parameters
+///
+/// * `header` - The full header line, e.g. `X-Foo: bar`.
+/// * `replace` - Whether to replace any existing header with the same name.
+///
+/// # Returns
+///
+/// Nothing on success, [`Error::SapiHeaderFailure`] if the header could not be
+/// added.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::zend::headers;
+///
+/// headers::add_header("X-Trace-Id: 1234", true).unwrap();
+/// ```
+pub fn add_header(header: &str, replace: bool) -> Result<()> {
+    // PHP takes ownership of the header line when `duplicate` is false, so we ask
+    // it to copy the string instead.
+    let result = unsafe { sapi_add_header_ex(header.as_ptr().cast(), header.len(), true, replace) };
+
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(Error::SapiHeaderFailure)
+    }
+}
+
+/// Removes all headers with the given name from the response, equivalent to
+/// the userland `header_remove()` function.
+///
+/// # Parameters
+///
+/// * `name` - The name of the header to remove.
+pub fn remove_header(name: &str) -> Result<()> {
+    header_op(HeaderOp::Delete, name, 0)
+}
+
+/// Returns whether the response headers have already been sent, in which case
+/// they can no longer be modified.
+pub fn headers_sent() -> bool {
+    SapiGlobals::get().headers_sent != 0
+}
+
+/// Returns the header lines currently queued to be sent with the response,
+/// equivalent to the userland `headers_list()` function.
+///
+/// Headers that are not valid UTF-8 are skipped.
+pub fn headers() -> Vec<String> {
+    let globals = SapiGlobals::get();
+    globals
+        .sapi_headers()
+        .headers()
+        .filter_map(|header| {
+            std::str::from_utf8(header.as_bytes())
+                .ok()
+                .map(ToString::to_string)
+        })
+        .collect()
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+
+    #[test]
+    fn test_headers() {
+        Embed::run(|| {
+            assert!(add_header("X-Foo: bar", true).is_ok());
+            assert!(add_header("X-Baz: qux", true).is_ok());
+            assert!(headers().contains(&"X-Foo: bar".to_string()));
+
+            assert!(remove_header("X-Foo").is_ok());
+            let list = headers();
+            assert!(!list.contains(&"X-Foo: bar".to_string()));
+            assert!(list.contains(&"X-Baz: qux".to_string()));
+
+            let header = SapiGlobals::get()
+                .sapi_headers()
+                .headers()
+                .find(|header| header.name() == "X-Baz")
+                .map(|header| header.value());
+            assert_eq!(header, Some(Some("qux")));
+
+            Embed::eval("http_response_code(404);").expect("should set the status code");
+            assert_eq!(SapiGlobals::get().sapi_headers().status_code(), Some(404));
+        });
+    }
+}
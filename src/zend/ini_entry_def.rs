@@ -1,9 +1,22 @@
 //! Builder for creating inis and methods in PHP.
 //! See <https://www.phpinternalsbook.com/php7/extensions_design/ini_settings.html> for details.
 
-use std::{ffi::CString, os::raw::c_char, ptr};
+use std::{
+    ffi::{c_int, c_void, CString},
+    os::raw::c_char,
+    ptr,
+};
 
-use crate::{ffi::zend_ini_entry_def, ffi::zend_register_ini_entries, flags::IniEntryPermission};
+use crate::{
+    exception::catch_panic_or,
+    ffi::{zend_ini_entry, zend_ini_entry_def, zend_register_ini_entries, zend_string},
+    flags::{IniEntryPermission, ZendResult},
+    types::ZendStr,
+};
+
+/// The handler of the changes of an ini entry, given the old and new values.
+/// Returns whether the new value is accepted.
+type IniModifyHandler = Box<dyn Fn(Option<&str>, &str) -> bool + Send + Sync>;
 
 /// A Zend ini entry definition.
 ///
@@ -43,6 +56,61 @@ impl IniEntryDef {
         }
     }
 
+    /// Watches the changes of the value of the ini entry, e.g. with
+    /// `ini_set()`, so they are applied immediately instead of reading the
+    /// value on every call.
+    ///
+    /// The callback is called with the old and new values, converted with
+    /// [`FromIniValue`], when the entry is registered, when its value is
+    /// changed, and when it is restored at the end of the request. A new
+    /// value which cannot be converted is rejected, so `ini_set()` returns
+    /// `false` and the entry keeps its value.
+    ///
+    /// # Parameters
+    ///
+    /// * `callback` - The function called with the old value, if any, and the
+    ///   new value.
+    ///
+    /// # Example
+    ///
+    /// ```no_run
+    /// use std::sync::atomic::{AtomicI64, Ordering};
+    ///
+    /// use ext_php_rs::{flags::IniEntryPermission, zend::IniEntryDef};
+    ///
+    /// static MAX_DEPTH: AtomicI64 = AtomicI64::new(16);
+    ///
+    /// pub fn startup(ty: i32, mod_num: i32) -> i32 {
+    ///     let max_depth = IniEntryDef::new(
+    ///         "my_extension.max_depth".to_owned(),
+    ///         "16".to_owned(),
+    ///         IniEntryPermission::All,
+    ///     )
+    ///     .on_modify(|_old: Option<i64>, new: i64| MAX_DEPTH.store(new, Ordering::Relaxed));
+    ///     IniEntryDef::register(vec![max_depth], mod_num);
+    ///
+    ///     0
+    /// }
+    /// ```
+    pub fn on_modify<T, F>(mut self, callback: F) -> Self
+    where
+        T: FromIniValue,
+        F: Fn(Option<T>, T) + Send + Sync + 'static,
+    {
+        let handler: IniModifyHandler = Box::new(move |old, new| match T::from_ini_value(new) {
+            Some(new) => {
+                callback(old.and_then(T::from_ini_value), new);
+                true
+            }
+            None => false,
+        });
+        // The handler lives as long as the entry, i.e. until the module is
+        // shut down, like its name and value.
+        self.on_modify = Some(on_modify);
+        self.mh_arg1 = Box::into_raw(Box::new(handler)).cast::<c_void>();
+        self
+    }
+
     /// Converts the ini entry into a raw and pointer, releasing it to the
     /// C world.
     pub fn into_raw(self) -> *mut Self {
@@ -57,3 +125,110 @@ impl IniEntryDef {
         unsafe { zend_register_ini_entries(entries, module_number) };
     }
 }
+
+/// Calls the handler of the changes of an ini entry, set with
+/// [`IniEntryDef::on_modify`].
+extern "C" fn on_modify(
+    entry: *mut zend_ini_entry,
+    new_value: *mut zend_string,
+    mh_arg1: *mut c_void,
+    _: *mut c_void,
+    _: *mut c_void,
+    _: c_int,
+) -> c_int {
+    // SAFETY: `mh_arg1` is the handler set with the entry definition, and
+    // the engine passes the entry with its current value.
+    let Some(handler) = (unsafe { mh_arg1.cast::<IniModifyHandler>().as_ref() }) else {
+        return ZendResult::Failure.bits();
+    };
+    let old = unsafe { entry.as_ref().and_then(|entry| entry.value.as_ref()) };
+    let new = unsafe { new_value.as_ref() };
+    let old = old.and_then(|value| ZendStr::as_str(value).ok());
+    let Ok(new) = new.map_or(Ok(""), ZendStr::as_str) else {
+        return ZendResult::Failure.bits();
+    };
+
+    catch_panic_or(ZendResult::Failure.bits(), || {
+        if handler(old, new) {
+            ZendResult::Success.bits()
+        } else {
+            ZendResult::Failure.bits()
+        }
+    })
+}
+
+/// Values of ini entries, converted like the engine does for the entries of
+/// its own types.
+pub trait FromIniValue: Sized {
+    /// Converts the value of an ini entry, returning [`None`] if it is not
+    /// valid.
+    ///
+    /// # Parameters
+    ///
+    /// * `value` - The value, as set in the php.ini file or with `ini_set()`.
+    fn from_ini_value(value: &str) -> Option<Self>;
+}
+
+impl FromIniValue for String {
+    fn from_ini_value(value: &str) -> Option<Self> {
+        Some(value.to_owned())
+    }
+}
+
+impl FromIniValue for bool {
+    /// Converts the value like `zend_ini_parse_bool`, i.e. `on`, `yes` and
+    /// `true` are true, and other values are true if they are a non-zero
+    /// number.
+    fn from_ini_value(value: &str) -> Option<Self> {
+        let value = value.trim();
+        Some(
+            ["on", "yes", "true"]
+                .iter()
+                .any(|truthy| value.eq_ignore_ascii_case(truthy))
+                || i64::from_ini_value(value).is_some_and(|value| value != 0),
+        )
+    }
+}
+
+impl FromIniValue for i64 {
+    /// Converts the value like `zend_ini_parse_quantity`, i.e. an integer
+    /// with an optional `K`, `M` or `G` suffix multiplying it by 1024,
+    /// 1024², or 1024³.
+    fn from_ini_value(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (number, shift) = match value.as_bytes().last() {
+            Some(b'k' | b'K') => (&value[..value.len() - 1], 10),
+            Some(b'm' | b'M') => (&value[..value.len() - 1], 20),
+            Some(b'g' | b'G') => (&value[..value.len() - 1], 30),
+            _ => (value, 0),
+        };
+        number
+            .trim_end()
+            .parse::<i64>()
+            .ok()?
+            .checked_mul(1 << shift)
+    }
+}
+
+impl FromIniValue for f64 {
+    fn from_ini_value(value: &str) -> Option<Self> {
+        value.trim().parse().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_ini_value() {
+        assert_eq!(bool::from_ini_value("On"), Some(true));
+        assert_eq!(bool::from_ini_value("2"), Some(true));
+        assert_eq!(bool::from_ini_value("off"), Some(false));
+        assert_eq!(bool::from_ini_value(""), Some(false));
+        assert_eq!(i64::from_ini_value("128M"), Some(128 << 20));
+        assert_eq!(i64::from_ini_value(" -1 "), Some(-1));
+        assert_eq!(i64::from_ini_value("lots"), None);
+        assert_eq!(f64::from_ini_value("0.5"), Some(0.5));
+    }
+}
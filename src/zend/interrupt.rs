@@ -0,0 +1,208 @@
+//! Interrupts of the PHP VM, to run code on a thread executing PHP code from
+//! another thread.
+//!
+//! The VM checks its interrupt flag on function calls and backward jumps, and
+//! calls the `zend_interrupt_function` hook on the executing thread when the
+//! flag is set. Other threads set the flag of a thread through the
+//! [`InterruptHandle`] of that thread, and the handlers registered with
+//! [`register_interrupt_handler`] then run on the interrupted thread, between
+//! two opcodes, where they can safely inspect the state of the engine, e.g. to
+//! sample the call stack or measure the memory usage of a long running
+//! request.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    thread::{self, ThreadId},
+    time::Instant,
+};
+
+use parking_lot::{const_mutex, const_rwlock, Mutex, RwLock};
+
+use crate::{
+    exception::catch_panic_or,
+    ffi::{zend_execute_data, zend_interrupt_function},
+    zend::{ExecuteData, ExecutorGlobals},
+};
+
+#[cfg(php82)]
+type InterruptFlag = crate::ffi::zend_atomic_bool;
+#[cfg(not(php82))]
+type InterruptFlag = bool;
+
+type InterruptHandler = Box<dyn Fn(&ExecuteData) + Send + Sync>;
+type InterruptHook = unsafe extern "C" fn(*mut zend_execute_data);
+
+static HANDLERS: RwLock<Vec<InterruptHandler>> = const_rwlock(Vec::new());
+static PREVIOUS_HOOK: RwLock<Option<Option<InterruptHook>>> = const_rwlock(None);
+static RUNNING: Mutex<Vec<RunningRequest>> = const_mutex(Vec::new());
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A request being executed, which background threads of the crate interrupt
+/// to run the interrupt handlers on its thread.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct RunningRequest {
+    /// The unique identifier of the request.
+    pub id: u64,
+    /// The thread executing the request.
+    pub thread: ThreadId,
+    /// When the request started.
+    pub started: Instant,
+    /// The handle to interrupt the VM of the thread.
+    pub handle: InterruptHandle,
+}
+
+/// A handle to request an interrupt of the PHP VM of a thread from any other
+/// thread.
+///
+/// The handle points to the executor globals of the thread it was created on,
+/// so it must not be used after that thread has shut down PHP.
+#[derive(Debug, Clone, Copy)]
+pub struct InterruptHandle {
+    flag: *mut InterruptFlag,
+    timed_out: *mut InterruptFlag,
+}
+
+// SAFETY: The interrupt flag is meant to be set from other threads, e.g. by
+// the timeout signal handler, and is atomic since PHP 8.2.
+unsafe impl Send for InterruptHandle {}
+unsafe impl Sync for InterruptHandle {}
+
+impl InterruptHandle {
+    /// Returns the handle of the VM of the current thread.
+    pub fn current() -> Self {
+        let mut globals = ExecutorGlobals::get_mut();
+        Self {
+            flag: &mut globals.vm_interrupt,
+            timed_out: &mut globals.timed_out,
+        }
+    }
+
+    /// Requests an interrupt of the VM, which calls the interrupt handlers on
+    /// the thread of the VM once it reaches the next function call or backward
+    /// jump. Interrupts requested while the thread is not executing PHP code
+    /// are handled once it does.
+    pub fn interrupt(&self) {
+        store(self.flag);
+    }
+
+    /// Marks the execution of the VM as timed out and requests an interrupt,
+    /// which aborts the request with the fatal error of `max_execution_time`
+    /// once the thread reaches the next function call or backward jump,
+    /// instead of calling the interrupt handlers.
+    pub fn time_out(&self) {
+        store(self.timed_out);
+        store(self.flag);
+    }
+}
+
+/// Sets a flag of the executor globals.
+fn store(flag: *mut InterruptFlag) {
+    cfg_if::cfg_if! {
+        if #[cfg(php82)] {
+            unsafe { crate::ffi::zend_atomic_bool_store(flag, true) };
+        } else {
+            unsafe { flag.write_volatile(true) };
+        }
+    }
+}
+
+/// Registers a handler called on the interrupted thread when an interrupt of
+/// its VM was requested, with the frame being executed.
+///
+/// Handlers are called in registration order and before the interrupt
+/// function previously installed, e.g. by another extension. This must be
+/// called during module startup.
+///
+/// # Parameters
+///
+/// * `handler` - The handler to register.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::{thread, time::Duration};
+///
+/// use ext_php_rs::zend::interrupt::{register_interrupt_handler, InterruptHandle};
+///
+/// register_interrupt_handler(|frame| {
+///     if let Some(function) = frame.function() {
+///         println!("interrupted in {:?}", function.name());
+///     }
+/// });
+///
+/// // From a thread executing PHP code:
+/// let handle = InterruptHandle::current();
+/// thread::spawn(move || {
+///     thread::sleep(Duration::from_secs(1));
+///     handle.interrupt();
+/// });
+/// ```
+pub fn register_interrupt_handler<F>(handler: F)
+where
+    F: Fn(&ExecuteData) + Send + Sync + 'static,
+{
+    {
+        let mut previous = PREVIOUS_HOOK.write();
+        if previous.is_none() {
+            // SAFETY: The hook is only replaced during module startup, which is
+            // single threaded.
+            unsafe {
+                *previous = Some(zend_interrupt_function);
+                zend_interrupt_function = Some(interrupt_dispatcher);
+            }
+        }
+    }
+
+    HANDLERS.write().push(Box::new(handler));
+}
+
+unsafe extern "C" fn interrupt_dispatcher(execute_data: *mut zend_execute_data) {
+    if let Some(frame) = execute_data.as_ref() {
+        // Handlers may execute PHP code which is interrupted in turn, so the
+        // lock must be re-entrant.
+        let handlers = HANDLERS.read_recursive();
+        for handler in handlers.iter() {
+            catch_panic_or((), || handler(frame));
+        }
+    }
+
+    if let Some(Some(previous)) = *PREVIOUS_HOOK.read_recursive() {
+        previous(execute_data);
+    }
+}
+
+/// Records that a request started on this thread, if interrupt handlers are
+/// registered. This is called when a request starts.
+pub(crate) fn track_request() {
+    if HANDLERS.read().is_empty() {
+        return;
+    }
+
+    let request = RunningRequest {
+        id: NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed),
+        thread: thread::current().id(),
+        started: Instant::now(),
+        handle: InterruptHandle::current(),
+    };
+    let mut running = RUNNING.lock();
+    running.retain(|running| running.thread != request.thread);
+    running.push(request);
+}
+
+/// Records that the request of this thread ended. This is called when a
+/// request ends.
+pub(crate) fn untrack_request() {
+    let id = thread::current().id();
+    RUNNING.lock().retain(|running| running.thread != id);
+}
+
+/// Calls the given function with each request currently running.
+///
+/// The registry stays locked during the calls, so a request cannot end, and
+/// its thread cannot free the executor globals its [`InterruptHandle`] points
+/// to, while it is being interrupted.
+pub(crate) fn for_each_running(mut func: impl FnMut(&RunningRequest)) {
+    for request in RUNNING.lock().iter() {
+        func(request);
+    }
+}
@@ -0,0 +1,269 @@
+//! Observers of the lifecycle of requests.
+//!
+//! The request startup and shutdown functions belong to the extension which
+//! owns the [`ModuleBuilder`]. Libraries built on the crate, e.g. metrics
+//! exporters or connection pools, can hook the lifecycle of requests without
+//! owning the module by registering a [`RequestObserver`] during module
+//! startup instead.
+//!
+//! Observers are notified of the requests handled by a module built with
+//! [`ModuleBuilder`] or run with [`Embed::run`]. They are notified before the
+//! request startup function of the module is called, and after its request
+//! shutdown function returns.
+//!
+//! [`ModuleBuilder`]: crate::builders::ModuleBuilder
+//! [`Embed::run`]: crate::embed::Embed::run
+
+use std::{
+    cell::{Cell, RefCell},
+    ffi::CStr,
+    fmt::{self, Debug},
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+use parking_lot::{const_rwlock, RwLock};
+
+use crate::{
+    exception::catch_panic_or,
+    zend::{ProcessGlobals, SapiModule},
+};
+
+type Hook = Box<dyn Fn() + Send + Sync>;
+
+static OBSERVERS: RwLock<Vec<RequestObserver>> = const_rwlock(Vec::new());
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(1);
+
+thread_local! {
+    /// Whether observers were notified of the startup of a request.
+    static IN_REQUEST: Cell<bool> = const { Cell::new(false) };
+    /// The identifier of the current request, once requested.
+    static REQUEST_ID: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// An observer of the startup and shutdown of requests.
+///
+/// Observers are notified of the startup of a request in ascending order of
+/// priority, and of its shutdown in the reverse order, so an observer with a
+/// lower priority is set up before and torn down after those with a higher
+/// one. Observers with the same priority are notified of the startup in
+/// registration order.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::zend::lifecycle::RequestObserver;
+///
+/// // Set up before the observers with the default priority of 0.
+/// RequestObserver::new()
+///     .priority(-10)
+///     .on_startup(|| println!("request started"))
+///     .on_shutdown(|| println!("request ended"))
+///     .register();
+/// ```
+#[derive(Default)]
+pub struct RequestObserver {
+    priority: i32,
+    startup: Option<Hook>,
+    shutdown: Option<Hook>,
+}
+
+impl RequestObserver {
+    /// Creates an observer with the default priority of 0, which is not
+    /// notified of anything.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the priority of the observer.
+    ///
+    /// # Parameters
+    ///
+    /// * `priority` - The priority, lower priorities being notified first at
+    ///   the startup of requests and last at their shutdown.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets the function called when a request starts up.
+    pub fn on_startup<F>(mut self, hook: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.startup = Some(Box::new(hook));
+        self
+    }
+
+    /// Sets the function called when a request shuts down.
+    pub fn on_shutdown<F>(mut self, hook: F) -> Self
+    where
+        F: Fn() + Send + Sync + 'static,
+    {
+        self.shutdown = Some(Box::new(hook));
+        self
+    }
+
+    /// Registers the observer.
+    ///
+    /// This should be called during module startup, before any request
+    /// starts up.
+    pub fn register(self) {
+        let mut observers = OBSERVERS.write();
+        let index = observers.partition_point(|observer| observer.priority <= self.priority);
+        observers.insert(index, self);
+    }
+}
+
+impl Debug for RequestObserver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestObserver")
+            .field("priority", &self.priority)
+            .field("startup", &self.startup.is_some())
+            .field("shutdown", &self.shutdown.is_some())
+            .finish()
+    }
+}
+
+/// Returns the identifier of the current request, to correlate the logs and
+/// traces of a request.
+///
+/// The identifier is read from the `X-Request-Id` header of the request if
+/// present, and generated from the process ID and a counter otherwise. It is
+/// only available between the startup and shutdown of the requests observers
+/// are notified of.
+pub fn request_id() -> Option<String> {
+    if !IN_REQUEST.get() {
+        return None;
+    }
+
+    REQUEST_ID.with(|id| {
+        let mut id = id.borrow_mut();
+        if id.is_none() {
+            *id = Some(request_id_header().unwrap_or_else(|| {
+                format!(
+                    "{:x}-{:x}",
+                    std::process::id(),
+                    NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed)
+                )
+            }));
+        }
+        id.clone()
+    })
+}
+
+/// Reads the `X-Request-Id` header of the current request.
+///
+/// The header is read through the SAPI, which exposes the headers of the
+/// request as CGI variables, and from `$_SERVER` if the SAPI cannot look up
+/// variables, e.g. the CLI.
+fn request_id_header() -> Option<String> {
+    const NAME: &CStr = c"HTTP_X_REQUEST_ID";
+
+    let getenv = SapiModule::get().getenv;
+    let header = match getenv {
+        // SAFETY: The SAPI returns a NUL terminated string it owns, if any.
+        Some(getenv) => {
+            unsafe { getenv(NAME.as_ptr(), NAME.count_bytes()).as_ref() }.map(|header| {
+                unsafe { CStr::from_ptr(header) }
+                    .to_string_lossy()
+                    .into_owned()
+            })
+        }
+        None => ProcessGlobals::get()
+            .http_server_vars()
+            .and_then(|server| server.get(NAME.to_str().ok()?))
+            .and_then(|header| header.str())
+            .map(ToString::to_string),
+    };
+    header.filter(|header| !header.is_empty())
+}
+
+/// Returns whether observers were notified of the startup of a request on
+/// this thread, which did not shut down yet.
+pub(crate) fn in_request() -> bool {
+    IN_REQUEST.get()
+}
+
+/// Notifies the observers that a request started up.
+pub(crate) fn request_startup() {
+    IN_REQUEST.set(true);
+
+    // Observers may run requests of their own, e.g. through `Embed::run`.
+    let observers = OBSERVERS.read_recursive();
+    for hook in observers
+        .iter()
+        .filter_map(|observer| observer.startup.as_ref())
+    {
+        catch_panic_or((), hook);
+    }
+}
+
+/// Notifies the observers that a request shut down.
+pub(crate) fn request_shutdown() {
+    let observers = OBSERVERS.read_recursive();
+    for hook in observers
+        .iter()
+        .rev()
+        .filter_map(|observer| observer.shutdown.as_ref())
+    {
+        catch_panic_or((), hook);
+    }
+    IN_REQUEST.set(false);
+    REQUEST_ID.with(|id| *id.borrow_mut() = None);
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embed::Embed;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static EVENTS: RefCell<Vec<&'static str>> = const { RefCell::new(Vec::new()) };
+    }
+
+    fn record(event: &'static str) -> impl Fn() + Send + Sync {
+        move || EVENTS.with(|events| events.borrow_mut().push(event))
+    }
+
+    #[test]
+    fn test_request_observers() {
+        RequestObserver::new()
+            .priority(10)
+            .on_startup(record("late startup"))
+            .on_shutdown(record("late shutdown"))
+            .register();
+        RequestObserver::new()
+            .priority(-10)
+            .on_startup(record("early startup"))
+            .on_shutdown(record("early shutdown"))
+            .register();
+
+        Embed::run(|| {
+            assert_eq!(
+                EVENTS.with(|events| events.borrow().clone()),
+                ["early startup", "late startup"]
+            );
+            let id = request_id().expect("should have a request id");
+            assert!(!id.is_empty());
+            assert_eq!(request_id(), Some(id.clone()));
+
+            // The hooks of another module do not notify the observers again.
+            crate::internal::start_request();
+            crate::internal::finish_request();
+            assert_eq!(EVENTS.with(|events| events.borrow().len()), 2);
+            assert_eq!(request_id(), Some(id));
+        });
+        assert_eq!(request_id(), None);
+        assert_eq!(
+            EVENTS.with(|events| events.take()),
+            [
+                "early startup",
+                "late startup",
+                "late shutdown",
+                "early shutdown"
+            ]
+        );
+    }
+}
@@ -1,21 +1,32 @@
 //! Types used to interact with the Zend engine.
 
 mod _type;
+pub mod autoload;
+pub mod backtrace;
 pub mod ce;
 mod class;
+pub mod compile;
 mod ex;
 mod function;
+pub mod gc;
 mod globals;
 mod handlers;
+pub mod headers;
 mod ini_entry_def;
+pub mod interrupt;
+pub mod lifecycle;
 mod linked_list;
 mod module;
+pub mod observer;
+pub mod opcache;
 mod streams;
 mod try_catch;
+pub mod tsrm;
 
 use crate::{
     error::Result,
-    ffi::{php_printf, sapi_module},
+    ffi::{php_error_docref, php_printf, sapi_module, zend_error},
+    flags::ErrorType,
 };
 use std::ffi::CString;
 
@@ -24,19 +35,19 @@ pub use class::ClassEntry;
 pub use ex::ExecuteData;
 pub use function::Function;
 pub use function::FunctionEntry;
-pub use globals::ExecutorGlobals;
 pub use globals::CompilerGlobals;
+pub use globals::ExecutorGlobals;
 pub use globals::FileGlobals;
 pub use globals::ProcessGlobals;
 pub use globals::SapiGlobals;
-pub use globals::SapiModule;
 pub use globals::SapiHeader;
 pub use globals::SapiHeaders;
+pub use globals::SapiModule;
 pub use globals::SapiRequestInfo;
 pub use handlers::ZendObjectHandlers;
-pub use ini_entry_def::IniEntryDef;
+pub use ini_entry_def::{FromIniValue, IniEntryDef};
 pub use linked_list::ZendLinkedList;
-pub use module::ModuleEntry;
+pub use module::{BuildInfo, ModuleEntry};
 pub use streams::*;
 pub(crate) use try_catch::panic_wrapper;
 pub use try_catch::{bailout, try_catch, try_catch_first};
@@ -64,6 +75,83 @@ pub fn printf(message: &str) -> Result<()> {
     Ok(())
 }
 
+/// Raises a PHP diagnostic of the given level, the same way internal
+/// functions do with `php_error_docref`. The message is prefixed with the name
+/// of the currently executing function, e.g. `Warning: my_function(): message`.
+///
+/// Errors of level [`ErrorType::Error`] and similar fatal levels stop the
+/// execution of the script. Prefer throwing an exception in that case.
+///
+/// # Arguments
+///
+/// * `level` - The level of the diagnostic.
+/// * `message` - The message to report.
+///
+/// # Returns
+///
+/// Nothing on success, error if the message could not be converted to a
+/// [`CString`].
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::{flags::ErrorType, zend};
+///
+/// zend::error(ErrorType::Warning, "Something looks wrong").unwrap();
+/// ```
+pub fn error(level: ErrorType, message: &str) -> Result<()> {
+    let message = CString::new(message)?;
+    unsafe {
+        php_error_docref(
+            std::ptr::null(),
+            level.bits() as _,
+            FORMAT_STR.as_ptr().cast(),
+            message.as_ptr(),
+        );
+    };
+    Ok(())
+}
+
+/// Raises a PHP diagnostic of the given level through `zend_error`. Unlike
+/// [`error`], the message is not prefixed with the current function name,
+/// which is useful for diagnostics not tied to a function call.
+///
+/// # Arguments
+///
+/// * `level` - The level of the diagnostic.
+/// * `message` - The message to report.
+///
+/// # Returns
+///
+/// Nothing on success, error if the message could not be converted to a
+/// [`CString`].
+pub fn engine_error(level: ErrorType, message: &str) -> Result<()> {
+    let message = CString::new(message)?;
+    unsafe {
+        zend_error(
+            level.bits() as _,
+            FORMAT_STR.as_ptr().cast(),
+            message.as_ptr(),
+        );
+    };
+    Ok(())
+}
+
+/// Raises an `E_NOTICE` diagnostic. See [`error`].
+pub fn notice(message: &str) -> Result<()> {
+    error(ErrorType::Notice, message)
+}
+
+/// Raises an `E_WARNING` diagnostic. See [`error`].
+pub fn warning(message: &str) -> Result<()> {
+    error(ErrorType::Warning, message)
+}
+
+/// Raises an `E_DEPRECATED` diagnostic. See [`error`].
+pub fn deprecated(message: &str) -> Result<()> {
+    error(ErrorType::Deprecated, message)
+}
+
 /// Get the name of the SAPI module.
 pub fn php_sapi_name() -> String {
     let c_str = unsafe { std::ffi::CStr::from_ptr(sapi_module.name) };
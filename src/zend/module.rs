@@ -1,7 +1,24 @@
 //! Builder and objects for creating modules in PHP. A module is the base of a
 //! PHP extension.
 
-use crate::ffi::zend_module_entry;
+use std::{
+    ffi::{c_int, CString},
+    fmt::{self, Display},
+    mem, ptr,
+};
+
+use once_cell::sync::OnceCell;
+
+use crate::{
+    error::{php_error, Error, Result},
+    ffi::{module_registry, zend_hash_str_find, zend_module_entry, ZEND_MODULE_API_NO},
+    flags::{ErrorType, ZendResult},
+    PHP_DEBUG, PHP_ZTS,
+};
+
+/// The error reported by the startup function of a module entry returned by
+/// [`ModuleEntry::incompatible`].
+static INCOMPATIBLE: OnceCell<String> = OnceCell::new();
 
 /// A Zend module entry, also known as an extension.
 pub type ModuleEntry = zend_module_entry;
@@ -12,4 +29,189 @@ impl ModuleEntry {
     pub fn into_raw(self) -> *mut Self {
         Box::into_raw(Box::new(self))
     }
+
+    /// Returns a module entry which the running engine accepts, for an
+    /// extension which cannot be loaded into it. Its startup function raises
+    /// the error as a startup warning and fails, so PHP refuses the extension
+    /// with a clear message instead of reading a module entry built for
+    /// another engine.
+    ///
+    /// The entry carries the API number and build ID of the running engine,
+    /// and no functions, as the layout of the module entry is the same in
+    /// every supported version of PHP.
+    ///
+    /// # Parameters
+    ///
+    /// * `name` - The name of the extension.
+    /// * `error` - The reason the extension cannot be loaded.
+    pub fn incompatible(name: &str, error: &Error) -> Self {
+        extern "C" fn startup(_: c_int, _: c_int) -> c_int {
+            if let Some(error) = INCOMPATIBLE.get() {
+                php_error(ErrorType::CoreWarning, error);
+            }
+            ZendResult::Failure.bits()
+        }
+
+        let _ = INCOMPATIBLE.set(format!("Failed to load PHP module {name}: {error}"));
+        let name = CString::new(name).unwrap_or_default().into_raw();
+        let core = core_module();
+        Self {
+            size: core.map_or(mem::size_of::<Self>() as u16, |core| core.size),
+            zend_api: core.map_or(ZEND_MODULE_API_NO, |core| core.zend_api),
+            zend_debug: core.map_or(u8::from(PHP_DEBUG), |core| core.zend_debug),
+            zts: core.map_or(u8::from(PHP_ZTS), |core| core.zts),
+            ini_entry: ptr::null(),
+            deps: ptr::null(),
+            name,
+            functions: ptr::null(),
+            module_startup_func: Some(startup),
+            module_shutdown_func: None,
+            request_startup_func: None,
+            request_shutdown_func: None,
+            info_func: None,
+            version: ptr::null(),
+            globals_size: 0,
+            #[cfg(not(php_zts))]
+            globals_ptr: ptr::null_mut(),
+            #[cfg(php_zts)]
+            globals_id_ptr: ptr::null_mut(),
+            globals_ctor: None,
+            globals_dtor: None,
+            post_deactivate_func: None,
+            module_started: 0,
+            type_: 0,
+            handle: ptr::null_mut(),
+            module_number: 0,
+            build_id: core.map_or(ptr::null(), |core| core.build_id),
+        }
+    }
+}
+
+/// Returns the entry of the core module of the running engine, or [`None`]
+/// if the engine has not been started.
+fn core_module() -> Option<&'static ModuleEntry> {
+    let core =
+        unsafe { zend_hash_str_find(std::ptr::addr_of!(module_registry), c"core".as_ptr(), 4) };
+    // SAFETY: The module registry stores pointers to module entries, whose
+    // header has the same layout in every version of PHP.
+    unsafe { core.as_ref()?.value.ptr.cast::<ModuleEntry>().as_ref() }
+}
+
+/// The build of PHP an extension is compiled for, or the build of the engine
+/// it is loaded into. An extension can only be loaded into an engine with the
+/// same build.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildInfo {
+    /// The module API number, e.g. `20230831` for PHP 8.3.
+    pub api: u32,
+    /// Whether the build is thread-safe.
+    pub zts: bool,
+    /// Whether the build is a debug build.
+    pub debug: bool,
+}
+
+impl BuildInfo {
+    /// The build of PHP the extension is compiled for.
+    pub const COMPILED: Self = Self {
+        api: ZEND_MODULE_API_NO,
+        zts: PHP_ZTS,
+        debug: PHP_DEBUG,
+    };
+
+    /// Returns the build of the running engine, read from the header of its
+    /// core module, or [`None`] if the engine has not been started.
+    pub fn running() -> Option<Self> {
+        let entry = core_module()?;
+        Some(Self {
+            api: entry.zend_api,
+            zts: entry.zts != 0,
+            debug: entry.zend_debug != 0,
+        })
+    }
+
+    /// Returns the PHP version corresponding to the module API number, e.g.
+    /// `8.3`, or [`None`] if the API number is unknown.
+    pub fn php_version(&self) -> Option<&'static str> {
+        Some(match self.api {
+            20200930 => "8.0",
+            20210902 => "8.1",
+            20220829 => "8.2",
+            20230831 => "8.3",
+            20240924 => "8.4",
+            _ => return None,
+        })
+    }
+
+    /// Checks that an extension compiled for this crate's PHP build can be
+    /// loaded into the running engine. This is called by the `get_module`
+    /// function generated by [`macro@crate::php_module`] before the module is
+    /// built, as the module entry of an extension built for another version of
+    /// PHP cannot be read by the engine. The function returns
+    /// [`ModuleEntry::incompatible`] instead when the check fails.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::IncompatibleBuild`] if the running engine has another
+    /// API number, thread-safety or debug mode. Nothing is checked if the
+    /// engine has not been started.
+    pub fn check_compatibility() -> Result<()> {
+        match Self::running() {
+            Some(running) if running != Self::COMPILED => {
+                Err(Error::IncompatibleBuild(Self::COMPILED, running))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.php_version() {
+            Some(version) => write!(f, "PHP {version}")?,
+            None => write!(f, "PHP API {}", self.api)?,
+        }
+        write!(f, " {}", if self.zts { "ZTS" } else { "NTS" })?;
+        if self.debug {
+            write!(f, " debug")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_info_display() {
+        let built = BuildInfo {
+            api: 20230831,
+            zts: false,
+            debug: false,
+        };
+        let running = BuildInfo {
+            api: 20220829,
+            zts: true,
+            debug: true,
+        };
+        assert_eq!(built.to_string(), "PHP 8.3 NTS");
+        assert_eq!(running.to_string(), "PHP 8.2 ZTS debug");
+        assert_eq!(
+            Error::IncompatibleBuild(built, running).to_string(),
+            "Extension built for PHP 8.3 NTS, loaded into PHP 8.2 ZTS debug."
+        );
+
+        let unknown = BuildInfo { api: 1, ..built };
+        assert_eq!(unknown.to_string(), "PHP API 1 NTS");
+    }
+
+    #[cfg(feature = "embed")]
+    #[test]
+    fn test_build_info_running() {
+        crate::embed::Embed::run(|| {
+            assert_eq!(BuildInfo::running(), Some(BuildInfo::COMPILED));
+            assert!(BuildInfo::check_compatibility().is_ok());
+        });
+    }
 }
@@ -0,0 +1,627 @@
+//! Observers of the execution of PHP code.
+//!
+//! Function call observers, built on the observer API of the Zend engine, are
+//! notified when a function, either defined in PHP code or by an extension,
+//! begins and ends executing. They are the building block of profilers and
+//! APM extensions.
+//!
+//! The engine asks which observers are interested in a function the first
+//! time the function is called in a request, so observers restricted to some
+//! functions with [`FcallObserver::filter`] cost nothing for the others.
+//!
+//! Statement handlers are notified before each statement of PHP code is
+//! executed, which step debuggers and line coverage tools are built on.
+//!
+//! Fiber observers, from PHP 8.1, are notified when fibers are created,
+//! switched and destroyed, so event loops can track the fibers they schedule.
+//!
+//! Observers and handlers must be registered during module startup, before
+//! the engine finishes starting up. A panic in an observer or handler raises
+//! an `E_WARNING` instead of aborting the process.
+
+use std::{collections::BTreeMap, fmt::Debug, mem::MaybeUninit, ptr, sync::Arc};
+
+use parking_lot::{const_rwlock, RwLock};
+
+use crate::{
+    exception::catch_panic_or,
+    ffi::{
+        zend_execute_data, zend_extension, zend_observer_fcall_handlers,
+        zend_observer_fcall_register, zend_register_extension, zval, ZEND_COMPILE_EXTENDED_STMT,
+    },
+    types::Zval,
+    zend::{CompilerGlobals, ExecuteData, Function},
+};
+#[cfg(any(docs, php81))]
+use crate::{
+    ffi::{
+        zend_fiber_context, zend_observer_fiber_destroy_register,
+        zend_observer_fiber_init_register, zend_observer_fiber_switch_register,
+    },
+    zend::ExecutorGlobals,
+};
+
+type Filter = Box<dyn Fn(&CallFrame) -> bool + Send + Sync>;
+type BeginHandler = Box<dyn Fn(&CallFrame) + Send + Sync>;
+type EndHandler = Box<dyn Fn(&CallFrame, Option<&Zval>) + Send + Sync>;
+type StatementHandler = Box<dyn Fn(&Statement) + Send + Sync>;
+#[cfg(any(docs, php81))]
+type FiberHandler = Box<dyn Fn(FiberId) + Send + Sync>;
+#[cfg(any(docs, php81))]
+type FiberSwitchHandler = Box<dyn Fn(FiberId, FiberId) + Send + Sync>;
+
+static OBSERVERS: RwLock<Vec<FcallObserver>> = const_rwlock(Vec::new());
+/// The indices of the observers interested in each function, keyed by
+/// [`function_key`].
+static OBSERVED: RwLock<BTreeMap<usize, Arc<[usize]>>> = const_rwlock(BTreeMap::new());
+static STATEMENT_HANDLERS: RwLock<Vec<StatementHandler>> = const_rwlock(Vec::new());
+#[cfg(any(docs, php81))]
+static FIBER_OBSERVERS: RwLock<Vec<FiberObserver>> = const_rwlock(Vec::new());
+
+/// A function call, passed to function call observers.
+#[derive(Clone, Copy)]
+pub struct CallFrame<'a> {
+    execute_data: &'a ExecuteData,
+}
+
+impl<'a> CallFrame<'a> {
+    /// Returns the execute data of the call.
+    pub fn execute_data(&self) -> &'a ExecuteData {
+        self.execute_data
+    }
+
+    /// Returns the called function.
+    pub fn function(&self) -> Option<&'a Function> {
+        self.execute_data.function()
+    }
+
+    /// Returns the name of the called function, or [`None`] for the main
+    /// script of a file and for evaluated code.
+    pub fn function_name(&self) -> Option<&'a str> {
+        self.function()?.name()
+    }
+
+    /// Returns the name of the class the called function is a method of.
+    pub fn class_name(&self) -> Option<&'a str> {
+        self.function()?.class()?.name()
+    }
+
+    /// Returns the file the called function is defined in, or [`None`] for
+    /// internal functions.
+    pub fn file(&self) -> Option<&'a str> {
+        self.function()?.filename()
+    }
+
+    /// Returns the line the called function is defined on, or [`None`] for
+    /// internal functions.
+    pub fn line(&self) -> Option<u32> {
+        self.function()?.line_start()
+    }
+
+    /// Returns the file and line the function was called from, which is the
+    /// line executed in the closest caller defined in PHP code.
+    pub fn call_site(&self) -> Option<(&'a str, u32)> {
+        let mut frame = self.execute_data.previous();
+        while let Some(ex) = frame {
+            if let Some(line) = ex.lineno() {
+                return Some((ex.function()?.filename()?, line));
+            }
+            frame = ex.previous();
+        }
+        None
+    }
+
+    /// Returns the arguments passed to the function.
+    ///
+    /// The arguments are only collected when requested, as most observers do
+    /// not need them.
+    pub fn args(&self) -> Vec<&'a Zval> {
+        self.execute_data.args()
+    }
+}
+
+impl Debug for CallFrame<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CallFrame")
+            .field("function_name", &self.function_name())
+            .field("class_name", &self.class_name())
+            .field("file", &self.file())
+            .field("line", &self.line())
+            .finish()
+    }
+}
+
+/// An observer of function calls, notified when the functions it is
+/// interested in begin and end executing.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::zend::observer::FcallObserver;
+///
+/// FcallObserver::new()
+///     .filter(|frame| frame.class_name() == Some("PDO"))
+///     .on_begin(|frame| {
+///         println!("PDO::{} called", frame.function_name().unwrap_or_default());
+///     })
+///     .on_end(|frame, retval| {
+///         println!("PDO::{} returned {:?}", frame.function_name().unwrap_or_default(), retval);
+///     })
+///     .register();
+/// ```
+#[derive(Default)]
+pub struct FcallObserver {
+    filter: Option<Filter>,
+    begin: Option<BeginHandler>,
+    end: Option<EndHandler>,
+}
+
+impl FcallObserver {
+    /// Creates an observer which observes every function and does nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restricts the functions the observer is interested in.
+    ///
+    /// The filter is called on the first call of each function in a request,
+    /// so it must only depend on the function being called, e.g. its name or
+    /// class, and not on the arguments of the call.
+    ///
+    /// # Parameters
+    ///
+    /// * `filter` - Returns whether the function of the given call is
+    ///   observed.
+    pub fn filter<F>(mut self, filter: F) -> Self
+    where
+        F: Fn(&CallFrame) -> bool + Send + Sync + 'static,
+    {
+        self.filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Sets the handler called when an observed function begins executing.
+    ///
+    /// # Parameters
+    ///
+    /// * `handler` - The handler, called with the call.
+    pub fn on_begin<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&CallFrame) + Send + Sync + 'static,
+    {
+        self.begin = Some(Box::new(handler));
+        self
+    }
+
+    /// Sets the handler called when an observed function ends executing.
+    ///
+    /// # Parameters
+    ///
+    /// * `handler` - The handler, called with the call and its return value.
+    ///   The return value is [`None`] if the function threw an exception or
+    ///   the request is bailing out.
+    pub fn on_end<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&CallFrame, Option<&Zval>) + Send + Sync + 'static,
+    {
+        self.end = Some(Box::new(handler));
+        self
+    }
+
+    /// Registers the observer, which stays registered until the process
+    /// exits. This must be called during module startup.
+    pub fn register(self) {
+        let mut observers = OBSERVERS.write();
+        if observers.is_empty() {
+            unsafe { zend_observer_fcall_register(Some(fcall_init)) };
+        }
+        observers.push(self);
+    }
+
+    fn observes(&self, frame: &CallFrame) -> bool {
+        // A panicking filter does not observe the function.
+        self.filter
+            .as_ref()
+            .is_none_or(|filter| catch_panic_or(false, || filter(frame)))
+    }
+}
+
+impl Debug for FcallObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FcallObserver")
+            .field("filter", &self.filter.is_some())
+            .field("begin", &self.begin.is_some())
+            .field("end", &self.end.is_some())
+            .finish()
+    }
+}
+
+/// Registers a function call observer, called whenever a function begins
+/// executing. Use [`FcallObserver`] to filter the observed functions or to be
+/// notified when functions end.
+///
+/// This must be called during module startup.
+///
+/// # Parameters
+///
+/// * `observer` - The observer, called with the call.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::zend::observer::register_fcall_observer;
+///
+/// register_fcall_observer(|frame| {
+///     if let Some((file, line)) = frame.call_site() {
+///         println!("{:?} called from {file}:{line}", frame.function_name());
+///     }
+/// });
+/// ```
+pub fn register_fcall_observer<F>(observer: F)
+where
+    F: Fn(&CallFrame) + Send + Sync + 'static,
+{
+    FcallObserver::new().on_begin(observer).register();
+}
+
+/// Returns the key identifying a function in [`OBSERVED`]. Closures are copies
+/// of the function they are created from, sharing its opcodes.
+fn function_key(func: &Function) -> usize {
+    if func.is_user() {
+        unsafe { func.op_array.opcodes as usize }
+    } else {
+        func as *const Function as usize
+    }
+}
+
+/// Returns the indices of the observers interested in the function of the
+/// given call, asking them if the function is not known yet.
+fn observers_of(frame: &CallFrame, func: &Function) -> Arc<[usize]> {
+    let key = function_key(func);
+    if let Some(observed) = OBSERVED.read().get(&key) {
+        return observed.clone();
+    }
+    update_observed(key, ask_observers(frame))
+}
+
+/// Asks the observers whether they are interested in the function of the
+/// given call, returning the indices of those which are.
+fn ask_observers(frame: &CallFrame) -> Arc<[usize]> {
+    OBSERVERS
+        .read_recursive()
+        .iter()
+        .enumerate()
+        .filter(|(_, observer)| observer.observes(frame))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Stores the observers interested in a function, only taking the write lock
+/// if they changed, as functions are initialized on every thread.
+fn update_observed(key: usize, observed: Arc<[usize]>) -> Arc<[usize]> {
+    if let Some(cached) = OBSERVED.read().get(&key) {
+        if **cached == *observed {
+            return cached.clone();
+        }
+    }
+    OBSERVED.write().insert(key, observed.clone());
+    observed
+}
+
+unsafe extern "C" fn fcall_init(
+    execute_data: *mut zend_execute_data,
+) -> zend_observer_fcall_handlers {
+    let mut handlers = zend_observer_fcall_handlers {
+        begin: None,
+        end: None,
+    };
+    let Some(ex) = execute_data.as_ref() else {
+        return handlers;
+    };
+    let Some(func) = ex.function() else {
+        return handlers;
+    };
+
+    // The function may be a new one reusing the memory of a function of a
+    // previous request, so the observers are always asked again.
+    let frame = CallFrame { execute_data: ex };
+    let observed = update_observed(function_key(func), ask_observers(&frame));
+
+    let observers = OBSERVERS.read_recursive();
+    if observed.iter().any(|&i| observers[i].begin.is_some()) {
+        handlers.begin = Some(fcall_begin);
+    }
+    if observed.iter().any(|&i| observers[i].end.is_some()) {
+        handlers.end = Some(fcall_end);
+    }
+    handlers
+}
+
+unsafe extern "C" fn fcall_begin(execute_data: *mut zend_execute_data) {
+    let Some(ex) = execute_data.as_ref() else {
+        return;
+    };
+    let Some(func) = ex.function() else {
+        return;
+    };
+    let frame = CallFrame { execute_data: ex };
+
+    // Observers may call functions themselves, so the locks must be
+    // re-entrant.
+    let observers = OBSERVERS.read_recursive();
+    for &i in observers_of(&frame, func).iter() {
+        if let Some(begin) = &observers[i].begin {
+            catch_panic_or((), || begin(&frame));
+        }
+    }
+}
+
+unsafe extern "C" fn fcall_end(execute_data: *mut zend_execute_data, retval: *mut zval) {
+    let Some(ex) = execute_data.as_ref() else {
+        return;
+    };
+    let Some(func) = ex.function() else {
+        return;
+    };
+    let frame = CallFrame { execute_data: ex };
+    let retval = retval.as_ref();
+
+    let observers = OBSERVERS.read_recursive();
+    for &i in observers_of(&frame, func).iter() {
+        if let Some(end) = &observers[i].end {
+            catch_panic_or((), || end(&frame, retval));
+        }
+    }
+}
+
+/// Identifies a fiber, or the main context of a request, in the
+/// notifications of fiber observers.
+///
+/// The identifier is the address of the context of the fiber, which may be
+/// reused by a new fiber once the fiber was destroyed. It can be sent to
+/// other threads, e.g. to the tasks of a runtime resuming the fiber.
+#[cfg(any(docs, php81))]
+#[cfg_attr(docs, doc(cfg(php81)))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct FiberId(usize);
+
+#[cfg(any(docs, php81))]
+impl FiberId {
+    /// Returns the fiber running on the current thread, or the main context
+    /// of the request outside of a fiber.
+    pub fn current() -> Self {
+        Self(ExecutorGlobals::get().current_fiber_context as usize)
+    }
+
+    /// Returns whether the identifier is the main context of the request of
+    /// the current thread, i.e. the code running outside of fibers.
+    pub fn is_main(&self) -> bool {
+        self.0 == ExecutorGlobals::get().main_fiber_context as usize
+    }
+
+    fn from_context(context: *mut zend_fiber_context) -> Self {
+        Self(context as usize)
+    }
+}
+
+/// An observer of fibers, notified when fibers are created, switched to and
+/// from, and destroyed, e.g. by an event loop tracking which fibers are
+/// suspended and which can be resumed.
+///
+/// The handlers are called on the thread of the request running the fibers,
+/// and must not switch fibers themselves.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::zend::observer::FiberObserver;
+///
+/// FiberObserver::new()
+///     .on_init(|fiber| println!("{fiber:?} created"))
+///     .on_switch(|from, to| println!("{from:?} -> {to:?}"))
+///     .on_destroy(|fiber| println!("{fiber:?} destroyed"))
+///     .register();
+/// ```
+#[cfg(any(docs, php81))]
+#[cfg_attr(docs, doc(cfg(php81)))]
+#[derive(Default)]
+pub struct FiberObserver {
+    init: Option<FiberHandler>,
+    switch: Option<FiberSwitchHandler>,
+    destroy: Option<FiberHandler>,
+}
+
+#[cfg(any(docs, php81))]
+impl FiberObserver {
+    /// Creates an observer which does nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the handler called when a fiber is created, before it starts.
+    ///
+    /// # Parameters
+    ///
+    /// * `handler` - The handler, called with the fiber.
+    pub fn on_init<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(FiberId) + Send + Sync + 'static,
+    {
+        self.init = Some(Box::new(handler));
+        self
+    }
+
+    /// Sets the handler called when the execution switches from a fiber to
+    /// another, i.e. when a fiber is started, resumed, suspended or returns.
+    /// Either side may be the main context of the request.
+    ///
+    /// # Parameters
+    ///
+    /// * `handler` - The handler, called with the fiber switched from and the
+    ///   fiber switched to.
+    pub fn on_switch<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(FiberId, FiberId) + Send + Sync + 'static,
+    {
+        self.switch = Some(Box::new(handler));
+        self
+    }
+
+    /// Sets the handler called when a fiber is destroyed, after it returned
+    /// or when it is collected while suspended.
+    ///
+    /// # Parameters
+    ///
+    /// * `handler` - The handler, called with the fiber.
+    pub fn on_destroy<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(FiberId) + Send + Sync + 'static,
+    {
+        self.destroy = Some(Box::new(handler));
+        self
+    }
+
+    /// Registers the observer, which stays registered until the process
+    /// exits. This must be called during module startup.
+    pub fn register(self) {
+        let mut observers = FIBER_OBSERVERS.write();
+        if observers.is_empty() {
+            unsafe {
+                zend_observer_fiber_init_register(Some(fiber_init));
+                zend_observer_fiber_switch_register(Some(fiber_switch));
+                zend_observer_fiber_destroy_register(Some(fiber_destroy));
+            }
+        }
+        observers.push(self);
+    }
+}
+
+#[cfg(any(docs, php81))]
+impl Debug for FiberObserver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FiberObserver")
+            .field("init", &self.init.is_some())
+            .field("switch", &self.switch.is_some())
+            .field("destroy", &self.destroy.is_some())
+            .finish()
+    }
+}
+
+#[cfg(any(docs, php81))]
+unsafe extern "C" fn fiber_init(context: *mut zend_fiber_context) {
+    let fiber = FiberId::from_context(context);
+    for observer in FIBER_OBSERVERS.read_recursive().iter() {
+        if let Some(init) = &observer.init {
+            catch_panic_or((), || init(fiber));
+        }
+    }
+}
+
+#[cfg(any(docs, php81))]
+unsafe extern "C" fn fiber_switch(from: *mut zend_fiber_context, to: *mut zend_fiber_context) {
+    let (from, to) = (FiberId::from_context(from), FiberId::from_context(to));
+    for observer in FIBER_OBSERVERS.read_recursive().iter() {
+        if let Some(switch) = &observer.switch {
+            catch_panic_or((), || switch(from, to));
+        }
+    }
+}
+
+#[cfg(any(docs, php81))]
+unsafe extern "C" fn fiber_destroy(context: *mut zend_fiber_context) {
+    let fiber = FiberId::from_context(context);
+    for observer in FIBER_OBSERVERS.read_recursive().iter() {
+        if let Some(destroy) = &observer.destroy {
+            catch_panic_or((), || destroy(fiber));
+        }
+    }
+}
+
+/// A statement of PHP code about to be executed, passed to statement
+/// handlers.
+#[derive(Debug, Clone, Copy)]
+pub struct Statement<'a> {
+    /// The file the statement is in.
+    pub file: &'a str,
+    /// The line the statement is on.
+    pub line: u32,
+    /// The call of the function executing the statement.
+    pub frame: CallFrame<'a>,
+}
+
+/// Registers a statement handler, called before each statement of PHP code
+/// is executed.
+///
+/// The handler is registered as the statement handler of a Zend extension,
+/// and the compiler is instructed to emit a statement opcode before every
+/// statement, which slows down the execution of all PHP code. Code compiled
+/// before the handler is registered, or cached by OPcache without the
+/// statement opcodes, does not call the handler.
+///
+/// This must be called during module startup.
+///
+/// # Parameters
+///
+/// * `handler` - The handler, called with the statement.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::zend::observer::register_statement_handler;
+///
+/// register_statement_handler(|statement| {
+///     println!("{}:{}", statement.file, statement.line);
+/// });
+/// ```
+pub fn register_statement_handler<F>(handler: F)
+where
+    F: Fn(&Statement) + Send + Sync + 'static,
+{
+    let mut handlers = STATEMENT_HANDLERS.write();
+    if handlers.is_empty() {
+        // SAFETY: All fields of the extension are pointers or optional
+        // functions, which are valid when zeroed.
+        let mut extension: zend_extension = unsafe { MaybeUninit::zeroed().assume_init() };
+        extension.name = c"ext-php-rs".as_ptr();
+        extension.version = concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr().cast();
+        extension.author = c"ext-php-rs contributors".as_ptr();
+        extension.URL = c"https://github.com/davidcole1340/ext-php-rs".as_ptr();
+        extension.copyright = c"Copyright (c) ext-php-rs contributors".as_ptr();
+        extension.activate = Some(statement_activate);
+        extension.statement_handler = Some(statement_dispatcher);
+        // The engine keeps a copy of the extension.
+        unsafe { zend_register_extension(&mut extension, ptr::null_mut()) };
+        enable_statements();
+    }
+    handlers.push(Box::new(handler));
+}
+
+/// Makes the compiler emit the statement opcodes which call the statement
+/// handlers.
+fn enable_statements() {
+    CompilerGlobals::get_mut().compiler_options |= ZEND_COMPILE_EXTENDED_STMT;
+}
+
+unsafe extern "C" fn statement_activate() {
+    enable_statements();
+}
+
+unsafe extern "C" fn statement_dispatcher(execute_data: *mut zend_execute_data) {
+    let Some(ex) = execute_data.as_ref() else {
+        return;
+    };
+    let (Some(file), Some(line)) = (ex.function().and_then(Function::filename), ex.lineno()) else {
+        return;
+    };
+    let statement = Statement {
+        file,
+        line,
+        frame: CallFrame { execute_data: ex },
+    };
+
+    // Handlers may execute PHP code themselves, so the lock must be
+    // re-entrant.
+    for handler in STATEMENT_HANDLERS.read_recursive().iter() {
+        catch_panic_or((), || handler(&statement));
+    }
+}
@@ -0,0 +1,405 @@
+//! Status and events of the opcode cache.
+
+use std::{
+    cell::Cell,
+    fmt::{self, Debug},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use parking_lot::{const_mutex, const_rwlock, Mutex, RwLock};
+
+use crate::{
+    types::{ZendCallable, ZendHashTable},
+    zend::{compile::register_compile_observer, lifecycle::RequestObserver},
+};
+
+/// A snapshot of the status of the opcode cache, as reported by the userland
+/// `opcache_get_status()` function.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct OpcacheStatus {
+    /// Whether the opcode cache is enabled.
+    pub enabled: bool,
+    /// Whether the cache is full.
+    pub cache_full: bool,
+    /// Whether a restart of the cache is pending.
+    pub restart_pending: bool,
+    /// Whether the cache is restarting.
+    pub restart_in_progress: bool,
+    /// The shared memory used by the cache, in bytes.
+    pub used_memory: u64,
+    /// The shared memory available to the cache, in bytes.
+    pub free_memory: u64,
+    /// The shared memory used by invalidated scripts, in bytes.
+    pub wasted_memory: u64,
+    /// The number of cached scripts.
+    pub cached_scripts: u64,
+    /// The number of keys of the cached scripts.
+    pub cached_keys: u64,
+    /// The maximum number of keys which can be cached.
+    pub max_cached_keys: u64,
+    /// The number of scripts served from the cache.
+    pub hits: u64,
+    /// The number of scripts which were not cached.
+    pub misses: u64,
+    /// The number of restarts caused by the cache running out of memory.
+    pub oom_restarts: u64,
+    /// The number of restarts caused by the hash table of keys being full.
+    pub hash_restarts: u64,
+    /// The number of restarts requested with `opcache_reset()`.
+    pub manual_restarts: u64,
+    /// The percentage of scripts served from the cache.
+    pub hit_rate: f64,
+}
+
+impl OpcacheStatus {
+    /// Captures the current status of the opcode cache, without the list of
+    /// cached scripts.
+    ///
+    /// This calls `opcache_get_status()`, so it must be called while a request
+    /// is running.
+    ///
+    /// # Returns
+    ///
+    /// The status, or [`None`] if the opcode cache extension is not loaded or
+    /// is disabled, e.g. for the CLI SAPI unless `opcache.enable_cli` is set.
+    pub fn capture() -> Option<Self> {
+        let get_status = ZendCallable::try_from_name("opcache_get_status").ok()?;
+        let status = get_status.try_call(vec![&false]).ok()?;
+        let status = status.array()?;
+        let memory = status.get("memory_usage").and_then(|memory| memory.array());
+        let statistics = status
+            .get("opcache_statistics")
+            .and_then(|statistics| statistics.array());
+
+        Some(Self {
+            enabled: flag(status, "opcache_enabled"),
+            cache_full: flag(status, "cache_full"),
+            restart_pending: flag(status, "restart_pending"),
+            restart_in_progress: flag(status, "restart_in_progress"),
+            used_memory: count(memory, "used_memory"),
+            free_memory: count(memory, "free_memory"),
+            wasted_memory: count(memory, "wasted_memory"),
+            cached_scripts: count(statistics, "num_cached_scripts"),
+            cached_keys: count(statistics, "num_cached_keys"),
+            max_cached_keys: count(statistics, "max_cached_keys"),
+            hits: count(statistics, "hits"),
+            misses: count(statistics, "misses"),
+            oom_restarts: count(statistics, "oom_restarts"),
+            hash_restarts: count(statistics, "hash_restarts"),
+            manual_restarts: count(statistics, "manual_restarts"),
+            hit_rate: statistics
+                .and_then(|statistics| statistics.get("opcache_hit_rate"))
+                .and_then(|rate| rate.double())
+                .unwrap_or(0.0),
+        })
+    }
+}
+
+/// Reads a boolean entry of a status array, defaulting to `false`.
+fn flag(array: &ZendHashTable, key: &str) -> bool {
+    array
+        .get(key)
+        .and_then(|value| value.bool())
+        .unwrap_or(false)
+}
+
+/// Reads an integer entry of a status array, defaulting to 0.
+fn count(array: Option<&ZendHashTable>, key: &str) -> u64 {
+    array
+        .and_then(|array| array.get(key))
+        .and_then(|value| value.long())
+        .and_then(|value| u64::try_from(value).ok())
+        .unwrap_or(0)
+}
+
+/// Why the opcode cache restarted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestartReason {
+    /// The shared memory of the cache was exhausted.
+    OutOfMemory,
+    /// The hash table of keys was full.
+    HashFull,
+    /// A restart was requested with `opcache_reset()`.
+    Manual,
+}
+
+/// An event of the opcode cache, detected by an [`OpcacheMonitor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpcacheEvent {
+    /// The cache restarted, discarding all cached scripts.
+    Restart(RestartReason),
+    /// The cache became full, so new scripts are compiled for each request
+    /// until it restarts.
+    CacheFull,
+    /// A restart of the cache was scheduled, which happens once no request
+    /// uses it anymore.
+    RestartPending,
+}
+
+/// The scripts loaded by the current request, split by whether they were
+/// served from the opcode cache.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RequestCacheStats {
+    /// The number of scripts served from the cache.
+    pub hits: u64,
+    /// The number of scripts compiled.
+    pub misses: u64,
+}
+
+/// The counters of an [`OpcacheMonitor`], accumulated over the lifetime of
+/// the process.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OpcacheCounters {
+    /// The number of requests which ended.
+    pub requests: u64,
+    /// The number of scripts served from the cache.
+    pub hits: u64,
+    /// The number of scripts compiled.
+    pub misses: u64,
+    /// The number of restarts of the cache.
+    pub restarts: u64,
+    /// The number of times the cache became full.
+    pub cache_full: u64,
+}
+
+type EventHandler = Box<dyn Fn(&OpcacheEvent) + Send + Sync>;
+
+static EVENT_HANDLERS: RwLock<Vec<EventHandler>> = const_rwlock(Vec::new());
+static MONITORED: AtomicBool = AtomicBool::new(false);
+static POLL_INTERVAL: Mutex<Duration> = const_mutex(Duration::from_secs(1));
+/// The time and result of the last poll of the status of the cache.
+static LAST_POLL: Mutex<Option<(Instant, OpcacheStatus)>> = const_mutex(None);
+
+static REQUESTS: AtomicU64 = AtomicU64::new(0);
+static HITS: AtomicU64 = AtomicU64::new(0);
+static MISSES: AtomicU64 = AtomicU64::new(0);
+static RESTARTS: AtomicU64 = AtomicU64::new(0);
+static CACHE_FULL: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    static REQUEST_STATS: Cell<RequestCacheStats> = const {
+        Cell::new(RequestCacheStats { hits: 0, misses: 0 })
+    };
+}
+
+/// Monitors the opcode cache, counting the scripts served from the cache by
+/// each request, and detecting restarts and cache-full conditions, so hosts
+/// can alert on cache thrash.
+///
+/// Hits and misses are counted as scripts are loaded. Restarts and cache-full
+/// conditions are detected by polling the status of the cache at the end of
+/// requests, at most once per poll interval, and notified to the event
+/// handlers on the thread of the request which polled.
+///
+/// # Example
+///
+/// ```no_run
+/// use ext_php_rs::zend::opcache::{OpcacheEvent, OpcacheMonitor};
+///
+/// OpcacheMonitor::new()
+///     .on_event(|event| {
+///         if let OpcacheEvent::Restart(reason) = event {
+///             eprintln!("opcache restarted: {reason:?}");
+///         }
+///     })
+///     .register();
+/// ```
+pub struct OpcacheMonitor {
+    poll_interval: Duration,
+    handlers: Vec<EventHandler>,
+}
+
+impl OpcacheMonitor {
+    /// Creates a monitor polling the status of the cache at most once per
+    /// second.
+    pub fn new() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(1),
+            handlers: vec![],
+        }
+    }
+
+    /// Sets the minimum interval between two polls of the status of the
+    /// cache, which calls `opcache_get_status()`.
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Adds a handler notified of the events of the cache.
+    pub fn on_event<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&OpcacheEvent) + Send + Sync + 'static,
+    {
+        self.handlers.push(Box::new(handler));
+        self
+    }
+
+    /// Starts monitoring the cache. Registering another monitor adds its
+    /// handlers and replaces the poll interval.
+    ///
+    /// This must be called during module startup.
+    pub fn register(self) {
+        *POLL_INTERVAL.lock() = self.poll_interval;
+        EVENT_HANDLERS.write().extend(self.handlers);
+        if MONITORED.swap(true, Ordering::AcqRel) {
+            return;
+        }
+
+        register_compile_observer(|file| {
+            REQUEST_STATS.with(|stats| {
+                let mut current = stats.get();
+                if file.cached {
+                    current.hits += 1;
+                    HITS.fetch_add(1, Ordering::Relaxed);
+                } else {
+                    current.misses += 1;
+                    MISSES.fetch_add(1, Ordering::Relaxed);
+                }
+                stats.set(current);
+            });
+        });
+        RequestObserver::new()
+            .on_startup(|| REQUEST_STATS.with(|stats| stats.set(RequestCacheStats::default())))
+            .on_shutdown(|| {
+                REQUESTS.fetch_add(1, Ordering::Relaxed);
+                poll();
+            })
+            .register();
+    }
+}
+
+impl Default for OpcacheMonitor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Debug for OpcacheMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OpcacheMonitor")
+            .field("poll_interval", &self.poll_interval)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Returns the scripts loaded by the current request so far, once an
+/// [`OpcacheMonitor`] is registered.
+pub fn request_cache_stats() -> RequestCacheStats {
+    REQUEST_STATS.with(Cell::get)
+}
+
+/// Returns the counters accumulated since an [`OpcacheMonitor`] was
+/// registered.
+pub fn counters() -> OpcacheCounters {
+    OpcacheCounters {
+        requests: REQUESTS.load(Ordering::Relaxed),
+        hits: HITS.load(Ordering::Relaxed),
+        misses: MISSES.load(Ordering::Relaxed),
+        restarts: RESTARTS.load(Ordering::Relaxed),
+        cache_full: CACHE_FULL.load(Ordering::Relaxed),
+    }
+}
+
+/// Polls the status of the cache if the poll interval elapsed, and notifies
+/// the events since the previous poll.
+fn poll() {
+    let interval = *POLL_INTERVAL.lock();
+    // Another request is polling, or the interval did not elapse.
+    let Some(mut last) = LAST_POLL.try_lock() else {
+        return;
+    };
+    if last
+        .as_ref()
+        .is_some_and(|(polled, _)| polled.elapsed() < interval)
+    {
+        return;
+    }
+    let Some(status) = OpcacheStatus::capture() else {
+        return;
+    };
+    let previous = last
+        .replace((Instant::now(), status))
+        .map(|(_, status)| status);
+    drop(last);
+
+    let events = previous
+        .map(|previous| events(&previous, &status))
+        .unwrap_or_default();
+    let handlers = EVENT_HANDLERS.read_recursive();
+    for event in &events {
+        match event {
+            OpcacheEvent::Restart(_) => RESTARTS.fetch_add(1, Ordering::Relaxed),
+            OpcacheEvent::CacheFull => CACHE_FULL.fetch_add(1, Ordering::Relaxed),
+            OpcacheEvent::RestartPending => 0,
+        };
+        for handler in handlers.iter() {
+            handler(event);
+        }
+    }
+}
+
+/// Returns the events which happened between two statuses of the cache.
+fn events(previous: &OpcacheStatus, current: &OpcacheStatus) -> Vec<OpcacheEvent> {
+    let mut events = vec![];
+    let restarts = [
+        (
+            previous.oom_restarts,
+            current.oom_restarts,
+            RestartReason::OutOfMemory,
+        ),
+        (
+            previous.hash_restarts,
+            current.hash_restarts,
+            RestartReason::HashFull,
+        ),
+        (
+            previous.manual_restarts,
+            current.manual_restarts,
+            RestartReason::Manual,
+        ),
+    ];
+    for (previous, current, reason) in restarts {
+        for _ in previous..current {
+            events.push(OpcacheEvent::Restart(reason));
+        }
+    }
+    if current.cache_full && !previous.cache_full {
+        events.push(OpcacheEvent::CacheFull);
+    }
+    if current.restart_pending && !previous.restart_pending {
+        events.push(OpcacheEvent::RestartPending);
+    }
+    events
+}
+
+#[cfg(feature = "embed")]
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events() {
+        let previous = OpcacheStatus {
+            oom_restarts: 1,
+            ..Default::default()
+        };
+        assert_eq!(events(&previous, &previous), []);
+
+        let current = OpcacheStatus {
+            cache_full: true,
+            oom_restarts: 2,
+            manual_restarts: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            events(&previous, &current),
+            [
+                OpcacheEvent::Restart(RestartReason::OutOfMemory),
+                OpcacheEvent::Restart(RestartReason::Manual),
+                OpcacheEvent::CacheFull,
+            ]
+        );
+    }
+}
@@ -1,14 +1,21 @@
-use std::ptr::{self, NonNull};
+use std::{
+    io::{self, SeekFrom},
+    ptr::{self, NonNull},
+};
 
 use crate::{
     error::Error,
     ffi::{
-        php_register_url_stream_wrapper, php_register_url_stream_wrapper_volatile, php_stream,
-        php_stream_context, php_stream_locate_url_wrapper, php_stream_wrapper,
-        php_stream_wrapper_ops, php_unregister_url_stream_wrapper,
-        php_unregister_url_stream_wrapper_volatile, zend_string,
+        _php_stream_cast, _php_stream_flush, _php_stream_read, _php_stream_seek,
+        _php_stream_set_option, _php_stream_tell, _php_stream_write, php_file_le_pstream,
+        php_file_le_stream, php_register_url_stream_wrapper,
+        php_register_url_stream_wrapper_volatile, php_stream, php_stream_context,
+        php_stream_locate_url_wrapper, php_stream_wrapper, php_stream_wrapper_ops,
+        php_unregister_url_stream_wrapper, php_unregister_url_stream_wrapper_volatile, zend_off_t,
+        zend_resource, zend_string, PHP_STREAM_AS_FD_FOR_SELECT, PHP_STREAM_OPTION_BLOCKING,
+        PHP_STREAM_OPTION_RETURN_NOTIMPL,
     },
-    types::ZendStr,
+    types::{ZendStr, Zval},
 };
 
 /// Wrapper for PHP streams
@@ -48,13 +55,16 @@ impl StreamWrapper {
 
     /// Register stream wrapper for name
     ///
-    /// # Panics
-    /// - If the name cannot be converted to a C string
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidCString`] if the name contains a NUL byte, or
+    /// [`Error::StreamWrapperRegistrationFailure`] if the wrapper could not be
+    /// registered.
     pub fn register(self, name: &str) -> Result<Self, Error> {
+        let name = std::ffi::CString::new(name)?;
         // We have to convert it to a static so owned streamwrapper doesn't get dropped.
         let copy = Box::new(self);
         let copy = Box::leak(copy);
-        let name = std::ffi::CString::new(name).expect("Could not create C string for name!");
         let result = unsafe { php_register_url_stream_wrapper(name.as_ptr(), copy) };
         if result == 0 {
             Ok(*copy)
@@ -80,10 +90,13 @@ impl StreamWrapper {
 
     /// Unregister stream wrapper by name
     ///
-    /// # Panics
-    /// - If the name cannot be converted to a C string
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidCString`] if the name contains a NUL byte, or
+    /// [`Error::StreamWrapperUnregistrationFailure`] if the wrapper could not
+    /// be unregistered.
     pub fn unregister(name: &str) -> Result<(), Error> {
-        let name = std::ffi::CString::new(name).expect("Could not create C string for name!");
+        let name = std::ffi::CString::new(name)?;
         match unsafe { php_unregister_url_stream_wrapper(name.as_ptr()) } {
             0 => Ok(()),
             _ => Err(Error::StreamWrapperUnregistrationFailure),
@@ -113,6 +126,105 @@ impl StreamWrapper {
 /// A PHP stream
 pub type Stream = php_stream;
 
+impl Stream {
+    /// Returns the stream held by a zval, e.g. the resource returned by
+    /// `fopen()`.
+    pub fn from_zval(zval: &mut Zval) -> Option<&mut Self> {
+        Self::from_resource(unsafe { zval.resource()?.as_mut()? })
+    }
+
+    /// Returns the stream held by a resource, or [`None`] if the resource is
+    /// not a stream or was closed, e.g. with `fclose()`.
+    pub fn from_resource(resource: &mut zend_resource) -> Option<&mut Self> {
+        let ty = resource.type_;
+        if ty != unsafe { php_file_le_stream() } && ty != unsafe { php_file_le_pstream() } {
+            return None;
+        }
+        unsafe { resource.ptr.cast::<Self>().as_mut() }
+    }
+
+    /// Reads bytes from the stream into the buffer, returning how many were
+    /// read. A non-blocking stream returns `0` when no data is available yet,
+    /// and sets the `eof` flag once the end of the stream is reached.
+    pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = unsafe { _php_stream_read(self, buf.as_mut_ptr().cast(), buf.len()) };
+        usize::try_from(read).map_err(|_| io::Error::other("failed to read from the stream"))
+    }
+
+    /// Writes bytes from the buffer to the stream, returning how many were
+    /// written. A non-blocking stream returns `0` when it cannot be written to
+    /// yet.
+    pub fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = unsafe { _php_stream_write(self, buf.as_ptr().cast(), buf.len()) };
+        usize::try_from(written).map_err(|_| io::Error::other("failed to write to the stream"))
+    }
+
+    /// Moves the position of the stream, returning the new position from the
+    /// start of the stream.
+    pub fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        // The values of `SEEK_SET`, `SEEK_CUR` and `SEEK_END`.
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (
+                zend_off_t::try_from(offset).map_err(|_| io::ErrorKind::InvalidInput)?,
+                0,
+            ),
+            SeekFrom::Current(offset) => (offset as zend_off_t, 1),
+            SeekFrom::End(offset) => (offset as zend_off_t, 2),
+        };
+        if unsafe { _php_stream_seek(self, offset, whence) } != 0 {
+            return Err(io::Error::other("failed to seek in the stream"));
+        }
+        u64::try_from(unsafe { _php_stream_tell(self) })
+            .map_err(|_| io::Error::other("failed to tell the position in the stream"))
+    }
+
+    /// Flushes the bytes written to the stream.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match unsafe { _php_stream_flush(self, 0) } {
+            0 => Ok(()),
+            _ => Err(io::Error::other("failed to flush the stream")),
+        }
+    }
+
+    /// Sets whether reads and writes block until the stream is ready, like
+    /// `stream_set_blocking()`.
+    ///
+    /// # Returns
+    ///
+    /// Whether the stream was blocking, or [`None`] if the stream does not
+    /// support non-blocking mode.
+    pub fn set_blocking(&mut self, blocking: bool) -> Option<bool> {
+        let previous = unsafe {
+            _php_stream_set_option(
+                self,
+                PHP_STREAM_OPTION_BLOCKING as _,
+                i32::from(blocking),
+                ptr::null_mut(),
+            )
+        };
+        match previous {
+            PHP_STREAM_OPTION_RETURN_NOTIMPL | -1 => None,
+            previous => Some(previous != 0),
+        }
+    }
+
+    /// Returns the file descriptor of the stream to wait on for readiness,
+    /// e.g. the socket of a network stream.
+    #[cfg(unix)]
+    pub fn as_raw_fd(&mut self) -> Option<std::os::unix::io::RawFd> {
+        let mut fd: std::os::unix::io::RawFd = -1;
+        let result = unsafe {
+            _php_stream_cast(
+                self,
+                PHP_STREAM_AS_FD_FOR_SELECT as _,
+                ptr::addr_of_mut!(fd).cast(),
+                0,
+            )
+        };
+        (result == 0 && fd >= 0).then_some(fd)
+    }
+}
+
 /// Operations that can be performed with a stream wrapper
 pub type StreamWrapperOps = php_stream_wrapper_ops;
 
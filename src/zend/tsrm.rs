@@ -0,0 +1,204 @@
+//! Helpers for thread-safe (ZTS) builds of PHP, where each thread running PHP
+//! code has its own globals, managed by the thread safe resource manager
+//! (TSRM).
+//!
+//! The accessors of the globals, e.g. [`ExecutorGlobals::get`], return the
+//! globals of the calling thread, found through the static TSRM cache of the
+//! crate, which is filled the first time each thread accesses its globals.
+//! They are guarded by locks of that thread, so threads running PHP do not
+//! contend on them. In non thread-safe builds, the globals and their locks
+//! are shared by the process.
+//!
+//! State of an extension which belongs to the thread running PHP, like the
+//! globals, can be kept in a [`PhpThreadLocal`].
+//!
+//! [`ExecutorGlobals::get`]: crate::zend::ExecutorGlobals::get
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt::{self, Debug},
+    thread::{self, ThreadId},
+};
+
+use parking_lot::{const_rwlock, RwLock};
+
+use crate::ffi::ext_php_rs_tsrm_is_main_thread;
+
+thread_local! {
+    /// The thread locals holding a value of this thread, cleared when it
+    /// exits.
+    static OWNED: RefCell<ThreadValues> = const { RefCell::new(ThreadValues(Vec::new())) };
+    /// The number of values of thread locals borrowed by this thread.
+    static BORROWED: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Returns whether the current thread is the one which started PHP. Always
+/// `true` in non thread-safe builds.
+pub fn is_main_thread() -> bool {
+    unsafe { ext_php_rs_tsrm_is_main_thread() }
+}
+
+/// A value with one instance per thread running PHP, which can be declared
+/// in a `static`, e.g. a per-thread cache or connection.
+///
+/// The value of a thread is created the first time the thread accesses it,
+/// and dropped when the thread exits. Unlike a `thread_local!`, the value is
+/// accessed through the `static` itself, and can be dropped early with
+/// [`PhpThreadLocal::clear`], e.g. when the thread shuts PHP down.
+///
+/// # Example
+///
+/// ```no_run
+/// use std::cell::Cell;
+///
+/// use ext_php_rs::zend::tsrm::PhpThreadLocal;
+///
+/// static REQUESTS: PhpThreadLocal<Cell<u64>> = PhpThreadLocal::new(|| Cell::new(0));
+///
+/// // In the request startup function:
+/// REQUESTS.with(|requests| requests.set(requests.get() + 1));
+/// ```
+pub struct PhpThreadLocal<T: 'static> {
+    init: fn() -> T,
+    values: RwLock<Vec<(ThreadId, Box<T>)>>,
+}
+
+impl<T: 'static> PhpThreadLocal<T> {
+    /// Creates a thread local, whose value is created with the given
+    /// function on each thread.
+    ///
+    /// # Parameters
+    ///
+    /// * `init` - Creates the value of a thread.
+    pub const fn new(init: fn() -> T) -> Self {
+        Self {
+            init,
+            values: const_rwlock(Vec::new()),
+        }
+    }
+
+    /// Calls a function with the value of the current thread, creating it
+    /// first if needed.
+    ///
+    /// # Parameters
+    ///
+    /// * `f` - The function.
+    pub fn with<R, F: FnOnce(&T) -> R>(&'static self, f: F) -> R {
+        let id = thread::current().id();
+        let value = match self.get(id) {
+            Some(value) => value,
+            None => {
+                // The value is created without holding the lock, as it may
+                // access other thread locals.
+                let value = Box::new((self.init)());
+                let ptr: *const T = &*value;
+                self.values.write().push((id, value));
+                // The value lives until the thread exits if it is already
+                // exiting, which is harmless.
+                let _ = OWNED.try_with(|owned| owned.borrow_mut().0.push(self));
+                ptr
+            }
+        };
+        BORROWED.set(BORROWED.get() + 1);
+        let _borrow = Borrow;
+        // SAFETY: The value of a thread is only dropped by that thread, when
+        // it exits or by `clear`, which is not called while values are
+        // borrowed.
+        f(unsafe { &*value })
+    }
+
+    /// Drops the value of the current thread, if it was created. The value is
+    /// created again on the next access.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called while the thread accesses the value of a thread
+    /// local, i.e. inside [`PhpThreadLocal::with`].
+    pub fn clear(&'static self) {
+        assert_eq!(
+            BORROWED.get(),
+            0,
+            "cannot clear a thread local while accessing one"
+        );
+        drop(self.take(thread::current().id()));
+    }
+
+    fn get(&self, id: ThreadId) -> Option<*const T> {
+        self.values
+            .read()
+            .iter()
+            .find(|(thread, _)| *thread == id)
+            .map(|(_, value)| &**value as *const T)
+    }
+
+    fn take(&self, id: ThreadId) -> Option<Box<T>> {
+        let mut values = self.values.write();
+        let index = values.iter().position(|(thread, _)| *thread == id)?;
+        Some(values.swap_remove(index).1)
+    }
+}
+
+impl<T: 'static> Debug for PhpThreadLocal<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PhpThreadLocal")
+            .field("threads", &self.values.read().len())
+            .finish()
+    }
+}
+
+// SAFETY: The value of a thread is only accessed by that thread. Values are
+// dropped by the thread owning them, except when the thread local itself is
+// dropped, which requires them to be `Send`.
+unsafe impl<T: Send + 'static> Sync for PhpThreadLocal<T> {}
+
+/// Releases a borrow of the value of a thread local, even on panic.
+struct Borrow;
+
+impl Drop for Borrow {
+    fn drop(&mut self) {
+        BORROWED.set(BORROWED.get() - 1);
+    }
+}
+
+/// Removes the values of a thread when it exits.
+trait ClearThread {
+    fn clear_thread(&self, id: ThreadId);
+}
+
+impl<T: 'static> ClearThread for PhpThreadLocal<T> {
+    fn clear_thread(&self, id: ThreadId) {
+        drop(self.take(id));
+    }
+}
+
+struct ThreadValues(Vec<&'static dyn ClearThread>);
+
+impl Drop for ThreadValues {
+    fn drop(&mut self) {
+        let id = thread::current().id();
+        for local in self.0.drain(..) {
+            local.clear_thread(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static COUNTER: PhpThreadLocal<Cell<u32>> = PhpThreadLocal::new(|| Cell::new(0));
+
+    #[test]
+    fn test_thread_local() {
+        COUNTER.with(|counter| counter.set(counter.get() + 1));
+        COUNTER.with(|counter| assert_eq!(counter.get(), 1));
+
+        thread::spawn(|| COUNTER.with(|counter| assert_eq!(counter.get(), 0)))
+            .join()
+            .expect("thread should not panic");
+        assert_eq!(COUNTER.values.read().len(), 1);
+
+        COUNTER.clear();
+        COUNTER.with(|counter| assert_eq!(counter.get(), 0));
+    }
+}
@@ -0,0 +1,103 @@
+#![allow(missing_docs)]
+#![cfg_attr(windows, feature(abi_vectorcall))]
+extern crate ext_php_rs;
+
+use ext_php_rs::embed::Embed;
+use ext_php_rs::php_test;
+use ext_php_rs::prelude::*;
+
+#[php_test]
+fn test_function() {
+    let greeting = Embed::eval("hello_world('foo');").expect("should call the function");
+    assert_eq!(greeting.str(), Some("Hello, foo!"));
+}
+
+#[php_test]
+fn test_class() {
+    Embed::eval("$counter = new Counter();").expect("should create the object");
+    Embed::eval("$counter->increment();").expect("should call the method");
+    let count = Embed::eval("$counter->increment();").expect("should call the method");
+    assert_eq!(count.long(), Some(2));
+}
+
+#[php_test]
+fn test_result() -> Result<(), String> {
+    let greeting = Embed::eval("hello_world('bar');").map_err(|e| format!("{e:?}"))?;
+    assert_eq!(greeting.str(), Some("Hello, bar!"));
+    Ok(())
+}
+
+#[php_test]
+#[should_panic(expected = "should fail")]
+fn test_panic() {
+    Embed::eval("$foo = hello_world('foo');").expect("should call the function");
+    panic!("should fail");
+}
+
+#[php_test]
+fn test_variables() {
+    Embed::eval("$foo = 'foo';").expect("should set the variable");
+    let foo = Embed::eval("$foo;").expect("should read the variable");
+    assert_eq!(foo.str(), Some("foo"));
+}
+
+#[php_test]
+fn test_var_export() {
+    ext_php_rs::assert_var_export!(vec![1, 2], "array (\n  0 => 1,\n  1 => 2,\n)");
+    ext_php_rs::assert_var_export!(None::<String>, "NULL");
+}
+
+#[php_test]
+fn test_roundtrip() {
+    ext_php_rs::assert_roundtrip!(String::from("foo"), "'foo'");
+    ext_php_rs::assert_roundtrip!(1.5, "1.5");
+    ext_php_rs::assert_roundtrip!(
+        vec![1, 2, 3],
+        "array_reverse($value)",
+        "array (\n  0 => 3,\n  1 => 2,\n  2 => 1,\n)",
+        vec![3, 2, 1]
+    );
+    ext_php_rs::assert_roundtrip!("foo", "strtoupper($value)", "'FOO'", String::from("FOO"));
+}
+
+#[test]
+fn test_embed_run_after_start() {
+    // PHP started by the tests keeps the module loaded for `Embed::run`.
+    ext_php_rs::embed::run_test(get_module, || {});
+    let greeting = Embed::run(|| {
+        Embed::eval("hello_world('baz');")
+            .ok()
+            .and_then(|greeting| greeting.str().map(str::to_string))
+    });
+    assert_eq!(greeting.as_deref(), Some("Hello, baz!"));
+}
+
+/// Gives you a nice greeting!
+#[php_function]
+pub fn hello_world(name: String) -> String {
+    format!("Hello, {}!", name)
+}
+
+#[php_class]
+pub struct Counter {
+    count: i64,
+}
+
+#[php_impl]
+impl Counter {
+    pub fn __construct() -> Self {
+        Self { count: 0 }
+    }
+
+    pub fn increment(&mut self) -> i64 {
+        self.count += 1;
+        self.count
+    }
+}
+
+#[php_module]
+pub fn module(module: ModuleBuilder) -> ModuleBuilder {
+    module
+        .class::<Counter>()
+        .function(wrap_function!(hello_world))
+}
@@ -3,7 +3,7 @@
 #![cfg_attr(windows, feature(abi_vectorcall))]
 extern crate ext_php_rs;
 
-use ext_php_rs::builders::SapiBuilder;
+use ext_php_rs::builders::{IniBuilder, SapiBuilder};
 use ext_php_rs::embed::{ext_php_rs_sapi_startup, Embed};
 use ext_php_rs::ffi::{
     php_module_shutdown, php_module_startup, php_request_shutdown, php_request_startup,
@@ -30,8 +30,14 @@ extern "C" fn output_tester(str: *const c_char, str_length: usize) -> usize {
 
 #[test]
 fn test_sapi() {
+    let mut ini = IniBuilder::new();
+    ini.unquoted("precision", "10");
+
     let mut builder = SapiBuilder::new("test", "Test");
-    builder = builder.ub_write_function(output_tester);
+    builder = builder
+        .ub_write_function(output_tester)
+        .ini_entries(ini)
+        .function(wrap_function!(test_sapi_function));
 
     let sapi = builder.build().unwrap().into_raw();
     let module = get_module();
@@ -68,6 +74,17 @@ fn test_sapi() {
         let result = Embed::eval("var_dump($foo);");
 
         assert!(result.is_ok());
+
+        let result = Embed::eval("test_sapi_function();");
+
+        assert_eq!(result.ok().and_then(|zval| zval.long()), Some(42));
+
+        let result = Embed::eval("ini_get('precision');");
+
+        assert_eq!(
+            result.ok().and_then(|zval| zval.string()).as_deref(),
+            Some("10")
+        );
     });
 
     unsafe {
@@ -93,6 +110,13 @@ pub fn hello_world(name: String) -> String {
     format!("Hello, {}!", name)
 }
 
+/// A fixture registered by the SAPI rather than the module, returning a
+/// constant to check that SAPI functions are callable.
+#[php_function]
+pub fn test_sapi_function() -> i64 {
+    42
+}
+
 #[php_module]
 pub fn module(module: ModuleBuilder) -> ModuleBuilder {
     module.function(wrap_function!(hello_world))
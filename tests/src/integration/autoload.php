@@ -0,0 +1,16 @@
+<?php
+
+require('_utils.php');
+
+spl_autoload_register(function (string $class) {
+    if ($class === 'UserlandAutoloaded') {
+        eval('class UserlandAutoloaded {}');
+    }
+});
+
+assert(!class_exists('RustAutoloaded', false));
+assert((new RustAutoloaded())->name() === 'rust');
+
+// Classes not handled by the Rust autoloader fall back to userland autoloaders.
+assert(class_exists('UserlandAutoloaded'));
+assert(!class_exists('UnknownAutoloaded'));
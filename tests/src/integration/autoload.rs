@@ -0,0 +1,4 @@
+#[test]
+fn autoload_works() {
+    assert!(crate::integration::run_php("autoload.php"));
+}
@@ -3,3 +3,21 @@
 require('_utils.php');
 
 assert(test_callable(fn (string $a) => $a, 'test') === 'test');
+
+// Exceptions thrown by the callable are caught and cleared on the Rust side.
+$result = test_callable_exception(function () {
+    throw new InvalidArgumentException('bad argument');
+});
+assert($result === 'InvalidArgumentException: bad argument');
+assert(test_callable(fn (string $a) => $a, 'still works') === 'still works');
+
+// Returning the error rethrows the original exception object.
+$thrown = new RuntimeException('original');
+try {
+    test_callable_rethrow(function () use ($thrown) {
+        throw $thrown;
+    });
+    assert(false, 'Expected an exception to be thrown');
+} catch (RuntimeException $e) {
+    assert($e === $thrown);
+}
@@ -0,0 +1,23 @@
+<?php
+
+require('_utils.php');
+
+$errors = [];
+set_error_handler(function (int $errno, string $errstr) use (&$errors) {
+    $errors[] = [$errno, $errstr];
+    return true;
+});
+
+test_warning('careful with 100%s');
+
+assert(count($errors) === 1);
+assert($errors[0][0] === E_WARNING);
+assert($errors[0][1] === 'test_warning(): careful with 100%s');
+
+$errors = [];
+assert(test_exception_warning(false) === false);
+assert(test_exception_warning(true) === null);
+
+assert(count($errors) === 2);
+assert($errors[0][0] === E_WARNING);
+assert($errors[0][1] === 'test_exception_warning(): legacy failure');
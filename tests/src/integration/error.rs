@@ -0,0 +1,4 @@
+#[test]
+fn error_works() {
+    assert!(crate::integration::run_php("error.php"));
+}
@@ -0,0 +1,98 @@
+<?php
+
+require('_utils.php');
+
+try {
+    test_exception_previous();
+    assert(false, 'Expected an exception to be thrown');
+} catch (Exception $e) {
+    assert($e->getMessage() === 'outer');
+
+    $previous = $e->getPrevious();
+    assert($previous instanceof ValueError);
+    assert($previous->getMessage() === 'inner');
+    assert($previous->getCode() === 2);
+    assert($previous->getPrevious() === null);
+}
+
+try {
+    test_exception_backtrace();
+    assert(false, 'Expected an exception to be thrown');
+} catch (Exception $e) {
+    assert(str_starts_with($e->getMessage(), "with backtrace\n\nRust backtrace:\n"));
+}
+
+assert(test_error_mapping('42') === 42);
+
+try {
+    test_error_mapping('abc');
+    assert(false, 'Expected an exception to be thrown');
+} catch (ValueError $e) {
+    assert($e->getMessage() === 'invalid digit found in string');
+}
+
+try {
+    test_error_mapping('');
+    assert(false, 'Expected an exception to be thrown');
+} catch (Exception $e) {
+    assert(get_class($e) === 'Exception');
+    assert($e->getMessage() === 'empty value');
+}
+
+try {
+    test_panic();
+    assert(false, 'Expected an exception to be thrown');
+} catch (Error $e) {
+    assert($e->getMessage() === 'Rust panic: boom');
+}
+
+class DomainFailure extends Exception
+{
+    public ?string $detail = null;
+}
+
+try {
+    test_exception_details('DomainFailure');
+    assert(false, 'Expected an exception to be thrown');
+} catch (DomainFailure $e) {
+    assert($e->getMessage() === 'details');
+    assert($e->getCode() === 3);
+    assert($e->getFile() === 'lib.rs');
+    assert($e->getLine() === 7);
+    assert($e->detail === 'extra');
+}
+
+try {
+    test_derive_error('invalid');
+    assert(false, 'Expected an exception to be thrown');
+} catch (ValueError $e) {
+    assert($e->getMessage() === 'invalid value for a: 5');
+    assert($e->getCode() === 1);
+}
+
+try {
+    test_derive_error('missing');
+    assert(false, 'Expected an exception to be thrown');
+} catch (Exception $e) {
+    assert($e->getMessage() === 'missing key b');
+    assert($e->getCode() === 2);
+}
+
+try {
+    test_derive_error('empty');
+    assert(false, 'Expected an exception to be thrown');
+} catch (Exception $e) {
+    assert($e->getMessage() === 'empty');
+    assert($e->getCode() === 1);
+}
+
+assert(is_subclass_of('Test\\TimeoutException', RuntimeException::class));
+
+try {
+    test_exception_class();
+    assert(false, 'Expected an exception to be thrown');
+} catch (Test\TimeoutException $e) {
+    assert($e->getMessage() === 'timed out');
+    assert($e->getCode() === 0);
+    assert($e->getLine() > 0);
+}
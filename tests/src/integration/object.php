@@ -14,3 +14,43 @@ assert($test->string === 'string');
 assert($test->bool === true);
 assert($test->number === 2022);
 assert($test->array === [1, 2, 3]);
+
+class Greeter
+{
+    public function greet()
+    {
+        return 'hello';
+    }
+
+    public static function make()
+    {
+        return 'static';
+    }
+
+    private static function secret()
+    {
+        return 'secret';
+    }
+
+    public function fail()
+    {
+        throw new Exception('boom');
+    }
+}
+
+assert(test_object_call_method(new Greeter, 'greet') === 'hello');
+assert(test_object_call_static(new Greeter, 'make') === 'static');
+assert(test_object_call_static('Greeter', 'make') === 'static');
+
+try {
+    test_object_call_static('Greeter', 'secret');
+    assert(false);
+} catch (Exception $e) {
+}
+
+try {
+    test_object_call_method(new Greeter, 'fail');
+    assert(false);
+} catch (Exception $e) {
+    assert(str_contains($e->getMessage(), 'boom'));
+}
@@ -2,9 +2,10 @@
 use ext_php_rs::{
     binary::Binary,
     boxed::ZBox,
+    exception::{Severity, WarningReturn},
     prelude::*,
     types::{ArrayKey, ZendHashTable, ZendObject, Zval},
-    zend::ProcessGlobals,
+    zend::{ce, ClassEntry, ProcessGlobals},
 };
 use std::collections::HashMap;
 
@@ -63,6 +64,16 @@ pub fn test_object(a: &mut ZendObject) -> &mut ZendObject {
     a
 }
 
+#[php_function]
+pub fn test_object_call_method(a: &Zval, method: &str) -> PhpResult<String> {
+    Ok(a.call_method(method, vec![])?)
+}
+
+#[php_function]
+pub fn test_object_call_static(a: &Zval, method: &str) -> PhpResult<String> {
+    Ok(a.call_static(method, vec![])?)
+}
+
 // GLOBALS
 #[php_function]
 pub fn test_globals_http_get() -> ZBox<ZendHashTable> {
@@ -112,6 +123,26 @@ pub fn test_callable(call: ZendCallable, a: String) -> Zval {
     call.try_call(vec![&a]).expect("Failed to call function")
 }
 
+#[php_function]
+pub fn test_callable_exception(call: ZendCallable) -> String {
+    match call.try_call(vec![]) {
+        Ok(_) => "no exception".into(),
+        Err(err) => {
+            let ex = PhpException::from(err);
+            format!(
+                "{}: {}",
+                ex.class().name().unwrap_or_default(),
+                ex.message()
+            )
+        }
+    }
+}
+
+#[php_function]
+pub fn test_callable_rethrow(call: ZendCallable) -> PhpResult<Zval> {
+    Ok(call.try_call(vec![])?)
+}
+
 #[php_function]
 pub fn iter_next(ht: &ZendHashTable) -> Vec<Zval> {
     ht.iter()
@@ -181,6 +212,91 @@ pub fn test_variadic_add_required(number: u32, numbers: &[&Zval]) -> u32 {
             .sum::<u32>()
 }
 
+#[php_function]
+pub fn test_exception_previous() -> PhpResult<()> {
+    Err(
+        PhpException::default("outer".into()).with_previous(PhpException::new(
+            "inner".into(),
+            2,
+            ce::value_error(),
+        )),
+    )
+}
+
+#[php_function]
+pub fn test_exception_backtrace() -> PhpResult<()> {
+    Err(PhpException::default("with backtrace".into()).with_backtrace())
+}
+
+#[php_function]
+pub fn test_error_mapping(value: &str) -> Result<i64, Box<dyn std::error::Error + Send + Sync>> {
+    if value.is_empty() {
+        return Err(std::io::Error::other("empty value").into());
+    }
+    Ok(value.parse::<i64>()?)
+}
+
+#[php_function]
+pub fn test_exception_details(class: &str) -> PhpResult<()> {
+    let ex = ClassEntry::try_find(class).ok_or("Class not found")?;
+    Err(PhpException::new("details".into(), 3, ex)
+        .with_file("lib.rs")
+        .with_line(7)
+        .with_property("detail", "extra"))
+}
+
+#[derive(Debug, PhpError)]
+#[php_error(code = 1)]
+pub enum TestError {
+    #[php_error(ex = ce::value_error, message = "invalid value for {key}: {value}")]
+    InvalidValue { key: String, value: i64 },
+    #[php_error(message = "missing key {0}", code = 2)]
+    MissingKey(String),
+    #[php_error(message = "empty")]
+    Empty,
+}
+
+#[php_function]
+pub fn test_derive_error(variant: &str) -> Result<(), TestError> {
+    Err(match variant {
+        "invalid" => TestError::InvalidValue {
+            key: "a".into(),
+            value: 5,
+        },
+        "missing" => TestError::MissingKey("b".into()),
+        _ => TestError::Empty,
+    })
+}
+
+php_exception_class! {
+    pub struct TestTimeoutException("Test\\TimeoutException") extends ce::runtime_exception;
+}
+
+#[php_function]
+pub fn test_exception_class() -> PhpResult<()> {
+    Err(TestTimeoutException::exception("timed out"))
+}
+
+#[php_function]
+pub fn test_panic() {
+    panic!("boom");
+}
+
+#[php_function]
+pub fn test_exception_warning(null: bool) -> PhpResult<Zval> {
+    let ex = PhpException::warning("legacy failure".into());
+    Err(if null {
+        ex.with_severity(Severity::Warning(WarningReturn::Null))
+    } else {
+        ex
+    })
+}
+
+#[php_function]
+pub fn test_warning(message: &str) {
+    ext_php_rs::zend::warning(message).expect("Failed to raise warning");
+}
+
 #[php_class]
 pub struct TestClass {
     string: String,
@@ -221,10 +337,34 @@ pub fn test_class(string: String, number: i32) -> TestClass {
     }
 }
 
-#[php_module]
+/// Registers an autoloader defining the `RustAutoloaded` class, as
+/// autoloaders must be registered during module startup.
+pub fn startup(_ty: i32, _module_number: i32) -> i32 {
+    let registered = ext_php_rs::zend::autoload::register_autoloader(|class| {
+        if class != "RustAutoloaded" {
+            return false;
+        }
+
+        unsafe {
+            ext_php_rs::ffi::zend_eval_string(
+                b"class RustAutoloaded { public function name() { return 'rust'; } }\0".as_ptr()
+                    as *const _,
+                std::ptr::null_mut(),
+                b"autoload\0".as_ptr() as *const _,
+            );
+        }
+        true
+    });
+    i32::from(registered.is_err())
+}
+
+#[php_module(startup = "startup")]
 pub fn build_module(module: ModuleBuilder) -> ModuleBuilder {
+    ext_php_rs::exception::register_error_mapping::<std::num::ParseIntError, _>(ce::value_error);
+
     module
         .class::<TestClass>()
+        .exception_class::<TestTimeoutException>()
         .function(wrap_function!(test_str))
         .function(wrap_function!(test_string))
         .function(wrap_function!(test_bool))
@@ -236,6 +376,8 @@ pub fn build_module(module: ModuleBuilder) -> ModuleBuilder {
         .function(wrap_function!(test_binary))
         .function(wrap_function!(test_nullable))
         .function(wrap_function!(test_object))
+        .function(wrap_function!(test_object_call_method))
+        .function(wrap_function!(test_object_call_static))
         .function(wrap_function!(test_globals_http_get))
         .function(wrap_function!(test_globals_http_post))
         .function(wrap_function!(test_globals_http_cookie))
@@ -245,12 +387,23 @@ pub fn build_module(module: ModuleBuilder) -> ModuleBuilder {
         .function(wrap_function!(test_closure))
         .function(wrap_function!(test_closure_once))
         .function(wrap_function!(test_callable))
+        .function(wrap_function!(test_callable_exception))
+        .function(wrap_function!(test_callable_rethrow))
         .function(wrap_function!(iter_next))
         .function(wrap_function!(iter_back))
         .function(wrap_function!(iter_next_back))
         .function(wrap_function!(test_class))
         .function(wrap_function!(test_variadic_args))
         .function(wrap_function!(test_variadic_add_required))
+        .function(wrap_function!(test_exception_previous))
+        .function(wrap_function!(test_exception_backtrace))
+        .function(wrap_function!(test_error_mapping))
+        .function(wrap_function!(test_panic))
+        .function(wrap_function!(test_derive_error))
+        .function(wrap_function!(test_exception_class))
+        .function(wrap_function!(test_exception_details))
+        .function(wrap_function!(test_warning))
+        .function(wrap_function!(test_exception_warning))
 }
 
 #[cfg(test)]
@@ -310,11 +463,14 @@ mod integration {
     }
 
     mod array;
+    mod autoload;
     mod binary;
     mod bool;
     mod callable;
     mod class;
     mod closure;
+    mod error;
+    mod exception;
     mod globals;
     mod iterator;
     mod nullable;
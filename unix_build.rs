@@ -1,17 +1,42 @@
-use std::{path::PathBuf, process::Command};
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use anyhow::{bail, Context, Result};
 
-use crate::{find_executable, path_from_env, PHPInfo, PHPProvider};
+use crate::{
+    env_flag, find_executable, find_php, path_from_env, php_include_dir, PHPInfo, PHPProvider,
+};
+
+/// The `pkg-config` modules provided by PHP, in order of preference.
+const PKG_CONFIG_MODULES: &[&str] = &["php-embed", "php"];
+
+/// The tool used to discover the PHP headers and libraries.
+enum Source {
+    /// The `php-config` executable at the given path.
+    PhpConfig(PathBuf),
+    /// The given `pkg-config` module, used where `php-config` is not
+    /// available, e.g. when only the embed development package is installed
+    /// or in a cross-compilation sysroot.
+    PkgConfig(String),
+    /// The given include directory, used when cross-compiling against a PHP
+    /// sysroot whose `php-config` can't be executed on the host.
+    Headers(PathBuf),
+}
 
 pub struct Provider<'a> {
     info: &'a PHPInfo,
+    source: Source,
 }
 
 impl<'a> Provider<'a> {
     /// Runs `php-config` with one argument, returning the stdout.
     fn php_config(&self, arg: &str) -> Result<String> {
-        let cmd = Command::new(self.find_bin()?)
+        let Source::PhpConfig(bin) = &self.source else {
+            bail!("`php-config` is not available");
+        };
+        let cmd = Command::new(bin)
             .arg(arg)
             .output()
             .context("Failed to run `php-config`")?;
@@ -23,7 +48,117 @@ impl<'a> Provider<'a> {
         Ok(stdout.to_string())
     }
 
-    fn find_bin(&self) -> Result<PathBuf> {
+    /// Runs `pkg-config` with the given arguments for a module, returning the
+    /// stdout.
+    fn pkg_config(module: &str, args: &[&str]) -> Result<String> {
+        let bin = std::env::var_os("PKG_CONFIG").unwrap_or_else(|| "pkg-config".into());
+        let cmd = Command::new(bin)
+            .args(args)
+            .arg(module)
+            .output()
+            .context("Failed to run `pkg-config`")?;
+        let stdout = String::from_utf8_lossy(&cmd.stdout);
+        if !cmd.status.success() {
+            let stderr = String::from_utf8_lossy(&cmd.stderr);
+            bail!("Failed to run `pkg-config`: {} {}", stdout, stderr);
+        }
+        Ok(stdout.to_string())
+    }
+
+    /// Returns the linker flags of PHP, including the libraries it was built
+    /// against.
+    fn static_link_flags(&self) -> Result<String> {
+        match &self.source {
+            // `--ldflags` contains the search paths of the libraries listed by
+            // `--libs`, e.g. `-lresolv -lxml2 -lssl`.
+            Source::PhpConfig(_) => Ok(format!(
+                "{} {}",
+                self.php_config("--ldflags")?,
+                self.php_config("--libs")?
+            )),
+            Source::PkgConfig(module) => Self::pkg_config(module, &["--static", "--libs"]),
+            // The libraries PHP was built against are unknown without `php-config`, and
+            // must be linked through `PHP_LIB_DIR` and `RUSTFLAGS`.
+            Source::Headers(_) => Ok(String::new()),
+        }
+    }
+
+    /// Returns the directory containing the PHP library, which can be
+    /// overridden with the `PHP_LIB_DIR` environment variable.
+    fn lib_dir(&self) -> Result<PathBuf> {
+        if let Some(dir) = path_from_env("PHP_LIB_DIR") {
+            return Ok(dir);
+        }
+
+        match &self.source {
+            Source::PhpConfig(_) => {
+                Ok(PathBuf::from(self.php_config("--prefix")?.trim()).join("lib"))
+            }
+            Source::PkgConfig(module) => Ok(PathBuf::from(
+                Self::pkg_config(module, &["--variable=libdir"])?.trim(),
+            )),
+            // `<prefix>/include/php` to `<prefix>/lib`.
+            Source::Headers(include_dir) => include_dir
+                .parent()
+                .and_then(Path::parent)
+                .map(|prefix| prefix.join("lib"))
+                .context("Failed to get PHP library directory, set `PHP_LIB_DIR`"),
+        }
+    }
+
+    /// Finds the tool used to discover PHP, preferring `php-config` and
+    /// falling back to `pkg-config`.
+    fn find_source(info: &PHPInfo) -> Result<Source> {
+        if let Some(include_dir) = php_include_dir()? {
+            return Ok(Source::Headers(include_dir));
+        }
+        if let Ok(module) = std::env::var("PHP_PKG_CONFIG") {
+            return Ok(Source::PkgConfig(module));
+        }
+
+        let php_config = Self::find_bin(info);
+        if php_config.is_err() && path_from_env("PHP_CONFIG").is_none() {
+            if let Some(module) = PKG_CONFIG_MODULES
+                .iter()
+                .find(|module| Self::pkg_config(module, &["--exists"]).is_ok())
+            {
+                return Ok(Source::PkgConfig(module.to_string()));
+            }
+        }
+        php_config.map(Source::PhpConfig)
+    }
+
+    /// Links `libphp.a` statically, along with the libraries PHP and its
+    /// bundled extensions were built against.
+    fn print_static_link_args(&self) -> Result<()> {
+        let lib_dir = self.lib_dir()?;
+        let lib_name = std::env::var("PHP_LIB_NAME").unwrap_or_else(|_| "php".into());
+        if !lib_dir.join(format!("lib{lib_name}.a")).exists() {
+            bail!(
+                "The `static-embed` feature requires `lib{}.a`, which was not found in {:?}. \
+                Build PHP with `--enable-embed=static`.",
+                lib_name,
+                lib_dir
+            );
+        }
+        println!("cargo:rustc-link-search=native={}", lib_dir.display());
+        println!("cargo:rustc-link-lib=static={lib_name}");
+
+        for flag in self.static_link_flags()?.split_whitespace() {
+            if let Some(path) = flag.strip_prefix("-L") {
+                println!("cargo:rustc-link-search=native={path}");
+            } else if let Some(lib) = flag.strip_prefix("-l") {
+                // PHP itself is already linked statically.
+                if lib != lib_name {
+                    println!("cargo:rustc-link-lib={lib}");
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn find_bin(info: &PHPInfo) -> Result<PathBuf> {
         // If path is given via env, it takes priority.
         if let Some(path) = path_from_env("PHP_CONFIG") {
             if !path.try_exists()? {
@@ -32,23 +167,50 @@ impl<'a> Provider<'a> {
             }
             return Ok(path);
         }
-        find_executable("php-config").with_context(|| {
-            "Could not find `php-config` executable. \
-            Please ensure `php-config` is in your PATH or the \
-            `PHP_CONFIG` environment variable is set."
-        })
+        // Prefer the `php-config` installed alongside `php`, e.g. in a Homebrew
+        // keg or as `php-config83` next to `php83`.
+        let sibling = find_php().ok().and_then(|php| {
+            let name = php.file_name()?.to_str()?;
+            let config = php.with_file_name(name.replacen("php", "php-config", 1));
+            config.exists().then_some(config)
+        });
+        sibling
+            .or_else(|| find_executable("php-config"))
+            .or_else(|| {
+                // Distributions such as Alpine Linux only ship versioned executables,
+                // e.g. `php-config83` alongside `php83`.
+                let version: String = info.version().ok()?.split('.').take(2).collect();
+                find_executable(&format!("php-config{version}"))
+            })
+            .with_context(|| {
+                "Could not find `php-config` executable. \
+            Please ensure `php-config` is in your PATH, the \
+            `PHP_CONFIG` environment variable is set, or PHP provides \
+            `pkg-config` metadata."
+            })
     }
 }
 
 impl<'a> PHPProvider<'a> for Provider<'a> {
     fn new(info: &'a PHPInfo) -> Result<Self> {
-        Ok(Self { info })
+        check_macos_arch();
+        let source = Self::find_source(info)?;
+        Ok(Self { info, source })
     }
 
     fn get_includes(&self) -> Result<Vec<PathBuf>> {
-        Ok(self
-            .php_config("--includes")?
-            .split(' ')
+        let includes = match &self.source {
+            Source::PhpConfig(_) => self.php_config("--includes")?,
+            Source::PkgConfig(module) => Self::pkg_config(module, &["--cflags-only-I"])?,
+            Source::Headers(include_dir) => {
+                return Ok(["", "main", "Zend", "TSRM", "ext", "ext/date/lib"]
+                    .iter()
+                    .map(|dir| include_dir.join(dir))
+                    .collect())
+            }
+        };
+        Ok(includes
+            .split_whitespace()
             .map(|s| s.trim_start_matches("-I"))
             .map(PathBuf::from)
             .collect())
@@ -58,15 +220,164 @@ impl<'a> PHPProvider<'a> for Provider<'a> {
         let mut defines = vec![];
         if self.info.thread_safety()? {
             defines.push(("ZTS", "1"));
-            // defines.push(("ZEND_ENABLE_STATIC_TSRMLS_CACHE", "1"));
+            defines.push(("ZEND_ENABLE_STATIC_TSRMLS_CACHE", "1"));
         }
         Ok(defines)
     }
 
+    fn describe_headers(&self) -> String {
+        let output = |command: String, result: Result<String>| match result {
+            Ok(stdout) => format!("`{command}` reported:\n  {}\n", stdout.trim()),
+            Err(error) => format!("`{command}` failed: {error}\n"),
+        };
+        match &self.source {
+            Source::PhpConfig(bin) => ["--version", "--includes"]
+                .iter()
+                .map(|arg| output(format!("{} {arg}", bin.display()), self.php_config(arg)))
+                .collect(),
+            Source::PkgConfig(module) => [&["--modversion"][..], &["--cflags-only-I"]]
+                .iter()
+                .map(|args| {
+                    output(
+                        format!("pkg-config {} {module}", args.join(" ")),
+                        Self::pkg_config(module, args),
+                    )
+                })
+                .collect(),
+            Source::Headers(include_dir) => {
+                format!("The headers were read from `PHP_INCLUDE_DIR` {include_dir:?}.\n")
+            }
+        }
+    }
+
     fn print_extra_link_args(&self) -> Result<()> {
-        println!("cargo:rustc-link-search=/usr/local/lib");
-        println!("cargo:rustc-link-lib=dylib=php");
+        if std::env::var_os("CARGO_FEATURE_STATIC_EMBED").is_some() {
+            return self.print_static_link_args();
+        }
+
+        // On macOS, extensions loaded by PHP must not link `libphp` as it would
+        // be loaded a second time, so it is only linked by SAPI hosts.
+        let is_macos = std::env::var("CARGO_CFG_TARGET_OS").is_ok_and(|os| os == "macos");
+        let embed = std::env::var_os("CARGO_FEATURE_EMBED").is_some();
+        if !env_flag("PHP_EMBED").unwrap_or(embed || !is_macos) {
+            // Extensions are loaded by PHP, which provides the symbols at runtime.
+            if is_macos {
+                println!("cargo:rustc-cdylib-link-arg=-undefined");
+                println!("cargo:rustc-cdylib-link-arg=dynamic_lookup");
+            }
+            return Ok(());
+        }
+
+        let lib_dir = self.lib_dir()?;
+        let lib_name = match std::env::var("PHP_LIB_NAME") {
+            Ok(name) => name,
+            Err(_) => php_lib_name(&lib_dir, self.info.version()?),
+        };
+        println!("cargo:rustc-link-search=native={}", lib_dir.display());
+        println!("cargo:rustc-link-lib=dylib={lib_name}");
+
+        if let Some(rpath) = rpath(&lib_dir)? {
+            println!("cargo:rustc-link-arg=-Wl,-rpath,{rpath}");
+            // Link arguments only apply to this package, so the rpath is also
+            // exported to dependents as `DEP_PHP_RPATH`.
+            println!("cargo:rpath={rpath}");
+        }
 
         Ok(())
     }
 }
+
+/// Warns when the PHP executable does not contain the target architecture on
+/// macOS, e.g. when building for `x86_64` against an `arm64` Homebrew PHP.
+/// Universal builds require a PHP installation for each architecture, built
+/// separately and merged with `lipo`.
+fn check_macos_arch() {
+    if !cfg!(target_os = "macos")
+        || !std::env::var("CARGO_CFG_TARGET_OS").is_ok_and(|os| os == "macos")
+    {
+        return;
+    }
+    let (Ok(php), Ok(arch)) = (find_php(), std::env::var("CARGO_CFG_TARGET_ARCH")) else {
+        return;
+    };
+    let arch = if arch == "aarch64" {
+        "arm64"
+    } else {
+        arch.as_str()
+    };
+    let Ok(output) = Command::new("lipo").arg("-archs").arg(&php).output() else {
+        return;
+    };
+    let archs = String::from_utf8_lossy(&output.stdout);
+    if output.status.success() && !archs.split_whitespace().any(|a| a == arch) {
+        println!(
+            "cargo:warning=The PHP executable {:?} is built for `{}`, not the target architecture `{}`. Set `PHP` and `PHP_CONFIG` to a PHP installation for `{}`, e.g. from the Homebrew prefix of that architecture.",
+            php,
+            archs.trim(),
+            arch,
+            arch
+        );
+    }
+}
+
+/// Returns the rpath to embed in the produced binaries, configured through the
+/// `PHP_RPATH` environment variable:
+///
+/// * `none` (default) - no rpath, relying on the system library paths.
+/// * `origin` - the directory of the binary, for a PHP library shipped
+///   alongside it.
+/// * `libdir` - the directory the PHP library was found in, for a PHP library
+///   installed on the system.
+/// * any other value is used as the rpath as-is.
+fn rpath(lib_dir: &Path) -> Result<Option<String>> {
+    let Ok(rpath) = std::env::var("PHP_RPATH") else {
+        return Ok(None);
+    };
+
+    Ok(match rpath.as_str() {
+        "" | "none" => None,
+        "origin" => {
+            let is_macos = std::env::var("CARGO_CFG_TARGET_OS").is_ok_and(|os| os == "macos");
+            Some(if is_macos { "@loader_path" } else { "$ORIGIN" }.into())
+        }
+        "libdir" => Some(
+            lib_dir
+                .to_str()
+                .context("PHP library directory is not valid UTF-8")?
+                .into(),
+        ),
+        path => Some(path.into()),
+    })
+}
+
+/// Returns the name of the shared PHP library in the given directory, which is
+/// versioned on some distributions, e.g. `libphp83.so` on Alpine Linux.
+fn php_lib_name(lib_dir: &Path, version: &str) -> String {
+    let Ok(entries) = std::fs::read_dir(lib_dir) else {
+        return "php".into();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            let stem = name
+                .strip_suffix(".so")
+                .or_else(|| name.strip_suffix(".dylib"))?;
+            Some(stem.strip_prefix("lib")?.to_string())
+        })
+        .filter(|name| {
+            name.strip_prefix("php")
+                .is_some_and(|version| version.chars().all(|c| c.is_ascii_digit()))
+        })
+        .collect();
+    names.sort();
+    // Prefer the unversioned name, then the name matching the version of PHP,
+    // then the most recent version.
+    let versioned = format!("php{}", version.split('.').take(2).collect::<String>());
+    names
+        .iter()
+        .find(|name| *name == "php")
+        .or_else(|| names.iter().find(|name| **name == versioned))
+        .or(names.last())
+        .cloned()
+        .unwrap_or_else(|| "php".into())
+}
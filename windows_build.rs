@@ -8,7 +8,7 @@ use std::{
 };
 use ureq::tls::{TlsConfig, TlsProvider};
 
-use crate::{PHPInfo, PHPProvider};
+use crate::{path_from_env, PHPInfo, PHPProvider};
 
 const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
 
@@ -18,11 +18,16 @@ pub struct Provider<'a> {
 }
 
 impl<'a> Provider<'a> {
+    /// Returns the path of the PHP import library to link against.
+    fn php_lib(&self) -> Result<PathBuf> {
+        self.devel
+            .php_lib(self.info.debug()?, self.info.thread_safety()?)
+    }
+
     /// Retrieves the PHP library name (filename without extension).
     fn get_php_lib_name(&self) -> Result<String> {
         Ok(self
-            .devel
-            .php_lib(self.info.debug()?)
+            .php_lib()?
             .file_stem()
             .context("Failed to get PHP library name")?
             .to_string_lossy()
@@ -35,7 +40,10 @@ impl<'a> PHPProvider<'a> for Provider<'a> {
         let version = info.version()?;
         let is_zts = info.thread_safety()?;
         let arch = info.architecture()?;
-        let devel = DevelPack::new(version, is_zts, arch)?;
+        let devel = match path_from_env("PHP_DEVEL_PACK") {
+            Some(path) => DevelPack::local(path)?,
+            None => DevelPack::download(version, is_zts, arch)?,
+        };
         if let Ok(linker) = get_rustc_linker() {
             if looks_like_msvc_linker(&linker) {
                 println!("cargo:warning=It looks like you are using a MSVC linker. You may encounter issues when attempting to load your compiled extension into PHP if your MSVC linker version is not compatible with the linker used to compile your PHP. It is recommended to use `rust-lld` as your linker.");
@@ -58,7 +66,8 @@ impl<'a> PHPProvider<'a> for Provider<'a> {
             ("ZEND_DEBUG", if self.info.debug()? { "1" } else { "0" }),
         ];
         if self.info.thread_safety()? {
-            defines.push(("ZTS", ""));
+            defines.push(("ZTS", "1"));
+            defines.push(("ZEND_ENABLE_STATIC_TSRMLS_CACHE", "1"));
         }
         Ok(defines)
     }
@@ -82,15 +91,26 @@ impl<'a> PHPProvider<'a> for Provider<'a> {
 
     fn print_extra_link_args(&self) -> Result<()> {
         let php_lib_name = self.get_php_lib_name()?;
-        let php_lib_search = self
-            .devel
-            .php_lib(self.info.debug()?)
+        let php_lib = self.php_lib()?;
+        let php_lib_search = php_lib
             .parent()
-            .context("Failed to get PHP library parent folder")?
-            .to_string_lossy()
-            .to_string();
+            .context("Failed to get PHP library parent folder")?;
         println!("cargo:rustc-link-lib=dylib={}", php_lib_name);
-        println!("cargo:rustc-link-search={}", php_lib_search);
+        println!("cargo:rustc-link-search={}", php_lib_search.display());
+
+        // SAPI hosts using the embed SAPI also link its static library, which is
+        // shipped next to the import library.
+        if std::env::var_os("CARGO_FEATURE_EMBED").is_some() {
+            let embed_lib = php_lib_search.join("php8embed.lib");
+            if !embed_lib.exists() {
+                bail!(
+                    "The `embed` feature requires `{}`, which was not found. \
+                    Use a development pack or PHP SDK build with the embed SAPI enabled.",
+                    embed_lib.display()
+                );
+            }
+            println!("cargo:rustc-link-lib=static=php8embed");
+        }
         Ok(())
     }
 }
@@ -156,27 +176,49 @@ impl Display for Arch {
     }
 }
 
+/// Returns the Visual Studio toolset the official Windows builds of a PHP
+/// version were compiled with, which is part of the name of its development
+/// pack. Can be overridden with the `PHP_VS_VERSION` environment variable.
+fn visual_studio_version(version: &str) -> Result<String> {
+    if let Ok(vs) = std::env::var("PHP_VS_VERSION") {
+        return Ok(vs);
+    }
+
+    let mut parts = version.split('.').map(str::parse::<u32>);
+    let (Some(Ok(major)), Some(Ok(minor))) = (parts.next(), parts.next()) else {
+        bail!("Failed to parse PHP version {}", version);
+    };
+
+    Ok(match (major, minor) {
+        // PHP 7.2 to 7.4 were built with Visual C++ 15.
+        (7, _) => "vc15",
+        // PHP 8.0 to 8.3 were built with Visual Studio 2019.
+        (8, 0..=3) => "vs16",
+        // PHP builds switched to Visual Studio 2022 in PHP 8.4.
+        _ => "vs17",
+    }
+    .to_string())
+}
+
 struct DevelPack(PathBuf);
 
 impl DevelPack {
+    /// Uses a PHP development pack already present on the system, such as
+    /// one extracted manually or produced by a PHP SDK build.
+    fn local(path: PathBuf) -> Result<DevelPack> {
+        if !path.join("include").is_dir() {
+            bail!(
+                "PHP development pack not found at {:?}: missing `include` folder",
+                path
+            );
+        }
+        Ok(DevelPack(path))
+    }
+
     /// Downloads a new PHP development pack, unzips it in the build script
     /// temporary directory.
-    fn new(version: &str, is_zts: bool, arch: Arch) -> Result<DevelPack> {
-        // If the PHP version is more than 8.4.1, use VS17 instead of VS16.
-        let version_float = version
-            .split('.')
-            .take(2)
-            .collect::<Vec<_>>()
-            .join(".")
-            .parse::<f32>()
-            .context("Failed to parse PHP version as float")?;
-
-        // PHP builds switched to VS17 in PHP 8.4.1.
-        let visual_studio_version = if version_float >= 8.4f32 {
-            "vs17"
-        } else {
-            "vs16"
-        };
+    fn download(version: &str, is_zts: bool, arch: Arch) -> Result<DevelPack> {
+        let visual_studio_version = visual_studio_version(version)?;
 
         let zip_name = format!(
             "php-devel-pack-{}{}-Win32-{}-{}.zip",
@@ -185,7 +227,6 @@ impl DevelPack {
             visual_studio_version,
             arch
         );
-
         fn download(zip_name: &str, archive: bool) -> Result<PathBuf> {
             let out_dir = PathBuf::from(std::env::var_os("OUT_DIR").unwrap());
             let url = format!(
@@ -237,47 +278,52 @@ impl DevelPack {
         self.0.join("include")
     }
 
-    /// Returns the path of the PHP library containing symbols for linking.
-    pub fn php_lib(&self, is_debug: bool) -> PathBuf {
-        let php_lib_path = std::env::var("PHP_LIB")
-            .map(PathBuf::from)
-            .unwrap_or_else(|_| self.0.join("lib"));
+    /// Returns the path of the PHP import library containing symbols for
+    /// linking, matching the debug and thread safety settings of PHP. The
+    /// folder and name of the library can be overridden with the `PHP_LIB_DIR`
+    /// and `PHP_LIB_NAME` environment variables.
+    pub fn php_lib(&self, is_debug: bool, is_zts: bool) -> Result<PathBuf> {
+        let php_lib_path = path_from_env("PHP_LIB_DIR")
+            .or_else(|| path_from_env("PHP_LIB"))
+            .unwrap_or_else(|| self.0.join("lib"));
 
         if !php_lib_path.exists() {
-            panic!(
-                "Error: Specified PHP library path '{}' does not exist.",
+            bail!(
+                "Specified PHP library path '{}' does not exist.",
                 php_lib_path.display()
             );
         }
 
-        let candidates = if is_debug {
-            ["php8_debug.lib", "php8ts_debug.lib"]
-        } else {
-            ["php8.lib", "php8ts.lib"]
+        let lib_name = match std::env::var("PHP_LIB_NAME") {
+            Ok(name) => format!("{name}.lib"),
+            Err(_) => format!(
+                "php8{}{}.lib",
+                if is_zts { "ts" } else { "" },
+                if is_debug { "_debug" } else { "" }
+            ),
         };
+        let lib = php_lib_path.join(&lib_name);
+        if lib.exists() {
+            return Ok(lib);
+        }
 
-        candidates
-            .iter()
-            .map(|lib| php_lib_path.join(lib))
-            .find(|path| path.exists())
-            .expect(&format!(
-                "{}",
-                if is_debug {
-                    format!(
-                        r#"Error: No suitable PHP library found in '{}'.
+        if is_debug {
+            bail!(
+                r#"No suitable PHP library found in '{}', expected `{}`.
 To build the application in DEBUG mode on Windows,
 you must have a PHP SDK built with the DEBUG option enabled
 and specify the PHP_LIB to the folder containing the lib files.
 For example: set PHP_LIB=C:\php-sdk\php-dev\vc16\x64\php-8.3.13-src\x64\Debug_TS."#,
-                        php_lib_path.display()
-                    )
-                } else {
-                    format!(
-                        "Error: No suitable PHP library found in '{}'.",
-                        php_lib_path.display()
-                    )
-                }
-            ))
+                php_lib_path.display(),
+                lib_name
+            )
+        } else {
+            bail!(
+                "No suitable PHP library found in '{}', expected `{}`.",
+                php_lib_path.display(),
+                lib_name
+            )
+        }
     }
 
     /// Returns a list of include paths to pass to the compiler.